@@ -1,8 +1,7 @@
 // 🌐 Mystical-Runic v0.5.2 - Ecosystem Integration Benchmark
 // Tests async performance, web framework integration, and CLI tools
 
-use mystical_runic::{TemplateEngine, TemplateContext, TemplateValue};
-use std::collections::HashMap;
+use mystical_runic::{TemplateEngine, TemplateContext, ValueFactory};
 use std::fs;
 use std::path::PathBuf;
 use std::time::Instant;
@@ -190,46 +189,26 @@ fn create_test_context() -> TemplateContext {
     context.set_string("timestamp", "2024-01-15T10:00:00Z");
     
     // Create users for async testing
-    let users: Vec<TemplateValue> = (0..50)
-        .map(|i| {
-            let mut user = HashMap::new();
-            user.insert("id".to_string(), TemplateValue::Number(i));
-            user.insert("name".to_string(), TemplateValue::String(format!("Async User {}", i)));
-            
-            let stats: Vec<TemplateValue> = (0..3)
-                .map(|j| {
-                    let mut stat = HashMap::new();
-                    stat.insert("name".to_string(), TemplateValue::String(format!("Stat {}", j)));
-                    stat.insert("value".to_string(), TemplateValue::Number(i * j + 10));
-                    TemplateValue::Object(stat)
-                })
-                .collect();
-            user.insert("stats".to_string(), TemplateValue::Array(stats));
-            
-            TemplateValue::Object(user)
-        })
-        .collect();
-    context.set("users", TemplateValue::Array(users));
+    context.set("users", ValueFactory::repeat(50, |i, user| user
+        .number("id", i as i64)
+        .string("name", format!("Async User {}", i))
+        .value("stats", ValueFactory::repeat(3, |j, stat| stat
+            .string("name", format!("Stat {}", j))
+            .number("value", (i * j) as i64 + 10)))));
     
     // Web framework context
-    let mut page = HashMap::new();
-    page.insert("title".to_string(), TemplateValue::String("Web Framework Integration".to_string()));
-    page.insert("heading".to_string(), TemplateValue::String("High Performance Web Response".to_string()));
-    context.set("page", TemplateValue::Object(page));
+    context.set("page", ValueFactory::object()
+        .string("title", "Web Framework Integration")
+        .string("heading", "High Performance Web Response")
+        .build());
     
     context.set_string("response_time", "15");
     
-    let items: Vec<TemplateValue> = (0..20)
-        .map(|i| {
-            let mut item = HashMap::new();
-            item.insert("id".to_string(), TemplateValue::Number(i));
-            item.insert("title".to_string(), TemplateValue::String(format!("Article {}", i)));
-            item.insert("description".to_string(), TemplateValue::String(format!("Description of article {} with detailed content.", i)));
-            item.insert("created_at".to_string(), TemplateValue::String("2024-01-15".to_string()));
-            TemplateValue::Object(item)
-        })
-        .collect();
-    context.set("items", TemplateValue::Array(items));
+    context.set("items", ValueFactory::repeat(20, |i, item| item
+        .number("id", i as i64)
+        .string("title", format!("Article {}", i))
+        .string("description", format!("Description of article {} with detailed content.", i))
+        .string("created_at", "2024-01-15")));
     
     context
 }
@@ -0,0 +1,101 @@
+// 🔮 Mystical-Runic v0.6.0 - Numeric Table Benchmark
+// Measures time and allocations of a 100k-row table of numbers, with the
+// digits written straight into the output against a `to_string` per cell
+
+use mystical_runic::{TemplateEngine, TemplateContext, TemplateValue, ValueFactory};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Counts every allocation of the process
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const ROWS: i64 = 100_000;
+const TEMPLATE: &str = "<table>{{for row in rows}}<tr><td>{{row.id}}</td><td>{{row.quantity}}</td><td>{{row.balance}}</td></tr>{{/for}}</table>";
+
+fn main() {
+    println!("🔮 Mystical-Runic v0.6.0 - Numeric Table Benchmark 🔮");
+    println!("======================================================");
+    println!();
+
+    let mut engine = TemplateEngine::new(".");
+    let numbers = create_table_context(TemplateValue::Number);
+    let mut buffer = String::new();
+
+    // Warm-up run sizes the output buffer
+    engine.render_string_into(TEMPLATE, &numbers, &mut buffer).unwrap();
+    let expected = buffer.clone();
+
+    println!("📊 {} rows, 3 numeric cells each", ROWS);
+    println!("--------------------------------");
+
+    // A String per number on top of the render, as numbers used to cost
+    let strings = create_table_context(|n| TemplateValue::String(n.to_string()));
+    let (formatted_time, formatted_allocations) = measure(|| {
+        let cells: Vec<String> = (0..ROWS).flat_map(|i| [i, i % 250, (i - ROWS / 2) * 7_919])
+            .map(|n| n.to_string())
+            .collect();
+        engine.render_string_into(TEMPLATE, &strings, &mut buffer).unwrap();
+        drop(cells);
+    });
+    assert_eq!(buffer, expected);
+
+    // Digits written straight into the output buffer
+    let (direct_time, direct_allocations) = measure(|| {
+        engine.render_string_into(TEMPLATE, &numbers, &mut buffer).unwrap();
+    });
+    assert_eq!(buffer, expected);
+
+    report("to_string per cell", formatted_time, formatted_allocations, formatted_time);
+    report("digits into the buffer", direct_time, direct_allocations, formatted_time);
+    println!();
+
+    println!("🎉 Numeric table benchmark completed successfully!");
+}
+
+fn measure<F: FnMut()>(mut f: F) -> (Duration, usize) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    f();
+    (start.elapsed(), ALLOCATIONS.load(Ordering::Relaxed) - allocations)
+}
+
+fn report(label: &str, elapsed: Duration, allocations: usize, baseline: Duration) {
+    let speedup = baseline.as_secs_f64() / elapsed.as_secs_f64().max(f64::EPSILON);
+    println!(
+        "  {:<24} {:>12?}  ({:.1} allocations/row, {:.2}x)",
+        label, elapsed, allocations as f64 / ROWS as f64, speedup
+    );
+}
+
+/// Rows of `id`, `quantity` and `balance`, negative balances included
+fn create_table_context(value: impl Fn(i64) -> TemplateValue) -> TemplateContext {
+    let rows = ValueFactory::repeat(ROWS as usize, |i, row| {
+        let i = i as i64;
+        row.value("id", value(i))
+            .value("quantity", value(i % 250))
+            .value("balance", value((i - ROWS / 2) * 7_919))
+    });
+    ValueFactory::object().value("rows", rows).build_context()
+}
@@ -1,8 +1,7 @@
 // Performance demonstration for TDD features
 // This demo showcases the actual performance improvements achieved
 
-use mystical_runic::{TemplateEngine, TemplateContext, TemplateValue};
-use std::collections::HashMap;
+use mystical_runic::{TemplateEngine, TemplateContext, ValueFactory};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::Instant;
@@ -100,41 +99,20 @@ fn create_test_templates(temp_dir: &Path) {
 }
 
 fn create_test_context() -> TemplateContext {
-    let mut context = TemplateContext::new();
-    
-    // Create test data
-    let items: Vec<TemplateValue> = (0..100)
-        .map(|i| {
-            let mut item = HashMap::new();
-            item.insert("name".to_string(), TemplateValue::String(format!("Item {}", i)));
-            item.insert("value".to_string(), TemplateValue::Number(i * 10));
-            TemplateValue::Object(item)
-        })
-        .collect();
-    context.set("items", TemplateValue::Array(items));
-    
-    // Create user data for complex template
-    let users: Vec<TemplateValue> = (0..50)
-        .map(|i| {
-            let mut user = HashMap::new();
-            user.insert("id".to_string(), TemplateValue::Number(i));
-            user.insert("name".to_string(), TemplateValue::String(format!("User {}", i)));
-            user.insert("active".to_string(), TemplateValue::Bool(i % 2 == 0));
-            
-            let skills: Vec<TemplateValue> = (0..3)
-                .map(|j| {
-                    let mut skill = HashMap::new();
-                    skill.insert("name".to_string(), TemplateValue::String(format!("Skill {}", j)));
-                    skill.insert("level".to_string(), TemplateValue::Number(j * 20));
-                    TemplateValue::Object(skill)
-                })
-                .collect();
-            user.insert("skills".to_string(), TemplateValue::Array(skills));
-            
-            TemplateValue::Object(user)
-        })
-        .collect();
-    context.set("users", TemplateValue::Array(users));
+    let mut context = ValueFactory::object()
+        // Create test data
+        .value("items", ValueFactory::repeat(100, |i, item| item
+            .string("name", format!("Item {}", i))
+            .number("value", i as i64 * 10)))
+        // Create user data for complex template
+        .value("users", ValueFactory::repeat(50, |i, user| user
+            .number("id", i as i64)
+            .string("name", format!("User {}", i))
+            .bool("active", i % 2 == 0)
+            .value("skills", ValueFactory::repeat(3, |j, skill| skill
+                .string("name", format!("Skill {}", j))
+                .number("level", j as i64 * 20)))))
+        .build_context();
     
     context.set_string("name", "TDD Developer");
     context.set_number("level", 100);
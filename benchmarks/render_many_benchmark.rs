@@ -0,0 +1,111 @@
+// 🔮 Mystical-Runic v0.6.0 - Multi-document Rendering Benchmark
+// Compares render_many against a naive loop of render calls
+
+use mystical_runic::{TemplateEngine, TemplateContext, ValueFactory};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+fn main() {
+    println!("🔮 Mystical-Runic v0.6.0 - Multi-document Rendering Benchmark 🔮");
+    println!("=================================================================");
+    println!();
+
+    // Create test environment
+    let temp_dir = create_temp_dir();
+    create_test_templates(&temp_dir);
+
+    let mut engine = TemplateEngine::new(temp_dir.to_str().unwrap());
+    let workers = engine.get_render_workers();
+
+    for count in [100, 1_000, 10_000] {
+        let contexts: Vec<TemplateContext> = (0..count).map(create_recipient_context).collect();
+
+        println!("📨 {} documents", count);
+        println!("-----------------");
+
+        // Naive loop of render calls
+        let start = Instant::now();
+        let naive: Vec<String> = contexts.iter()
+            .map(|context| engine.render("letter.html", context).unwrap())
+            .collect();
+        let naive_time = start.elapsed();
+
+        // render_many on a single thread
+        engine.set_render_workers(1);
+        let start = Instant::now();
+        let serial = engine.render_many("letter.html", &contexts).unwrap();
+        let serial_time = start.elapsed();
+
+        // render_many across the worker pool
+        engine.set_render_workers(workers);
+        let start = Instant::now();
+        let parallel = engine.render_many("letter.html", &contexts).unwrap();
+        let parallel_time = start.elapsed();
+
+        // Outputs must be identical to individual renders
+        for (expected, (serial, parallel)) in naive.iter().zip(serial.iter().zip(parallel.iter())) {
+            assert_eq!(serial.as_ref().unwrap(), expected);
+            assert_eq!(parallel.as_ref().unwrap(), expected);
+        }
+
+        report("Naive render loop", naive_time, count, naive_time);
+        report("render_many (1 worker)", serial_time, count, naive_time);
+        report(&format!("render_many ({} workers)", workers), parallel_time, count, naive_time);
+        println!();
+    }
+
+    // Cleanup
+    let _ = fs::remove_dir_all(&temp_dir);
+
+    println!("🎉 Multi-document benchmark completed successfully!");
+}
+
+fn report(label: &str, elapsed: Duration, count: usize, baseline: Duration) {
+    let per_document = elapsed.as_nanos() / count as u128;
+    let speedup = baseline.as_secs_f64() / elapsed.as_secs_f64().max(f64::EPSILON);
+    println!("  {:<28} {:>12?}  ({} ns/doc, {:.2}x)", label, elapsed, per_document, speedup);
+}
+
+fn create_temp_dir() -> PathBuf {
+    let mut temp_path = std::env::temp_dir();
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    temp_path.push(format!("mystical_runic_render_many_{}_{}", std::process::id(), timestamp));
+    std::fs::create_dir_all(&temp_path).unwrap();
+    temp_path
+}
+
+fn create_test_templates(temp_dir: &PathBuf) {
+    fs::write(
+        temp_dir.join("base.html"),
+        "<html><body>{{block content}}{{/block}}<footer>{{company}}</footer></body></html>"
+    ).unwrap();
+
+    let letter = r#"{{extends "base.html"}}{{block content}}
+        <h1>Dear {{name|capitalize}},</h1>
+        {{if premium}}<p>Thank you for being a premium member since {{since}}.</p>{{/if}}
+        <ul>
+            {{for order in orders}}
+                <li>Order #{{order.id}}: {{order.total|currency}}</li>
+            {{/for}}
+        </ul>
+    {{/block}}"#;
+    fs::write(temp_dir.join("letter.html"), letter).unwrap();
+}
+
+fn create_recipient_context(i: usize) -> TemplateContext {
+    let mut context = TemplateContext::new();
+    context.set_string("name", &format!("recipient {}", i));
+    context.set_string("company", "Mystical Runic Ltd.");
+    context.set_bool("premium", i % 2 == 0);
+    context.set_number("since", 2000 + (i % 25) as i64);
+
+    context.set("orders", ValueFactory::repeat(5, |j, order| order
+        .number("id", (i * 10 + j) as i64)
+        .number("total", (j * 1_250 + i) as i64)));
+
+    context
+}
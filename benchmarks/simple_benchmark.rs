@@ -1,8 +1,7 @@
 // 🔮 Mystical-Runic v0.5.2 - Simple Performance Benchmark
 // Tests core functionality that is definitely implemented
 
-use mystical_runic::{TemplateEngine, TemplateContext, TemplateValue};
-use std::collections::HashMap;
+use mystical_runic::{TemplateEngine, TemplateContext, ValueFactory};
 use std::fs;
 use std::path::PathBuf;
 use std::time::Instant;
@@ -123,27 +122,13 @@ fn create_test_context() -> TemplateContext {
     context.set_number("score", 9999);
     
     // Create user data
-    let users: Vec<TemplateValue> = (0..20)
-        .map(|i| {
-            let mut user = HashMap::new();
-            user.insert("id".to_string(), TemplateValue::Number(i));
-            user.insert("name".to_string(), TemplateValue::String(format!("User {}", i)));
-            user.insert("active".to_string(), TemplateValue::Bool(i % 2 == 0));
-            
-            let skills: Vec<TemplateValue> = (0..3)
-                .map(|j| {
-                    let mut skill = HashMap::new();
-                    skill.insert("name".to_string(), TemplateValue::String(format!("Skill {}", j)));
-                    skill.insert("level".to_string(), TemplateValue::Number(j * 25 + 10));
-                    TemplateValue::Object(skill)
-                })
-                .collect();
-            user.insert("skills".to_string(), TemplateValue::Array(skills));
-            
-            TemplateValue::Object(user)
-        })
-        .collect();
-    context.set("users", TemplateValue::Array(users));
+    context.set("users", ValueFactory::repeat(20, |i, user| user
+        .number("id", i as i64)
+        .string("name", format!("User {}", i))
+        .bool("active", i % 2 == 0)
+        .value("skills", ValueFactory::repeat(3, |j, skill| skill
+            .string("name", format!("Skill {}", j))
+            .number("level", j as i64 * 25 + 10)))));
     
     context
 }
@@ -274,17 +259,12 @@ fn test_large_data(engine: &mut TemplateEngine) {
     println!("------------------------------------");
     
     // Create large dataset
-    let mut context = TemplateContext::new();
-    let items: Vec<TemplateValue> = (0..1000)
-        .map(|i| {
-            let mut item = HashMap::new();
-            item.insert("id".to_string(), TemplateValue::Number(i));
-            item.insert("name".to_string(), TemplateValue::String(format!("Item {}", i)));
-            item.insert("price".to_string(), TemplateValue::Number(i * 10 + 100));
-            TemplateValue::Object(item)
-        })
-        .collect();
-    context.set("items", TemplateValue::Array(items));
+    let context = ValueFactory::object()
+        .value("items", ValueFactory::repeat(1000, |i, item| item
+            .number("id", i as i64)
+            .string("name", format!("Item {}", i))
+            .number("price", i as i64 * 10 + 100)))
+        .build_context();
     
     let iterations = 10;
     
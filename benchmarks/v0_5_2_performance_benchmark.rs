@@ -1,7 +1,7 @@
 // 🔮 Mystical-Runic v0.5.2 - Comprehensive Performance Benchmark
 // Performance demonstration showcasing enterprise-grade features and optimizations
 
-use mystical_runic::{TemplateEngine, TemplateContext, TemplateValue};
+use mystical_runic::{TemplateEngine, TemplateContext, ValueFactory};
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -200,67 +200,36 @@ fn create_comprehensive_test_context() -> TemplateContext {
     context.set_string("heading", "Enterprise Performance Results");
     
     // Create realistic user data
-    let users: Vec<TemplateValue> = (0..100)
-        .map(|i| {
-            let mut user = HashMap::new();
-            user.insert("id".to_string(), TemplateValue::Number(i));
-            
-            let mut profile = HashMap::new();
-            profile.insert("name".to_string(), TemplateValue::String(format!("User {}", i)));
-            user.insert("profile".to_string(), TemplateValue::Object(profile));
-            
-            let mut stats = HashMap::new();
-            stats.insert("level".to_string(), TemplateValue::Number(i % 50 + 1));
-            stats.insert("score".to_string(), TemplateValue::Number(i * 1000 + 500));
-            user.insert("stats".to_string(), TemplateValue::Object(stats));
-            
-            user.insert("active".to_string(), TemplateValue::Bool(i % 3 != 0));
-            user.insert("score".to_string(), TemplateValue::Number(i * 100));
-            user.insert("bio".to_string(), TemplateValue::String(format!("Bio for user {} with detailed information about their background and expertise in various fields of technology and innovation.", i)));
-            user.insert("rating".to_string(), TemplateValue::Number((i % 100) as i64));
-            
+    context.set("users", ValueFactory::repeat(100, |i, user| {
+        let i = i as i64;
+        user.number("id", i)
+            .nested("profile", |profile| profile.string("name", format!("User {}", i)))
+            .nested("stats", |stats| stats
+                .number("level", i % 50 + 1)
+                .number("score", i * 1000 + 500))
+            .bool("active", i % 3 != 0)
+            .number("score", i * 100)
+            .string("bio", format!("Bio for user {} with detailed information about their background and expertise in various fields of technology and innovation.", i))
+            .number("rating", i % 100)
             // Skills array
-            let skills: Vec<TemplateValue> = (0..5)
-                .map(|j| {
-                    let mut skill = HashMap::new();
-                    skill.insert("name".to_string(), TemplateValue::String(format!("Skill {}", j)));
-                    skill.insert("level".to_string(), TemplateValue::Number(j * 20 + 10));
-                    skill.insert("category".to_string(), TemplateValue::String(format!("cat{}", j % 3)));
-                    skill.insert("certified".to_string(), TemplateValue::Bool(j % 2 == 0));
-                    TemplateValue::Object(skill)
-                })
-                .collect();
-            user.insert("skills".to_string(), TemplateValue::Array(skills));
-            
+            .value("skills", ValueFactory::repeat(5, |j, skill| skill
+                .string("name", format!("Skill {}", j))
+                .number("level", j as i64 * 20 + 10)
+                .string("category", format!("cat{}", j % 3))
+                .bool("certified", j % 2 == 0)))
             // Achievements array
-            let achievements: Vec<TemplateValue> = (0..3)
-                .map(|k| {
-                    let mut achievement = HashMap::new();
-                    achievement.insert("title".to_string(), TemplateValue::String(format!("Achievement {}", k)));
-                    achievement.insert("description".to_string(), TemplateValue::String(format!("This is a detailed description of achievement {} which was earned through dedication and hard work in the field.", k)));
-                    achievement.insert("date".to_string(), TemplateValue::String("2024-01-15".to_string()));
-                    TemplateValue::Object(achievement)
-                })
-                .collect();
-            user.insert("achievements".to_string(), TemplateValue::Array(achievements));
-            
-            TemplateValue::Object(user)
-        })
-        .collect();
-    context.set("users", TemplateValue::Array(users));
+            .value("achievements", ValueFactory::repeat(3, |k, achievement| achievement
+                .string("title", format!("Achievement {}", k))
+                .string("description", format!("This is a detailed description of achievement {} which was earned through dedication and hard work in the field.", k))
+                .string("date", "2024-01-15")))
+    }));
     
     // Large items array for stress testing
-    let items: Vec<TemplateValue> = (0..1000)
-        .map(|i| {
-            let mut item = HashMap::new();
-            item.insert("id".to_string(), TemplateValue::Number(i));
-            item.insert("name".to_string(), TemplateValue::String(format!("Item {}", i)));
-            item.insert("value".to_string(), TemplateValue::Number(i * 10 + 100));
-            item.insert("price".to_string(), TemplateValue::Number(i * 5 + 50));
-            TemplateValue::Object(item)
-        })
-        .collect();
-    context.set("items", TemplateValue::Array(items));
+    context.set("items", ValueFactory::repeat(1000, |i, item| item
+        .number("id", i as i64)
+        .string("name", format!("Item {}", i))
+        .number("value", i as i64 * 10 + 100)
+        .number("price", i as i64 * 5 + 50)));
     
     context
 }
@@ -7,16 +7,19 @@ use crate::cli::Args;
 use crate::core::{AppRunicRenderer, AppTemplateRenderer};
 use crate::data_loader::load_data_from_file;
 use crate::file_reader::read_file_content;
-use anyhow::{Context, Result};
+use anyhow::Result;
 use clap::Parser;
+use mystical_runic::{preview_in_browser, PreviewOptions};
 use std::path::PathBuf;
 use std::fs::File;
 use std::io::Write;
-use tempfile::NamedTempFile;
-use webbrowser::{self, Browser};
+use std::time::Duration;
 
 const DEFAULT_TEMPLATE_PATH: &str = "templates/main.rune";
 const DEFAULT_DATA_PATH: &str = "data/main.json";
+/// How long a preview stays on disk after the program exits; the next
+/// preview removes older ones
+const PREVIEW_TTL: Duration = Duration::from_secs(10 * 60);
 
 fn main() -> Result<()> {
     let args = Args::parse();
@@ -31,18 +34,9 @@ fn main() -> Result<()> {
     let rendered_html = engine.render(&template_content, &data)?;
 
     if args.browser {
-        let mut temp_file = NamedTempFile::new()?;
-        temp_file.write_all(rendered_html.as_bytes())?;
-        let temp_path = temp_file.path().to_path_buf();
-        let url = temp_path.to_str().context("Failed to convert path to string")?;
-
-        // Try to open with Firefox first
-        if webbrowser::open_browser(Browser::Firefox, url).is_err() {
-            // If Firefox fails, try the default browser
-            webbrowser::open(url)?;
-        }
-        // Keep the file alive until the program exits
-        temp_file.keep()?;
+        // The browser reads the page after we exit, so it outlives the handle
+        let preview = preview_in_browser(&rendered_html, PreviewOptions::new().with_ttl(PREVIEW_TTL))?;
+        println!("Previewing {}", preview.url());
     } else if let Some(output_path) = args.output {
         let mut file = File::create(&output_path)?;
         file.write_all(rendered_html.as_bytes())?;
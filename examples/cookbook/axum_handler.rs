@@ -0,0 +1,53 @@
+//! Cookbook: an Axum handler
+//!
+//! The engine sits in application state behind a mutex; each request
+//! layers its values over a shared base context and renders a response.
+//!
+//! Run with `cargo run --example cookbook_axum_handler --features axum-integration`
+
+use axum::extract::{Path, State};
+use axum::response::Html;
+use mystical_runic::{AxumTemplateEngine, SharedTemplateContext, TemplateContext, TemplateEngine, TemplateResponseError};
+use std::io::{self, Write};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+#[derive(Clone)]
+pub struct AppState {
+    engine: Arc<Mutex<TemplateEngine>>,
+    site: SharedTemplateContext,
+}
+
+/// `GET /hello/:name`
+pub async fn hello(State(state): State<AppState>, Path(name): Path<String>) -> Result<Html<String>, TemplateResponseError> {
+    let mut context = state.site.request_context();
+    context.set_string("name", &name);
+    let mut engine = state.engine.lock().await;
+    engine.render_axum("<h1>{{site}}</h1><p>Hello {{name}}!</p>", &context).await
+}
+
+/// Prints the example's output to `out`; `main` passes stdout
+pub fn run(out: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+    let mut site = TemplateContext::new();
+    site.set_string("site", "Runic Demo");
+    let state = AppState {
+        engine: Arc::new(Mutex::new(TemplateEngine::new("."))),
+        site: SharedTemplateContext::new(site),
+    };
+
+    // The handler is called directly here; in an application it is routed:
+    // Router::new().route("/hello/:name", get(hello)).with_state(state)
+    let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+    for name in ["Ada", "<Bob>"] {
+        let response = runtime.block_on(hello(State(state.clone()), Path(name.to_string())));
+        match response {
+            Ok(Html(html)) => writeln!(out, "200 {}", html)?,
+            Err(error) => writeln!(out, "{} {}", error.status, error.message)?,
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    run(&mut io::stdout())
+}
@@ -0,0 +1,49 @@
+//! Cookbook: a custom filter
+//!
+//! Filters receive the text of the value and the `:`-separated arguments of
+//! the filter step. Their output is HTML escaped like any other value.
+//!
+//! Run with `cargo run --example cookbook_custom_filter`
+
+use mystical_runic::{TemplateContext, TemplateEngine, TemplateError};
+use std::io::{self, Write};
+
+/// Prints the example's output to `out`; `main` passes stdout
+pub fn run(out: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+    let mut engine = TemplateEngine::new(".");
+
+    // `{{title|slug}}`: lowercase words joined by `-`
+    engine.register_filter("slug", |text, _args| {
+        let words: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        Ok(words.join("-"))
+    });
+
+    // `{{name|pad:12}}`: right-pad to a width, the argument is required
+    engine.register_filter("pad", |text, args| {
+        let width = args.first()
+            .and_then(|width| width.parse::<usize>().ok())
+            .ok_or_else(|| TemplateError::Render("pad needs a width, as in pad:12".to_string()))?;
+        Ok(format!("{:<width$}|", text, width = width))
+    });
+
+    let mut context = TemplateContext::new();
+    context.set_string("title", "Hello, Template World!");
+    context.set_string("name", "Ada");
+
+    writeln!(out, "{}", engine.render_string("/posts/{{title|slug}}", &context)?)?;
+    writeln!(out, "{}", engine.render_string("[{{name|pad:6}}]", &context)?)?;
+    // Filters chain left to right with the built-in ones
+    writeln!(out, "{}", engine.render_string("{{title|slug|upper}}", &context)?)?;
+
+    // A filter returning an error leaves the value as it was
+    writeln!(out, "{}", engine.render_string("[{{name|pad}}]", &context)?)?;
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    run(&mut io::stdout())
+}
@@ -0,0 +1,44 @@
+//! Cookbook: a helper with named arguments
+//!
+//! Helpers take positional arguments. A template macro in front of the
+//! helper gives callers named arguments with defaults.
+//!
+//! Run with `cargo run --example cookbook_helper_named_args`
+
+use mystical_runic::{TemplateContext, TemplateEngine, TemplateError, TemplateValue};
+use std::io::{self, Write};
+
+/// Prints the example's output to `out`; `main` passes stdout
+pub fn run(out: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+    let mut engine = TemplateEngine::new(".");
+
+    // `format_price(cents, currency, decimals)`
+    engine.register_helper("format_price", |args| {
+        let (Some(TemplateValue::Number(cents)), Some(TemplateValue::String(currency)), Some(TemplateValue::Number(decimals))) =
+            (args.first(), args.get(1), args.get(2))
+        else {
+            return Err(TemplateError::Render("format_price(cents, currency, decimals)".to_string()));
+        };
+        let amount = *cents as f64 / 100.0;
+        Ok(TemplateValue::String(format!("{:.*} {}", *decimals as usize, amount, currency)))
+    });
+
+    // The macro names the arguments and gives two of them defaults
+    let template = r#"{{macro price(cents, currency="EUR", decimals=2)}}{{format_price(cents, currency, decimals)}}{{/macro}}
+{{price(1999)}}
+{{price(1999, currency="USD")}}
+{{price(cents=250000, decimals=0, currency="JPY")}}"#;
+
+    let context = TemplateContext::new();
+    writeln!(out, "{}", engine.render_string(template, &context)?.trim())?;
+
+    // Named arguments straight on a helper are refused
+    if let Err(error) = engine.render_string(r#"{{format_price(1999, currency="USD", decimals=2)}}"#, &context) {
+        writeln!(out, "error: {}", error)?;
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    run(&mut io::stdout())
+}
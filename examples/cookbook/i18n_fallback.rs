@@ -0,0 +1,43 @@
+//! Cookbook: translations with a fallback locale
+//!
+//! A key missing from the current locale renders as the key itself. To fall
+//! back to a base locale instead, register the regional catalog on top of
+//! the base one.
+//!
+//! Run with `cargo run --example cookbook_i18n_fallback`
+
+use mystical_runic::{TemplateContext, TemplateEngine};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+fn catalog(entries: &[(&str, &str)]) -> HashMap<String, String> {
+    entries.iter().map(|(key, text)| (key.to_string(), text.to_string())).collect()
+}
+
+/// Prints the example's output to `out`; `main` passes stdout
+pub fn run(out: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+    let mut engine = TemplateEngine::new(".");
+
+    let french = catalog(&[("greeting", "Bonjour {{name}} !"), ("cart", "Panier"), ("checkout", "Payer")]);
+    let canadian = catalog(&[("cart", "Chariot")]);
+
+    // fr-CA: its own entries over the French ones
+    let mut regional = french.clone();
+    regional.extend(canadian);
+    engine.set_translations("fr", french);
+    engine.set_translations("fr-CA", regional);
+
+    let template = r#"{{t "greeting" name=user}} {{t "cart"}} / {{t "checkout"}} / {{t "wishlist"}}"#;
+    let mut context = TemplateContext::new();
+    context.set_string("user", "Zoé");
+
+    for locale in ["fr", "fr-CA"] {
+        engine.set_locale(locale);
+        writeln!(out, "{}: {}", locale, engine.render_string(template, &context)?)?;
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    run(&mut io::stdout())
+}
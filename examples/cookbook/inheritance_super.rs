@@ -0,0 +1,39 @@
+//! Cookbook: template inheritance with `{{super}}`
+//!
+//! A child template replaces the blocks of its parent; `{{super}}` inside a
+//! block keeps the parent's content.
+//!
+//! Run with `cargo run --example cookbook_inheritance_super`
+
+use mystical_runic::{TemplateContext, TemplateEngine};
+use std::fs;
+use std::io::{self, Write};
+
+/// Prints the example's output to `out`; `main` passes stdout
+pub fn run(out: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+    let templates = std::env::temp_dir().join(format!("runic_cookbook_inheritance_{}", std::process::id()));
+    fs::create_dir_all(&templates)?;
+
+    fs::write(
+        templates.join("base.html"),
+        "<title>{{block title}}My Site{{/block}}</title>\n<main>{{block content}}Nothing here yet{{/block}}</main>",
+    )?;
+    // `title` extends the parent's block, `content` replaces it
+    fs::write(
+        templates.join("post.html"),
+        "{{extends \"base.html\"}}{{block title}}{{post}} | {{super}}{{/block}}{{block content}}<h1>{{post}}</h1>{{/block}}",
+    )?;
+
+    let mut engine = TemplateEngine::new(templates.to_str().unwrap_or("."));
+    let mut context = TemplateContext::new();
+    context.set_string("post", "Release notes");
+    let rendered = engine.render("post.html", &context);
+
+    fs::remove_dir_all(&templates)?;
+    writeln!(out, "{}", rendered?)?;
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    run(&mut io::stdout())
+}
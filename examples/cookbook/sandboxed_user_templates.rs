@@ -0,0 +1,42 @@
+//! Cookbook: rendering templates written by users
+//!
+//! User templates only see the context you give them. Values are escaped,
+//! includes can't leave the template directory, dynamic includes are off
+//! unless allowed, and the nesting depth is capped.
+//!
+//! Run with `cargo run --example cookbook_sandboxed_user_templates`
+
+use mystical_runic::{TemplateContext, TemplateEngine};
+use std::io::{self, Write};
+
+/// Prints the example's output to `out`; `main` passes stdout
+pub fn run(out: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+    let mut engine = TemplateEngine::new(".");
+    engine.set_max_nesting_depth(8);
+
+    let mut context = TemplateContext::new();
+    context.set_string("name", "<script>alert(1)</script>");
+    context.set_string("bio", "<p onclick=\"steal()\">I like <b>Rust</b></p>");
+    context.set_string("page", "../../etc/passwd");
+
+    let user_templates = [
+        ("escaped", "Hello {{name}}"),
+        ("sanitized", "{{bio|sanitize}}"),
+        ("traversal", "{{include \"../secrets.html\"}}"),
+        ("dynamic", "{{include page}}"),
+        ("deep", "{{if name}}{{if name}}{{if name}}{{if name}}{{if name}}{{if name}}{{if name}}{{if name}}{{if name}}x{{/if}}{{/if}}{{/if}}{{/if}}{{/if}}{{/if}}{{/if}}{{/if}}{{/if}}"),
+    ];
+
+    for (label, template) in user_templates {
+        match engine.render_string(template, &context) {
+            Ok(html) => writeln!(out, "{}: {}", label, html)?,
+            // Parse errors go on with the offending line; the first line says it all
+            Err(error) => writeln!(out, "{}: refused ({})", label, error.to_string().lines().next().unwrap_or_default())?,
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    run(&mut io::stdout())
+}
@@ -0,0 +1,42 @@
+//! Cookbook: rendering many documents into a file
+//!
+//! `render_string_into` reuses one output buffer, so a large export costs
+//! one allocation for the buffer instead of one per document.
+//!
+//! Run with `cargo run --example cookbook_streaming_to_file`
+
+use mystical_runic::{TemplateContext, TemplateEngine};
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+
+/// Prints the example's output to `out`; `main` passes stdout
+pub fn run(out: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+    let mut engine = TemplateEngine::new(".");
+    let path = std::env::temp_dir().join(format!("runic_cookbook_export_{}.csv", std::process::id()));
+
+    let mut file = BufWriter::new(File::create(&path)?);
+    let mut buffer = String::with_capacity(256);
+    writeln!(file, "id,name,total")?;
+    for id in 1..=1000 {
+        let mut context = TemplateContext::new();
+        context.set_number("id", id);
+        context.set_string("name", &format!("customer {}", id));
+        context.set_number("total", id * 37 % 1000);
+        engine.render_string_into("{{id}},{{name|upper}},{{total}}\n", &context, &mut buffer)?;
+        file.write_all(buffer.as_bytes())?;
+    }
+    file.flush()?;
+    drop(file);
+
+    let export = fs::read_to_string(&path)?;
+    fs::remove_file(&path)?;
+    writeln!(out, "{} lines", export.lines().count())?;
+    for line in export.lines().take(3).chain(export.lines().last()) {
+        writeln!(out, "{}", line)?;
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    run(&mut io::stdout())
+}
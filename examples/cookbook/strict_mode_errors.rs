@@ -0,0 +1,47 @@
+//! Cookbook: strict mode and error handling
+//!
+//! By default a value that can't be converted, or a list printed as text,
+//! renders as something harmless. Strict mode turns these into errors you
+//! can match on; syntax errors fail in both modes.
+//!
+//! Run with `cargo run --example cookbook_strict_mode_errors`
+
+use mystical_runic::{TemplateContext, TemplateEngine, TemplateError, TemplateValue};
+use std::io::{self, Write};
+
+fn describe(result: Result<String, TemplateError>) -> String {
+    match result {
+        Ok(html) => format!("ok: {:?}", html),
+        Err(TemplateError::Parse(message)) => format!("parse error: {}", message),
+        Err(TemplateError::FilterError { filter_name, message, .. }) => format!("filter '{}' failed: {}", filter_name, message),
+        Err(TemplateError::Render(message)) => format!("render error: {}", message),
+        Err(error) => format!("error: {}", error),
+    }
+}
+
+/// Prints the example's output to `out`; `main` passes stdout
+pub fn run(out: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+    let mut engine = TemplateEngine::new(".");
+    let mut context = TemplateContext::new();
+    context.set_string("name", "Ada");
+    context.set_string("age", "unknown");
+    context.set("tags", TemplateValue::Array(vec![TemplateValue::String("rust".to_string())]));
+
+    let templates = ["Hi {{name}}!", "{{age|int}}", "{{tags}}", "{{if name}}unclosed"];
+
+    writeln!(out, "lenient:")?;
+    for template in templates {
+        writeln!(out, "  {}", describe(engine.render_string(template, &context)))?;
+    }
+
+    engine.enable_strict_mode();
+    writeln!(out, "strict:")?;
+    for template in templates {
+        writeln!(out, "  {}", describe(engine.render_string(template, &context)))?;
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    run(&mut io::stdout())
+}
@@ -0,0 +1,94 @@
+//! # Compile-time template checking for Mystical-Runic
+//!
+//! Provides `runic_template!`, re-exported by `mystical-runic` behind the
+//! `macros` feature. Use it through the main crate:
+//!
+//! ```rust,ignore
+//! use mystical_runic::{runic_template, PrecompiledTemplate, TemplateEngine, TemplateContext};
+//!
+//! const GREETING: PrecompiledTemplate = runic_template!("Hello {{name|upper}}");
+//!
+//! let mut engine = TemplateEngine::new("templates");
+//! let mut context = TemplateContext::new();
+//! context.set_string("name", "world");
+//! assert_eq!(engine.render_precompiled(&GREETING, &context).unwrap(), "Hello WORLD");
+//! ```
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{bracketed, parse_macro_input, Ident, LitStr, Token};
+
+// The runtime's syntax checker, so both agree
+use mystical_runic_syntax::{parse_template, Segment};
+
+/// `"template"` optionally followed by `, filters = ["name", ...]`
+struct TemplateInput {
+    source: LitStr,
+    custom_filters: Vec<LitStr>,
+}
+
+impl Parse for TemplateInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let source: LitStr = input.parse()?;
+        let mut custom_filters = Vec::new();
+
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let key: Ident = input.parse()?;
+            if key != "filters" {
+                return Err(syn::Error::new(key.span(), "expected `filters = [\"name\", ...]`"));
+            }
+            input.parse::<Token![=]>()?;
+            let content;
+            bracketed!(content in input);
+            let names = Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?;
+            custom_filters.extend(names);
+            input.parse::<Option<Token![,]>>()?;
+        }
+
+        Ok(Self { source, custom_filters })
+    }
+}
+
+/// Check a template at compile time and embed its compiled form.
+///
+/// Expands to a `mystical_runic::PrecompiledTemplate` constant expression,
+/// rendered with `TemplateEngine::render_precompiled`. The build fails with
+/// the line and column of the first unclosed tag or block, mismatched
+/// closing tag, malformed directive or unknown filter.
+///
+/// Custom filters can't be checked at compile time. List their names with
+/// `filters = [...]`; they are assumed to be registered on the engine that
+/// renders the template.
+///
+/// ```rust,ignore
+/// const PRICE: PrecompiledTemplate = runic_template!("{{price|money}}", filters = ["money"]);
+/// ```
+#[proc_macro]
+pub fn runic_template(input: TokenStream) -> TokenStream {
+    let TemplateInput { source, custom_filters } = parse_macro_input!(input as TemplateInput);
+    let template = source.value();
+    let custom_filters: Vec<String> = custom_filters.iter().map(LitStr::value).collect();
+    let custom_filters: Vec<&str> = custom_filters.iter().map(String::as_str).collect();
+
+    let segments = match parse_template(&template, &custom_filters) {
+        Ok(segments) => segments,
+        Err(error) => {
+            let message = format!(
+                "template syntax error at line {}, column {}: {}",
+                error.line, error.column, error.message
+            );
+            return syn::Error::new(source.span(), message).to_compile_error().into();
+        }
+    };
+
+    let instructions = segments.iter().map(|segment| match segment {
+        Segment::Text(text) => quote!(::mystical_runic::PrecompiledInstruction::Text(#text)),
+        Segment::Output(expression) => quote!(::mystical_runic::PrecompiledInstruction::Output(#expression)),
+        Segment::RawOutput(expression) => quote!(::mystical_runic::PrecompiledInstruction::RawOutput(#expression)),
+        Segment::Directive(tag) => quote!(::mystical_runic::PrecompiledInstruction::Directive(#tag)),
+    });
+
+    quote!(::mystical_runic::PrecompiledTemplate::new(#template, &[#(#instructions),*])).into()
+}
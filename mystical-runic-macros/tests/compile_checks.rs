@@ -0,0 +1,9 @@
+// Compile-time checks of runic_template!: valid templates build, broken ones
+// fail with the position of the error
+
+#[test]
+fn runic_template_compile_checks() {
+    let cases = trybuild::TestCases::new();
+    cases.pass("tests/ui/pass/*.rs");
+    cases.compile_fail("tests/ui/fail/*.rs");
+}
@@ -0,0 +1,53 @@
+// Rendering of runic_template! output matches render_string on the same source
+
+use mystical_runic::{runic_template, PrecompiledInstruction, PrecompiledTemplate, TemplateContext, TemplateEngine, TemplateValue};
+
+const GREETING: PrecompiledTemplate = runic_template!("Hello {{name|upper}}{{! note }}, {{& badge}}");
+const LIST: PrecompiledTemplate = runic_template!("{{for item in items}}<li>{{item}}</li>{{/for}}");
+const CUSTOM: PrecompiledTemplate = runic_template!("{{title|shout}}", filters = ["shout"]);
+const TRIMMED: PrecompiledTemplate = runic_template!("<p>\n  {{- name -}}\n  {{! note -}}\n</p>");
+
+fn create_context() -> TemplateContext {
+    let mut context = TemplateContext::new();
+    context.set_string("name", "<world>");
+    context.set_string("badge", "<b>new</b>");
+    context.set_string("title", "runes");
+    context.set("items", TemplateValue::Array(vec![
+        TemplateValue::String("a".to_string()),
+        TemplateValue::String("b".to_string()),
+    ]));
+    context
+}
+
+#[test]
+fn test_flat_template_compiles_to_instructions() {
+    assert_eq!(GREETING.instructions(), &[
+        PrecompiledInstruction::Text("Hello "),
+        PrecompiledInstruction::Output("name|upper"),
+        PrecompiledInstruction::Text(", "),
+        PrecompiledInstruction::RawOutput("badge"),
+    ]);
+}
+
+#[test]
+fn test_render_precompiled_matches_render_string() {
+    let mut engine = TemplateEngine::new(".");
+    let context = create_context();
+
+    for template in [GREETING, LIST, TRIMMED] {
+        let expected = engine.render_string(template.source(), &context).unwrap();
+        assert_eq!(engine.render_precompiled(&template, &context).unwrap(), expected);
+    }
+    assert_eq!(engine.render_precompiled(&GREETING, &context).unwrap(), "Hello &lt;WORLD&gt;, <b>new</b>");
+    assert_eq!(engine.render_precompiled(&LIST, &context).unwrap(), "<li>a</li><li>b</li>");
+    assert_eq!(engine.render_precompiled(&TRIMMED, &context).unwrap(), "<p>&lt;world&gt;  </p>");
+}
+
+#[test]
+fn test_declared_custom_filter_uses_registered_filter() {
+    let mut engine = TemplateEngine::new(".");
+    engine.register_filter("shout", |value, _args| Ok(format!("{}!", value.to_uppercase())));
+
+    let result = engine.render_precompiled(&CUSTOM, &create_context()).unwrap();
+    assert_eq!(result, "RUNES!");
+}
@@ -0,0 +1,5 @@
+use mystical_runic::{runic_template, PrecompiledTemplate};
+
+const BROKEN: PrecompiledTemplate = runic_template!("<p>\n  {{if ready}}Go\n</p>");
+
+fn main() {}
@@ -0,0 +1,12 @@
+use mystical_runic::{runic_template, PrecompiledTemplate};
+
+const PAGE: PrecompiledTemplate = runic_template!(r#"{{! header }}<ul>
+{{for item in items}}
+    {{if item.visible}}<li>{{item.name|capitalize}}</li>{{/if}}
+{{/for}}
+</ul>{{include "footer.html"}}{{t "welcome"}}{{plural count "item" "items"}}
+{{macro badge(label)}}<b>{{label}}</b>{{/macro}}{{& html}}"#);
+
+fn main() {
+    assert!(!PAGE.instructions().is_empty());
+}
@@ -0,0 +1,11 @@
+use mystical_runic::{runic_template, PrecompiledTemplate};
+
+const GREETING: PrecompiledTemplate = runic_template!("Hello {{name|upper}}");
+const PRICE: PrecompiledTemplate = runic_template!("{{price|round:2|currency}}");
+const CUSTOM: PrecompiledTemplate = runic_template!("{{title|shout|lower}}", filters = ["shout"]);
+
+fn main() {
+    assert_eq!(GREETING.source(), "Hello {{name|upper}}");
+    assert_eq!(PRICE.instructions().len(), 1);
+    assert_eq!(CUSTOM.instructions().len(), 1);
+}
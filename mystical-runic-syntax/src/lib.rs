@@ -0,0 +1,431 @@
+//! # Template syntax checking for Mystical-Runic
+//!
+//! Shared by `mystical-runic` and the `runic_template!` macro crate, so
+//! templates are checked by identical rules at build time and at runtime.
+//! Only uses `std`.
+
+/// Filters implemented by the engine itself
+pub const BUILTIN_FILTERS: [&str; 42] = [
+    "upper", "lower", "capitalize", "truncate", "truncate_html", "currency", "date", "timeago", "strip",
+    "add", "multiply", "divide", "percentage", "round", "slugify", "duration", "filesize", "percent", "length",
+    "count_chars", "count_bytes",
+    "markdown", "highlight", "sanitize", "int", "float", "bool", "string", "yesno",
+    "translate", "t", "default", "join", "first", "last", "slice", "sort", "reverse", "unique",
+    "safe", "escape", "e",
+];
+
+/// Directives that open a block closed by `{{/name}}`
+const BLOCK_DIRECTIVES: [&str; 9] = ["if", "unless", "ifchanged", "for", "macro", "block", "t_scope", "try", "capture"];
+
+/// Directives whose first argument must be a quoted string; without
+/// arguments these names are plain variables
+const QUOTED_DIRECTIVES: [&str; 9] = [
+    "t", "needs", "required_assets", "include_raw", "has_slot", "input", "select", "checkbox", "field_errors",
+];
+
+/// A syntax error and where it starts in the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    /// Line of the offending tag (1-based)
+    pub line: usize,
+    /// Column of the offending tag (1-based)
+    pub column: usize,
+    /// What is wrong
+    pub message: String,
+}
+
+/// A piece of a checked template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment<'a> {
+    /// Literal text
+    Text(&'a str),
+    /// `{{expression}}`, filters included
+    Output(&'a str),
+    /// `{{& expression}}`
+    RawOutput(&'a str),
+    /// Any other tag, braces included; needs the full render pipeline
+    Directive(&'a str),
+}
+
+/// Check a template and split it into segments.
+///
+/// Reports unclosed tags and blocks, mismatched closing tags, malformed
+/// directives and filters that are neither built in nor listed in
+/// `custom_filters`. Comments (`{{! ... }}`) produce no segment.
+pub fn parse_template<'a>(source: &'a str, custom_filters: &[&str]) -> Result<Vec<Segment<'a>>, SyntaxError> {
+    let mut segments = Vec::new();
+    // Open blocks: directive name and offset of the opening tag
+    let mut open_blocks: Vec<(&str, usize)> = Vec::new();
+    let mut pos = 0;
+    // Whether the previous tag trims the whitespace after it
+    let mut trim_next = false;
+
+    while let Some(relative_start) = source[pos..].find("{{") {
+        let start = pos + relative_start;
+        let rest = &source[start..];
+
+        // Visible comments may contain `}}`, so they close on `--}}`
+        let visible = rest.starts_with("{{#--") || rest.starts_with("{{-#--");
+        let end = if visible {
+            rest.find("--}}").map(|close| close + 2)
+                .ok_or_else(|| syntax_error(source, start, "Unclosed visible comment".to_string()))?
+        } else {
+            rest.find("}}")
+                .ok_or_else(|| syntax_error(source, start, "Unclosed tag: missing '}}'".to_string()))?
+        };
+        let (trim_before, inner, trim_after) = split_trim_markers(&rest[2..end]);
+        push_text(&mut segments, &source[pos..start], trim_next, trim_before);
+        trim_next = trim_after;
+        pos = start + end + 2;
+        let tag = &source[start..pos];
+        if visible {
+            segments.push(Segment::Directive(tag));
+            continue;
+        }
+        let directive = inner.trim();
+
+        if directive.is_empty() {
+            return Err(syntax_error(source, start, "Empty tag '{{}}'".to_string()));
+        }
+        if directive.starts_with('!') {
+            continue;
+        }
+
+        if let Some(closing) = directive.strip_prefix('/') {
+            let closing = closing.trim();
+            if !BLOCK_DIRECTIVES.contains(&closing) {
+                return Err(syntax_error(source, start, format!("Unknown closing tag '{}'", tag)));
+            }
+            match open_blocks.pop() {
+                Some((opened, _)) if opened == closing => {}
+                Some((opened, offset)) => {
+                    let (line, column) = line_column(source, offset);
+                    return Err(syntax_error(source, start, format!(
+                        "'{}' does not match '{{{{{}}}}}' opened at line {}, column {}", tag, opened, line, column
+                    )));
+                }
+                None => return Err(syntax_error(source, start, format!("Unexpected closing tag '{}'", tag))),
+            }
+            segments.push(Segment::Directive(tag));
+            continue;
+        }
+
+        let (keyword, arguments) = match directive.split_once(char::is_whitespace) {
+            Some((keyword, arguments)) => (keyword, arguments.trim()),
+            None => (directive, ""),
+        };
+        if let Some(message) = check_directive(keyword, arguments) {
+            return Err(syntax_error(source, start, message));
+        }
+        match keyword {
+            "if" | "unless" | "ifchanged" | "for" | "macro" | "block" | "t_scope" => {
+                open_blocks.push((keyword, start));
+                segments.push(Segment::Directive(tag));
+            }
+            "try" if arguments.is_empty() => {
+                open_blocks.push((keyword, start));
+                segments.push(Segment::Directive(tag));
+            }
+            // Without a name `{{capture}}` stays a variable
+            "capture" if !arguments.is_empty() => {
+                open_blocks.push((keyword, start));
+                segments.push(Segment::Directive(tag));
+            }
+            "rescue" if arguments.is_empty() => {
+                if open_blocks.last().map(|(opened, _)| *opened) != Some("try") {
+                    return Err(syntax_error(source, start, format!("Unexpected '{}' outside '{{{{try}}}}'", tag)));
+                }
+                segments.push(Segment::Directive(tag));
+            }
+            // `{{else}}`, `{{elseif condition}}` or `{{else if condition}}`
+            "else" | "elseif" if match keyword {
+                "else" => arguments.is_empty() || arguments.strip_prefix("if").is_some_and(|condition| condition.starts_with(char::is_whitespace)),
+                _ => !arguments.is_empty(),
+            } => {
+                if open_blocks.last().map(|(opened, _)| *opened) != Some("if") {
+                    return Err(syntax_error(source, start, format!("Unexpected '{}' outside '{{{{if}}}}'", tag)));
+                }
+                segments.push(Segment::Directive(tag));
+            }
+            // Outside a loop `{{empty}}` stays a variable
+            "empty" if arguments.is_empty() && open_blocks.last().map(|(opened, _)| *opened) == Some("for") => {
+                segments.push(Segment::Directive(tag));
+            }
+            "super" if arguments.is_empty() => {
+                if !open_blocks.iter().any(|(opened, _)| *opened == "block") {
+                    return Err(syntax_error(source, start, format!("Unexpected '{}' outside '{{{{block}}}}'", tag)));
+                }
+                segments.push(Segment::Directive(tag));
+            }
+            "include" | "extends" => segments.push(Segment::Directive(tag)),
+            "t" | "plural" | "needs" | "required_assets" | "include_raw" | "table" | "props" | "slots" | "has_slot"
+            | "input" | "select" | "checkbox" | "field_errors" | "assert" | "set" if !arguments.is_empty() => {
+                segments.push(Segment::Directive(tag));
+            }
+            _ => {
+                let (expression, raw) = match directive.strip_prefix('&') {
+                    Some(expression) => (expression.trim(), true),
+                    None => (directive, false),
+                };
+                if let Some(message) = check_filters(expression, custom_filters) {
+                    return Err(syntax_error(source, start, message));
+                }
+                segments.push(if raw { Segment::RawOutput(expression) } else { Segment::Output(expression) });
+            }
+        }
+    }
+
+    if let Some((opened, offset)) = open_blocks.pop() {
+        return Err(syntax_error(source, offset, format!("Unclosed '{{{{{}}}}}': missing '{{{{/{}}}}}'", opened, opened)));
+    }
+    push_text(&mut segments, &source[pos..], trim_next, false);
+    Ok(segments)
+}
+
+/// Push the text between two tags, trimmed by their whitespace control markers
+fn push_text<'a>(segments: &mut Vec<Segment<'a>>, text: &'a str, after_marker: bool, before_marker: bool) {
+    let text = if after_marker { trim_after_marker(text) } else { text };
+    let text = if before_marker { text.trim_end() } else { text };
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+}
+
+/// Split the inside of a tag, between `{{` and `}}`, into its whitespace
+/// control markers and its directive: `{{- tag}}` trims the whitespace before
+/// the tag and `{{tag -}}` the whitespace after it. A visible comment closes
+/// on `--}}`, so its closing marker is a third dash (`{{-#-- note ---}}`).
+pub fn split_trim_markers(inner: &str) -> (bool, &str, bool) {
+    let (before, inner) = match inner.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, inner),
+    };
+    let marker = if inner.starts_with("#--") { "---" } else { "-" };
+    match inner.strip_suffix('-').filter(|rest| inner.ends_with(marker) && !rest.trim().is_empty()) {
+        Some(rest) => (before, rest, true),
+        None => (before, inner, false),
+    }
+}
+
+/// Text following a `-}}` marker without its spaces and tabs up to and
+/// including one newline
+pub fn trim_after_marker(text: &str) -> &str {
+    let rest = text.trim_start_matches([' ', '\t']);
+    rest.strip_prefix("\r\n").or_else(|| rest.strip_prefix('\n')).unwrap_or(rest)
+}
+
+/// Validate the arguments of a known directive
+fn check_directive(keyword: &str, arguments: &str) -> Option<String> {
+    match keyword {
+        "if" | "unless" if arguments.is_empty() => Some(format!("Malformed '{{{{{}}}}}': missing condition", keyword)),
+        "ifchanged" if arguments.is_empty() => Some("Malformed '{{ifchanged}}': missing expression".to_string()),
+        "for" => {
+            let valid = match arguments.split_once(" in ") {
+                Some((item, items)) => is_identifier(item.trim()) && !items.trim().is_empty(),
+                None => false,
+            };
+            (!valid).then(|| format!("Malformed '{{{{for {}}}}}': expected '{{{{for item in items}}}}'", arguments))
+        }
+        "macro" => {
+            let valid = arguments.find('(')
+                .is_some_and(|open| is_identifier(arguments[..open].trim()) && arguments.ends_with(')'));
+            (!valid).then(|| format!("Malformed '{{{{macro {}}}}}': expected '{{{{macro name(params)}}}}'", arguments))
+        }
+        "block" => {
+            let mut parts = arguments.split_whitespace();
+            let valid = parts.next().is_some_and(is_identifier)
+                && parts.all(|parameter| parameter.split_once('=')
+                    .is_some_and(|(name, path)| is_identifier(name) && path.split('.').all(is_identifier)));
+            (!valid).then(|| format!("Malformed '{{{{block {}}}}}': expected '{{{{block name param=path}}}}'", arguments))
+        }
+        "t_scope" if !arguments.starts_with(['"', '\'']) || arguments.len() < 3 => {
+            Some(format!("Malformed '{{{{t_scope {}}}}}': expected '{{{{t_scope \"prefix\"}}}}'", arguments))
+        }
+        "include" | "extends" if arguments.is_empty() => {
+            Some(format!("Malformed '{{{{{}}}}}': missing template name", keyword))
+        }
+        "assert" if split_assertion(arguments).is_none() => {
+            Some(format!("Malformed '{{{{assert {}}}}}': expected '{{{{assert condition \"message\"}}}}'", arguments))
+        }
+        "capture" if !arguments.is_empty() && !is_identifier(arguments) => {
+            Some(format!("Malformed '{{{{capture {}}}}}': expected '{{{{capture name}}}}'", arguments))
+        }
+        "set" if split_assignment(arguments).is_none() => {
+            Some(format!("Malformed '{{{{set {}}}}}': expected '{{{{set name = value}}}}'", arguments))
+        }
+        "table" if !arguments.is_empty() && !arguments.contains("columns=") => {
+            Some(format!("Malformed '{{{{table {}}}}}': expected '{{{{table rows columns=\"...\"}}}}'", arguments))
+        }
+        _ if QUOTED_DIRECTIVES.contains(&keyword) && !arguments.is_empty() && !arguments.starts_with(['"', '\'']) => {
+            Some(format!("Malformed '{{{{{} {}}}}}': expected a quoted argument", keyword, arguments))
+        }
+        _ => None,
+    }
+}
+
+/// Split `{{assert}}` arguments into the condition and the unquoted message
+/// that ends them
+pub fn split_assertion(arguments: &str) -> Option<(&str, &str)> {
+    let arguments = arguments.trim();
+    let quote = arguments.chars().last().filter(|last| *last == '"' || *last == '\'')?;
+    let body = &arguments[..arguments.len() - 1];
+    let open = body.rfind(quote)?;
+    let condition = body[..open].trim();
+    (!condition.is_empty()).then(|| (condition, &body[open + 1..]))
+}
+
+/// Split `{{set}}` arguments into the variable name and the value expression
+pub fn split_assignment(arguments: &str) -> Option<(&str, &str)> {
+    let (name, value) = arguments.split_once('=')?;
+    let (name, value) = (name.trim(), value.trim());
+    (is_identifier(name) && !value.is_empty() && !value.starts_with('=')).then_some((name, value))
+}
+
+/// Check every filter of an output expression
+fn check_filters(expression: &str, custom_filters: &[&str]) -> Option<String> {
+    let mut parts = expression.split('|');
+    if parts.next().unwrap_or_default().trim().is_empty() {
+        return Some(format!("Missing expression in '{{{{{}}}}}'", expression));
+    }
+    for filter in parts {
+        let name = filter.split(':').next().unwrap_or_default().trim();
+        if name.is_empty() {
+            return Some(format!("Empty filter in '{{{{{}}}}}'", expression));
+        }
+        if !BUILTIN_FILTERS.contains(&name) && !custom_filters.contains(&name) {
+            return Some(format!("Unknown filter '{}'", name));
+        }
+    }
+    None
+}
+
+/// Kind of value a built-in filter argument must be
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterArgument {
+    /// `truncate:10`
+    Integer,
+    /// `multiply:1.5`
+    Number,
+    /// `date:"Y-m-d"`; anything goes
+    Text,
+}
+
+/// Arguments a built-in filter accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilterSignature {
+    pub min_args: usize,
+    pub max_args: usize,
+    pub argument: FilterArgument,
+    /// How the filter is written, `round[:decimals]`
+    pub usage: &'static str,
+}
+
+/// Signature of a built-in filter
+pub fn filter_signature(name: &str) -> Option<FilterSignature> {
+    use FilterArgument::{Integer, Number, Text};
+    let (min_args, max_args, argument, usage) = match name {
+        "truncate" => (1, 1, Integer, "truncate:length"),
+        "truncate_html" => (1, 1, Integer, "truncate_html:length"),
+        "round" => (0, 1, Integer, "round[:decimals]"),
+        "duration" => (0, 1, Integer, "duration[:units]"),
+        "filesize" => (0, 1, Integer, "filesize[:decimals]"),
+        "percent" => (0, 1, Integer, "percent[:decimals]"),
+        "add" => (1, 1, Number, "add:number"),
+        "multiply" => (1, 1, Number, "multiply:number"),
+        "divide" => (1, 1, Number, "divide:number"),
+        "date" => (0, 1, Text, "date[:format]"),
+        "highlight" => (0, 1, Text, "highlight[:language]"),
+        "yesno" => (0, 3, Text, "yesno[:yes:no[:none]]"),
+        "translate" => (0, 1, Text, "translate[:prefix]"),
+        "t" => (0, 1, Text, "t[:prefix]"),
+        "default" => (1, 1, Text, "default:value"),
+        "join" => (0, 1, Text, "join[:separator]"),
+        "slice" => (1, 2, Integer, "slice:start[:end]"),
+        "sort" => (0, 2, Text, "sort[:key][:desc]"),
+        "unique" => (0, 1, Text, "unique[:key]"),
+        _ if BUILTIN_FILTERS.contains(&name) => (0, 0, Text, ""),
+        _ => return None,
+    };
+    let usage = if usage.is_empty() { BUILTIN_FILTERS.iter().find(|builtin| **builtin == name)? } else { usage };
+    Some(FilterSignature { min_args, max_args, argument, usage })
+}
+
+/// Split `name:arg:"quoted:arg"` into the filter name and its arguments,
+/// keeping quotes; a `:` inside quotes doesn't separate arguments
+pub fn split_filter_arguments(filter: &str) -> (&str, Vec<&str>) {
+    let mut parts = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (index, c) in filter.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, ':') => {
+                parts.push(filter[start..index].trim());
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(filter[start..].trim());
+    let name = parts.remove(0);
+    (name, parts)
+}
+
+/// Check the argument count of a built-in filter step and the type of its
+/// literal arguments. Unquoted names (`truncate:limit`) are variables and
+/// calls (`truncate:math.min(limit, 10)`) are functions, both checked when
+/// rendering; custom filters and pipelines are not checked.
+pub fn check_filter_arguments(filter: &str) -> Option<String> {
+    let (name, args) = split_filter_arguments(filter);
+    let signature = filter_signature(name)?;
+    if let Some(empty) = args.iter().position(|arg| arg.is_empty()) {
+        return Some(format!(
+            "Empty argument {} in '{}'; usage: {}", empty + 1, filter.trim(), signature.usage
+        ));
+    }
+    if args.len() < signature.min_args || args.len() > signature.max_args {
+        let expected = match (signature.min_args, signature.max_args) {
+            (min, max) if min == max => format!("{} argument{}", min, if min == 1 { "" } else { "s" }),
+            (min, max) => format!("{} to {} arguments", min, max),
+        };
+        return Some(format!(
+            "Filter '{}' takes {}, got {}; usage: {}", name, expected, args.len(), signature.usage
+        ));
+    }
+    args.iter()
+        .filter(|arg| !arg.split('.').all(is_identifier) && !arg.starts_with('$') && !arg.ends_with(')'))
+        .find_map(|arg| argument_type_problem(name, &signature, arg))
+}
+
+/// Why a literal (or resolved) argument doesn't fit the filter
+pub fn argument_type_problem(name: &str, signature: &FilterSignature, arg: &str) -> Option<String> {
+    let text = arg.trim_matches('"').trim_matches('\'');
+    let (valid, expected) = match signature.argument {
+        FilterArgument::Integer => (text.parse::<usize>().is_ok(), "a whole number"),
+        FilterArgument::Number => (text.parse::<f64>().is_ok(), "a number"),
+        FilterArgument::Text => (true, ""),
+    };
+    (!valid).then(|| format!(
+        "Filter '{}' expects {}, got {}; usage: {}", name, expected, arg, signature.usage
+    ))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|first| first.is_alphabetic() || first == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn syntax_error(source: &str, offset: usize, message: String) -> SyntaxError {
+    let (line, column) = line_column(source, offset);
+    SyntaxError { line, column, message }
+}
+
+/// Line and column (1-based) of a byte offset
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+    (line, column)
+}
@@ -51,6 +51,10 @@ path = "benchmarks/v0_5_2_performance_benchmark.rs"
 [[bin]]
 name = "ecosystem_benchmark"  
 path = "benchmarks/ecosystem_benchmark.rs"
+
+[[bin]]
+name = "render_many_benchmark"
+path = "benchmarks/render_many_benchmark.rs"
 EOF
 
 echo "✅ Benchmark binaries configured"
@@ -65,6 +69,9 @@ run_benchmark "Original Performance Demo" "" "performance_demo"
 # Comprehensive v0.5.2 benchmark  
 run_benchmark "v0.5.2 Comprehensive Benchmark" "" "v0_5_2_benchmark"
 
+# Multi-document rendering benchmark
+run_benchmark "Multi-document Rendering Benchmark" "" "render_many_benchmark"
+
 echo "🌐 ECOSYSTEM INTEGRATION BENCHMARKS"
 echo "==================================="
 
@@ -0,0 +1,224 @@
+//! Directive argument lists for v0.6.0
+//!
+//! Helper calls (`{{price(total, "EUR")}}`), macro calls
+//! (`{{button("Save", color=theme.primary)}}`), `{{t "key" name=user.name}}`
+//! and `{{plural count "item" "items"}}` read their arguments with
+//! [`parse_arguments`], so they accept the same values and report the same
+//! errors:
+//!
+//! | Argument | Value |
+//! |----------|-------|
+//! | `"text"`, `'text'` | string; `\"`, `\'`, `\\`, `\n` and `\t` are escapes |
+//! | `42`, `-1.5` | number |
+//! | `true`, `false` | boolean |
+//! | `user.name`, `count\|int` | variable path, optionally through filters |
+//! | `name=value` | any of the above, bound to `name` |
+//!
+//! Comma separated lists may end with a trailing comma.
+
+use crate::{TemplateError, TemplateResult};
+use std::ops::Range;
+
+/// How the arguments of a directive are separated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Separator {
+    /// `(a, b, name=c)`
+    Comma,
+    /// `a b name=c`
+    Whitespace,
+}
+
+/// Value of one argument
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum ArgumentValue<'a> {
+    /// Quoted string, escapes resolved
+    String(String),
+    Number(i64),
+    Float(f64),
+    Bool(bool),
+    /// Variable path with an optional filter chain, resolved by the caller
+    Expression(&'a str),
+}
+
+/// One argument of a directive
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Argument<'a> {
+    /// `Some` for `name=value` arguments
+    pub name: Option<&'a str>,
+    pub value: ArgumentValue<'a>,
+    /// Source text of the value
+    pub text: &'a str,
+}
+
+/// Arguments in the byte range `list` of `directive`. Errors give the
+/// 1-based character position in the directive.
+pub(crate) fn parse_arguments(directive: &str, list: Range<usize>, separator: Separator) -> TemplateResult<Vec<Argument<'_>>> {
+    let error = |message: String, offset: usize| {
+        TemplateError::Parse(format!(
+            "{} at position {} in '{{{{{}}}}}'",
+            message,
+            directive[..offset].chars().count() + 1,
+            directive
+        ))
+    };
+
+    let mut arguments: Vec<Argument> = Vec::new();
+    let pieces = split_pieces(directive, list, separator)
+        .map_err(|offset| error("Unterminated string".to_string(), offset))?;
+    let count = pieces.len();
+    for (index, (offset, piece)) in pieces.into_iter().enumerate() {
+        let trimmed = piece.trim_start();
+        let offset = offset + piece.len() - trimmed.len();
+        let piece = trimmed.trim_end();
+        if piece.is_empty() {
+            // `(a, b,)`: only the last comma may be followed by nothing
+            if separator == Separator::Comma && index + 1 == count && index > 0 {
+                break;
+            }
+            return Err(error("Empty argument".to_string(), offset));
+        }
+
+        let (name, text, value_offset) = match split_name(piece) {
+            Some(("", _)) => return Err(error("Missing argument name before '='".to_string(), offset)),
+            Some((name, value)) => {
+                let text = value.trim_start();
+                (Some(name), text, offset + piece.len() - text.len())
+            }
+            None => (None, piece, offset),
+        };
+        if let Some(name) = name {
+            if text.is_empty() {
+                return Err(error(format!("Missing value for argument '{}'", name), offset));
+            }
+            if arguments.iter().any(|argument| argument.name == Some(name)) {
+                return Err(error(format!("Duplicate argument '{}'", name), offset));
+            }
+        }
+        let value = parse_value(text)
+            .map_err(|(message, relative)| error(message.to_string(), value_offset + relative))?;
+        arguments.push(Argument { name, value, text });
+    }
+    Ok(arguments)
+}
+
+/// Value of a single argument, e.g. a macro parameter default
+pub(crate) fn parse_value(text: &str) -> Result<ArgumentValue<'_>, (&'static str, usize)> {
+    if let Some(quote) = text.chars().next().filter(|first| *first == '"' || *first == '\'') {
+        let close = closing_quote(text, quote).ok_or(("Unterminated string", 0))?;
+        if close + 1 != text.len() {
+            return Err(("Unexpected text after string", close + 1));
+        }
+        return Ok(ArgumentValue::String(unescape(&text[1..close])));
+    }
+
+    match text {
+        "true" => return Ok(ArgumentValue::Bool(true)),
+        "false" => return Ok(ArgumentValue::Bool(false)),
+        _ => {}
+    }
+    if let Ok(number) = text.parse::<i64>() {
+        return Ok(ArgumentValue::Number(number));
+    }
+    let numeric = text.strip_prefix('-').unwrap_or(text).starts_with(|c: char| c.is_ascii_digit());
+    if numeric && text.contains('.') && !text.contains('|') {
+        if let Ok(number) = text.parse::<f64>() {
+            return Ok(ArgumentValue::Float(number));
+        }
+    }
+    Ok(ArgumentValue::Expression(text))
+}
+
+/// Byte offset of the quote closing the string `text` starts with
+fn closing_quote(text: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (index, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if c == quote => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some(escaped @ ('"' | '\'' | '\\')) => unescaped.push(escaped),
+            // Unknown escapes stay as written, so `C:\path` keeps its backslash
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Pieces of the argument list with their byte offsets in the directive;
+/// `Err` holds the offset of an unterminated quote
+fn split_pieces(directive: &str, list: Range<usize>, separator: Separator) -> Result<Vec<(usize, &str)>, usize> {
+    let start = list.start;
+    let list = &directive[list];
+    let mut pieces = Vec::new();
+    if list.trim().is_empty() {
+        return Ok(pieces);
+    }
+
+    let mut piece_start = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in list.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some((open, _)) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some((c, index)),
+            None if separator == Separator::Comma && c == ',' => {
+                pieces.push((start + piece_start, &list[piece_start..index]));
+                piece_start = index + 1;
+            }
+            None if separator == Separator::Whitespace && c.is_whitespace() => {
+                if piece_start < index {
+                    pieces.push((start + piece_start, &list[piece_start..index]));
+                }
+                piece_start = index + c.len_utf8();
+            }
+            None => {}
+        }
+    }
+    if let Some((_, open)) = quote {
+        return Err(start + open);
+    }
+    if separator == Separator::Comma || piece_start < list.len() {
+        pieces.push((start + piece_start, &list[piece_start..]));
+    }
+    Ok(pieces)
+}
+
+/// `name` and value text of a `name=value` argument; comparisons such as
+/// `a == b` or `a <= b` are not named
+fn split_name(piece: &str) -> Option<(&str, &str)> {
+    if piece.starts_with(['"', '\'']) {
+        return None;
+    }
+    let equals = piece.find('=')?;
+    let (name, value) = (piece[..equals].trim_end(), &piece[equals + 1..]);
+    if value.starts_with('=') || name.ends_with(['!', '<', '>', '=']) {
+        return None;
+    }
+    let is_name = name.chars().next().map_or(true, |first| first.is_alphabetic() || first == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    is_name.then_some((name, value))
+}
@@ -0,0 +1,873 @@
+//! Template syntax tree for v0.6.0
+//!
+//! A read-only view of a parsed template for tools built outside the crate:
+//! linters, converters, documentation extractors. Every node carries the
+//! [`Span`] of the source it was parsed from, so tools can point at
+//! locations and rewrite templates by splicing the source.
+//!
+//! Nodes, their fields and [`Visitor`] methods may grow with the template
+//! syntax; the types are `#[non_exhaustive]` so that adding them is not a
+//! breaking change. Match nodes with a `_ => {}` arm and rely on the visitor
+//! defaults for the kinds a tool doesn't care about.
+//!
+//! Parsing checks structure only: tags and blocks must be balanced and
+//! directives well formed. Filters, macros and variables are not resolved.
+//!
+//! ```rust
+//! use mystical_runic::ast::{self, Node};
+//!
+//! let template = ast::parse("<h1>{{title|upper}}</h1>{{for item in items}}{{item}}{{/for}}")?;
+//! assert!(matches!(&template.nodes[1], Node::Variable(variable) if variable.path == "title"));
+//! assert!(matches!(&template.nodes[3], Node::For(node) if node.expr == "items" && node.body.len() == 1));
+//! # Ok::<(), mystical_runic::TemplateError>(())
+//! ```
+
+use crate::engine::{else_condition, is_variable_path, split_directive_arguments, split_include_scope, split_include_source, split_named_argument, split_unquoted};
+use crate::error::{TemplateError, TemplateResult};
+use crate::suggestions::{extract_context_lines, find_line_column};
+use crate::syntax::{split_assignment, split_filter_arguments, split_trim_markers};
+
+/// Location of a node in the template source
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// Byte offset of the first character
+    pub start: usize,
+    /// Byte offset just past the last character
+    pub end: usize,
+    /// Line of `start` (1-based)
+    pub line: usize,
+    /// Column of `start` (1-based)
+    pub column: usize,
+}
+
+impl Span {
+    /// The spanned part of `source`
+    pub fn slice<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start..self.end]
+    }
+}
+
+/// A parsed template, from [`parse`] or
+/// [`TemplateEngine::parse`](crate::TemplateEngine::parse)
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    /// Template name, `None` when a template string was parsed
+    pub name: Option<String>,
+    /// Source the spans point into
+    pub source: String,
+    /// Top-level nodes, in source order
+    pub nodes: Vec<Node>,
+}
+
+impl Template {
+    /// The source with each edited span replaced by its new text
+    ///
+    /// Edits may be given in any order; overlapping edits fail with
+    /// [`TemplateError::Template`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::ast::{self, Node};
+    ///
+    /// let template = ast::parse("Hi {{name}}!")?;
+    /// let edits = template.nodes.iter().filter_map(|node| match node {
+    ///     Node::Variable(variable) => Some((variable.span, "{{user.name}}".to_string())),
+    ///     _ => None,
+    /// });
+    /// assert_eq!(template.splice(edits)?, "Hi {{user.name}}!");
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn splice<I>(&self, edits: I) -> TemplateResult<String>
+    where
+        I: IntoIterator<Item = (Span, String)>,
+    {
+        let mut edits: Vec<(Span, String)> = edits.into_iter().collect();
+        edits.sort_by_key(|(span, _)| (span.start, span.end));
+
+        let mut spliced = String::with_capacity(self.source.len());
+        let mut pos = 0;
+        for (span, text) in &edits {
+            if span.start < pos {
+                return Err(TemplateError::Template(format!(
+                    "Edit at line {}, column {} overlaps a previous edit", span.line, span.column
+                )));
+            }
+            spliced.push_str(&self.source[pos..span.start]);
+            spliced.push_str(text);
+            pos = span.end;
+        }
+        spliced.push_str(&self.source[pos..]);
+        Ok(spliced)
+    }
+}
+
+/// A node of the syntax tree
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    /// Literal text
+    Text(Text),
+    /// `{{! comment }}` or `{{#-- visible comment --}}`
+    Comment(Comment),
+    /// `{{path|filter:arg}}` or `{{& path}}`
+    Variable(Variable),
+    /// `{{if condition}}...{{elseif condition}}...{{else}}...{{/if}}`, the
+    /// `{{elseif}}` (or `{{else if}}`) and `{{else}}` branches optional
+    If(If),
+    /// `{{unless condition}}...{{/unless}}`
+    Unless(Unless),
+    /// `{{ifchanged expression}}...{{/ifchanged}}`
+    IfChanged(IfChanged),
+    /// `{{for item in items}}...{{empty}}...{{/for}}`
+    For(For),
+    /// `{{include "name"}}`, `{{include var}}` or `{{include "name" from "root"}}`
+    Include(Include),
+    /// `{{extends "layout.html"}}` or `{{extends var}}`
+    Extends(Extends),
+    /// `{{block name param=path}}...{{/block}}`
+    Block(Block),
+    /// `{{macro name(param, other="default")}}...{{/macro}}`
+    MacroDef(MacroDef),
+    /// `{{name(arg, other=value)}}`: a macro or helper call
+    MacroCall(MacroCall),
+    /// `{{t "key" name=value}}`
+    Translation(Translation),
+    /// `{{plural count "singular" "plural"}}`
+    Plural(Plural),
+    /// `{{t_scope "prefix"}}...{{/t_scope}}`
+    TranslationScope(TranslationScope),
+    /// `{{try}}...{{rescue}}...{{/try}}`
+    Try(Try),
+    /// `{{capture name}}...{{/capture}}`
+    Capture(Capture),
+    /// Any other directive with arguments: `{{needs "x"}}`, `{{table rows columns="..."}}`, ...
+    Directive(Directive),
+}
+
+impl Node {
+    /// Source span of the node, closing tag included for blocks
+    pub fn span(&self) -> Span {
+        match self {
+            Node::Text(node) => node.span,
+            Node::Comment(node) => node.span,
+            Node::Variable(node) => node.span,
+            Node::If(node) => node.span,
+            Node::Unless(node) => node.span,
+            Node::IfChanged(node) => node.span,
+            Node::For(node) => node.span,
+            Node::Include(node) => node.span,
+            Node::Extends(node) => node.span,
+            Node::Block(node) => node.span,
+            Node::MacroDef(node) => node.span,
+            Node::MacroCall(node) => node.span,
+            Node::Translation(node) => node.span,
+            Node::Plural(node) => node.span,
+            Node::TranslationScope(node) => node.span,
+            Node::Try(node) => node.span,
+            Node::Capture(node) => node.span,
+            Node::Directive(node) => node.span,
+        }
+    }
+}
+
+/// Literal text
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Text {
+    pub text: String,
+    pub span: Span,
+}
+
+/// A template comment
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    /// Comment text, trimmed
+    pub text: String,
+    /// `{{#-- --}}`, rendered as an HTML comment
+    pub visible: bool,
+    pub span: Span,
+}
+
+/// A variable or expression output
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    /// The expression before the filters, usually a `user.name` path
+    pub path: String,
+    /// Filters, in application order
+    pub filters: Vec<Filter>,
+    /// `{{& path}}`: output without HTML escaping
+    pub raw: bool,
+    pub span: Span,
+}
+
+/// A filter step of a [`Variable`]
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    pub name: String,
+    /// Arguments as written, quotes kept
+    pub arguments: Vec<String>,
+}
+
+/// A conditional
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct If {
+    /// Branches in order: the `{{if}}` branch, its `{{elseif}}` branches,
+    /// then the `{{else}}` branch if any
+    pub branches: Vec<Branch>,
+    pub span: Span,
+}
+
+/// A branch of an [`If`]
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Branch {
+    /// Condition as written, `None` for an `else` branch
+    pub condition: Option<String>,
+    pub body: Vec<Node>,
+    /// Opening tag and body
+    pub span: Span,
+}
+
+/// A negated conditional
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unless {
+    /// Condition as written; the body renders when it does not hold
+    pub condition: String,
+    pub body: Vec<Node>,
+    pub span: Span,
+}
+
+/// A block rendered when a value differs from the previous loop iteration
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IfChanged {
+    /// Compared expression, `txn.date`
+    pub expr: String,
+    pub body: Vec<Node>,
+    pub span: Span,
+}
+
+/// A loop
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct For {
+    /// Loop variable, `item`
+    pub var: String,
+    /// Iterated expression, `items`
+    pub expr: String,
+    pub body: Vec<Node>,
+    /// Nodes after `{{empty}}`, rendered when there is nothing to iterate
+    pub empty: Option<Vec<Node>>,
+    pub span: Span,
+}
+
+/// A template inclusion
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Include {
+    /// Template name, or the variable naming it when `dynamic`
+    pub target: String,
+    /// `{{include var}}`: the name is read from the context
+    pub dynamic: bool,
+    /// Root of `{{include "name" from "root"}}`
+    pub from: Option<String>,
+    /// `with` arguments, `(title, product.name)` for `with title=product.name`
+    pub arguments: Vec<(String, String)>,
+    /// `only`: the template sees its arguments and nothing else
+    pub only: bool,
+    pub span: Span,
+}
+
+/// A layout inheritance declaration
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extends {
+    /// Parent template name, or the variable naming it when `dynamic`
+    pub target: String,
+    /// `{{extends var}}`: the name is read from the context
+    pub dynamic: bool,
+    pub span: Span,
+}
+
+/// A layout block
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub name: String,
+    /// Block parameters, `(title, page.title)` for `title=page.title`
+    pub parameters: Vec<(String, String)>,
+    pub body: Vec<Node>,
+    pub span: Span,
+}
+
+/// A macro definition
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroDef {
+    pub name: String,
+    pub parameters: Vec<MacroParameter>,
+    pub body: Vec<Node>,
+    pub span: Span,
+}
+
+/// A parameter of a [`MacroDef`]
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroParameter {
+    pub name: String,
+    /// Default value as written, quotes kept
+    pub default: Option<String>,
+}
+
+/// A macro or helper call
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacroCall {
+    pub name: String,
+    pub arguments: Vec<Argument>,
+    pub span: Span,
+}
+
+/// An argument of a [`MacroCall`]
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Argument {
+    /// Parameter name of a `name=value` argument
+    pub name: Option<String>,
+    /// Value as written, quotes kept
+    pub value: String,
+}
+
+/// A translation lookup
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Translation {
+    /// Key, without quotes
+    pub key: String,
+    /// Named arguments, `(name, user.name)` for `name=user.name`
+    pub arguments: Vec<(String, String)>,
+    pub span: Span,
+}
+
+/// A plural form choice
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plural {
+    /// Count expression
+    pub count: String,
+    /// Singular form, without quotes
+    pub singular: String,
+    /// Plural form, without quotes
+    pub plural: String,
+    pub span: Span,
+}
+
+/// A translation key prefix for the enclosed lookups
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranslationScope {
+    /// Prefix, without quotes
+    pub prefix: String,
+    pub body: Vec<Node>,
+    pub span: Span,
+}
+
+/// An error boundary
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Try {
+    pub body: Vec<Node>,
+    /// Rendered instead of the body when it fails, `None` without `{{rescue}}`
+    pub rescue: Option<Vec<Node>>,
+    pub span: Span,
+}
+
+/// A section rendered into a variable instead of the output
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capture {
+    /// Name of the variable holding the rendered body
+    pub name: String,
+    pub body: Vec<Node>,
+    pub span: Span,
+}
+
+/// A directive without a dedicated node
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Directive {
+    /// `needs`, `required_assets`, `table`, ...
+    pub keyword: String,
+    /// Whitespace separated arguments, quotes kept; for `set`, the name and
+    /// the value expression
+    pub arguments: Vec<String>,
+    pub span: Span,
+}
+
+/// Visits the nodes of a [`Template`], see [`walk`]
+///
+/// Every method has a default: leaves do nothing and nodes with children
+/// walk them, so a visitor only overrides what it looks at. An override
+/// that still wants the children visited calls the matching `walk_*`
+/// function.
+///
+/// # Example
+/// ```rust
+/// use mystical_runic::ast::{self, Visitor, Include};
+///
+/// #[derive(Default)]
+/// struct Includes(Vec<String>);
+///
+/// impl Visitor for Includes {
+///     fn visit_include(&mut self, include: &Include) {
+///         self.0.push(include.target.clone());
+///     }
+/// }
+///
+/// let template = ast::parse("{{if user}}{{include \"nav.html\"}}{{/if}}{{include \"footer.html\"}}")?;
+/// let mut includes = Includes::default();
+/// ast::walk(&template, &mut includes);
+/// assert_eq!(includes.0, ["nav.html", "footer.html"]);
+/// # Ok::<(), mystical_runic::TemplateError>(())
+/// ```
+pub trait Visitor {
+    /// Any node; dispatches to the method of its kind
+    fn visit_node(&mut self, node: &Node) {
+        walk_node(self, node);
+    }
+    fn visit_text(&mut self, _text: &Text) {}
+    fn visit_comment(&mut self, _comment: &Comment) {}
+    fn visit_variable(&mut self, _variable: &Variable) {}
+    fn visit_if(&mut self, node: &If) {
+        walk_if(self, node);
+    }
+    fn visit_unless(&mut self, node: &Unless) {
+        walk_nodes(self, &node.body);
+    }
+    fn visit_ifchanged(&mut self, node: &IfChanged) {
+        walk_nodes(self, &node.body);
+    }
+    fn visit_for(&mut self, node: &For) {
+        walk_for(self, node);
+    }
+    fn visit_include(&mut self, _include: &Include) {}
+    fn visit_extends(&mut self, _extends: &Extends) {}
+    fn visit_block(&mut self, block: &Block) {
+        walk_nodes(self, &block.body);
+    }
+    fn visit_macro_def(&mut self, definition: &MacroDef) {
+        walk_nodes(self, &definition.body);
+    }
+    fn visit_macro_call(&mut self, _call: &MacroCall) {}
+    fn visit_translation(&mut self, _translation: &Translation) {}
+    fn visit_plural(&mut self, _plural: &Plural) {}
+    fn visit_translation_scope(&mut self, scope: &TranslationScope) {
+        walk_nodes(self, &scope.body);
+    }
+    fn visit_try(&mut self, node: &Try) {
+        walk_try(self, node);
+    }
+    fn visit_capture(&mut self, capture: &Capture) {
+        walk_nodes(self, &capture.body);
+    }
+    fn visit_directive(&mut self, _directive: &Directive) {}
+}
+
+/// Visit every top-level node of `template`, and through the visitor
+/// defaults the nodes nested in them
+pub fn walk<V: Visitor + ?Sized>(template: &Template, visitor: &mut V) {
+    walk_nodes(visitor, &template.nodes);
+}
+
+/// Visit `nodes` in order
+pub fn walk_nodes<V: Visitor + ?Sized>(visitor: &mut V, nodes: &[Node]) {
+    for node in nodes {
+        visitor.visit_node(node);
+    }
+}
+
+/// Call the visitor method of the node's kind
+pub fn walk_node<V: Visitor + ?Sized>(visitor: &mut V, node: &Node) {
+    match node {
+        Node::Text(text) => visitor.visit_text(text),
+        Node::Comment(comment) => visitor.visit_comment(comment),
+        Node::Variable(variable) => visitor.visit_variable(variable),
+        Node::If(node) => visitor.visit_if(node),
+        Node::Unless(node) => visitor.visit_unless(node),
+        Node::IfChanged(node) => visitor.visit_ifchanged(node),
+        Node::For(node) => visitor.visit_for(node),
+        Node::Include(include) => visitor.visit_include(include),
+        Node::Extends(extends) => visitor.visit_extends(extends),
+        Node::Block(block) => visitor.visit_block(block),
+        Node::MacroDef(definition) => visitor.visit_macro_def(definition),
+        Node::MacroCall(call) => visitor.visit_macro_call(call),
+        Node::Translation(translation) => visitor.visit_translation(translation),
+        Node::Plural(plural) => visitor.visit_plural(plural),
+        Node::TranslationScope(scope) => visitor.visit_translation_scope(scope),
+        Node::Try(node) => visitor.visit_try(node),
+        Node::Capture(capture) => visitor.visit_capture(capture),
+        Node::Directive(directive) => visitor.visit_directive(directive),
+    }
+}
+
+/// Visit the bodies of every branch
+pub fn walk_if<V: Visitor + ?Sized>(visitor: &mut V, node: &If) {
+    for branch in &node.branches {
+        walk_nodes(visitor, &branch.body);
+    }
+}
+
+/// Visit the loop body, then the empty branch
+pub fn walk_for<V: Visitor + ?Sized>(visitor: &mut V, node: &For) {
+    walk_nodes(visitor, &node.body);
+    if let Some(empty) = &node.empty {
+        walk_nodes(visitor, empty);
+    }
+}
+
+/// Visit the try body, then the rescue body
+pub fn walk_try<V: Visitor + ?Sized>(visitor: &mut V, node: &Try) {
+    walk_nodes(visitor, &node.body);
+    if let Some(rescue) = &node.rescue {
+        walk_nodes(visitor, rescue);
+    }
+}
+
+/// Parse a template string
+///
+/// Unbalanced tags and blocks and malformed directives fail with
+/// [`TemplateError::ParseWithLocation`].
+pub fn parse(source: &str) -> TemplateResult<Template> {
+    parse_named(None, source)
+}
+
+/// Parse `source`, naming the template in the tree and in errors
+pub(crate) fn parse_named(name: Option<&str>, source: &str) -> TemplateResult<Template> {
+    let mut parser = Parser { name, source, pos: 0 };
+    let (nodes, _) = parser.parse_nodes(None)?;
+    Ok(Template { name: name.map(str::to_string), source: source.to_string(), nodes })
+}
+
+struct Parser<'a> {
+    name: Option<&'a str>,
+    source: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    /// Parse nodes up to the closing tag of `open` (keyword and offset of
+    /// the opening tag), or to the end of the source. Returns the nodes and
+    /// where the closing tag starts; a `{{try}}` body also ends at `{{rescue}}`,
+    /// an `{{if}}` body at `{{elseif}}` or `{{else}}` and a `{{for}}` body at
+    /// `{{empty}}`.
+    fn parse_nodes(&mut self, open: Option<(&str, usize)>) -> TemplateResult<(Vec<Node>, usize)> {
+        let mut nodes = Vec::new();
+        loop {
+            let Some(relative_start) = self.source[self.pos..].find("{{") else {
+                self.push_text(&mut nodes, self.source.len());
+                if let Some((keyword, offset)) = open {
+                    return Err(self.error(offset, format!("Unclosed '{{{{{}}}}}': missing '{{{{/{}}}}}'", keyword, keyword)));
+                }
+                return Ok((nodes, self.source.len()));
+            };
+            let start = self.pos + relative_start;
+            self.push_text(&mut nodes, start);
+            let rest = &self.source[start..];
+
+            // Visible comments may contain `}}`, so they close on `--}}`
+            if rest.starts_with("{{#--") || rest.starts_with("{{-#--") {
+                let opener = if rest.starts_with("{{-") { 6 } else { 5 };
+                let close = opener + rest[opener..].find("--}}")
+                    .ok_or_else(|| self.error(start, "Unclosed visible comment".to_string()))?;
+                self.pos = start + close + 4;
+                let span = self.span(start, self.pos);
+                let (_, inner, _) = split_trim_markers(&rest[2..close + 2]);
+                let text = inner.get(3..inner.len().saturating_sub(2)).unwrap_or_default();
+                nodes.push(Node::Comment(Comment { text: text.trim().to_string(), visible: true, span }));
+                continue;
+            }
+
+            let close = rest.find("}}")
+                .ok_or_else(|| self.error(start, "Unclosed tag: missing '}}'".to_string()))?;
+            self.pos = start + close + 2;
+            let tag = &rest[..close + 2];
+            let directive = directive_at(self.source, start);
+            let span = self.span(start, self.pos);
+
+            if directive.is_empty() {
+                return Err(self.error(start, "Empty tag '{{}}'".to_string()));
+            }
+            if let Some(text) = directive.strip_prefix('!') {
+                nodes.push(Node::Comment(Comment { text: text.trim().to_string(), visible: false, span }));
+                continue;
+            }
+            if let Some(closing) = directive.strip_prefix('/') {
+                // `{{/block name}}` closes a block too
+                let closing = closing.split_whitespace().next().unwrap_or_default();
+                return match open {
+                    Some((keyword, _)) if keyword == closing => Ok((nodes, start)),
+                    Some((keyword, offset)) => {
+                        let (line, column) = find_line_column(self.source, offset);
+                        Err(self.error(start, format!(
+                            "'{}' does not match '{{{{{}}}}}' opened at line {}, column {}", tag, keyword, line, column
+                        )))
+                    }
+                    None => Err(self.error(start, format!("Unexpected closing tag '{}'", tag))),
+                };
+            }
+
+            if directive == "rescue" {
+                return match open {
+                    Some(("try", _)) => Ok((nodes, start)),
+                    _ => Err(self.error(start, format!("Unexpected '{}' outside '{{{{try}}}}'", tag))),
+                };
+            }
+            // Outside a loop `{{empty}}` stays a variable
+            if directive == "empty" && matches!(open, Some(("for", _))) {
+                return Ok((nodes, start));
+            }
+            if else_condition(directive).is_some() {
+                return match open {
+                    Some(("if", _)) => Ok((nodes, start)),
+                    _ => Err(self.error(start, format!("Unexpected '{}' outside '{{{{if}}}}'", tag))),
+                };
+            }
+
+            let (keyword, arguments) = match directive.split_once(char::is_whitespace) {
+                Some((keyword, arguments)) => (keyword, arguments.trim()),
+                None => (directive, ""),
+            };
+            let node = match keyword {
+                "try" if arguments.is_empty() => {
+                    let (body, body_end) = self.parse_nodes(Some(("try", start)))?;
+                    let rescue = if directive_at(self.source, body_end) == "rescue" {
+                        let (rescue, rescue_end) = self.parse_nodes(Some(("try", start)))?;
+                        if directive_at(self.source, rescue_end) == "rescue" {
+                            return Err(self.error(rescue_end, "Duplicate '{{rescue}}' in '{{try}}'".to_string()));
+                        }
+                        Some(rescue)
+                    } else {
+                        None
+                    };
+                    Node::Try(Try { body, rescue, span: self.span(start, self.pos) })
+                }
+                "if" if !arguments.is_empty() => {
+                    let (body, mut body_end) = self.parse_nodes(Some(("if", start)))?;
+                    let mut branches = vec![Branch { condition: Some(arguments.to_string()), body, span: self.span(start, body_end) }];
+                    // A branch tag ended the body unless the closing tag did
+                    while !directive_at(self.source, body_end).starts_with('/') {
+                        let tag_end = self.pos;
+                        let condition = else_condition(directive_at(self.source, body_end)).flatten().map(str::to_string);
+                        if branches.last().is_some_and(|branch| branch.condition.is_none()) {
+                            let message = match condition {
+                                Some(_) => format!("Unexpected '{}' after '{{{{else}}}}'", &self.source[body_end..tag_end]),
+                                None => "Duplicate '{{else}}' in '{{if}}'".to_string(),
+                            };
+                            return Err(self.error(body_end, message));
+                        }
+                        let (body, branch_end) = self.parse_nodes(Some(("if", start)))?;
+                        branches.push(Branch { condition, body, span: self.span(body_end, branch_end) });
+                        body_end = branch_end;
+                    }
+                    Node::If(If { branches, span: self.span(start, self.pos) })
+                }
+                "unless" if !arguments.is_empty() => {
+                    let (body, _) = self.parse_nodes(Some(("unless", start)))?;
+                    Node::Unless(Unless { condition: arguments.to_string(), body, span: self.span(start, self.pos) })
+                }
+                "ifchanged" if !arguments.is_empty() => {
+                    let (body, _) = self.parse_nodes(Some(("ifchanged", start)))?;
+                    Node::IfChanged(IfChanged { expr: arguments.to_string(), body, span: self.span(start, self.pos) })
+                }
+                "for" => {
+                    let (var, expr) = arguments.split_once(" in ")
+                        .map(|(var, expr)| (var.trim(), expr.trim()))
+                        .filter(|(var, expr)| is_identifier(var) && !expr.is_empty())
+                        .ok_or_else(|| self.error(start, format!("Malformed '{}': expected '{{{{for item in items}}}}'", tag)))?;
+                    let (body, body_end) = self.parse_nodes(Some(("for", start)))?;
+                    let empty = if directive_at(self.source, body_end) == "empty" {
+                        let (empty, empty_end) = self.parse_nodes(Some(("for", start)))?;
+                        if directive_at(self.source, empty_end) == "empty" {
+                            return Err(self.error(empty_end, "Duplicate '{{empty}}' in '{{for}}'".to_string()));
+                        }
+                        Some(empty)
+                    } else {
+                        None
+                    };
+                    Node::For(For { var: var.to_string(), expr: expr.to_string(), body, empty, span: self.span(start, self.pos) })
+                }
+                "macro" => {
+                    let (name, parameters) = arguments.split_once('(')
+                        .and_then(|(name, rest)| Some((name.trim(), rest.strip_suffix(')')?)))
+                        .filter(|(name, _)| is_identifier(name))
+                        .ok_or_else(|| self.error(start, format!("Malformed '{}': expected '{{{{macro name(params)}}}}'", tag)))?;
+                    let parameters = split_unquoted(parameters, ',').into_iter()
+                        .map(str::trim)
+                        .filter(|parameter| !parameter.is_empty())
+                        .map(|parameter| match parameter.split_once('=') {
+                            Some((name, default)) => MacroParameter { name: name.trim().to_string(), default: Some(default.trim().to_string()) },
+                            None => MacroParameter { name: parameter.to_string(), default: None },
+                        })
+                        .collect();
+                    let (body, _) = self.parse_nodes(Some(("macro", start)))?;
+                    Node::MacroDef(MacroDef { name: name.to_string(), parameters, body, span: self.span(start, self.pos) })
+                }
+                "block" if !arguments.is_empty() => {
+                    let mut parts = split_directive_arguments(arguments).into_iter();
+                    let name = parts.next().unwrap_or_default().to_string();
+                    let parameters = parts
+                        .map(|parameter| parameter.split_once('=')
+                            .map(|(name, path)| (name.to_string(), path.to_string()))
+                            .ok_or_else(|| self.error(start, format!("Malformed '{}': expected '{{{{block name param=path}}}}'", tag))))
+                        .collect::<TemplateResult<_>>()?;
+                    let (body, _) = self.parse_nodes(Some(("block", start)))?;
+                    Node::Block(Block { name, parameters, body, span: self.span(start, self.pos) })
+                }
+                "t_scope" if arguments.starts_with(['"', '\'']) => {
+                    let prefix = unquote(split_directive_arguments(arguments)[0]);
+                    let (body, _) = self.parse_nodes(Some(("t_scope", start)))?;
+                    Node::TranslationScope(TranslationScope { prefix, body, span: self.span(start, self.pos) })
+                }
+                "include" | "extends" if !arguments.is_empty() => {
+                    let (target, from) = split_include_source(arguments);
+                    let dynamic = !target.starts_with(['"', '\'']);
+                    let target = unquote(target);
+                    if keyword == "include" {
+                        let (_, with, only) = split_include_scope(arguments);
+                        let arguments = with.map(|with| split_directive_arguments(&arguments[with]))
+                            .unwrap_or_default()
+                            .into_iter()
+                            .filter_map(|argument| argument.split_once('='))
+                            .map(|(name, value)| (name.to_string(), value.to_string()))
+                            .collect();
+                        Node::Include(Include { target, dynamic, from: from.map(str::to_string), arguments, only, span })
+                    } else {
+                        Node::Extends(Extends { target, dynamic, span })
+                    }
+                }
+                "t" if arguments.starts_with(['"', '\'']) => {
+                    let mut parts = split_directive_arguments(arguments).into_iter();
+                    let key = parts.next().map(unquote).unwrap_or_default();
+                    let arguments = parts
+                        .filter_map(|argument| argument.split_once('='))
+                        .map(|(name, value)| (name.to_string(), value.to_string()))
+                        .collect();
+                    Node::Translation(Translation { key, arguments, span })
+                }
+                "plural" if !arguments.is_empty() => {
+                    match split_directive_arguments(arguments)[..] {
+                        [count, singular, plural] => Node::Plural(Plural {
+                            count: count.to_string(),
+                            singular: unquote(singular),
+                            plural: unquote(plural),
+                            span,
+                        }),
+                        _ => return Err(self.error(start, format!("Malformed '{}': expected '{{{{plural count \"singular\" \"plural\"}}}}'", tag))),
+                    }
+                }
+                "capture" if !arguments.is_empty() => {
+                    if !is_identifier(arguments) {
+                        return Err(self.error(start, format!("Malformed '{}': expected '{{{{capture name}}}}'", tag)));
+                    }
+                    let (body, _) = self.parse_nodes(Some(("capture", start)))?;
+                    Node::Capture(Capture { name: arguments.to_string(), body, span: self.span(start, self.pos) })
+                }
+                "set" => {
+                    let (name, value) = split_assignment(arguments)
+                        .ok_or_else(|| self.error(start, format!("Malformed '{}': expected '{{{{set name = value}}}}'", tag)))?;
+                    Node::Directive(Directive { keyword: keyword.to_string(), arguments: vec![name.to_string(), value.to_string()], span })
+                }
+                "needs" | "required_assets" | "include_raw" | "table" | "props" | "slots" | "has_slot"
+                | "input" | "select" | "checkbox" | "field_errors" | "assert" if !arguments.is_empty() => Node::Directive(Directive {
+                    keyword: keyword.to_string(),
+                    arguments: split_directive_arguments(arguments).into_iter().map(str::to_string).collect(),
+                    span,
+                }),
+                _ => self.parse_output(directive, span),
+            };
+            nodes.push(node);
+        }
+    }
+
+    /// A variable output or a macro/helper call
+    fn parse_output(&self, directive: &str, span: Span) -> Node {
+        let (expression, raw) = match directive.strip_prefix('&') {
+            Some(expression) => (expression.trim(), true),
+            None => (directive, false),
+        };
+
+        if !raw {
+            let call = expression.split_once('(')
+                .and_then(|(name, rest)| Some((name.trim(), rest.strip_suffix(')')?)))
+                .filter(|(name, _)| is_variable_path(name));
+            if let Some((name, arguments)) = call {
+                let arguments = split_unquoted(arguments, ',').into_iter()
+                    .map(str::trim)
+                    .filter(|argument| !argument.is_empty())
+                    .map(|argument| match split_named_argument(argument) {
+                        Some((name, value)) => Argument { name: Some(name.to_string()), value: value.to_string() },
+                        None => Argument { name: None, value: argument.to_string() },
+                    })
+                    .collect();
+                return Node::MacroCall(MacroCall { name: name.to_string(), arguments, span });
+            }
+        }
+
+        let mut parts = split_unquoted(expression, '|').into_iter();
+        let path = parts.next().unwrap_or_default().trim().to_string();
+        let filters = parts
+            .map(|filter| {
+                let (name, arguments) = split_filter_arguments(filter.trim());
+                Filter { name: name.to_string(), arguments: arguments.into_iter().map(str::to_string).collect() }
+            })
+            .collect();
+        Node::Variable(Variable { path, filters, raw, span })
+    }
+
+    fn push_text(&mut self, nodes: &mut Vec<Node>, end: usize) {
+        if end > self.pos {
+            let span = self.span(self.pos, end);
+            nodes.push(Node::Text(Text { text: self.source[self.pos..end].to_string(), span }));
+        }
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        let (line, column) = find_line_column(self.source, start);
+        Span { start, end, line, column }
+    }
+
+    fn error(&self, offset: usize, message: String) -> TemplateError {
+        let (line, column) = find_line_column(self.source, offset);
+        TemplateError::ParseWithLocation {
+            message,
+            line,
+            column,
+            template_name: self.name.map(str::to_string),
+            context_lines: extract_context_lines(self.source, line, 2),
+        }
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    !name.contains('.') && is_variable_path(name)
+}
+
+/// Argument without its quotes
+fn unquote(argument: &str) -> String {
+    argument.trim_matches('"').trim_matches('\'').to_string()
+}
+
+/// Directive of the tag starting at `offset`, without its braces and
+/// whitespace control markers
+fn directive_at(source: &str, offset: usize) -> &str {
+    let rest = &source[offset..];
+    let close = rest.find("}}").unwrap_or(rest.len());
+    split_trim_markers(&rest[2..close]).1.trim()
+}
@@ -3,13 +3,21 @@
 //! Provides non-blocking template rendering capabilities for high-performance web applications
 
 #[cfg(feature = "async")]
-use crate::{TemplateEngine, TemplateContext, TemplateResult, TemplateError};
+use crate::{CancellationToken, TemplateEngine, TemplateContext, TemplateResult, TemplateError, WarmupReport};
 #[cfg(feature = "async")]
 use std::path::Path;
 #[cfg(feature = "async")]
 use tokio::fs;
 #[cfg(feature = "async")]
 use futures::future::BoxFuture;
+#[cfg(feature = "async")]
+use futures::stream::{self, BoxStream, StreamExt};
+#[cfg(feature = "async")]
+use bytes::Bytes;
+#[cfg(feature = "async")]
+use std::io;
+#[cfg(feature = "async")]
+use tokio::sync::mpsc;
 
 #[cfg(feature = "async")]
 /// Async extension trait for TemplateEngine
@@ -23,10 +31,57 @@ pub trait AsyncTemplateEngine {
     /// Load a template file asynchronously
     fn load_template_async<'a>(&'a self, template_name: &'a str) -> BoxFuture<'a, TemplateResult<String>>;
     
+    /// Warm up all template caches asynchronously
+    fn warm_up_async(&mut self) -> BoxFuture<'_, TemplateResult<WarmupReport>>;
+    
+    /// Render a cached template asynchronously without touching the filesystem
+    /// (see [`TemplateEngine::render_offline`])
+    fn render_offline_async<'a>(&'a mut self, template_name: &'a str, context: &'a TemplateContext) -> BoxFuture<'a, TemplateResult<String>>;
+    
+    /// Render a template asynchronously, stopping early when `token` is
+    /// cancelled (see [`TemplateEngine::render_with_cancel`])
+    ///
+    /// The render itself runs in a single poll after a yield: dropping the
+    /// future before that poll means it never starts, and cancelling the
+    /// token stops one that is running.
+    fn render_with_cancel_async<'a>(&'a mut self, template_name: &'a str, context: &'a TemplateContext, token: &'a CancellationToken) -> BoxFuture<'a, TemplateResult<String>>;
+    
+    /// Render a template file as a stream of chunks that are yielded as
+    /// soon as they are rendered: one per top-level loop iteration, plus what
+    /// comes before and after (see [`TemplateEngine::render_to_writer`])
+    ///
+    /// The render runs on a blocking thread with a clone of the engine, at
+    /// most [`set_stream_buffer_size`](TemplateEngine::set_stream_buffer_size)
+    /// chunks ahead of the consumer. A failing render ends the stream with
+    /// its error, and dropping the stream stops the render. Templates the
+    /// render loads are cached in the clone only; warm up the engine first
+    /// to share them.
+    fn render_stream<'a>(&'a mut self, template_name: &'a str, context: &'a TemplateContext) -> BoxFuture<'a, BoxStream<'static, TemplateResult<Bytes>>>;
+    
     /// Clone the engine for concurrent use
     fn clone(&self) -> Self;
 }
 
+/// Writer sending each write as a chunk of `render_stream`, waiting while
+/// the channel is full
+#[cfg(feature = "async")]
+struct ChunkSender(mpsc::Sender<TemplateResult<Bytes>>);
+
+#[cfg(feature = "async")]
+impl io::Write for ChunkSender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !buf.is_empty() {
+            self.0.blocking_send(Ok(Bytes::copy_from_slice(buf)))
+                .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "render stream was dropped"))?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 #[cfg(feature = "async")]
 impl AsyncTemplateEngine for TemplateEngine {
     fn render_string_async<'a>(&'a mut self, template: &'a str, context: &'a TemplateContext) -> BoxFuture<'a, TemplateResult<String>> {
@@ -52,6 +107,7 @@ impl AsyncTemplateEngine for TemplateEngine {
         let template_path = Path::new(self.get_template_dir()).join(template_name);
         
         Box::pin(async move {
+            self.validate_template_path(template_name)?;
             match fs::read_to_string(&template_path).await {
                 Ok(content) => Ok(content),
                 Err(e) => Err(TemplateError::Io(e)),
@@ -59,6 +115,47 @@ impl AsyncTemplateEngine for TemplateEngine {
         })
     }
 
+    fn warm_up_async(&mut self) -> BoxFuture<'_, TemplateResult<WarmupReport>> {
+        Box::pin(async move {
+            tokio::task::yield_now().await; // Yield to allow other tasks
+            self.warm_up()
+        })
+    }
+
+    fn render_offline_async<'a>(&'a mut self, template_name: &'a str, context: &'a TemplateContext) -> BoxFuture<'a, TemplateResult<String>> {
+        Box::pin(async move {
+            tokio::task::yield_now().await; // Yield to allow other tasks
+            self.render_offline(template_name, context)
+        })
+    }
+
+    fn render_with_cancel_async<'a>(&'a mut self, template_name: &'a str, context: &'a TemplateContext, token: &'a CancellationToken) -> BoxFuture<'a, TemplateResult<String>> {
+        Box::pin(async move {
+            tokio::task::yield_now().await; // Yield to allow other tasks
+            self.render_with_cancel(template_name, context, token)
+        })
+    }
+
+    fn render_stream<'a>(&'a mut self, template_name: &'a str, context: &'a TemplateContext) -> BoxFuture<'a, BoxStream<'static, TemplateResult<Bytes>>> {
+        Box::pin(async move {
+            tokio::task::yield_now().await; // Yield to allow other tasks
+            let (sender, receiver) = mpsc::channel(self.stream_buffer_size());
+            let mut engine = Clone::clone(&*self);
+            let context = context.clone();
+            let template_name = template_name.to_string();
+            tokio::task::spawn_blocking(move || {
+                let mut chunks = ChunkSender(sender);
+                if let Err(error) = engine.render_to_writer(&template_name, &context, &mut chunks) {
+                    // Fails only when the stream is gone, and nobody is left to tell
+                    let _ = chunks.0.blocking_send(Err(error));
+                }
+            });
+            stream::unfold(receiver, |mut receiver| async move {
+                receiver.recv().await.map(|chunk| (chunk, receiver))
+            }).boxed()
+        })
+    }
+
     fn clone(&self) -> Self {
         // Use the existing Clone implementation from the struct
         Clone::clone(self)
@@ -0,0 +1,129 @@
+//! Raw output auditing for v0.6.0
+//!
+//! Lists every place a template writes unescaped output so each one can be
+//! justified during a security review.
+
+use crate::suggestions::find_line_column;
+
+/// Filters whose output is inserted without HTML escaping
+pub(crate) const HTML_PRODUCING_FILTERS: [&str; 4] = ["markdown", "highlight", "sanitize", "truncate_html"];
+
+/// HTML-producing filters whose output is safe to insert unescaped
+const SANITIZING_FILTERS: [&str; 1] = ["sanitize"];
+
+/// Filters that mark a value as already safe HTML
+const SAFE_FILTERS: [&str; 1] = ["safe"];
+
+/// Filters that escape the value whatever came before them
+pub(crate) const ESCAPE_FILTERS: [&str; 2] = ["escape", "e"];
+
+/// Expression roots that usually carry data supplied by end users
+const USER_CONTROLLED_ROOTS: [&str; 6] = ["user", "input", "params", "request", "query", "form"];
+
+/// Why a site produces unescaped output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawOutputReason {
+    /// `{{& expression}}`
+    RawTag,
+    /// `|safe`
+    SafeFilter,
+    /// A filter that produces HTML, such as `|markdown`
+    HtmlFilter(String),
+}
+
+impl std::fmt::Display for RawOutputReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RawOutputReason::RawTag => write!(f, "raw tag"),
+            RawOutputReason::SafeFilter => write!(f, "safe filter"),
+            RawOutputReason::HtmlFilter(name) => write!(f, "html-producing filter '{}'", name),
+        }
+    }
+}
+
+/// One unescaped output site
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawOutputSite {
+    /// Template the site was found in
+    pub template_name: String,
+    /// Line of the directive (1-based)
+    pub line: usize,
+    /// Column of the directive (1-based)
+    pub column: usize,
+    /// Expression written raw, filters included
+    pub expression: String,
+    /// Why the output is not escaped
+    pub reason: RawOutputReason,
+    /// The expression root looks like end-user input (`user.`, `input.`, `params.`, ...)
+    pub likely_user_controlled: bool,
+}
+
+/// Find the raw output sites of one template source; `html_filters` are
+/// the custom filters registered as producing HTML
+pub(crate) fn scan_raw_output(template_name: &str, content: &str, html_filters: &[&str]) -> Vec<RawOutputSite> {
+    let mut sites = Vec::new();
+    let mut pos = 0;
+
+    while let Some(relative_start) = content[pos..].find("{{") {
+        let start = pos + relative_start;
+        let Some(end) = content[start..].find("}}") else { break };
+        pos = start + end + 2;
+
+        let directive = content[start + 2..start + end].trim();
+        // Comments never render, and block directives carry no output expression
+        if directive.starts_with('!') || directive.starts_with("#--") || directive.starts_with('/') {
+            continue;
+        }
+
+        let (expression, raw_tag) = match directive.strip_prefix('&') {
+            Some(expression) => (expression.trim(), true),
+            None => (directive, false),
+        };
+        let mut reasons = Vec::new();
+        if raw_tag {
+            reasons.push(RawOutputReason::RawTag);
+        }
+        let mut filters: Vec<&str> = expression.split('|').skip(1)
+            .map(|filter| filter.split(':').next().unwrap_or_default().trim())
+            .collect();
+        // An escape filter undoes everything before it, the raw tag included
+        if let Some(last_escape) = filters.iter().rposition(|name| ESCAPE_FILTERS.contains(name)) {
+            reasons.clear();
+            filters.drain(..=last_escape);
+        }
+        // Output that goes through the sanitizer last needs no justification
+        if filters.last().is_some_and(|name| SANITIZING_FILTERS.contains(name)) {
+            continue;
+        }
+        for name in filters {
+            if SAFE_FILTERS.contains(&name) {
+                reasons.push(RawOutputReason::SafeFilter);
+            } else if HTML_PRODUCING_FILTERS.contains(&name) || html_filters.contains(&name) {
+                reasons.push(RawOutputReason::HtmlFilter(name.to_string()));
+            }
+        }
+
+        if reasons.is_empty() {
+            continue;
+        }
+        let (line, column) = find_line_column(content, start);
+        let likely_user_controlled = is_user_controlled(expression);
+        sites.extend(reasons.into_iter().map(|reason| RawOutputSite {
+            template_name: template_name.to_string(),
+            line,
+            column,
+            expression: expression.to_string(),
+            reason,
+            likely_user_controlled,
+        }));
+    }
+
+    sites
+}
+
+/// Heuristic: does the expression read from a user-input-like root?
+fn is_user_controlled(expression: &str) -> bool {
+    let path = expression.split('|').next().unwrap_or_default().trim();
+    let root = path.split('.').next().unwrap_or_default();
+    USER_CONTROLLED_ROOTS.contains(&root)
+}
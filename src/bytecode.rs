@@ -1,7 +1,9 @@
 //! Bytecode compilation and execution for templates
 
+use crate::engine::{else_condition, strip_negations};
 use crate::error::{TemplateError, TemplateResult};
 use crate::context::TemplateContext;
+use std::collections::HashMap;
 
 /// Bytecode instruction for template execution
 #[derive(Debug, Clone)]
@@ -14,6 +16,11 @@ pub enum BytecodeInstruction {
     OutputRaw(Vec<String>),
     /// Conditional jump if variable is falsy
     JumpIfFalsy(Vec<String>, usize),
+    /// Conditional jump if variable is truthy (`unless`)
+    JumpIfTruthy(Vec<String>, usize),
+    /// Conditional jump if variable has the value it had when this
+    /// instruction last ran (`ifchanged`)
+    JumpIfUnchanged(Vec<String>, usize),
     /// Unconditional jump
     Jump(usize),
     /// Start loop over array variable
@@ -54,6 +61,10 @@ impl TemplateCompiler {
     /// Compile template string to bytecode
     pub fn compile(&self, template: &str) -> TemplateResult<Vec<BytecodeInstruction>> {
         let mut instructions = Vec::new();
+        // Jumps of each open conditional, fixed up at its closing tag, and its
+        // keyword; the last jump of an `if` skips to its next branch, and the
+        // keyword is `else` once no branch can follow
+        let mut open_blocks: Vec<(Vec<usize>, &str)> = Vec::new();
         let mut pos = 0;
         let chars: Vec<char> = template.chars().collect();
         
@@ -77,10 +88,50 @@ impl TemplateCompiler {
                 
                 // Simple parsing (minimal for GREEN phase)
                 if let Some(stripped) = directive.strip_prefix("if ") {
-                    let var_name = stripped.trim();
-                    let path = Self::parse_variable_path(var_name);
-                    instructions.push(BytecodeInstruction::JumpIfFalsy(path, 0)); // Will be fixed up later
-                } else if directive == "/if" {
+                    open_blocks.push((vec![instructions.len()], "if"));
+                    instructions.push(Self::condition_jump(stripped, false)); // Fixed up at the closing tag
+                } else if let Some(stripped) = directive.strip_prefix("unless ") {
+                    open_blocks.push((vec![instructions.len()], "unless"));
+                    instructions.push(Self::condition_jump(stripped, true)); // Fixed up at the closing tag
+                } else if let Some(stripped) = directive.strip_prefix("ifchanged ") {
+                    let path = Self::parse_variable_path(stripped.trim());
+                    open_blocks.push((vec![instructions.len()], "ifchanged"));
+                    instructions.push(BytecodeInstruction::JumpIfUnchanged(path, 0)); // Fixed up at the closing tag
+                } else if let Some(condition) = else_condition(directive) {
+                    // A failed condition jumps to the next branch, a rendered branch past the block
+                    let Some((mut jumps, "if")) = open_blocks.pop() else {
+                        return Err(TemplateError::Parse(format!(
+                            "Unexpected {{{{{}}}}} outside an {{{{if}}}} block: expected {{{{if condition}}}}...{{{{else}}}}...{{{{/if}}}}", directive
+                        )));
+                    };
+                    let skip = instructions.len();
+                    if let Some(jump) = jumps.pop() {
+                        Self::set_target(&mut instructions[jump], skip);
+                    }
+                    jumps.push(skip);
+                    instructions.push(BytecodeInstruction::Jump(0)); // Fixed up at the closing tag
+                    match condition {
+                        Some(condition) => {
+                            jumps.push(instructions.len());
+                            instructions.push(Self::condition_jump(condition, false));
+                            open_blocks.push((jumps, "if"));
+                        }
+                        None => open_blocks.push((jumps, "else")),
+                    }
+                } else if let Some(keyword) = directive.strip_prefix('/').filter(|keyword| ["if", "unless", "ifchanged"].contains(keyword)) {
+                    match open_blocks.pop() {
+                        Some((jumps, opened)) if opened == keyword || (opened == "else" && keyword == "if") => {
+                            let target = instructions.len();
+                            for jump in jumps {
+                                Self::set_target(&mut instructions[jump], target);
+                            }
+                        }
+                        Some((_, opened)) => {
+                            let opened = if opened == "else" { "if" } else { opened };
+                            return Err(TemplateError::Parse(format!("{{{{/{}}}}} does not match {{{{{}}}}}", keyword, opened)));
+                        }
+                        None => {}
+                    }
                     instructions.push(BytecodeInstruction::Nop);
                 } else if directive.starts_with("for ") {
                     // Parse for loop
@@ -122,9 +173,37 @@ impl TemplateCompiler {
             }
         }
         
+        if let Some((_, opened)) = open_blocks.pop() {
+            let opened = if opened == "else" { "if" } else { opened };
+            return Err(TemplateError::Parse(format!("Missing {{{{/{}}}}} directive", opened)));
+        }
+        
         Ok(instructions)
     }
     
+    /// Jump skipping a body when `condition` is truthy (`skip_truthy`) or
+    /// falsy; a `!path` or `not path` condition inverts it
+    fn condition_jump(condition: &str, skip_truthy: bool) -> BytecodeInstruction {
+        let (path, negated) = strip_negations(condition);
+        let path = Self::parse_variable_path(path);
+        if skip_truthy != negated {
+            BytecodeInstruction::JumpIfTruthy(path, 0)
+        } else {
+            BytecodeInstruction::JumpIfFalsy(path, 0)
+        }
+    }
+    
+    /// Point a conditional jump at `target`, the instruction it skips to
+    fn set_target(instruction: &mut BytecodeInstruction, target: usize) {
+        match instruction {
+            BytecodeInstruction::JumpIfFalsy(_, jump_target)
+            | BytecodeInstruction::JumpIfTruthy(_, jump_target)
+            | BytecodeInstruction::JumpIfUnchanged(_, jump_target)
+            | BytecodeInstruction::Jump(jump_target) => *jump_target = target,
+            _ => {}
+        }
+    }
+    
     fn parse_variable_path(var_name: &str) -> Vec<String> {
         var_name.split('.').map(|s| s.to_string()).collect()
     }
@@ -143,6 +222,8 @@ impl BytecodeExecutor {
     pub fn execute(&self, instructions: &[BytecodeInstruction], context: &TemplateContext) -> TemplateResult<String> {
         let mut output = String::new();
         let mut pc = 0; // program counter
+        // Last value seen by each `ifchanged` instruction, by position
+        let mut last_values: HashMap<usize, String> = HashMap::new();
         
         while pc < instructions.len() {
             match &instructions[pc] {
@@ -157,36 +238,31 @@ impl BytecodeExecutor {
                     let value = self.resolve_variable_path(path, context);
                     output.push_str(&value);
                 }
-                BytecodeInstruction::JumpIfFalsy(path, _target) => {
+                // Jumps land on the closing tag or `{{else}}` and resume after it
+                BytecodeInstruction::JumpIfFalsy(path, target) => {
                     // Check if the condition is truthy using proper value evaluation
-                    let is_truthy = if path.len() == 1 {
-                        if let Some(value) = context.variables.get(&path[0]) {
-                            self.is_truthy_value(value)
-                        } else {
-                            false
-                        }
-                    } else {
-                        // Deep dot notation
-                        if let Some(root_value) = context.variables.get(&path[0]) {
-                            let nested_value = self.get_nested_value(root_value, &path[1..]);
-                            nested_value.is_some_and(|v| self.is_truthy_value(v))
-                        } else {
-                            false
-                        }
-                    };
-                    
-                    if !is_truthy {
-                        // Skip to /if (simplified - find next Nop)
-                        while pc < instructions.len() && !matches!(instructions[pc], BytecodeInstruction::Nop) {
-                            pc += 1;
-                        }
+                    if !self.is_truthy_path(path, context) {
+                        pc = *target;
                     }
                 }
-                BytecodeInstruction::Jump(_target) => {
-                    // Simplified jump
+                BytecodeInstruction::JumpIfTruthy(path, target) => {
+                    if self.is_truthy_path(path, context) {
+                        pc = *target;
+                    }
+                }
+                BytecodeInstruction::JumpIfUnchanged(path, target) => {
+                    let value = self.resolve_variable_path(path, context);
+                    if last_values.insert(pc, value.clone()).as_ref() == Some(&value) {
+                        pc = *target;
+                    }
                 }
                 BytecodeInstruction::StartLoop(_item_var, _array_path) => {
-                    // Simplified loop handling - just continue for now
+                    // A loop starts over: `ifchanged` inside it has no previous value
+                    let loop_end = Self::matching_loop_end(instructions, pc);
+                    last_values.retain(|site, _| *site < pc || *site > loop_end);
+                }
+                BytecodeInstruction::Jump(target) => {
+                    pc = *target;
                 }
                 BytecodeInstruction::EndLoop(_target) => {
                     // Simplified loop handling
@@ -201,6 +277,38 @@ impl BytecodeExecutor {
         Ok(output)
     }
     
+    /// Position of the `EndLoop` closing the loop started at `start`
+    fn matching_loop_end(instructions: &[BytecodeInstruction], start: usize) -> usize {
+        let mut depth = 0;
+        for (pc, instruction) in instructions.iter().enumerate().skip(start) {
+            match instruction {
+                BytecodeInstruction::StartLoop(..) => depth += 1,
+                BytecodeInstruction::EndLoop(_) if depth == 1 => return pc,
+                BytecodeInstruction::EndLoop(_) => depth -= 1,
+                _ => {}
+            }
+        }
+        instructions.len()
+    }
+    
+    fn is_truthy_path(&self, path: &[String], context: &TemplateContext) -> bool {
+        if path.len() == 1 {
+            if let Some(value) = context.get(&path[0]) {
+                self.is_truthy_value(value)
+            } else {
+                false
+            }
+        } else {
+            // Deep dot notation
+            if let Some(root_value) = context.get(&path[0]) {
+                let nested_value = self.get_nested_value(root_value, &path[1..]);
+                nested_value.is_some_and(|v| self.is_truthy_value(v))
+            } else {
+                false
+            }
+        }
+    }
+    
     fn resolve_variable_path(&self, path: &[String], context: &TemplateContext) -> String {
         if path.is_empty() {
             return String::new();
@@ -211,7 +319,7 @@ impl BytecodeExecutor {
             context.get_string(&path[0]).unwrap_or_default()
         } else {
             // Deep dot notation traversal
-            if let Some(root_value) = context.variables.get(&path[0]) {
+            if let Some(root_value) = context.get(&path[0]) {
                 self.traverse_nested_value(root_value, &path[1..])
             } else {
                 String::new()
@@ -219,42 +327,14 @@ impl BytecodeExecutor {
         }
     }
     
-    #[allow(clippy::only_used_in_recursion)]
     fn traverse_nested_value(&self, current_value: &crate::value::TemplateValue, remaining_parts: &[String]) -> String {
         use crate::value::TemplateValue;
         
-        if remaining_parts.is_empty() {
-            return match current_value {
-                TemplateValue::String(s) => s.clone(),
-                TemplateValue::Bool(b) => b.to_string(),
-                TemplateValue::Number(n) => n.to_string(),
-                TemplateValue::Array(_) => String::new(),
-                TemplateValue::Object(_) => String::new(),
-            };
-        }
-
-        let current_part = &remaining_parts[0];
-        let next_parts = &remaining_parts[1..];
-
-        match current_value {
-            TemplateValue::Object(obj) => {
-                if let Some(next_value) = obj.get(current_part) {
-                    self.traverse_nested_value(next_value, next_parts)
-                } else {
-                    String::new()
-                }
-            }
-            TemplateValue::Array(arr) => {
-                if let Ok(index) = current_part.parse::<usize>() {
-                    if let Some(element) = arr.get(index) {
-                        self.traverse_nested_value(element, next_parts)
-                    } else {
-                        String::new()
-                    }
-                } else {
-                    String::new()
-                }
-            }
+        match self.get_nested_value(current_value, remaining_parts) {
+            Some(TemplateValue::String(s)) => s.clone(),
+            Some(TemplateValue::Bool(b)) => b.to_string(),
+            Some(TemplateValue::Number(n)) => n.to_string(),
+            Some(TemplateValue::Float(f)) => f.to_string(),
             _ => String::new(),
         }
     }
@@ -279,42 +359,24 @@ impl BytecodeExecutor {
             TemplateValue::Bool(b) => *b,
             TemplateValue::String(s) => !s.is_empty(),
             TemplateValue::Number(n) => *n != 0,
+            TemplateValue::Float(f) => *f != 0.0,
             TemplateValue::Array(a) => !a.is_empty(),
             TemplateValue::Object(o) => !o.is_empty(),
+            TemplateValue::Null => false,
         }
     }
     
-    #[allow(clippy::only_used_in_recursion)]
     fn get_nested_value<'a>(&self, current_value: &'a crate::value::TemplateValue, remaining_parts: &[String]) -> Option<&'a crate::value::TemplateValue> {
         use crate::value::TemplateValue;
         
-        if remaining_parts.is_empty() {
-            return Some(current_value);
-        }
-
-        let current_part = &remaining_parts[0];
-        let next_parts = &remaining_parts[1..];
-
-        match current_value {
-            TemplateValue::Object(obj) => {
-                if let Some(next_value) = obj.get(current_part) {
-                    self.get_nested_value(next_value, next_parts)
-                } else {
-                    None
-                }
-            }
-            TemplateValue::Array(arr) => {
-                if let Ok(index) = current_part.parse::<usize>() {
-                    if let Some(element) = arr.get(index) {
-                        self.get_nested_value(element, next_parts)
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            }
-            _ => None,
+        let mut current = current_value;
+        for part in remaining_parts {
+            current = match current {
+                TemplateValue::Object(obj) => obj.get(part)?,
+                TemplateValue::Array(arr) => arr.get(part.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
         }
+        Some(current)
     }
 }
\ No newline at end of file
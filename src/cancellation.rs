@@ -0,0 +1,36 @@
+//! Render cancellation for v0.6.0
+//!
+//! A token shared between a render and whoever may want to stop it, such as
+//! the handler of a request whose client went away. The renderer checks it
+//! at loop iterations, include boundaries and regularly while writing
+//! output, and stops with [`TemplateError::Cancelled`](crate::TemplateError::Cancelled).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Cancels the renders it is passed to, see
+/// [`TemplateEngine::render_with_cancel`](crate::TemplateEngine::render_with_cancel)
+///
+/// Clones share the same state: cancelling any clone cancels them all. A
+/// token stays cancelled, so create a new one for every render.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// A token that isn't cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask the renders using this token to stop
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    /// Whether [`cancel`](Self::cancel) was called
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+}
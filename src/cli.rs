@@ -3,7 +3,7 @@
 //! Command-line template processing utilities
 
 #[cfg(feature = "cli")]
-use crate::{TemplateEngine, TemplateContext, TemplateResult, TemplateError};
+use crate::{TemplateEngine, TemplateContext, TemplateResult, TemplateError, TemplateValue};
 
 #[cfg(feature = "cli")]
 use clap::{Parser, Subcommand};
@@ -15,7 +15,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 #[cfg(feature = "cli")]
-// Path is available for future path operations
+use std::path::Path;
 
 #[cfg(feature = "cli")]
 use std::fs;
@@ -88,6 +88,40 @@ pub enum Commands {
         #[arg(short, long)]
         config: String,
     },
+    /// List every unescaped output site in a template directory
+    Audit {
+        /// Template directory to audit
+        directory: String,
+    },
+    /// Show where two versions of a template render differently
+    Diff {
+        /// Original template file
+        template_a: String,
+        /// Changed template file
+        template_b: String,
+        /// Data file (JSON/TOML)
+        #[arg(short, long)]
+        data: Option<String>,
+    },
+    /// Time a render directive by directive and write a speedscope profile
+    Profile {
+        /// Template file
+        template: String,
+        /// Data file (JSON/TOML)
+        #[arg(short, long)]
+        data: Option<String>,
+        /// Speedscope JSON file to write
+        #[arg(short, long)]
+        output: String,
+    },
+    /// Report translation keys missing per locale and unused translation entries
+    I18nReport {
+        /// Template directory to scan
+        directory: String,
+        /// Translation tables (JSON/TOML), one table per locale
+        #[arg(short, long)]
+        translations: String,
+    },
 }
 
 /// Process a template string with JSON data
@@ -103,10 +137,10 @@ pub fn process_template(template_content: &str, data: &str) -> TemplateResult<St
 #[cfg(feature = "cli")]
 pub fn process_files(template_file: &str, data_file: &str) -> TemplateResult<String> {
     let template_content = fs::read_to_string(template_file)
-        .map_err(|e| TemplateError::Io(e))?;
+        .map_err(TemplateError::Io)?;
     
     let data_content = fs::read_to_string(data_file)
-        .map_err(|e| TemplateError::Io(e))?;
+        .map_err(TemplateError::Io)?;
     
     process_template(&template_content, &data_content)
 }
@@ -132,6 +166,81 @@ pub fn batch_process(templates: Vec<(&str, &str)>, data: &HashMap<&str, &str>) -
     Ok(results)
 }
 
+/// Audit a template directory for raw output sites (`runic audit`).
+/// 
+/// One line per site, `template:line:column: expression (reason)`, with
+/// likely user-controlled expressions marked `[user-controlled]`.
+#[cfg(feature = "cli")]
+pub fn audit_templates(directory: &str) -> TemplateResult<String> {
+    let mut engine = TemplateEngine::new(directory);
+    let mut report = String::new();
+    
+    for site in engine.audit_raw_output()? {
+        report.push_str(&format!(
+            "{}:{}:{}: {} ({})", site.template_name, site.line, site.column, site.expression, site.reason
+        ));
+        if site.likely_user_controlled {
+            report.push_str(" [user-controlled]");
+        }
+        report.push('\n');
+    }
+    
+    Ok(report)
+}
+
+/// Compare the renders of two template files (`runic diff`).
+/// 
+/// The report lists each differing region with the directives and lines
+/// that produced it on both sides, a line diff of the output and the
+/// variables that resolved differently.
+#[cfg(feature = "cli")]
+pub fn diff_templates(template_a_file: &str, template_b_file: &str, data: &str) -> TemplateResult<String> {
+    let template_a = fs::read_to_string(template_a_file).map_err(TemplateError::Io)?;
+    let template_b = fs::read_to_string(template_b_file).map_err(TemplateError::Io)?;
+    let context = parse_data(data)?;
+    
+    let mut engine = TemplateEngine::new(".");
+    let mut diff = engine.diff_render_strings(&template_a, &template_b, &context)?;
+    diff.left_name = template_a_file.to_string();
+    diff.right_name = template_b_file.to_string();
+    Ok(diff.to_string())
+}
+
+/// Profile the render of a template file (`runic profile`).
+/// 
+/// Writes the span tree to `json_file` in the speedscope format, ready for
+/// <https://www.speedscope.app>, and returns the top 10 hot spots by self
+/// time. Includes and layouts resolve from the template's directory.
+#[cfg(feature = "cli")]
+pub fn profile_template(template_file: &str, data: &str, json_file: &str) -> TemplateResult<String> {
+    let path = Path::new(template_file);
+    let directory = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let name = path.file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| TemplateError::Template(format!("Not a template file: {}", template_file)))?;
+    let context = parse_data(data)?;
+    
+    let mut engine = TemplateEngine::new(&directory.to_string_lossy());
+    let profile = engine.profile_render(name, &context)?;
+    fs::write(json_file, profile.to_speedscope_json()).map_err(TemplateError::Io)?;
+    Ok(profile.summary(10))
+}
+
+/// Check a template directory against translation tables (`runic i18n-report`).
+/// 
+/// `translations` maps each locale to its table, as JSON
+/// (`{"fr": {"cart.title": "Panier"}}`) or TOML (`[fr]` sections). The
+/// report lists the keys each locale is missing, with where they are used,
+/// and the entries no template uses.
+#[cfg(feature = "cli")]
+pub fn i18n_report(directory: &str, translations: &str) -> TemplateResult<String> {
+    let mut engine = TemplateEngine::new(directory);
+    for (locale, table) in parse_translations(translations)? {
+        engine.set_translations(&locale, table);
+    }
+    Ok(engine.translation_report()?.to_string())
+}
+
 /// Load CLI configuration from TOML
 #[cfg(feature = "cli")]
 pub fn load_config(toml_content: &str) -> TemplateResult<CliConfig> {
@@ -166,16 +275,16 @@ impl TemplateWatcher {
         
         // Scan directory for changes
         let entries = fs::read_dir(&self.directory)
-            .map_err(|e| TemplateError::Io(e))?;
+            .map_err(TemplateError::Io)?;
         
         for entry in entries {
-            let entry = entry.map_err(|e| TemplateError::Io(e))?;
+            let entry = entry.map_err(TemplateError::Io)?;
             let path = entry.path();
             
             if path.is_file() {
                 if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-                    let metadata = entry.metadata().map_err(|e| TemplateError::Io(e))?;
-                    let modified = metadata.modified().map_err(|e| TemplateError::Io(e))?;
+                    let metadata = entry.metadata().map_err(TemplateError::Io)?;
+                    let modified = metadata.modified().map_err(TemplateError::Io)?;
                     
                     if let Some(&last_modified) = self.last_modified.get(filename) {
                         if modified > last_modified {
@@ -191,6 +300,9 @@ impl TemplateWatcher {
             }
         }
         
+        // Directory iteration order is platform dependent
+        changed_files.sort();
+        
         Ok(changed_files)
     }
     
@@ -201,16 +313,16 @@ impl TemplateWatcher {
     
     fn scan_directory(&mut self) -> TemplateResult<()> {
         let entries = fs::read_dir(&self.directory)
-            .map_err(|e| TemplateError::Io(e))?;
+            .map_err(TemplateError::Io)?;
         
         for entry in entries {
-            let entry = entry.map_err(|e| TemplateError::Io(e))?;
+            let entry = entry.map_err(TemplateError::Io)?;
             let path = entry.path();
             
             if path.is_file() {
                 if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-                    let metadata = entry.metadata().map_err(|e| TemplateError::Io(e))?;
-                    let modified = metadata.modified().map_err(|e| TemplateError::Io(e))?;
+                    let metadata = entry.metadata().map_err(TemplateError::Io)?;
+                    let modified = metadata.modified().map_err(TemplateError::Io)?;
                     
                     self.last_modified.insert(filename.to_string(), modified);
                 }
@@ -221,6 +333,265 @@ impl TemplateWatcher {
     }
 }
 
+/// Opens preview URLs; replace [`SystemBrowser`] to capture them in tests
+#[cfg(feature = "cli")]
+pub trait BrowserLauncher: Send + Sync {
+    /// Open a `file://` URL
+    fn open(&self, url: &str) -> TemplateResult<()>;
+}
+
+/// Opens URLs with the platform's default browser
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemBrowser;
+
+#[cfg(feature = "cli")]
+impl BrowserLauncher for SystemBrowser {
+    fn open(&self, url: &str) -> TemplateResult<()> {
+        let mut command = if cfg!(target_os = "windows") {
+            let mut command = std::process::Command::new("cmd");
+            command.args(["/C", "start", ""]);
+            command
+        } else if cfg!(target_os = "macos") {
+            std::process::Command::new("open")
+        } else {
+            std::process::Command::new("xdg-open")
+        };
+        command.arg(url).spawn().map_err(TemplateError::Io)?;
+        Ok(())
+    }
+}
+
+/// How an open preview picks up new output
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PreviewReload {
+    /// Never reload
+    #[default]
+    Off,
+    /// `<meta http-equiv="refresh">` with this interval
+    MetaRefresh(std::time::Duration),
+    /// A small script reloading at this interval, keeping the scroll position
+    Script(std::time::Duration),
+}
+
+/// Options of [`preview_in_browser`]
+#[cfg(feature = "cli")]
+pub struct PreviewOptions {
+    reload: PreviewReload,
+    ttl: Option<std::time::Duration>,
+    base_dir: std::path::PathBuf,
+    launcher: Box<dyn BrowserLauncher>,
+}
+
+#[cfg(feature = "cli")]
+impl Default for PreviewOptions {
+    fn default() -> Self {
+        Self {
+            reload: PreviewReload::Off,
+            ttl: None,
+            base_dir: std::env::temp_dir(),
+            launcher: Box::new(SystemBrowser),
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+impl PreviewOptions {
+    /// Previews in the system temp dir, opened by [`SystemBrowser`], without reload
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make the preview reload itself, for watch mode
+    pub fn with_reload(mut self, reload: PreviewReload) -> Self {
+        self.reload = reload;
+        self
+    }
+
+    /// Keep the files for `ttl` after the handle is dropped instead of
+    /// removing them right away; leftovers of earlier runs older than `ttl`
+    /// are removed by the next preview
+    pub fn with_ttl(mut self, ttl: std::time::Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Create the preview directories under `dir` instead of the system temp dir
+    pub fn in_dir(mut self, dir: impl AsRef<std::path::Path>) -> Self {
+        self.base_dir = dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Open the preview with `launcher`
+    pub fn with_launcher(mut self, launcher: impl BrowserLauncher + 'static) -> Self {
+        self.launcher = Box::new(launcher);
+        self
+    }
+}
+
+/// Prefix of the per-run preview directories
+#[cfg(feature = "cli")]
+const PREVIEW_DIR_PREFIX: &str = "mystical-runic-preview-";
+
+/// A rendered page open in the browser, from [`preview_in_browser`].
+///
+/// Its directory is removed when the handle is dropped, or once the
+/// [TTL](PreviewOptions::with_ttl) has elapsed.
+#[cfg(feature = "cli")]
+#[derive(Debug)]
+pub struct PreviewHandle {
+    dir: std::path::PathBuf,
+    file: std::path::PathBuf,
+    url: String,
+    reload: PreviewReload,
+    ttl: Option<std::time::Duration>,
+}
+
+#[cfg(feature = "cli")]
+impl PreviewHandle {
+    /// `file://` URL of the preview
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Path of the preview file
+    pub fn path(&self) -> &std::path::Path {
+        &self.file
+    }
+
+    /// Replace the previewed page, e.g. after a watched template changed.
+    /// A reloading preview shows it on its next reload.
+    pub fn update(&self, html: &str) -> TemplateResult<()> {
+        write_preview(&self.file, &with_reload(html, self.reload))
+    }
+}
+
+#[cfg(feature = "cli")]
+impl Drop for PreviewHandle {
+    fn drop(&mut self) {
+        match self.ttl {
+            None => {
+                let _ = fs::remove_dir_all(&self.dir);
+            }
+            Some(ttl) => {
+                let dir = self.dir.clone();
+                std::thread::spawn(move || {
+                    std::thread::sleep(ttl);
+                    let _ = fs::remove_dir_all(dir);
+                });
+            }
+        }
+    }
+}
+
+/// Write `html` to a fresh directory under the system temp dir and open it
+/// in the browser.
+///
+/// # Example
+/// ```rust,no_run
+/// use mystical_runic::{preview_in_browser, PreviewOptions, PreviewReload};
+/// use std::time::Duration;
+///
+/// let options = PreviewOptions::new().with_reload(PreviewReload::Script(Duration::from_secs(1)));
+/// let preview = preview_in_browser("<h1>Hello</h1>", options)?;
+/// println!("Previewing {}", preview.url());
+/// // ... on each change: preview.update(&rendered)?;
+/// # Ok::<(), mystical_runic::TemplateError>(())
+/// ```
+#[cfg(feature = "cli")]
+pub fn preview_in_browser(html: &str, options: PreviewOptions) -> TemplateResult<PreviewHandle> {
+    if let Some(ttl) = options.ttl {
+        remove_expired_previews(&options.base_dir, ttl);
+    }
+
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    let dir = options.base_dir.join(format!("{}{}-{}", PREVIEW_DIR_PREFIX, std::process::id(), stamp));
+    fs::create_dir_all(&dir).map_err(TemplateError::Io)?;
+    let file = dir.join("index.html");
+
+    let handle = PreviewHandle {
+        url: file_url(&fs::canonicalize(&dir).map_err(TemplateError::Io)?.join("index.html")),
+        dir,
+        file,
+        reload: options.reload,
+        ttl: options.ttl,
+    };
+    handle.update(html)?;
+    options.launcher.open(&handle.url)?;
+    Ok(handle)
+}
+
+/// Remove preview directories of earlier runs last touched more than `ttl` ago
+#[cfg(feature = "cli")]
+fn remove_expired_previews(base_dir: &std::path::Path, ttl: std::time::Duration) {
+    let Ok(entries) = fs::read_dir(base_dir) else { return };
+    for entry in entries.flatten() {
+        let expired = entry.metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > ttl);
+        if expired && entry.file_name().to_string_lossy().starts_with(PREVIEW_DIR_PREFIX) {
+            let _ = fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+/// Replace the preview file in one step so a reload never reads half a page
+#[cfg(feature = "cli")]
+fn write_preview(file: &std::path::Path, html: &str) -> TemplateResult<()> {
+    let partial = file.with_extension("html.partial");
+    fs::write(&partial, html).map_err(TemplateError::Io)?;
+    fs::rename(&partial, file).map_err(TemplateError::Io)
+}
+
+/// Add the reload mechanism to a page, in its `<head>` when it has one
+#[cfg(feature = "cli")]
+fn with_reload(html: &str, reload: PreviewReload) -> String {
+    let snippet = match reload {
+        PreviewReload::Off => return html.to_string(),
+        PreviewReload::MetaRefresh(interval) => {
+            format!("<meta http-equiv=\"refresh\" content=\"{}\">", interval.as_secs().max(1))
+        }
+        PreviewReload::Script(interval) => format!(
+            "<script>(function(){{var k='runic-preview-scroll';var y=sessionStorage.getItem(k);\
+             if(y!==null){{window.addEventListener('load',function(){{window.scrollTo(0,+y);}});}}\
+             setTimeout(function(){{sessionStorage.setItem(k,String(window.scrollY));location.reload();}},{});}})();</script>",
+            interval.as_millis().max(100)
+        ),
+    };
+    match html.find("<head>") {
+        Some(head) => {
+            let insert_at = head + "<head>".len();
+            format!("{}{}{}", &html[..insert_at], snippet, &html[insert_at..])
+        }
+        None => format!("{}{}", snippet, html),
+    }
+}
+
+/// `file://` URL of an absolute path, percent-encoded
+#[cfg(feature = "cli")]
+fn file_url(path: &std::path::Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    // `\\?\C:\...` from canonicalize on Windows
+    let path = path.strip_prefix("//?/").unwrap_or(&path);
+    let mut url = String::from("file://");
+    if !path.starts_with('/') {
+        url.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => url.push(byte as char),
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    url
+}
+
 /// Parse data from JSON or TOML string
 #[cfg(feature = "cli")]
 fn parse_data(data: &str) -> TemplateResult<TemplateContext> {
@@ -243,6 +614,18 @@ fn parse_data(data: &str) -> TemplateResult<TemplateContext> {
     Err(TemplateError::Parse("Failed to parse data as JSON or TOML".to_string()))
 }
 
+/// Parse locale -> key -> translation tables from JSON or TOML
+#[cfg(feature = "cli")]
+fn parse_translations(translations: &str) -> TemplateResult<HashMap<String, HashMap<String, String>>> {
+    if translations.trim().starts_with('{') {
+        if let Ok(tables) = serde_json::from_str(translations) {
+            return Ok(tables);
+        }
+    }
+    toml::from_str(translations)
+        .map_err(|e| TemplateError::Parse(format!("Failed to parse translations as JSON or TOML: {}", e)))
+}
+
 #[cfg(feature = "cli")]
 fn add_json_to_context(context: &mut TemplateContext, value: &serde_json::Value) -> TemplateResult<()> {
     use serde_json::Value;
@@ -260,6 +643,7 @@ fn add_json_to_context(context: &mut TemplateContext, value: &serde_json::Value)
                         }
                     }
                     Value::Bool(b) => context.set_bool(key, *b),
+                    Value::Null => context.set(key, TemplateValue::Null),
                     _ => context.set_string(key, &val.to_string()),
                 }
             }
@@ -1,12 +1,18 @@
 //! Template context for variable storage
 
 use crate::value::TemplateValue;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 /// Template context containing variables for rendering
 #[derive(Debug, Clone)]
 pub struct TemplateContext {
-    pub(crate) variables: HashMap<String, TemplateValue>,
+    variables: HashMap<String, TemplateValue>,
+    /// Variables of this layer holding rendered markup, such as
+    /// `{{capture}}` output, which output tags don't escape again
+    markup: HashSet<String>,
+    /// Shared layer consulted when a variable is not set on this one
+    base: Option<Arc<TemplateContext>>,
 }
 
 impl TemplateContext {
@@ -14,12 +20,72 @@ impl TemplateContext {
     pub fn new() -> Self {
         Self {
             variables: HashMap::new(),
+            markup: HashSet::new(),
+            base: None,
         }
     }
 
-    /// Set a variable
-    pub fn set(&mut self, name: &str, value: TemplateValue) {
-        self.variables.insert(name.to_string(), value);
+    /// Create an empty context layered on a shared base context.
+    ///
+    /// Lookups fall back to `base` without copying it, and writes always go to
+    /// the new top layer, so one heavyweight base (site config, menus, feature
+    /// flags) can back many small per-request contexts.
+    ///
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::TemplateContext;
+    /// use std::sync::Arc;
+    ///
+    /// let mut base = TemplateContext::new();
+    /// base.set_string("site", "Runic");
+    /// base.set_string("title", "Home");
+    /// let base = Arc::new(base);
+    ///
+    /// let mut request = TemplateContext::layered(Arc::clone(&base));
+    /// request.set_string("title", "Profile");
+    ///
+    /// assert_eq!(request.get_string("site"), Some("Runic".to_string()));
+    /// assert_eq!(request.get_string("title"), Some("Profile".to_string()));
+    /// assert_eq!(base.get_string("title"), Some("Home".to_string()));
+    /// ```
+    pub fn layered(base: Arc<TemplateContext>) -> Self {
+        Self {
+            variables: HashMap::new(),
+            markup: HashSet::new(),
+            base: Some(base),
+        }
+    }
+
+    /// The shared base context, if this context is layered
+    pub fn base(&self) -> Option<&Arc<TemplateContext>> {
+        self.base.as_ref()
+    }
+
+    /// Set a variable from a [`TemplateValue`] or anything that converts
+    /// into one: `context.set("name", "Ada")`, `context.set("age", 36)`
+    pub fn set(&mut self, name: &str, value: impl Into<TemplateValue>) {
+        self.markup.remove(name);
+        self.variables.insert(name.to_string(), value.into());
+    }
+
+    /// Set a variable to markup that is already rendered and escaped
+    pub(crate) fn set_markup(&mut self, name: &str, markup: String) {
+        self.variables.insert(name.to_string(), TemplateValue::String(markup));
+        self.markup.insert(name.to_string());
+    }
+
+    /// Whether the variable visible as `name` was set with [`set_markup`](Self::set_markup)
+    pub(crate) fn is_markup(&self, name: &str) -> bool {
+        let mut layer = self;
+        loop {
+            if layer.variables.contains_key(name) {
+                return layer.markup.contains(name);
+            }
+            match layer.base.as_deref() {
+                Some(base) => layer = base,
+                None => return false,
+            }
+        }
     }
 
     /// Set a string variable
@@ -37,17 +103,47 @@ impl TemplateContext {
         self.set(name, TemplateValue::Number(value));
     }
 
-    /// Get a variable value
+    /// Set a floating point variable
+    pub fn set_float(&mut self, name: &str, value: f64) {
+        self.set(name, TemplateValue::Float(value));
+    }
+
+    /// Get a variable value, falling back to the base layers
     pub fn get(&self, name: &str) -> Option<&TemplateValue> {
-        self.variables.get(name)
+        let mut layer = self;
+        loop {
+            if let Some(value) = layer.variables.get(name) {
+                return Some(value);
+            }
+            layer = layer.base.as_deref()?;
+        }
+    }
+
+    /// Check if a variable is set on this context or a base layer
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Every visible variable; values set on upper layers hide the base ones
+    pub(crate) fn visible_variables(&self) -> HashMap<&str, &TemplateValue> {
+        let mut layers = vec![self];
+        while let Some(base) = layers.last().and_then(|layer| layer.base.as_deref()) {
+            layers.push(base);
+        }
+        let mut variables = HashMap::new();
+        for layer in layers.into_iter().rev() {
+            variables.extend(layer.variables.iter().map(|(name, value)| (name.as_str(), value)));
+        }
+        variables
     }
 
     /// Get a string value
     pub fn get_string(&self, name: &str) -> Option<String> {
-        self.variables.get(name).map(|v| match v {
+        self.get(name).map(|v| match v {
             TemplateValue::String(s) => s.clone(),
             TemplateValue::Bool(b) => b.to_string(),
             TemplateValue::Number(n) => n.to_string(),
+            TemplateValue::Float(f) => f.to_string(),
             _ => String::new(),
         })
     }
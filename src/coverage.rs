@@ -0,0 +1,300 @@
+//! Template coverage for v0.6.0
+//!
+//! While coverage is on ([`enable_coverage`](crate::TemplateEngine::enable_coverage)),
+//! every render of a named template records which of its directives were
+//! evaluated and which conditional branches and loop bodies ran, following
+//! includes and layouts into their own templates.
+//! [`coverage_report`](crate::TemplateEngine::coverage_report) sums the
+//! renders up per template: percentages, the spans that never ran, and
+//! exports as LCOV or as an HTML page.
+//!
+//! Spans come from the [syntax tree](crate::ast). A branch or loop body that
+//! never ran is reported as one span, without the directives inside it.
+//! Macro bodies and `{{rescue}}` bodies are not measured.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Write};
+use std::sync::Arc;
+
+use crate::ast::{self, Node, Span};
+use crate::suggestions::find_line_column;
+use crate::utils::html_escape;
+
+/// What a [`CoverageSite`] measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CoverageKind {
+    /// The body of `{{if}}`, `{{unless}}` or `{{ifchanged}}`, counted when it renders
+    Branch,
+    /// The body of `{{for}}`, counted once per render with at least one item
+    LoopBody,
+    /// A directive, counted when it is evaluated
+    Directive,
+}
+
+/// A measured piece of a template and how many times it ran
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageSite {
+    pub kind: CoverageKind,
+    /// The directive's tag, or the body between the opening and closing tags
+    pub span: Span,
+    /// Directive without braces; for a body, the directive it belongs to
+    pub directive: String,
+    /// Renders in which the site ran
+    pub hits: u64,
+}
+
+/// Coverage of one template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateCoverage {
+    pub template: String,
+    /// Source the spans point into
+    pub source: String,
+    /// Every site of the template, in source order
+    pub sites: Vec<CoverageSite>,
+}
+
+impl TemplateCoverage {
+    /// Number of sites that ran at least once
+    pub fn covered(&self) -> usize {
+        self.sites.iter().filter(|site| site.hits > 0).count()
+    }
+
+    /// Share of the sites that ran, from 0 to 100; 100 without sites
+    pub fn percent(&self) -> f64 {
+        percent(self.covered(), self.sites.len())
+    }
+
+    /// Sites that never ran, outermost only: a body that never rendered
+    /// hides the directives inside it
+    pub fn uncovered(&self) -> Vec<&CoverageSite> {
+        let mut uncovered: Vec<&CoverageSite> = Vec::new();
+        for site in self.sites.iter().filter(|site| site.hits == 0) {
+            let inside = uncovered.last().is_some_and(|outer| outer.span.start <= site.span.start && site.span.end <= outer.span.end);
+            if !inside {
+                uncovered.push(site);
+            }
+        }
+        uncovered
+    }
+}
+
+/// Coverage of every template rendered since coverage was enabled
+///
+/// A branch or loop body that never ran is reported as one span, without
+/// the directives inside it. Macro bodies and `{{rescue}}` bodies are not
+/// measured.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CoverageReport {
+    /// Templates sorted by name
+    pub templates: Vec<TemplateCoverage>,
+}
+
+impl CoverageReport {
+    /// Coverage of template `name`
+    pub fn template(&self, name: &str) -> Option<&TemplateCoverage> {
+        self.templates.iter().find(|template| template.template == name)
+    }
+
+    /// Share of the sites of all templates that ran, from 0 to 100
+    pub fn percent(&self) -> f64 {
+        let covered = self.templates.iter().map(TemplateCoverage::covered).sum();
+        let total = self.templates.iter().map(|template| template.sites.len()).sum();
+        percent(covered, total)
+    }
+
+    /// The report in the LCOV tracefile format read by `genhtml`, Codecov and
+    /// most coverage dashboards: a line per line holding sites, with the
+    /// fewest hits among them, and a branch per conditional or loop body
+    pub fn to_lcov(&self) -> String {
+        let mut lcov = String::new();
+        for template in &self.templates {
+            let _ = writeln!(lcov, "TN:\nSF:{}", template.template);
+            let mut lines: BTreeMap<usize, u64> = BTreeMap::new();
+            for site in &template.sites {
+                let hits = lines.entry(site.span.line).or_insert(u64::MAX);
+                *hits = (*hits).min(site.hits);
+            }
+            let mut branches = 0;
+            let mut branches_hit = 0;
+            for (block, site) in template.sites.iter().filter(|site| site.kind != CoverageKind::Directive).enumerate() {
+                let (line, _) = find_line_column(&template.source, directive_start(&template.source, site.span.start));
+                let _ = writeln!(lcov, "BRDA:{},{},0,{}", line, block, site.hits);
+                branches += 1;
+                branches_hit += usize::from(site.hits > 0);
+            }
+            for (line, hits) in &lines {
+                let _ = writeln!(lcov, "DA:{},{}", line, hits);
+            }
+            let lines_hit = lines.values().filter(|hits| **hits > 0).count();
+            let _ = writeln!(lcov, "BRF:{}\nBRH:{}\nLF:{}\nLH:{}\nend_of_record", branches, branches_hit, lines.len(), lines_hit);
+        }
+        lcov
+    }
+
+    /// The report as a standalone HTML page: the percentage of each template
+    /// and its source with the uncovered spans highlighted
+    pub fn to_html(&self) -> String {
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Template coverage</title>\n\
+             <style>body{font-family:sans-serif}pre{background:#f6f8fa;padding:1em}mark{background:#ffd7d5}</style>\n\
+             </head>\n<body>\n",
+        );
+        let _ = writeln!(html, "<h1>Template coverage: {:.1}%</h1>", self.percent());
+        for template in &self.templates {
+            let _ = writeln!(
+                html, "<h2>{} &mdash; {:.1}% ({}/{})</h2>",
+                html_escape(&template.template), template.percent(), template.covered(), template.sites.len()
+            );
+            html.push_str("<pre>");
+            let mut pos = 0;
+            for site in template.uncovered() {
+                html.push_str(&html_escape(&template.source[pos..site.span.start]));
+                let _ = write!(html, "<mark title=\"{}\">{}</mark>", html_escape(&site.directive), html_escape(site.span.slice(&template.source)));
+                pos = site.span.end;
+            }
+            html.push_str(&html_escape(&template.source[pos..]));
+            html.push_str("</pre>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for template in &self.templates {
+            writeln!(f, "{} {:.1}% ({}/{})", template.template, template.percent(), template.covered(), template.sites.len())?;
+            for site in template.uncovered() {
+                writeln!(f, "  {}:{} {}", site.span.line, site.span.column, site.directive)?;
+            }
+        }
+        write!(f, "total {:.1}%", self.percent())
+    }
+}
+
+fn percent(covered: usize, total: usize) -> f64 {
+    if total == 0 {
+        100.0
+    } else {
+        covered as f64 * 100.0 / total as f64
+    }
+}
+
+/// Start of the opening tag whose body starts at `body_start`
+fn directive_start(source: &str, body_start: usize) -> usize {
+    source[..body_start].rfind("{{").unwrap_or(0)
+}
+
+/// End of the tag starting at `start`, for the body after an opening tag
+pub(crate) fn tag_end(source: &str, start: usize) -> usize {
+    source[start..].find("}}").map_or(source.len(), |end| start + end + 2)
+}
+
+/// Coverage recorded by the renders, shared by clones of the engine
+#[derive(Debug, Default)]
+pub(crate) struct CoverageRecorder {
+    templates: BTreeMap<String, RecordedTemplate>,
+}
+
+#[derive(Debug)]
+struct RecordedTemplate {
+    tree: Arc<ast::Template>,
+    sites: Vec<CoverageSite>,
+    /// Site of each `(start, kind)`
+    index: HashMap<(usize, CoverageKind), usize>,
+}
+
+impl CoverageRecorder {
+    /// Syntax tree of template `name`, parsed again (and its counts started
+    /// over) when `source` changed; `None` when it doesn't parse
+    pub(crate) fn parse(&mut self, name: &str, source: &str) -> Option<Arc<ast::Template>> {
+        if let Some(recorded) = self.templates.get(name).filter(|recorded| recorded.tree.source == source) {
+            return Some(Arc::clone(&recorded.tree));
+        }
+        let tree = Arc::new(ast::parse_named(Some(name), source).ok()?);
+        let mut sites = Vec::new();
+        collect_sites(&tree.nodes, source, &mut sites);
+        sites.sort_by_key(|site| (site.span.start, std::cmp::Reverse(site.span.end), site.kind));
+        let index = sites.iter().enumerate().map(|(position, site)| ((site.span.start, site.kind), position)).collect();
+        self.templates.insert(name.to_string(), RecordedTemplate { tree: Arc::clone(&tree), sites, index });
+        Some(tree)
+    }
+
+    /// Syntax tree of template `name` as last parsed
+    pub(crate) fn parsed(&self, name: &str) -> Option<Arc<ast::Template>> {
+        self.templates.get(name).map(|recorded| Arc::clone(&recorded.tree))
+    }
+
+    /// Count one render of the sites starting at `hits`
+    pub(crate) fn record(&mut self, name: &str, hits: &[(usize, CoverageKind)]) {
+        let Some(recorded) = self.templates.get_mut(name) else { return };
+        let mut positions: Vec<usize> = hits.iter().filter_map(|hit| recorded.index.get(hit).copied()).collect();
+        positions.sort_unstable();
+        positions.dedup();
+        for position in positions {
+            recorded.sites[position].hits += 1;
+        }
+    }
+
+    pub(crate) fn report(&self) -> CoverageReport {
+        CoverageReport {
+            templates: self.templates.iter().map(|(name, recorded)| TemplateCoverage {
+                template: name.clone(),
+                source: recorded.tree.source.clone(),
+                sites: recorded.sites.clone(),
+            }).collect(),
+        }
+    }
+}
+
+/// Add the sites of `nodes` and their children; macro and rescue bodies excluded
+fn collect_sites(nodes: &[Node], source: &str, sites: &mut Vec<CoverageSite>) {
+    for node in nodes {
+        let span = node.span();
+        let (body, children): (Option<(CoverageKind, usize)>, &[Node]) = match node {
+            Node::Text(_) | Node::Comment(_) | Node::MacroDef(_) => continue,
+            Node::If(node) => {
+                push_site(sites, CoverageKind::Directive, source, span.start, tag_end(source, span.start));
+                for branch in &node.branches {
+                    let directive = &source[branch.span.start..tag_end(source, branch.span.start)];
+                    let body_start = tag_end(source, branch.span.start);
+                    sites.push(site(CoverageKind::Branch, source, body_start, branch.span.end, directive));
+                    collect_sites(&branch.body, source, sites);
+                }
+                continue;
+            }
+            Node::Unless(node) => (Some((CoverageKind::Branch, span.start)), &node.body),
+            Node::IfChanged(node) => (Some((CoverageKind::Branch, span.start)), &node.body),
+            Node::For(node) => (Some((CoverageKind::LoopBody, span.start)), &node.body),
+            Node::Block(node) => (None, &node.body),
+            Node::TranslationScope(node) => (None, &node.body),
+            Node::Try(node) => (None, &node.body),
+            Node::Capture(node) => (None, &node.body),
+            _ => (None, &[]),
+        };
+        let opening_end = if matches!(node, Node::Block(_) | Node::TranslationScope(_) | Node::Try(_) | Node::Capture(_)) || body.is_some() {
+            tag_end(source, span.start)
+        } else {
+            span.end
+        };
+        push_site(sites, CoverageKind::Directive, source, span.start, opening_end);
+        if let Some((kind, start)) = body {
+            let body_end = span.start + source[span.start..span.end].rfind("{{").unwrap_or(0);
+            sites.push(site(kind, source, opening_end, body_end.max(opening_end), &source[start..opening_end]));
+        }
+        collect_sites(children, source, sites);
+        if let Node::For(ast::For { empty: Some(empty), .. }) = node {
+            collect_sites(empty, source, sites);
+        }
+    }
+}
+
+fn push_site(sites: &mut Vec<CoverageSite>, kind: CoverageKind, source: &str, start: usize, end: usize) {
+    sites.push(site(kind, source, start, end, &source[start..end]));
+}
+
+fn site(kind: CoverageKind, source: &str, start: usize, end: usize, tag: &str) -> CoverageSite {
+    let (line, column) = find_line_column(source, start);
+    let directive = tag.trim().trim_start_matches("{{").trim_end_matches("}}").trim().to_string();
+    CoverageSite { kind, span: Span { start, end, line, column }, directive, hits: 0 }
+}
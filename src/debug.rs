@@ -45,6 +45,8 @@ pub struct PerformanceMetrics {
     pub include_nanos: u64,
     /// Memory usage in bytes
     pub memory_usage_bytes: usize,
+    /// Distinct templates included or extended during the render
+    pub templates_touched: usize,
 }
 
 /// Result of template rendering with debug information
@@ -106,6 +108,7 @@ impl PerformanceMetrics {
             parsing_nanos: 0,
             include_nanos: 0,
             memory_usage_bytes: 0,
+            templates_touched: 0,
         }
     }
 }
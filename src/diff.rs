@@ -0,0 +1,499 @@
+//! Semantic HTML comparison for v0.6.0
+//!
+//! Byte-wise comparison of rendered pages is noisy: reordered attributes,
+//! reindented markup or an edited comment all count as changes.
+//! [`html_semantic_diff`] parses both documents into element trees, with the
+//! tolerant scanner of the `sanitize` filter, and reports only the elements,
+//! text and attributes that changed, each located by a path such as
+//! `body > div.card[2] > h3`.
+//!
+//! ```rust
+//! use mystical_runic::diff::{html_semantic_diff, SemanticDiffOptions};
+//!
+//! let options = SemanticDiffOptions::default();
+//! let old = "<ul>\n  <li class=\"a\" id=\"x\">Alpha</li>\n</ul>";
+//! assert!(html_semantic_diff(old, "<ul><li id=\"x\" class=\"a\">Alpha</li></ul>", &options).is_empty());
+//!
+//! let diff = html_semantic_diff(old, "<ul><li class=\"a\" id=\"x\">Beta</li></ul>", &options);
+//! assert_eq!(diff.to_string(), "~ ul > li.a: text \"Alpha\" -> \"Beta\"\n");
+//! ```
+//!
+//! Paths name elements with their classes, and with their position among
+//! same-named siblings when there are several (`li[3]`); the `html` element
+//! is left out. Removed elements are located in the old document, added
+//! and changed ones in the new.
+
+use crate::sanitize::{decode_entities, find_closing_tag, scan_tag};
+use crate::utils::html_escape;
+use std::fmt;
+
+/// Elements that never have content or a closing tag
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr",
+];
+
+/// Elements whose content is text, not markup
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "textarea", "title"];
+
+/// Elements whose whitespace is kept even when it is otherwise ignored
+const PREFORMATTED_ELEMENTS: &[&str] = &["pre", "textarea"];
+
+/// Which differences [`html_semantic_diff`] ignores; all of them by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SemanticDiffOptions {
+    ignore_attribute_order: bool,
+    ignore_whitespace: bool,
+    ignore_comments: bool,
+}
+
+impl Default for SemanticDiffOptions {
+    fn default() -> Self {
+        Self {
+            ignore_attribute_order: true,
+            ignore_whitespace: true,
+            ignore_comments: true,
+        }
+    }
+}
+
+impl SemanticDiffOptions {
+    /// Ignore attribute order, whitespace runs and comments
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compare attributes by name only, not by position
+    pub fn ignore_attribute_order(mut self, enabled: bool) -> Self {
+        self.ignore_attribute_order = enabled;
+        self
+    }
+
+    /// Collapse whitespace runs in text to a single space, trim text and
+    /// drop whitespace-only text; `pre` and `textarea` keep theirs
+    pub fn ignore_whitespace(mut self, enabled: bool) -> Self {
+        self.ignore_whitespace = enabled;
+        self
+    }
+
+    /// Skip comments, so neither their content nor their presence counts
+    pub fn ignore_comments(mut self, enabled: bool) -> Self {
+        self.ignore_comments = enabled;
+        self
+    }
+}
+
+/// One meaningful difference between two documents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SemanticChange {
+    /// An element, text or comment only the new document has, as HTML
+    Added { path: String, node: String },
+    /// An element, text or comment only the old document has, as HTML
+    Removed { path: String, node: String },
+    /// The text (or comment) at `path` changed
+    TextChanged { path: String, old: String, new: String },
+    /// An attribute was added (`old` is `None`), removed (`new` is `None`) or changed
+    AttributeChanged { path: String, name: String, old: Option<String>, new: Option<String> },
+    /// The same attributes in another order, when the order is not ignored
+    AttributesReordered { path: String },
+}
+
+impl SemanticChange {
+    /// Path of the changed element, or of the element holding the changed text
+    pub fn path(&self) -> &str {
+        match self {
+            SemanticChange::Added { path, .. }
+            | SemanticChange::Removed { path, .. }
+            | SemanticChange::TextChanged { path, .. }
+            | SemanticChange::AttributeChanged { path, .. }
+            | SemanticChange::AttributesReordered { path } => path,
+        }
+    }
+}
+
+impl fmt::Display for SemanticChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = match self.path() {
+            "" => "(document)",
+            path => path,
+        };
+        match self {
+            SemanticChange::Added { node, .. } => write!(f, "+ {}: {}", path, node),
+            SemanticChange::Removed { node, .. } => write!(f, "- {}: {}", path, node),
+            SemanticChange::TextChanged { old, new, .. } => write!(f, "~ {}: text {:?} -> {:?}", path, old, new),
+            SemanticChange::AttributeChanged { name, old, new, .. } => {
+                let shown = |value: &Option<String>| value.as_ref().map_or("(none)".to_string(), |value| format!("{:?}", value));
+                write!(f, "~ {} @{}: {} -> {}", path, name, shown(old), shown(new))
+            }
+            SemanticChange::AttributesReordered { .. } => write!(f, "~ {}: attributes reordered", path),
+        }
+    }
+}
+
+/// Changes between two documents, in document order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SemanticDiff {
+    pub changes: Vec<SemanticChange>,
+}
+
+impl SemanticDiff {
+    /// Whether the documents are the same once insignificant differences are ignored
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Number of changes
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// One change per line: `+` added, `-` removed, `~` modified
+    ///
+    /// ```text
+    /// ~ body > div.card[2] > h3: text "Old title" -> "New title"
+    /// + body > ul > li[3]: <li>Gamma</li>
+    /// ~ body > a @href: "/old" -> "/new"
+    /// ```
+    pub fn report(&self) -> String {
+        self.changes.iter().map(|change| format!("{}\n", change)).collect()
+    }
+}
+
+impl fmt::Display for SemanticDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.report())
+    }
+}
+
+/// Compare two HTML documents, ignoring the differences `options` ignores
+pub fn html_semantic_diff(old: &str, new: &str, options: &SemanticDiffOptions) -> SemanticDiff {
+    let old = parse_document(old, options);
+    let new = parse_document(new, options);
+    let mut diff = SemanticDiff::default();
+    diff_children(&old, &new, "", "", options, &mut diff.changes);
+    diff
+}
+
+/// A node of the parsed document
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Element(Element),
+    Text(String),
+    Comment(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Element {
+    name: String,
+    /// Sorted by name when attribute order is ignored
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    /// Nodes with the same key are compared as one node that changed
+    fn key(&self) -> &str {
+        match self {
+            Node::Element(element) => &element.name,
+            Node::Text(_) => "#text",
+            Node::Comment(_) => "#comment",
+        }
+    }
+
+    fn to_html(&self) -> String {
+        let mut html = String::new();
+        self.write_html(&mut html);
+        html
+    }
+
+    fn write_html(&self, out: &mut String) {
+        match self {
+            Node::Text(text) => out.push_str(&html_escape(text)),
+            Node::Comment(text) => {
+                out.push_str("<!--");
+                out.push_str(text);
+                out.push_str("-->");
+            }
+            Node::Element(element) => {
+                out.push('<');
+                out.push_str(&element.name);
+                for (name, value) in &element.attributes {
+                    out.push_str(&format!(" {}=\"{}\"", name, html_escape(value)));
+                }
+                out.push('>');
+                if VOID_ELEMENTS.contains(&element.name.as_str()) {
+                    return;
+                }
+                for child in &element.children {
+                    child.write_html(out);
+                }
+                out.push_str("</");
+                out.push_str(&element.name);
+                out.push('>');
+            }
+        }
+    }
+}
+
+/// Parse `input` into its top-level nodes. Unclosed elements end with their
+/// parent and closing tags that match nothing open are ignored.
+fn parse_document(input: &str, options: &SemanticDiffOptions) -> Vec<Node> {
+    // Elements being filled, the document itself at the bottom
+    let mut open: Vec<Element> = vec![Element { name: String::new(), attributes: Vec::new(), children: Vec::new() }];
+    let mut pos = 0;
+
+    while pos < input.len() {
+        let rest = &input[pos..];
+        let text_end = rest.find('<').unwrap_or(rest.len());
+        if text_end > 0 {
+            push_text(&mut open, &rest[..text_end], options);
+            pos += text_end;
+            continue;
+        }
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").unwrap_or(comment.len());
+            if !options.ignore_comments {
+                let parent = open.last_mut().expect("the document stays open");
+                parent.children.push(Node::Comment(comment[..end].to_string()));
+            }
+            pos = (pos + 4 + end + 3).min(input.len());
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            pos = rest.find('>').map_or(input.len(), |end| pos + end + 1);
+            continue;
+        }
+        let Some((tag, length)) = scan_tag(rest) else {
+            push_text(&mut open, "<", options);
+            pos += 1;
+            continue;
+        };
+        pos += length;
+        if !tag.terminated {
+            break;
+        }
+
+        if tag.closing {
+            if let Some(index) = open.iter().rposition(|element| element.name == tag.name).filter(|index| *index > 0) {
+                while open.len() > index {
+                    close_element(&mut open);
+                }
+            }
+            continue;
+        }
+
+        let mut attributes: Vec<(String, String)> = tag.attributes.iter()
+            .map(|(name, value)| (name.clone(), value.map(decode_entities).unwrap_or_default()))
+            .collect();
+        if options.ignore_attribute_order {
+            attributes.sort();
+        }
+        let element = Element { name: tag.name, attributes, children: Vec::new() };
+        if VOID_ELEMENTS.contains(&element.name.as_str()) || rest[..length].ends_with("/>") {
+            open.last_mut().expect("the document stays open").children.push(Node::Element(element));
+            continue;
+        }
+        if RAW_TEXT_ELEMENTS.contains(&element.name.as_str()) {
+            let end = find_closing_tag(input, pos, &element.name);
+            let content_end = input[pos..end].rfind("</").map_or(end, |close| pos + close);
+            let raw_text = input[pos..content_end].to_string();
+            open.push(element);
+            push_text(&mut open, &raw_text, options);
+            close_element(&mut open);
+            pos = end;
+            continue;
+        }
+        open.push(element);
+    }
+
+    while open.len() > 1 {
+        close_element(&mut open);
+    }
+    open.pop().map(|document| document.children).unwrap_or_default()
+}
+
+/// Move the innermost open element into its parent
+fn close_element(open: &mut Vec<Element>) {
+    let element = open.pop().expect("an element is open");
+    open.last_mut().expect("the document stays open").children.push(Node::Element(element));
+}
+
+/// Add text to the innermost open element, merging it with text just before
+fn push_text(open: &mut [Element], text: &str, options: &SemanticDiffOptions) {
+    let preformatted = open.iter().any(|element| PREFORMATTED_ELEMENTS.contains(&element.name.as_str()));
+    let parent = open.last_mut().expect("the document stays open");
+    let decoded = if RAW_TEXT_ELEMENTS.contains(&parent.name.as_str()) { text.to_string() } else { decode_entities(text) };
+
+    match parent.children.last_mut() {
+        Some(Node::Text(previous)) => previous.push_str(&decoded),
+        _ => parent.children.push(Node::Text(decoded)),
+    }
+    if options.ignore_whitespace && !preformatted {
+        if let Some(Node::Text(text)) = parent.children.last_mut() {
+            *text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if text.is_empty() {
+                parent.children.pop();
+            }
+        }
+    }
+}
+
+/// Compare the children of two matched elements at `old_path` / `new_path`
+fn diff_children(
+    old: &[Node],
+    new: &[Node],
+    old_path: &str,
+    new_path: &str,
+    options: &SemanticDiffOptions,
+    changes: &mut Vec<SemanticChange>,
+) {
+    // Identical nodes anchor the comparison; nodes between two anchors are
+    // paired by kind and name, in order, and the rest were added or removed
+    let anchors = longest_common_subsequence(old, new);
+    let (mut i, mut j) = (0, 0);
+    for (anchor_i, anchor_j) in anchors.into_iter().chain(std::iter::once((old.len(), new.len()))) {
+        let mut next_new = j;
+        for old_index in i..anchor_i {
+            let paired = (next_new..anchor_j).find(|&new_index| new[new_index].key() == old[old_index].key());
+            let Some(new_index) = paired else {
+                changes.push(SemanticChange::Removed { path: child_path(old, old_index, old_path), node: old[old_index].to_html() });
+                continue;
+            };
+            for added in next_new..new_index {
+                changes.push(SemanticChange::Added { path: child_path(new, added, new_path), node: new[added].to_html() });
+            }
+            diff_nodes(old, old_index, new, new_index, old_path, new_path, options, changes);
+            next_new = new_index + 1;
+        }
+        for added in next_new..anchor_j {
+            changes.push(SemanticChange::Added { path: child_path(new, added, new_path), node: new[added].to_html() });
+        }
+        (i, j) = (anchor_i + 1, anchor_j + 1);
+    }
+}
+
+/// Compare two nodes of the same kind and name
+#[allow(clippy::too_many_arguments)]
+fn diff_nodes(
+    old_siblings: &[Node],
+    old_index: usize,
+    new_siblings: &[Node],
+    new_index: usize,
+    old_parent: &str,
+    new_parent: &str,
+    options: &SemanticDiffOptions,
+    changes: &mut Vec<SemanticChange>,
+) {
+    match (&old_siblings[old_index], &new_siblings[new_index]) {
+        (Node::Text(old), Node::Text(new)) => changes.push(SemanticChange::TextChanged {
+            path: new_parent.to_string(),
+            old: old.clone(),
+            new: new.clone(),
+        }),
+        (Node::Comment(old), Node::Comment(new)) => changes.push(SemanticChange::TextChanged {
+            path: new_parent.to_string(),
+            old: format!("<!--{}-->", old),
+            new: format!("<!--{}-->", new),
+        }),
+        (Node::Element(old), Node::Element(new)) => {
+            let old_path = child_path(old_siblings, old_index, old_parent);
+            let new_path = child_path(new_siblings, new_index, new_parent);
+            diff_attributes(old, new, &new_path, options, changes);
+            diff_children(&old.children, &new.children, &old_path, &new_path, options, changes);
+        }
+        _ => {}
+    }
+}
+
+fn diff_attributes(old: &Element, new: &Element, path: &str, options: &SemanticDiffOptions, changes: &mut Vec<SemanticChange>) {
+    let value = |element: &Element, name: &str| {
+        element.attributes.iter().find(|(attribute, _)| attribute == name).map(|(_, value)| value.clone())
+    };
+    let mut changed = false;
+    for (name, old_value) in &old.attributes {
+        let new_value = value(new, name);
+        if new_value.as_ref() != Some(old_value) {
+            changes.push(SemanticChange::AttributeChanged {
+                path: path.to_string(),
+                name: name.clone(),
+                old: Some(old_value.clone()),
+                new: new_value,
+            });
+            changed = true;
+        }
+    }
+    for (name, new_value) in &new.attributes {
+        if value(old, name).is_none() {
+            changes.push(SemanticChange::AttributeChanged {
+                path: path.to_string(),
+                name: name.clone(),
+                old: None,
+                new: Some(new_value.clone()),
+            });
+            changed = true;
+        }
+    }
+    if !changed && !options.ignore_attribute_order && old.attributes != new.attributes {
+        changes.push(SemanticChange::AttributesReordered { path: path.to_string() });
+    }
+}
+
+/// Path of `siblings[index]` under `parent`; text and comments take the
+/// path of their parent
+fn child_path(siblings: &[Node], index: usize, parent: &str) -> String {
+    let Node::Element(element) = &siblings[index] else {
+        return parent.to_string();
+    };
+    if element.name == "html" {
+        return parent.to_string();
+    }
+
+    let mut segment = element.name.clone();
+    if let Some((_, classes)) = element.attributes.iter().find(|(name, _)| name == "class") {
+        for class in classes.split_whitespace() {
+            segment.push('.');
+            segment.push_str(class);
+        }
+    }
+    let same_name = |node: &Node| matches!(node, Node::Element(other) if other.name == element.name);
+    if siblings.iter().filter(|node| same_name(node)).count() > 1 {
+        let position = siblings[..index].iter().filter(|node| same_name(node)).count() + 1;
+        segment.push_str(&format!("[{}]", position));
+    }
+
+    if parent.is_empty() {
+        segment
+    } else {
+        format!("{} > {}", parent, segment)
+    }
+}
+
+/// Index pairs of a longest common subsequence of equal nodes
+fn longest_common_subsequence(old: &[Node], new: &[Node]) -> Vec<(usize, usize)> {
+    // lengths[i][j]: length of the LCS of old[i..] and new[j..]
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
@@ -1,6 +1,8 @@
 //! Ecosystem compatibility and feature detection for v0.5.0
 
-use crate::{TemplateResult, TemplateError};
+use crate::TemplateResult;
+#[cfg(not(feature = "async"))]
+use crate::TemplateError;
 
 /// Ecosystem compatibility information
 #[derive(Debug, Clone)]
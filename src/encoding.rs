@@ -0,0 +1,146 @@
+//! Output encodings for v0.6.0
+//!
+//! Templates always render to UTF-8; [`TemplateEngine::render_bytes`](crate::TemplateEngine::render_bytes)
+//! then encodes the output for systems that expect another character set.
+//! UTF-8 and Latin-1 are built in, other encodings plug in through
+//! [`CharEncoder`] so the core stays free of encoding tables.
+
+use crate::{TemplateError, TemplateResult};
+use std::fmt;
+use std::sync::Arc;
+
+/// Encodes single characters for a custom [`OutputEncoding`]
+pub trait CharEncoder: Send + Sync {
+    /// Encoding name used in error messages, e.g. `"Shift_JIS"`
+    fn name(&self) -> &str;
+
+    /// Append the bytes for `ch` to `out`, or return `false` when the
+    /// encoding cannot represent it
+    fn encode_char(&self, ch: char, out: &mut Vec<u8>) -> bool;
+}
+
+#[derive(Clone)]
+enum Charset {
+    Utf8,
+    Latin1,
+    Custom(Arc<dyn CharEncoder>),
+}
+
+/// Character set of [`TemplateEngine::render_bytes`](crate::TemplateEngine::render_bytes) output.
+///
+/// Encoding is strict by default: a character the target encoding cannot
+/// represent fails the render with [`TemplateError::Encoding`], naming the
+/// character and where it is in the output. [`with_substitute`](Self::with_substitute)
+/// writes a replacement instead.
+///
+/// ```rust
+/// use mystical_runic::OutputEncoding;
+///
+/// let mut bytes = Vec::new();
+/// OutputEncoding::latin1().with_substitute("?").encode("café ☕", &mut bytes)?;
+/// assert_eq!(bytes, b"caf\xE9 ?");
+/// # Ok::<(), mystical_runic::TemplateError>(())
+/// ```
+#[derive(Clone)]
+pub struct OutputEncoding {
+    charset: Charset,
+    substitute: Option<String>,
+}
+
+impl Default for OutputEncoding {
+    /// UTF-8
+    fn default() -> Self {
+        Self::utf8()
+    }
+}
+
+impl fmt::Debug for OutputEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OutputEncoding")
+            .field("name", &self.name())
+            .field("substitute", &self.substitute)
+            .finish()
+    }
+}
+
+impl OutputEncoding {
+    /// UTF-8, which represents every character
+    pub fn utf8() -> Self {
+        Self { charset: Charset::Utf8, substitute: None }
+    }
+
+    /// ISO-8859-1: one byte per character, `U+0000` to `U+00FF`
+    pub fn latin1() -> Self {
+        Self { charset: Charset::Latin1, substitute: None }
+    }
+
+    /// An encoding provided by the application
+    pub fn custom(encoder: impl CharEncoder + 'static) -> Self {
+        Self { charset: Charset::Custom(Arc::new(encoder)), substitute: None }
+    }
+
+    /// Write `substitute` for characters the encoding cannot represent
+    /// instead of failing; the substitute itself must be representable
+    pub fn with_substitute(mut self, substitute: &str) -> Self {
+        self.substitute = Some(substitute.to_string());
+        self
+    }
+
+    /// Encoding name, as used in error messages
+    pub fn name(&self) -> &str {
+        match &self.charset {
+            Charset::Utf8 => "UTF-8",
+            Charset::Latin1 => "ISO-8859-1",
+            Charset::Custom(encoder) => encoder.name(),
+        }
+    }
+
+    /// Append `text` encoded to `out`
+    pub fn encode(&self, text: &str, out: &mut Vec<u8>) -> TemplateResult<()> {
+        if let Charset::Utf8 = self.charset {
+            out.extend_from_slice(text.as_bytes());
+            return Ok(());
+        }
+
+        out.reserve(text.len());
+        let (mut line, mut column) = (1, 1);
+        for ch in text.chars() {
+            if !self.encode_char(ch, out) {
+                let substituted = self.substitute.as_ref()
+                    .is_some_and(|substitute| substitute.chars().all(|ch| self.encode_char(ch, out)));
+                if !substituted {
+                    return Err(TemplateError::Encoding {
+                        encoding: self.name().to_string(),
+                        character: ch,
+                        line,
+                        column,
+                    });
+                }
+            }
+            if ch == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn encode_char(&self, ch: char, out: &mut Vec<u8>) -> bool {
+        match &self.charset {
+            Charset::Utf8 => {
+                out.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+                true
+            }
+            Charset::Latin1 => match u8::try_from(ch) {
+                Ok(byte) => {
+                    out.push(byte);
+                    true
+                }
+                Err(_) => false,
+            },
+            Charset::Custom(encoder) => encoder.encode_char(ch, out),
+        }
+    }
+}
@@ -21,31 +21,186 @@
 //! - **I18n Support**: Multi-language template rendering
 //! - **IDE Integration**: LSP support for development tools
 
-use crate::error::{TemplateError, TemplateResult};
+use crate::ast;
+use crate::error::{FrameKind, TemplateError, TemplateFrame, TemplateResult};
 use crate::context::TemplateContext;
 use crate::value::TemplateValue;
-use crate::utils::html_escape;
+use crate::utils::{html_escape, html_escape_into, minify_html, write_integer};
 use crate::bytecode::{CompiledTemplate, TemplateCompiler, BytecodeExecutor};
+#[cfg(feature = "inheritance")]
 use crate::layouts::LayoutProcessor;
+#[cfg(feature = "debug-tools")]
 use crate::debug::{DebugInfo, DebugRenderResult, ExecutionStep};
-use crate::suggestions::{suggest_templates, extract_context_lines, find_line_column};
+#[cfg(feature = "suggestions")]
+use crate::suggestions::suggest_templates;
+use crate::suggestions::{extract_context_lines, find_line_column};
+use crate::usage::{ContextUsage, scan_template};
+use crate::audit::{RawOutputSite, scan_raw_output, ESCAPE_FILTERS, HTML_PRODUCING_FILTERS};
+use crate::syntax::{
+    argument_type_problem, check_filter_arguments, filter_signature, parse_template, split_assertion, split_assignment, split_filter_arguments,
+    split_trim_markers, trim_after_marker, FilterArgument, BUILTIN_FILTERS,
+};
+use crate::precompiled::{PrecompiledInstruction, PrecompiledTemplate};
+use crate::render_diff::{RenderDiff, RenderSegment, RenderTrace, SegmentKind, SegmentScope};
+use crate::render_profile::{ProfileResult, ProfileSpan, SpanKind, merge_spans};
+use crate::sanitize::{SanitizerPolicy, sanitize_html, truncate_html};
+use crate::graphemes::{grapheme_count, grapheme_prefix, graphemes};
+use crate::cancellation::CancellationToken;
+use crate::manifest::{PublicTemplate, TemplateManifest, MANIFEST_FILE_NAME};
+use crate::project::{ProjectConfig, ProjectSymbol};
+use crate::provenance::{ProvenanceOptions, NO_PROVENANCE_TAG};
+use crate::raw_include::RawIncludeOptions;
+use crate::streaming::{FlushOn, FlushingWriter, StreamSink};
+use crate::coverage::{tag_end, CoverageKind, CoverageRecorder, CoverageReport};
+use crate::functions;
+use crate::include_trace::{IncludeRecorder, IncludeRelation, IncludeTree, LoadSource};
+use crate::encoding::OutputEncoding;
+use crate::pack::TemplatePack;
+use crate::security::path::{check_template_name, PathRule, PathViolation};
+#[cfg(feature = "lsp")]
+use crate::html_check::check_tag_balance;
+use crate::arguments::{parse_arguments, parse_value, Argument, ArgumentValue, Separator};
+use crate::redaction::{Redact, RedactionFunction, mask_placeholder, redact_value, HIDDEN_PLACEHOLDER};
+#[cfg(feature = "i18n")]
+use crate::i18n::{MissingTranslation, TranslationKeyUsage, TranslationMode, TranslationReport, TranslationSnapshot, TranslationStore, TranslationVersion, flatten_translation_catalog, pseudo_localize, resolve_translation_scopes, scan_translation_keys, split_key};
+use crate::roots::{TemplateRoot, ResolutionTrace, ResolutionCandidate, APP_ROOT_NAME, split_namespace};
+#[cfg(feature = "lsp")]
 use crate::lsp::{LspParseResult, TemplateBlock, CompletionItem, SyntaxToken, Diagnostic, HoverInfo, DefinitionInfo};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::fmt;
 use std::fs;
-use std::path::Path;
-use std::sync::Arc;
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::thread;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Opening delimiter of a visible comment (`{{#-- ... --}}`)
+const VISIBLE_COMMENT_OPEN: &str = "{{#--";
+
+/// Closing delimiter of a visible comment
+const VISIBLE_COMMENT_CLOSE: &str = "--}}";
+
+/// Prefix of `{{uid}}` and `{{uid()}}` ids
+const DEFAULT_UID_PREFIX: &str = "uid";
+
+/// Default maximum depth of nested `for`/`if`/`macro`/`block` directives
+const DEFAULT_MAX_NESTING_DEPTH: usize = 128;
+
+/// Default maximum depth of nested `{{include}}` directives
+const DEFAULT_MAX_INCLUDE_DEPTH: usize = 64;
+
+/// Default for [`TemplateEngine::set_max_range_items`]
+const DEFAULT_MAX_RANGE_ITEMS: usize = 1_000_000;
+
+/// Default for [`TemplateEngine::set_stream_buffer_size`]
+const DEFAULT_STREAM_BUFFER_SIZE: usize = 16;
+
+/// Output written between two cancellation checks of `render_with_cancel`
+const CANCEL_CHECK_BYTES: usize = 16 * 1024;
+
+/// Opens a deferred marker left in the output by `{{needs}}`,
+/// `{{required_assets}}`, `{{include_raw}}`, flush points, `{{try}}` blocks
+/// and scoped includes (private use characters, never valid template text);
+/// the marker key of the render starts its body, so values can't forge one
+const ASSET_MARKER_OPEN: char = '\u{E000}';
+
+/// Closes a deferred asset marker
+const ASSET_MARKER_CLOSE: char = '\u{E001}';
+
+/// Opens an error boundary (`{{try}}...{{rescue}}...{{/try}}`)
+const TRY_TAG: &str = "{{try}}";
+
+/// Opens a render-time assertion (`{{assert condition "message"}}`)
+const ASSERT_TAG: &str = "{{assert ";
+
+/// Opens an assignment (`{{set name = value}}`)
+const SET_TAG: &str = "{{set ";
+
+/// Opens a captured section (`{{capture name}}...{{/capture}}`)
+const CAPTURE_TAG: &str = "{{capture ";
+
+/// Closes a captured section
+const CAPTURE_END_TAG: &str = "{{/capture}}";
+
+/// Opens a negated conditional (`{{unless condition}}...{{/unless}}`)
+const UNLESS_TAG: &str = "{{unless ";
+
+/// Opens a change check (`{{ifchanged expression}}...{{/ifchanged}}`)
+const IFCHANGED_TAG: &str = "{{ifchanged ";
+
+/// Blocks whose closing tags the editor diagnostics match
+#[cfg(feature = "lsp")]
+const LSP_BLOCKS: [&str; 5] = ["if", "unless", "ifchanged", "for", "capture"];
+
+/// Form helper directives, each overridable by a component of the same name
+const FORM_HELPERS: [&str; 4] = ["input", "select", "checkbox", "field_errors"];
 
 /// Macro definition for reusable template components
 #[derive(Debug, Clone)]
+#[cfg(feature = "template-macros")]
 pub struct MacroDefinition {
-    #[allow(dead_code)]
     pub name: String,
     pub parameters: Vec<String>,
+    /// Parameter -> default expression from the header (`color="blue"`)
+    pub defaults: HashMap<String, String>,
     pub body: String,
 }
 
+#[cfg(feature = "template-macros")]
+impl MacroDefinition {
+    /// Header form of the macro, `button(text, color="blue")`
+    pub fn signature(&self) -> String {
+        let parameters: Vec<String> = self.parameters.iter()
+            .map(|param| match self.defaults.get(param) {
+                Some(default) => format!("{}={}", param, default),
+                None => param.clone(),
+            })
+            .collect();
+        format!("{}({})", self.name, parameters.join(", "))
+    }
+
+    /// Why a call with these arguments doesn't match the definition: too
+    /// many positional arguments, an unknown named one or a missing required one
+    fn call_problem(&self, args: &[Argument]) -> Option<String> {
+        let mut given = Vec::new();
+        let mut positional = 0;
+        for arg in args {
+            match arg.name {
+                Some(name) if !self.parameters.iter().any(|param| param == name) => {
+                    return Some(format!(
+                        "Macro '{}' has no parameter '{}'; expected {}", self.name, name, self.signature()
+                    ));
+                }
+                Some(name) => given.push(name),
+                None if positional >= self.parameters.len() => {
+                    return Some(format!(
+                        "Macro '{}' takes {} argument{} but got extra argument {}; expected {}",
+                        self.name, self.parameters.len(), if self.parameters.len() == 1 { "" } else { "s" },
+                        arg.text, self.signature()
+                    ));
+                }
+                None => {
+                    given.push(self.parameters[positional].as_str());
+                    positional += 1;
+                }
+            }
+        }
+
+        self.parameters.iter()
+            .find(|param| !self.defaults.contains_key(*param) && !given.contains(&param.as_str()))
+            .map(|param| format!(
+                "Macro '{}' is missing required argument '{}'; expected {}", self.name, param, self.signature()
+            ))
+    }
+}
+
 /// Custom helper function type
 pub type HelperFunction = Arc<dyn Fn(&[TemplateValue]) -> TemplateResult<TemplateValue> + Send + Sync>;
 
@@ -87,28 +242,164 @@ pub type FilterFunction = Arc<dyn Fn(&str, &[&str]) -> TemplateResult<String> +
 #[derive(Clone)]
 pub struct TemplateEngine {
     template_dir: String,
-    cache: HashMap<String, String>,
+    /// Template sources shared with `pack_templates`, loads and renders
+    cache: HashMap<String, Arc<str>>,
     bytecode_cache_enabled: bool,
     bytecode_cache: HashMap<String, CompiledTemplate>,
     compiler: TemplateCompiler,
     executor: BytecodeExecutor,
+    #[cfg(feature = "inheritance")]
     layout_processor: LayoutProcessor,
+    #[cfg(feature = "template-macros")]
     macros: HashMap<String, MacroDefinition>,
     helpers: HashMap<String, HelperFunction>,
     // i18n support
-    translations: HashMap<String, HashMap<String, String>>, // locale -> key -> translation
+    /// Versioned translations, shared with clones of the engine
+    #[cfg(feature = "i18n")]
+    translation_store: Arc<TranslationStore>,
+    /// Translations the current render reads, taken from the store when it starts
+    #[cfg(feature = "i18n")]
+    translations: Arc<TranslationSnapshot>,
+    #[cfg(feature = "i18n")]
     current_locale: Option<String>,
     // Custom filters
     custom_filters: HashMap<String, FilterFunction>,
+    /// Custom filters whose output is HTML, written without escaping
+    html_filters: HashSet<String>,
+    /// Filter pipelines as registered: name -> `strip|truncate:$1`
+    pipeline_definitions: HashMap<String, String>,
+    /// Filter pipelines expanded to built-in and custom filter steps
+    pipelines: HashMap<String, Vec<String>>,
+    /// Drop `{{#-- ... --}}` comments instead of emitting HTML comments
+    strip_visible_comments: bool,
+    /// Drop the newline after block tags
+    trim_blocks: bool,
+    /// Drop the indentation before block tags
+    lstrip_blocks: bool,
+    /// Collapse formatting whitespace and drop HTML comments from the output
+    minify_output: bool,
+    /// Maximum number of template sources kept in the cache (unbounded if `None`)
+    template_cache_capacity: Option<usize>,
+    /// Raise errors for invalid filter coercions instead of rendering empty
+    strict_mode: bool,
+    /// Raise errors for output tags naming variables missing from the context
+    strict_variables: bool,
+    /// Template names/prefixes that `{{include var}}` and `{{extends var}}` may resolve to
+    dynamic_include_patterns: Vec<String>,
+    /// How bare boolean values are rendered
+    bool_format: BoolFormat,
+    /// How bare arrays, objects, booleans and missing values are rendered
+    value_formatters: HashMap<ValueKind, ValueFormatter>,
+    /// Tags, attributes and URL schemes kept by the `sanitize` filter
+    sanitizer_policy: SanitizerPolicy,
+    /// Context paths read during `render_with_usage`
+    context_reads: Option<Arc<Mutex<ContextUsage>>>,
+    /// Maximum worker threads used by `render_many`
+    render_workers: usize,
+    /// Maximum directive nesting depth accepted when parsing
+    max_nesting_depth: usize,
+    /// Maximum depth of templates including each other
+    max_include_depth: usize,
+    /// Maximum number of integers of a `{{for i in 1..n}}` or `range()` range
+    max_range_items: usize,
+    /// Template rendered by name, the first link of the include chain;
+    /// empty for string templates and kept allocated between renders
+    include_root: String,
+    /// Templates being included, outermost first, to detect circular includes
+    include_chain: Vec<String>,
+    /// Output of `{{try}}` blocks and scoped includes, rendered before the
+    /// passes that follow them and put back by `write_source` in place of
+    /// their `~index` markers, so that it is never read as template source
+    rendered_parts: Vec<String>,
+    /// Random key starting the body of the deferred markers of the current
+    /// render; markers without it came from values and are left as text
+    marker_key: u64,
+    /// Asset name -> tags emitted by `{{required_assets}}`
+    assets: HashMap<String, AssetTags>,
+    /// Additional template roots and their priorities, in registration order
+    template_roots: Vec<(TemplateRoot, i32)>,
+    /// Cache key -> file the cached source was read from
+    resolved_paths: HashMap<String, PathBuf>,
+    /// Set during `render_offline`: templates missing from the cache fail instead of being read
+    offline: bool,
+    /// How context values appear in debug output, IDE info, render diffs and errors
+    redaction: RedactionFunction,
+    /// Set during `render_with_cancel`: checked at loop iterations, includes and while writing output
+    cancellation: Option<CancellationToken>,
+    /// Errors rescued by `{{try}}` blocks during `render_with_rescued_errors`
+    rescued_errors: Option<Arc<Mutex<Vec<TemplateError>>>>,
+    /// Component name -> template rendered by `render_component`
+    components: HashMap<String, String>,
+    /// Seed of `nonce()` ids; set while deterministic rendering is on
+    deterministic_seed: Option<u64>,
+    /// Time read by `now()` and `timeago` instead of the system clock
+    fixed_time: Option<SystemTime>,
+    /// Number of `nonce()` ids handed out by the current render
+    nonce_counter: Arc<AtomicU64>,
+    /// Prefix -> number of `uid()` ids handed out by the current render
+    uid_counters: Arc<Mutex<HashMap<String, u64>>>,
+    /// Set by `enable_coverage`: renders record the directives and bodies that ran
+    coverage_enabled: bool,
+    /// Coverage recorded since it was enabled, shared by clones of the engine
+    coverage: Arc<Mutex<CoverageRecorder>>,
+    /// Parsed `runic.manifest` and the stamp of the file it was read from
+    manifest: Option<(TemplateManifest, Option<FileStamp>)>,
+    /// When `render_public` last read each template that has a cache TTL
+    public_loaded_at: HashMap<String, Instant>,
+    /// `runic.toml` read by `load_project_config`
+    project_config: Option<ProjectConfig>,
+    /// Marker put atop named renders; taken while rendering a
+    /// `provenance=off` public template
+    provenance: Option<ProvenanceOptions>,
+    /// How `{{include_raw}}` files are read
+    raw_include_options: RawIncludeOptions,
+    /// Set during `render_to_writer`: large raw includes are left as markers
+    /// to stream, flush points are marked
+    streaming: bool,
+    /// Bytes `render_to_writer` writes between two flushes at most
+    stream_chunk_size: Option<usize>,
+    /// Where `render_to_writer` flushes besides the chunk size
+    stream_flush_on: FlushOn,
+    /// Chunks `render_stream` renders ahead of its consumer at most
+    stream_buffer_size: usize,
+    /// Whether output variables are HTML-escaped; off while rendering an
+    /// `autoescape=off` public template
+    autoescape: bool,
+    /// Templates loaded during `render_with_trace`
+    include_trace: Option<Arc<Mutex<IncludeRecorder>>>,
+    /// Parent replacing the rendered template's `{{extends}}` during
+    /// `render_with_layout`; `Some(None)` renders it without a parent
+    layout_override: Option<Option<String>>,
+    /// Template name -> source registered by `import_pack`, never read from disk
+    pack_templates: HashMap<String, Arc<str>>,
+    /// Failed `{{assert}}` directives are recorded instead of failing the render
+    soft_assertions: bool,
+    /// Assertions that failed in soft mode during `render_with_failed_assertions`
+    failed_assertions: Option<Arc<Mutex<Vec<TemplateError>>>>,
+    /// `{{assert ...}}` directive text, or any tag text with strict variables,
+    /// -> included template and location of its first use
+    tag_sites: HashMap<String, (Option<String>, usize, usize)>,
+    /// Rendered output is checked for unbalanced tags (debug builds only)
+    #[cfg(feature = "lsp")]
+    validate_html: bool,
+    /// Tag balance warnings of the last `render` or `render_string`
+    #[cfg(feature = "lsp")]
+    html_diagnostics: Vec<Diagnostic>,
+    /// Missing key markers or pseudo-localization for QA
+    #[cfg(feature = "i18n")]
+    translation_mode: TranslationMode,
+    /// Translation keys the current render did not find
+    #[cfg(feature = "i18n")]
+    missing_translations: Arc<Mutex<Vec<MissingTranslation>>>,
     
     // v0.4.0 Developer Experience features
     /// Debug mode enabled
     debug_enabled: bool,
     /// Hot reload enabled
     hot_reload_enabled: bool,
-    /// File modification times for hot reload
-    file_mtimes: HashMap<String, SystemTime>,
-    /// Template dependency tracking for hot reload
+    /// Modification time and size of each cached template's file, for hot reload
+    file_mtimes: HashMap<String, FileStamp>,
+    /// Template -> templates including or extending it by name, for hot reload
     template_dependencies: HashMap<String, Vec<String>>,
     
     // v0.5.0 Ecosystem Integration features
@@ -160,12 +451,79 @@ impl TemplateEngine {
             bytecode_cache: HashMap::new(),
             compiler: TemplateCompiler::new(),
             executor: BytecodeExecutor::new(),
+            #[cfg(feature = "inheritance")]
             layout_processor: LayoutProcessor::new(),
+            #[cfg(feature = "template-macros")]
             macros: HashMap::new(),
             helpers: HashMap::new(),
-            translations: HashMap::new(),
+            #[cfg(feature = "i18n")]
+            translation_store: Arc::new(TranslationStore::default()),
+            #[cfg(feature = "i18n")]
+            translations: Arc::new(TranslationSnapshot::default()),
+            #[cfg(feature = "i18n")]
             current_locale: None,
             custom_filters: HashMap::new(),
+            html_filters: HashSet::new(),
+            pipeline_definitions: HashMap::new(),
+            pipelines: HashMap::new(),
+            strip_visible_comments: false,
+            trim_blocks: false,
+            lstrip_blocks: false,
+            minify_output: false,
+            template_cache_capacity: None,
+            strict_mode: false,
+            strict_variables: false,
+            dynamic_include_patterns: Vec::new(),
+            bool_format: BoolFormat::TrueFalse,
+            value_formatters: HashMap::new(),
+            sanitizer_policy: SanitizerPolicy::default(),
+            context_reads: None,
+            render_workers: thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+            max_include_depth: DEFAULT_MAX_INCLUDE_DEPTH,
+            max_range_items: DEFAULT_MAX_RANGE_ITEMS,
+            include_root: String::new(),
+            include_chain: Vec::new(),
+            rendered_parts: Vec::new(),
+            marker_key: new_marker_key(),
+            assets: HashMap::new(),
+            template_roots: Vec::new(),
+            resolved_paths: HashMap::new(),
+            offline: false,
+            redaction: Arc::new(Redact::secret_names),
+            cancellation: None,
+            rescued_errors: None,
+            components: HashMap::new(),
+            deterministic_seed: None,
+            fixed_time: None,
+            nonce_counter: Arc::new(AtomicU64::new(0)),
+            uid_counters: Arc::new(Mutex::new(HashMap::new())),
+            coverage_enabled: false,
+            coverage: Arc::new(Mutex::new(CoverageRecorder::default())),
+            manifest: None,
+            public_loaded_at: HashMap::new(),
+            project_config: None,
+            provenance: None,
+            raw_include_options: RawIncludeOptions::default(),
+            streaming: false,
+            stream_chunk_size: None,
+            stream_flush_on: FlushOn::default(),
+            stream_buffer_size: DEFAULT_STREAM_BUFFER_SIZE,
+            autoescape: true,
+            include_trace: None,
+            layout_override: None,
+            pack_templates: HashMap::new(),
+            soft_assertions: false,
+            failed_assertions: None,
+            tag_sites: HashMap::new(),
+            #[cfg(feature = "lsp")]
+            validate_html: false,
+            #[cfg(feature = "lsp")]
+            html_diagnostics: Vec::new(),
+            #[cfg(feature = "i18n")]
+            translation_mode: TranslationMode::Normal,
+            #[cfg(feature = "i18n")]
+            missing_translations: Arc::new(Mutex::new(Vec::new())),
             // v0.4.0 Developer Experience features
             debug_enabled: false,
             hot_reload_enabled: false,
@@ -185,6 +543,12 @@ impl TemplateEngine {
     }
     
     /// Register a custom helper function
+    /// 
+    /// A helper that panics fails the render with [`TemplateError::HelperError`]
+    /// carrying the panic message, instead of unwinding through the engine.
+    /// The engine only lends the helper its arguments, so its own state stays
+    /// intact; state the helper captures must stay usable after a panic
+    /// (unwind safe), for instance a `Mutex` it doesn't hold while panicking.
     pub fn register_helper<F>(&mut self, name: &str, func: F)
     where
         F: Fn(&[TemplateValue]) -> TemplateResult<TemplateValue> + Send + Sync + 'static,
@@ -193,19 +557,49 @@ impl TemplateEngine {
     }
 
     /// Set translations for a specific locale
+    /// 
+    /// Values may interpolate variables with filters (`Hello {{name|capitalize}}`),
+    /// hold `{{! comments }}` and reference other keys with `{{t "key"}}`.
+    /// Includes, layouts, blocks, loops, conditionals, macros, helpers and raw
+    /// output are rejected with [`TemplateError::Security`] when rendered, and
+    /// keys referencing themselves fail with the key chain.
+    /// 
+    /// Clones of the engine share their translations; this is
+    /// [`swap_translations`](Self::swap_translations) without the version.
+    #[cfg(feature = "i18n")]
     pub fn set_translations(&mut self, locale: &str, translations: HashMap<String, String>) {
-        self.translations.insert(locale.to_string(), translations);
+        self.swap_translations(locale, translations);
+    }
+
+    /// Set translations for a specific locale from a nested catalog
+    ///
+    /// Nested objects are flattened to dotted keys, so
+    /// `{"nav": {"products": "Produits"}}` provides `nav.products`; numbers
+    /// and booleans are stored as text. Templates can look keys up relative
+    /// to a prefix with `{{t_scope "nav"}}{{t "products"}}{{/t_scope}}`.
+    ///
+    /// A key defined both as a flat dotted key and as a nested path
+    /// (`"nav.products"` next to `nav: { products }`), an array value or a
+    /// catalog that is not an object fails with [`TemplateError::Template`],
+    /// leaving the locale unchanged.
+    #[cfg(feature = "i18n")]
+    pub fn set_translations_nested(&mut self, locale: &str, catalog: TemplateValue) -> TemplateResult<()> {
+        let translations = flatten_translation_catalog(locale, &catalog)?;
+        self.set_translations(locale, translations);
+        Ok(())
     }
 
     /// Set the current locale for translations
+    #[cfg(feature = "i18n")]
     pub fn set_locale(&mut self, locale: &str) {
         self.current_locale = Some(locale.to_string());
     }
 
     /// Get translation for a key in the current locale
+    #[cfg(feature = "i18n")]
     pub fn get_translation(&self, key: &str) -> String {
         if let Some(ref locale) = self.current_locale {
-            if let Some(translations) = self.translations.get(locale) {
+            if let Some(translations) = self.translation_store.snapshot().get(locale) {
                 if let Some(translation) = translations.get(key) {
                     return translation.clone();
                 }
@@ -216,2530 +610,9734 @@ impl TemplateEngine {
     }
 
     /// Register a custom filter function
+    /// 
+    /// A filter that fails, returning an error or panicking, leaves its input
+    /// unchanged; in [strict mode](Self::enable_strict_mode) it fails the
+    /// render with the error, a panic becoming a [`TemplateError::FilterError`]
+    /// that carries the panic message. As with
+    /// [`register_helper`](Self::register_helper), state the filter captures
+    /// must stay usable after a panic.
     pub fn register_filter<F>(&mut self, name: &str, func: F)
     where
         F: Fn(&str, &[&str]) -> TemplateResult<String> + Send + Sync + 'static,
     {
+        self.html_filters.remove(name);
         self.custom_filters.insert(name.to_string(), Arc::new(func));
     }
+    
+    /// Register a custom filter whose output is HTML, written without
+    /// escaping like the output of `markdown`. Escaping is decided by the
+    /// last such filter, `safe`, or `escape` (alias `e`) of an output tag:
+    /// `{{bio|linkify|escape}}` escapes the HTML of `linkify` again.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// engine.register_html_filter("bold", |input, _args| Ok(format!("<b>{}</b>", input)));
+    /// 
+    /// let mut context = TemplateContext::new();
+    /// context.set_string("name", "Ada");
+    /// assert_eq!(engine.render_string("{{name|bold}} {{name|bold|e}}", &context)?, "<b>Ada</b> &lt;b&gt;Ada&lt;/b&gt;");
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn register_html_filter<F>(&mut self, name: &str, func: F)
+    where
+        F: Fn(&str, &[&str]) -> TemplateResult<String> + Send + Sync + 'static,
+    {
+        self.register_filter(name, func);
+        self.html_filters.insert(name.to_string());
+    }
+
+    /// Strip visible comments (`{{#-- ... --}}`) from the output instead of
+    /// rendering them as HTML comments. Useful for production builds.
+    pub fn set_strip_visible_comments(&mut self, strip: bool) {
+        self.strip_visible_comments = strip;
+    }
+
+    /// Remove the first newline after a block tag (`{{if}}`, `{{for}}`,
+    /// `{{block}}`, `{{macro}}`, `{{include}}`, `{{extends}}`, their closing
+    /// tags and `{{! comments }}`), so block bodies can start and end on their
+    /// own lines without adding blank lines to the output. Off by default.
+    /// 
+    /// Applies to template files as they are loaded and to template strings,
+    /// in both the interpreter and the bytecode compiler. For a single tag of
+    /// any kind, `{{- tag}}` trims the whitespace before it and `{{tag -}}`
+    /// the whitespace after it up to and including one newline, whatever
+    /// this setting.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// engine.set_trim_blocks(true);
+    /// engine.set_lstrip_blocks(true);
+    /// 
+    /// let mut context = TemplateContext::new();
+    /// context.set_bool("admin", true);
+    /// let template = "<ul>\n    {{if admin}}\n    <li>Admin</li>\n    {{/if}}\n</ul>";
+    /// assert_eq!(engine.render_string(template, &context)?, "<ul>\n    <li>Admin</li>\n</ul>");
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn set_trim_blocks(&mut self, enabled: bool) {
+        if self.trim_blocks != enabled {
+            self.trim_blocks = enabled;
+            self.forget_template_sources();
+        }
+    }
+    
+    /// Check if the newline after block tags is removed
+    pub fn is_trim_blocks_enabled(&self) -> bool {
+        self.trim_blocks
+    }
+    
+    /// Remove the spaces and tabs between the start of a line and a block
+    /// tag, see [`set_trim_blocks`](Self::set_trim_blocks). Off by default.
+    pub fn set_lstrip_blocks(&mut self, enabled: bool) {
+        if self.lstrip_blocks != enabled {
+            self.lstrip_blocks = enabled;
+            self.forget_template_sources();
+        }
+    }
+    
+    /// Check if the indentation before block tags is removed
+    pub fn is_lstrip_blocks_enabled(&self) -> bool {
+        self.lstrip_blocks
+    }
+    
+    /// Template sources as loaded with the current block whitespace settings
+    fn trim_block_whitespace(&self, source: String) -> String {
+        match trim_block_tags(&source, self.trim_blocks, self.lstrip_blocks) {
+            Cow::Borrowed(_) => source,
+            Cow::Owned(trimmed) => trimmed,
+        }
+    }
+    
+    /// Drop every cached source, parsed layout and bytecode after a change
+    /// to how sources are loaded
+    fn forget_template_sources(&mut self) {
+        self.cache.clear();
+        self.bytecode_cache.clear();
+        #[cfg(feature = "inheritance")]
+        self.layout_processor.templates.clear();
+    }
+    
+    /// Check if visible comments are stripped from the output
+    pub fn is_strip_visible_comments_enabled(&self) -> bool {
+        self.strip_visible_comments
+    }
+    
+    /// Minify the rendered output: whitespace between tags is removed, other
+    /// whitespace runs collapse to a single space and HTML comments (including
+    /// visible comments) are dropped. `<pre>`, `<textarea>`, `<script>` and
+    /// `<style>` contents are left untouched.
+    pub fn set_minify_output(&mut self, minify: bool) {
+        self.minify_output = minify;
+    }
+    
+    /// Check if the rendered output is minified
+    pub fn is_minify_output_enabled(&self) -> bool {
+        self.minify_output
+    }
+    
+    /// Limit how many template sources are cached; `None` (the default) keeps
+    /// every loaded template. When the cache is full an arbitrary entry is evicted.
+    pub fn set_template_cache_capacity(&mut self, capacity: Option<usize>) {
+        self.template_cache_capacity = capacity;
+        if let Some(capacity) = capacity {
+            while self.cache.len() > capacity {
+                self.evict_cached_template();
+            }
+        }
+    }
+    
+    /// Get the template cache capacity
+    pub fn get_template_cache_capacity(&self) -> Option<usize> {
+        self.template_cache_capacity
+    }
+    
+    /// Store a template source, respecting the cache capacity
+    fn cache_template(&mut self, name: &str, content: &Arc<str>) {
+        match self.template_cache_capacity {
+            Some(0) => return,
+            Some(capacity) if !self.cache.contains_key(name) => {
+                while self.cache.len() >= capacity {
+                    self.evict_cached_template();
+                }
+            }
+            _ => {}
+        }
+        self.cache.insert(name.to_string(), Arc::clone(content));
+    }
+    
+    /// Bytes of the distinct sources held by the cache and packs
+    fn template_source_bytes(&self) -> usize {
+        let mut seen = HashSet::new();
+        self.cache.values().chain(self.pack_templates.values())
+            .filter(|source| seen.insert(Arc::as_ptr(source) as *const u8))
+            .map(|source| source.len())
+            .sum()
+    }
+    
+    fn evict_cached_template(&mut self) {
+        if let Some(name) = self.cache.keys().next().cloned() {
+            self.cache.remove(&name);
+        }
+    }
+    
+    /// Enable strict mode: invalid coercions (e.g. `{{"abc"|int}}`) raise
+    /// [`TemplateError::FilterError`] instead of rendering an empty string, and
+    /// arrays or objects emitted without a [`ValueFormatter`] raise
+    /// [`TemplateError::Render`]. Macro calls with extra arguments, unknown
+    /// named parameters or missing required ones, and built-in filters given
+    /// the wrong number of arguments or a literal of the wrong type
+    /// (`{{n|round:"abc"}}`), raise a located [`TemplateError::ParseWithLocation`];
+    /// lenient mode reports them as warning diagnostics and renders anyway.
+    /// Variable filter arguments (`{{title|truncate:limit}}`) are checked when
+    /// rendering and raise [`TemplateError::FilterError`].
+    pub fn enable_strict_mode(&mut self) {
+        self.strict_mode = true;
+    }
+    
+    /// Disable strict mode (lenient rendering, the default)
+    pub fn disable_strict_mode(&mut self) {
+        self.strict_mode = false;
+    }
+    
+    /// Check if strict mode is enabled
+    pub fn is_strict_mode_enabled(&self) -> bool {
+        self.strict_mode
+    }
+    
+    /// Fail renders that output a variable missing from the context.
+    /// 
+    /// With strict variables, `{{user.nickname}}` raises a [`TemplateError::Render`]
+    /// naming the variable and the line and column of its tag when `user` or
+    /// its `nickname` doesn't exist, instead of rendering `""`. A `default`
+    /// filter in the chain
+    /// (`{{user.nickname|default:"Anonymous"}}`) supplies the value instead;
+    /// conditions such as `{{if user.nickname}}` still read missing
+    /// variables as empty. Bytecode renders are not checked. Off by default.
+    pub fn set_strict_variables(&mut self, enabled: bool) {
+        self.strict_variables = enabled;
+    }
+    
+    /// Check if missing variables fail the render
+    pub fn is_strict_variables_enabled(&self) -> bool {
+        self.strict_variables
+    }
+    
+    /// Choose how bare boolean values such as `{{active}}` are rendered.
+    /// 
+    /// Only the output is affected; conditions and comparisons still see the
+    /// boolean itself.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, TemplateContext, BoolFormat};
+    /// 
+    /// let mut engine = TemplateEngine::new(".");
+    /// engine.set_bool_format(BoolFormat::YesNo);
+    /// let mut context = TemplateContext::new();
+    /// context.set_bool("active", true);
+    /// assert_eq!(engine.render_string("{{active}}", &context)?, "Yes");
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn set_bool_format(&mut self, format: BoolFormat) {
+        self.bool_format = format;
+    }
+    
+    /// Get the current boolean display format
+    pub fn get_bool_format(&self) -> &BoolFormat {
+        &self.bool_format
+    }
+    
+    /// Choose how bare values of a kind, such as `{{tags}}` for an array, are
+    /// rendered.
+    /// 
+    /// Without a formatter arrays, objects and missing values render as an
+    /// empty string and booleans follow [`set_bool_format`](Self::set_bool_format).
+    /// Formatters apply to variable output, to text filters given an array or
+    /// object (`{{tags|upper}}`) and to helper results. In strict mode, an
+    /// array or object emitted without a formatter is an error.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, TemplateContext, TemplateValue, ValueFormatter, ValueKind};
+    /// 
+    /// let mut engine = TemplateEngine::new(".");
+    /// engine.set_value_formatter(ValueKind::Array, ValueFormatter::Join(", ".to_string()));
+    /// let mut context = TemplateContext::new();
+    /// context.set("tags", TemplateValue::Array(vec![
+    ///     TemplateValue::String("rust".to_string()),
+    ///     TemplateValue::String("web".to_string()),
+    /// ]));
+    /// assert_eq!(engine.render_string("{{tags}}", &context)?, "rust, web");
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn set_value_formatter(&mut self, kind: ValueKind, formatter: impl Into<ValueFormatter>) {
+        self.value_formatters.insert(kind, formatter.into());
+    }
+    
+    /// Remove the formatter of a kind, restoring the default rendering
+    pub fn clear_value_formatter(&mut self, kind: ValueKind) {
+        self.value_formatters.remove(&kind);
+    }
+    
+    /// Get the formatter registered for a kind
+    pub fn get_value_formatter(&self, kind: ValueKind) -> Option<&ValueFormatter> {
+        self.value_formatters.get(&kind)
+    }
+    
+    /// Set how context values appear outside the rendered output
+    /// 
+    /// The policy gets the key path (`user.email`, `items.0`) and value of
+    /// every context value surfaced by debug renders, hover and completion
+    /// info, render diffs and filter errors. Values of objects and arrays
+    /// are checked entry by entry. Rendering is unaffected. The default is
+    /// [`Redact::secret_names`], which masks passwords, tokens, keys and
+    /// secrets; `|_, _| Redact::Show` turns redaction off.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{Redact, TemplateEngine, TemplateContext};
+    /// 
+    /// let mut engine = TemplateEngine::new(".");
+    /// engine.set_redaction(|path, value| match path {
+    ///     "user.email" => Redact::Hide,
+    ///     _ => Redact::secret_names(path, value),
+    /// });
+    /// 
+    /// let mut context = TemplateContext::new();
+    /// context.set_string("api_token", "sk-123");
+    /// # #[cfg(feature = "lsp")]
+    /// # {
+    /// let hover = engine.get_hover_info_at_position("{{api_token}}", 4, &context)?;
+    /// assert_eq!(hover.current_value, "•••• (string, 6 chars)");
+    /// # }
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn set_redaction<F>(&mut self, policy: F)
+    where
+        F: Fn(&str, &TemplateValue) -> Redact + Send + Sync + 'static,
+    {
+        self.redaction = Arc::new(policy);
+    }
+    
+    /// Text of the value at `path` as the redaction policy lets it appear
+    /// outside the output; `show` formats the (possibly masked) value
+    fn surfaced_value(&self, path: &str, value: &TemplateValue, show: impl FnOnce(&TemplateValue) -> String) -> String {
+        match redact_value(&self.redaction, path, value) {
+            Some(value) => show(&value),
+            None => HIDDEN_PLACEHOLDER.to_string(),
+        }
+    }
+    
+    /// Allow dynamic includes (`{{include page.partial}}`) and dynamic
+    /// inheritance (`{{extends page.layout}}`) to resolve to the given templates.
+    /// 
+    /// Each entry is a template name prefix (`"partials/"`) or a `*` glob
+    /// (`"cards/*.html"`). Dynamic names are rejected with
+    /// [`TemplateError::Security`] unless they match an entry, so user data
+    /// can never pull in arbitrary files. Quoted includes are not affected.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::TemplateEngine;
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// engine.allow_dynamic_includes(["partials/", "layouts/*.html"]);
+    /// ```
+    pub fn allow_dynamic_includes<I, S>(&mut self, patterns: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.dynamic_include_patterns
+            .extend(patterns.into_iter().map(|pattern| pattern.as_ref().to_string()));
+    }
 
     /// Load and cache a template
     pub fn load_template(&mut self, name: &str) -> TemplateResult<String> {
-        if let Some(cached) = self.cache.get(name) {
-            return Ok(cached.clone());
+        self.load_template_arc(name).map(|source| source.to_string())
+    }
+    
+    /// Load and cache a template, sharing the cached source: loading a
+    /// cached or pack template again only bumps a reference count
+    pub fn load_template_arc(&mut self, name: &str) -> TemplateResult<Arc<str>> {
+        self.load_template_from_root(name, None)
+    }
+    
+    /// Load a template, optionally only from the root named `from`
+    fn load_template_from_root(&mut self, name: &str, from: Option<&str>) -> TemplateResult<Arc<str>> {
+        let started = Instant::now();
+        if from.is_none() {
+            if let Some(source) = self.pack_templates.get(name) {
+                let source = Arc::clone(source);
+                self.trace_includes(|trace| trace.loaded(name, LoadSource::Registered, elapsed_nanos(started)));
+                return Ok(source);
+            }
+        }
+        let cache_key = match from {
+            Some(root) => Cow::Owned(format!("{} from {}", name, root)),
+            None => Cow::Borrowed(name),
+        };
+        
+        // Creating or removing a shadowing file changes which file wins
+        if self.hot_reload_enabled && !self.offline && self.cache.contains_key(cache_key.as_ref()) {
+            let path = self.resolve_template_path(name, from)?;
+            if self.resolved_paths.get(cache_key.as_ref()) != Some(&path) {
+                self.cache.remove(cache_key.as_ref());
+                self.bytecode_cache.remove(cache_key.as_ref());
+            }
+        }
+        
+        if let Some(cached) = self.cache.get(cache_key.as_ref()) {
+            let cached = Arc::clone(cached);
+            self.trace_includes(|trace| trace.loaded(name, LoadSource::Cache, elapsed_nanos(started)));
+            return Ok(cached);
+        }
+        if self.offline {
+            return Err(TemplateError::OfflineMiss { name: cache_key.into_owned() });
         }
 
         // Validate template path to prevent path traversal attacks
         self.validate_template_path(name)?;
 
-        let path = Path::new(&self.template_dir).join(name);
+        let path = self.resolve_template_path(name, from)?;
+        // Stamped before reading: a write racing the read is seen by the next reload
+        let stamp = file_stamp(&path);
         let content = fs::read_to_string(&path)
             .map_err(|e| TemplateError::Template(format!("Failed to read template '{}': {}", name, e)))?;
+        let content: Arc<str> = self.trim_block_whitespace(content).into();
 
-        self.cache.insert(name.to_string(), content.clone());
+        if let Some(stamp) = stamp {
+            self.file_mtimes.insert(cache_key.to_string(), stamp);
+        }
+        self.record_dependencies(&cache_key, &content);
+        self.resolved_paths.insert(cache_key.to_string(), path);
+        self.cache_template(&cache_key, &content);
+        self.trace_includes(|trace| trace.loaded(name, LoadSource::Disk, elapsed_nanos(started)));
         Ok(content)
     }
 
+    /// Check if a template's source is held in the template cache
+    pub fn is_template_cached(&self, name: &str) -> bool {
+        self.cache.contains_key(name)
+    }
+    
+    /// Number of template sources held in the template cache
+    pub(crate) fn cached_template_count(&self) -> usize {
+        self.cache.len()
+    }
+
     /// Render a template with context
     pub fn render(&mut self, template_name: &str, context: &TemplateContext) -> TemplateResult<String> {
-        let template = self.load_template(template_name)?;
+        let mut output = String::new();
+        self.render_into(template_name, context, &mut output)?;
+        Ok(output)
+    }
+    
+    /// Render a template into a caller-provided buffer.
+    /// 
+    /// The buffer is cleared first and its capacity is kept, so rendering the
+    /// same template repeatedly into one buffer avoids reallocating the output.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// let context = TemplateContext::new();
+    /// let mut buffer = String::with_capacity(4096);
+    /// for _ in 0..100 {
+    ///     engine.render_into("page.html", &context, &mut buffer)?;
+    /// }
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn render_into(&mut self, template_name: &str, context: &TemplateContext, buf: &mut String) -> TemplateResult<()> {
+        self.render_template_into(template_name, None, context, buf, None)
+    }
+    
+    /// Render a template into `buf`; with a `sink`, the finished parts of a
+    /// streamable render go to it as they are produced and `buf` keeps the rest.
+    /// `prepared` is the source of the template with its layouts already
+    /// resolved, as `render_many` shares it across contexts.
+    fn render_template_into(&mut self, template_name: &str, prepared: Option<&Arc<str>>, context: &TemplateContext, buf: &mut String, sink: Option<&mut StreamSink<'_>>) -> TemplateResult<()> {
+        self.start_render();
+        let layout_override = self.layout_override.clone();
+        // Only a render nested in another one takes the buffer of the root name
+        let previous_root = (!self.include_root.is_empty()).then(|| std::mem::take(&mut self.include_root));
+        self.include_root.clear();
+        self.include_root.push_str(template_name);
+        let rendered = prepared.map_or_else(|| self.prepare_template_source(template_name, Some(context)), |source| Ok(Arc::clone(source)))
+            .and_then(|final_template| {
+                self.render_source_into(&final_template, context, buf, sink)?;
+                self.resolve_raw_includes(buf)?;
+                Ok(final_template.contains(NO_PROVENANCE_TAG))
+            });
+        match previous_root {
+            Some(root) => self.include_root = root,
+            None => self.include_root.clear(),
+        }
+        let opted_out = match rendered {
+            Ok(opted_out) => opted_out,
+            Err(error) => {
+                self.layout_override = layout_override;
+                let error = self.with_template_backtrace(template_name, context, error);
+                self.layout_override = None;
+                return Err(error);
+            }
+        };
+        if self.coverage_enabled {
+            self.record_coverage(template_name, context);
+        }
+        #[cfg(feature = "lsp")]
+        self.check_rendered_html(|engine| engine.load_template_arc(template_name), buf, context);
+        if let Some(provenance) = self.provenance.as_ref().filter(|_| !opted_out) {
+            provenance.insert_marker(template_name, self.unix_now(), buf);
+        }
+        Ok(())
+    }
+    
+    /// Load a template and resolve its layout inheritance into a single source
+    #[cfg(feature = "inheritance")]
+    fn prepare_template_source(&mut self, template_name: &str, context: Option<&TemplateContext>) -> TemplateResult<Arc<str>> {
+        let template = self.load_template_arc(template_name)?;
         
         // Parse template for layout information
-        self.layout_processor.parse_template(template_name, &template)?;
+        let started = Instant::now();
+        self.layout_processor.parse_template(template_name, Arc::clone(&template))?;
+        self.trace_includes(|trace| trace.parsed(template_name, elapsed_nanos(started)));
+        
+        // Only the rendered template takes the layout of `render_with_layout`
+        let layout_override = self.layout_override.take();
+        if let (Some(parent), Some(layout)) = (&layout_override, self.layout_processor.templates.get_mut(template_name)) {
+            layout.extends = parent.clone();
+            layout.extends_variable = None;
+        }
         
         // Load and parse parent templates if needed
-        self.load_parent_templates(template_name)?;
-        
-        // Check if template has inheritance
-        let final_template = if self.has_layout_inheritance(template_name) {
+        self.load_parent_templates(template_name, context)?;
+        
+        // Check if template has inheritance (or block parameters to bind);
+        // without a parent an overridden template renders its own blocks
+        if self.has_layout_inheritance(template_name)
+            || self.layout_processor.has_block_parameters(template_name)
+            || layout_override.is_some()
+        {
             // Resolve inheritance chain and merge blocks
-            self.layout_processor.resolve_inheritance(template_name)?
+            self.layout_processor.resolve_inheritance(template_name).map(Arc::from)
         } else {
-            template
-        };
-        
-        self.render_string(&final_template, context)
+            Ok(template)
+        }
+    }
+    
+    /// Without the `inheritance` feature layouts and blocks fail
+    #[cfg(not(feature = "inheritance"))]
+    fn prepare_template_source(&mut self, template_name: &str, _context: Option<&TemplateContext>) -> TemplateResult<Arc<str>> {
+        if let Some(Some(_)) = self.layout_override.take() {
+            return Err(feature_disabled("render_with_layout", "inheritance"));
+        }
+        let template = self.load_template_arc(template_name)?;
+        for directive in ["extends", "block"] {
+            if template.contains(&format!("{{{{{} ", directive)) {
+                return Err(feature_disabled(&format!("{{{{{}}}}}", directive), "inheritance"));
+            }
+        }
+        Ok(template)
     }
     
     /// Check if template uses layout inheritance
+    #[cfg(feature = "inheritance")]
     fn has_layout_inheritance(&self, template_name: &str) -> bool {
         self.layout_processor.templates.get(template_name)
             .map(|layout| layout.extends.is_some())
             .unwrap_or(false)
     }
     
-    /// Load and parse parent templates recursively
-    fn load_parent_templates(&mut self, template_name: &str) -> TemplateResult<()> {
-        if let Some(layout) = self.layout_processor.templates.get(template_name).cloned() {
-            if let Some(parent_name) = layout.extends {
-                // Load parent template if not already loaded
-                if !self.layout_processor.templates.contains_key(&parent_name) {
-                    let parent_content = self.load_template(&parent_name)?;
-                    self.layout_processor.parse_template(&parent_name, &parent_content)?;
-                }
-                
-                // Recursively load grandparent templates
-                self.load_parent_templates(&parent_name)?;
-            }
+    /// Load and parse the chain of parent templates.
+    /// 
+    /// Dynamic parents (`{{extends var}}`) are resolved against `context`;
+    /// without a context they are left unresolved.
+    #[cfg(feature = "inheritance")]
+    fn load_parent_templates(&mut self, template_name: &str, context: Option<&TemplateContext>) -> TemplateResult<()> {
+        let extends_nothing = self.layout_processor.templates.get(template_name)
+            .map_or(true, |layout| layout.extends.is_none() && layout.extends_variable.is_none());
+        if extends_nothing {
+            return Ok(());
         }
-        Ok(())
-    }
-
-    /// Render a template string with context
-    pub fn render_string(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
-        let mut result = template.to_string();
-        
-        // Process macros first (extract definitions and process calls with context)
-        result = self.process_macros_with_context(&result, context)?;
-        
-        // Process includes 
-        result = self.process_includes(&result)?;
-        
-        // Process conditionals
-        result = self.process_conditionals(&result, context)?;
-        
-        // Process loops
-        result = self.process_loops(&result, context)?;
-        
-        // Process translations
-        result = self.process_translations(&result, context)?;
-        
-        // Process pluralization
-        result = self.process_pluralization(&result, context)?;
-        
-        // Process variables
-        result = self.process_variables(&result, context)?;
-        
-        // Remove comments
-        result = self.process_comments(&result);
+        let mut current = template_name.to_string();
+        let mut chain = HashSet::new();
         
-        Ok(result)
+        // Each parent nests under the template extending it in a traced render
+        let result = self.load_parent_chain(&mut current, &mut chain, context);
+        for _ in 1..chain.len() {
+            self.trace_includes(IncludeRecorder::close);
+        }
+        result
     }
-
-    /// Process include directives recursively
-    fn process_includes(&mut self, template: &str) -> TemplateResult<String> {
-        let mut result = template.to_string();
-        
-        while let Some(start) = result.find("{{include ") {
-            let end = result[start..].find("}}")
-                .ok_or_else(|| TemplateError::Parse("Unclosed include directive".to_string()))?;
+    
+    /// Walk the `{{extends}}` chain from `current`, loading unparsed parents
+    #[cfg(feature = "inheritance")]
+    fn load_parent_chain(&mut self, current: &mut String, chain: &mut HashSet<String>, context: Option<&TemplateContext>) -> TemplateResult<()> {
+        loop {
+            if !chain.insert(current.clone()) {
+                return Err(TemplateError::Template(format!(
+                    "Circular template inheritance: '{}' extends itself through its parents", current
+                )));
+            }
             
-            let directive = &result[start + 10..start + end];
-            let include_name = directive.trim().trim_matches('"').trim_matches('\'');
+            if let Some(context) = context {
+                self.resolve_dynamic_extends(current, context)?;
+            }
             
-            let included_content = self.load_template(include_name)?;
+            let Some(parent_name) = self.layout_processor.templates.get(current.as_str())
+                .and_then(|layout| layout.extends.clone()) else {
+                return Ok(());
+            };
             
-            // Process includes recursively within the included template
-            let processed_included_content = self.process_includes(&included_content)?;
+            // Parents chosen at render time are dependencies too, for hot reload
+            self.record_dependency(&parent_name, current);
             
-            result.replace_range(start..start + end + 2, &processed_included_content);
+            // Load parent template if not already loaded
+            self.trace_includes(|trace| trace.open(&parent_name, IncludeRelation::Extends));
+            if !self.layout_processor.templates.contains_key(&parent_name) {
+                let parent_content = self.load_template_arc(&parent_name)?;
+                let started = Instant::now();
+                self.layout_processor.parse_template(&parent_name, parent_content)?;
+                self.trace_includes(|trace| trace.parsed(&parent_name, elapsed_nanos(started)));
+            } else {
+                self.trace_includes(|trace| trace.loaded(&parent_name, LoadSource::Cache, 0));
+            }
+            
+            *current = parent_name;
         }
-        
-        Ok(result)
     }
-
-    /// Process conditional blocks
-    fn process_conditionals(&self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
-        let mut result = template.to_string();
-        
-        while let Some(if_start) = result.find("{{if ") {
-            let if_end = result[if_start..].find("}}")
-                .ok_or_else(|| TemplateError::Parse("Unclosed if directive".to_string()))?;
-            
-            let condition = &result[if_start + 5..if_start + if_end].trim();
-            
-            let block_start = if_start + if_end + 2;
-            let block_end = result[block_start..].find("{{/if}}")
-                .ok_or_else(|| TemplateError::Parse("Missing {{/if}} directive".to_string()))?;
-            
-            let block_content = result[block_start..block_start + block_end].to_string();
-            
-            let should_include = self.evaluate_condition(condition, context);
-            let replacement = if should_include { &block_content } else { "" };
-            
-            result.replace_range(if_start..block_start + block_end + 7, replacement);
+    
+    /// Point a `{{extends var}}` template at the parent named by the context
+    #[cfg(feature = "inheritance")]
+    fn resolve_dynamic_extends(&mut self, template_name: &str, context: &TemplateContext) -> TemplateResult<()> {
+        let Some(variable) = self.layout_processor.templates.get(template_name)
+            .and_then(|layout| layout.extends_variable.clone()) else {
+            return Ok(());
+        };
+        
+        let root = variable.split('.').next().unwrap_or_default();
+        if context.get(root).is_none() {
+            return Err(TemplateError::Template(format!(
+                "Dynamic extends '{}' could not be resolved: variable '{}' is not set", variable, root
+            )));
         }
         
-        Ok(result)
+        let parent_name = self.resolve_dynamic_template_name(&variable, context)?;
+        if let Some(layout) = self.layout_processor.templates.get_mut(template_name) {
+            layout.extends = Some(parent_name);
+        }
+        Ok(())
     }
-
-    /// Process loop blocks
-    fn process_loops(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
-        let mut result = template.to_string();
-        
-        while let Some(for_start) = result.find("{{for ") {
-            let for_end = result[for_start..].find("}}")
-                .ok_or_else(|| TemplateError::Parse("Unclosed for directive".to_string()))?;
-            
-            let loop_def = &result[for_start + 6..for_start + for_end].trim();
-            let parts: Vec<&str> = loop_def.split(" in ").collect();
-            
-            if parts.len() != 2 {
-                return Err(TemplateError::Parse("Invalid for loop syntax".to_string()));
-            }
-            
-            let item_var = parts[0].trim();
-            let array_var = parts[1].trim();
-            
-            let block_start = for_start + for_end + 2;
-            
-            // Find matching {{/for}} using stack-based parsing to handle nested loops
-            let block_end = self.find_matching_for_end(&result[block_start..])?;
-            
-            let block_content = &result[block_start..block_start + block_end];
-            
-            let replacement = self.render_loop(item_var, array_var, block_content, context)?;
-            
-            result.replace_range(for_start..block_start + block_end + 8, &replacement);
+    
+    /// Resolve a dynamic template reference and check it against the allow-list
+    fn resolve_dynamic_template_name(&self, variable: &str, context: &TemplateContext) -> TemplateResult<String> {
+        let name = filter_value_to_string(&self.resolve_variable_from_context(variable, context));
+        
+        let allowed = self.dynamic_include_patterns.iter()
+            .any(|pattern| template_matches_pattern(&name, pattern));
+        if !allowed {
+            return Err(TemplateError::Security(format!(
+                "Dynamic template '{}' (from '{}') is not allowed by allow_dynamic_includes", name, variable
+            )));
         }
         
-        Ok(result)
+        Ok(name)
     }
 
-    /// Process variable substitutions
-    fn process_variables(&self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
-        let mut result = template.to_string();
+    /// Render a template string with context
+    pub fn render_string(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
+        let mut output = String::new();
+        self.render_string_into(template, context, &mut output)?;
+        Ok(output)
+    }
+    
+    /// Render a template string into a caller-provided buffer.
+    /// 
+    /// The buffer is cleared first and its capacity is kept. Stages whose
+    /// directives don't appear in the template are skipped without copying it,
+    /// and the final pass writes straight into `buf`.
+    pub fn render_string_into(&mut self, template: &str, context: &TemplateContext, buf: &mut String) -> TemplateResult<()> {
+        self.render_string_template_into(template, context, buf, None)
+    }
+    
+    /// Render a template string into `buf`, streaming to `sink` like
+    /// [`render_template_into`](Self::render_template_into)
+    fn render_string_template_into(&mut self, template: &str, context: &TemplateContext, buf: &mut String, sink: Option<&mut StreamSink<'_>>) -> TemplateResult<()> {
+        self.start_render();
+        let trimmed = trim_block_tags(template, self.trim_blocks, self.lstrip_blocks);
+        self.render_source_into(&trimmed, context, buf, sink)?;
+        self.resolve_raw_includes(buf)?;
+        #[cfg(feature = "lsp")]
+        self.check_rendered_html(|_| Ok(trimmed.as_ref().into()), buf, context);
+        Ok(())
+    }
+    
+    /// Render template source whose block whitespace is already trimmed
+    fn render_source_into(&mut self, template: &str, context: &TemplateContext, buf: &mut String, sink: Option<&mut StreamSink<'_>>) -> TemplateResult<()> {
+        buf.clear();
+        self.check_nesting_depth(template)?;
+        check_super_placement(template)?;
+        if self.strict_mode {
+            if let Some((offset, message)) = filter_argument_problems(template).into_iter().next() {
+                let (line, column) = find_line_column(template, offset);
+                return Err(TemplateError::ParseWithLocation {
+                    message,
+                    line,
+                    column,
+                    template_name: None,
+                    context_lines: extract_context_lines(template, line, 2),
+                });
+            }
+        }
+        if !self.tag_sites.is_empty() {
+            self.tag_sites.clear();
+        }
+        self.record_tag_sites(template, None);
+        self.write_source(template, context, buf, sink)?;
+        self.finish_output(buf)
+    }
+    
+    /// Run the render stages over `template`, appending the output to `out`;
+    /// a `sink` takes what `out` holds after every top-level loop iteration
+    fn write_source(&mut self, template: &str, context: &TemplateContext, out: &mut String, sink: Option<&mut StreamSink<'_>>) -> TemplateResult<()> {
+        let parts = self.rendered_parts.len();
+        // Streamed output is put back as it is drained, and `out` with it
+        let from = if sink.is_some() { 0 } else { out.len() };
+        let written = self.write_source_passes(template, context, out, sink);
+        if written.is_ok() {
+            self.put_back_rendered_parts(out, from);
+        }
+        self.rendered_parts.truncate(parts);
+        written
+    }
+    
+    /// The passes of [`write_source`](Self::write_source), which leaves the
+    /// output of `{{try}}` blocks and scoped includes as markers
+    fn write_source_passes(&mut self, template: &str, context: &TemplateContext, out: &mut String, sink: Option<&mut StreamSink<'_>>) -> TemplateResult<()> {
+        let mut result = Cow::Borrowed(template);
+        
+        // Prefix the keys of scoped translations while the scopes are still lexical
+        if result.contains("{{t_scope") {
+            #[cfg(feature = "i18n")]
+            {
+                result = Cow::Owned(resolve_translation_scopes(&result)?);
+            }
+            #[cfg(not(feature = "i18n"))]
+            return Err(feature_disabled("{{t_scope}}", "i18n"));
+        }
         
-        // Process raw variables {{& variable}}
-        while let Some(start) = result.find("{{& ") {
-            let end = result[start..].find("}}")
-                .ok_or_else(|| TemplateError::Parse("Unclosed variable directive".to_string()))?;
-            
-            let var_name = &result[start + 4..start + end].trim();
-            let value = self.get_variable_value(var_name, context);
-            
-            result.replace_range(start..start + end + 2, &value);
+        // Process macros first (extract definitions and process calls with context)
+        #[cfg(feature = "template-macros")]
+        if result.contains("{{macro ") || self.has_macros() {
+            result = Cow::Owned(self.process_macros_with_context(&result, context)?);
+        }
+        #[cfg(not(feature = "template-macros"))]
+        if result.contains("{{macro ") {
+            return Err(feature_disabled("{{macro}}", "template-macros"));
         }
         
-        // Process escaped variables {{variable}}
-        while let Some(start) = result.find("{{") {
-            if result[start..].starts_with("{{if ") || 
-               result[start..].starts_with("{{for ") ||
-               result[start..].starts_with("{{include ") ||
-               result[start..].starts_with("{{!") ||
-               result[start..].starts_with("{{/") {
-                // Skip processed directives
-                if let Some(skip_end) = result[start..].find("}}") {
-                    result = result[..start].to_string() + &result[start + skip_end + 2..];
-                    continue;
-                } else {
-                    break;
-                }
-            }
-            
-            let end = result[start..].find("}}")
-                .ok_or_else(|| TemplateError::Parse("Unclosed variable directive".to_string()))?;
-            
-            let var_name = &result[start + 2..start + end].trim();
-            
-            // Check if this is a helper function call
-            if let Some(helper_result) = self.process_helper_call(var_name, context)? {
-                result.replace_range(start..start + end + 2, &helper_result);
-                continue;
+        // Error boundaries render before includes so that missing includes can be rescued
+        if result.contains(TRY_TAG) {
+            result = Cow::Owned(self.process_error_boundaries(&result, context)?);
+        }
+        
+        // Process includes 
+        if result.contains("{{include ") {
+            result = Cow::Owned(self.process_includes(&result, context)?);
+            // Included templates can deepen the nesting
+            self.check_nesting_depth(&result)?;
+            if result.contains(TRY_TAG) {
+                result = Cow::Owned(self.process_error_boundaries(&result, context)?);
             }
-            
-            let value = self.get_variable_value(var_name, context);
-            
-            // Check if filters that produce HTML are being used
-            let should_escape = if var_name.contains('|') {
-                !self.uses_html_producing_filter(var_name)
-            } else {
-                true
-            };
-            
-            let final_value = if should_escape {
-                html_escape(&value)
-            } else {
-                value
-            };
-            
-            result.replace_range(start..start + end + 2, &final_value);
         }
         
-        Ok(result)
+        // Output that later passes rewrite as a whole can't leave early
+        let sink = sink.filter(|_| self.can_stream(&result));
+        
+        // Assignments apply to what follows them, so the rest renders in sections
+        self.write_scoped(&result, context, None, out, sink)
     }
     
-    /// Process helper function calls like "helper_name(arg1, arg2)"
-    fn process_helper_call(&self, expression: &str, context: &TemplateContext) -> TemplateResult<Option<String>> {
-        // Check if this looks like a function call (contains parentheses)
-        if let Some(paren_pos) = expression.find('(') {
-            let func_name = expression[..paren_pos].trim();
-            
-            // Check if this is a registered helper
-            if let Some(helper) = self.helpers.get(func_name) {
-                if let Some(close_paren) = expression.rfind(')') {
-                    let args_str = &expression[paren_pos + 1..close_paren];
-                    let args = self.parse_helper_args(args_str, context)?;
-                    
-                    // Call the helper function
-                    let result_value = helper(&args)?;
-                    let result_string = self.template_value_to_string(&result_value);
-                    return Ok(Some(result_string));
-                } else {
-                    return Err(TemplateError::Parse(format!("Unclosed parentheses in helper call: {}", expression)));
+    /// Run the render stages from assignments on. `{{set}}` tags and
+    /// `{{capture}}` blocks outside conditionals and loops split `template`
+    /// into sections, each rendered with the values assigned before it; a
+    /// loop iteration keeps its assignments to itself. `previous` is the
+    /// context of the previous loop iteration, for `{{ifchanged}}`.
+    fn write_scoped(&mut self, template: &str, context: &TemplateContext, previous: Option<&TemplateContext>, out: &mut String, mut sink: Option<&mut StreamSink<'_>>) -> TemplateResult<()> {
+        let mut assigned: Option<TemplateContext> = None;
+        let mut pos = 0;
+        while let Some((start, end)) = find_assignment(template, pos)? {
+            self.write_section(&template[pos..start], assigned.as_ref().unwrap_or(context), previous, out, sink.as_deref_mut())?;
+            if template[start..].starts_with(CAPTURE_TAG) {
+                // The body renders here, with what was assigned so far, into the value
+                let tag_end = start + template[start..].find("}}").map_or(0, |close| close + 2);
+                let name = template[start + CAPTURE_TAG.len()..tag_end - 2].trim();
+                if !is_variable_path(name) || name.contains('.') {
+                    return Err(TemplateError::Parse(format!(
+                        "Malformed '{{{{capture {}}}}}': expected '{{{{capture name}}}}'", name
+                    )));
                 }
+                let mut captured = String::new();
+                let body = &template[tag_end..end - CAPTURE_END_TAG.len()];
+                self.write_scoped(body, assigned.as_ref().unwrap_or(context), previous, &mut captured, None)?;
+                self.put_back_rendered_parts(&mut captured, 0);
+                assigned.get_or_insert_with(|| TemplateContext::layered(Arc::new(context.clone())))
+                    .set_markup(name, captured);
+                pos = end;
+                continue;
             }
+            let arguments = template[start + SET_TAG.len()..end - 2].trim();
+            let (name, value) = split_assignment(arguments).ok_or_else(|| TemplateError::Parse(format!(
+                "Malformed '{{{{set {}}}}}': expected '{{{{set name = value}}}}'", arguments
+            )))?;
+            let value = self.evaluate_assigned_value(value, assigned.as_ref().unwrap_or(context))?;
+            assigned.get_or_insert_with(|| TemplateContext::layered(Arc::new(context.clone()))).set(name, value);
+            pos = end;
         }
-        
-        Ok(None)
+        self.write_section(&template[pos..], assigned.as_ref().unwrap_or(context), previous, out, sink)
     }
     
-    /// Parse helper function arguments
-    fn parse_helper_args(&self, args_str: &str, context: &TemplateContext) -> TemplateResult<Vec<TemplateValue>> {
-        let mut args = Vec::new();
+    /// Render a section without assignments of its own: conditionals, loops,
+    /// translations and variables
+    fn write_section(&mut self, template: &str, context: &TemplateContext, previous: Option<&TemplateContext>, out: &mut String, sink: Option<&mut StreamSink<'_>>) -> TemplateResult<()> {
+        let mut result = Cow::Borrowed(template);
         
-        if args_str.trim().is_empty() {
-            return Ok(args);
+        // Process conditionals
+        if result.contains("{{if ") {
+            result = Cow::Owned(self.process_conditionals(&result, context)?);
+        }
+        if result.contains(UNLESS_TAG) {
+            result = Cow::Owned(self.process_unless(&result, context)?);
+        }
+        // Assignments in the chosen branches apply to what follows them
+        if (result.contains(SET_TAG) || result.contains(CAPTURE_TAG)) && find_assignment(&result, 0)?.is_some() {
+            return self.write_scoped(&result, context, previous, out, sink);
         }
         
-        // Simple argument parsing (split by comma, but respect quotes)
-        let mut current_arg = String::new();
-        let mut in_quotes = false;
-        let mut quote_char = '"';
-        
-        for ch in args_str.chars() {
-            match ch {
-                '"' | '\'' if !in_quotes => {
-                    in_quotes = true;
-                    quote_char = ch;
-                    current_arg.push(ch);
-                },
-                ch if in_quotes && ch == quote_char => {
-                    in_quotes = false;
-                    current_arg.push(ch);
-                },
-                ',' if !in_quotes => {
-                    let arg_value = self.parse_single_helper_arg(current_arg.trim(), context);
-                    args.push(arg_value);
-                    current_arg.clear();
-                },
-                ch => {
-                    current_arg.push(ch);
+        self.write_blocks(&result, context, previous, out, sink)
+    }
+    
+    /// Write the loops and `{{ifchanged}}` blocks of a section in order,
+    /// each loop iteration straight to `out`; the text around them goes
+    /// through the remaining stages.
+    /// 
+    /// An `{{ifchanged}}` body renders when its expression differs from the
+    /// value it had in the `previous` loop iteration, compared like `==`.
+    /// Without a previous iteration every body renders.
+    fn write_blocks(&mut self, template: &str, context: &TemplateContext, previous: Option<&TemplateContext>, out: &mut String, mut sink: Option<&mut StreamSink<'_>>) -> TemplateResult<()> {
+        let mut pos = 0;
+        while let Some(block) = self.next_section_block(template, pos)? {
+            self.write_text(&template[pos..block.start], context, out)?;
+            let body = &template[block.body];
+            match block.kind {
+                SectionBlockKind::IfChanged(expression) => {
+                    if self.value_changed(expression, context, previous)? {
+                        self.write_blocks(body, context, previous, out, sink.as_deref_mut())?;
+                    }
+                }
+                SectionBlockKind::Loop(item_var, array_var) => {
+                    self.write_loop(item_var, array_var, body, context, out, sink.as_deref_mut())?;
                 }
             }
+            pos = block.end;
         }
+        self.write_text(&template[pos..], context, out)
+    }
+    
+    /// The first loop or `{{ifchanged}}` block of a section from `from` on
+    fn next_section_block<'t>(&self, template: &'t str, from: usize) -> TemplateResult<Option<SectionBlock<'t>>> {
+        let next_loop = template[from..].find("{{for ");
+        let next_check = template[from..].find(IFCHANGED_TAG);
+        let Some(start) = next_loop.into_iter().chain(next_check).min().map(|start| from + start) else {
+            return Ok(None);
+        };
         
-        // Don't forget the last argument
-        if !current_arg.trim().is_empty() {
-            let arg_value = self.parse_single_helper_arg(current_arg.trim(), context);
-            args.push(arg_value);
+        if template[start..].starts_with(IFCHANGED_TAG) {
+            let tag_end = start + template[start..].find("}}")
+                .ok_or_else(|| TemplateError::Parse("Unclosed ifchanged directive".to_string()))?;
+            let body_start = tag_end + 2;
+            let body_end = body_start + find_block_end(&template[body_start..], "ifchanged")
+                .ok_or_else(|| TemplateError::Parse("Missing {{/ifchanged}} directive".to_string()))?;
+            return Ok(Some(SectionBlock {
+                start,
+                kind: SectionBlockKind::IfChanged(&template[start + IFCHANGED_TAG.len()..tag_end]),
+                body: body_start..body_end,
+                end: body_end + "{{/ifchanged}}".len(),
+            }));
         }
         
-        Ok(args)
+        let tag_end = start + template[start..].find("}}")
+            .ok_or_else(|| TemplateError::Parse("Unclosed for directive".to_string()))?;
+        let parts: Vec<&str> = template[start + 6..tag_end].trim().split(" in ").collect();
+        if parts.len() != 2 {
+            return Err(TemplateError::Parse("Invalid for loop syntax".to_string()));
+        }
+        let body_start = tag_end + 2;
+        // Find matching {{/for}} using stack-based parsing to handle nested loops
+        let body_end = body_start + self.find_matching_for_end(&template[body_start..])?;
+        Ok(Some(SectionBlock {
+            start,
+            kind: SectionBlockKind::Loop(parts[0].trim(), parts[1].trim()),
+            body: body_start..body_end,
+            end: body_end + "{{/for}}".len(),
+        }))
     }
     
-    /// Parse a single helper argument (string literal, number, variable, etc.)
-    fn parse_single_helper_arg(&self, arg: &str, context: &TemplateContext) -> TemplateValue {
-        let arg = arg.trim();
+    /// Write text without blocks: translations, plurals, variables and comments
+    fn write_text(&mut self, template: &str, context: &TemplateContext, out: &mut String) -> TemplateResult<()> {
+        // Dynamic includes are resolved by now, either here or in their loop
+        self.check_unresolved_includes(template, context)?;
         
-        // String literal
-        if (arg.starts_with('"') && arg.ends_with('"')) || (arg.starts_with('\'') && arg.ends_with('\'')) {
-            return TemplateValue::String(arg[1..arg.len()-1].to_string());
+        // Translations are written as they interpolate, so the values of
+        // their arguments are never read as tags by the passes below
+        let mut pos = 0;
+        while let Some(relative_start) = template[pos..].find("{{t ") {
+            let start = pos + relative_start;
+            let end = template[start..].find("}}")
+                .ok_or_else(|| TemplateError::Parse("Unclosed translation directive".to_string()))?;
+            self.write_interpolated(&template[pos..start], context, out)?;
+            self.write_translation(&template[start + 2..start + end], context, out)?;
+            pos = start + end + 2;
         }
+        self.write_interpolated(&template[pos..], context, out)
+    }
+    
+    /// Write text without translations: plurals, variables and comments
+    fn write_interpolated(&self, template: &str, context: &TemplateContext, out: &mut String) -> TemplateResult<()> {
+        let mut result = Cow::Borrowed(template);
         
-        // Number literal
-        if let Ok(num) = arg.parse::<i64>() {
-            return TemplateValue::Number(num);
+        // Process pluralization
+        if result.contains("{{plural ") {
+            result = Cow::Owned(self.process_pluralization(&result, context)?);
         }
         
-        // Boolean literal
-        if arg == "true" {
-            return TemplateValue::Bool(true);
-        } else if arg == "false" {
-            return TemplateValue::Bool(false);
+        // Process variables and comments, writing the output directly
+        self.write_variables(&result, context, out)
+    }
+    
+    /// Final passes over the complete output
+    fn finish_output(&self, buf: &mut String) -> TemplateResult<()> {
+        // Required assets are only known once the whole tree has rendered
+        if buf.contains(ASSET_MARKER_OPEN) {
+            self.resolve_asset_markers(buf)?;
         }
         
-        // Variable reference (with possible dot notation)
-        if arg.contains('.') {
-            let parts: Vec<&str> = arg.split('.').collect();
-            if let Some(root_value) = context.variables.get(parts[0]) {
-                return self.get_nested_value(root_value, &parts[1..]);
-            }
-        } else if let Some(value) = context.variables.get(arg) {
-            return value.clone();
+        if self.minify_output {
+            let minified = minify_html(buf);
+            buf.clear();
+            buf.push_str(&minified);
+        }
+        Ok(())
+    }
+
+    /// Process include directives recursively
+    fn process_includes(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
+        let mut result = template.to_string();
+        let mut search_from = 0;
+        
+        while let Some(relative_start) = result[search_from..].find("{{include ") {
+            self.check_cancelled()?;
+            let start = search_from + relative_start;
+            let end = result[start..].find("}}")
+                .ok_or_else(|| TemplateError::Parse("Unclosed include directive".to_string()))?;
+            
+            let tag = result[start + 10..start + end].trim().to_string();
+            let (directive, from) = split_include_source(&tag);
+            let (directive, from) = (directive.to_string(), from.map(str::to_string));
+            // `with` arguments may read loop variables bound only once the loop renders
+            let (_, arguments, only) = split_include_scope(&tag);
+            let scope = match (arguments, only) {
+                (None, false) => None,
+                (arguments, _) => match self.include_scope(&tag, arguments, only, context)? {
+                    Some(scope) => Some(scope),
+                    None => {
+                        search_from = start + end + 2;
+                        continue;
+                    }
+                },
+            };
+            let include_name = if directive.starts_with('"') || directive.starts_with('\'') {
+                directive.trim_matches('"').trim_matches('\'').to_string()
+            } else {
+                // Dynamic include: the variable may be a loop variable that is
+                // only known once the enclosing loop renders its body
+                let root = directive.split('.').next().unwrap_or_default();
+                if context.get(root).is_none() {
+                    search_from = start + end + 2;
+                    continue;
+                }
+                self.resolve_dynamic_template_name(&directive, context)?
+            };
+            
+            // Process includes recursively within the included template
+            self.enter_include(&include_name)?;
+            self.trace_includes(|trace| trace.open(&include_name, IncludeRelation::Include));
+            let processed = self.load_template_from_root(&include_name, from.as_deref())
+                .and_then(|included_content| {
+                    self.record_tag_sites(&included_content, Some(&include_name));
+                    match &scope {
+                        // A scoped partial renders on its own, with only its scope
+                        Some(scope) => {
+                            let mut output = String::new();
+                            self.write_source(&included_content, scope, &mut output, None)?;
+                            let mut marker = String::new();
+                            self.defer_rendered_part(output, &mut marker);
+                            Ok(marker)
+                        }
+                        None => self.process_includes(&included_content, context),
+                    }
+                });
+            self.trace_includes(IncludeRecorder::close);
+            self.include_chain.pop();
+            let processed_included_content = processed?;
+            
+            result.replace_range(start..start + end + 2, &processed_included_content);
+            search_from = start + processed_included_content.len();
         }
         
-        // Default to string
-        TemplateValue::String(arg.to_string())
+        Ok(result)
     }
     
-    /// Convert TemplateValue to string for output
-    #[allow(clippy::only_used_in_recursion)]
-    fn template_value_to_string(&self, value: &TemplateValue) -> String {
-        match value {
-            TemplateValue::String(s) => s.clone(),
-            TemplateValue::Number(n) => n.to_string(),
-            TemplateValue::Bool(b) => b.to_string(),
-            TemplateValue::Array(arr) => {
-                let items: Vec<String> = arr.iter().map(|v| self.template_value_to_string(v)).collect();
-                format!("[{}]", items.join(", "))
-            },
-            TemplateValue::Object(obj) => {
-                let pairs: Vec<String> = obj.iter()
-                    .map(|(k, v)| format!("{}: {}", k, self.template_value_to_string(v)))
-                    .collect();
-                format!("{{{}}}", pairs.join(", "))
-            }
+    /// Add `name` to the include chain; fails when it is already in the
+    /// chain, naming the circle, or when the chain is too deep
+    fn enter_include(&mut self, name: &str) -> TemplateResult<()> {
+        if self.include_root == name || self.include_chain.iter().any(|included| included == name) {
+            let chain: Vec<&str> = std::iter::once(&self.include_root).chain(&self.include_chain).map(String::as_str)
+                .filter(|included| !included.is_empty())
+                .skip_while(|included| *included != name)
+                .chain([name])
+                .collect();
+            return Err(TemplateError::Template(format!("Circular include detected: {}", chain.join(" -> "))));
+        }
+        if self.include_chain.len() >= self.max_include_depth {
+            return Err(TemplateError::Template(format!(
+                "Maximum include depth of {} exceeded by '{}'", self.max_include_depth, name
+            )));
         }
+        self.include_chain.push(name.to_string());
+        Ok(())
     }
-
-    /// Get variable value with support for deep dot notation and filters
-    fn get_variable_value(&self, var_name: &str, context: &TemplateContext) -> String {
-        // Check if filters are present (contains |)
-        if var_name.contains('|') {
-            return self.apply_filters(var_name, context);
+    
+    /// Context of a scoped include, `{{include "card.html" with title=product.name}}`:
+    /// the outer context, or nothing with `only`, plus the `with` arguments.
+    /// `None` while an argument reads a variable that is not set yet, such
+    /// as a loop variable outside its loop
+    fn include_scope(&self, tag: &str, arguments: Option<std::ops::Range<usize>>, only: bool, context: &TemplateContext) -> TemplateResult<Option<TemplateContext>> {
+        let arguments = match arguments {
+            Some(arguments) => parse_arguments(tag, arguments, Separator::Whitespace)?,
+            None => Vec::new(),
+        };
+        if arguments.iter().any(|argument| unset_argument_root(&argument.value, context).is_some()) {
+            return Ok(None);
         }
         
-        if var_name.contains('.') {
-            let parts: Vec<&str> = var_name.split('.').collect();
-            if let Some(root_value) = context.variables.get(parts[0]) {
-                return self.traverse_nested_value(root_value, &parts[1..]);
-            }
-            String::new()
-        } else {
-            context.get_string(var_name).unwrap_or_default()
+        let mut scope = if only { TemplateContext::new() } else { context.clone() };
+        for argument in arguments {
+            let Some(name) = argument.name else {
+                return Err(TemplateError::Parse(format!(
+                    "Unexpected argument '{}' in '{{{{include {}}}}}'; include arguments are name=value",
+                    argument.text, tag
+                )));
+            };
+            let value = self.argument_value(&argument.value, context)?
+                .unwrap_or_else(|| TemplateValue::String(String::new()));
+            scope.set(name, value);
         }
+        Ok(Some(scope))
     }
     
-    /// Apply filters to a variable (e.g., "name|upper|truncate:10")
-    fn apply_filters(&self, expression: &str, context: &TemplateContext) -> String {
-        let parts: Vec<&str> = expression.split('|').collect();
-        if parts.is_empty() {
-            return String::new();
+    /// Reject dynamic includes whose variable never resolved, and scoped
+    /// includes whose arguments never did
+    fn check_unresolved_includes(&self, template: &str, context: &TemplateContext) -> TemplateResult<()> {
+        if let Some(start) = template.find("{{include ") {
+            let end = template[start..].find("}}").unwrap_or(template.len() - start);
+            let tag = template[start + 10..start + end].trim();
+            let (directive, arguments, _) = split_include_scope(tag);
+            if let Some(arguments) = arguments {
+                let arguments = parse_arguments(tag, arguments, Separator::Whitespace)?;
+                if let Some(root) = arguments.iter().find_map(|argument| unset_argument_root(&argument.value, context)) {
+                    return Err(TemplateError::Template(format!(
+                        "Include {} could not be resolved: variable '{}' of its arguments is not set", directive, root
+                    )));
+                }
+            }
+            let root = directive.split('.').next().unwrap_or_default();
+            return Err(TemplateError::Template(format!(
+                "Dynamic include '{}' could not be resolved: variable '{}' is not set", directive, root
+            )));
+        }
+        Ok(())
+    }
+
+    /// Process `{{if}}` blocks and their `{{elseif}}` and `{{else}}`
+    /// branches; blocks inside loop bodies are left to the loop, which
+    /// renders them with the loop variable set
+    fn process_conditionals(&self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
+        let mut result = template.to_string();
+        let mut search_from = 0;
+        
+        while let Some(relative_start) = result[search_from..].find("{{if ") {
+            let if_start = search_from + relative_start;
+            if let Some(for_start) = result[search_from..if_start].find("{{for ").map(|offset| search_from + offset) {
+                let body_start = for_start + result[for_start..].find("}}")
+                    .ok_or_else(|| TemplateError::Parse("Unclosed for directive".to_string()))? + 2;
+                search_from = body_start + self.find_matching_for_end(&result[body_start..])? + "{{/for}}".len();
+                continue;
+            }
+            
+            let if_end = result[if_start..].find("}}")
+                .ok_or_else(|| TemplateError::Parse("Unclosed if directive".to_string()))?;
+            
+            let condition = &result[if_start + 5..if_start + if_end].trim();
+            
+            let block_start = if_start + if_end + 2;
+            let block_end = block_start + find_block_end(&result[block_start..], "if")
+                .ok_or_else(|| TemplateError::Parse("Missing {{/if}} directive".to_string()))?;
+            
+            let holds = self.evaluate_condition(condition, context)?;
+            let replacement = match self.taken_branch(&result, block_start..block_end, "if", holds, context)? {
+                Some(branch) => result[branch].to_string(),
+                None => String::new(),
+            };
+            
+            // Blocks nested in the kept branch are processed next
+            result.replace_range(if_start..block_end + "{{/if}}".len(), &replacement);
+            search_from = if_start;
         }
         
-        // Get the initial variable value
-        let var_name = parts[0].trim();
-        let mut value = if var_name.contains('.') {
-            let dot_parts: Vec<&str> = var_name.split('.').collect();
-            if let Some(root_value) = context.variables.get(dot_parts[0]) {
-                self.traverse_nested_value(root_value, &dot_parts[1..])
-            } else {
-                String::new()
+        Ok(result)
+    }
+
+    /// Range of the branch of a conditional block `body` that renders, given
+    /// whether its opening condition `holds`: the whole body of `unless` and
+    /// `ifchanged`, the first branch of an `if` whose condition holds
+    fn taken_branch(&self, source: &str, body: std::ops::Range<usize>, keyword: &str, mut holds: bool, context: &TemplateContext) -> TemplateResult<Option<std::ops::Range<usize>>> {
+        if keyword != "if" {
+            return Ok(holds.then_some(body));
+        }
+        // All branches are checked before any condition is evaluated, so
+        // that whether the block is valid doesn't depend on the data
+        let mut branch_start = body.start;
+        let mut after_else = false;
+        while let Some((start, end, condition)) = find_branch(&source[branch_start..body.end]) {
+            if after_else {
+                return Err(TemplateError::Parse(format!(
+                    "Unexpected {} after {{{{else}}}}: {{{{else}}}} must be the last branch of an {{{{if}}}} block",
+                    &source[branch_start + start..branch_start + end]
+                )));
             }
-        } else {
-            context.get_string(var_name).unwrap_or_default()
-        };
+            after_else = condition.is_none();
+            branch_start += end;
+        }
         
-        // Apply each filter in sequence
-        for filter_expr in &parts[1..] {
-            value = self.apply_single_filter(&value, filter_expr.trim());
+        let mut branch_start = body.start;
+        loop {
+            let branch = find_branch(&source[branch_start..body.end]);
+            if holds {
+                return Ok(Some(branch_start..branch.map_or(body.end, |(start, _, _)| branch_start + start)));
+            }
+            let Some((_, end, condition)) = branch else {
+                return Ok(None);
+            };
+            holds = match condition {
+                Some(condition) => self.evaluate_condition(condition, context)?,
+                None => true,
+            };
+            branch_start += end;
+        }
+    }
+
+    /// Process `{{unless}}` blocks; blocks inside loop bodies are left to
+    /// the loop, which renders them with the loop variable set
+    fn process_unless(&self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
+        let mut result = template.to_string();
+        let mut search_from = 0;
+        
+        while let Some(relative_start) = result[search_from..].find(UNLESS_TAG) {
+            let start = search_from + relative_start;
+            if let Some(for_start) = result[search_from..start].find("{{for ").map(|offset| search_from + offset) {
+                let body_start = for_start + result[for_start..].find("}}")
+                    .ok_or_else(|| TemplateError::Parse("Unclosed for directive".to_string()))? + 2;
+                search_from = body_start + self.find_matching_for_end(&result[body_start..])? + "{{/for}}".len();
+                continue;
+            }
+            
+            let tag_end = start + result[start..].find("}}")
+                .ok_or_else(|| TemplateError::Parse("Unclosed unless directive".to_string()))?;
+            let block_start = tag_end + 2;
+            let block_end = block_start + find_block_end(&result[block_start..], "unless")
+                .ok_or_else(|| TemplateError::Parse("Missing {{/unless}} directive".to_string()))?;
+            
+            let condition = &result[start + UNLESS_TAG.len()..tag_end];
+            let replacement = if self.evaluate_condition(condition, context)? {
+                String::new()
+            } else {
+                result[block_start..block_end].to_string()
+            };
+            
+            // Blocks nested in the kept body are processed next
+            result.replace_range(start..block_end + "{{/unless}}".len(), &replacement);
+            search_from = start;
         }
         
-        value
+        Ok(result)
     }
     
-    /// Apply a single filter to a value
-    fn apply_single_filter(&self, value: &str, filter_expr: &str) -> String {
-        let filter_parts: Vec<&str> = filter_expr.split(':').collect();
-        let filter_name = filter_parts[0];
-        let args: Vec<&str> = if filter_parts.len() > 1 {
-            filter_parts[1..].iter().map(|arg| arg.trim_matches('"').trim_matches('\'')).collect()
-        } else {
-            Vec::new()
-        };
+    /// Whether `expression` has a different value in `context` than in the
+    /// `previous` loop iteration
+    fn value_changed(&self, expression: &str, context: &TemplateContext, previous: Option<&TemplateContext>) -> TemplateResult<bool> {
+        let Some(previous) = previous else { return Ok(true) };
+        let current = self.get_condition_value(expression, context)?;
+        let before = self.get_condition_value(expression, previous)?;
+        Ok(!self.values_equal(&current, &before))
+    }
+    
+    /// Process variable substitutions
+    fn process_variables(&self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
+        let mut output = String::with_capacity(template.len());
+        self.write_variables(template, context, &mut output)?;
+        Ok(output)
+    }
+    
+    /// Single pass over the template writing text, variables and comments to `out`
+    fn write_variables(&self, template: &str, context: &TemplateContext, out: &mut String) -> TemplateResult<()> {
+        let mut pos = 0;
+        let mut next_cancel_check = out.len() + CANCEL_CHECK_BYTES;
         
-        match filter_name {
-            "upper" => value.to_uppercase(),
-            "lower" => value.to_lowercase(),
-            "capitalize" => {
-                if value.is_empty() {
-                    String::new()
-                } else {
-                    value.split_whitespace()
-                        .map(|word| {
-                            let mut chars: Vec<char> = word.chars().collect();
-                            if !chars.is_empty() {
-                                chars[0] = chars[0].to_uppercase().next().unwrap_or(chars[0]);
-                            }
-                            chars.into_iter().collect::<String>()
-                        })
-                        .collect::<Vec<_>>()
-                        .join(" ")
-                }
-            },
-            "truncate" => {
-                if let Some(limit_str) = args.first() {
-                    if let Ok(limit) = limit_str.parse::<usize>() {
-                        if value.len() > limit {
-                            format!("{}...", &value[..limit.min(value.len())])
-                        } else {
-                            value.to_string()
-                        }
-                    } else {
-                        value.to_string()
-                    }
-                } else {
-                    value.to_string()
-                }
-            },
-            "currency" => {
-                if let Ok(num) = value.parse::<i64>() {
-                    // If number is large (>= 100), treat as cents; otherwise as dollars
-                    if num >= 100 {
-                        format!("${:.2}", num as f64 / 100.0)
-                    } else {
-                        format!("${:.2}", num as f64)
-                    }
-                } else if let Ok(num) = value.parse::<f64>() {
-                    format!("${:.2}", num)
-                } else {
-                    format!("${}", value)
-                }
-            },
-            "date" => {
-                // Simple date formatting - in production would use chrono
-                if let Some(format) = args.first() {
-                    // For now, just return the date as-is with basic format support
-                    match *format {
-                        "Y-m-d" => value.to_string(), // Assume input is already in this format
-                        _ => value.to_string(),
-                    }
-                } else {
-                    value.to_string()
-                }
-            },
-            "strip" => value.trim().to_string(),
-            "add" => {
-                if let Some(addend_str) = args.first() {
-                    if let (Ok(num), Ok(addend)) = (value.parse::<i64>(), addend_str.parse::<i64>()) {
-                        (num + addend).to_string()
-                    } else {
-                        value.to_string()
-                    }
-                } else {
-                    value.to_string()
-                }
-            },
-            "multiply" => {
-                if let Some(factor_str) = args.first() {
-                    if let (Ok(num), Ok(factor)) = (value.parse::<i64>(), factor_str.parse::<i64>()) {
-                        (num * factor).to_string()
-                    } else {
-                        value.to_string()
-                    }
-                } else {
-                    value.to_string()
+        while let Some(relative_start) = template[pos..].find("{{") {
+            if out.len() >= next_cancel_check {
+                self.check_cancelled()?;
+                next_cancel_check = out.len() + CANCEL_CHECK_BYTES;
+            }
+            let start = pos + relative_start;
+            out.push_str(&template[pos..start]);
+            let rest = &template[start..];
+            
+            // Visible comments {{#-- ... --}} become HTML comments unless stripped
+            if let Some(body) = rest.strip_prefix(VISIBLE_COMMENT_OPEN) {
+                let close = body.find(VISIBLE_COMMENT_CLOSE)
+                    .ok_or_else(|| TemplateError::Parse("Unclosed visible comment".to_string()))?;
+                if !self.strip_visible_comments && !self.minify_output {
+                    out.push_str("<!--");
+                    out.push_str(&escape_html_comment(&body[..close]));
+                    out.push_str("-->");
                 }
-            },
-            // Custom filters for the test
-            "markdown" => {
-                // Simple markdown to HTML conversion - handle **text** -> <strong>text</strong>
-                let mut result = value.to_string();
-                while let Some(start) = result.find("**") {
-                    if let Some(end) = result[start + 2..].find("**") {
-                        let text = &result[start + 2..start + 2 + end];
-                        let replacement = format!("<strong>{}</strong>", text);
-                        result.replace_range(start..start + 2 + end + 2, &replacement);
-                    } else {
-                        break; // No closing **
+                pos = start + VISIBLE_COMMENT_OPEN.len() + close + VISIBLE_COMMENT_CLOSE.len();
+                continue;
+            }
+            
+            // Comments and already processed directives render nothing
+            if rest.starts_with("{{if ") || 
+               rest.starts_with("{{for ") ||
+               rest.starts_with("{{include ") ||
+               rest.starts_with("{{!") ||
+               rest.starts_with("{{/") {
+                match rest.find("}}") {
+                    Some(skip_end) => {
+                        pos = start + skip_end + 2;
+                        continue;
                     }
-                }
-                format!("<p>{}</p>", result)
-            },
-            "highlight" => {
-                if let Some(lang) = args.first() {
-                    format!("<pre><code class=\"{}\">{}</code></pre>", lang, value)
-                } else {
-                    format!("<pre><code>{}</code></pre>", value)
-                }
-            },
-            "slugify" => {
-                value.to_lowercase()
-                    .chars()
-                    .map(|c| if c.is_alphanumeric() { c } else { '-' })
-                    .collect::<String>()
-                    .split('-')
-                    .filter(|s| !s.is_empty())
-                    .collect::<Vec<_>>()
-                    .join("-")
-            },
-            // Additional math filters
-            "divide" => {
-                if let Some(arg) = args.first() {
-                    if let Ok(num_value) = value.parse::<f64>() {
-                        if let Ok(div_value) = arg.parse::<f64>() {
-                            if div_value != 0.0 {
-                                return (num_value / div_value).to_string();
-                            }
-                        }
+                    None => {
+                        out.push_str(rest);
+                        return Ok(());
                     }
                 }
-                value.to_string()
-            },
-            "percentage" => {
-                format!("{}%", value)
-            },
-            "round" => {
-                if let Some(arg) = args.first() {
-                    if let Ok(num_value) = value.parse::<f64>() {
-                        if let Ok(decimals) = arg.parse::<usize>() {
-                            let factor = 10_f64.powi(decimals as i32);
-                            let rounded = (num_value * factor).round() / factor;
-                            return format!("{:.1$}", rounded, decimals);
-                        }
-                    }
+            }
+            
+            let end = rest.find("}}")
+                .ok_or_else(|| TemplateError::Parse("Unclosed variable directive".to_string()))?;
+            pos = start + end + 2;
+            
+            // Asset requirements are deferred to the end of the render
+            let directive = rest[2..end].trim();
+            if else_condition(directive).is_some() {
+                return Err(TemplateError::Parse(format!(
+                    "Unexpected {{{{{}}}}} outside an {{{{if}}}} block: expected {{{{if condition}}}}...{{{{else}}}}...{{{{/if}}}}", directive
+                )));
+            }
+            if directive == "no_provenance" {
+                // A tag on a line of its own leaves no blank line, so an XML declaration can follow it
+                pos += ["\r\n", "\n"].into_iter().find(|line_break| template[pos..].starts_with(line_break)).map_or(0, str::len);
+                continue;
+            }
+            // A {{super}} of a block without a parent block renders nothing
+            if directive == "super" && !self.variable_exists_in_context("super", context) {
+                continue;
+            }
+            if directive == "uid" && !self.variable_exists_in_context("uid", context) {
+                out.push_str(&self.next_uid(DEFAULT_UID_PREFIX));
+                continue;
+            }
+            if let Some(prefix) = directive.strip_prefix("uid ") {
+                match quoted_arguments(prefix, "uid")?.as_slice() {
+                    [prefix] => out.push_str(&self.next_uid(prefix)),
+                    _ => return Err(TemplateError::Parse(format!(
+                        "Invalid {{{{{}}}}}: expected one quoted prefix", directive
+                    ))),
                 }
-                // Default rounding to 2 decimal places
-                if let Ok(num_value) = value.parse::<f64>() {
-                    format!("{:.2}", num_value)
-                } else {
-                    value.to_string()
+                continue;
+            }
+            if directive == "flush" {
+                if self.streaming && self.stream_flush_on != FlushOn::Manual {
+                    write_asset_marker(out, self.marker_key, '!', "");
                 }
-            },
-            _ => {
-                // Check for custom filters
-                if let Some(custom_filter) = self.custom_filters.get(filter_name) {
-                    match custom_filter(value, &args) {
-                        Ok(result) => result,
-                        Err(_) => value.to_string(), // Fallback on error
-                    }
-                } else {
-                    value.to_string() // Unknown filter, return original value
+                continue;
+            }
+            if let Some(names) = directive.strip_prefix("needs ") {
+                for name in quoted_arguments(names, "needs")? {
+                    write_asset_marker(out, self.marker_key, '+', name);
                 }
+                continue;
             }
-        }
-    }
-
-    /// Recursively traverse nested object properties
-    #[allow(clippy::only_used_in_recursion)]
-    fn traverse_nested_value(&self, current_value: &TemplateValue, remaining_parts: &[&str]) -> String {
-        if remaining_parts.is_empty() {
-            // We've reached the end of the path, convert the value to string
-            return match current_value {
-                TemplateValue::String(s) => s.clone(),
-                TemplateValue::Bool(b) => b.to_string(),
-                TemplateValue::Number(n) => n.to_string(),
-                TemplateValue::Array(_) => String::new(), // Arrays render as empty string when accessed directly
-                TemplateValue::Object(_) => String::new(), // Objects render as empty string when accessed directly
-            };
-        }
-
-        // We still have more parts to traverse
-        let current_part = remaining_parts[0];
-        let next_parts = &remaining_parts[1..];
-
-        match current_value {
-            TemplateValue::Object(obj) => {
-                if let Some(next_value) = obj.get(current_part) {
-                    self.traverse_nested_value(next_value, next_parts)
-                } else {
-                    String::new() // Property not found
+            if let Some(name) = directive.strip_prefix("include_raw ") {
+                match quoted_arguments(name, "include_raw")?.as_slice() {
+                    [name] => write_asset_marker(out, self.marker_key, '=', name),
+                    _ => return Err(TemplateError::Parse(format!(
+                        "Invalid {{{{{}}}}}: expected one quoted file name", directive
+                    ))),
                 }
+                continue;
             }
-            TemplateValue::Array(arr) => {
-                // Support array indexing with numeric strings (optional enhancement)
-                if let Ok(index) = current_part.parse::<usize>() {
-                    if let Some(element) = arr.get(index) {
-                        self.traverse_nested_value(element, next_parts)
-                    } else {
-                        String::new() // Index out of bounds
-                    }
-                } else {
-                    String::new() // Invalid array index
+            if let Some(kind) = directive.strip_prefix("required_assets ") {
+                match quoted_arguments(kind, "required_assets")?.as_slice() {
+                    [kind @ ("css" | "js")] => write_asset_marker(out, self.marker_key, '@', kind),
+                    _ => return Err(TemplateError::Parse(format!(
+                        "Invalid asset kind in {{{{{}}}}}: expected \"css\" or \"js\"", directive
+                    ))),
                 }
+                continue;
             }
-            _ => String::new(), // Can't traverse further on non-object/non-array values
-        }
-    }
-
-    /// Process comments
-    fn process_comments(&self, template: &str) -> String {
-        let mut result = template.to_string();
-        
-        while let Some(start) = result.find("{{!") {
-            if let Some(end) = result[start..].find("}}") {
-                result.replace_range(start..start + end + 2, "");
-            } else {
-                break;
+            if let Some(args) = directive.strip_prefix("table ") {
+                self.write_table(args, context, out)?;
+                continue;
+            }
+            if let Some(args) = directive.strip_prefix("assert ") {
+                self.check_assertion(directive, args, context)?;
+                continue;
+            }
+            if let Some((helper, args)) = directive.split_once(' ').filter(|(helper, _)| FORM_HELPERS.contains(helper)) {
+                self.write_form_helper(helper, args, context, out)?;
+                continue;
+            }
+            
+            // Component declarations render nothing; `has_slot` reports a slot
+            if directive.starts_with("props ") || directive.starts_with("slots ") {
+                continue;
+            }
+            if let Some(args) = directive.strip_prefix("has_slot ") {
+                out.push_str(&self.format_bool(has_slot(args, context)?));
+                continue;
+            }
+            
+            // Raw variables {{& variable}}
+            if let Some(raw_name) = rest[..end].strip_prefix("{{& ") {
+                self.write_expression(raw_name.trim(), true, context, out)?;
+                continue;
             }
+            
+            self.write_expression(rest[2..end].trim(), false, context, out)?;
         }
         
-        result
+        out.push_str(&template[pos..]);
+        Ok(())
     }
     
-    /// Process macro definitions and macro calls with context
-    fn process_macros_with_context(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
-        let mut result = template.to_string();
+    /// Write one output expression (variable, filter chain or helper call)
+    fn write_expression(&self, expression: &str, raw: bool, context: &TemplateContext, out: &mut String) -> TemplateResult<()> {
+        if !raw {
+            // Check if this is a helper function call
+            if let Some(helper_result) = self.process_helper_call(expression, context)? {
+                out.push_str(&helper_result);
+                return Ok(());
+            }
+        }
+        self.check_variable_defined(expression, context)?;
         
-        // First pass: extract macro definitions
-        result = self.extract_macro_definitions(&result)?;
+        // Filters that produce HTML, `safe` and `escape` decide over the tag;
+        // captured markup was escaped when it was rendered
+        let root = expression.split(['|', '.']).next().unwrap_or("").trim();
+        let escape = self.filter_escaping(expression).unwrap_or(!raw && self.autoescape && !context.is_markup(root));
         
-        // Second pass: process macro calls with context
-        result = self.process_macro_calls_with_context(&result, context)?;
+        if expression.contains('|') {
+            let value = self.evaluate_filter_expression(expression, context)?;
+            return self.write_value(&value, escape, out, || format!("'{}'", expression));
+        }
         
-        Ok(result)
+        self.record_read(expression);
+        let value = if expression.contains('.') {
+            let parts: Vec<&str> = expression.split('.').collect();
+            context.get(parts[0]).and_then(|root_value| lookup_nested(root_value, &parts[1..]))
+        } else {
+            context.get(expression)
+        };
+        match value {
+            Some(value) => self.write_value(value, escape, out, || format!("'{}'", expression)),
+            None => {
+                write_text(&self.format_missing(), escape, out);
+                Ok(())
+            }
+        }
     }
-
-    /// Process macro definitions and macro calls (old method for backwards compatibility)
-    #[allow(dead_code)]
-    fn process_macros(&mut self, template: &str) -> TemplateResult<String> {
-        let mut result = template.to_string();
-        
-        // First pass: extract macro definitions
-        result = self.extract_macro_definitions(&result)?;
-        
-        // Second pass: process macro calls
-        result = self.process_macro_calls(&result)?;
+    
+    /// With strict variables, fail an output tag whose variable is missing
+    /// from the context, unless a `default` filter supplies a value
+    fn check_variable_defined(&self, expression: &str, context: &TemplateContext) -> TemplateResult<()> {
+        if !self.strict_variables {
+            return Ok(());
+        }
+        let mut steps = expression.split('|');
+        let path = steps.next().unwrap_or_default().trim();
+        let parts: Vec<&str> = path.split('.').collect();
+        if !is_variable_path(path)
+            || steps.any(|step| split_filter_arguments(step.trim()).0 == "default")
+            || context.get(parts[0]).and_then(|root_value| lookup_nested(root_value, &parts[1..])).is_some()
+        {
+            return Ok(());
+        }
         
-        Ok(result)
+        let site = self.tag_sites.get(expression).or_else(|| self.tag_sites.get(&format!("& {}", expression)));
+        let message = match site {
+            Some((Some(template_name), line, column)) => format!(
+                "Undefined variable '{}' in template '{}' at line {}, column {}", path, template_name, line, column
+            ),
+            Some((None, line, column)) => format!("Undefined variable '{}' at line {}, column {}", path, line, column),
+            None => format!("Undefined variable '{}'", path),
+        };
+        Err(TemplateError::Render(message))
     }
     
-    /// Extract macro definitions from template
-    fn extract_macro_definitions(&mut self, template: &str) -> TemplateResult<String> {
-        let mut result = template.to_string();
-        
-        while let Some(macro_start) = result.find("{{macro ") {
-            let header_end = result[macro_start..].find("}}")
-                .ok_or_else(|| TemplateError::Parse("Unclosed macro definition".to_string()))?;
+    /// Write the text of an output value; strings and numbers go straight
+    /// into `out`, matching what [`Self::get_variable_value`] returns
+    fn write_value(
+        &self,
+        value: &TemplateValue,
+        escape: bool,
+        out: &mut String,
+        describe: impl FnOnce() -> String,
+    ) -> TemplateResult<()> {
+        match value {
+            TemplateValue::String(text) => write_text(text, escape, out),
+            TemplateValue::Number(n) => write_integer(out, *n),
+            TemplateValue::Float(f) => {
+                // Formatting into a String can't fail
+                let _ = fmt::Write::write_fmt(out, format_args!("{}", f));
+            }
+            TemplateValue::Bool(b) => write_text(&self.format_bool(*b), escape, out),
+            value => {
+                let text = self.stringify_value(value, filter_value_to_string, describe)?;
+                write_text(&text, escape, out);
+            }
+        }
+        Ok(())
+    }
+    
+    /// Process helper function calls like "helper_name(arg1, arg2)"
+    fn process_helper_call(&self, expression: &str, context: &TemplateContext) -> TemplateResult<Option<String>> {
+        // Check if this looks like a function call (contains parentheses)
+        if let Some(paren_pos) = expression.find('(') {
+            let func_name = expression[..paren_pos].trim();
             
-            let macro_header = &result[macro_start + 8..macro_start + header_end];
-            let body_start = macro_start + header_end + 2;
+            // Registered helpers, then the standard library functions
+            if let Some(result_value) = self.call_function(expression, context)? {
+                let result_string = self.stringify_value(
+                    &result_value,
+                    |value| self.template_value_to_string(value),
+                    || format!("returned by helper '{}'", func_name),
+                )?;
+                return Ok(Some(result_string));
+            }
+            if self.helpers.contains_key(func_name) && !expression.contains(')') {
+                return Err(TemplateError::Parse(format!("Unclosed parentheses in helper call: {}", expression)));
+            }
             
-            // Find matching {{/macro}}
-            let body_end = result[body_start..].find("{{/macro}}")
-                .ok_or_else(|| TemplateError::Parse("Missing {{/macro}} directive".to_string()))?;
-            
-            let macro_body = result[body_start..body_start + body_end].trim().to_string();
-            
-            // Parse macro header: name(param1, param2="default", ...)
-            let (macro_name, parameters) = self.parse_macro_header(macro_header)?;
-            
-            // Store macro definition
-            self.macros.insert(macro_name.clone(), MacroDefinition {
-                name: macro_name,
-                parameters,
-                body: macro_body,
-            });
-            
-            // Remove macro definition from template
-            let macro_end = body_start + body_end + 10; // +10 for {{/macro}}
-            result.replace_range(macro_start..macro_end, "");
-        }
-        
-        Ok(result)
-    }
-    
-    /// Parse macro header to extract name and parameters
-    fn parse_macro_header(&self, header: &str) -> TemplateResult<(String, Vec<String>)> {
-        // Simple parsing: name(param1, param2="default")
-        if let Some(paren_pos) = header.find('(') {
-            let macro_name = header[..paren_pos].trim().to_string();
-            let params_str = &header[paren_pos + 1..];
-            
-            if let Some(close_paren) = params_str.rfind(')') {
-                let params_content = &params_str[..close_paren];
-                let parameters = if params_content.trim().is_empty() {
-                    Vec::new()
-                } else {
-                    params_content.split(',')
-                        .map(|p| {
-                            // Extract parameter name (ignore default values for now)
-                            let param = p.trim();
-                            if let Some(eq_pos) = param.find('=') {
-                                param[..eq_pos].trim().to_string()
-                            } else {
-                                param.to_string()
-                            }
-                        })
-                        .collect()
-                };
-                Ok((macro_name, parameters))
-            } else {
-                Err(TemplateError::Parse(format!("Invalid macro header: {}", header)))
-            }
-        } else {
-            // No parameters
-            Ok((header.trim().to_string(), Vec::new()))
-        }
-    }
-    
-    /// Process macro calls in template with context
-    fn process_macro_calls_with_context(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
-        let mut result = template.to_string();
-        
-        // Find macro calls: {{macro_name(arg1, arg2)}}
-        let macros_clone = self.macros.clone();
-        for (macro_name, macro_def) in &macros_clone {
-            let call_pattern = format!("{}{}", macro_name, "(");
-            
-            while let Some(call_start) = result.find(&call_pattern) {
-                // Find the start of the macro call
-                let start_pos = result[..call_start].rfind("{{")
-                    .ok_or_else(|| TemplateError::Parse("Invalid macro call".to_string()))?;
-                
-                // Find the end of the macro call
-                let end_pos = result[call_start..].find("}}")
-                    .ok_or_else(|| TemplateError::Parse("Unclosed macro call".to_string()))? + call_start + 2;
-                
-                let call_content = &result[start_pos + 2..end_pos - 2];
-                
-                // Check if all required variables for this macro call exist in context
-                if !self.can_resolve_macro_args(call_content, context)? {
-                    // Skip this macro call if variables aren't available - it will be processed later in loop context
-                    break;
+            // Built-in helpers, unless a helper of the same name is registered
+            if expression[paren_pos..].trim() == "()" {
+                match func_name {
+                    "now" => return Ok(Some(self.unix_now().to_string())),
+                    "nonce" => return Ok(Some(self.next_nonce())),
+                    "uid" => return Ok(Some(self.next_uid(DEFAULT_UID_PREFIX))),
+                    _ => {}
                 }
-                
-                // Parse arguments and resolve them from context
-                let args = self.parse_macro_call_args_with_context(call_content, context)?;
-                
-                // Expand macro with resolved values
-                let expanded = self.expand_macro_with_values(macro_def, &args)?;
-                
-                result.replace_range(start_pos..end_pos, &expanded);
+            }
+            if func_name == "uid" {
+                let close_paren = expression.rfind(')')
+                    .ok_or_else(|| TemplateError::Parse(format!("Unclosed parentheses in helper call: {}", expression)))?;
+                return match self.parse_helper_args(expression, paren_pos + 1..close_paren, context)?.as_slice() {
+                    [TemplateValue::String(prefix)] => Ok(Some(self.next_uid(prefix))),
+                    _ => Err(TemplateError::Render(format!("Invalid {}: expected one prefix string", expression))),
+                };
             }
         }
         
-        Ok(result)
-    }
-
-    /// Process macro calls in template (old method for backwards compatibility)
-    #[allow(dead_code)]
-    fn process_macro_calls(&mut self, template: &str) -> TemplateResult<String> {
-        let empty_context = TemplateContext::new();
-        self.process_macro_calls_with_context(template, &empty_context)
+        Ok(None)
     }
     
-    /// Parse macro call arguments
-    #[allow(dead_code)]
-    fn parse_macro_call_args(&self, call_content: &str) -> TemplateResult<HashMap<String, String>> {
-        // Parse: macro_name(arg1, arg2="value", param="value")
-        if let Some(paren_start) = call_content.find('(') {
-            if let Some(paren_end) = call_content.rfind(')') {
-                let args_str = &call_content[paren_start + 1..paren_end];
-                let mut args_map = HashMap::new();
-                let mut positional_index = 0;
-                
-                if !args_str.trim().is_empty() {
-                    // Split arguments, being careful about quotes
-                    let args = self.parse_argument_list(args_str)?;
-                    
-                    for arg in args {
-                        if let Some(eq_pos) = arg.find('=') {
-                            // Named parameter: param="value"
-                            let param_name = arg[..eq_pos].trim().to_string();
-                            let param_value = arg[eq_pos + 1..].trim().trim_matches('"').trim_matches('\'').to_string();
-                            args_map.insert(param_name, param_value);
-                        } else {
-                            // Positional parameter
-                            let param_value = arg.trim().trim_matches('"').trim_matches('\'').to_string();
-                            args_map.insert(positional_index.to_string(), param_value);
-                            positional_index += 1;
-                        }
-                    }
-                }
-                Ok(args_map)
-            } else {
-                Err(TemplateError::Parse("Invalid macro call syntax".to_string()))
-            }
-        } else {
-            Err(TemplateError::Parse("Invalid macro call syntax".to_string()))
+    /// Value of `expression` when it is a `name(...)` call of a registered
+    /// helper or of a function of the [standard library](crate::functions);
+    /// registered helpers come first, dotted names included
+    fn call_function(&self, expression: &str, context: &TemplateContext) -> TemplateResult<Option<TemplateValue>> {
+        let expression = expression.trim();
+        let Some((name, arguments)) = split_call(expression) else {
+            return Ok(None);
+        };
+        if let Some(helper) = self.helpers.get(name) {
+            let args = self.parse_helper_args(expression, arguments, context)?;
+            return call_helper(name, helper, &args).map(Some);
+        }
+        if functions::is_function(name) {
+            let args = self.parse_helper_args(expression, arguments, context)?;
+            return functions::call(name, &args, |a, b| self.values_equal(a, b), self.max_range_items).map(Some);
         }
+        Ok(None)
     }
     
-    /// Parse argument list, handling quoted strings with commas
-    fn parse_argument_list(&self, args_str: &str) -> TemplateResult<Vec<String>> {
+    /// Parse helper function arguments, `expression[arguments]` of a
+    /// `helper(...)` call; helpers only take positional arguments
+    fn parse_helper_args(&self, expression: &str, arguments: std::ops::Range<usize>, context: &TemplateContext) -> TemplateResult<Vec<TemplateValue>> {
         let mut args = Vec::new();
-        let mut current_arg = String::new();
-        let mut in_quotes = false;
-        let mut quote_char = '"';
-        
-        for ch in args_str.chars() {
-            match ch {
-                '"' | '\'' if !in_quotes => {
-                    in_quotes = true;
-                    quote_char = ch;
-                    current_arg.push(ch);
-                },
-                ch if in_quotes && ch == quote_char => {
-                    in_quotes = false;
-                    current_arg.push(ch);
-                },
-                ',' if !in_quotes => {
-                    args.push(current_arg.trim().to_string());
-                    current_arg.clear();
-                },
-                ch => {
-                    current_arg.push(ch);
-                }
+        for argument in parse_arguments(expression, arguments, Separator::Comma)? {
+            if let Some(name) = argument.name {
+                return Err(TemplateError::Parse(format!(
+                    "Helper arguments are positional; found '{}=' in '{{{{{}}}}}'", name, expression
+                )));
             }
+            let value = self.argument_value(&argument.value, context)?;
+            args.push(value.unwrap_or_else(|| TemplateValue::String(argument.text.to_string())));
         }
-        
-        if !current_arg.trim().is_empty() {
-            args.push(current_arg.trim().to_string());
-        }
-        
         Ok(args)
     }
     
-    /// Check if macro arguments can be resolved in the current context
-    fn can_resolve_macro_args(&self, call_content: &str, context: &TemplateContext) -> TemplateResult<bool> {
-        // Parse: macro_name(arg1, arg2="value", param="value")
-        if let Some(paren_start) = call_content.find('(') {
-            if let Some(paren_end) = call_content.rfind(')') {
-                let args_str = &call_content[paren_start + 1..paren_end];
-                
-                if !args_str.trim().is_empty() {
-                    // Split arguments, being careful about quotes
-                    let args = self.parse_argument_list(args_str)?;
-                    
-                    for arg in args {
-                        if let Some(eq_pos) = arg.find('=') {
-                            // Named parameter: param="value"
-                            let param_value_str = arg[eq_pos + 1..].trim();
-                            if !param_value_str.starts_with('"') && !param_value_str.starts_with('\'') {
-                                // Variable reference - check if it exists in context
-                                if !self.variable_exists_in_context(param_value_str, context) {
-                                    return Ok(false);
-                                }
-                            }
-                        } else {
-                            // Positional parameter
-                            let arg_trimmed = arg.trim();
-                            if !arg_trimmed.starts_with('"') && !arg_trimmed.starts_with('\'') {
-                                // Variable reference - check if it exists in context
-                                if !self.variable_exists_in_context(arg_trimmed, context) {
-                                    return Ok(false);
-                                }
-                            }
-                        }
-                    }
+    /// Parse a single helper argument (string literal, number, variable, etc.);
+    /// text that is neither a literal nor a known variable is kept as a string
+    fn parse_single_helper_arg(&self, arg: &str, context: &TemplateContext) -> TemplateValue {
+        let arg = arg.trim();
+        parse_value(arg).ok()
+            .and_then(|value| self.argument_value(&value, context).ok().flatten())
+            .unwrap_or_else(|| TemplateValue::String(arg.to_string()))
+    }
+    
+    /// Value of a parsed argument; `None` for a variable missing from the context
+    fn argument_value(&self, value: &ArgumentValue, context: &TemplateContext) -> TemplateResult<Option<TemplateValue>> {
+        Ok(Some(match value {
+            ArgumentValue::String(text) => TemplateValue::String(text.clone()),
+            ArgumentValue::Number(number) => TemplateValue::Number(*number),
+            ArgumentValue::Float(number) => TemplateValue::Float(*number),
+            ArgumentValue::Bool(flag) => TemplateValue::Bool(*flag),
+            ArgumentValue::Expression(expression) if expression.contains('|') => {
+                self.evaluate_filter_expression(expression, context)?
+            }
+            ArgumentValue::Expression(expression) if expression.ends_with(')') => {
+                match self.call_function(expression, context)? {
+                    Some(value) => value,
+                    None => return Ok(None),
                 }
-                Ok(true)
-            } else {
-                Err(TemplateError::Parse("Invalid macro call syntax".to_string()))
             }
-        } else {
-            Err(TemplateError::Parse("Invalid macro call syntax".to_string()))
-        }
+            ArgumentValue::Expression(path) if self.variable_exists_in_context(path, context) => {
+                self.resolve_variable_from_context(path, context)
+            }
+            ArgumentValue::Expression(path) => {
+                self.record_read(path);
+                return Ok(None);
+            }
+        }))
     }
     
-    /// Check if a variable exists in the context (handles dot notation)
-    fn variable_exists_in_context(&self, variable_name: &str, context: &TemplateContext) -> bool {
-        if variable_name.contains('.') {
-            let parts: Vec<&str> = variable_name.split('.').collect();
-            context.get(parts[0]).is_some()
-        } else {
-            context.get(variable_name).is_some()
-        }
-    }
-
-    /// Parse macro call arguments with context resolution
-    fn parse_macro_call_args_with_context(&self, call_content: &str, context: &TemplateContext) -> TemplateResult<HashMap<String, TemplateValue>> {
-        // Parse: macro_name(arg1, arg2="value", param="value")
-        if let Some(paren_start) = call_content.find('(') {
-            if let Some(paren_end) = call_content.rfind(')') {
-                let args_str = &call_content[paren_start + 1..paren_end];
-                let mut args_map = HashMap::new();
-                let mut positional_index = 0;
-                
-                if !args_str.trim().is_empty() {
-                    // Split arguments, being careful about quotes
-                    let args = self.parse_argument_list(args_str)?;
-                    
-                    for arg in args {
-                        if let Some(eq_pos) = arg.find('=') {
-                            // Named parameter: param="value"
-                            let param_name = arg[..eq_pos].trim().to_string();
-                            let param_value_str = arg[eq_pos + 1..].trim();
-                            let param_value = if param_value_str.starts_with('"') || param_value_str.starts_with('\'') {
-                                // String literal
-                                TemplateValue::String(param_value_str.trim_matches('"').trim_matches('\'').to_string())
-                            } else {
-                                // Variable reference - resolve from context
-                                self.resolve_variable_from_context(param_value_str, context)
-                            };
-                            args_map.insert(param_name, param_value);
-                        } else {
-                            // Positional parameter - resolve from context
-                            let arg_trimmed = arg.trim();
-                            let param_value = if arg_trimmed.starts_with('"') || arg_trimmed.starts_with('\'') {
-                                // String literal
-                                TemplateValue::String(arg_trimmed.trim_matches('"').trim_matches('\'').to_string())
-                            } else {
-                                // Variable reference - resolve from context
-                                self.resolve_variable_from_context(arg_trimmed, context)
-                            };
-                            args_map.insert(positional_index.to_string(), param_value);
-                            positional_index += 1;
-                        }
-                    }
-                }
-                Ok(args_map)
-            } else {
-                Err(TemplateError::Parse("Invalid macro call syntax".to_string()))
+    /// Convert TemplateValue to string for output
+    #[allow(clippy::only_used_in_recursion)]
+    fn template_value_to_string(&self, value: &TemplateValue) -> String {
+        match value {
+            TemplateValue::String(s) => s.clone(),
+            TemplateValue::Number(n) => n.to_string(),
+            TemplateValue::Float(f) => f.to_string(),
+            TemplateValue::Bool(b) => b.to_string(),
+            TemplateValue::Null => String::new(),
+            TemplateValue::Array(arr) => {
+                let items: Vec<String> = arr.iter().map(|v| self.template_value_to_string(v)).collect();
+                format!("[{}]", items.join(", "))
+            },
+            TemplateValue::Object(obj) => {
+                // Keys are sorted so the same object always renders identically
+                let mut keys: Vec<&String> = obj.keys().collect();
+                keys.sort();
+                let pairs: Vec<String> = keys.into_iter()
+                    .map(|k| format!("{}: {}", k, self.template_value_to_string(&obj[k])))
+                    .collect();
+                format!("{{{}}}", pairs.join(", "))
             }
-        } else {
-            Err(TemplateError::Parse("Invalid macro call syntax".to_string()))
         }
     }
 
-    /// Resolve variable from context (handles nested properties)
-    fn resolve_variable_from_context(&self, variable_name: &str, context: &TemplateContext) -> TemplateValue {
-        if variable_name.contains('.') {
-            // Handle nested property access
-            let parts: Vec<&str> = variable_name.split('.').collect();
-            if let Some(root_value) = context.get(parts[0]) {
-                self.get_nested_value(root_value, &parts[1..])
-            } else {
-                TemplateValue::String(String::new())
-            }
+    /// Get variable value with support for deep dot notation and filters
+    fn get_variable_value(&self, var_name: &str, context: &TemplateContext) -> TemplateResult<String> {
+        // Check if filters are present (contains |)
+        if var_name.contains('|') {
+            return self.apply_filters(var_name, context);
+        }
+        
+        self.record_read(var_name);
+        let value = if var_name.contains('.') {
+            let parts: Vec<&str> = var_name.split('.').collect();
+            context.get(parts[0]).and_then(|root_value| lookup_nested(root_value, &parts[1..]))
         } else {
-            // Simple variable
-            context.get(variable_name).unwrap_or(&TemplateValue::String(String::new())).clone()
+            context.get(var_name)
+        };
+        match value {
+            Some(TemplateValue::Bool(b)) => Ok(self.format_bool(*b)),
+            Some(value) => self.stringify_value(value, filter_value_to_string, || format!("'{}'", var_name)),
+            None => Ok(self.format_missing()),
         }
     }
     
-    /// Expand macro with given arguments - handles both String and TemplateValue args
-    #[allow(dead_code)]
-    fn expand_macro(&mut self, macro_def: &MacroDefinition, args: &HashMap<String, String>) -> TemplateResult<String> {
-        let macro_body = macro_def.body.clone();
-        
-        // Create a temporary context with macro parameters
-        let mut macro_context = TemplateContext::new();
-        
-        // Add macro parameters to the context
-        for (i, param) in macro_def.parameters.iter().enumerate() {
-            let value = if let Some(named_value) = args.get(param) {
-                // Treat as string (no JSON parsing needed)
-                TemplateValue::String(named_value.clone())
-            } else if let Some(positional_value) = args.get(&i.to_string()) {
-                // Treat as string
-                TemplateValue::String(positional_value.clone())
-            } else {
-                TemplateValue::String(String::new())
-            };
-            
-            macro_context.set(param, value);
+    /// Apply filters to a variable (e.g., "name|upper|truncate:10")
+    fn apply_filters(&self, expression: &str, context: &TemplateContext) -> TemplateResult<String> {
+        match self.evaluate_filter_expression(expression, context)? {
+            TemplateValue::Bool(b) => Ok(self.format_bool(b)),
+            value => self.stringify_value(&value, filter_value_to_string, || format!("'{}'", expression)),
         }
-        
-        // Process the macro body with the macro context
-        self.render_string(&macro_body, &macro_context)
     }
-
-    /// Expand macro with TemplateValue arguments (new method for context-aware calls)
-    fn expand_macro_with_values(&mut self, macro_def: &MacroDefinition, args: &HashMap<String, TemplateValue>) -> TemplateResult<String> {
-        let macro_body = macro_def.body.clone();
+    
+    /// Evaluate a filter chain to a typed value, so coercions such as
+    /// `qty|int` can take part in comparisons, arithmetic and plural counts
+    fn evaluate_filter_expression(&self, expression: &str, context: &TemplateContext) -> TemplateResult<TemplateValue> {
+        let parts: Vec<&str> = expression.split('|').collect();
         
-        // Create a temporary context with macro parameters
-        let mut macro_context = TemplateContext::new();
+        // Get the initial variable value
+        let path = parts[0].trim();
+        let mut value = match self.call_function(path, context)? {
+            Some(value) => value,
+            None => self.resolve_variable_from_context(path, context),
+        };
+        let original = value.clone();
         
-        // Add macro parameters to the context
-        for (i, param) in macro_def.parameters.iter().enumerate() {
-            let value = if let Some(named_value) = args.get(param) {
-                named_value.clone()
-            } else if let Some(positional_value) = args.get(&i.to_string()) {
-                positional_value.clone()
-            } else {
-                TemplateValue::String(String::new())
-            };
-            
-            macro_context.set(param, value);
+        // Apply each filter in sequence
+        for filter_expr in &parts[1..] {
+            let filter_expr = self.bind_filter_arguments(filter_expr.trim(), context)?;
+            value = self.apply_value_filter(value, &filter_expr)
+                .map_err(|error| self.redact_filter_error(error, path, &original))?;
         }
         
-        // Process the macro body with the macro context
-        self.render_string(&macro_body, &macro_context)
+        Ok(value)
     }
     
-    /// Check if the variable expression uses HTML-producing filters
-    fn uses_html_producing_filter(&self, var_expression: &str) -> bool {
-        let html_filters = ["markdown", "highlight"];
-        
-        if let Some(_filter_part) = var_expression.split('|').nth(1) {
-            let filters: Vec<&str> = var_expression.split('|').skip(1).collect();
-            for filter_expr in filters {
-                let filter_name = filter_expr.split(':').next().unwrap_or("").trim();
-                if html_filters.contains(&filter_name) {
-                    return true;
-                }
+    /// Keep the filtered value out of a filter error unless the redaction
+    /// policy shows the variable it came from
+    fn redact_filter_error(&self, error: TemplateError, path: &str, original: &TemplateValue) -> TemplateError {
+        match error {
+            TemplateError::FilterError { filter_name, value, message } if (self.redaction)(path, original) != Redact::Show => {
+                let shown = match (self.redaction)(path, original) {
+                    Redact::Hide => HIDDEN_PLACEHOLDER.to_string(),
+                    _ => mask_placeholder(&TemplateValue::String(value.clone())),
+                };
+                let message = if value.is_empty() { message } else { message.replace(&value, &shown) };
+                TemplateError::FilterError { filter_name, value: shown, message }
             }
+            error => error,
         }
-        
-        false
     }
-
-    /// Evaluate a condition
-    fn evaluate_condition(&self, condition: &str, context: &TemplateContext) -> bool {
-        let condition = condition.trim();
-        
-        // Check for comparison operators
-        if let Some(result) = self.evaluate_comparison(condition, context) {
-            return result;
+    
+    /// Replace the variable arguments of a filter step (`truncate:limit`)
+    /// with their quoted values. Names missing from the context stay as
+    /// written; in strict mode a value, or a missing name, that a built-in
+    /// filter can't take raises [`TemplateError::FilterError`].
+    fn bind_filter_arguments<'a>(&self, filter_expr: &'a str, context: &TemplateContext) -> TemplateResult<Cow<'a, str>> {
+        let (filter_name, args) = split_filter_arguments(filter_expr);
+        if !args.iter().any(|arg| is_variable_path(arg) || split_call(arg).is_some()) {
+            return Ok(Cow::Borrowed(filter_expr));
         }
         
-        // Support both simple variables and deep dot notation in conditionals
-        if condition.contains('.') {
-            let parts: Vec<&str> = condition.split('.').collect();
-            if let Some(root_value) = context.variables.get(parts[0]) {
-                return self.evaluate_nested_condition(root_value, &parts[1..]);
+        let signature = filter_signature(filter_name);
+        let mut bound = filter_name.to_string();
+        for arg in args {
+            bound.push(':');
+            let called = self.call_function(arg, context)?;
+            if called.is_none() && !is_variable_path(arg) {
+                bound.push_str(arg);
+            } else if called.is_some() || self.variable_exists_in_context(arg, context) {
+                let value = match called {
+                    Some(value) => value,
+                    None => self.resolve_variable_from_context(arg, context),
+                };
+                let text = filter_value_to_string(&value);
+                if let Some(message) = signature.and_then(|signature| argument_type_problem(filter_name, &signature, &text)) {
+                    if self.strict_mode {
+                        let shown = self.surfaced_value(arg, &value, filter_value_to_string);
+                        return Err(TemplateError::FilterError {
+                            filter_name: filter_name.to_string(),
+                            message: message.replace(&text, &shown),
+                            value: shown,
+                        });
+                    }
+                }
+                bound.push('"');
+                bound.push_str(&text);
+                bound.push('"');
+            } else if self.strict_mode && signature.is_some_and(|signature| signature.argument != FilterArgument::Text) {
+                return Err(TemplateError::FilterError {
+                    filter_name: filter_name.to_string(),
+                    value: arg.to_string(),
+                    message: format!("argument '{}' is not in the context", arg),
+                });
+            } else {
+                bound.push_str(arg);
             }
-            false
-        } else if let Some(value) = context.variables.get(condition) {
-            self.is_truthy(value)
-        } else {
-            false
         }
+        Ok(Cow::Owned(bound))
     }
     
-    /// Evaluate comparison expressions like "x == y", "count > 5", etc.
-    fn evaluate_comparison(&self, condition: &str, context: &TemplateContext) -> Option<bool> {
-        // List of operators to check, ordered by length (longest first to avoid conflicts)
-        let operators = ["==", "!=", "<=", ">=", "<", ">"];
-        
-        for op in &operators {
-            if let Some(op_pos) = condition.find(op) {
-                let left_expr = condition[..op_pos].trim();
-                let right_expr = condition[op_pos + op.len()..].trim();
-                
-                let left_val = self.get_condition_value(left_expr, context);
-                let right_val = self.get_condition_value(right_expr, context);
-                
-                return Some(match *op {
-                    "==" => self.values_equal(&left_val, &right_val),
-                    "!=" => !self.values_equal(&left_val, &right_val),
-                    "<" => self.compare_values(&left_val, &right_val) < 0,
-                    ">" => self.compare_values(&left_val, &right_val) > 0,
-                    "<=" => self.compare_values(&left_val, &right_val) <= 0,
-                    ">=" => self.compare_values(&left_val, &right_val) >= 0,
-                    _ => false,
-                });
+    /// Apply a single filter to a typed value.
+    /// 
+    /// Coercion filters produce real types:
+    /// - `int`: numbers are truncated, numeric strings parsed, booleans become 0/1
+    /// - `float`: numbers, numeric strings and booleans (0.0/1.0)
+    /// - `bool`: `"false"`, `"0"`, `"no"`, `"off"` and `""` (any case) are false,
+    ///   any other string is true; other values follow truthiness
+    /// - `string`: the rendered text of the value
+    /// 
+    /// `translate` (alias `t`) looks the value up in the current locale, with an
+    /// optional key prefix (`status|t:"statuses."`), keeping the raw value when missing.
+    /// 
+    /// `timeago` shows a Unix timestamp (seconds) relative to the engine clock,
+    /// `3 hours ago` or `in 2 days`.
+    /// 
+    /// `length` counts array items, object fields or characters as readers
+    /// see them (grapheme clusters: `é` with a combining accent, a family
+    /// emoji or a flag count once), the unit `truncate` cuts by.
+    /// `count_chars` counts Unicode code points and `count_bytes` UTF-8 bytes.
+    /// 
+    /// Dashboard formatting, with unit labels from the `units.*_short`
+    /// translation keys of the current locale:
+    /// - `duration[:units]`: seconds as `3d 4h 12m`, at most 3 units by default
+    /// - `filesize[:decimals]`: bytes in binary units, `1.4 GiB` (1 decimal by default)
+    /// - `percent[:decimals]`: a ratio as a percentage, `0.875|percent:1` is `87.5%`
+    /// 
+    /// Arrays keep their items through the chain: `join[:separator]` (`", "`
+    /// by default), `first`, `last` and `slice:start[:end]`
+    /// (`items|slice:0:3|join:" · "`); `sort[:key][:desc]`, `reverse` and
+    /// `unique[:key]` reorder them, in loops too (`{{for p in products|sort:"price"}}`). On text, `first`, `last` and `slice`
+    /// work on characters as `length` counts them.
+    /// 
+    /// `default:value` replaces an empty or missing value with a quoted string
+    /// or a number (`nickname|default:"Anonymous"`, `stock|default:0`).
+    /// 
+    /// `add` and `multiply` keep numbers typed; every other filter works on text.
+    fn apply_value_filter(&self, value: TemplateValue, filter_expr: &str) -> TemplateResult<TemplateValue> {
+        let filter_name = filter_expr.split(':').next().unwrap_or("").trim();
+        if let Some(steps) = self.pipelines.get(filter_name) {
+            let args: Vec<&str> = filter_expr.split(':').skip(1).map(str::trim).collect();
+            let mut value = value;
+            for step in steps {
+                value = self.apply_value_filter(value, &substitute_pipeline_arguments(step, &args))?;
             }
+            return Ok(value);
         }
+        let arg = filter_expr.split(':').nth(1).map(|arg| arg.trim().trim_matches('"').trim_matches('\''));
         
-        None
-    }
-    
-    /// Get the value for a condition expression (variable, string literal, or number)
-    fn get_condition_value(&self, expr: &str, context: &TemplateContext) -> TemplateValue {
-        let expr = expr.trim();
-        
-        // Check if it's a string literal (quoted)
-        if (expr.starts_with('"') && expr.ends_with('"')) || (expr.starts_with('\'') && expr.ends_with('\'')) {
-            return TemplateValue::String(expr[1..expr.len()-1].to_string());
-        }
-        
-        // Check if it's a number literal
-        if let Ok(num) = expr.parse::<i64>() {
-            return TemplateValue::Number(num);
-        }
-        
-        // Check if it's a boolean literal
-        if expr == "true" {
-            return TemplateValue::Bool(true);
-        } else if expr == "false" {
-            return TemplateValue::Bool(false);
-        }
-        
-        // Otherwise treat as variable name (with possible dot notation)
-        if expr.contains('.') {
-            let parts: Vec<&str> = expr.split('.').collect();
-            if let Some(root_value) = context.variables.get(parts[0]) {
-                return self.get_nested_value(root_value, &parts[1..]);
-            }
-        } else if let Some(value) = context.variables.get(expr) {
-            return value.clone();
-        }
-        
-        // Default to empty string if not found
-        TemplateValue::String(String::new())
-    }
-    
-    /// Get nested value from object traversal
-    #[allow(clippy::only_used_in_recursion)]
-    fn get_nested_value(&self, current_value: &TemplateValue, remaining_parts: &[&str]) -> TemplateValue {
-        if remaining_parts.is_empty() {
-            return current_value.clone();
-        }
-
-        let current_part = remaining_parts[0];
-        let next_parts = &remaining_parts[1..];
-
-        match current_value {
-            TemplateValue::Object(obj) => {
-                if let Some(next_value) = obj.get(current_part) {
-                    self.get_nested_value(next_value, next_parts)
-                } else {
-                    TemplateValue::String(String::new())
+        match (filter_name, &value) {
+            ("int", _) => match coerce_to_int(&value) {
+                Some(n) => Ok(TemplateValue::Number(n)),
+                None => self.invalid_coercion("int", &value, "cannot convert to an integer"),
+            },
+            ("float", _) => match coerce_to_float(&value) {
+                Some(f) => Ok(TemplateValue::Float(f)),
+                None => self.invalid_coercion("float", &value, "cannot convert to a float"),
+            },
+            ("bool", _) => Ok(TemplateValue::Bool(self.coerce_to_bool(&value))),
+            ("timeago", _) => match coerce_to_int(&value) {
+                Some(timestamp) => Ok(TemplateValue::String(format_time_ago(self.unix_now() - timestamp))),
+                None => self.invalid_coercion("timeago", &value, "expected a Unix timestamp in seconds"),
+            },
+            ("duration" | "filesize" | "percent", _) => {
+                let Some(number) = coerce_to_float(&value) else {
+                    return self.invalid_coercion(filter_name, &value, "expected a number");
+                };
+                let precision = arg.and_then(|arg| arg.parse::<usize>().ok());
+                let label = |key: &str, default: &str| self.translation_or(&format!("units.{}_short", key), default);
+                Ok(TemplateValue::String(match filter_name {
+                    "duration" => format_duration(number, precision.unwrap_or(3), label),
+                    "filesize" => format_file_size(number, precision.unwrap_or(1), label),
+                    _ => format!("{:.*}{}", precision.unwrap_or(0), number * 100.0, label("percent", "%")),
+                }))
+            },
+            ("length", TemplateValue::Array(items)) => Ok(TemplateValue::Number(items.len() as i64)),
+            ("length", TemplateValue::Object(fields)) => Ok(TemplateValue::Number(fields.len() as i64)),
+            // Characters as readers count them; see the `graphemes` module
+            ("length" | "count_chars" | "count_bytes", _) => {
+                let text = self.stringify_value(&value, filter_value_to_string, || format!("passed to filter '{}'", filter_name))?;
+                let count = match filter_name {
+                    "count_chars" => text.chars().count(),
+                    "count_bytes" => text.len(),
+                    _ => grapheme_count(&text),
+                };
+                Ok(TemplateValue::Number(count as i64))
+            },
+            ("yesno", _) => Ok(TemplateValue::String(self.format_yesno(&value, filter_expr))),
+            // Escaping is decided where the value is written, see `filter_escaping`
+            ("safe" | "escape" | "e", _) => Ok(value),
+            // A registered filter of the same name replaces these
+            ("sort" | "reverse" | "unique", _) if !self.custom_filters.contains_key(filter_name) => {
+                Ok(self.reorder_items(filter_name, value, filter_expr))
+            },
+            ("join", TemplateValue::Array(items)) => {
+                let (_, args) = split_filter_arguments(filter_expr);
+                let separator = args.first().map_or(", ", |separator| separator.trim_matches('"').trim_matches('\''));
+                let mut texts = Vec::with_capacity(items.len());
+                for item in items {
+                    texts.push(self.stringify_value(item, filter_value_to_string, || "joined by filter 'join'".to_string())?);
                 }
-            }
-            TemplateValue::Array(arr) => {
-                if let Ok(index) = current_part.parse::<usize>() {
-                    if let Some(element) = arr.get(index) {
-                        self.get_nested_value(element, next_parts)
-                    } else {
-                        TemplateValue::String(String::new())
+                Ok(TemplateValue::String(texts.join(separator)))
+            },
+            ("join", _) => Ok(value),
+            ("first", TemplateValue::Array(items)) => Ok(items.first().cloned().unwrap_or_else(|| TemplateValue::String(String::new()))),
+            ("last", TemplateValue::Array(items)) => Ok(items.last().cloned().unwrap_or_else(|| TemplateValue::String(String::new()))),
+            ("slice", _) => {
+                let (_, args) = split_filter_arguments(filter_expr);
+                let index = |n: usize| args.get(n).and_then(|arg| arg.trim_matches('"').parse::<usize>().ok());
+                let start = index(0).unwrap_or(0);
+                let end = index(1).unwrap_or(usize::MAX).max(start);
+                match value {
+                    TemplateValue::Array(items) => {
+                        Ok(TemplateValue::Array(items.into_iter().skip(start).take(end - start).collect()))
+                    }
+                    value => {
+                        let text = self.stringify_value(&value, filter_value_to_string, || "passed to filter 'slice'".to_string())?;
+                        Ok(TemplateValue::String(graphemes(&text).skip(start).take(end - start).collect()))
                     }
-                } else {
-                    TemplateValue::String(String::new())
                 }
-            }
-            _ => TemplateValue::String(String::new()),
-        }
-    }
-    
-    /// Check if two values are equal
-    fn values_equal(&self, left: &TemplateValue, right: &TemplateValue) -> bool {
-        match (left, right) {
-            (TemplateValue::String(a), TemplateValue::String(b)) => a == b,
-            (TemplateValue::Number(a), TemplateValue::Number(b)) => a == b,
-            (TemplateValue::Bool(a), TemplateValue::Bool(b)) => a == b,
-            (TemplateValue::Array(a), TemplateValue::Array(b)) => a.len() == b.len(),
-            (TemplateValue::Object(a), TemplateValue::Object(b)) => a.len() == b.len(),
-            // Type coercion: convert to strings and compare
-            _ => self.value_to_string(left) == self.value_to_string(right),
-        }
-    }
-    
-    /// Compare two values for ordering (-1, 0, 1)
-    fn compare_values(&self, left: &TemplateValue, right: &TemplateValue) -> i32 {
-        match (left, right) {
-            (TemplateValue::Number(a), TemplateValue::Number(b)) => {
-                a.cmp(b) as i32
-            }
-            (TemplateValue::String(a), TemplateValue::String(b)) => {
-                a.cmp(b) as i32
-            }
-            // For other types, convert to strings and compare
+            },
+            // Strings give their first or last character as readers see it
+            ("first" | "last", _) => {
+                let text = self.stringify_value(&value, filter_value_to_string, || format!("passed to filter '{}'", filter_name))?;
+                let mut clusters = graphemes(&text);
+                let cluster = if filter_name == "first" { clusters.next() } else { clusters.last() };
+                Ok(TemplateValue::String(cluster.unwrap_or_default().to_string()))
+            },
+            ("default", TemplateValue::Null) | ("default", TemplateValue::String(_)) if filter_value_to_string(&value).is_empty() => {
+                let (_, args) = split_filter_arguments(filter_expr);
+                Ok(match args.first().map(|arg| parse_value(arg)) {
+                    Some(Ok(ArgumentValue::String(text))) => TemplateValue::String(text),
+                    Some(Ok(ArgumentValue::Number(number))) => TemplateValue::Number(number),
+                    Some(Ok(ArgumentValue::Float(number))) => TemplateValue::Float(number),
+                    Some(Ok(ArgumentValue::Bool(flag))) => TemplateValue::Bool(flag),
+                    _ => TemplateValue::String(args.first().map_or_else(String::new, |arg| arg.to_string())),
+                })
+            },
+            ("default", _) => Ok(value),
+            ("translate" | "t", _) => {
+                let raw = self.stringify_value(&value, filter_value_to_string, || format!("passed to filter '{}'", filter_name))?;
+                let key = format!("{}{}", arg.unwrap_or_default(), raw);
+                Ok(TemplateValue::String(self.translation_or(&key, &raw)))
+            },
+            ("string", _) => Ok(TemplateValue::String(self.template_value_to_string(&value))),
+            ("add" | "multiply", TemplateValue::Number(_) | TemplateValue::Float(_)) => {
+                let Some(operand) = arg.and_then(|a| a.parse::<f64>().ok()) else {
+                    return Ok(value);
+                };
+                let int_operand = arg.and_then(|a| a.parse::<i64>().ok());
+                let result = match (&value, int_operand) {
+                    (TemplateValue::Number(n), Some(m)) if filter_name == "add" => n.checked_add(m).map(TemplateValue::Number),
+                    (TemplateValue::Number(n), Some(m)) => n.checked_mul(m).map(TemplateValue::Number),
+                    _ => None,
+                };
+                Ok(result.unwrap_or_else(|| {
+                    let current = coerce_to_float(&value).unwrap_or_default();
+                    if filter_name == "add" {
+                        TemplateValue::Float(current + operand)
+                    } else {
+                        TemplateValue::Float(current * operand)
+                    }
+                }))
+            },
+            // Floats are always amounts in dollars, never cents
+            ("currency", TemplateValue::Float(f)) => Ok(TemplateValue::String(format!("${:.2}", f))),
             _ => {
-                let a_str = self.value_to_string(left);
-                let b_str = self.value_to_string(right);
-                a_str.cmp(&b_str) as i32
+                let text = self.stringify_value(&value, filter_value_to_string, || format!("passed to filter '{}'", filter_name))?;
+                Ok(TemplateValue::String(self.apply_single_filter(&text, filter_expr)?))
             }
         }
     }
     
-    /// Convert TemplateValue to string for comparisons
-    fn value_to_string(&self, value: &TemplateValue) -> String {
+    /// `sort[:key][:desc]`, `reverse` and `unique[:key]`; keys are dot paths
+    /// into the items. Sorting is stable, so items with equal keys keep their
+    /// order; `unique` keeps the first of the items equal as `==` compares
+    /// them. Text is reversed by character and other values are unchanged.
+    fn reorder_items(&self, filter_name: &str, value: TemplateValue, filter_expr: &str) -> TemplateValue {
+        let (_, args) = split_filter_arguments(filter_expr);
+        let key: Option<Vec<&str>> = args.iter()
+            .find(|arg| !matches!(**arg, "asc" | "desc"))
+            .map(|key| key.trim_matches('"').trim_matches('\'').split('.').collect());
+        let key = key.as_deref();
+        
         match value {
-            TemplateValue::String(s) => s.clone(),
-            TemplateValue::Number(n) => n.to_string(),
-            TemplateValue::Bool(b) => b.to_string(),
-            TemplateValue::Array(_) => "[Array]".to_string(),
-            TemplateValue::Object(_) => "[Object]".to_string(),
-        }
-    }
-
-    /// Evaluate condition for nested properties
-    fn evaluate_nested_condition(&self, current_value: &TemplateValue, remaining_parts: &[&str]) -> bool {
-        if remaining_parts.is_empty() {
-            return self.is_truthy(current_value);
-        }
-
-        let current_part = remaining_parts[0];
-        let next_parts = &remaining_parts[1..];
-
-        match current_value {
-            TemplateValue::Object(obj) => {
-                if let Some(next_value) = obj.get(current_part) {
-                    self.evaluate_nested_condition(next_value, next_parts)
-                } else {
-                    false // Property not found
-                }
-            }
-            TemplateValue::Array(arr) => {
-                // Support array indexing in conditionals too
-                if let Ok(index) = current_part.parse::<usize>() {
-                    if let Some(element) = arr.get(index) {
-                        self.evaluate_nested_condition(element, next_parts)
-                    } else {
-                        false // Index out of bounds
+            TemplateValue::Array(mut items) => {
+                match filter_name {
+                    "sort" => {
+                        let descending = args.contains(&"desc");
+                        items.sort_by(|a, b| {
+                            let order = sort_order(item_key(a, key), item_key(b, key));
+                            if descending { order.reverse() } else { order }
+                        });
+                    }
+                    "reverse" => items.reverse(),
+                    _ => {
+                        let mut unique: Vec<TemplateValue> = Vec::with_capacity(items.len());
+                        for item in items {
+                            if !unique.iter().any(|kept| self.values_equal(item_key(kept, key), item_key(&item, key))) {
+                                unique.push(item);
+                            }
+                        }
+                        items = unique;
                     }
-                } else {
-                    false // Invalid array index
                 }
+                TemplateValue::Array(items)
             }
-            _ => false, // Can't traverse further
-        }
-    }
-
-    /// Check if a value is truthy
-    fn is_truthy(&self, value: &TemplateValue) -> bool {
-        match value {
-            TemplateValue::Bool(b) => *b,
-            TemplateValue::String(s) => !s.is_empty(),
-            TemplateValue::Number(n) => *n != 0,
-            TemplateValue::Array(a) => !a.is_empty(),
-            TemplateValue::Object(o) => !o.is_empty(),
-        }
-    }
-
-    /// Render a loop
-    fn render_loop(&mut self, item_var: &str, array_var: &str, block: &str, context: &TemplateContext) -> TemplateResult<String> {
-        if let Some(TemplateValue::Array(items)) = context.variables.get(array_var) {
-            let mut result = String::new();
-            
-            for item in items {
-                let mut loop_context = context.clone();
-                loop_context.set(item_var, item.clone());
-                
-                // Process macro calls within the loop context (so they have access to loop variables)
-                let mut processed_block = self.process_macro_calls_with_context(block, &loop_context)?;
-                
-                // Process nested loops within the loop context (IMPORTANT for nested loops support)
-                processed_block = self.process_loops(&processed_block, &loop_context)?;
-                
-                // Process conditionals within the loop context
-                processed_block = self.process_conditionals(&processed_block, &loop_context)?;
-                
-                // Then process variables
-                processed_block = self.process_variables(&processed_block, &loop_context)?;
-                
-                result.push_str(&processed_block);
-            }
-            
-            Ok(result)
-        } else {
-            // Check if the array_var looks like a function call (contains parentheses)
-            if array_var.contains('(') && array_var.contains(')') {
-                return Err(TemplateError::Template(format!("Function '{}' is not supported", array_var)));
+            TemplateValue::String(text) if filter_name == "reverse" => {
+                let mut clusters: Vec<&str> = graphemes(&text).collect();
+                clusters.reverse();
+                TemplateValue::String(clusters.concat())
             }
-            // For regular variables (missing or non-array), maintain backward compatibility by returning empty string
-            Ok(String::new())
+            value => value,
         }
     }
     
-    /// Validate template path to prevent path traversal attacks
-    fn validate_template_path(&self, name: &str) -> TemplateResult<()> {
-        // Check for obvious path traversal patterns
-        if name.contains("..") {
-            return Err(TemplateError::Security("Path traversal attempt detected".to_string()));
-        }
-        
-        // Check for absolute paths
-        if name.starts_with('/') || name.starts_with('\\') {
-            return Err(TemplateError::Security("Absolute path not allowed".to_string()));
-        }
-        
-        // Check for Windows drive letters
-        if name.len() >= 3 && name.chars().nth(1) == Some(':') {
-            return Err(TemplateError::Security("Drive letter path not allowed".to_string()));
-        }
-        
-        // Resolve the path and check if it stays within the template directory
-        let template_dir = Path::new(&self.template_dir).canonicalize()
-            .map_err(|_| TemplateError::Security("Invalid template directory".to_string()))?;
-        
-        let requested_path = template_dir.join(name).canonicalize();
-        
-        match requested_path {
-            Ok(resolved_path) => {
-                if !resolved_path.starts_with(&template_dir) {
-                    return Err(TemplateError::Security("Path traversal attempt detected".to_string()));
-                }
-                Ok(())
-            }
-            Err(_) => {
-                // Path doesn't exist or can't be resolved - this is OK for now, 
-                // the actual file read will handle the error appropriately
-                Ok(())
+    /// Truthiness used by the `bool` and `yesno` filters
+    fn coerce_to_bool(&self, value: &TemplateValue) -> bool {
+        match value {
+            TemplateValue::String(s) => {
+                let falsy = ["false", "0", "no", "off", ""];
+                !falsy.contains(&s.trim().to_lowercase().as_str())
             }
+            _ => self.is_truthy(value),
         }
     }
     
-    /// Find the matching {{/for}} for nested loops using stack-based parsing
-    fn find_matching_for_end(&self, content: &str) -> TemplateResult<usize> {
-        let mut depth = 1; // We start at depth 1 since we're already inside a {{for}}
-        let mut pos = 0;
+    /// `yesno:"Yes":"No":"Unknown"` - the third word is used for missing
+    /// (empty) values and falls back to the "no" word. Without arguments the
+    /// words come from the `yes`/`no`/`none` translation keys of the current locale.
+    fn format_yesno(&self, value: &TemplateValue, filter_expr: &str) -> String {
+        let args: Vec<String> = filter_expr.split(':').skip(1)
+            .map(|arg| arg.trim().trim_matches('"').trim_matches('\'').to_string())
+            .collect();
+        let (yes, no) = match (args.first(), args.get(1)) {
+            (Some(yes), Some(no)) => (yes.clone(), no.clone()),
+            _ => self.yes_no_words(),
+        };
         
-        while pos < content.len() && depth > 0 {
-            if let Some(for_pos) = content[pos..].find("{{for ") {
-                let actual_for_pos = pos + for_pos;
-                
-                // Check for {{/for}} before this {{for}}
-                if let Some(end_for_pos) = content[pos..pos + for_pos].find("{{/for}}") {
-                    let actual_end_for_pos = pos + end_for_pos;
-                    depth -= 1;
-                    if depth == 0 {
-                        return Ok(actual_end_for_pos);
-                    }
-                    pos = actual_end_for_pos + 8; // Move past {{/for}}
-                    continue;
-                }
-                
-                // Found a nested {{for}}, increase depth
-                depth += 1;
-                pos = actual_for_pos + 6; // Move past {{for 
-            } else if let Some(end_for_pos) = content[pos..].find("{{/for}}") {
-                let actual_end_for_pos = pos + end_for_pos;
-                depth -= 1;
-                if depth == 0 {
-                    return Ok(actual_end_for_pos);
-                }
-                pos = actual_end_for_pos + 8; // Move past {{/for}}
-            } else {
-                break; // No more {{for}} or {{/for}} found
+        let is_missing = matches!(value, TemplateValue::String(s) if s.is_empty());
+        if is_missing {
+            match args.get(2) {
+                Some(none) => none.clone(),
+                None if args.is_empty() => self.translation_or("none", &no),
+                None => no,
             }
+        } else if self.coerce_to_bool(value) {
+            yes
+        } else {
+            no
         }
-        
-        Err(TemplateError::Parse("Missing {{/for}} directive".to_string()))
     }
     
+    /// "Yes"/"No", or the current locale's `yes`/`no` translations when present
+    fn yes_no_words(&self) -> (String, String) {
+        (self.translation_or("yes", "Yes"), self.translation_or("no", "No"))
+    }
     
-    // Performance features for TDD
-    
-    /// Render multiple templates in parallel
-    pub fn render_parallel(&mut self, template_names: &[String], context: &TemplateContext) -> TemplateResult<Vec<String>> {
-        let context = Arc::new(context.clone());
-        let template_dir = Arc::new(self.template_dir.clone());
-        
-        let handles: Vec<_> = template_names.iter().map(|name| {
-            let name = name.clone();
-            let context = Arc::clone(&context);
-            let template_dir = Arc::clone(&template_dir);
-            
-            thread::spawn(move || {
-                let mut engine = TemplateEngine::new(&template_dir);
-                engine.render(&name, &context)
-            })
-        }).collect();
-        
-        let mut results = Vec::new();
-        for handle in handles {
-            let result = handle.join().map_err(|_| TemplateError::Render("Thread panic".to_string()))??;
-            results.push(result);
+    /// Translation for `key` in the current locale, or `default` when missing
+    #[cfg(feature = "i18n")]
+    fn translation_or(&self, key: &str, default: &str) -> String {
+        match self.lookup_translation(key) {
+            Some(translation) => self.localized(translation),
+            None => self.missing_translation(key, default),
         }
-        
-        Ok(results)
     }
     
-    /// Load template using memory mapping (minimal implementation)
-    /// In production, this would use memmap2 crate for true memory mapping
-    pub fn load_template_mmap(&mut self, name: &str) -> TemplateResult<String> {
-        // Check cache first for memory efficiency
-        if let Some(cached) = self.cache.get(name) {
-            return Ok(cached.clone());
+    /// Translation of `key` in the current locale, as written in the table
+    #[cfg(feature = "i18n")]
+    fn lookup_translation(&self, key: &str) -> Option<&String> {
+        self.current_locale.as_ref()
+            .and_then(|locale| self.translations.get(locale))
+            .and_then(|translations| translations.get(key))
+    }
+    
+    /// A found translation as the translation mode shows it
+    #[cfg(feature = "i18n")]
+    fn localized(&self, translation: &str) -> String {
+        match self.translation_mode {
+            TranslationMode::PseudoLocalize => pseudo_localize(translation),
+            _ => translation.to_string(),
         }
-
-        // Validate template path to prevent path traversal attacks
-        self.validate_template_path(name)?;
-
-        let path = Path::new(&self.template_dir).join(name);
-        let content = fs::read_to_string(&path)
-            .map_err(|e| TemplateError::Template(format!("Failed to mmap template '{}': {}", name, e)))?;
-
-        // In a real implementation with memmap2:
-        // let file = File::open(&path)?;
-        // let mmap = unsafe { MmapOptions::new().map(&file)? };
-        // let content = std::str::from_utf8(&mmap)?;
-        
-        self.cache.insert(name.to_string(), content.clone());
-        Ok(content)
     }
     
-    /// Compile template to bytecode
-    pub fn compile_to_bytecode(&mut self, template_name: &str) -> TemplateResult<CompiledTemplate> {
-        if self.bytecode_cache_enabled {
-            if let Some(cached) = self.bytecode_cache.get(template_name) {
-                return Ok(cached.clone());
+    /// Note a missing key for `missing_translations`; returns what to render
+    /// instead, `fallback` or a `⟦key⟧` marker
+    #[cfg(feature = "i18n")]
+    fn missing_translation(&self, key: &str, fallback: &str) -> String {
+        if let Ok(mut missing) = self.missing_translations.lock() {
+            match missing.iter_mut().find(|missing| missing.key == key) {
+                Some(missing) => missing.count += 1,
+                None => missing.push(MissingTranslation { key: key.to_string(), locale: self.current_locale.clone(), count: 1 }),
             }
         }
-        
-        let template_content = self.load_template(template_name)?;
-        let instructions = self.compiler.compile(&template_content)?;
-        let compiled = CompiledTemplate::new(template_name.to_string(), instructions);
-        
-        if self.bytecode_cache_enabled {
-            self.bytecode_cache.insert(template_name.to_string(), compiled.clone());
+        match self.translation_mode {
+            TranslationMode::Normal => fallback.to_string(),
+            _ => format!("⟦{}⟧", key),
         }
-        
-        Ok(compiled)
     }
     
-    /// Compile template to bytecode without caching
-    pub fn compile_to_bytecode_uncached(&mut self, template_name: &str) -> TemplateResult<CompiledTemplate> {
-        let template_content = self.load_template(template_name)?;
-        let instructions = self.compiler.compile(&template_content)?;
-        Ok(CompiledTemplate::new(template_name.to_string(), instructions))
+    /// Without translations every lookup falls back to `default`
+    #[cfg(not(feature = "i18n"))]
+    fn translation_or(&self, _key: &str, default: &str) -> String {
+        default.to_string()
     }
     
-    /// Render compiled template
-    pub fn render_compiled(&self, compiled_template: &CompiledTemplate, context: &TemplateContext) -> TemplateResult<String> {
-        self.executor.execute(&compiled_template.instructions, context)
+    /// Render a bare boolean according to the configured [`BoolFormat`]
+    fn format_bool(&self, value: bool) -> String {
+        if let Some(formatter) = self.value_formatters.get(&ValueKind::Bool) {
+            return formatter.format(&TemplateValue::Bool(value));
+        }
+        match &self.bool_format {
+            BoolFormat::TrueFalse => value.to_string(),
+            BoolFormat::YesNo => {
+                let (yes, no) = self.yes_no_words();
+                if value { yes } else { no }
+            }
+            BoolFormat::Empty => String::new(),
+            BoolFormat::Custom(yes, no) => if value { yes.clone() } else { no.clone() },
+        }
     }
     
-    /// Check if template is cached in bytecode cache
-    pub fn is_bytecode_cached(&self, template_name: &str) -> bool {
-        self.bytecode_cache.contains_key(template_name)
+    /// Render a missing variable or property through the `Null` formatter
+    fn format_missing(&self) -> String {
+        self.value_formatters.get(&ValueKind::Null)
+            .map(ValueFormatter::format_missing)
+            .unwrap_or_default()
     }
     
-    /// Enable or disable bytecode caching
-    pub fn enable_bytecode_cache(&mut self, enabled: bool) {
-        self.bytecode_cache_enabled = enabled;
-        if !enabled {
-            self.bytecode_cache.clear();
+    /// Text of a value for output. Arrays and objects go through the
+    /// formatter of their kind, or `fallback` when none is set; in strict mode
+    /// that is an error naming the value with `describe`.
+    fn stringify_value(
+        &self,
+        value: &TemplateValue,
+        fallback: impl FnOnce(&TemplateValue) -> String,
+        describe: impl FnOnce() -> String,
+    ) -> TemplateResult<String> {
+        let kind = match value {
+            TemplateValue::Array(_) => ValueKind::Array,
+            TemplateValue::Object(_) => ValueKind::Object,
+            scalar => return Ok(filter_value_to_string(scalar)),
+        };
+        match self.value_formatters.get(&kind) {
+            Some(formatter) => Ok(formatter.format(value)),
+            None if self.strict_mode => Err(TemplateError::Render(format!(
+                "Cannot render {} {} as text in strict mode; set a value formatter for ValueKind::{:?}",
+                kind, describe(), kind
+            ))),
+            None => Ok(fallback(value)),
         }
     }
     
-    /// Compile multiple templates in parallel
-    pub fn compile_templates_parallel(&mut self, template_names: &[String]) -> TemplateResult<Vec<CompiledTemplate>> {
-        let template_dir = Arc::new(self.template_dir.clone());
-        
-        let handles: Vec<_> = template_names.iter().map(|name| {
-            let name = name.clone();
-            let template_dir = Arc::clone(&template_dir);
-            
-            thread::spawn(move || {
-                let mut engine = TemplateEngine::new(&template_dir);
-                engine.compile_to_bytecode(&name)
+    /// Handle a failed coercion: an error in strict mode, empty output otherwise
+    fn invalid_coercion(&self, filter_name: &str, value: &TemplateValue, message: &str) -> TemplateResult<TemplateValue> {
+        if self.strict_mode {
+            Err(TemplateError::FilterError {
+                filter_name: filter_name.to_string(),
+                value: self.template_value_to_string(value),
+                message: message.to_string(),
             })
-        }).collect();
-        
-        let mut results = Vec::new();
-        for handle in handles {
-            let result = handle.join().map_err(|_| TemplateError::Render("Thread panic".to_string()))??;
-            results.push(result);
+        } else {
+            Ok(TemplateValue::String(String::new()))
         }
-        
-        Ok(results)
     }
     
-    /// Render multiple compiled templates in parallel
-    pub fn render_compiled_parallel(&self, compiled_templates: &[CompiledTemplate], context: &TemplateContext) -> TemplateResult<Vec<String>> {
-        let context = Arc::new(context.clone());
-        let executor = Arc::new(self.executor.clone());
+    /// Apply a single filter to a value
+    fn apply_single_filter(&self, value: &str, filter_expr: &str) -> TemplateResult<String> {
+        let filter_parts: Vec<&str> = filter_expr.split(':').collect();
+        let filter_name = filter_parts[0];
+        let args: Vec<&str> = if filter_parts.len() > 1 {
+            filter_parts[1..].iter().map(|arg| arg.trim_matches('"').trim_matches('\'')).collect()
+        } else {
+            Vec::new()
+        };
         
-        let handles: Vec<_> = compiled_templates.iter().map(|template| {
-            let template = template.clone();
-            let context = Arc::clone(&context);
-            let executor: Arc<BytecodeExecutor> = Arc::clone(&executor);
-            
-            thread::spawn(move || {
-                executor.execute(&template.instructions, &context)
-            })
-        }).collect();
+        Ok(match filter_name {
+            "upper" => value.to_uppercase(),
+            "lower" => value.to_lowercase(),
+            "capitalize" => {
+                if value.is_empty() {
+                    String::new()
+                } else {
+                    value.split_whitespace()
+                        .map(|word| {
+                            let mut chars: Vec<char> = word.chars().collect();
+                            if !chars.is_empty() {
+                                chars[0] = chars[0].to_uppercase().next().unwrap_or(chars[0]);
+                            }
+                            chars.into_iter().collect::<String>()
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                }
+            },
+            "truncate" => {
+                if let Some(limit_str) = args.first() {
+                    if let Ok(limit) = limit_str.parse::<usize>() {
+                        match grapheme_prefix(value, limit) {
+                            Some(kept) => format!("{}...", kept),
+                            None => value.to_string(),
+                        }
+                    } else {
+                        value.to_string()
+                    }
+                } else {
+                    value.to_string()
+                }
+            },
+            "currency" => {
+                if let Ok(num) = value.parse::<i64>() {
+                    // If number is large (>= 100), treat as cents; otherwise as dollars
+                    if num >= 100 {
+                        format!("${:.2}", num as f64 / 100.0)
+                    } else {
+                        format!("${:.2}", num as f64)
+                    }
+                } else if let Ok(num) = value.parse::<f64>() {
+                    format!("${:.2}", num)
+                } else {
+                    format!("${}", value)
+                }
+            },
+            "date" => {
+                // Simple date formatting - in production would use chrono
+                if let Some(format) = args.first() {
+                    // For now, just return the date as-is with basic format support
+                    match *format {
+                        "Y-m-d" => value.to_string(), // Assume input is already in this format
+                        _ => value.to_string(),
+                    }
+                } else {
+                    value.to_string()
+                }
+            },
+            "strip" => value.trim().to_string(),
+            "add" => {
+                if let Some(addend_str) = args.first() {
+                    if let (Ok(num), Ok(addend)) = (value.parse::<i64>(), addend_str.parse::<i64>()) {
+                        (num + addend).to_string()
+                    } else {
+                        value.to_string()
+                    }
+                } else {
+                    value.to_string()
+                }
+            },
+            "multiply" => {
+                if let Some(factor_str) = args.first() {
+                    if let (Ok(num), Ok(factor)) = (value.parse::<i64>(), factor_str.parse::<i64>()) {
+                        (num * factor).to_string()
+                    } else {
+                        value.to_string()
+                    }
+                } else {
+                    value.to_string()
+                }
+            },
+            // Custom filters for the test
+            "markdown" => {
+                // Simple markdown to HTML conversion - handle **text** -> <strong>text</strong>
+                let mut result = value.to_string();
+                while let Some(start) = result.find("**") {
+                    if let Some(end) = result[start + 2..].find("**") {
+                        let text = &result[start + 2..start + 2 + end];
+                        let replacement = format!("<strong>{}</strong>", text);
+                        result.replace_range(start..start + 2 + end + 2, &replacement);
+                    } else {
+                        break; // No closing **
+                    }
+                }
+                format!("<p>{}</p>", result)
+            },
+            "sanitize" => sanitize_html(value, &self.sanitizer_policy),
+            "truncate_html" => match args.first().and_then(|limit| limit.parse::<usize>().ok()) {
+                Some(limit) => truncate_html(value, limit),
+                None => value.to_string(),
+            },
+            "highlight" => {
+                if let Some(lang) = args.first() {
+                    format!("<pre><code class=\"{}\">{}</code></pre>", lang, value)
+                } else {
+                    format!("<pre><code>{}</code></pre>", value)
+                }
+            },
+            "slugify" => {
+                value.to_lowercase()
+                    .chars()
+                    .map(|c| if c.is_alphanumeric() { c } else { '-' })
+                    .collect::<String>()
+                    .split('-')
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("-")
+            },
+            // Additional math filters
+            "divide" => {
+                if let Some(arg) = args.first() {
+                    if let Ok(num_value) = value.parse::<f64>() {
+                        if let Ok(div_value) = arg.parse::<f64>() {
+                            if div_value != 0.0 {
+                                return Ok((num_value / div_value).to_string());
+                            }
+                        }
+                    }
+                }
+                value.to_string()
+            },
+            "percentage" => {
+                format!("{}%", value)
+            },
+            "round" => {
+                if let Some(arg) = args.first() {
+                    if let Ok(num_value) = value.parse::<f64>() {
+                        if let Ok(decimals) = arg.parse::<usize>() {
+                            let factor = 10_f64.powi(decimals as i32);
+                            let rounded = (num_value * factor).round() / factor;
+                            return Ok(format!("{:.1$}", rounded, decimals));
+                        }
+                    }
+                }
+                // Default rounding to 2 decimal places
+                if let Ok(num_value) = value.parse::<f64>() {
+                    format!("{:.2}", num_value)
+                } else {
+                    value.to_string()
+                }
+            },
+            _ => {
+                // Check for custom filters
+                if let Some(custom_filter) = self.custom_filters.get(filter_name) {
+                    match call_filter(filter_name, custom_filter, value, &args) {
+                        Ok(result) => result,
+                        Err(error) if self.strict_mode => return Err(error),
+                        Err(_) => value.to_string(), // Fallback on error
+                    }
+                } else {
+                    value.to_string() // Unknown filter, return original value
+                }
+            }
+        })
+    }
+
+    /// Process macro definitions and macro calls with context
+    #[cfg(feature = "template-macros")]
+    fn process_macros_with_context(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
+        let mut result = template.to_string();
         
-        let mut results = Vec::new();
-        for handle in handles {
-            let result = handle.join().map_err(|_| TemplateError::Render("Thread panic".to_string()))??;
-            results.push(result);
+        // First pass: extract macro definitions
+        result = self.extract_macro_definitions(&result)?;
+        
+        // Calls are checked against the source so errors point at the template
+        if self.strict_mode {
+            if let Some((offset, message)) = self.macro_call_problems(template).into_iter().next() {
+                let (line, column) = find_line_column(template, offset);
+                return Err(TemplateError::ParseWithLocation {
+                    message,
+                    line,
+                    column,
+                    template_name: None,
+                    context_lines: extract_context_lines(template, line, 2),
+                });
+            }
         }
         
-        Ok(results)
+        // Second pass: process macro calls with context
+        result = self.process_macro_calls_with_context(&result, context)?;
+        
+        Ok(result)
     }
 
-    /// Process translation directives {{t "key"}}
-    fn process_translations(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
+    /// Process macro definitions and macro calls (old method for backwards compatibility)
+    #[allow(dead_code)]
+    #[cfg(feature = "template-macros")]
+    fn process_macros(&mut self, template: &str) -> TemplateResult<String> {
         let mut result = template.to_string();
         
-        while let Some(start) = result.find("{{t ") {
-            let end = result[start..].find("}}")
-                .ok_or_else(|| TemplateError::Parse("Unclosed translation directive".to_string()))?;
-            
-            let directive = &result[start + 4..start + end];
-            let translation_key = directive.trim().trim_matches('"').trim_matches('\'');
-            
-            let translation = self.get_translation(translation_key);
-            
-            // Process the translation string as a template (for variable substitution)
-            let processed_translation = self.render_string(&translation, context)?;
-            
-            result.replace_range(start..start + end + 2, &processed_translation);
-        }
+        // First pass: extract macro definitions
+        result = self.extract_macro_definitions(&result)?;
+        
+        // Second pass: process macro calls
+        result = self.process_macro_calls(&result)?;
         
         Ok(result)
     }
-
-    /// Process pluralization directives {{plural count "singular" "plural"}}
-    fn process_pluralization(&self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
+    
+    /// Extract macro definitions from template
+    #[cfg(feature = "template-macros")]
+    fn extract_macro_definitions(&mut self, template: &str) -> TemplateResult<String> {
         let mut result = template.to_string();
         
-        while let Some(start) = result.find("{{plural ") {
-            let end = result[start..].find("}}")
-                .ok_or_else(|| TemplateError::Parse("Unclosed pluralization directive".to_string()))?;
+        while let Some(macro_start) = result.find("{{macro ") {
+            let header_end = result[macro_start..].find("}}")
+                .ok_or_else(|| TemplateError::Parse("Unclosed macro definition".to_string()))?;
             
-            let directive = result[start + 9..start + end].to_string();
-            let parts: Vec<&str> = directive.split_whitespace().collect();
+            let macro_header = &result[macro_start + 8..macro_start + header_end];
+            let body_start = macro_start + header_end + 2;
             
-            if parts.len() != 3 {
-                return Err(TemplateError::Parse("Invalid pluralization syntax. Use: {{plural count \"singular\" \"plural\"}}".to_string()));
-            }
+            // Find matching {{/macro}}
+            let body_end = result[body_start..].find("{{/macro}}")
+                .ok_or_else(|| TemplateError::Parse("Missing {{/macro}} directive".to_string()))?;
             
-            let count_var = parts[0];
-            let singular = parts[1].trim_matches('"').trim_matches('\'');
-            let plural = parts[2].trim_matches('"').trim_matches('\'');
+            let macro_body = result[body_start..body_start + body_end].trim().to_string();
             
-            // Get the count value
-            let count = if let Some(TemplateValue::Number(n)) = context.get(count_var) {
-                *n
-            } else {
-                0
-            };
+            // Parse macro header: name(param1, param2="default", ...)
+            let mut macro_def = self.parse_macro_header(macro_header)?;
+            macro_def.body = macro_body;
             
-            let chosen_form = if count == 1 { singular } else { plural };
+            // Store macro definition
+            self.macros.insert(macro_def.name.clone(), macro_def);
             
-            result.replace_range(start..start + end + 2, chosen_form);
+            // Remove macro definition from template
+            let macro_end = body_start + body_end + 10; // +10 for {{/macro}}
+            result.replace_range(macro_start..macro_end, "");
         }
         
         Ok(result)
     }
     
-    // ====================
-    // v0.4.0 Developer Experience Methods
-    // ====================
-    
-    /// Enable debug mode for detailed execution tracking
-    pub fn enable_debug_mode(&mut self) {
-        self.debug_enabled = true;
+    /// Parse macro header to extract name, parameters and default values;
+    /// the body is left empty
+    #[cfg(feature = "template-macros")]
+    fn parse_macro_header(&self, header: &str) -> TemplateResult<MacroDefinition> {
+        // Simple parsing: name(param1, param2="default")
+        let mut macro_def = MacroDefinition {
+            name: header.trim().to_string(),
+            parameters: Vec::new(),
+            defaults: HashMap::new(),
+            body: String::new(),
+        };
+        if let Some(paren_pos) = header.find('(') {
+            macro_def.name = header[..paren_pos].trim().to_string();
+            let params_str = &header[paren_pos + 1..];
+            
+            if let Some(close_paren) = params_str.rfind(')') {
+                let params = paren_pos + 1..paren_pos + 1 + close_paren;
+                for param in parse_arguments(header, params, Separator::Comma)? {
+                    match (param.name, param.value) {
+                        (Some(name), _) => {
+                            macro_def.parameters.push(name.to_string());
+                            macro_def.defaults.insert(name.to_string(), param.text.to_string());
+                        }
+                        (None, ArgumentValue::Expression(name)) if is_variable_path(name) && !name.contains('.') => {
+                            macro_def.parameters.push(name.to_string());
+                        }
+                        (None, _) => {
+                            return Err(TemplateError::Parse(format!(
+                                "Invalid macro parameter '{}' in '{}'", param.text, header.trim()
+                            )));
+                        }
+                    }
+                }
+                Ok(macro_def)
+            } else {
+                Err(TemplateError::Parse(format!("Invalid macro header: {}", header)))
+            }
+        } else {
+            // No parameters
+            Ok(macro_def)
+        }
     }
     
-    /// Disable debug mode
-    pub fn disable_debug_mode(&mut self) {
-        self.debug_enabled = false;
+    /// Macro calls of `template` that don't match their definition, as the
+    /// call's byte offset and the problem. Definitions in `template` itself
+    /// are checked against along with the ones already registered.
+    #[cfg(feature = "template-macros")]
+    fn macro_call_problems(&self, template: &str) -> Vec<(usize, String)> {
+        let mut local_macros = HashMap::new();
+        let mut calls = Vec::new();
+        let mut pos = 0;
+        while let Some(relative_start) = template[pos..].find("{{") {
+            let start = pos + relative_start;
+            let Some(end) = template[start..].find("}}") else { break };
+            pos = start + end + 2;
+            
+            let directive = template[start + 2..start + end].trim();
+            if let Some(header) = directive.strip_prefix("macro ") {
+                if let Ok(macro_def) = self.parse_macro_header(header) {
+                    local_macros.insert(macro_def.name.clone(), macro_def);
+                }
+            } else if directive.contains('(') {
+                calls.push((start, directive));
+            }
+        }
+        
+        let mut problems = Vec::new();
+        for (start, directive) in calls {
+            let name = directive[..directive.find('(').unwrap_or_default()].trim();
+            let Some(macro_def) = local_macros.get(name).or_else(|| self.macros.get(name)) else { continue };
+            let Ok(args) = macro_call_arguments(directive) else { continue };
+            if let Some(problem) = macro_def.call_problem(&args) {
+                problems.push((start, problem));
+            }
+        }
+        problems
     }
     
-    /// Check if debug mode is enabled
-    pub fn is_debug_enabled(&self) -> bool {
-        self.debug_enabled
+    /// Process macro calls in template with context
+    #[cfg(feature = "template-macros")]
+    fn process_macro_calls_with_context(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
+        let mut result = template.to_string();
+        
+        // Find macro calls: {{macro_name(arg1, arg2)}}
+        // Longest names first (then alphabetical) so expansion order is deterministic
+        // and `card(` never claims a call to `info_card(`
+        let mut macros_sorted: Vec<(String, MacroDefinition)> = self.macros.clone().into_iter().collect();
+        macros_sorted.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        for (macro_name, macro_def) in &macros_sorted {
+            let call_pattern = format!("{}{}", macro_name, "(");
+            
+            let mut search_from = 0;
+            while let Some(relative_start) = result[search_from..].find(&call_pattern) {
+                let call_start = search_from + relative_start;
+                search_from = call_start + call_pattern.len();
+                
+                // Find the start of the macro call; `price(` in text or inside
+                // `{{format_price(...)}}` is not a call of `price`
+                let Some(start_pos) = result[..call_start].rfind("{{") else { continue };
+                if !result[start_pos + 2..call_start].trim().is_empty() {
+                    continue;
+                }
+                
+                // Find the end of the macro call
+                let end_pos = result[call_start..].find("}}")
+                    .ok_or_else(|| TemplateError::Parse("Unclosed macro call".to_string()))? + call_start + 2;
+                
+                let call_content = &result[start_pos + 2..end_pos - 2];
+                
+                // Check if all required variables for this macro call exist in context
+                if !self.can_resolve_macro_args(call_content, context)? {
+                    // Skip this macro call if variables aren't available - it will be processed later in loop context
+                    break;
+                }
+                
+                // Parse arguments and resolve them from context
+                let mut args = self.parse_macro_call_args_with_context(call_content, context)?;
+                
+                // Omitted parameters take their default, evaluated in the caller's context
+                for (i, param) in macro_def.parameters.iter().enumerate() {
+                    if let Some(default) = macro_def.defaults.get(param) {
+                        if !args.contains_key(param) && !args.contains_key(&i.to_string()) {
+                            args.insert(param.clone(), self.parse_single_helper_arg(default, context));
+                        }
+                    }
+                }
+                
+                // Expand macro with resolved values
+                let expanded = self.expand_macro_with_values(macro_def, &args)?;
+                
+                result.replace_range(start_pos..end_pos, &expanded);
+                search_from = start_pos;
+            }
+        }
+        
+        Ok(result)
     }
-    
-    // v0.5.0 Ecosystem Integration methods
-    
-    /// Get the template directory (for async and other integrations)
-    pub fn get_template_dir(&self) -> &str {
-        &self.template_dir
+
+    /// Process macro calls in template (old method for backwards compatibility)
+    #[allow(dead_code)]
+    #[cfg(feature = "template-macros")]
+    fn process_macro_calls(&mut self, template: &str) -> TemplateResult<String> {
+        let empty_context = TemplateContext::new();
+        self.process_macro_calls_with_context(template, &empty_context)
     }
     
-    /// Get WASM console logging status
-    #[cfg(feature = "wasm")]
-    pub fn get_wasm_console_logging(&self) -> bool {
-        self.wasm_console_logging
+    /// Check if macro arguments can be resolved in the current context
+    #[cfg(feature = "template-macros")]
+    fn can_resolve_macro_args(&self, call_content: &str, context: &TemplateContext) -> TemplateResult<bool> {
+        Ok(macro_call_arguments(call_content)?.iter().all(|arg| match arg.value {
+            // Variable reference - check if it exists in context
+            ArgumentValue::Expression(expression) => {
+                let path = expression.split('|').next().unwrap_or_default().trim();
+                self.variable_exists_in_context(path, context)
+            }
+            _ => true,
+        }))
     }
     
-    /// Set WASM console logging
-    #[cfg(feature = "wasm")]
-    pub fn set_wasm_console_logging(&mut self, enabled: bool) {
-        self.wasm_console_logging = enabled;
+    /// Check if a variable exists in the context (handles dot notation)
+    fn variable_exists_in_context(&self, variable_name: &str, context: &TemplateContext) -> bool {
+        if variable_name.contains('.') {
+            let parts: Vec<&str> = variable_name.split('.').collect();
+            context.get(parts[0]).is_some()
+        } else {
+            context.get(variable_name).is_some()
+        }
     }
-    
-    /// Get cache size for WASM memory usage calculation
-    #[cfg(feature = "wasm")]
-    pub fn get_cache_size(&self) -> usize {
-        self.cache.len()
+
+    /// Parse macro call arguments with context resolution; positional
+    /// arguments are keyed by their index
+    #[cfg(feature = "template-macros")]
+    fn parse_macro_call_args_with_context(&self, call_content: &str, context: &TemplateContext) -> TemplateResult<HashMap<String, TemplateValue>> {
+        let mut args_map = HashMap::new();
+        let mut positional_index = 0;
+        for arg in macro_call_arguments(call_content)? {
+            let value = self.argument_value(&arg.value, context)?
+                .unwrap_or_else(|| TemplateValue::String(String::new()));
+            match arg.name {
+                Some(name) => {
+                    args_map.insert(name.to_string(), value);
+                }
+                None => {
+                    args_map.insert(positional_index.to_string(), value);
+                    positional_index += 1;
+                }
+            }
+        }
+        Ok(args_map)
     }
     
-    /// Get macro count for WASM memory usage calculation
-    #[cfg(feature = "wasm")]
-    pub fn get_macro_count(&self) -> usize {
-        self.macros.len()
+    /// Resolve variable from context (handles nested properties)
+    fn resolve_variable_from_context(&self, variable_name: &str, context: &TemplateContext) -> TemplateValue {
+        self.record_read(variable_name);
+        if variable_name.contains('.') {
+            // Handle nested property access
+            let parts: Vec<&str> = variable_name.split('.').collect();
+            if let Some(root_value) = context.get(parts[0]) {
+                self.get_nested_value(root_value, &parts[1..])
+            } else {
+                TemplateValue::String(String::new())
+            }
+        } else {
+            // Simple variable
+            context.get(variable_name).unwrap_or(&TemplateValue::String(String::new())).clone()
+        }
     }
     
-    /// Enable hot reload functionality
-    pub fn enable_hot_reload(&mut self) {
-        self.hot_reload_enabled = true;
+    /// Expand macro with given arguments - handles both String and TemplateValue args
+    #[allow(dead_code)]
+    #[cfg(feature = "template-macros")]
+    fn expand_macro(&mut self, macro_def: &MacroDefinition, args: &HashMap<String, String>) -> TemplateResult<String> {
+        let macro_body = macro_def.body.clone();
+        
+        // Create a temporary context with macro parameters
+        let mut macro_context = TemplateContext::new();
+        
+        // Add macro parameters to the context
+        for (i, param) in macro_def.parameters.iter().enumerate() {
+            let value = if let Some(named_value) = args.get(param) {
+                // Treat as string (no JSON parsing needed)
+                TemplateValue::String(named_value.clone())
+            } else if let Some(positional_value) = args.get(&i.to_string()) {
+                // Treat as string
+                TemplateValue::String(positional_value.clone())
+            } else {
+                TemplateValue::String(String::new())
+            };
+            
+            macro_context.set(param, value);
+        }
+        
+        // Process the macro body with the macro context
+        let mut output = String::new();
+        self.render_source_into(&macro_body, &macro_context, &mut output, None)?;
+        Ok(output)
+    }
+
+    /// Expand macro with TemplateValue arguments (new method for context-aware calls)
+    #[cfg(feature = "template-macros")]
+    fn expand_macro_with_values(&mut self, macro_def: &MacroDefinition, args: &HashMap<String, TemplateValue>) -> TemplateResult<String> {
+        let macro_body = macro_def.body.clone();
+        
+        // Create a temporary context with macro parameters
+        let mut macro_context = TemplateContext::new();
+        
+        // Add macro parameters to the context
+        for (i, param) in macro_def.parameters.iter().enumerate() {
+            let value = if let Some(named_value) = args.get(param) {
+                named_value.clone()
+            } else if let Some(positional_value) = args.get(&i.to_string()) {
+                positional_value.clone()
+            } else {
+                TemplateValue::String(String::new())
+            };
+            
+            macro_context.set(param, value);
+        }
+        
+        // Process the macro body with the macro context
+        let mut output = String::new();
+        self.render_source_into(&macro_body, &macro_context, &mut output, None)?;
+        Ok(output)
     }
     
-    /// Disable hot reload functionality
-    pub fn disable_hot_reload(&mut self) {
-        self.hot_reload_enabled = false;
+    /// Escaping the filters of an output expression ask for, the last one
+    /// deciding: `Some(false)` after `safe` or a filter that produces HTML,
+    /// `Some(true)` after `escape` or `e`, `None` when no filter decides
+    fn filter_escaping(&self, expression: &str) -> Option<bool> {
+        expression.split('|').skip(1)
+            .filter_map(|filter_expr| self.filter_step_escaping(filter_expr.split(':').next().unwrap_or("").trim()))
+            .last()
     }
     
-    /// Check if hot reload is enabled
-    pub fn is_hot_reload_enabled(&self) -> bool {
-        self.hot_reload_enabled
+    /// Names of the custom filters registered as producing HTML
+    fn html_filter_names(&self) -> Vec<&str> {
+        self.html_filters.iter().map(String::as_str).collect()
     }
     
-    /// Render template with debug information
-    pub fn render_string_with_debug(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<DebugRenderResult> {
-        let start_time = SystemTime::now();
-        let mut debug_info = DebugInfo::new();
+    /// Escaping one filter asks for; a pipeline asks for what its last deciding step does
+    fn filter_step_escaping(&self, filter_name: &str) -> Option<bool> {
+        if ESCAPE_FILTERS.contains(&filter_name) {
+            Some(true)
+        } else if filter_name == "safe" || HTML_PRODUCING_FILTERS.contains(&filter_name) || self.html_filters.contains(filter_name) {
+            Some(false)
+        } else {
+            self.pipelines.get(filter_name)?.iter().rev()
+                .find_map(|step| self.filter_step_escaping(step.split(':').next().unwrap_or("").trim()))
+        }
+    }
+
+    /// Evaluate a condition
+    fn evaluate_condition(&self, condition: &str, context: &TemplateContext) -> TemplateResult<bool> {
+        // Negations such as `{{if !user.verified}}` or `{{if not (count > 5)}}`
+        let (condition, negated) = strip_negations(condition);
+        if negated {
+            return self.evaluate_condition(condition, context).map(|holds| !holds);
+        }
         
-        // Track template processing
-        debug_info.add_template_processed("inline_template");
+        // Tests such as `{{if user.middle_name is defined}}` or `{{if avatar is not null}}`
+        if let Some((expr, test, negated)) = split_value_test(condition) {
+            self.record_read(expr);
+            let parts: Vec<&str> = expr.split('.').collect();
+            let value = context.get(parts[0]).and_then(|root_value| lookup_nested(root_value, &parts[1..]));
+            let holds = match test {
+                ValueTest::Defined => value.is_some(),
+                ValueTest::Null => matches!(value, Some(TemplateValue::Null)),
+            };
+            return Ok(holds != negated);
+        }
         
-        // Add initial execution step
-        debug_info.add_execution_step(ExecutionStep::new("start", "template_render", 1, 1));
+        // Slots passed to `render_component`
+        if let Some(args) = condition.strip_prefix("has_slot ") {
+            return has_slot(args, context);
+        }
         
-        // Perform the actual rendering with debug tracking
-        let output = self.render_string_with_debug_tracking(template, context, &mut debug_info)?;
+        // Helper and function calls such as `{{if str.contains(title, "draft")}}`
+        if split_call(condition).is_some() {
+            if let Some(value) = self.call_function(condition, context)? {
+                return Ok(self.is_truthy(&value));
+            }
+        }
         
-        // Calculate total time
-        if let Ok(duration) = start_time.elapsed() {
-            debug_info.performance_metrics.total_time_nanos = duration.as_nanos() as u64;
+        // Check for comparison operators
+        if let Some(result) = self.evaluate_comparison(condition, context)? {
+            return Ok(result);
         }
         
-        // Add final execution step
-        debug_info.add_execution_step(ExecutionStep::new("end", "template_render", 1, template.len()));
+        // Filtered conditions such as `{{if flag|bool}}`
+        if condition.contains('|') {
+            let value = self.evaluate_filter_expression(condition, context)?;
+            return Ok(self.is_truthy(&value));
+        }
         
-        Ok(DebugRenderResult {
-            output,
-            debug_info,
-        })
+        // Support both simple variables and deep dot notation in conditionals
+        self.record_read(condition);
+        if condition.contains('.') {
+            let parts: Vec<&str> = condition.split('.').collect();
+            if let Some(root_value) = context.get(parts[0]) {
+                return Ok(self.evaluate_nested_condition(root_value, &parts[1..]));
+            }
+            Ok(false)
+        } else if let Some(value) = context.get(condition) {
+            Ok(self.is_truthy(value))
+        } else {
+            Ok(false)
+        }
     }
     
-    /// Internal method for rendering with debug tracking
-    fn render_string_with_debug_tracking(&mut self, template: &str, context: &TemplateContext, debug_info: &mut DebugInfo) -> TemplateResult<String> {
-        // For now, delegate to regular render_string but track variables
-        // In a full implementation, this would intercept variable access and track execution steps
+    /// Evaluate comparison expressions like "x == y", "count > 5", etc.
+    fn evaluate_comparison(&self, condition: &str, context: &TemplateContext) -> TemplateResult<Option<bool>> {
+        // List of operators to check, ordered by length (longest first to avoid conflicts)
+        let operators = ["==", "!=", "<=", ">=", "<", ">"];
         
-        // Simple variable tracking by scanning template content
-        let mut current_pos = 0;
-        while let Some(start) = template[current_pos..].find("{{") {
-            let abs_start = current_pos + start;
-            if let Some(end) = template[abs_start..].find("}}") {
-                let var_content = &template[abs_start + 2..abs_start + end];
-                let (line, column) = find_line_column(template, abs_start);
+        for op in &operators {
+            if let Some(op_pos) = condition.find(op) {
+                let left_expr = condition[..op_pos].trim();
+                let right_expr = condition[op_pos + op.len()..].trim();
                 
-                // Track different types of template directives
-                if let Some(stripped) = var_content.strip_prefix("if ") {
-                    let condition = stripped.trim();
-                    debug_info.add_execution_step(ExecutionStep::new("conditional", condition, line, column));
-                    debug_info.add_variable_access(condition);
-                } else if let Some(stripped) = var_content.strip_prefix("for ") {
-                    let loop_expr = stripped.trim();
-                    debug_info.add_execution_step(ExecutionStep::new("loop", loop_expr, line, column));
-                    if let Some(in_pos) = loop_expr.find(" in ") {
-                        let array_var = &loop_expr[in_pos + 4..];
-                        debug_info.add_variable_access(array_var.trim());
-                    }
-                } else if !var_content.starts_with("/") && !var_content.starts_with("!") {
-                    // Regular variable
-                    let var_name = var_content.split('|').next().unwrap_or(var_content).trim();
-                    if !var_name.is_empty() {
-                        debug_info.add_execution_step(ExecutionStep::new("variable", var_name, line, column));
-                        debug_info.add_variable_access(var_name);
-                    }
-                }
+                let left_val = self.get_condition_value(left_expr, context)?;
+                let right_val = self.get_condition_value(right_expr, context)?;
                 
-                current_pos = abs_start + end + 2;
-            } else {
-                break;
+                let ordering = || match self.compare_values(&left_val, &right_val) {
+                    Some(ordering) => Ok(Some(ordering)),
+                    None if self.strict_mode => Err(TemplateError::Render(format!(
+                        "Cannot compare {} with {} using '{}' in `{}` in strict mode",
+                        comparison_kind(&left_val), comparison_kind(&right_val), op, condition
+                    ))),
+                    None => Ok(None),
+                };
+                
+                return Ok(Some(match *op {
+                    "==" => self.values_equal(&left_val, &right_val),
+                    "!=" => !self.values_equal(&left_val, &right_val),
+                    "<" => ordering()?.is_some_and(cmp::Ordering::is_lt),
+                    ">" => ordering()?.is_some_and(cmp::Ordering::is_gt),
+                    "<=" => ordering()?.is_some_and(cmp::Ordering::is_le),
+                    ">=" => ordering()?.is_some_and(cmp::Ordering::is_ge),
+                    _ => false,
+                }));
             }
         }
         
-        // Delegate to original rendering to avoid recursion
-        self.render_string_original(template, context)
+        Ok(None)
     }
     
-    /// Enhanced render method with better error messages and suggestions (v0.4.0 override)
-    pub fn render_v040(&mut self, template_name: &str, context: &TemplateContext) -> TemplateResult<String> {
-        // Check for hot reload
-        if self.hot_reload_enabled {
-            self.check_and_reload_if_needed(template_name)?;
-        }
+    /// Get the value for a condition expression (variable, string literal, or number)
+    fn get_condition_value(&self, expr: &str, context: &TemplateContext) -> TemplateResult<TemplateValue> {
+        let expr = expr.trim();
         
-        // Try to load template with enhanced error handling
-        match self.load_template_with_enhanced_errors(template_name) {
-            Ok(template_content) => {
-                self.render_string_with_error_enhancement(&template_content, context, Some(template_name.to_string()))
-            },
-            Err(e) => Err(e),
+        // Check if it's a string literal (quoted)
+        if (expr.starts_with('"') && expr.ends_with('"')) || (expr.starts_with('\'') && expr.ends_with('\'')) {
+            return Ok(TemplateValue::String(expr[1..expr.len()-1].to_string()));
         }
-    }
-    
-    /// Load template with enhanced error messages and suggestions
-    fn load_template_with_enhanced_errors(&mut self, template_name: &str) -> TemplateResult<String> {
-        // Check if template exists
-        let template_path = Path::new(&self.template_dir).join(template_name);
         
-        if !template_path.exists() {
-            // Generate helpful suggestions
-            let available_templates = self.list_available_templates()?;
-            let suggestions = suggest_templates(template_name, &available_templates, 3);
-            
-            return Err(TemplateError::TemplateNotFoundWithSuggestions {
-                template_name: template_name.to_string(),
-                template_dir: self.template_dir.clone(),
-                suggestions,
-                available_templates,
-            });
+        // Check if it's a number literal
+        if let Ok(num) = expr.parse::<i64>() {
+            return Ok(TemplateValue::Number(num));
+        }
+        if expr.starts_with(|c: char| c.is_ascii_digit() || c == '-') {
+            if let Ok(num) = expr.parse::<f64>() {
+                return Ok(TemplateValue::Float(num));
+            }
         }
         
-        // Load and cache template
-        self.load_template(template_name)
-    }
-    
-    /// List all available templates in the template directory
-    fn list_available_templates(&self) -> TemplateResult<Vec<String>> {
-        let mut templates = Vec::new();
-        let template_dir = Path::new(&self.template_dir);
+        // Check if it's a boolean or null literal
+        if expr == "true" {
+            return Ok(TemplateValue::Bool(true));
+        } else if expr == "false" {
+            return Ok(TemplateValue::Bool(false));
+        } else if expr == "null" {
+            return Ok(TemplateValue::Null);
+        }
         
-        if template_dir.exists() && template_dir.is_dir() {
-            for entry in fs::read_dir(template_dir)? {
-                let entry = entry?;
-                let path = entry.path();
-                if path.is_file() {
-                    if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                        if file_name.ends_with(".html") || file_name.ends_with(".htm") {
-                            templates.push(file_name.to_string());
-                        }
-                    }
-                }
-            }
+        // Filtered values keep the type produced by the filter chain
+        if expr.contains('|') {
+            return self.evaluate_filter_expression(expr, context);
         }
         
-        Ok(templates)
-    }
-    
-    /// Check if templates need to be reloaded for hot reload functionality
-    fn check_and_reload_if_needed(&mut self, template_name: &str) -> TemplateResult<()> {
-        let template_path = Path::new(&self.template_dir).join(template_name);
+        // Helper and function calls
+        if let Some(value) = self.call_function(expr, context)? {
+            return Ok(value);
+        }
         
-        if let Ok(metadata) = fs::metadata(&template_path) {
-            if let Ok(modified) = metadata.modified() {
-                let should_reload = match self.file_mtimes.get(template_name) {
-                    Some(cached_time) => modified > *cached_time,
-                    None => true,
-                };
-                
-                if should_reload {
-                    // Clear cache for this template
-                    self.cache.remove(template_name);
-                    self.bytecode_cache.remove(template_name);
-                    
-                    // Update modification time
-                    self.file_mtimes.insert(template_name.to_string(), modified);
-                    
-                    // Also reload dependent templates
-                    if let Some(dependents) = self.template_dependencies.get(template_name).cloned() {
-                        for dependent in dependents {
-                            self.cache.remove(&dependent);
-                            self.bytecode_cache.remove(&dependent);
-                        }
-                    }
-                }
+        // Otherwise treat as variable name (with possible dot notation)
+        self.record_read(expr);
+        if expr.contains('.') {
+            let parts: Vec<&str> = expr.split('.').collect();
+            if let Some(root_value) = context.get(parts[0]) {
+                return Ok(self.get_nested_value(root_value, &parts[1..]));
             }
+        } else if let Some(value) = context.get(expr) {
+            return Ok(value.clone());
         }
         
-        Ok(())
+        // Default to empty string if not found
+        Ok(TemplateValue::String(String::new()))
     }
     
-    /// Enhanced render_string with better error handling (v0.4.0 override)
-    pub fn render_string_v040(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
-        // Parse and render with enhanced error handling
-        self.render_string_with_error_enhancement(template, context, None)
+    /// Get nested value from object traversal
+    fn get_nested_value(&self, current_value: &TemplateValue, remaining_parts: &[&str]) -> TemplateValue {
+        lookup_nested(current_value, remaining_parts)
+            .cloned()
+            .unwrap_or_else(|| TemplateValue::String(String::new()))
     }
     
-    /// Legacy render_string method that calls the original implementation
-    fn render_string_original(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
-        // This calls the original implementation logic
-        self.parse_and_render_internal(template, context, None)
+    /// Check if two values are equal
+    /// 
+    /// - Numbers, floats and strings that parse as numbers compare by value:
+    ///   `5 == "5"`, `"5.0" == 5` and `"01" == "1"`.
+    /// - Other strings compare by text; booleans equal booleans and the
+    ///   strings `"true"` and `"false"`.
+    /// - Arrays are equal when their items are equal in order, objects when
+    ///   they have the same keys with equal values.
+    /// - `null` equals only `null`; missing variables and properties read
+    ///   as `""`, so they aren't null: test them with `is defined`.
+    fn values_equal(&self, left: &TemplateValue, right: &TemplateValue) -> bool {
+        if let (Some(a), Some(b)) = (comparable_number(left), comparable_number(right)) {
+            return a.compare(b) == Some(cmp::Ordering::Equal);
+        }
+        match (left, right) {
+            (TemplateValue::String(a), TemplateValue::String(b)) => a == b,
+            (TemplateValue::Bool(a), TemplateValue::Bool(b)) => a == b,
+            (TemplateValue::Bool(a), TemplateValue::String(b)) |
+            (TemplateValue::String(b), TemplateValue::Bool(a)) => a.to_string() == *b,
+            (TemplateValue::Array(a), TemplateValue::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| self.values_equal(a, b))
+            }
+            (TemplateValue::Object(a), TemplateValue::Object(b)) => {
+                a.len() == b.len() && a.iter().all(|(key, a)| b.get(key).is_some_and(|b| self.values_equal(a, b)))
+            }
+            (TemplateValue::Null, TemplateValue::Null) => true,
+            _ => false,
+        }
     }
     
-    /// Internal method with enhanced error handling
-    fn render_string_with_error_enhancement(&mut self, template: &str, context: &TemplateContext, template_name: Option<String>) -> TemplateResult<String> {
-        // Try to parse template and catch errors with location info
-        match self.parse_and_render_internal(template, context, template_name.as_deref()) {
-            Ok(result) => Ok(result),
-            Err(TemplateError::Parse(msg)) => {
-                // Enhance parse errors with location information
-                self.enhance_parse_error(&msg, template, template_name)
-            },
-            Err(other) => Err(other),
+    /// Order two values for `<`, `>`, `<=` and `>=`
+    /// 
+    /// Values that parse as numbers (numbers, floats, numeric strings) are
+    /// ordered numerically, so `"10" > "5"`; other strings are ordered by
+    /// text. Any other pair, such as a number and a word, booleans, arrays
+    /// or objects, can't be ordered: `None`, which fails the condition, or
+    /// the render in strict mode.
+    fn compare_values(&self, left: &TemplateValue, right: &TemplateValue) -> Option<cmp::Ordering> {
+        if let (Some(a), Some(b)) = (comparable_number(left), comparable_number(right)) {
+            return a.compare(b);
+        }
+        match (left, right) {
+            (TemplateValue::String(a), TemplateValue::String(b)) => Some(a.cmp(b)),
+            _ => None,
         }
     }
     
-    /// Enhance parse errors with location and context information
-    fn enhance_parse_error(&self, error_msg: &str, template: &str, template_name: Option<String>) -> TemplateResult<String> {
-        // Try to find error location by looking for unclosed tags
-        let (line, column) = if error_msg.contains("unclosed") {
-            // Find the unclosed tag
-            if let Some(pos) = template.find("{{if") {
-                find_line_column(template, pos)
-            } else {
-                (1, 1)
-            }
-        } else {
-            (1, 1)
-        };
-        
-        let context_lines = extract_context_lines(template, line, 2);
-        
-        Err(TemplateError::ParseWithLocation {
-            message: error_msg.to_string(),
-            line,
-            column,
-            template_name,
-            context_lines,
+    /// Evaluate condition for nested properties
+    fn evaluate_nested_condition(&self, current_value: &TemplateValue, remaining_parts: &[&str]) -> bool {
+        // Missing properties, bad indexes and non-traversable values are falsy
+        lookup_nested(current_value, remaining_parts).is_some_and(|value| self.is_truthy(value))
+    }
+
+    /// Check if a value is truthy
+    fn is_truthy(&self, value: &TemplateValue) -> bool {
+        match value {
+            TemplateValue::Bool(b) => *b,
+            TemplateValue::String(s) => !s.is_empty(),
+            TemplateValue::Number(n) => *n != 0,
+            TemplateValue::Float(f) => *f != 0.0,
+            TemplateValue::Array(a) => !a.is_empty(),
+            TemplateValue::Object(o) => !o.is_empty(),
+            TemplateValue::Null => false,
+        }
+    }
+
+    /// Value a `{{for}}` loop iterates: a variable, a dotted path, a function
+    /// call, a filter chain (`products|sort:"price"`) or an integer range
+    /// (`1..=5`, `0..count`)
+    fn loop_source<'c>(&self, array_var: &str, context: &'c TemplateContext) -> TemplateResult<Option<Cow<'c, TemplateValue>>> {
+        if array_var.contains('|') {
+            return self.evaluate_filter_expression(array_var, context).map(|value| Some(Cow::Owned(value)));
+        }
+        if let Some((start, end, inclusive)) = split_range(array_var) {
+            // A bound missing from the context leaves nothing to iterate
+            let (Some(start), Some(end)) = (self.range_bound(start, context)?, self.range_bound(end, context)?) else {
+                return Ok(None);
+            };
+            return functions::integer_range(start, end, 1, inclusive, self.max_range_items).map(|range| Some(Cow::Owned(range)));
+        }
+        self.record_read(array_var);
+        Ok(match context.get(array_var) {
+            None if array_var.ends_with(')') => Some(Cow::Owned(self.call_function(array_var, context)?
+                .ok_or_else(|| TemplateError::Template(format!("Function '{}' is not supported", array_var)))?)),
+            None if array_var.contains('.') => Some(Cow::Owned(self.resolve_variable_from_context(array_var, context))),
+            array => array.map(Cow::Borrowed),
         })
     }
     
-    /// Internal parsing and rendering - simplified implementation for v0.4.0
-    fn parse_and_render_internal(&mut self, template: &str, context: &TemplateContext, _template_name: Option<&str>) -> TemplateResult<String> {
-        // For v0.4.0, we'll create a simplified version that just processes basic variables
-        // This avoids complex method signature issues while we focus on error handling
-        
-        let mut result = template.to_string();
-        
-        // Basic variable processing
-        result = self.process_variables(&result, context)?;
-        
-        // Check for unclosed tags to trigger parse errors for testing
-        if result.contains("{{if") && !result.contains("{{/if}}") {
-            return Err(TemplateError::Parse("Unclosed {{if}} tag".to_string()));
+    /// Value of a range bound, an integer literal or a variable; `None` when
+    /// the variable is not set
+    fn range_bound(&self, bound: &str, context: &TemplateContext) -> TemplateResult<Option<i64>> {
+        if let Ok(number) = bound.parse() {
+            return Ok(Some(number));
         }
-        
-        Ok(result)
+        self.record_read(bound);
+        if !self.variable_exists_in_context(bound, context) {
+            return Ok(None);
+        }
+        let value = self.resolve_variable_from_context(bound, context);
+        functions::integer(&value).map(Some).ok_or_else(|| TemplateError::Render(format!(
+            "Range bound '{}' must be an integer, got '{}'", bound, self.template_value_to_string(&value)
+        )))
     }
     
-    // ====================
-    // v0.4.1 IDE Integration Methods  
-    // ====================
-    
-    /// Parse template for Language Server Protocol analysis
-    pub fn parse_for_lsp(&mut self, template_content: &str, _file_path: &str) -> TemplateResult<LspParseResult> {
-        let mut result = LspParseResult::new();
-        
-        // Scan template for all variables, blocks, and filters
-        let mut current_pos = 0;
-        let _line = 1;
-        let _column = 1;
-        
-        while let Some(start) = template_content[current_pos..].find("{{") {
-            let abs_start = current_pos + start;
-            if let Some(end) = template_content[abs_start..].find("}}") {
-                let directive_content = &template_content[abs_start + 2..abs_start + end];
-                let (current_line, current_column) = self.calculate_line_column(template_content, abs_start);
+    /// Render a loop, appending each iteration to `out` and handing it to
+    /// `sink`, if any, once complete
+    fn write_loop(&mut self, item_var: &str, array_var: &str, block: &str, context: &TemplateContext, out: &mut String, mut sink: Option<&mut StreamSink<'_>>) -> TemplateResult<()> {
+        let (block, empty) = split_loop_empty(block);
+        let array = self.loop_source(array_var, context)?;
+        let has_items = matches!(array.as_deref(), Some(TemplateValue::Array(items)) if !items.is_empty());
+        if let Some(TemplateValue::Array(items)) = array.as_deref().filter(|_| has_items || empty.is_none()) {
+            // Items get a thin layer over the enclosing context instead of a full copy
+            let shared_context = Arc::new(context.clone());
+            let mut previous_context: Option<TemplateContext> = None;
+            for item in items {
+                self.check_cancelled()?;
+                let mut loop_context = TemplateContext::layered(Arc::clone(&shared_context));
+                loop_context.set(item_var, item.clone());
+                let (parts, start) = (self.rendered_parts.len(), out.len());
                 
-                // Parse different types of directives
-                if directive_content.trim().starts_with("if ") {
-                    let condition = directive_content.trim()[3..].trim();
-                    result.add_block(TemplateBlock::new("if", current_line, current_column, condition));
-                    result.add_variable(condition);
-                } else if directive_content.trim().starts_with("for ") {
-                    let for_expr = directive_content.trim()[4..].trim();
-                    result.add_block(TemplateBlock::new("for", current_line, current_column, for_expr));
-                    if let Some(in_pos) = for_expr.find(" in ") {
-                        let array_var = &for_expr[in_pos + 4..];
-                        result.add_variable(array_var.trim());
-                    }
-                } else if directive_content.trim().starts_with("macro ") {
-                    let macro_def = directive_content.trim()[6..].trim();
-                    let macro_name = macro_def.split('(').next().unwrap_or(macro_def);
-                    result.macros.push(macro_name.to_string());
-                } else if !directive_content.starts_with("/") && !directive_content.starts_with("!") {
-                    // Regular variable or filter chain
-                    let parts: Vec<&str> = directive_content.split('|').collect();
-                    let var_name = parts[0].trim();
-                    if !var_name.is_empty() {
-                        result.add_variable(var_name);
-                    }
-                    
-                    // Add filters
-                    for filter in parts.iter().skip(1) {
-                        let filter_name = filter.split(':').next().unwrap_or(filter).trim();
-                        result.add_filter(filter_name);
-                    }
+                // Process macro calls within the loop context (so they have access to loop variables)
+                #[cfg(feature = "template-macros")]
+                let mut processed_block = self.process_macro_calls_with_context(block, &loop_context)?;
+                #[cfg(not(feature = "template-macros"))]
+                let mut processed_block = block.to_string();
+                
+                // Error boundaries may read the loop variable, before and after includes
+                if processed_block.contains(TRY_TAG) {
+                    processed_block = self.process_error_boundaries(&processed_block, &loop_context)?;
                 }
                 
-                current_pos = abs_start + end + 2;
-            } else {
-                break;
+                // Resolve dynamic includes that depend on the loop variable
+                processed_block = self.process_includes(&processed_block, &loop_context)?;
+                if processed_block.contains(TRY_TAG) {
+                    processed_block = self.process_error_boundaries(&processed_block, &loop_context)?;
+                }
+                
+                // Assignments, conditionals, nested loops, translations and
+                // variables, with the loop variable set; change checks compare
+                // with the previous item, nested loops starting over for each item
+                self.write_scoped(&processed_block, &loop_context, previous_context.as_ref(), out, None)?;
+                self.put_back_rendered_parts(out, start);
+                self.rendered_parts.truncate(parts);
+                if self.streaming && self.stream_flush_on == FlushOn::LoopIteration {
+                    write_asset_marker(out, self.marker_key, '!', "");
+                }
+                if let Some(sink) = sink.as_deref_mut() {
+                    self.drain_stream(out, sink)?;
+                }
+                previous_context = Some(loop_context);
+            }
+            
+            Ok(())
+        } else {
+            // Check if the array_var looks like a function call (contains parentheses)
+            if !matches!(array.as_deref(), Some(TemplateValue::Array(_))) && array_var.contains('(') && array_var.contains(')') {
+                return Err(TemplateError::Template(format!("Function '{}' is not supported", array_var)));
+            }
+            // Missing, non-array and empty values render the {{empty}} section, or nothing
+            match empty {
+                Some(empty) => self.write_source(empty, context, out, sink),
+                None => Ok(()),
             }
         }
-        
-        Ok(result)
     }
     
-    /// Get auto-completions at a specific position in the template
-    pub fn get_completions_at_position(&mut self, template: &str, position: usize, context: &TemplateContext) -> TemplateResult<Vec<CompletionItem>> {
-        let mut completions = Vec::new();
+    /// Validate template path to prevent path traversal attacks: the name
+    /// must pass [`check_template_name`] and, when the file exists, resolve
+    /// inside the template directory
+    pub(crate) fn validate_template_path(&self, name: &str) -> TemplateResult<()> {
+        check_template_name(name)?;
         
-        // Find the current token being typed
-        let (current_token, token_type) = self.get_token_at_position(template, position);
+        // Resolve the path and check if it stays within the template directory
+        let template_dir = Path::new(&self.template_dir).canonicalize()
+            .map_err(|_| TemplateError::Security("Invalid template directory".to_string()))?;
         
-        match token_type.as_str() {
-            "variable" => {
-                // Complete variable names
-                for (var_name, var_value) in &context.variables {
-                    if var_name.starts_with(&current_token) {
-                        let detail = match var_value {
-                            TemplateValue::String(s) => format!("String: {}", s),
-                            TemplateValue::Number(n) => format!("Number: {}", n),
-                            TemplateValue::Bool(b) => format!("Boolean: {}", b),
-                            TemplateValue::Array(_) => "Array".to_string(),
-                            TemplateValue::Object(_) => "Object".to_string(),
-                        };
-                        completions.push(CompletionItem::new(var_name, "variable", &detail));
-                    }
-                }
-            },
-            "filter" => {
-                // Complete filter names
-                let built_in_filters = vec![
-                    ("upper", "Convert text to uppercase"),
-                    ("lower", "Convert text to lowercase"),
-                    ("currency", "Format as currency"),
-                    ("truncate", "Truncate text with ellipsis"),
-                    ("round", "Round numbers to specified decimals"),
-                ];
-                
-                for (filter_name, description) in built_in_filters {
-                    if filter_name.starts_with(&current_token) {
-                        completions.push(CompletionItem::new(filter_name, "filter", description));
-                    }
-                }
-            },
-            "directive" => {
-                // Complete template directives
-                let directives = vec![
-                    ("if", "Conditional rendering"),
-                    ("for", "Loop over arrays"),
-                    ("include", "Include another template"),
-                    ("macro", "Define reusable component"),
-                ];
-                
-                for (directive_name, description) in directives {
-                    if directive_name.starts_with(&current_token) {
-                        completions.push(CompletionItem::new(directive_name, "directive", description));
-                    }
+        let (_, relative_name) = split_namespace(name);
+        let requested_path = template_dir.join(relative_name).canonicalize();
+        
+        match requested_path {
+            Ok(resolved_path) => {
+                if !resolved_path.starts_with(&template_dir) {
+                    return Err(PathViolation::new(PathRule::OutsideRoot, name).into());
                 }
-            },
-            _ => {}
+                Ok(())
+            }
+            Err(_) => {
+                // Path doesn't exist or can't be resolved - this is OK for now, 
+                // the actual file read will handle the error appropriately
+                Ok(())
+            }
         }
-        
-        Ok(completions)
     }
     
-    /// Tokenize template for syntax highlighting
-    pub fn tokenize_for_syntax_highlighting(&mut self, template: &str) -> TemplateResult<Vec<SyntaxToken>> {
-        let mut tokens = Vec::new();
-        let mut current_pos = 0;
+    /// Find the matching {{/for}} for nested loops using stack-based parsing
+    fn find_matching_for_end(&self, content: &str) -> TemplateResult<usize> {
+        let mut depth = 1; // We start at depth 1 since we're already inside a {{for}}
+        let mut pos = 0;
         
-        while current_pos < template.len() {
-            // Look for template directives
-            if let Some(start) = template[current_pos..].find("{{") {
-                let abs_start = current_pos + start;
+        while pos < content.len() && depth > 0 {
+            if let Some(for_pos) = content[pos..].find("{{for ") {
+                let actual_for_pos = pos + for_pos;
                 
-                // Add HTML content before directive as html_content token
-                if start > 0 {
-                    let html_content = &template[current_pos..abs_start];
-                    let (line, column) = self.calculate_line_column(template, current_pos);
-                    
-                    // Look for HTML tags
-                    if let Some(tag_start) = html_content.find('<') {
-                        if let Some(tag_end) = html_content[tag_start..].find('>') {
-                            let tag = &html_content[tag_start..tag_start + tag_end + 1];
-                            tokens.push(SyntaxToken::new(tag, "html_tag", current_pos + tag_start, line, column));
-                        }
+                // Check for {{/for}} before this {{for}}
+                if let Some(end_for_pos) = content[pos..pos + for_pos].find("{{/for}}") {
+                    let actual_end_for_pos = pos + end_for_pos;
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(actual_end_for_pos);
                     }
+                    pos = actual_end_for_pos + 8; // Move past {{/for}}
+                    continue;
                 }
                 
-                if let Some(end) = template[abs_start..].find("}}") {
-                    let directive_content = &template[abs_start + 2..abs_start + end];
-                    let (line, column) = self.calculate_line_column(template, abs_start);
-                    
-                    // Parse directive content
-                    if directive_content.contains('|') {
-                        // Variable with filters
-                        let parts: Vec<&str> = directive_content.split('|').collect();
-                        let var_name = parts[0].trim();
-                        tokens.push(SyntaxToken::new(var_name, "template_variable", abs_start + 2, line, column + 2));
-                        
-                        for filter in parts.iter().skip(1) {
-                            let filter_name = filter.split(':').next().unwrap_or(filter).trim();
-                            tokens.push(SyntaxToken::new(filter_name, "template_filter", abs_start + 2, line, column + 2));
-                        }
-                    } else if directive_content.trim().starts_with("if") || 
-                              directive_content.trim().starts_with("for") ||
-                              directive_content.trim().starts_with("/if") ||
-                              directive_content.trim().starts_with("/for") {
-                        tokens.push(SyntaxToken::new(directive_content.trim(), "template_directive", abs_start + 2, line, column + 2));
-                    } else {
-                        // Regular variable
-                        tokens.push(SyntaxToken::new(directive_content.trim(), "template_variable", abs_start + 2, line, column + 2));
-                    }
-                    
-                    current_pos = abs_start + end + 2;
-                } else {
-                    break;
+                // Found a nested {{for}}, increase depth
+                depth += 1;
+                if depth > self.max_nesting_depth {
+                    return Err(TemplateError::Parse(format!(
+                        "Maximum nesting depth of {} exceeded by nested {{{{for}}}} loops", self.max_nesting_depth
+                    )));
+                }
+                pos = actual_for_pos + 6; // Move past {{for 
+            } else if let Some(end_for_pos) = content[pos..].find("{{/for}}") {
+                let actual_end_for_pos = pos + end_for_pos;
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(actual_end_for_pos);
                 }
+                pos = actual_end_for_pos + 8; // Move past {{/for}}
             } else {
-                break;
+                break; // No more {{for}} or {{/for}} found
             }
         }
         
-        Ok(tokens)
+        Err(TemplateError::Parse("Missing {{/for}} directive".to_string()))
     }
     
-    /// Get syntax theme information for editors
-    pub fn get_syntax_theme_info(&self) -> TemplateResult<HashMap<String, String>> {
-        let mut theme = HashMap::new();
-        
-        // Define semantic colors for different token types
-        theme.insert("template_variable".to_string(), "#569cd6".to_string()); // Blue
-        theme.insert("template_filter".to_string(), "#4ec9b0".to_string());   // Cyan
-        theme.insert("template_directive".to_string(), "#c586c0".to_string()); // Purple
-        theme.insert("html_tag".to_string(), "#ce9178".to_string());          // Orange
-        theme.insert("html_content".to_string(), "#d4d4d4".to_string());      // Light gray
-        theme.insert("comment".to_string(), "#6a9955".to_string());           // Green
-        
-        Ok(theme)
-    }
     
-    /// Get real-time diagnostics for error squiggles
-    pub fn get_diagnostics_for_editor(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<Vec<Diagnostic>> {
-        let mut diagnostics = Vec::new();
+    // Performance features for TDD
+    
+    /// Render multiple templates in parallel
+    pub fn render_parallel(&mut self, template_names: &[String], context: &TemplateContext) -> TemplateResult<Vec<String>> {
+        let context = Arc::new(context.clone());
+        let template_dir = Arc::new(self.template_dir.clone());
+        let template_roots = Arc::new(self.template_roots.clone());
+        let (trim_blocks, lstrip_blocks) = (self.trim_blocks, self.lstrip_blocks);
         
-        // Check for unclosed directives
-        let mut directive_stack = Vec::new();
-        let mut current_pos = 0;
+        let handles: Vec<_> = template_names.iter().map(|name| {
+            let name = name.clone();
+            let context = Arc::clone(&context);
+            let template_dir = Arc::clone(&template_dir);
+            let template_roots = Arc::clone(&template_roots);
+            
+            thread::spawn(move || {
+                let mut engine = TemplateEngine::new(&template_dir);
+                engine.template_roots = template_roots.to_vec();
+                engine.trim_blocks = trim_blocks;
+                engine.lstrip_blocks = lstrip_blocks;
+                engine.render(&name, &context)
+            })
+        }).collect();
         
-        while let Some(start) = template[current_pos..].find("{{") {
-            let abs_start = current_pos + start;
-            if let Some(end) = template[abs_start..].find("}}") {
-                let directive_content = &template[abs_start + 2..abs_start + end].trim();
-                let (line, column) = self.calculate_line_column(template, abs_start);
-                
-                if directive_content.starts_with("if ") {
-                    directive_stack.push(("if", line, column));
-                } else if directive_content.starts_with("for ") {
-                    directive_stack.push(("for", line, column));
-                } else if directive_content.starts_with("/if") {
-                    if let Some((directive_type, _, _)) = directive_stack.pop() {
-                        if directive_type != "if" {
-                            diagnostics.push(Diagnostic::new(
-                                "Mismatched closing directive",
-                                "error",
-                                line,
-                                column
-                            ));
-                        }
-                    } else {
-                        diagnostics.push(Diagnostic::new(
-                            "Unexpected closing directive",
-                            "error",
-                            line,
-                            column
-                        ));
-                    }
-                } else if directive_content.starts_with("/for") {
-                    if let Some((directive_type, _, _)) = directive_stack.pop() {
-                        if directive_type != "for" {
-                            diagnostics.push(Diagnostic::new(
-                                "Mismatched closing directive",
-                                "error",
-                                line,
-                                column
-                            ));
-                        }
-                    }
-                } else if !directive_content.starts_with("/") && !directive_content.starts_with("!") {
-                    // Check for unknown variables
-                    let parts: Vec<&str> = directive_content.split('|').collect();
-                    let var_name = parts[0].trim();
-                    if !var_name.is_empty() && !context.variables.contains_key(var_name) {
-                        diagnostics.push(Diagnostic::new(
-                            &format!("Unknown variable: {}", var_name),
-                            "warning",
-                            line,
-                            column
-                        ));
-                    }
-                    
-                    // Check for unknown filters
-                    for filter_part in parts.iter().skip(1) {
-                        let filter_name = filter_part.split(':').next().unwrap_or(filter_part).trim();
-                        if !self.is_known_filter(filter_name) {
-                            diagnostics.push(Diagnostic::new(
-                                &format!("Unknown filter: {}", filter_name),
-                                "error",
-                                line,
-                                column
-                            ));
-                        }
-                    }
-                }
-                
-                current_pos = abs_start + end + 2;
-            } else {
-                break;
-            }
+        let mut results = Vec::new();
+        for handle in handles {
+            let result = handle.join().map_err(|_| TemplateError::Render("Thread panic".to_string()))??;
+            results.push(result);
         }
         
-        // Check for unclosed directives
-        for (directive_type, line, column) in directive_stack {
-            diagnostics.push(Diagnostic::new(
-                &format!("Unclosed {} directive", directive_type),
-                "error",
-                line,
-                column
-            ));
+        Ok(results)
+    }
+    
+    /// Load template using memory mapping (minimal implementation)
+    /// In production, this would use memmap2 crate for true memory mapping
+    pub fn load_template_mmap(&mut self, name: &str) -> TemplateResult<String> {
+        // Check cache first for memory efficiency
+        if let Some(cached) = self.cache.get(name) {
+            return Ok(cached.to_string());
         }
+        if self.offline {
+            return Err(TemplateError::OfflineMiss { name: name.to_string() });
+        }
+
+        // Validate template path to prevent path traversal attacks
+        self.validate_template_path(name)?;
+
+        let path = Path::new(&self.template_dir).join(name);
+        let content = fs::read_to_string(&path)
+            .map_err(|e| TemplateError::Template(format!("Failed to mmap template '{}': {}", name, e)))?;
+        let content = self.trim_block_whitespace(content);
+
+        // In a real implementation with memmap2:
+        // let file = File::open(&path)?;
+        // let mmap = unsafe { MmapOptions::new().map(&file)? };
+        // let content = std::str::from_utf8(&mmap)?;
         
-        Ok(diagnostics)
+        let content: Arc<str> = content.into();
+        self.cache_template(name, &content);
+        Ok(content.to_string())
     }
     
-    /// Get hover information at a specific position
-    pub fn get_hover_info_at_position(&mut self, template: &str, position: usize, context: &TemplateContext) -> TemplateResult<HoverInfo> {
-        let token = self.get_full_token_at_position(template, position);
+    /// Compile template to bytecode
+    pub fn compile_to_bytecode(&mut self, template_name: &str) -> TemplateResult<CompiledTemplate> {
+        if self.coverage_enabled {
+            self.register_coverage_source(template_name)?;
+        }
+        if self.bytecode_cache_enabled {
+            if let Some(cached) = self.bytecode_cache.get(template_name) {
+                return Ok(cached.clone());
+            }
+        }
         
-        if let Some(value) = context.variables.get(&token) {
-            let (var_type, current_value) = match value {
-                TemplateValue::String(s) => ("String", s.clone()),
-                TemplateValue::Number(n) => ("Number", n.to_string()),
-                TemplateValue::Bool(b) => ("Boolean", b.to_string()),
-                TemplateValue::Array(arr) => ("Array", format!("[{} items]", arr.len())),
-                TemplateValue::Object(obj) => ("Object", format!("{{{}  keys}}", obj.len())),
-            };
+        let template_content = self.load_template_arc(template_name)?;
+        let instructions = self.compiler.compile(&template_content)?;
+        let compiled = CompiledTemplate::new(template_name.to_string(), instructions);
+        
+        if self.bytecode_cache_enabled {
+            self.bytecode_cache.insert(template_name.to_string(), compiled.clone());
+        }
+        
+        Ok(compiled)
+    }
+    
+    /// Compile template to bytecode without caching
+    pub fn compile_to_bytecode_uncached(&mut self, template_name: &str) -> TemplateResult<CompiledTemplate> {
+        if self.coverage_enabled {
+            self.register_coverage_source(template_name)?;
+        }
+        let template_content = self.load_template_arc(template_name)?;
+        let instructions = self.compiler.compile(&template_content)?;
+        Ok(CompiledTemplate::new(template_name.to_string(), instructions))
+    }
+    
+    /// Render compiled template
+    pub fn render_compiled(&self, compiled_template: &CompiledTemplate, context: &TemplateContext) -> TemplateResult<String> {
+        let output = self.executor.execute(&compiled_template.instructions, context)?;
+        if self.coverage_enabled {
+            // Bytecode renders don't include other templates
+            self.cover_template(&compiled_template.name, None, context, &mut Vec::new());
+        }
+        Ok(output)
+    }
+    
+    /// Parse a template for the coverage of its bytecode renders
+    fn register_coverage_source(&mut self, template_name: &str) -> TemplateResult<()> {
+        let source = self.load_template_arc(template_name)?;
+        self.coverage.lock().unwrap_or_else(PoisonError::into_inner).parse(template_name, &source);
+        Ok(())
+    }
+    
+    /// Check if template is cached in bytecode cache
+    pub fn is_bytecode_cached(&self, template_name: &str) -> bool {
+        self.bytecode_cache.contains_key(template_name)
+    }
+    
+    /// Enable or disable bytecode caching
+    pub fn enable_bytecode_cache(&mut self, enabled: bool) {
+        self.bytecode_cache_enabled = enabled;
+        if !enabled {
+            self.bytecode_cache.clear();
+        }
+    }
+    
+    /// Check if bytecode caching is enabled
+    pub fn is_bytecode_cache_enabled(&self) -> bool {
+        self.bytecode_cache_enabled
+    }
+    
+    /// Compile multiple templates in parallel
+    pub fn compile_templates_parallel(&mut self, template_names: &[String]) -> TemplateResult<Vec<CompiledTemplate>> {
+        let template_dir = Arc::new(self.template_dir.clone());
+        let template_roots = Arc::new(self.template_roots.clone());
+        let (trim_blocks, lstrip_blocks) = (self.trim_blocks, self.lstrip_blocks);
+        
+        let handles: Vec<_> = template_names.iter().map(|name| {
+            let name = name.clone();
+            let template_dir = Arc::clone(&template_dir);
+            let template_roots = Arc::clone(&template_roots);
             
-            Ok(HoverInfo {
-                variable_name: token.clone(),
-                variable_type: var_type.to_string(),
-                current_value,
-                description: format!("Template variable of type {}", var_type),
+            thread::spawn(move || {
+                let mut engine = TemplateEngine::new(&template_dir);
+                engine.template_roots = template_roots.to_vec();
+                engine.trim_blocks = trim_blocks;
+                engine.lstrip_blocks = lstrip_blocks;
+                engine.compile_to_bytecode(&name)
             })
+        }).collect();
+        
+        let mut results = Vec::new();
+        for handle in handles {
+            let result = handle.join().map_err(|_| TemplateError::Render("Thread panic".to_string()))??;
+            results.push(result);
+        }
+        
+        Ok(results)
+    }
+    
+    /// Render multiple compiled templates in parallel
+    pub fn render_compiled_parallel(&self, compiled_templates: &[CompiledTemplate], context: &TemplateContext) -> TemplateResult<Vec<String>> {
+        let context = Arc::new(context.clone());
+        let executor = Arc::new(self.executor.clone());
+        
+        let handles: Vec<_> = compiled_templates.iter().map(|template| {
+            let template = template.clone();
+            let context = Arc::clone(&context);
+            let executor: Arc<BytecodeExecutor> = Arc::clone(&executor);
+            
+            thread::spawn(move || {
+                executor.execute(&template.instructions, &context)
+            })
+        }).collect();
+        
+        let mut results = Vec::new();
+        for handle in handles {
+            let result = handle.join().map_err(|_| TemplateError::Render("Thread panic".to_string()))??;
+            results.push(result);
+        }
+        
+        Ok(results)
+    }
+
+    /// Write the translation of one `{{t "key" name=value}}` directive
+    /// 
+    /// Translation values are not full templates: they may only interpolate
+    /// variables (`{{name}}`, `{{user.name|upper}}`), hold comments and
+    /// reference other keys with `{{t "key"}}`. Any other directive is
+    /// rejected with [`TemplateError::Security`], and a key that ends up
+    /// referencing itself fails with the key chain in the message. Named
+    /// arguments are set as variables for the translation.
+    #[cfg(feature = "i18n")]
+    fn write_translation(&self, directive: &str, context: &TemplateContext, out: &mut String) -> TemplateResult<()> {
+        let arguments = parse_arguments(directive, 2..directive.len(), Separator::Whitespace)?;
+        let Some((key, arguments)) = arguments.split_first().filter(|(key, _)| key.name.is_none()) else {
+            return Err(TemplateError::Parse(format!("Missing translation key in '{{{{{}}}}}'", directive)));
+        };
+        let translation_key = match &key.value {
+            ArgumentValue::String(key) => key.as_str(),
+            _ => key.text,
+        };
+        let translation = self.expand_translation(translation_key, &mut Vec::new())?;
+        
+        if arguments.is_empty() {
+            self.write_variables(&translation, context, out)
         } else {
-            Err(TemplateError::Runtime(format!("No information available for '{}'", token)))
+            let mut translation_context = context.clone();
+            for argument in arguments {
+                let Some(name) = argument.name else {
+                    return Err(TemplateError::Parse(format!(
+                        "Unexpected argument '{}' in '{{{{{}}}}}'; translation arguments are name=value",
+                        argument.text, directive
+                    )));
+                };
+                let value = self.argument_value(&argument.value, context)?
+                    .unwrap_or_else(|| TemplateValue::String(String::new()));
+                translation_context.set(name, value);
+            }
+            self.write_variables(&translation, &translation_context, out)
         }
     }
     
-    /// Get the full token at position (for hover information)
-    fn get_full_token_at_position(&self, template: &str, position: usize) -> String {
-        let mut current_pos = 0;
+    /// Without the `i18n` feature `{{t}}` fails instead of rendering the key
+    #[cfg(not(feature = "i18n"))]
+    fn write_translation(&self, _directive: &str, _context: &TemplateContext, _out: &mut String) -> TemplateResult<()> {
+        Err(feature_disabled("{{t}}", "i18n"))
+    }
+    
+    /// Translation of `key` with its `{{t "key"}}` references expanded,
+    /// checked to hold only interpolations and comments
+    #[cfg(feature = "i18n")]
+    fn expand_translation(&self, key: &str, chain: &mut Vec<String>) -> TemplateResult<String> {
+        if chain.iter().any(|seen| seen == key) {
+            chain.push(key.to_string());
+            return Err(TemplateError::Render(format!("Recursive translation: {}", chain.join(" -> "))));
+        }
+        chain.push(key.to_string());
         
-        while let Some(start) = template[current_pos..].find("{{") {
-            let abs_start = current_pos + start;
-            if let Some(end) = template[abs_start..].find("}}") {
-                let abs_end = abs_start + end + 2;
-                
-                if position >= abs_start && position <= abs_end {
-                    let directive_content = &template[abs_start + 2..abs_start + end];
-                    
-                    // Extract the full variable name, not partial
-                    if directive_content.contains('|') {
-                        let parts: Vec<&str> = directive_content.split('|').collect();
-                        return parts[0].trim().to_string();
-                    } else {
-                        return directive_content.trim().to_string();
-                    }
-                }
-                
-                current_pos = abs_end;
+        let translation = match self.lookup_translation(key) {
+            Some(translation) => self.localized(translation),
+            None => self.missing_translation(key, key),
+        };
+        let mut expanded = String::new();
+        let mut pos = 0;
+        while let Some(relative_start) = translation[pos..].find("{{") {
+            let start = pos + relative_start;
+            expanded.push_str(&translation[pos..start]);
+            let end = translation[start..].find("}}")
+                .ok_or_else(|| TemplateError::Parse(format!("Unclosed directive in translation '{}'", key)))?;
+            let tag = &translation[start..start + end + 2];
+            let directive = tag[2..tag.len() - 2].trim();
+            
+            if let Some(args) = directive.strip_prefix("t ") {
+                let (nested_key, _) = split_key(args.trim());
+                expanded.push_str(&self.expand_translation(nested_key, chain)?);
+            } else if directive.starts_with('!') || is_variable_path(directive.split('|').next().unwrap_or_default().trim()) {
+                expanded.push_str(tag);
             } else {
-                break;
+                return Err(TemplateError::Security(format!(
+                    "{} is not allowed in translation '{}': '{}'",
+                    forbidden_construct(directive), key, tag
+                )));
             }
+            pos = start + end + 2;
         }
+        expanded.push_str(&translation[pos..]);
         
-        "".to_string()
+        chain.pop();
+        Ok(expanded)
     }
-    
-    /// Get definition location at a specific position
-    pub fn get_definition_at_position(&mut self, template: &str, position: usize) -> TemplateResult<DefinitionInfo> {
-        let token = self.get_full_token_at_position(template, position);
+
+    /// Process pluralization directives {{plural count "singular" "plural"}}
+    fn process_pluralization(&self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
+        let mut result = template.to_string();
         
-        // Check if it's a macro call by looking for function call syntax
-        if token.contains('(') {
-            let macro_name = token.split('(').next().unwrap_or(&token).trim();
+        while let Some(start) = result.find("{{plural ") {
+            let end = result[start..].find("}}")
+                .ok_or_else(|| TemplateError::Parse("Unclosed pluralization directive".to_string()))?;
             
-            // Find macro definition
-            let mut current_pos = 0;
-            while let Some(start) = template[current_pos..].find("{{macro ") {
-                let abs_start = current_pos + start;
-                if let Some(end) = template[abs_start..].find("}}") {
-                    let macro_content = &template[abs_start + 8..abs_start + end].trim();
-                    let defined_macro_name = macro_content.split('(').next().unwrap_or(macro_content);
-                    
-                    if defined_macro_name.trim() == macro_name {
-                        let (line, column) = self.calculate_line_column(template, abs_start);
-                        return Ok(DefinitionInfo {
-                            definition_type: "macro".to_string(),
-                            name: macro_name.to_string(),
-                            line,
-                            column: column + 8, // After "{{macro "
-                            file_path: None,
-                        });
-                    }
-                    
-                    current_pos = abs_start + end + 2;
-                } else {
-                    break;
-                }
+            let directive = &result[start + 2..start + end];
+            let arguments = parse_arguments(directive, 7..directive.len(), Separator::Whitespace)?;
+            let [count, singular, plural] = &arguments[..] else {
+                return Err(TemplateError::Parse("Invalid pluralization syntax. Use: {{plural count \"singular\" \"plural\"}}".to_string()));
+            };
+            if let Some(named) = arguments.iter().find_map(|argument| argument.name) {
+                return Err(TemplateError::Parse(format!(
+                    "Unexpected argument '{}=' in '{{{{{}}}}}'; use {{{{plural count \"singular\" \"plural\"}}}}", named, directive
+                )));
             }
+            
+            // Get the count value (filters such as `count|int` coerce it first)
+            let count = self.argument_value(&count.value, context)?.unwrap_or(TemplateValue::Number(0));
+            
+            let is_singular = match count {
+                TemplateValue::Number(n) => n == 1,
+                TemplateValue::Float(f) => f == 1.0,
+                _ => false,
+            };
+            // Unquoted forms (`{{plural n item items}}`) are taken as written
+            let chosen_form = match if is_singular { singular } else { plural } {
+                Argument { value: ArgumentValue::String(form), .. } => form.clone(),
+                form => form.text.to_string(),
+            };
+            #[cfg(feature = "i18n")]
+            let chosen_form = self.localized(&chosen_form);
+            
+            result.replace_range(start..start + end + 2, &chosen_form);
         }
         
-        Err(TemplateError::Runtime(format!("No definition found for '{}'", token)))
+        Ok(result)
     }
     
-    // Helper methods for LSP functionality
+    /// Value of a `{{set}}` expression: a helper call, whose value is kept
+    /// as is, a filter chain, a literal or a variable
+    fn evaluate_assigned_value(&self, expression: &str, context: &TemplateContext) -> TemplateResult<TemplateValue> {
+        if let Some(open) = expression.find('(').filter(|_| expression.ends_with(')')) {
+            let name = expression[..open].trim();
+            if let Some(value) = self.call_function(expression, context)? {
+                return Ok(value);
+            }
+            if is_variable_path(name) && !name.contains('.') {
+                if name == "paginate" {
+                    let arguments = self.parse_helper_args(expression, open + 1..expression.len() - 1, context)?;
+                    return paginate(&arguments, self.strict_mode);
+                }
+                return match self.process_helper_call(expression, context)? {
+                    Some(output) => Ok(TemplateValue::String(output)),
+                    None => Err(TemplateError::Render(format!("Unknown helper '{}' in '{{{{set ... = {}}}}}'", name, expression))),
+                };
+            }
+        }
+        let value = parse_value(expression).map_err(|(message, _)| {
+            TemplateError::Parse(format!("{} in '{{{{set ... = {}}}}}'", message, expression))
+        })?;
+        Ok(self.argument_value(&value, context)?.unwrap_or_else(|| TemplateValue::String(String::new())))
+    }
     
-    /// Calculate line and column from position
-    fn calculate_line_column(&self, content: &str, position: usize) -> (usize, usize) {
-        find_line_column(content, position)
+    // ====================
+    // v0.4.0 Developer Experience Methods
+    // ====================
+    
+    /// Enable debug mode for detailed execution tracking
+    pub fn enable_debug_mode(&mut self) {
+        self.debug_enabled = true;
     }
     
-    /// Get token at specific position
-    fn get_token_at_position(&self, template: &str, position: usize) -> (String, String) {
-        // Find the template directive containing this position
-        let mut current_pos = 0;
-        
-        while let Some(start) = template[current_pos..].find("{{") {
-            let abs_start = current_pos + start;
-            if let Some(end) = template[abs_start..].find("}}") {
-                let abs_end = abs_start + end + 2;
-                
-                if position >= abs_start && position <= abs_end {
-                    let directive_content = &template[abs_start + 2..abs_start + end];
-                    let rel_pos = position - (abs_start + 2);
-                    
-                    // Determine token type and extract current token at cursor position
-                    if directive_content.contains('|') {
-                        let parts: Vec<&str> = directive_content.split('|').collect();
-                        let mut current_char_pos = 0;
-                        
-                        for (i, part) in parts.iter().enumerate() {
-                            if rel_pos >= current_char_pos && rel_pos <= current_char_pos + part.len() {
-                                if i == 0 {
-                                    // Extract partial variable name up to cursor
-                                    let partial_var = &part.trim()[..std::cmp::min(rel_pos.saturating_sub(current_char_pos), part.trim().len())];
-                                    return (partial_var.to_string(), "variable".to_string());
-                                } else {
-                                    // Extract partial filter name up to cursor  
-                                    let filter_start = current_char_pos;
-                                    let partial_filter = &part.trim()[..std::cmp::min(rel_pos - filter_start, part.trim().len())];
-                                    return (partial_filter.to_string(), "filter".to_string());
-                                }
-                            }
-                            current_char_pos += part.len() + 1; // +1 for the '|' separator
-                        }
-                    } else {
-                        // Check if it's a potential directive (single words that could be directives)
-                        let partial_content = &directive_content[..std::cmp::min(rel_pos, directive_content.len())].trim();
-                        
-                        // If the partial content looks like it could be a directive
-                        let directive_keywords = ["if", "for", "include", "macro"];
-                        let is_potential_directive = directive_keywords.iter().any(|&kw| kw.starts_with(partial_content) || partial_content.is_empty());
-                        
-                        if is_potential_directive && !partial_content.contains(' ') {
-                            return (partial_content.to_string(), "directive".to_string());
-                        } else {
-                            return (partial_content.to_string(), "variable".to_string());
-                        }
-                    }
+    /// Disable debug mode
+    pub fn disable_debug_mode(&mut self) {
+        self.debug_enabled = false;
+    }
+    
+    /// Check if debug mode is enabled
+    pub fn is_debug_enabled(&self) -> bool {
+        self.debug_enabled
+    }
+    
+    // v0.5.0 Ecosystem Integration methods
+    
+    /// Get the template directory (for async and other integrations)
+    pub fn get_template_dir(&self) -> &str {
+        &self.template_dir
+    }
+    
+    /// Get WASM console logging status
+    #[cfg(feature = "wasm")]
+    pub fn get_wasm_console_logging(&self) -> bool {
+        self.wasm_console_logging
+    }
+    
+    /// Set WASM console logging
+    #[cfg(feature = "wasm")]
+    pub fn set_wasm_console_logging(&mut self, enabled: bool) {
+        self.wasm_console_logging = enabled;
+    }
+    
+    /// Get cache size for WASM memory usage calculation
+    #[cfg(feature = "wasm")]
+    pub fn get_cache_size(&self) -> usize {
+        self.cache.len()
+    }
+    
+    /// Whether macros are registered, making every render run the macro pass
+    #[cfg(feature = "template-macros")]
+    fn has_macros(&self) -> bool {
+        !self.macros.is_empty()
+    }
+    
+    #[cfg(not(feature = "template-macros"))]
+    fn has_macros(&self) -> bool {
+        false
+    }
+    
+    /// Get macro count for WASM memory usage calculation
+    #[cfg(all(feature = "wasm", feature = "template-macros"))]
+    pub fn get_macro_count(&self) -> usize {
+        self.macros.len()
+    }
+    
+    /// Enable hot reload functionality
+    pub fn enable_hot_reload(&mut self) {
+        self.hot_reload_enabled = true;
+    }
+    
+    /// Disable hot reload functionality
+    pub fn disable_hot_reload(&mut self) {
+        self.hot_reload_enabled = false;
+    }
+    
+    /// Check if hot reload is enabled
+    pub fn is_hot_reload_enabled(&self) -> bool {
+        self.hot_reload_enabled
+    }
+    
+    /// Render template with debug information
+    #[cfg(feature = "debug-tools")]
+    pub fn render_string_with_debug(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<DebugRenderResult> {
+        let start_time = SystemTime::now();
+        let mut debug_info = DebugInfo::new();
+        
+        // Track template processing
+        debug_info.add_template_processed("inline_template");
+        
+        // Add initial execution step
+        debug_info.add_execution_step(ExecutionStep::new("start", "template_render", 1, 1));
+        
+        // Perform the actual rendering with debug tracking
+        let recorder = Arc::new(Mutex::new(IncludeRecorder::new("inline_template")));
+        let previous = self.include_trace.replace(Arc::clone(&recorder));
+        let result = self.render_string_with_debug_tracking(template, context, &mut debug_info);
+        self.include_trace = previous;
+        let output = result?;
+        if let Ok(recorder) = recorder.lock() {
+            debug_info.performance_metrics.templates_touched = recorder.tree().unique_templates().len();
+        }
+        
+        // Calculate total time
+        if let Ok(duration) = start_time.elapsed() {
+            debug_info.performance_metrics.total_time_nanos = duration.as_nanos() as u64;
+        }
+        
+        // Add final execution step
+        debug_info.add_execution_step(ExecutionStep::new("end", "template_render", 1, template.len()));
+        
+        Ok(DebugRenderResult {
+            output,
+            debug_info,
+        })
+    }
+    
+    /// Internal method for rendering with debug tracking
+    #[cfg(feature = "debug-tools")]
+    fn render_string_with_debug_tracking(&mut self, template: &str, context: &TemplateContext, debug_info: &mut DebugInfo) -> TemplateResult<String> {
+        // For now, delegate to regular render_string but track variables
+        // In a full implementation, this would intercept variable access and track execution steps
+        
+        // Simple variable tracking by scanning template content
+        let mut current_pos = 0;
+        while let Some(start) = template[current_pos..].find("{{") {
+            let abs_start = current_pos + start;
+            if let Some(end) = template[abs_start..].find("}}") {
+                let (_, var_content, _) = split_trim_markers(&template[abs_start + 2..abs_start + end]);
+                let (line, column) = find_line_column(template, abs_start);
+                
+                // Track different types of template directives
+                if let Some(stripped) = var_content.strip_prefix("if ") {
+                    let condition = stripped.trim();
+                    debug_info.add_execution_step(ExecutionStep::new("conditional", condition, line, column));
+                    debug_info.add_variable_access(condition);
+                } else if let Some(Some(condition)) = else_condition(var_content.trim()) {
+                    debug_info.add_execution_step(ExecutionStep::new("conditional", condition, line, column));
+                    debug_info.add_variable_access(condition);
+                } else if let Some(stripped) = var_content.strip_prefix("for ") {
+                    let loop_expr = stripped.trim();
+                    debug_info.add_execution_step(ExecutionStep::new("loop", loop_expr, line, column));
+                    if let Some(in_pos) = loop_expr.find(" in ") {
+                        let array_var = &loop_expr[in_pos + 4..];
+                        debug_info.add_variable_access(array_var.trim());
+                    }
+                } else if !var_content.starts_with("/") && !var_content.starts_with("!") && !var_content.starts_with("#--") && else_condition(var_content.trim()).is_none()
+                    && var_content.trim() != "empty"
+                {
+                    // Regular variable
+                    let var_name = var_content.split('|').next().unwrap_or(var_content).trim();
+                    if !var_name.is_empty() {
+                        let mut step = ExecutionStep::new("variable", var_name, line, column);
+                        if is_variable_path(var_name) && self.variable_exists_in_context(var_name, context) {
+                            let value = self.resolve_variable_from_context(var_name, context);
+                            step = step.with_result(self.surfaced_value(var_name, &value, |value| self.template_value_to_string(value)));
+                        }
+                        debug_info.add_execution_step(step);
+                        debug_info.add_variable_access(var_name);
+                    }
+                }
+                
+                current_pos = abs_start + end + 2;
+            } else {
+                break;
+            }
+        }
+        
+        // Includes are expanded first so the include trace sees them
+        let parts = self.rendered_parts.len();
+        let started = Instant::now();
+        let template = self.process_includes(template, context)?;
+        debug_info.performance_metrics.include_nanos = elapsed_nanos(started);
+        
+        // Delegate to original rendering to avoid recursion
+        let rendered = self.render_string_original(&template, context).map(|mut output| {
+            self.put_back_rendered_parts(&mut output, 0);
+            output
+        });
+        self.rendered_parts.truncate(parts);
+        rendered
+    }
+    
+    /// Enhanced render method with better error messages and suggestions (v0.4.0 override)
+    pub fn render_v040(&mut self, template_name: &str, context: &TemplateContext) -> TemplateResult<String> {
+        // Check for hot reload
+        if self.hot_reload_enabled {
+            self.check_and_reload_if_needed(template_name)?;
+        }
+        
+        // Try to load template with enhanced error handling
+        match self.load_template_with_enhanced_errors(template_name) {
+            Ok(template_content) => {
+                self.render_string_with_error_enhancement(&template_content, context, Some(template_name.to_string()))
+            },
+            Err(e) => Err(e),
+        }
+    }
+    
+    /// Load template with enhanced error messages and suggestions
+    fn load_template_with_enhanced_errors(&mut self, template_name: &str) -> TemplateResult<Arc<str>> {
+        // Check if template exists
+        let template_path = Path::new(&self.template_dir).join(template_name);
+        
+        if !template_path.exists() {
+            // Generate helpful suggestions
+            let available_templates = self.list_available_templates()?;
+            #[cfg(feature = "suggestions")]
+            let suggestions = suggest_templates(template_name, &available_templates, 3);
+            #[cfg(not(feature = "suggestions"))]
+            let suggestions = Vec::new();
+            
+            return Err(TemplateError::TemplateNotFoundWithSuggestions {
+                template_name: template_name.to_string(),
+                template_dir: self.template_dir.clone(),
+                suggestions,
+                available_templates,
+            });
+        }
+        
+        // Load and cache template
+        self.load_template_arc(template_name)
+    }
+    
+    /// List all available templates in the template directory (including subdirectories)
+    fn list_available_templates(&self) -> TemplateResult<Vec<String>> {
+        let mut templates = Vec::new();
+        let template_dir = Path::new(&self.template_dir);
+        
+        if template_dir.exists() && template_dir.is_dir() {
+            Self::collect_template_files(template_dir, "", &mut templates)?;
+        }
+        
+        // Directory iteration order is platform dependent
+        templates.sort();
+        
+        Ok(templates)
+    }
+    
+    /// Recursively collect `.html`/`.htm` files as `/`-separated names relative to the template directory
+    fn collect_template_files(dir: &Path, prefix: &str, templates: &mut Vec<String>) -> TemplateResult<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            let file_name = entry.file_name();
+            let Some(file_name) = file_name.to_str() else { continue };
+            
+            let relative_name = if prefix.is_empty() {
+                file_name.to_string()
+            } else {
+                format!("{}/{}", prefix, file_name)
+            };
+            
+            // file_type() does not follow symlinks, so directory cycles cannot recurse forever
+            if file_type.is_dir() {
+                Self::collect_template_files(&entry.path(), &relative_name, templates)?;
+            } else if entry.path().is_file() && (file_name.ends_with(".html") || file_name.ends_with(".htm")) {
+                templates.push(relative_name);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Check if templates need to be reloaded for hot reload functionality
+    fn check_and_reload_if_needed(&mut self, template_name: &str) -> TemplateResult<()> {
+        let template_path = Path::new(&self.template_dir).join(template_name);
+        
+        if let Some(stamp) = file_stamp(&template_path) {
+            if self.file_mtimes.get(template_name) != Some(&stamp) {
+                // Also reloads the templates including or extending it
+                self.reload_templates(&[template_name]);
+                self.file_mtimes.insert(template_name.to_string(), stamp);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Enhanced render_string with better error handling (v0.4.0 override)
+    pub fn render_string_v040(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
+        // Parse and render with enhanced error handling
+        self.render_string_with_error_enhancement(template, context, None)
+    }
+    
+    /// Legacy render_string method that calls the original implementation
+    #[cfg(feature = "debug-tools")]
+    fn render_string_original(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
+        // This calls the original implementation logic
+        self.parse_and_render_internal(template, context, None)
+    }
+    
+    /// Internal method with enhanced error handling
+    fn render_string_with_error_enhancement(&mut self, template: &str, context: &TemplateContext, template_name: Option<String>) -> TemplateResult<String> {
+        // Try to parse template and catch errors with location info
+        match self.parse_and_render_internal(template, context, template_name.as_deref()) {
+            Ok(result) => Ok(result),
+            Err(TemplateError::Parse(msg)) => {
+                // Enhance parse errors with location information
+                self.enhance_parse_error(&msg, template, template_name)
+            },
+            Err(other) => Err(other),
+        }
+    }
+    
+    /// Enhance parse errors with location and context information
+    fn enhance_parse_error(&self, error_msg: &str, template: &str, template_name: Option<String>) -> TemplateResult<String> {
+        // Try to find error location by looking for unclosed tags
+        let (line, column) = if error_msg.contains("unclosed") {
+            // Find the unclosed tag
+            if let Some(pos) = template.find("{{if") {
+                find_line_column(template, pos)
+            } else {
+                (1, 1)
+            }
+        } else {
+            (1, 1)
+        };
+        
+        let context_lines = extract_context_lines(template, line, 2);
+        
+        Err(TemplateError::ParseWithLocation {
+            message: error_msg.to_string(),
+            line,
+            column,
+            template_name,
+            context_lines,
+        })
+    }
+    
+    /// Internal parsing and rendering - simplified implementation for v0.4.0
+    fn parse_and_render_internal(&mut self, template: &str, context: &TemplateContext, _template_name: Option<&str>) -> TemplateResult<String> {
+        // For v0.4.0, we'll create a simplified version that just processes basic variables
+        // This avoids complex method signature issues while we focus on error handling
+        
+        let mut result = template.to_string();
+        
+        // Basic variable processing
+        result = self.process_variables(&result, context)?;
+        
+        // Check for unclosed tags to trigger parse errors for testing
+        if result.contains("{{if") && !result.contains("{{/if}}") {
+            return Err(TemplateError::Parse("Unclosed {{if}} tag".to_string()));
+        }
+        
+        Ok(result)
+    }
+    
+    // ====================
+    // v0.4.1 IDE Integration Methods  
+    // ====================
+    
+    /// Parse template for Language Server Protocol analysis
+    #[cfg(feature = "lsp")]
+    pub fn parse_for_lsp(&mut self, template_content: &str, _file_path: &str) -> TemplateResult<LspParseResult> {
+        let mut result = LspParseResult::new();
+        
+        // Scan template for all variables, blocks, and filters
+        let mut current_pos = 0;
+        let _line = 1;
+        let _column = 1;
+        
+        while let Some(start) = template_content[current_pos..].find("{{") {
+            let abs_start = current_pos + start;
+            if let Some(end) = template_content[abs_start..].find("}}") {
+                let (_, directive_content, _) = split_trim_markers(&template_content[abs_start + 2..abs_start + end]);
+                let (current_line, current_column) = self.calculate_line_column(template_content, abs_start);
+                
+                // Parse different types of directives
+                if directive_content.trim().starts_with("if ") {
+                    let condition = directive_content.trim()[3..].trim();
+                    result.add_block(TemplateBlock::new("if", current_line, current_column, condition));
+                    result.add_variable(condition);
+                } else if let Some(Some(condition)) = else_condition(directive_content.trim()) {
+                    result.add_variable(condition);
+                } else if let Some(keyword) = ["unless", "ifchanged"].into_iter().find(|keyword| {
+                    directive_content.trim().strip_prefix(keyword).is_some_and(|rest| rest.starts_with(' '))
+                }) {
+                    let argument = directive_content.trim()[keyword.len()..].trim();
+                    result.add_block(TemplateBlock::new(keyword, current_line, current_column, argument));
+                    result.add_variable(argument);
+                } else if directive_content.trim().starts_with("for ") {
+                    let for_expr = directive_content.trim()[4..].trim();
+                    result.add_block(TemplateBlock::new("for", current_line, current_column, for_expr));
+                    if let Some(in_pos) = for_expr.find(" in ") {
+                        let array_var = &for_expr[in_pos + 4..];
+                        result.add_variable(array_var.trim());
+                    }
+                } else if directive_content.trim().starts_with("macro ") {
+                    let macro_def = directive_content.trim()[6..].trim();
+                    let macro_name = macro_def.split('(').next().unwrap_or(macro_def);
+                    result.macros.push(macro_name.to_string());
+                } else if let Some(name) = directive_content.trim().strip_prefix("capture ") {
+                    result.add_block(TemplateBlock::new("capture", current_line, current_column, name.trim()));
+                } else if let Some((_, value)) = directive_content.trim().strip_prefix("set ").and_then(split_assignment) {
+                    let mut parts = value.split('|');
+                    let var_name = parts.next().unwrap_or_default().trim();
+                    if is_variable_path(var_name) {
+                        result.add_variable(var_name);
+                    }
+                    for filter in parts {
+                        result.add_filter(filter.split(':').next().unwrap_or(filter).trim());
+                    }
+                } else if !directive_content.starts_with("/") && !directive_content.starts_with("!") && !directive_content.starts_with("#--")
+                    && !matches!(directive_content.trim(), "no_provenance" | "flush" | "uid" | "empty")
+                    && else_condition(directive_content.trim()).is_none()
+                    && !directive_content.trim().starts_with("uid ")
+                {
+                    // Regular variable or filter chain
+                    let parts: Vec<&str> = directive_content.split('|').collect();
+                    let var_name = parts[0].trim();
+                    if !var_name.is_empty() && !self.is_known_helper_call(var_name) {
+                        result.add_variable(var_name);
+                    }
+                    
+                    // Add filters
+                    for filter in parts.iter().skip(1) {
+                        let filter_name = filter.split(':').next().unwrap_or(filter).trim();
+                        result.add_filter(filter_name);
+                    }
+                }
+                
+                current_pos = abs_start + end + 2;
+            } else {
+                break;
+            }
+        }
+        
+        Ok(result)
+    }
+    
+    /// Get auto-completions at a specific position in the template
+    #[cfg(feature = "lsp")]
+    pub fn get_completions_at_position(&mut self, template: &str, position: usize, context: &TemplateContext) -> TemplateResult<Vec<CompletionItem>> {
+        let mut completions = Vec::new();
+        
+        // Find the current token being typed
+        let (current_token, token_type) = self.get_token_at_position(template, position);
+        
+        match token_type.as_str() {
+            "variable" => {
+                // Complete variable names (sorted for stable completion lists)
+                let mut variables: Vec<(&str, &TemplateValue)> = context.visible_variables().into_iter().collect();
+                variables.sort_by(|a, b| a.0.cmp(b.0));
+                for (var_name, var_value) in variables {
+                    if var_name.starts_with(&current_token) {
+                        let shown = || self.surfaced_value(var_name, var_value, |value| self.template_value_to_string(value));
+                        let detail = match var_value {
+                            TemplateValue::String(_) => format!("String: {}", shown()),
+                            TemplateValue::Number(_) => format!("Number: {}", shown()),
+                            TemplateValue::Float(_) => format!("Float: {}", shown()),
+                            TemplateValue::Bool(_) => format!("Boolean: {}", shown()),
+                            TemplateValue::Array(_) => "Array".to_string(),
+                            TemplateValue::Object(_) => "Object".to_string(),
+                            TemplateValue::Null => "Null".to_string(),
+                        };
+                        completions.push(CompletionItem::new(var_name, "variable", &detail));
+                    }
+                }
+                
+                for helper in self.project_config.iter().flat_map(|config| &config.helpers) {
+                    if helper.name.starts_with(&current_token) {
+                        completions.push(CompletionItem::new(&helper.name, "helper", &format!("Helper: {}", helper.signature())));
+                    }
+                }
+            },
+            "filter" => {
+                // Complete filter names
+                let built_in_filters = vec![
+                    ("upper", "Convert text to uppercase"),
+                    ("lower", "Convert text to lowercase"),
+                    ("currency", "Format as currency"),
+                    ("truncate", "Truncate text with ellipsis"),
+                    ("round", "Round numbers to specified decimals"),
+                    ("duration", "Format seconds as days, hours and minutes"),
+                    ("filesize", "Format bytes in KiB, MiB, GiB"),
+                    ("percent", "Format a ratio as a percentage"),
+                ];
+                
+                for (filter_name, description) in built_in_filters {
+                    if filter_name.starts_with(&current_token) {
+                        completions.push(CompletionItem::new(filter_name, "filter", description));
+                    }
+                }
+                
+                for filter in self.project_config.iter().flat_map(|config| &config.filters) {
+                    if filter.name.starts_with(&current_token) {
+                        completions.push(CompletionItem::new(&filter.name, "filter", &format!("Project filter: {}", filter.signature())));
+                    }
+                }
+                
+                let mut pipelines: Vec<(&String, &String)> = self.pipeline_definitions.iter().collect();
+                pipelines.sort();
+                for (pipeline_name, definition) in pipelines {
+                    if pipeline_name.starts_with(&current_token) {
+                        completions.push(CompletionItem::new(pipeline_name, "filter", &format!("Pipeline: {}", definition)));
+                    }
+                }
+            },
+            "directive" => {
+                // Complete template directives
+                let directives = vec![
+                    ("if", "Conditional rendering"),
+                    ("elseif", "Render when the previous {{if}} conditions are false and this one holds"),
+                    ("else", "Render when the {{if}} condition is false"),
+                    ("unless", "Render when a condition is false"),
+                    ("ifchanged", "Render when a value changed since the previous loop item"),
+                    ("for", "Loop over arrays"),
+                    ("set", "Assign a value for the rest of the scope"),
+                    ("capture", "Render a section into a variable"),
+                    ("include", "Include another template"),
+                    ("macro", "Define reusable component"),
+                    ("no_provenance", "Leave out the provenance marker"),
+                    ("flush", "Flush the writer of render_to_writer"),
+                ];
+                
+                for (directive_name, description) in directives {
+                    if directive_name.starts_with(&current_token) {
+                        completions.push(CompletionItem::new(directive_name, "directive", description));
+                    }
+                }
+            },
+            _ => {}
+        }
+        
+        Ok(completions)
+    }
+    
+    /// Tokenize template for syntax highlighting
+    #[cfg(feature = "lsp")]
+    pub fn tokenize_for_syntax_highlighting(&mut self, template: &str) -> TemplateResult<Vec<SyntaxToken>> {
+        let mut tokens = Vec::new();
+        let mut current_pos = 0;
+        
+        while current_pos < template.len() {
+            // Look for template directives
+            if let Some(start) = template[current_pos..].find("{{") {
+                let abs_start = current_pos + start;
+                
+                // Add HTML content before directive as html_content token
+                if start > 0 {
+                    let html_content = &template[current_pos..abs_start];
+                    let (line, column) = self.calculate_line_column(template, current_pos);
+                    
+                    // Look for HTML tags
+                    if let Some(tag_start) = html_content.find('<') {
+                        if let Some(tag_end) = html_content[tag_start..].find('>') {
+                            let tag = &html_content[tag_start..tag_start + tag_end + 1];
+                            tokens.push(SyntaxToken::new(tag, "html_tag", current_pos + tag_start, line, column));
+                        }
+                    }
+                }
+                
+                if let Some(end) = template[abs_start..].find("}}") {
+                    let (_, directive_content, _) = split_trim_markers(&template[abs_start + 2..abs_start + end]);
+                    let (line, column) = self.calculate_line_column(template, abs_start);
+                    
+                    // Parse directive content
+                    if directive_content.starts_with('!') || directive_content.starts_with("#--") {
+                        // Stripped or visible comment
+                        let comment_end = if directive_content.starts_with("#--") {
+                            template[abs_start..].find(VISIBLE_COMMENT_CLOSE)
+                                .map(|close| close + VISIBLE_COMMENT_CLOSE.len())
+                                .unwrap_or(end + 2)
+                        } else {
+                            end + 2
+                        };
+                        let comment = &template[abs_start..abs_start + comment_end];
+                        tokens.push(SyntaxToken::new(comment, "comment", abs_start, line, column));
+                        current_pos = abs_start + comment_end;
+                        continue;
+                    } else if let Some((name, value)) = directive_content.trim().strip_prefix("set ").and_then(split_assignment) {
+                        // Assignment: the directive, the assigned name and the value expression
+                        tokens.push(SyntaxToken::new("set", "template_directive", abs_start + 2, line, column + 2));
+                        tokens.push(SyntaxToken::new(name, "template_variable", abs_start + 2, line, column + 2));
+                        let mut parts = value.split('|');
+                        let value = parts.next().unwrap_or_default().trim();
+                        tokens.push(SyntaxToken::new(value, "template_variable", abs_start + 2, line, column + 2));
+                        for filter in parts {
+                            let filter_name = filter.split(':').next().unwrap_or(filter).trim();
+                            tokens.push(SyntaxToken::new(filter_name, "template_filter", abs_start + 2, line, column + 2));
+                        }
+                    } else if directive_content.contains('|') {
+                        // Variable with filters
+                        let parts: Vec<&str> = directive_content.split('|').collect();
+                        let var_name = parts[0].trim();
+                        tokens.push(SyntaxToken::new(var_name, "template_variable", abs_start + 2, line, column + 2));
+                        
+                        for filter in parts.iter().skip(1) {
+                            let filter_name = filter.split(':').next().unwrap_or(filter).trim();
+                            tokens.push(SyntaxToken::new(filter_name, "template_filter", abs_start + 2, line, column + 2));
+                        }
+                    } else if directive_content.trim().starts_with("if") || 
+                              directive_content.trim().starts_with("unless") ||
+                              directive_content.trim().starts_with("for") ||
+                              directive_content.trim().starts_with("capture ") ||
+                              else_condition(directive_content.trim()).is_some() ||
+                              directive_content.trim().starts_with("/if") ||
+                              directive_content.trim().starts_with("/unless") ||
+                              directive_content.trim().starts_with("/for") ||
+                              directive_content.trim().starts_with("/capture") {
+                        tokens.push(SyntaxToken::new(directive_content.trim(), "template_directive", abs_start + 2, line, column + 2));
+                    } else {
+                        // Regular variable
+                        tokens.push(SyntaxToken::new(directive_content.trim(), "template_variable", abs_start + 2, line, column + 2));
+                    }
+                    
+                    current_pos = abs_start + end + 2;
+                } else {
+                    break;
+                }
+            } else {
+                break;
+            }
+        }
+        
+        Ok(tokens)
+    }
+    
+    /// Get syntax theme information for editors
+    #[cfg(feature = "lsp")]
+    pub fn get_syntax_theme_info(&self) -> TemplateResult<HashMap<String, String>> {
+        let mut theme = HashMap::new();
+        
+        // Define semantic colors for different token types
+        theme.insert("template_variable".to_string(), "#569cd6".to_string()); // Blue
+        theme.insert("template_filter".to_string(), "#4ec9b0".to_string());   // Cyan
+        theme.insert("template_directive".to_string(), "#c586c0".to_string()); // Purple
+        theme.insert("html_tag".to_string(), "#ce9178".to_string());          // Orange
+        theme.insert("html_content".to_string(), "#d4d4d4".to_string());      // Light gray
+        theme.insert("comment".to_string(), "#6a9955".to_string());           // Green
+        
+        Ok(theme)
+    }
+    
+    /// Get real-time diagnostics for error squiggles
+    #[cfg(feature = "lsp")]
+    pub fn get_diagnostics_for_editor(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<Vec<Diagnostic>> {
+        Ok(self.collect_diagnostics(template, Some(context)))
+    }
+    
+    /// Directive structure and filter diagnostics; unknown variables are only
+    /// reported when a context is given
+    #[cfg(feature = "lsp")]
+    fn collect_diagnostics(&self, template: &str, context: Option<&TemplateContext>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        
+        // Check for unclosed directives
+        let mut directive_stack = Vec::new();
+        let mut current_pos = 0;
+        // Names of `{{set}}` tags and `{{capture}}` blocks, known to the tags that follow them
+        let mut assigned = HashSet::new();
+        
+        while let Some(start) = template[current_pos..].find("{{") {
+            let abs_start = current_pos + start;
+            if let Some(end) = template[abs_start..].find("}}") {
+                let directive_content = &split_trim_markers(&template[abs_start + 2..abs_start + end]).1.trim();
+                let (line, column) = self.calculate_line_column(template, abs_start);
+                
+                let opened = LSP_BLOCKS.into_iter().find(|keyword| {
+                    directive_content.strip_prefix(keyword).is_some_and(|rest| rest.starts_with(' '))
+                });
+                let closed = directive_content.strip_prefix('/')
+                    .and_then(|closing| LSP_BLOCKS.into_iter().find(|keyword| closing.trim() == *keyword));
+                if let Some(keyword) = opened {
+                    directive_stack.push((keyword, line, column));
+                    if keyword == "capture" {
+                        assigned.insert(directive_content["capture".len()..].trim());
+                    }
+                } else if let Some(keyword) = closed {
+                    if let Some((directive_type, _, _)) = directive_stack.pop() {
+                        if directive_type != keyword {
+                            diagnostics.push(Diagnostic::new(
+                                "Mismatched closing directive",
+                                "error",
+                                line,
+                                column
+                            ));
+                        }
+                    } else {
+                        diagnostics.push(Diagnostic::new(
+                            "Unexpected closing directive",
+                            "error",
+                            line,
+                            column
+                        ));
+                    }
+                } else if else_condition(directive_content).is_some() {
+                    if !directive_stack.last().is_some_and(|(directive_type, _, _)| *directive_type == "if") {
+                        diagnostics.push(Diagnostic::new(
+                            "Unexpected branch outside an {{if}} block",
+                            "error",
+                            line,
+                            column
+                        ));
+                    }
+                } else if !directive_content.starts_with("/") && !directive_content.starts_with("!") && !directive_content.starts_with("#--")
+                    && !matches!(*directive_content, "no_provenance" | "flush" | "uid")
+                    && !directive_content.starts_with("uid ")
+                {
+                    // An assignment reads its value expression
+                    let (assigned_name, expression) = match directive_content.strip_prefix("set ").and_then(split_assignment) {
+                        Some((name, value)) => (Some(name), value),
+                        None => (None, *directive_content),
+                    };
+                    
+                    // Check for unknown variables
+                    let parts: Vec<&str> = expression.split('|').collect();
+                    let var_name = parts[0].trim();
+                    let unknown = context.is_some_and(|context| !context.contains(var_name))
+                        && !self.is_known_helper_call(var_name)
+                        && !assigned.contains(var_name.split('.').next().unwrap_or(var_name))
+                        && (assigned_name.is_none() || is_variable_path(var_name));
+                    if !var_name.is_empty() && unknown {
+                        diagnostics.push(Diagnostic::new(
+                            &format!("Unknown variable: {}", var_name),
+                            "warning",
+                            line,
+                            column
+                        ));
+                    }
+                    
+                    // Check for unknown filters
+                    for filter_part in parts.iter().skip(1) {
+                        let filter_name = filter_part.split(':').next().unwrap_or(filter_part).trim();
+                        if !self.is_known_filter(filter_name) {
+                            diagnostics.push(Diagnostic::new(
+                                &format!("Unknown filter: {}", filter_name),
+                                "error",
+                                line,
+                                column
+                            ));
+                        }
+                    }
+                    assigned.extend(assigned_name);
+                }
+                
+                current_pos = abs_start + end + 2;
+            } else {
+                break;
+            }
+        }
+        
+        // Check macro calls against their definitions
+        let severity = if self.strict_mode { "error" } else { "warning" };
+        #[cfg(feature = "template-macros")]
+        for (offset, message) in self.macro_call_problems(template) {
+            let (line, column) = self.calculate_line_column(template, offset);
+            diagnostics.push(Diagnostic::new(&message, severity, line, column).with_code("macro-call"));
+        }
+        
+        // Check literal filter arguments against the filter signatures
+        for (offset, message) in filter_argument_problems(template) {
+            let (line, column) = self.calculate_line_column(template, offset);
+            diagnostics.push(Diagnostic::new(&message, severity, line, column).with_code("filter-arguments"));
+        }
+        
+        // Check for unclosed directives
+        for (directive_type, line, column) in directive_stack {
+            diagnostics.push(Diagnostic::new(
+                &format!("Unclosed {} directive", directive_type),
+                "error",
+                line,
+                column
+            ));
+        }
+        
+        diagnostics
+    }
+    
+    /// Get hover information at a specific position
+    #[cfg(feature = "lsp")]
+    pub fn get_hover_info_at_position(&mut self, template: &str, position: usize, context: &TemplateContext) -> TemplateResult<HoverInfo> {
+        let token = self.get_full_token_at_position(template, position);
+        
+        if let Some(value) = context.get(&token) {
+            let var_type = match value {
+                TemplateValue::String(_) => "String",
+                TemplateValue::Number(_) => "Number",
+                TemplateValue::Float(_) => "Float",
+                TemplateValue::Bool(_) => "Boolean",
+                TemplateValue::Array(_) => "Array",
+                TemplateValue::Object(_) => "Object",
+                TemplateValue::Null => "Null",
+            };
+            let current_value = self.surfaced_value(&token, value, |value| match value {
+                TemplateValue::String(s) => s.clone(),
+                TemplateValue::Number(n) => n.to_string(),
+                TemplateValue::Float(f) => f.to_string(),
+                TemplateValue::Bool(b) => b.to_string(),
+                TemplateValue::Array(arr) => format!("[{} items]", arr.len()),
+                TemplateValue::Object(obj) => format!("{{{}  keys}}", obj.len()),
+                TemplateValue::Null => "null".to_string(),
+            });
+            
+            Ok(HoverInfo {
+                variable_name: token.clone(),
+                variable_type: var_type.to_string(),
+                current_value,
+                description: format!("Template variable of type {}", var_type),
+            })
+        } else {
+            Err(TemplateError::Runtime(format!("No information available for '{}'", token)))
+        }
+    }
+    
+    /// Get the full token at position (for hover information)
+    #[cfg(feature = "lsp")]
+    fn get_full_token_at_position(&self, template: &str, position: usize) -> String {
+        let mut current_pos = 0;
+        
+        while let Some(start) = template[current_pos..].find("{{") {
+            let abs_start = current_pos + start;
+            if let Some(end) = template[abs_start..].find("}}") {
+                let abs_end = abs_start + end + 2;
+                
+                if position >= abs_start && position <= abs_end {
+                    let (_, directive_content, _) = split_trim_markers(&template[abs_start + 2..abs_start + end]);
+                    
+                    // Extract the full variable name, not partial
+                    if directive_content.contains('|') {
+                        let parts: Vec<&str> = directive_content.split('|').collect();
+                        return parts[0].trim().to_string();
+                    } else {
+                        return directive_content.trim().to_string();
+                    }
+                }
+                
+                current_pos = abs_end;
+            } else {
+                break;
+            }
+        }
+        
+        "".to_string()
+    }
+    
+    /// Get definition location at a specific position
+    #[cfg(feature = "lsp")]
+    pub fn get_definition_at_position(&mut self, template: &str, position: usize) -> TemplateResult<DefinitionInfo> {
+        let token = self.get_full_token_at_position(template, position);
+        
+        // Check if it's a macro call by looking for function call syntax
+        if token.contains('(') {
+            let macro_name = token.split('(').next().unwrap_or(&token).trim();
+            
+            // Find macro definition
+            let mut current_pos = 0;
+            while let Some(start) = template[current_pos..].find("{{macro ") {
+                let abs_start = current_pos + start;
+                if let Some(end) = template[abs_start..].find("}}") {
+                    let macro_content = &template[abs_start + 8..abs_start + end].trim();
+                    let defined_macro_name = macro_content.split('(').next().unwrap_or(macro_content);
+                    
+                    if defined_macro_name.trim() == macro_name {
+                        let (line, column) = self.calculate_line_column(template, abs_start);
+                        return Ok(DefinitionInfo {
+                            definition_type: "macro".to_string(),
+                            name: macro_name.to_string(),
+                            line,
+                            column: column + 8, // After "{{macro "
+                            file_path: None,
+                        });
+                    }
+                    
+                    current_pos = abs_start + end + 2;
+                } else {
+                    break;
+                }
+            }
+        }
+        
+        Err(TemplateError::Runtime(format!("No definition found for '{}'", token)))
+    }
+    
+    // Helper methods for LSP functionality
+    
+    /// Calculate line and column from position
+    #[cfg(feature = "lsp")]
+    fn calculate_line_column(&self, content: &str, position: usize) -> (usize, usize) {
+        find_line_column(content, position)
+    }
+    
+    /// Get token at specific position
+    #[cfg(feature = "lsp")]
+    fn get_token_at_position(&self, template: &str, position: usize) -> (String, String) {
+        // Find the template directive containing this position
+        let mut current_pos = 0;
+        
+        while let Some(start) = template[current_pos..].find("{{") {
+            let abs_start = current_pos + start;
+            if let Some(end) = template[abs_start..].find("}}") {
+                let abs_end = abs_start + end + 2;
+                
+                if position >= abs_start && position <= abs_end {
+                    let directive_content = &template[abs_start + 2..abs_start + end];
+                    let rel_pos = position - (abs_start + 2);
+                    
+                    // Determine token type and extract current token at cursor position
+                    if directive_content.contains('|') {
+                        let parts: Vec<&str> = directive_content.split('|').collect();
+                        let mut current_char_pos = 0;
+                        
+                        for (i, part) in parts.iter().enumerate() {
+                            if rel_pos >= current_char_pos && rel_pos <= current_char_pos + part.len() {
+                                if i == 0 {
+                                    // Extract partial variable name up to cursor
+                                    let partial_var = &part.trim()[..std::cmp::min(rel_pos.saturating_sub(current_char_pos), part.trim().len())];
+                                    return (partial_var.to_string(), "variable".to_string());
+                                } else {
+                                    // Extract partial filter name up to cursor  
+                                    let filter_start = current_char_pos;
+                                    let partial_filter = &part.trim()[..std::cmp::min(rel_pos - filter_start, part.trim().len())];
+                                    return (partial_filter.to_string(), "filter".to_string());
+                                }
+                            }
+                            current_char_pos += part.len() + 1; // +1 for the '|' separator
+                        }
+                    } else {
+                        // Check if it's a potential directive (single words that could be directives)
+                        let partial_content = &directive_content[..std::cmp::min(rel_pos, directive_content.len())].trim();
+                        
+                        // If the partial content looks like it could be a directive
+                        let directive_keywords = ["if", "elseif", "else", "unless", "ifchanged", "for", "include", "macro"];
+                        let is_potential_directive = directive_keywords.iter().any(|&kw| kw.starts_with(partial_content) || partial_content.is_empty());
+                        
+                        if is_potential_directive && !partial_content.contains(' ') {
+                            return (partial_content.to_string(), "directive".to_string());
+                        } else {
+                            return (partial_content.to_string(), "variable".to_string());
+                        }
+                    }
+                }
+                
+                current_pos = abs_end;
+            } else {
+                break;
+            }
+        }
+        
+        ("".to_string(), "unknown".to_string())
+    }
+    
+    /// Check if a filter is known/built-in
+    #[cfg(feature = "lsp")]
+    fn is_known_filter(&self, filter_name: &str) -> bool {
+        BUILTIN_FILTERS.contains(&filter_name)
+            || self.custom_filters.contains_key(filter_name)
+            || self.pipelines.contains_key(filter_name)
+            || self.project_config.as_ref().is_some_and(|config| config.filter(filter_name).is_some())
+    }
+    
+    /// Check if `directive` calls a registered, built-in or project helper
+    #[cfg(feature = "lsp")]
+    fn is_known_helper_call(&self, directive: &str) -> bool {
+        let Some((name, _)) = directive.split_once('(') else { return false };
+        let name = name.trim();
+        self.helpers.contains_key(name)
+            || name == "now"
+            || name == "nonce"
+            || name == "uid"
+            || functions::is_function(name)
+            || self.project_config.as_ref().is_some_and(|config| config.helper(name).is_some())
+    }
+    
+    // =============================================================================
+    // v0.6.0 Raw Includes
+    // =============================================================================
+    
+    /// Set how `{{include_raw "file"}}` reads files: the size from which
+    /// [`render_to_writer`](Self::render_to_writer) streams them, the size
+    /// limit, and whether large files enter the template cache.
+    /// 
+    /// A raw include puts the file into the output as is, neither rendered
+    /// nor escaped. Its name is checked like a template name and resolved
+    /// against the template roots.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{RawIncludeOptions, TemplateEngine};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// engine.set_template_cache_capacity(Some(100));
+    /// // Sprites over 1 MiB don't push templates out of the cache
+    /// engine.set_raw_include_options(RawIncludeOptions::default()
+    ///     .passthrough_threshold(1024 * 1024)
+    ///     .cache_large_files(false));
+    /// ```
+    pub fn set_raw_include_options(&mut self, options: RawIncludeOptions) {
+        self.raw_include_options = options;
+    }
+    
+    /// Options given to [`set_raw_include_options`](Self::set_raw_include_options)
+    pub fn raw_include_options(&self) -> &RawIncludeOptions {
+        &self.raw_include_options
+    }
+    
+    /// Render a template to a writer, streaming loops and large raw includes.
+    /// 
+    /// The output is the same as [`render`](Self::render), escaping and
+    /// filters included, but it is written as it is produced: what precedes
+    /// a top-level `{{for}}` loop and then every iteration go to `writer`
+    /// once rendered, so a loop over 100,000 rows never holds more than one
+    /// row in memory. Output that is rewritten as a whole once complete is
+    /// written at the end instead: with
+    /// [`set_minify_output`](Self::set_minify_output), provenance markers,
+    /// HTML validation or `{{required_assets}}`.
+    /// 
+    /// Files of `{{include_raw}}` at least
+    /// [`passthrough_threshold`](RawIncludeOptions::passthrough_threshold)
+    /// bytes long are copied from disk to `writer` in small chunks: they are
+    /// never read into memory nor cached. They are checked against the size
+    /// limit when rendering and again when streamed. If rendering or writing
+    /// fails, part of the output may already be written.
+    /// 
+    /// The writer is flushed at `{{flush}}` and at the other flush points
+    /// set by [`set_stream_flush_on`](Self::set_stream_flush_on) and
+    /// [`set_stream_chunk_size`](Self::set_stream_chunk_size), never at the
+    /// end: a `BufWriter` flushes the rest when dropped.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// use std::fs::File;
+    /// use std::io::BufWriter;
+    /// 
+    /// // index.html: `<body>{{include_raw "icons/sprite.svg"}}...</body>`
+    /// let mut engine = TemplateEngine::new("templates");
+    /// let mut out = BufWriter::new(File::create("dist/index.html")?);
+    /// engine.render_to_writer("index.html", &TemplateContext::new(), &mut out)?;
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn render_to_writer<W: io::Write + ?Sized>(&mut self, template_name: &str, context: &TemplateContext, writer: &mut W) -> TemplateResult<()> {
+        let mut writer = writer;
+        let mut sink: StreamSink<'_> = FlushingWriter::new(&mut writer, self.stream_chunk_size);
+        let was_streaming = std::mem::replace(&mut self.streaming, true);
+        let mut output = String::new();
+        let rendered = self.render_template_into(template_name, None, context, &mut output, Some(&mut sink))
+            .and_then(|_| self.drain_stream(&mut output, &mut sink));
+        self.streaming = was_streaming;
+        rendered
+    }
+    
+    /// Render a template string to a writer, like
+    /// [`render_to_writer`](Self::render_to_writer)
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, TemplateContext, TemplateValue};
+    /// 
+    /// let mut engine = TemplateEngine::new(".");
+    /// let mut context = TemplateContext::new();
+    /// context.set("rows", TemplateValue::Array((1..=3).map(TemplateValue::Number).collect()));
+    /// let mut out = Vec::new();
+    /// engine.render_string_to_writer("{{for row in rows}}<td>{{row}}</td>{{/for}}", &context, &mut out)?;
+    /// assert_eq!(out, b"<td>1</td><td>2</td><td>3</td>");
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn render_string_to_writer<W: io::Write + ?Sized>(&mut self, template: &str, context: &TemplateContext, writer: &mut W) -> TemplateResult<()> {
+        let mut writer = writer;
+        let mut sink: StreamSink<'_> = FlushingWriter::new(&mut writer, self.stream_chunk_size);
+        let was_streaming = std::mem::replace(&mut self.streaming, true);
+        let mut output = String::new();
+        let rendered = self.render_string_template_into(template, context, &mut output, Some(&mut sink))
+            .and_then(|_| self.drain_stream(&mut output, &mut sink));
+        self.streaming = was_streaming;
+        rendered
+    }
+    
+    /// Whether the output of `template` can go to the writer before the
+    /// render ends: minifying, provenance markers, HTML validation and
+    /// `{{required_assets}}` rewrite the whole output once it is complete
+    fn can_stream(&self, template: &str) -> bool {
+        #[cfg(feature = "lsp")]
+        if self.validate_html {
+            return false;
+        }
+        !self.minify_output && self.provenance.is_none() && !template.contains("{{required_assets")
+    }
+    
+    /// Write `out` to the sink and clear it: small raw includes are put in
+    /// place, large ones streamed from disk, and flush markers flush
+    fn drain_stream(&mut self, out: &mut String, sink: &mut StreamSink<'_>) -> TemplateResult<()> {
+        self.put_back_rendered_parts(out, 0);
+        self.resolve_raw_includes(out)?;
+        let mut pos = 0;
+        for (range, kind, name) in deferred_markers(out, self.marker_key, &['=', '!', '+']) {
+            sink.write_all(&out.as_bytes()[pos..range.start])?;
+            pos = range.end;
+            match kind {
+                '!' => sink.flush_point()?,
+                '=' => {
+                    let path = self.resolve_template_path(name, None)?;
+                    self.raw_include_options.stream(name, &path, sink)?;
+                }
+                // Nothing lists the needed assets once the output is gone
+                _ if self.strict_mode && !self.assets.contains_key(name) => {
+                    return Err(TemplateError::Template(format!("Asset '{}' is not registered", name)));
+                }
+                _ => {}
+            }
+        }
+        sink.write_all(&out.as_bytes()[pos..])?;
+        out.clear();
+        Ok(())
+    }
+    
+    /// Flush the writer of [`render_to_writer`](Self::render_to_writer)
+    /// every time `bytes` bytes are written without a flush, or `None` (the
+    /// default) to flush at flush points only.
+    /// 
+    /// Chunks end at the limit even within a streamed raw include, whatever
+    /// [`set_stream_flush_on`](Self::set_stream_flush_on) says.
+    pub fn set_stream_chunk_size(&mut self, bytes: Option<usize>) {
+        self.stream_chunk_size = bytes;
+    }
+    
+    /// Set where [`render_to_writer`](Self::render_to_writer) flushes the
+    /// writer: at `{{flush}}` ([`FlushOn::Directive`], the default), also
+    /// after every loop iteration, or never.
+    /// 
+    /// Flushing sends what was written so far to the client, so a browser
+    /// starts loading stylesheets while the page is still transferred.
+    /// Other renders ignore `{{flush}}`.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{FlushOn, TemplateEngine, TemplateContext};
+    /// use std::io::Write;
+    /// 
+    /// // feed.html: `<head>...</head>{{flush}}{{for card in cards}}...{{/for}}`
+    /// let mut engine = TemplateEngine::new("templates");
+    /// engine.set_stream_flush_on(FlushOn::LoopIteration);
+    /// engine.set_stream_chunk_size(Some(16 * 1024));
+    /// let mut stdout = std::io::stdout().lock();
+    /// engine.render_to_writer("feed.html", &TemplateContext::new(), &mut stdout)?;
+    /// stdout.flush()?;
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn set_stream_flush_on(&mut self, flush_on: FlushOn) {
+        self.stream_flush_on = flush_on;
+    }
+    
+    /// Let the async `render_stream` render at most `chunks` chunks (16 by
+    /// default, at least 1) that its consumer has not taken yet; the render
+    /// waits until the consumer catches up.
+    /// 
+    /// A slow client then holds back the render instead of the whole page
+    /// piling up in memory.
+    pub fn set_stream_buffer_size(&mut self, chunks: usize) {
+        self.stream_buffer_size = chunks.max(1);
+    }
+    
+    /// Chunk count given to [`set_stream_buffer_size`](Self::set_stream_buffer_size)
+    pub fn stream_buffer_size(&self) -> usize {
+        self.stream_buffer_size
+    }
+    
+    /// Chunk size given to [`set_stream_chunk_size`](Self::set_stream_chunk_size)
+    pub fn stream_chunk_size(&self) -> Option<usize> {
+        self.stream_chunk_size
+    }
+    
+    /// Flush points set by [`set_stream_flush_on`](Self::set_stream_flush_on)
+    pub fn stream_flush_on(&self) -> FlushOn {
+        self.stream_flush_on
+    }
+    
+    /// Replace the `{{include_raw}}` markers of `buf` with the files they
+    /// name; during `render_to_writer` large files are left to stream
+    fn resolve_raw_includes(&mut self, buf: &mut String) -> TemplateResult<()> {
+        if !buf.contains(ASSET_MARKER_OPEN) {
+            return Ok(());
+        }
+        let markers: Vec<(std::ops::Range<usize>, String)> = deferred_markers(buf, self.marker_key, &['='])
+            .into_iter()
+            .map(|(range, _, name)| (range, name.to_string()))
+            .collect();
+        if markers.is_empty() {
+            return Ok(());
+        }
+        
+        // A file included in a loop is read once
+        let mut sources: HashMap<String, Option<Arc<str>>> = HashMap::new();
+        let mut output = String::with_capacity(buf.len());
+        let mut pos = 0;
+        for (range, name) in markers {
+            output.push_str(&buf[pos..range.start]);
+            if !sources.contains_key(&name) {
+                let source = self.load_raw_include(&name)?;
+                sources.insert(name.clone(), source);
+            }
+            match &sources[&name] {
+                Some(source) => output.push_str(source),
+                None => output.push_str(&buf[range.clone()]),
+            }
+            pos = range.end;
+        }
+        output.push_str(&buf[pos..]);
+        
+        buf.clear();
+        buf.push_str(&output);
+        Ok(())
+    }
+    
+    /// Source of a raw include, read through the template cache; `None` when
+    /// `render_to_writer` streams it
+    fn load_raw_include(&mut self, name: &str) -> TemplateResult<Option<Arc<str>>> {
+        if self.hot_reload_enabled && !self.offline && self.cache.contains_key(name) {
+            let path = self.resolve_template_path(name, None)?;
+            if self.resolved_paths.get(name) != Some(&path) || file_stamp(&path).as_ref() != self.file_mtimes.get(name) {
+                self.cache.remove(name);
+            }
+        }
+        if let Some(cached) = self.cache.get(name) {
+            if !(self.streaming && self.raw_include_options.is_large(cached.len() as u64)) {
+                return Ok(Some(Arc::clone(cached)));
+            }
+        }
+        if self.offline {
+            return Err(TemplateError::OfflineMiss { name: name.to_string() });
+        }
+        
+        self.validate_template_path(name)?;
+        let path = self.resolve_template_path(name, None)?;
+        let stamp = file_stamp(&path);
+        let size = fs::metadata(&path)
+            .map_err(|e| TemplateError::Template(format!("Failed to read raw include '{}': {}", name, e)))?
+            .len();
+        self.raw_include_options.check_size(name, size)?;
+        let large = self.raw_include_options.is_large(size);
+        if large && self.streaming {
+            return Ok(None);
+        }
+        
+        let content: Arc<str> = fs::read_to_string(&path)
+            .map_err(|e| TemplateError::Template(format!("Failed to read raw include '{}': {}", name, e)))?
+            .into();
+        self.raw_include_options.check_size(name, content.len() as u64)?;
+        if !large || self.raw_include_options.cache_large_files {
+            if let Some(stamp) = stamp {
+                self.file_mtimes.insert(name.to_string(), stamp);
+            }
+            self.resolved_paths.insert(name.to_string(), path);
+            self.cache_template(name, &content);
+        }
+        Ok(Some(content))
+    }
+    
+    // =============================================================================
+    // v0.6.0 Provenance Markers
+    // =============================================================================
+    
+    /// Start the output of named renders with a comment saying which
+    /// template generated it, for generated files committed to a repository.
+    /// 
+    /// The comment syntax comes from the template's extension (`<!-- -->`
+    /// for HTML and XML, `#` for YAML and config files, `//` for JavaScript,
+    /// see [`ProvenanceOptions::default`]); templates with other extensions
+    /// and [`render_string`](Self::render_string) get no marker. The marker
+    /// text names the template, the engine version and the render time,
+    /// read from the engine clock so [deterministic](Self::set_deterministic)
+    /// builds stay reproducible. It goes after an XML declaration or a `#!`
+    /// shebang line, which must come first.
+    /// 
+    /// A template opts out with `{{no_provenance}}` in its source or one of
+    /// its layouts, or with `provenance=off` in the [manifest](Self::render_public).
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{CommentStyle, ProvenanceOptions, TemplateEngine, TemplateContext};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// engine.set_provenance(ProvenanceOptions::default()
+    ///     .marker("Generated from templates/{{template}}, do not edit")
+    ///     .comment_style("nginx", CommentStyle::line("#")));
+    /// 
+    /// // <!-- Generated from templates/index.html, do not edit -->
+    /// let page = engine.render("index.html", &TemplateContext::new())?;
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn set_provenance(&mut self, options: ProvenanceOptions) {
+        self.provenance = Some(options);
+    }
+    
+    /// Stop adding the provenance marker
+    pub fn clear_provenance(&mut self) {
+        self.provenance = None;
+    }
+    
+    /// Options given to [`set_provenance`](Self::set_provenance)
+    pub fn provenance(&self) -> Option<&ProvenanceOptions> {
+        self.provenance.as_ref()
+    }
+    
+    // =============================================================================
+    // v0.6.0 Translation Hot Swap
+    // =============================================================================
+    
+    /// Replace the translations of `locale` in one step and return their
+    /// new version.
+    /// 
+    /// Renders that start afterwards use the new table; renders already
+    /// running keep the translations of every locale as they were when they
+    /// started, so no render mixes two versions. Clones of the engine, such
+    /// as the workers of a [`SharedTemplateEngine`](crate::SharedTemplateEngine),
+    /// share the translations and see the swap too. The last few versions
+    /// of each locale are kept for [`rollback_translations`](Self::rollback_translations).
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// use std::collections::HashMap;
+    /// 
+    /// let mut engine = TemplateEngine::new(".");
+    /// engine.set_locale("fr");
+    /// let good = engine.swap_translations("fr", HashMap::from([("hello".to_string(), "Bonjour".to_string())]));
+    /// engine.swap_translations("fr", HashMap::from([("hello".to_string(), "Bonjuor".to_string())]));
+    /// 
+    /// engine.rollback_translations("fr", good)?;
+    /// assert_eq!(engine.translation_version("fr"), Some(good));
+    /// assert_eq!(engine.render_string("{{t \"hello\"}}", &TemplateContext::new())?, "Bonjour");
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    #[cfg(feature = "i18n")]
+    pub fn swap_translations(&self, locale: &str, translations: HashMap<String, String>) -> TranslationVersion {
+        self.translation_store.swap(locale, translations)
+    }
+    
+    /// Make a version of `locale` returned by [`swap_translations`](Self::swap_translations)
+    /// current again, for the renders that start afterwards.
+    /// 
+    /// Fails with [`TemplateError::Template`] when the version is no longer
+    /// kept; the current translations are left as they are.
+    #[cfg(feature = "i18n")]
+    pub fn rollback_translations(&self, locale: &str, version: TranslationVersion) -> TemplateResult<()> {
+        self.translation_store.rollback(locale, version)
+    }
+    
+    /// Version of `locale` that renders starting now would use; `None` when
+    /// the locale has no translations
+    #[cfg(feature = "i18n")]
+    pub fn translation_version(&self, locale: &str) -> Option<TranslationVersion> {
+        self.translation_store.snapshot().version(locale)
+    }
+    
+    // =============================================================================
+    // v0.6.0 Project Configuration
+    // =============================================================================
+    
+    /// Apply a `runic.toml` project configuration (see [`ProjectConfig`]).
+    /// 
+    /// The template directory and roots are resolved against the directory
+    /// of the file and strict mode is set when the file says so. The filter
+    /// and helper names are kept for the editor tooling: `parse_for_lsp`,
+    /// `get_diagnostics_for_editor`, `get_completions_at_position` and
+    /// `check_syntax` accept them like registered ones. Rendering still
+    /// needs the real filters and helpers, registered in code.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::TemplateEngine;
+    /// 
+    /// let mut engine = TemplateEngine::new(".");
+    /// engine.load_project_config("runic.toml")?;
+    /// assert!(engine.check_syntax("{{price|money}}").is_ok());
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn load_project_config(&mut self, path: impl AsRef<Path>) -> TemplateResult<()> {
+        let path = path.as_ref();
+        let config = ProjectConfig::parse(&fs::read_to_string(path)?)?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        
+        if let Some(dir) = &config.template_dir {
+            self.template_dir = base.join(dir).to_string_lossy().into_owned();
+            self.forget_template_sources();
+            self.resolved_paths.clear();
+            self.manifest = None;
+        }
+        for (name, dir) in &config.roots {
+            self.add_template_root(TemplateRoot::new(name, base.join(dir)));
+        }
+        match config.strict {
+            Some(true) => self.enable_strict_mode(),
+            Some(false) => self.disable_strict_mode(),
+            None => {}
+        }
+        self.project_config = Some(config);
+        Ok(())
+    }
+    
+    /// Project configuration applied by [`load_project_config`](Self::load_project_config)
+    pub fn project_config(&self) -> Option<&ProjectConfig> {
+        self.project_config.as_ref()
+    }
+    
+    /// Write a `runic.toml` describing this engine to `path`, for projects
+    /// that configure the engine in code, and return it.
+    /// 
+    /// The file lists the template directory and roots relative to `path`,
+    /// the registered filters, pipelines and helpers, strict mode and the
+    /// features this build enables. Signatures come from a configuration
+    /// loaded earlier; its names that aren't registered are kept.
+    pub fn generate_project_config(&self, path: impl AsRef<Path>) -> TemplateResult<ProjectConfig> {
+        let path = path.as_ref();
+        let base = path.parent().unwrap_or(Path::new(""));
+        let relative = |dir: &Path| dir.strip_prefix(base).unwrap_or(dir).to_path_buf();
+        let loaded = self.project_config.as_ref();
+        
+        let symbols = |names: Vec<&String>, listed: Option<&Vec<ProjectSymbol>>| {
+            let mut symbols: BTreeMap<&str, ProjectSymbol> = listed.into_iter().flatten()
+                .map(|symbol| (symbol.name.as_str(), symbol.clone()))
+                .collect();
+            for name in names {
+                symbols.entry(name).or_insert_with(|| ProjectSymbol::new(name));
+            }
+            symbols.into_values().collect()
+        };
+        
+        let features = [
+            ("inheritance", cfg!(feature = "inheritance")),
+            ("i18n", cfg!(feature = "i18n")),
+            ("template-macros", cfg!(feature = "template-macros")),
+            ("lsp", cfg!(feature = "lsp")),
+            ("debug-tools", cfg!(feature = "debug-tools")),
+            ("suggestions", cfg!(feature = "suggestions")),
+            ("async", cfg!(feature = "async")),
+            ("wasm", cfg!(feature = "wasm")),
+            ("cli", cfg!(feature = "cli")),
+        ];
+        let config = ProjectConfig {
+            template_dir: Some(relative(Path::new(&self.template_dir))),
+            roots: self.template_roots.iter()
+                .map(|(root, _)| (root.name().to_string(), relative(root.dir())))
+                .collect(),
+            filters: symbols(self.custom_filters.keys().chain(self.pipelines.keys()).collect(), loaded.map(|config| &config.filters)),
+            helpers: symbols(self.helpers.keys().collect(), loaded.map(|config| &config.helpers)),
+            strict: Some(self.strict_mode),
+            features: features.iter().filter(|(_, enabled)| *enabled).map(|(name, _)| name.to_string()).collect(),
+            ..ProjectConfig::default()
+        };
+        
+        fs::write(path, config.to_string())?;
+        Ok(config)
+    }
+    
+    // =============================================================================
+    // v0.6.0 Template Backtraces
+    // =============================================================================
+    
+    /// Locate the error of a failed render of `template_name` by walking the
+    /// template again directive by directive, as [`profile_render`](Self::profile_render)
+    /// does, and attach the template call stack the walk failed in. See
+    /// [`TemplateError::template_backtrace`]. Errors the walk doesn't
+    /// reproduce, or raised outside any include, layout, loop, block,
+    /// conditional or macro call, are returned as they are, and so are the
+    /// errors callers branch on: security violations, cancellation and
+    /// offline misses. I/O errors, such as a streaming writer whose reader
+    /// went away, aren't walked either.
+    fn with_template_backtrace(&mut self, template_name: &str, context: &TemplateContext, error: TemplateError) -> TemplateError {
+        if !is_rescuable(&error) || matches!(error, TemplateError::Io(_) | TemplateError::OfflineMiss { .. } | TemplateError::Backtraced { .. }) {
+            return error;
+        }
+        let located = self.keeping_macros(|engine| engine.walk_to_error(template_name, context));
+        let backtrace = match located {
+            Err(located) if located.kind() == error.kind() => located.template_backtrace().to_vec(),
+            _ => return error,
+        };
+        let nested = backtrace.iter().any(|frame| !matches!(frame.kind, FrameKind::Template | FrameKind::Output));
+        if !nested {
+            return error;
+        }
+        TemplateError::Backtraced { error: Box::new(error), backtrace }
+    }
+    
+    /// Walk a template and its layout until a directive fails
+    fn walk_to_error(&mut self, template_name: &str, context: &TemplateContext) -> TemplateResult<()> {
+        let root = TemplateFrame::new(FrameKind::Template, template_name, "", 1);
+        let source = self.load_template_arc(template_name).map_err(|error| error.within(root.clone()))?;
+        let walked = match source.find("{{extends ") {
+            Some(start) => {
+                let end = source[start..].find("}}").map_or(source.len(), |end| start + end);
+                let directive = source[start + 2..end].trim();
+                let (line, _) = find_line_column(&source, start);
+                let walked = self.prepare_template_source(template_name, Some(context))
+                    .and_then(|merged| self.profile_range(&merged, 0..merged.len(), template_name, context, None, &mut Vec::new()));
+                #[cfg(feature = "inheritance")]
+                let walked = walked.map_err(|error| self.locate_in_layouts(template_name, error));
+                walked.map_err(|error| error.within(TemplateFrame::new(FrameKind::Layout, template_name, directive, line)))
+            }
+            None => self.profile_range(&source, 0..source.len(), template_name, context, None, &mut Vec::new()),
+        };
+        walked.map_err(|error| error.within(root))
+    }
+    
+    /// Frames of a merged layout are attributed to the rendered template;
+    /// point them at the template of its layout chain that holds the directive
+    #[cfg(feature = "inheritance")]
+    fn locate_in_layouts(&mut self, template_name: &str, error: TemplateError) -> TemplateError {
+        let TemplateError::Backtraced { error, mut backtrace } = error else {
+            return error;
+        };
+        let mut chain: Vec<(String, Arc<str>)> = Vec::new();
+        let mut current = Some(template_name.to_string());
+        while let Some(name) = current.take() {
+            if chain.iter().any(|(seen, _)| *seen == name) {
+                break;
+            }
+            current = self.layout_processor.templates.get(&name).and_then(|layout| layout.extends.clone());
+            if let Ok(source) = self.load_template_arc(&name) {
+                chain.push((name, source));
+            }
+        }
+        
+        for frame in backtrace.iter_mut().filter(|frame| frame.template == template_name) {
+            let found = chain.iter().find_map(|(name, source)| {
+                source.find(frame.directive.as_str()).map(|offset| (name, find_line_column(source, offset).0))
+            });
+            if let Some((name, line)) = found {
+                frame.template = name.clone();
+                frame.line = line;
+            }
+        }
+        TemplateError::Backtraced { error, backtrace }
+    }
+    
+    // =============================================================================
+    // v0.6.0 Translation Modes
+    // =============================================================================
+    
+    /// Choose how translations render, to catch hardcoded strings and
+    /// layouts that break with longer text before translators finish.
+    /// 
+    /// The mode applies to `{{t}}`, the `translate` filter, `{{plural}}`
+    /// forms and the locale defaults of `yesno`, `BoolFormat::YesNo` and the
+    /// dashboard filter units. [`TranslationMode::PseudoLocalize`](crate::TranslationMode::PseudoLocalize)
+    /// keeps `{name}` and `{{name}}` placeholders, so interpolated values
+    /// are shown as they are.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, TemplateContext, TranslationMode};
+    /// use std::collections::HashMap;
+    /// 
+    /// let mut engine = TemplateEngine::new(".");
+    /// engine.set_translations("en", HashMap::from([("hello".to_string(), "Hello {{name}}".to_string())]));
+    /// engine.set_locale("en");
+    /// engine.set_translation_mode(TranslationMode::PseudoLocalize);
+    /// let mut context = TemplateContext::new();
+    /// context.set_string("name", "Ada");
+    /// 
+    /// let output = engine.render_string(r#"{{t "hello"}} {{t "bye"}}"#, &context)?;
+    /// assert_eq!(output, "⟪Ĥélló Ada~~⟫ ⟦bye⟧");
+    /// assert_eq!(engine.missing_translations()[0].key, "bye");
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    #[cfg(feature = "i18n")]
+    pub fn set_translation_mode(&mut self, mode: TranslationMode) {
+        self.translation_mode = mode;
+    }
+    
+    /// Get the engine-wide translation mode
+    #[cfg(feature = "i18n")]
+    pub fn get_translation_mode(&self) -> TranslationMode {
+        self.translation_mode
+    }
+    
+    /// Render a template with `mode` instead of the engine-wide translation mode
+    #[cfg(feature = "i18n")]
+    pub fn render_with_translation_mode(&mut self, template_name: &str, context: &TemplateContext, mode: TranslationMode) -> TemplateResult<String> {
+        let previous = std::mem::replace(&mut self.translation_mode, mode);
+        let result = self.render(template_name, context);
+        self.translation_mode = previous;
+        result
+    }
+    
+    /// Like [`render_with_translation_mode`](Self::render_with_translation_mode), for a template string
+    #[cfg(feature = "i18n")]
+    pub fn render_string_with_translation_mode(&mut self, template: &str, context: &TemplateContext, mode: TranslationMode) -> TemplateResult<String> {
+        let previous = std::mem::replace(&mut self.translation_mode, mode);
+        let result = self.render_string(template, context);
+        self.translation_mode = previous;
+        result
+    }
+    
+    /// Translation keys the last render looked up without finding them in
+    /// the current locale, in the order first met, whatever the mode
+    #[cfg(feature = "i18n")]
+    pub fn missing_translations(&self) -> Vec<MissingTranslation> {
+        self.missing_translations.lock().map(|missing| missing.clone()).unwrap_or_default()
+    }
+    
+    // =============================================================================
+    // v0.6.0 Render Profiling
+    // =============================================================================
+    
+    /// Render a template and time it directive by directive.
+    /// 
+    /// The [`ProfileResult`](crate::ProfileResult) holds the output and a tree
+    /// of spans: the layout merge, includes, blocks, loops (with their
+    /// iteration count), conditions, output tags and each step of their
+    /// filter chains, helper and macro calls and translations. Every span
+    /// has its total and self time. The spans come from a second pass that
+    /// renders the template piece by piece, so their sum is close to, not
+    /// the same as, the time of a plain render. Includes written in a layout
+    /// appear under the page, as in [`render_with_trace`](Self::render_with_trace).
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// let context = TemplateContext::new();
+    /// let profile = engine.profile_render("dashboard.html", &context)?;
+    /// println!("{}", profile.summary(10));
+    /// std::fs::write("dashboard.speedscope.json", profile.to_speedscope_json())?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn profile_render(&mut self, template_name: &str, context: &TemplateContext) -> TemplateResult<ProfileResult> {
+        self.keeping_macros(|engine| {
+            let output = engine.render(template_name, context)?;
+            
+            let started = Instant::now();
+            let mut root = ProfileSpan::new(SpanKind::Template, template_name, template_name, 1);
+            let mut source = engine.load_template_arc(template_name)?;
+            if let Some(start) = source.find("{{extends ") {
+                let parent = source[start + 10..].split("}}").next().unwrap_or_default();
+                let parent = parent.trim().trim_matches('"').trim_matches('\'').to_string();
+                let (line, _) = find_line_column(&source, start);
+                let layout_started = Instant::now();
+                source = engine.prepare_template_source(template_name, Some(context))?;
+                root.children.push(ProfileSpan::new(SpanKind::Layout, &parent, template_name, line).finish(elapsed_nanos(layout_started)));
+            }
+            engine.profile_range(&source, 0..source.len(), template_name, context, None, &mut root.children)?;
+            
+            Ok(ProfileResult { output, root: root.finish(elapsed_nanos(started)) })
+        })
+    }
+    
+    /// Render `source[range]` of `template` piece by piece, adding a timed
+    /// span per directive to `spans`; mirrors [`Self::trace_range`]
+    fn profile_range(
+        &mut self,
+        source: &str,
+        range: std::ops::Range<usize>,
+        template: &str,
+        context: &TemplateContext,
+        previous: Option<&TemplateContext>,
+        spans: &mut Vec<ProfileSpan>,
+    ) -> TemplateResult<()> {
+        let end = range.end;
+        let mut pos = range.start;
+        
+        while let Some(relative_start) = source[pos..end].find("{{") {
+            let tag_start = pos + relative_start;
+            let tag_end = if source[tag_start..end].starts_with("{{#--") {
+                source[tag_start..end].find("--}}").map(|close| tag_start + close + 4)
+            } else {
+                source[tag_start..end].find("}}").map(|close| tag_start + close + 2)
+            };
+            let Some(tag_end) = tag_end else { break };
+            let directive = source[tag_start + 2..tag_end - 2].trim();
+            let (line, _) = find_line_column(source, tag_start);
+            pos = tag_end;
+            
+            if directive.starts_with('!') || directive.starts_with('/') || directive.starts_with("#--") {
+                continue;
+            }
+            let started = Instant::now();
+            let in_frame = |kind: FrameKind| move |error: TemplateError| error.within(TemplateFrame::new(kind, template, directive, line));
+            
+            if let Some(loop_def) = directive.strip_prefix("for ") {
+                let body_end = tag_end + self.find_matching_for_end(&source[tag_end..end])?;
+                pos = body_end + "{{/for}}".len();
+                let (item_var, array_var) = loop_def.split_once(" in ")
+                    .map(|(item, array)| (item.trim(), array.trim()))
+                    .ok_or_else(|| TemplateError::Parse("Invalid for loop syntax".to_string()))?;
+                let (body, empty) = split_loop_empty(&source[tag_end..body_end]);
+                let mut span = ProfileSpan::new(SpanKind::Loop, loop_def.trim(), template, line);
+                span.count = 0;
+                let array = self.loop_source(array_var, context)?;
+                let items = match array.as_deref() {
+                    Some(TemplateValue::Array(items)) => items.as_slice(),
+                    _ => &[],
+                };
+                // Nothing to iterate: the {{empty}} section renders instead
+                if let Some(empty) = empty.filter(|_| items.is_empty()) {
+                    self.profile_range(source, body_end - empty.len()..body_end, template, context, previous, &mut span.children)?;
+                }
+                let body_end = tag_end + body.len();
+                let shared_context = Arc::new(context.clone());
+                let mut previous_context: Option<TemplateContext> = None;
+                for (index, item) in items.iter().enumerate() {
+                    let mut loop_context = TemplateContext::layered(Arc::clone(&shared_context));
+                    loop_context.set(item_var, item.clone());
+                    let mut iteration = Vec::new();
+                    self.profile_range(source, tag_end..body_end, template, &loop_context, previous_context.as_ref(), &mut iteration)
+                        .map_err(in_frame(FrameKind::Loop { iteration: index + 1 }))?;
+                    merge_spans(&mut span.children, iteration);
+                    span.count += 1;
+                    previous_context = Some(loop_context);
+                }
+                spans.push(span.finish(elapsed_nanos(started)));
+                continue;
+            }
+            if directive == "try" {
+                let boundary = find_error_boundaries(&source[tag_start..end])?.into_iter().next()
+                    .ok_or_else(|| TemplateError::Parse("Missing {{/try}} directive".to_string()))?;
+                pos = tag_start + boundary.end;
+                let mut span = ProfileSpan::new(SpanKind::ErrorBoundary, "", template, line);
+                let body = tag_start + boundary.body.start..tag_start + boundary.body.end;
+                match self.profile_range(source, body, template, context, previous, &mut span.children) {
+                    Ok(()) => {}
+                    Err(error) if !is_rescuable(&error) => return Err(error),
+                    Err(error) => {
+                        // The render already recorded the rescued error
+                        if let Some(rescue) = boundary.rescue {
+                            let rescue_context = rescue_context(context, error.root_cause());
+                            self.profile_range(source, tag_start + rescue.start..tag_start + rescue.end, template, &rescue_context, previous, &mut span.children)?;
+                        }
+                    }
+                }
+                spans.push(span.finish(elapsed_nanos(started)));
+                continue;
+            }
+            if let Some(keyword) = ["if", "unless", "ifchanged", "macro", "block", "capture"].into_iter().find(|keyword| {
+                directive.strip_prefix(keyword).is_some_and(|rest| rest.starts_with(char::is_whitespace))
+            }) {
+                let body_end = find_block_end(&source[tag_end..end], keyword)
+                    .map(|offset| tag_end + offset)
+                    .ok_or_else(|| TemplateError::Parse(format!("Missing {{{{/{}}}}} directive", keyword)))?;
+                pos = body_end + keyword.len() + 5;
+                let argument = directive[keyword.len()..].trim();
+                let (mut span, frame) = match keyword {
+                    "if" => (ProfileSpan::new(SpanKind::Condition, argument, template, line), FrameKind::Condition),
+                    "block" => (ProfileSpan::new(SpanKind::Block, argument, template, line), FrameKind::Block),
+                    // Macro definitions produce no output
+                    "macro" => continue,
+                    "capture" => (ProfileSpan::new(SpanKind::Output, directive, template, line), FrameKind::Output),
+                    _ => (ProfileSpan::new(SpanKind::Condition, directive, template, line), FrameKind::Condition),
+                };
+                let renders = match keyword {
+                    "if" => self.evaluate_condition(argument, context),
+                    "unless" => self.evaluate_condition(argument, context).map(|holds| !holds),
+                    "ifchanged" => self.value_changed(argument, context, previous),
+                    _ => Ok(true),
+                };
+                let holds = renders.map_err(in_frame(frame))?;
+                if let Some(branch) = self.taken_branch(source, tag_end..body_end, keyword, holds, context).map_err(in_frame(frame))? {
+                    self.profile_range(source, branch, template, context, previous, &mut span.children)
+                        .map_err(in_frame(frame))?;
+                }
+                spans.push(span.finish(elapsed_nanos(started)));
+                continue;
+            }
+            if let Some(tag) = directive.strip_prefix("include ") {
+                let tag = tag.trim();
+                let (target, from) = split_include_source(tag);
+                let include_name = if target.starts_with(['"', '\'']) {
+                    target.trim_matches('"').trim_matches('\'').to_string()
+                } else {
+                    self.resolve_dynamic_template_name(target, context).map_err(in_frame(FrameKind::Include))?
+                };
+                let scope = match split_include_scope(tag) {
+                    (_, None, false) => None,
+                    (_, arguments, only) => match self.include_scope(tag, arguments, only, context).map_err(in_frame(FrameKind::Include))? {
+                        Some(scope) => Some(scope),
+                        None => return Err(in_frame(FrameKind::Include)(TemplateError::Template(format!(
+                            "Include {} could not be resolved: a variable of its arguments is not set", target
+                        )))),
+                    },
+                };
+                let mut span = ProfileSpan::new(SpanKind::Include, &include_name, template, line);
+                self.enter_include(&include_name).map_err(in_frame(FrameKind::Include))?;
+                let profiled = self.load_template_from_root(&include_name, from).and_then(|included| {
+                    self.profile_range(&included, 0..included.len(), &include_name, scope.as_ref().unwrap_or(context), previous, &mut span.children)
+                });
+                self.include_chain.pop();
+                profiled.map_err(in_frame(FrameKind::Include))?;
+                spans.push(span.finish(elapsed_nanos(started)));
+                continue;
+            }
+            
+            let span = self.profile_output(&source[tag_start..tag_end], directive, template, line, context)?;
+            spans.push(span.finish(elapsed_nanos(started)));
+        }
+        Ok(())
+    }
+    
+    /// Render one output tag, with a span per filter step of a variable's
+    /// filter chain; the returned span is not finished
+    fn profile_output(&mut self, tag: &str, directive: &str, template: &str, line: usize, context: &TemplateContext) -> TemplateResult<ProfileSpan> {
+        let call_name = directive.split_once('(').map(|(name, _)| name.trim());
+        let kind = if call_name.is_some_and(|name| self.helpers.contains_key(name) || matches!(name, "now" | "nonce" | "uid") || functions::is_function(name)) {
+            SpanKind::Helper
+        } else if call_name.is_some_and(|name| self.is_macro(name)) {
+            SpanKind::Macro
+        } else if directive.starts_with("t ") || directive.starts_with("plural ") {
+            SpanKind::Translation
+        } else {
+            SpanKind::Output
+        };
+        let name = match (kind, call_name) {
+            (SpanKind::Helper | SpanKind::Macro, Some(name)) => name,
+            _ => directive,
+        };
+        let mut span = ProfileSpan::new(kind, name, template, line);
+        let frame = if kind == SpanKind::Macro { FrameKind::Macro } else { FrameKind::Output };
+        let in_frame = |error: TemplateError| error.within(TemplateFrame::new(frame, template, directive, line));
+        
+        let raw = directive.starts_with('&');
+        let expression = directive.trim_start_matches('&').trim();
+        let path = expression.split('|').next().unwrap_or_default().trim();
+        if kind != SpanKind::Output || !expression.contains('|') || !is_variable_path(path) {
+            self.render_string(tag, context).map_err(in_frame)?;
+            return Ok(span);
+        }
+        
+        let mut value = self.resolve_variable_from_context(path, context);
+        for filter_expr in expression.split('|').skip(1) {
+            let filter_expr = filter_expr.trim();
+            let started = Instant::now();
+            let filter_name = filter_expr.split(':').next().unwrap_or_default().trim();
+            let bound = self.bind_filter_arguments(filter_expr, context).map_err(in_frame)?;
+            value = self.apply_value_filter(value, &bound).map_err(in_frame)?;
+            span.children.push(ProfileSpan::new(SpanKind::Filter, filter_name, template, line).finish(elapsed_nanos(started)));
+        }
+        let escape = self.filter_escaping(expression).unwrap_or(!raw && self.autoescape);
+        self.write_value(&value, escape, &mut String::new(), || format!("'{}'", expression)).map_err(in_frame)?;
+        Ok(span)
+    }
+    
+    #[cfg(feature = "template-macros")]
+    fn is_macro(&self, name: &str) -> bool {
+        self.macros.contains_key(name)
+    }
+    
+    #[cfg(not(feature = "template-macros"))]
+    fn is_macro(&self, _name: &str) -> bool {
+        false
+    }
+    
+    // =============================================================================
+    // v0.6.0 Template Coverage
+    // =============================================================================
+    
+    /// Record, from now on, which directives of the rendered templates are
+    /// evaluated and which branches and loop bodies run; `false` stops
+    /// recording and keeps what was recorded for
+    /// [`coverage_report`](Self::coverage_report).
+    /// 
+    /// Enabling it again starts over. Renders of named templates are
+    /// recorded, with the templates they include and extend, and so are
+    /// [`render_compiled`](Self::render_compiled) renders of templates
+    /// compiled while coverage is on; template strings are not. Clones of the
+    /// engine record into the same report. Each recorded render walks the
+    /// template a second time, like [`profile_render`](Self::profile_render);
+    /// with coverage off, renders do no extra work.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// engine.enable_coverage(true);
+    /// engine.render("checkout.html", &TemplateContext::new())?;
+    /// let report = engine.coverage_report();
+    /// println!("{}", report);
+    /// std::fs::write("templates.lcov", report.to_lcov())?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn enable_coverage(&mut self, enabled: bool) {
+        if enabled && !self.coverage_enabled {
+            self.coverage = Arc::new(Mutex::new(CoverageRecorder::default()));
+        }
+        self.coverage_enabled = enabled;
+    }
+    
+    /// Check if coverage is being recorded
+    pub fn is_coverage_enabled(&self) -> bool {
+        self.coverage_enabled
+    }
+    
+    /// Coverage recorded since coverage was last enabled, per template, with
+    /// the spans that never ran; [`CoverageReport::to_lcov`](crate::CoverageReport::to_lcov)
+    /// and [`to_html`](crate::CoverageReport::to_html) export it
+    pub fn coverage_report(&self) -> CoverageReport {
+        self.coverage.lock().unwrap_or_else(PoisonError::into_inner).report()
+    }
+    
+    /// Record the coverage of a successful render of `template_name`, then of
+    /// the templates it includes or extends
+    fn record_coverage(&mut self, template_name: &str, context: &TemplateContext) {
+        let mut pending = vec![(template_name.to_string(), None, context.clone(), 0)];
+        while let Some((name, from, context, depth)) = pending.pop() {
+            let Ok(source) = self.load_template_from_root(&name, from.as_deref()) else { continue };
+            let key = match &from {
+                Some(root) => format!("{} from {}", name, root),
+                None => name,
+            };
+            let mut included = Vec::new();
+            self.cover_template(&key, Some(&source), &context, &mut included);
+            if depth < self.max_nesting_depth {
+                pending.extend(included.into_iter().map(|(name, from, context)| (name, from, context, depth + 1)));
+            }
+        }
+    }
+    
+    /// Walk template `name` (parsed from `source`, or as recorded before)
+    /// with `context` and count the sites that ran; the templates it
+    /// includes or extends are added to `included`
+    fn cover_template(&self, name: &str, source: Option<&str>, context: &TemplateContext, included: &mut Vec<(String, Option<String>, TemplateContext)>) {
+        let tree = {
+            let mut recorder = self.coverage.lock().unwrap_or_else(PoisonError::into_inner);
+            match source {
+                Some(source) => recorder.parse(name, source),
+                None => recorder.parsed(name),
+            }
+        };
+        let Some(tree) = tree else { return };
+        let mut hits = Vec::new();
+        self.cover_nodes(&tree.nodes, &tree.source, context, None, &mut hits, included);
+        self.coverage.lock().unwrap_or_else(PoisonError::into_inner).record(name, &hits);
+    }
+    
+    /// Add the sites of `nodes` that run with `context` to `hits`, as
+    /// `(start, kind)`; evaluation errors count as branches not taken
+    fn cover_nodes(
+        &self,
+        nodes: &[ast::Node],
+        source: &str,
+        context: &TemplateContext,
+        previous: Option<&TemplateContext>,
+        hits: &mut Vec<(usize, CoverageKind)>,
+        included: &mut Vec<(String, Option<String>, TemplateContext)>,
+    ) {
+        // `{{set}}` assignments apply to the nodes after them
+        let mut assigned: Option<TemplateContext> = None;
+        for node in nodes {
+            let current = assigned.as_ref().unwrap_or(context);
+            let start = node.span().start;
+            match node {
+                ast::Node::Text(_) | ast::Node::Comment(_) | ast::Node::MacroDef(_) => continue,
+                _ => hits.push((start, CoverageKind::Directive)),
+            }
+            let body_start = tag_end(source, start);
+            match node {
+                ast::Node::If(node) => {
+                    for branch in &node.branches {
+                        let holds = branch.condition.as_ref()
+                            .map_or(true, |condition| self.evaluate_condition(condition, current).unwrap_or(false));
+                        if holds {
+                            hits.push((tag_end(source, branch.span.start), CoverageKind::Branch));
+                            self.cover_nodes(&branch.body, source, current, previous, hits, included);
+                            break;
+                        }
+                    }
+                }
+                ast::Node::Unless(node) if !self.evaluate_condition(&node.condition, current).unwrap_or(true) => {
+                    hits.push((body_start, CoverageKind::Branch));
+                    self.cover_nodes(&node.body, source, current, previous, hits, included);
+                }
+                ast::Node::IfChanged(node) if self.value_changed(&node.expr, current, previous).unwrap_or(false) => {
+                    hits.push((body_start, CoverageKind::Branch));
+                    self.cover_nodes(&node.body, source, current, previous, hits, included);
+                }
+                ast::Node::For(node) => {
+                    let items = match current.get(&node.expr) {
+                        Some(items) => Some(items.clone()),
+                        None if node.expr.ends_with(')') => self.call_function(&node.expr, current).ok().flatten(),
+                        None => Some(self.resolve_variable_from_context(&node.expr, current)),
+                    };
+                    let items = match items {
+                        Some(TemplateValue::Array(items)) => items,
+                        _ => Vec::new(),
+                    };
+                    if items.is_empty() {
+                        if let Some(empty) = &node.empty {
+                            self.cover_nodes(empty, source, current, previous, hits, included);
+                        }
+                        continue;
+                    }
+                    hits.push((body_start, CoverageKind::LoopBody));
+                    let shared_context = Arc::new(current.clone());
+                    let mut previous_context: Option<TemplateContext> = None;
+                    for item in items {
+                        let mut loop_context = TemplateContext::layered(Arc::clone(&shared_context));
+                        loop_context.set(&node.var, item);
+                        self.cover_nodes(&node.body, source, &loop_context, previous_context.as_ref(), hits, included);
+                        previous_context = Some(loop_context);
+                    }
+                }
+                ast::Node::Include(node) => {
+                    let name = if node.dynamic {
+                        self.resolve_dynamic_template_name(&node.target, current).ok()
+                    } else {
+                        Some(node.target.clone())
+                    };
+                    if let Some(name) = name {
+                        included.push((name, node.from.clone(), current.clone()));
+                    }
+                }
+                ast::Node::Extends(node) => {
+                    let name = if node.dynamic {
+                        Some(filter_value_to_string(&self.resolve_variable_from_context(&node.target, current)))
+                    } else {
+                        Some(node.target.clone())
+                    };
+                    if let Some(name) = name.filter(|name| !name.is_empty()) {
+                        included.push((name, None, current.clone()));
+                    }
+                }
+                ast::Node::Block(node) => self.cover_nodes(&node.body, source, current, previous, hits, included),
+                ast::Node::TranslationScope(node) => self.cover_nodes(&node.body, source, current, previous, hits, included),
+                ast::Node::Try(node) => self.cover_nodes(&node.body, source, current, previous, hits, included),
+                ast::Node::Capture(node) => self.cover_nodes(&node.body, source, current, previous, hits, included),
+                ast::Node::Directive(node) if node.keyword == "set" => {
+                    if let [name, expression] = node.arguments.as_slice() {
+                        if let Ok(value) = self.evaluate_assigned_value(expression, current) {
+                            let mut next = assigned.take().unwrap_or_else(|| context.clone());
+                            next.set(name, value);
+                            assigned = Some(next);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    
+    // =============================================================================
+    // v0.6.0 Layout Selection
+    // =============================================================================
+    
+    /// Render a template with the layout chosen by the caller instead of the
+    /// one its `{{extends}}` names, e.g. a bare layout for embedded views of
+    /// a full-chrome page.
+    /// 
+    /// `Some(layout)` makes the template extend `layout`, whether or not it
+    /// declares a parent; `None` renders its blocks on their own. Only the
+    /// rendered template is affected: the layout keeps its own `{{extends}}`.
+    /// The layout is loaded like any template, so its name is validated, and
+    /// it becomes a dependency of the template for hot reload. Templates can
+    /// also pick their layout from the context with `{{extends layout_name}}`,
+    /// subject to [`allow_dynamic_includes`](Self::allow_dynamic_includes).
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// let context = TemplateContext::new();
+    /// let page = engine.render("article.html", &context)?;
+    /// let embedded = engine.render_with_layout("article.html", &context, Some("bare.html"))?;
+    /// let fragment = engine.render_with_layout("article.html", &context, None)?;
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn render_with_layout(&mut self, template_name: &str, context: &TemplateContext, layout: Option<&str>) -> TemplateResult<String> {
+        self.layout_override = Some(layout.map(str::to_string));
+        let result = self.render(template_name, context);
+        self.layout_override = None;
+        result
+    }
+    
+    // =============================================================================
+    // v0.6.0 HTML Validation
+    // =============================================================================
+    
+    /// Check the output of each `render` and `render_string` for broken
+    /// markup, such as a `</div>` that only one branch of an `{{if}}` writes.
+    /// 
+    /// Non-void elements left unclosed, closed out of order or closed without
+    /// being opened become `warning` diagnostics (code `html-balance`) in
+    /// [`html_diagnostics`](Self::html_diagnostics), located in the template
+    /// through the same tracing as [`diff_renders`](Self::diff_renders). A
+    /// tag written inside a conditional is reported at the conditional. The
+    /// render itself never fails. The check only runs in debug builds; in
+    /// release builds the setting is ignored.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// engine.set_validate_html(true);
+    /// let mut context = TemplateContext::new();
+    /// context.set_bool("boxed", true);
+    /// engine.render_string("<main>\n{{if boxed}}<div>{{/if}}\n</main>", &context)?;
+    /// # #[cfg(debug_assertions)]
+    /// assert_eq!(engine.html_diagnostics()[0].line, 2);
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    #[cfg(feature = "lsp")]
+    pub fn set_validate_html(&mut self, enabled: bool) {
+        self.validate_html = enabled;
+        self.html_diagnostics.clear();
+    }
+    
+    /// Whether rendered output is checked for unbalanced tags
+    #[cfg(feature = "lsp")]
+    pub fn is_validate_html_enabled(&self) -> bool {
+        self.validate_html
+    }
+    
+    /// Tag balance warnings of the last `render` or `render_string`; empty
+    /// unless [`set_validate_html`](Self::set_validate_html) is on
+    #[cfg(feature = "lsp")]
+    pub fn html_diagnostics(&self) -> &[Diagnostic] {
+        &self.html_diagnostics
+    }
+    
+    /// Check `output` and locate its problems in the template `source` returns
+    #[cfg(feature = "lsp")]
+    fn check_rendered_html(
+        &mut self,
+        source: impl FnOnce(&mut Self) -> TemplateResult<Arc<str>>,
+        output: &str,
+        context: &TemplateContext,
+    ) {
+        if !self.validate_html || !cfg!(debug_assertions) {
+            return;
+        }
+        let problems = check_tag_balance(output);
+        if problems.is_empty() {
+            self.html_diagnostics.clear();
+            return;
+        }
+        
+        // Tracing renders tags one at a time; those renders are not checked
+        self.validate_html = false;
+        let trace = source(self).and_then(|source| {
+            self.keeping_macros(|engine| engine.trace_render(&source, output.to_string(), context))
+        });
+        self.validate_html = true;
+        
+        self.html_diagnostics = problems.into_iter()
+            .map(|problem| {
+                let (line, column, message) = match &trace {
+                    Ok(trace) => locate_in_trace(trace, problem.offset, problem.message),
+                    Err(_) => {
+                        let (line, column) = find_line_column(output, problem.offset);
+                        (line, column, format!("{} (output line {})", problem.message, line))
+                    }
+                };
+                Diagnostic::new(&message, "warning", line, column).with_code("html-balance")
+            })
+            .collect();
+    }
+    
+    // =============================================================================
+    // v0.6.0 Template Assertions
+    // =============================================================================
+    
+    /// Record failed `{{assert}}` directives instead of failing the render,
+    /// e.g. in production where a slightly wrong page beats an error page.
+    /// 
+    /// `{{assert condition "message"}}` evaluates its condition like
+    /// `{{if}}` and renders nothing. By default a false condition fails the
+    /// render with [`TemplateError::AssertionFailed`]; in soft mode the render
+    /// continues and [`render_with_failed_assertions`](Self::render_with_failed_assertions)
+    /// reports the failures.
+    pub fn set_soft_assertions(&mut self, enabled: bool) {
+        self.soft_assertions = enabled;
+    }
+    
+    /// Render a template, returning the assertions that failed in soft mode.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, TemplateContext, TemplateValue};
+    /// 
+    /// let mut engine = TemplateEngine::new(".");
+    /// let template = r#"{{assert items|length <= 2 "too many items for this layout"}}{{items|length}} items"#;
+    /// let mut context = TemplateContext::new();
+    /// context.set("items", TemplateValue::Array(vec![TemplateValue::Number(1); 3]));
+    /// assert!(engine.render_string(template, &context).is_err());
+    /// 
+    /// engine.set_soft_assertions(true);
+    /// let (output, failed) = engine.render_string_with_failed_assertions(template, &context)?;
+    /// assert_eq!(output, "3 items");
+    /// assert_eq!(failed[0].kind(), "assertion");
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn render_with_failed_assertions(&mut self, template_name: &str, context: &TemplateContext) -> TemplateResult<(String, Vec<TemplateError>)> {
+        self.recording_failed_assertions(|engine| engine.render(template_name, context))
+    }
+    
+    /// Like [`render_with_failed_assertions`](Self::render_with_failed_assertions), for a template string
+    pub fn render_string_with_failed_assertions(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<(String, Vec<TemplateError>)> {
+        self.recording_failed_assertions(|engine| engine.render_string(template, context))
+    }
+    
+    fn recording_failed_assertions(&mut self, render: impl FnOnce(&mut Self) -> TemplateResult<String>) -> TemplateResult<(String, Vec<TemplateError>)> {
+        let failed = Arc::new(Mutex::new(Vec::new()));
+        let previous = self.failed_assertions.replace(Arc::clone(&failed));
+        let result = render(self);
+        self.failed_assertions = previous;
+        let output = result?;
+        
+        let errors = failed.lock().map(|mut errors| std::mem::take(&mut *errors)).unwrap_or_default();
+        Ok((output, errors))
+    }
+    
+    /// Evaluate `{{assert condition "message"}}`
+    fn check_assertion(&self, directive: &str, args: &str, context: &TemplateContext) -> TemplateResult<()> {
+        let (condition, message) = split_assertion(args).ok_or_else(|| TemplateError::Parse(format!(
+            "Malformed '{{{{{}}}}}': expected '{{{{assert condition \"message\"}}}}'", directive
+        )))?;
+        if self.evaluate_condition(condition, context)? {
+            return Ok(());
+        }
+        
+        let (template_name, line, column) = self.tag_sites.get(directive).cloned().unwrap_or((None, 0, 0));
+        let error = TemplateError::AssertionFailed {
+            message: message.to_string(),
+            condition: condition.to_string(),
+            values: self.assertion_values(condition, context),
+            template_name,
+            line,
+            column,
+        };
+        if !self.soft_assertions {
+            return Err(error);
+        }
+        if let Some(failed) = &self.failed_assertions {
+            if let Ok(mut failed) = failed.lock() {
+                failed.push(error);
+            }
+        }
+        Ok(())
+    }
+    
+    /// The variables a condition compares, with their current values
+    fn assertion_values(&self, condition: &str, context: &TemplateContext) -> Vec<(String, String)> {
+        let operands = ["==", "!=", "<=", ">=", "<", ">"].iter()
+            .find_map(|operator| condition.split_once(operator))
+            .map_or_else(|| vec![condition], |(left, right)| vec![left, right]);
+        
+        operands.into_iter()
+            .map(str::trim)
+            .filter(|operand| {
+                let path = operand.split('|').next().unwrap_or_default().trim();
+                is_variable_path(path) && path != "true" && path != "false"
+            })
+            .map(|operand| {
+                let path = operand.split('|').next().unwrap_or_default().trim();
+                let shown = if !operand.contains('|') && !self.variable_exists_in_context(path, context) {
+                    "missing".to_string()
+                } else {
+                    match self.get_condition_value(operand, context) {
+                        Ok(value) => self.surfaced_value(path, &value, filter_value_to_string),
+                        Err(error) => error.to_string(),
+                    }
+                };
+                (operand.to_string(), shown)
+            })
+            .collect()
+    }
+    
+    /// Remember where each `{{assert}}` of `source` is, and every other tag
+    /// with strict variables, keeping earlier locations
+    fn record_tag_sites(&mut self, source: &str, template_name: Option<&str>) {
+        let opener = if self.strict_variables { "{{" } else { ASSERT_TAG };
+        let mut pos = 0;
+        while let Some(start) = source[pos..].find(opener).map(|start| pos + start) {
+            let Some(end) = source[start..].find("}}") else { break };
+            let (line, column) = find_line_column(source, start);
+            self.tag_sites.entry(source[start + 2..start + end].trim().to_string())
+                .or_insert_with(|| (template_name.map(str::to_string), line, column));
+            pos = start + end + 2;
+        }
+    }
+    
+    // =============================================================================
+    // v0.6.0 Template Packs
+    // =============================================================================
+    
+    /// Bundle templates into a pack file another engine can import.
+    /// 
+    /// The pack holds the named templates, every template they include or
+    /// extend by a quoted name (transitively), the translations of every
+    /// locale for the keys they use, and a manifest with the pack format
+    /// version. Dynamic includes and includes `from` a named root are not
+    /// followed.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::TemplateEngine;
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// engine.export_pack(&["emails/welcome.html", "emails/reset.html"], "emails.runepack")?;
+    /// 
+    /// // In another service, without the template directory
+    /// let mut other = TemplateEngine::new("templates");
+    /// other.import_pack("emails.runepack", Some("mail"))?;
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn export_pack(&mut self, templates: &[&str], path: impl AsRef<Path>) -> TemplateResult<()> {
+        let mut pack = TemplatePack {
+            engine_version: env!("CARGO_PKG_VERSION").to_string(),
+            entries: templates.iter().map(|name| name.to_string()).collect(),
+            ..TemplatePack::default()
+        };
+        let mut pending = pack.entries.clone();
+        while let Some(name) = pending.pop() {
+            if pack.templates.contains_key(&name) {
+                continue;
+            }
+            let source = self.load_template(&name)?;
+            pending.extend(static_dependency_sites(&source).into_iter()
+                .filter(|(_, _, from)| from.is_none())
+                .map(|(_, dependency, _)| dependency.to_string()));
+            pack.templates.insert(name, source);
+        }
+        
+        #[cfg(feature = "i18n")]
+        {
+            let keys: HashSet<String> = pack.templates.iter()
+                .flat_map(|(name, source)| scan_translation_keys(name, source))
+                .map(|usage| usage.key)
+                .collect();
+            for (locale, table) in self.translation_store.snapshot().iter() {
+                let used: BTreeMap<String, String> = table.iter()
+                    .filter(|(key, _)| keys.contains(key.as_str()))
+                    .map(|(key, text)| (key.clone(), text.clone()))
+                    .collect();
+                if !used.is_empty() {
+                    pack.translations.insert(locale.clone(), used);
+                }
+            }
+        }
+        
+        fs::write(path, pack.to_bytes())?;
+        Ok(())
+    }
+    
+    /// Register the templates of a pack file as in-memory templates.
+    /// 
+    /// With a namespace, `page.html` is registered as `namespace::page.html`
+    /// and the pack's includes and layouts are renamed to match. Imported
+    /// templates take precedence over template files and are never reloaded.
+    /// Translations are added to the engine's locales, keeping the engine's
+    /// own text for keys it already has.
+    /// 
+    /// Fails, registering nothing, when the pack was written in another
+    /// format version or one of its templates would replace a template that
+    /// already exists (as a file, in the cache or from an earlier import).
+    /// Returns the registered template names, sorted.
+    pub fn import_pack(&mut self, path: impl AsRef<Path>, namespace: Option<&str>) -> TemplateResult<Vec<String>> {
+        let pack = TemplatePack::from_bytes(&fs::read(path)?)?;
+        for name in pack.templates.keys() {
+            check_template_name(name)?;
+        }
+        let rename = |name: &str| match namespace {
+            Some(namespace) => format!("{}::{}", namespace, name),
+            None => name.to_string(),
+        };
+        let templates: Vec<(String, String)> = pack.templates.iter()
+            .map(|(name, source)| {
+                let source = match namespace {
+                    Some(_) => rename_pack_references(source, &pack.templates, rename),
+                    None => source.clone(),
+                };
+                (rename(name), source)
+            })
+            .collect();
+        
+        let existing = templates.iter().find(|(name, _)| {
+            self.pack_templates.contains_key(name)
+                || self.cache.contains_key(name)
+                || self.trace_resolution(name, None).winner().is_some()
+        });
+        if let Some((name, _)) = existing {
+            return Err(TemplateError::Template(format!(
+                "Template pack would replace the existing template '{}'", name
+            )));
+        }
+        
+        let mut names = Vec::with_capacity(templates.len());
+        for (name, source) in templates {
+            let source = self.trim_block_whitespace(source);
+            self.record_dependencies(&name, &source);
+            self.pack_templates.insert(name.clone(), source.into());
+            names.push(name);
+        }
+        #[cfg(feature = "i18n")]
+        for (locale, table) in pack.translations {
+            let mut translations = self.translation_store.snapshot().get(&locale).cloned().unwrap_or_default();
+            for (key, text) in table {
+                translations.entry(key).or_insert(text);
+            }
+            self.translation_store.swap(&locale, translations);
+        }
+        Ok(names)
+    }
+    
+    // =============================================================================
+    // v0.6.0 Output Encoding
+    // =============================================================================
+    
+    /// Render a template and encode the output, e.g. as Latin-1 for a legacy
+    /// export format.
+    /// 
+    /// Rendering happens in UTF-8; a character the encoding can't represent
+    /// fails with [`TemplateError::Encoding`] unless the encoding has a
+    /// substitute.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{TemplateEngine, TemplateContext, OutputEncoding};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// let bytes = engine.render_bytes("export.txt", &TemplateContext::new(), OutputEncoding::latin1())?;
+    /// std::fs::write("export.txt", bytes)?;
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn render_bytes(&mut self, template_name: &str, context: &TemplateContext, encoding: OutputEncoding) -> TemplateResult<Vec<u8>> {
+        let mut output = Vec::new();
+        self.render_bytes_into(template_name, context, &encoding, &mut output)?;
+        Ok(output)
+    }
+    
+    /// Render and encode a template into a caller-provided buffer.
+    /// 
+    /// Like [`render_into`](Self::render_into), the buffer is cleared first
+    /// and its capacity is kept.
+    pub fn render_bytes_into(&mut self, template_name: &str, context: &TemplateContext, encoding: &OutputEncoding, buf: &mut Vec<u8>) -> TemplateResult<()> {
+        buf.clear();
+        let output = self.render(template_name, context)?;
+        encoding.encode(&output, buf)
+    }
+    
+    // =============================================================================
+    // v0.6.0 Include Tracing
+    // =============================================================================
+    
+    /// Render a template and report the templates it pulled in as a tree.
+    /// 
+    /// Each node of the [`IncludeTree`] names an included or extended
+    /// template, whether it came from the cache or the disk, and the time
+    /// spent loading and parsing it. Render the same page twice and the
+    /// second tree has the same shape with every node from the cache.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// let (_, tree) = engine.render_with_trace("page.html", &TemplateContext::new())?;
+    /// print!("{}", tree.to_text());
+    /// println!("{} templates, {} levels", tree.unique_templates().len(), tree.depth());
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn render_with_trace(&mut self, template_name: &str, context: &TemplateContext) -> TemplateResult<(String, IncludeTree)> {
+        let recorder = Arc::new(Mutex::new(IncludeRecorder::new(template_name)));
+        let previous = self.include_trace.replace(Arc::clone(&recorder));
+        let result = self.render(template_name, context);
+        self.include_trace = previous;
+        let output = result?;
+        
+        let tree = recorder.lock().map(|recorder| recorder.tree())
+            .map_err(|_| TemplateError::Runtime("Include trace was poisoned".to_string()))?;
+        Ok((output, tree))
+    }
+    
+    /// Update the tree while `render_with_trace` is recording
+    fn trace_includes(&self, record: impl FnOnce(&mut IncludeRecorder)) {
+        if let Some(trace) = &self.include_trace {
+            if let Ok(mut recorder) = trace.lock() {
+                record(&mut recorder);
+            }
+        }
+    }
+    
+    // =============================================================================
+    // v0.6.0 Form Helpers
+    // =============================================================================
+    
+    /// Write a form helper: `{{input "email" value=form.email type="email"}}`,
+    /// `{{select "country" options=countries selected=form.country}}`,
+    /// `{{checkbox "subscribe" checked=form.subscribe}}` or
+    /// `{{field_errors "email" errors=form.errors}}`.
+    /// 
+    /// Arguments are quoted strings, numbers, booleans or context paths.
+    /// Other arguments become escaped attributes, and `true`/`false` write or
+    /// omit boolean attributes (`required=true`). `select` options are
+    /// scalars or `value`/`label` objects, matched against `selected` (one
+    /// value or an array) by their text, so `2` selects `"2"`. `field_errors`
+    /// lists the field's messages from an object of field -> message(s) and
+    /// writes nothing when there are none.
+    /// 
+    /// A component registered under the helper's name replaces the built-in
+    /// markup. Its props are `name` and the arguments, with `options` as
+    /// `value`/`label`/`selected` objects, `checked` as a boolean and
+    /// `errors` as the field's messages.
+    fn write_form_helper(&self, helper: &str, args: &str, context: &TemplateContext, out: &mut String) -> TemplateResult<()> {
+        let arguments = split_directive_arguments(args);
+        let Some((name, options)) = arguments.split_first().filter(|(name, _)| name.starts_with(['"', '\''])) else {
+            return Err(TemplateError::Parse(format!(
+                "Malformed '{{{{{} {}}}}}': expected '{{{{{} \"field\" name=value ...}}}}'", helper, args, helper
+            )));
+        };
+        let name = name.trim_matches('"').trim_matches('\'');
+        
+        let mut props = Vec::with_capacity(options.len());
+        for option in options {
+            let Some((key, value)) = option.split_once('=').filter(|(key, _)| is_attribute_name(key)) else {
+                return Err(TemplateError::Parse(format!(
+                    "Malformed argument '{}' in {{{{{}}}}}: expected name=value", option, helper
+                )));
+            };
+            let value = match value {
+                "true" | "false" => self.parse_single_helper_arg(value, context),
+                _ if is_variable_path(value) => self.resolve_variable_from_context(value, context),
+                _ => self.parse_single_helper_arg(value, context),
+            };
+            props.push((key.to_string(), value));
+        }
+        
+        // Values the markup needs in a normalized shape
+        let prop = |key: &str| props.iter().find(|(name, _)| name == key).map(|(_, value)| value);
+        let normalized = match helper {
+            "select" => Some(("options", TemplateValue::Array(form_options(prop("options"), prop("selected"))))),
+            "checkbox" => Some(("checked", TemplateValue::Bool(prop("checked").is_some_and(|checked| self.is_truthy(checked))))),
+            "field_errors" => Some(("errors", TemplateValue::Array(field_messages(prop("errors"), name)))),
+            _ => None,
+        };
+        if let Some((key, value)) = normalized {
+            match props.iter_mut().find(|(name, _)| name == key) {
+                Some(prop) => prop.1 = value,
+                None => props.push((key.to_string(), value)),
+            }
+        }
+        
+        if self.components.contains_key(helper) {
+            let mut component_props = TemplateContext::new();
+            component_props.set_string("name", name);
+            for (key, value) in props {
+                component_props.set(&key, value);
+            }
+            let mut engine = self.clone();
+            out.push_str(&engine.render_component(helper, component_props, HashMap::new())?);
+            return Ok(());
+        }
+        
+        let prop = |key: &str| props.iter().find(|(name, _)| name == key).map(|(_, value)| value);
+        let attributes = |reserved: &[&str], out: &mut String| {
+            for (key, value) in props.iter().filter(|(key, _)| !reserved.contains(&key.as_str())) {
+                write_form_attribute(key, value, out);
+            }
+        };
+        match helper {
+            "input" => {
+                out.push_str("<input");
+                write_form_attribute("type", prop("type").unwrap_or(&TemplateValue::String("text".to_string())), out);
+                write_form_attribute("name", &TemplateValue::String(name.to_string()), out);
+                attributes(&["type"], out);
+                out.push('>');
+            }
+            "checkbox" => {
+                out.push_str("<input type=\"checkbox\"");
+                write_form_attribute("name", &TemplateValue::String(name.to_string()), out);
+                attributes(&["type"], out);
+                out.push('>');
+            }
+            "select" => {
+                out.push_str("<select");
+                write_form_attribute("name", &TemplateValue::String(name.to_string()), out);
+                attributes(&["options", "selected"], out);
+                out.push('>');
+                for option in array_items(prop("options")) {
+                    out.push_str("<option");
+                    write_form_attribute("value", lookup_nested(option, &["value"]).unwrap_or(&TemplateValue::Bool(false)), out);
+                    write_form_attribute("selected", lookup_nested(option, &["selected"]).unwrap_or(&TemplateValue::Bool(false)), out);
+                    out.push('>');
+                    html_escape_into(&lookup_nested(option, &["label"]).map(filter_value_to_string).unwrap_or_default(), out);
+                    out.push_str("</option>");
+                }
+                out.push_str("</select>");
+            }
+            _ => {
+                let messages = array_items(prop("errors"));
+                if messages.is_empty() {
+                    return Ok(());
+                }
+                out.push_str("<ul");
+                if prop("class").is_none() {
+                    out.push_str(" class=\"field-errors\"");
+                }
+                attributes(&["errors"], out);
+                out.push('>');
+                for message in messages {
+                    out.push_str("<li>");
+                    html_escape_into(&filter_value_to_string(message), out);
+                    out.push_str("</li>");
+                }
+                out.push_str("</ul>");
+            }
+        }
+        Ok(())
+    }
+    
+    // =============================================================================
+    // v0.6.0 Template Manifest
+    // =============================================================================
+    
+    /// Render a template the manifest makes public, by public name or alias.
+    /// 
+    /// The manifest is `runic.manifest` in the template directory (see
+    /// [`TemplateManifest`] for the format). Names it doesn't list, including
+    /// every partial and layout left out of it, fail with
+    /// [`TemplateError::Security`], as does everything when there is no
+    /// manifest. The entry's options apply to the render: `autoescape=off`
+    /// outputs variables unescaped, `locale=fr` renders in that locale,
+    /// `provenance=off` leaves out the [provenance marker](Self::set_provenance)
+    /// and `cache_ttl=300` reads the file again once the cached source is
+    /// older than five minutes. [`render`](Self::render) ignores the manifest.
+    /// 
+    /// The manifest is read once, then again when it changes if hot reload
+    /// is enabled or on [`reload_changed_templates`](Self::reload_changed_templates).
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{TemplateEngine, TemplateContext, TemplateError};
+    /// 
+    /// // runic.manifest: `home -> pages/home.html`
+    /// let mut engine = TemplateEngine::new("templates");
+    /// let page = engine.render_public("home", &TemplateContext::new())?;
+    /// 
+    /// let refused = engine.render_public("partials/nav.html", &TemplateContext::new());
+    /// assert!(matches!(refused, Err(TemplateError::Security(_))));
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn render_public(&mut self, name: &str, context: &TemplateContext) -> TemplateResult<String> {
+        let entry = self.template_manifest()?.get(name).cloned()
+            .ok_or_else(|| TemplateError::Security(format!(
+                "Template '{}' is not public: it isn't listed in {}", name, MANIFEST_FILE_NAME
+            )))?;
+        self.expire_public_template(&entry);
+        
+        #[cfg(feature = "i18n")]
+        let previous_locale = match &entry.locale {
+            Some(locale) => self.current_locale.replace(locale.clone()),
+            None => self.current_locale.clone(),
+        };
+        #[cfg(not(feature = "i18n"))]
+        if entry.locale.is_some() {
+            return Err(feature_disabled(&format!("{} locale=", MANIFEST_FILE_NAME), "i18n"));
+        }
+        let previous_autoescape = std::mem::replace(&mut self.autoescape, entry.autoescape);
+        let previous_provenance = if entry.provenance { None } else { self.provenance.take() };
+        let result = self.render(&entry.template, context);
+        self.autoescape = previous_autoescape;
+        if previous_provenance.is_some() {
+            self.provenance = previous_provenance;
+        }
+        #[cfg(feature = "i18n")]
+        {
+            self.current_locale = previous_locale;
+        }
+        result
+    }
+    
+    /// Public names of the manifest, sorted; the public-only counterpart of
+    /// the templates found in the template directory
+    pub fn list_public_templates(&mut self) -> TemplateResult<Vec<String>> {
+        Ok(self.template_manifest()?.names().map(str::to_string).collect())
+    }
+    
+    /// The manifest of the template directory, read when first needed and
+    /// again when it changed under hot reload; empty when there is none
+    pub fn template_manifest(&mut self) -> TemplateResult<&TemplateManifest> {
+        let path = self.manifest_path();
+        let current = match self.manifest.take() {
+            Some((manifest, stamp)) if !self.hot_reload_enabled || file_stamp(&path) == stamp => (manifest, stamp),
+            _ => {
+                // Stamped before reading, like templates
+                let stamp = file_stamp(&path);
+                let manifest = match fs::read_to_string(&path) {
+                    Ok(source) => TemplateManifest::parse(&source)?,
+                    Err(error) if error.kind() == std::io::ErrorKind::NotFound => TemplateManifest::default(),
+                    Err(error) => return Err(TemplateError::Io(error)),
+                };
+                (manifest, stamp)
+            }
+        };
+        Ok(&self.manifest.insert(current).0)
+    }
+    
+    /// Path of `runic.manifest`
+    fn manifest_path(&self) -> PathBuf {
+        Path::new(&self.template_dir).join(MANIFEST_FILE_NAME)
+    }
+    
+    /// Drop the cached source of a public template older than its cache TTL
+    fn expire_public_template(&mut self, entry: &PublicTemplate) {
+        let Some(ttl) = entry.cache_ttl else { return };
+        let now = Instant::now();
+        let loaded_at = *self.public_loaded_at.entry(entry.template.clone()).or_insert(now);
+        if now.duration_since(loaded_at) >= ttl {
+            self.reload_templates(&[entry.template.as_str()]);
+            self.public_loaded_at.insert(entry.template.clone(), now);
+        }
+    }
+    
+    // =============================================================================
+    // v0.6.0 Deterministic Rendering
+    // =============================================================================
+    
+    /// Make renders reproducible byte for byte, for static site builds.
+    /// 
+    /// While enabled, the `{{now()}}` helper and the `timeago` filter read the
+    /// time given to [`set_fixed_time`](Self::set_fixed_time) (the Unix epoch
+    /// if none was given) instead of the system clock, and `{{nonce()}}` ids
+    /// derive from `seed` and their position in the render instead of being
+    /// random: renders with the same seed get the same ids, another seed gets
+    /// other ids. The rest of the output never depended on hash order, since
+    /// objects are always iterated and printed in key order.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// use std::time::{Duration, UNIX_EPOCH};
+    /// 
+    /// let mut engine = TemplateEngine::new(".");
+    /// engine.set_deterministic(true, 42);
+    /// engine.set_fixed_time(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+    /// 
+    /// let template = "<script nonce=\"{{nonce()}}\"></script> built {{now()}}";
+    /// let first = engine.render_string(template, &TemplateContext::new())?;
+    /// assert_eq!(first, engine.render_string(template, &TemplateContext::new())?);
+    /// assert!(first.ends_with("built 1700000000"));
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn set_deterministic(&mut self, enabled: bool, seed: u64) {
+        self.deterministic_seed = enabled.then_some(seed);
+    }
+    
+    /// Time read by `{{now()}}` and the `timeago` filter instead of the system clock
+    pub fn set_fixed_time(&mut self, time: SystemTime) {
+        self.fixed_time = Some(time);
+    }
+    
+    /// Reset the per-render state of a top-level render
+    fn start_render(&mut self) {
+        self.marker_key = new_marker_key();
+        // A fresh counter rather than a reset, since clones of the engine share it
+        if self.nonce_counter.load(Ordering::Relaxed) != 0 {
+            self.nonce_counter = Arc::new(AtomicU64::new(0));
+        }
+        // A clone may be rendering with the shared map at the same time
+        if Arc::strong_count(&self.uid_counters) > 1 || !self.uid_counters.lock().unwrap_or_else(PoisonError::into_inner).is_empty() {
+            self.uid_counters = Arc::new(Mutex::new(HashMap::new()));
+        }
+        #[cfg(feature = "i18n")]
+        if self.missing_translations.lock().map_or(true, |missing| !missing.is_empty()) {
+            self.missing_translations = Arc::new(Mutex::new(Vec::new()));
+        }
+        // Swaps published from now on are for the next render
+        #[cfg(feature = "i18n")]
+        {
+            self.translations = self.translation_store.snapshot();
+        }
+    }
+    
+    /// Seconds since the Unix epoch on the engine clock
+    fn unix_now(&self) -> i64 {
+        let now = match (self.fixed_time, self.deterministic_seed) {
+            (Some(time), _) => time,
+            (None, Some(_)) => SystemTime::UNIX_EPOCH,
+            (None, None) => SystemTime::now(),
+        };
+        match now.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(elapsed) => elapsed.as_secs() as i64,
+            Err(before) => -(before.duration().as_secs() as i64),
+        }
+    }
+    
+    /// Next `{{nonce()}}` id of the render, 16 hex digits
+    fn next_nonce(&self) -> String {
+        let index = self.nonce_counter.fetch_add(1, Ordering::Relaxed);
+        let bits = match self.deterministic_seed {
+            Some(seed) => splitmix64(seed.wrapping_add(index.wrapping_mul(SPLITMIX_GAMMA))),
+            None => {
+                let mut hasher = RandomState::new().build_hasher();
+                hasher.write_u64(index);
+                hasher.finish()
+            }
+        };
+        format!("{:016x}", bits)
+    }
+    
+    /// Next `{{uid("prefix")}}` id of the render: `prefix-1`, `prefix-2`, ...
+    fn next_uid(&self, prefix: &str) -> String {
+        let mut counters = self.uid_counters.lock().unwrap_or_else(PoisonError::into_inner);
+        let counter = counters.entry(prefix.to_string()).or_insert(0);
+        *counter += 1;
+        format!("{}-{}", prefix, counter)
+    }
+    
+    // =============================================================================
+    // v0.6.0 Components
+    // =============================================================================
+    
+    /// Register a template as the component `name` for
+    /// [`render_component`](Self::render_component)
+    pub fn register_component(&mut self, name: &str, template_name: &str) {
+        self.components.insert(name.to_string(), template_name.to_string());
+    }
+    
+    /// Render a component with props and slots, the calling convention for
+    /// web components.
+    /// 
+    /// `name` is looked up among the templates registered with
+    /// [`register_component`](Self::register_component) first, then among the
+    /// macros defined by templates rendered so far. Props become the
+    /// component's whole context; slots are already rendered HTML, output
+    /// unescaped by `{{slot.name}}`. `{{has_slot "footer"}}` (and
+    /// `{{if has_slot "footer"}}`) tells whether a slot was given.
+    /// 
+    /// A component template can declare what it accepts with
+    /// `{{props label variant="primary"}}` (props with a default are optional)
+    /// and `{{slots body footer?}}` (`?` marks an optional slot); a macro's
+    /// parameters are its props. Once declared, missing required props or
+    /// slots and undeclared ones fail with [`TemplateError::ComponentError`].
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// use std::collections::HashMap;
+    /// 
+    /// let mut engine = TemplateEngine::new(".");
+    /// # #[cfg(feature = "template-macros")]
+    /// # {
+    /// engine.render_string(
+    ///     "{{macro card(title)}}{{slots body}}<h2>{{title}}</h2>{{slot.body}}{{/macro}}",
+    ///     &TemplateContext::new(),
+    /// )?;
+    /// 
+    /// let mut props = TemplateContext::new();
+    /// props.set_string("title", "News");
+    /// let slots = HashMap::from([("body".to_string(), "<p>Hello</p>".to_string())]);
+    /// assert_eq!(engine.render_component("card", props, slots)?, "<h2>News</h2><p>Hello</p>");
+    /// # }
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn render_component(&mut self, name: &str, props: TemplateContext, slots: HashMap<String, String>) -> TemplateResult<String> {
+        self.start_render();
+        let (source, signature) = self.component_source(name, &props)?;
+        check_component_call(name, &signature, &props, &slots)?;
+        
+        let mut context = props;
+        for (prop, default) in signature.props.iter().flatten() {
+            if let Some(default) = default {
+                if !context.contains(prop) {
+                    let value = self.parse_single_helper_arg(default, &context);
+                    context.set(prop, value);
+                }
+            }
+        }
+        let slots = slots.into_iter().map(|(slot, html)| (slot, TemplateValue::String(html))).collect();
+        context.set("slot", TemplateValue::Object(slots));
+        
+        // Slots hold rendered HTML
+        let source = source.replace("{{slot.", "{{& slot.");
+        let mut output = String::new();
+        self.render_source_into(&source, &context, &mut output, None)?;
+        Ok(output)
+    }
+    
+    /// Source and declarations of a registered component template or macro
+    fn component_source(&mut self, name: &str, props: &TemplateContext) -> TemplateResult<(Arc<str>, ComponentSignature)> {
+        if let Some(template_name) = self.components.get(name).cloned() {
+            let source = self.prepare_template_source(&template_name, Some(props))?;
+            let signature = component_signature(&source);
+            return Ok((source, signature));
+        }
+        
+        #[cfg(feature = "template-macros")]
+        if let Some(macro_def) = self.macros.get(name) {
+            let mut signature = component_signature(&macro_def.body);
+            signature.props = Some(macro_def.parameters.iter()
+                .map(|param| (param.clone(), macro_def.defaults.get(param).cloned()))
+                .collect());
+            return Ok((macro_def.body.as_str().into(), signature));
+        }
+        
+        Err(TemplateError::Template(format!(
+            "Unknown component '{}': register a template with register_component or define a macro named '{}'",
+            name, name
+        )))
+    }
+    
+    // =============================================================================
+    // v0.6.0 Error Boundaries
+    // =============================================================================
+    
+    /// Render a template and collect the errors its `{{try}}` blocks rescued.
+    /// 
+    /// `{{try}}...{{rescue}}...{{/try}}` renders the try body, or the rescue
+    /// body when the try body fails: helper, filter and strict mode errors,
+    /// missing includes and nesting limits exceeded by included templates are
+    /// all rescued. Inside the rescue body `{{error.message}}` and
+    /// `{{error.kind}}` (see [`TemplateError::kind`]) describe the error.
+    /// Boundaries nest, and an error in a rescue body goes to the enclosing
+    /// boundary. The rescue body is optional; without it a failing try body
+    /// renders nothing.
+    /// 
+    /// Security errors (path traversal, dynamic includes outside the
+    /// allow-list, ...) and cancellation are never rescued: they always fail
+    /// the whole render, so a boundary can't be used to probe for templates
+    /// or to keep rendering after the client went away.
+    /// 
+    /// Rescued errors are returned in the order they happened; a plain
+    /// [`render`](Self::render) rescues the same errors without collecting them.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, TemplateContext, TemplateError, TemplateValue};
+    /// 
+    /// let mut engine = TemplateEngine::new(".");
+    /// engine.register_helper("recommendations", |_| {
+    ///     Err(TemplateError::Runtime("service unavailable".to_string()))
+    /// });
+    /// let template = "{{try}}{{recommendations(user)}}{{rescue}}<p>Unavailable ({{error.kind}})</p>{{/try}}";
+    /// let mut context = TemplateContext::new();
+    /// context.set("user", TemplateValue::Number(7));
+    /// 
+    /// assert_eq!(engine.render_string(template, &context)?, "<p>Unavailable (runtime)</p>");
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn render_with_rescued_errors(&mut self, template_name: &str, context: &TemplateContext) -> TemplateResult<(String, Vec<TemplateError>)> {
+        let rescued = Arc::new(Mutex::new(Vec::new()));
+        let previous = self.rescued_errors.replace(Arc::clone(&rescued));
+        let result = self.render(template_name, context);
+        self.rescued_errors = previous;
+        let output = result?;
+        
+        let errors = rescued.lock().map(|mut errors| std::mem::take(&mut *errors)).unwrap_or_default();
+        Ok((output, errors))
+    }
+    
+    /// Replace the `{{try}}` blocks of `template` that are outside loops with
+    /// their try or rescue output; loop bodies handle their own blocks
+    fn process_error_boundaries(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
+        let mut result = String::with_capacity(template.len());
+        let mut pos = 0;
+        for boundary in find_error_boundaries(template)? {
+            result.push_str(&template[pos..boundary.start]);
+            let output = self.render_error_boundary(template, &boundary, context)?;
+            self.defer_rendered_part(output, &mut result);
+            pos = boundary.end;
+        }
+        result.push_str(&template[pos..]);
+        Ok(result)
+    }
+    
+    /// Output of one `{{try}}` block
+    fn render_error_boundary(&mut self, template: &str, boundary: &ErrorBoundary, context: &TemplateContext) -> TemplateResult<String> {
+        let mut output = String::new();
+        let error = match self.write_source(&template[boundary.body.clone()], context, &mut output, None) {
+            Ok(()) => return Ok(output),
+            Err(error) if !is_rescuable(&error) => return Err(error),
+            Err(error) => error,
+        };
+        
+        output.clear();
+        let rescue_context = rescue_context(context, &error);
+        self.record_rescued(error);
+        if let Some(rescue) = &boundary.rescue {
+            self.write_source(&template[rescue.clone()], &rescue_context, &mut output, None)?;
+        }
+        Ok(output)
+    }
+    
+    /// Keep rendered `output` aside and write a marker for it to `out`
+    fn defer_rendered_part(&mut self, output: String, out: &mut String) {
+        write_asset_marker(out, self.marker_key, '~', &self.rendered_parts.len().to_string());
+        self.rendered_parts.push(output);
+    }
+    
+    /// Put the parts kept by [`defer_rendered_part`](Self::defer_rendered_part)
+    /// back in place of their markers in `out[from..]`
+    fn put_back_rendered_parts(&self, out: &mut String, from: usize) {
+        if self.rendered_parts.is_empty() || !out[from..].contains(ASSET_MARKER_OPEN) {
+            return;
+        }
+        let markers = deferred_markers(&out[from..], self.marker_key, &['~']);
+        if markers.is_empty() {
+            return;
+        }
+        let mut restored = String::with_capacity(out.len() - from);
+        let mut pos = 0;
+        for (range, _, index) in markers {
+            restored.push_str(&out[from + pos..from + range.start]);
+            if let Some(part) = index.parse::<usize>().ok().and_then(|index| self.rendered_parts.get(index)) {
+                restored.push_str(part);
+            }
+            pos = range.end;
+        }
+        restored.push_str(&out[from + pos..]);
+        out.truncate(from);
+        out.push_str(&restored);
+    }
+    
+    /// Note a rescued error while `render_with_rescued_errors` is collecting
+    fn record_rescued(&self, error: TemplateError) {
+        if let Some(rescued) = &self.rescued_errors {
+            if let Ok(mut errors) = rescued.lock() {
+                errors.push(error);
+            }
+        }
+    }
+    
+    // =============================================================================
+    // v0.6.0 Template AST
+    // =============================================================================
+
+    /// Parse a template into a syntax tree for external tooling.
+    ///
+    /// `template_or_name` is parsed as template source when it contains a
+    /// `{{` tag and loaded as a template name otherwise. See the
+    /// [`ast`](crate::ast) module for the node types and the [`ast::Visitor`]
+    /// trait that walks them.
+    ///
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{ast, TemplateEngine};
+    ///
+    /// let mut engine = TemplateEngine::new("templates");
+    /// let template = engine.parse("{{for user in users}}{{user.name}}{{/for}}")?;
+    /// assert!(matches!(&template.nodes[0], ast::Node::For(node) if node.var == "user"));
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn parse(&mut self, template_or_name: &str) -> TemplateResult<ast::Template> {
+        if template_or_name.contains("{{") {
+            return ast::parse(template_or_name);
+        }
+        let source = self.load_template_arc(template_or_name)?;
+        ast::parse_named(Some(template_or_name), &source)
+    }
+
+    // =============================================================================
+    // v0.6.0 Render Cancellation
+    // =============================================================================
+    
+    /// Render a template, stopping early when `token` is cancelled.
+    /// 
+    /// The token is checked at every loop iteration, every include and every
+    /// few kilobytes of output, so a cancelled render returns
+    /// [`TemplateError::Cancelled`] within milliseconds, even in the middle
+    /// of a large loop. Nothing the render cached is left half-built: the
+    /// engine can render again right away. A token cancelled before the
+    /// call fails without rendering.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{CancellationToken, TemplateEngine, TemplateContext, TemplateError};
+    /// use std::{thread, time::Duration};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// let token = CancellationToken::new();
+    /// 
+    /// // Cancel from elsewhere, e.g. when the client disconnects
+    /// let client_gone = token.clone();
+    /// thread::spawn(move || {
+    ///     thread::sleep(Duration::from_millis(50));
+    ///     client_gone.cancel();
+    /// });
+    /// 
+    /// match engine.render_with_cancel("report.html", &TemplateContext::new(), &token) {
+    ///     Ok(html) => println!("{}", html),
+    ///     Err(TemplateError::Cancelled) => eprintln!("render abandoned"),
+    ///     Err(error) => eprintln!("{}", error),
+    /// }
+    /// ```
+    pub fn render_with_cancel(&mut self, template_name: &str, context: &TemplateContext, token: &CancellationToken) -> TemplateResult<String> {
+        if token.is_cancelled() {
+            return Err(TemplateError::Cancelled);
+        }
+        let previous = self.cancellation.replace(token.clone());
+        let result = self.render(template_name, context);
+        self.cancellation = previous;
+        result
+    }
+    
+    /// Fail with [`TemplateError::Cancelled`] once the current render's token is cancelled
+    fn check_cancelled(&self) -> TemplateResult<()> {
+        match &self.cancellation {
+            Some(token) if token.is_cancelled() => Err(TemplateError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+    
+    // =============================================================================
+    // v0.6.0 Offline Rendering
+    // =============================================================================
+    
+    /// Render a template without touching the filesystem.
+    /// 
+    /// Only cached sources are used: the template, its includes and its
+    /// layout parents must have been loaded before, typically by
+    /// [`warm_up`](Self::warm_up). A template that isn't cached fails with
+    /// [`TemplateError::OfflineMiss`] instead of being read from disk, and hot
+    /// reload checks are skipped. Translations and filters are always in memory.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{TemplateEngine, TemplateContext, TemplateError};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// engine.warm_up()?;
+    /// 
+    /// // On the request path
+    /// match engine.render_offline("page.html", &TemplateContext::new()) {
+    ///     Ok(html) => println!("{}", html),
+    ///     Err(TemplateError::OfflineMiss { name }) => eprintln!("'{}' was not warmed up", name),
+    ///     Err(error) => eprintln!("{}", error),
+    /// }
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn render_offline(&mut self, template_name: &str, context: &TemplateContext) -> TemplateResult<String> {
+        let was_offline = std::mem::replace(&mut self.offline, true);
+        let result = self.render(template_name, context);
+        self.offline = was_offline;
+        result
+    }
+    
+    // =============================================================================
+    // v0.6.0 Template Validation
+    // =============================================================================
+    
+    /// Create an engine, failing fast when a template of `template_dir` is broken.
+    /// 
+    /// See [`validate_templates`](Self::validate_templates) for the checks.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::TemplateEngine;
+    /// 
+    /// let engine = TemplateEngine::new_validated("templates").unwrap_or_else(|error| {
+    ///     eprintln!("{}", error);
+    ///     std::process::exit(1);
+    /// });
+    /// ```
+    pub fn new_validated(template_dir: &str) -> TemplateResult<Self> {
+        let mut engine = Self::new(template_dir);
+        engine.validate_templates(&[])?;
+        Ok(engine)
+    }
+    
+    /// Check every template of the template directory without rendering it.
+    /// 
+    /// Each template is parsed and linted (syntax only, no context), and its
+    /// quoted `{{include}}`/`{{extends}}` targets must exist. Templates
+    /// matching one of the `ignore` patterns (a name prefix such as
+    /// `"drafts/"`, or a glob with `*`) are skipped. Every broken template is
+    /// reported, with its first problem, in one
+    /// [`TemplateError::InvalidTemplates`]. Run it after registering custom
+    /// filters and pipelines so their uses count as known. Linting needs the
+    /// `lsp` feature; without it only the syntax and targets are checked.
+    pub fn validate_templates(&mut self, ignore: &[&str]) -> TemplateResult<()> {
+        let mut checked = 0;
+        let mut failures = Vec::new();
+        
+        for template_name in self.list_available_templates()? {
+            if ignore.iter().any(|pattern| template_matches_pattern(&template_name, pattern)) {
+                continue;
+            }
+            checked += 1;
+            let problem = match self.load_template_arc(&template_name) {
+                Ok(content) => self.first_template_problem(&content),
+                Err(error) => Some(error.to_string()),
+            };
+            if let Some(problem) = problem {
+                failures.push((template_name, problem));
+            }
+        }
+        
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(TemplateError::InvalidTemplates {
+                template_dir: self.template_dir.clone(),
+                checked,
+                failures,
+            })
+        }
+    }
+    
+    /// First syntax, lint or missing reference problem of a template, as `line:column: message`
+    fn first_template_problem(&self, content: &str) -> Option<String> {
+        if let Err(TemplateError::ParseWithLocation { message, line, column, .. }) = self.check_syntax(content) {
+            return Some(format!("{}:{}: {}", line, column, message));
+        }
+        #[cfg(feature = "lsp")]
+        if let Some(diagnostic) = self.collect_diagnostics(content, None).into_iter().find(|diagnostic| diagnostic.severity == "error") {
+            return Some(format!("{}:{}: {}", diagnostic.line, diagnostic.column, diagnostic.message));
+        }
+        
+        let mut sites = static_dependency_sites(content);
+        sites.sort_by_key(|(offset, _, _)| *offset);
+        sites.into_iter()
+            .find(|(_, name, from)| self.trace_resolution(name, *from).winner().is_none())
+            .map(|(offset, name, _)| {
+                let (line, column) = find_line_column(content, offset);
+                format!("{}:{}: Referenced template '{}' does not exist", line, column, name)
+            })
+    }
+    
+    // =============================================================================
+    // v0.6.0 Filter Pipelines
+    // =============================================================================
+    
+    /// Register a named filter chain, usable like any filter.
+    /// 
+    /// `$1`, `$2`, ... in the chain are replaced by the pipeline's own
+    /// arguments: with `gross` = `multiply:$1|round:2`, `{{net|gross:1.2}}`
+    /// runs `multiply:1.2|round:2`. Pipelines may use other pipelines; they
+    /// are expanded to plain filters here, once, so rendering a pipeline costs
+    /// no more than writing its chain out. Registered pipelines are known to
+    /// [`check_syntax`](Self::check_syntax), diagnostics and completions.
+    /// 
+    /// Fails, leaving the pipelines as they were, when the name is a built-in
+    /// filter or the pipelines would reference each other in a cycle.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// engine.register_pipeline("excerpt", "strip|truncate:$1|capitalize")?;
+    /// engine.register_pipeline("teaser", "excerpt:12|upper")?;
+    /// 
+    /// let mut context = TemplateContext::new();
+    /// context.set_string("body", "  the quick brown fox jumps  ");
+    /// assert_eq!(engine.render_string("{{body|teaser}}", &context)?, "THE QUICK BR...");
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn register_pipeline(&mut self, name: &str, chain: &str) -> TemplateResult<()> {
+        if BUILTIN_FILTERS.contains(&name) {
+            return Err(TemplateError::Template(format!("Pipeline '{}' would shadow the built-in filter", name)));
+        }
+        let mut definitions = self.pipeline_definitions.clone();
+        definitions.insert(name.to_string(), chain.to_string());
+        
+        let mut pipelines = HashMap::new();
+        for pipeline in definitions.keys() {
+            pipelines.insert(pipeline.clone(), expand_pipeline(pipeline, &definitions, &mut Vec::new())?);
+        }
+        self.pipeline_definitions = definitions;
+        self.pipelines = pipelines;
+        Ok(())
+    }
+    
+    // =============================================================================
+    // v0.6.0 Data Tables
+    // =============================================================================
+    
+    /// Write `{{table rows columns="key:Label|filter,..." class="..." row="macro"}}`.
+    /// 
+    /// Headers come from the column labels, or the humanized keys. Each cell
+    /// is the row's value for the key through the column's filters, escaped
+    /// unless a filter produces HTML; missing keys give empty cells. `columns`
+    /// may also name an array of column specs in the context. With `row`, the
+    /// named macro writes the cells of each row from its `row` and `index`
+    /// (1-based) parameters.
+    fn write_table(&self, args: &str, context: &TemplateContext, out: &mut String) -> TemplateResult<()> {
+        let arguments = split_directive_arguments(args);
+        let Some((rows_path, options)) = arguments.split_first().filter(|(rows, _)| !rows.contains('=')) else {
+            return Err(TemplateError::Parse(format!("Malformed '{{{{table {}}}}}': expected '{{{{table rows columns=\"...\"}}}}'", args)));
+        };
+        
+        let (mut columns, mut class, mut row_macro) = (None, None, None);
+        for option in options {
+            let Some((name, value)) = option.split_once('=') else {
+                return Err(TemplateError::Parse(format!("Malformed argument '{}' in {{{{table}}}}: expected name=\"value\"", option)));
+            };
+            let quoted = value.starts_with(['"', '\'']);
+            let value = value.trim_matches('"').trim_matches('\'');
+            match name {
+                "columns" if quoted => columns = Some(value.split(',').map(TableColumn::parse).collect::<Vec<_>>()),
+                "columns" => columns = Some(self.table_columns_from_context(value, context)?),
+                "class" => class = Some(value),
+                "row" => row_macro = Some(value),
+                _ => return Err(TemplateError::Parse(format!("Unknown argument '{}' in {{{{table}}}}", name))),
+            }
+        }
+        let columns = columns
+            .ok_or_else(|| TemplateError::Parse(format!("Missing columns=\"...\" in {{{{table {}}}}}", args)))?;
+        
+        let rows = match self.resolve_variable_from_context(rows_path, context) {
+            TemplateValue::Array(rows) => rows,
+            TemplateValue::String(missing) if missing.is_empty() => Vec::new(),
+            _ if self.strict_mode => {
+                return Err(TemplateError::Render(format!("{{{{table {}}}}} needs an array of rows", rows_path)));
+            }
+            _ => Vec::new(),
+        };
+        
+        out.push_str("<table");
+        if let Some(class) = class {
+            out.push_str(" class=\"");
+            html_escape_into(class, out);
+            out.push('"');
+        }
+        out.push_str("><thead><tr>");
+        for column in &columns {
+            out.push_str("<th>");
+            html_escape_into(&column.label, out);
+            out.push_str("</th>");
+        }
+        out.push_str("</tr></thead><tbody>");
+        
+        match row_macro {
+            Some(name) => self.write_macro_rows(name, &rows, out)?,
+            None => {
+                for row in &rows {
+                    out.push_str("<tr>");
+                    for column in &columns {
+                        out.push_str("<td>");
+                        self.write_table_cell(row, column, out)?;
+                        out.push_str("</td>");
+                    }
+                    out.push_str("</tr>");
+                }
+            }
+        }
+        out.push_str("</tbody></table>");
+        Ok(())
+    }
+    
+    /// Write the `{{table row="macro"}}` rows, the macro writing the cells of each
+    #[cfg(feature = "template-macros")]
+    fn write_macro_rows(&self, name: &str, rows: &[TemplateValue], out: &mut String) -> TemplateResult<()> {
+        let definition = self.macros.get(name).cloned()
+            .ok_or_else(|| TemplateError::Template(format!("Unknown row macro '{}' in {{{{table}}}}", name)))?;
+        let mut engine = self.clone();
+        for (index, row) in rows.iter().enumerate() {
+            let index = TemplateValue::Number(index as i64 + 1);
+            let args = HashMap::from([
+                ("row".to_string(), row.clone()),
+                ("0".to_string(), row.clone()),
+                ("index".to_string(), index.clone()),
+                ("1".to_string(), index),
+            ]);
+            out.push_str("<tr>");
+            out.push_str(&engine.expand_macro_with_values(&definition, &args)?);
+            out.push_str("</tr>");
+        }
+        Ok(())
+    }
+    
+    /// Without the `template-macros` feature row macros fail
+    #[cfg(not(feature = "template-macros"))]
+    fn write_macro_rows(&self, name: &str, _rows: &[TemplateValue], _out: &mut String) -> TemplateResult<()> {
+        Err(feature_disabled(&format!("{{{{table row=\"{}\"}}}}", name), "template-macros"))
+    }
+    
+    /// Column specs held by a context array (`columns=report_columns`)
+    fn table_columns_from_context(&self, variable: &str, context: &TemplateContext) -> TemplateResult<Vec<TableColumn>> {
+        match self.resolve_variable_from_context(variable, context) {
+            TemplateValue::Array(specs) => Ok(specs.iter()
+                .map(|spec| TableColumn::parse(&filter_value_to_string(spec)))
+                .collect()),
+            _ => Err(TemplateError::Render(format!("{{{{table}}}} columns '{}' must be an array of column specs", variable))),
+        }
+    }
+    
+    /// Write one cell: the row's value through the column filters
+    fn write_table_cell(&self, row: &TemplateValue, column: &TableColumn, out: &mut String) -> TemplateResult<()> {
+        let path: Vec<&str> = column.key.split('.').collect();
+        let Some(value) = lookup_nested(row, &path) else {
+            return Ok(());
+        };
+        
+        let mut value = Cow::Borrowed(value);
+        for filter in column.expression.split('|').skip(1) {
+            value = Cow::Owned(self.apply_value_filter(value.into_owned(), filter.trim())?);
+        }
+        
+        let escape = self.filter_escaping(&column.expression).unwrap_or(true);
+        self.write_value(&value, escape, out, || format!("'{}' in {{{{table}}}}", column.key))
+    }
+    
+    // =============================================================================
+    // v0.6.0 Translation Keys
+    // =============================================================================
+    
+    /// List every `{{t "key"}}` and `{{plural}}` key a template uses, with
+    /// its named arguments, location and presence in each configured locale.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::TemplateEngine;
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// for usage in engine.extract_translation_keys("checkout.html")? {
+    ///     for locale in usage.missing_locales() {
+    ///         println!("{}:{} '{}' is missing in {}", usage.line, usage.column, usage.key, locale);
+    ///     }
+    /// }
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    #[cfg(feature = "i18n")]
+    pub fn extract_translation_keys(&mut self, template_name: &str) -> TemplateResult<Vec<TranslationKeyUsage>> {
+        let content = self.load_template_arc(template_name)?;
+        let mut usages = scan_translation_keys(template_name, &content);
+        for usage in &mut usages {
+            usage.locales = self.translation_store.snapshot().iter()
+                .map(|(locale, table)| (locale.clone(), table.contains_key(&usage.key)))
+                .collect();
+        }
+        Ok(usages)
+    }
+    
+    /// [`extract_translation_keys`](Self::extract_translation_keys) for every
+    /// template in the template directory
+    #[cfg(feature = "i18n")]
+    pub fn extract_all_translation_keys(&mut self) -> TemplateResult<Vec<TranslationKeyUsage>> {
+        let mut usages = Vec::new();
+        for template_name in self.list_available_templates()? {
+            usages.extend(self.extract_translation_keys(&template_name)?);
+        }
+        Ok(usages)
+    }
+    
+    /// Keys missing per locale and table entries no template uses, over the
+    /// whole template directory
+    #[cfg(feature = "i18n")]
+    pub fn translation_report(&mut self) -> TemplateResult<TranslationReport> {
+        let usages = self.extract_all_translation_keys()?;
+        Ok(TranslationReport::new(usages, &self.translation_store.snapshot()))
+    }
+    
+    // =============================================================================
+    // v0.6.0 Atomic Hot Reload
+    // =============================================================================
+    
+    /// Reload every cached template whose file changed, disappeared or is now
+    /// shadowed by another root, along with the templates depending on it.
+    /// 
+    /// Returns the cache keys that were reloaded, sorted; see
+    /// [`reload_templates`](Self::reload_templates).
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::TemplateEngine;
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// engine.warm_up()?;
+    /// // ... edit templates/partials/nav.html ...
+    /// for name in engine.reload_changed_templates() {
+    ///     println!("reloaded {}", name);
+    /// }
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn reload_changed_templates(&mut self) -> Vec<String> {
+        if let Some((_, stamp)) = &self.manifest {
+            if file_stamp(&self.manifest_path()) != *stamp {
+                self.manifest = None;
+            }
+        }
+        let changed: Vec<String> = self.resolved_paths.iter()
+            .filter(|(key, path)| {
+                let (name, from) = split_cache_key(key);
+                let stamp = file_stamp(path);
+                stamp.is_none()
+                    || stamp.as_ref() != self.file_mtimes.get(key.as_str())
+                    || self.resolve_template_path(name, from).ok().as_ref() != Some(*path)
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        self.reload_templates(&changed)
+    }
+    
+    /// Reload templates together with every template that includes or
+    /// extends them, directly or through other templates.
+    /// 
+    /// The whole closure leaves the source, bytecode and layout caches before
+    /// any source is read again, so the engine never holds a reloaded partial
+    /// next to the stale layout around it. Templates that can no longer be
+    /// read stay uncached and report their error on the next render. Returns
+    /// the reloaded cache keys, sorted.
+    pub fn reload_templates<S: AsRef<str>>(&mut self, names: &[S]) -> Vec<String> {
+        let closure = self.dependency_closure(names);
+        let cached: Vec<String> = closure.iter()
+            .filter(|key| self.cache.contains_key(key.as_str()))
+            .cloned()
+            .collect();
+        
+        for key in &closure {
+            self.cache.remove(key);
+            self.bytecode_cache.remove(key);
+            self.resolved_paths.remove(key);
+            self.file_mtimes.remove(key);
+            #[cfg(feature = "inheritance")]
+            self.layout_processor.templates.remove(split_cache_key(key).0);
+        }
+        for key in &cached {
+            let (name, from) = split_cache_key(key);
+            let _ = self.load_template_from_root(name, from);
+        }
+        closure
+    }
+    
+    /// `names` and every template depending on them, sorted
+    fn dependency_closure<S: AsRef<str>>(&self, names: &[S]) -> Vec<String> {
+        let mut closure = BTreeSet::new();
+        let mut pending: Vec<String> = names.iter().map(|name| name.as_ref().to_string()).collect();
+        while let Some(key) = pending.pop() {
+            if let Some(dependents) = self.template_dependencies.get(&key) {
+                pending.extend(dependents.iter().filter(|dependent| !closure.contains(*dependent)).cloned());
+            }
+            closure.insert(key);
+        }
+        closure.into_iter().collect()
+    }
+    
+    /// Track the templates `content` includes or extends by name, replacing
+    /// what was known about the template cached as `key`
+    fn record_dependencies(&mut self, key: &str, content: &str) {
+        for dependents in self.template_dependencies.values_mut() {
+            dependents.retain(|dependent| dependent != key);
+        }
+        for dependency in static_dependencies(content) {
+            let dependents = self.template_dependencies.entry(dependency).or_default();
+            if !dependents.iter().any(|dependent| dependent == key) {
+                dependents.push(key.to_string());
+            }
+        }
+    }
+    
+    /// Note that `dependent` extends or includes `dependency`, keeping what
+    /// is already known
+    #[cfg(feature = "inheritance")]
+    fn record_dependency(&mut self, dependency: &str, dependent: &str) {
+        let dependents = self.template_dependencies.entry(dependency.to_string()).or_default();
+        if !dependents.iter().any(|known| known == dependent) {
+            dependents.push(dependent.to_string());
+        }
+    }
+    
+    // =============================================================================
+    // v0.6.0 Render Diffs
+    // =============================================================================
+    
+    /// Render two templates with the same context and report where their
+    /// output differs.
+    /// 
+    /// Every piece of output is traced to the text or directive that produced
+    /// it, including loop iterations, so each [`DiffRegion`](crate::DiffRegion)
+    /// names the directives and template lines responsible on both sides.
+    /// Variables read by output tags that resolved differently are listed too.
+    /// Templates using `{{extends}}` are traced as a single piece.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// let context = TemplateContext::new();
+    /// let diff = engine.diff_renders("cart.html", "cart_refactored.html", &context)?;
+    /// if !diff.is_identical() {
+    ///     println!("{}", diff);
+    /// }
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn diff_renders(&mut self, template_a: &str, template_b: &str, context: &TemplateContext) -> TemplateResult<RenderDiff> {
+        let left = self.trace_template(template_a, context)?;
+        let right = self.trace_template(template_b, context)?;
+        Ok(RenderDiff::new(template_a, template_b, left, right))
+    }
+    
+    /// Like [`diff_renders`](Self::diff_renders), for template sources
+    pub fn diff_render_strings(&mut self, template_a: &str, template_b: &str, context: &TemplateContext) -> TemplateResult<RenderDiff> {
+        let left = self.trace_source(template_a, context)?;
+        let right = self.trace_source(template_b, context)?;
+        Ok(RenderDiff::new("a", "b", left, right))
+    }
+    
+    /// Render one template with two contexts and report where the output differs
+    pub fn diff_contexts(&mut self, template_name: &str, context_a: &TemplateContext, context_b: &TemplateContext) -> TemplateResult<RenderDiff> {
+        let left = self.trace_template(template_name, context_a)?;
+        let right = self.trace_template(template_name, context_b)?;
+        Ok(RenderDiff::new(&format!("{} (a)", template_name), &format!("{} (b)", template_name), left, right))
+    }
+    
+    fn trace_template(&mut self, template_name: &str, context: &TemplateContext) -> TemplateResult<RenderTrace> {
+        self.keeping_macros(|engine| {
+            let output = engine.render(template_name, context)?;
+            let source = engine.load_template_arc(template_name)?;
+            engine.trace_render(&source, output, context)
+        })
+    }
+    
+    fn trace_source(&mut self, template: &str, context: &TemplateContext) -> TemplateResult<RenderTrace> {
+        self.keeping_macros(|engine| {
+            let output = engine.render_string(template, context)?;
+            let template = trim_block_tags(template, engine.trim_blocks, engine.lstrip_blocks);
+            engine.trace_render(&template, output, context)
+        })
+    }
+    
+    /// Run `f`, then forget the macros the renders in it defined
+    #[cfg(feature = "template-macros")]
+    fn keeping_macros<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        let saved_macros = self.macros.clone();
+        let result = f(self);
+        self.macros = saved_macros;
+        result
+    }
+    
+    #[cfg(not(feature = "template-macros"))]
+    fn keeping_macros<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+        f(self)
+    }
+    
+    /// Trace the segments of a template that rendered to `output`; macros
+    /// defined by the template are registered by that render
+    fn trace_render(&mut self, source: &str, output: String, context: &TemplateContext) -> TemplateResult<RenderTrace> {
+        let mut trace = RenderTrace::new(output);
+        if source.contains("{{extends ") {
+            trace.segments.push(RenderSegment {
+                kind: SegmentKind::Layout,
+                source: source.to_string(),
+                line: 1,
+                column: 1,
+                scope: Vec::new(),
+                output: trace.output.clone(),
+            });
+        } else {
+            self.trace_range(source, 0..source.len(), context, None, &mut Vec::new(), &mut trace)?;
+        }
+        Ok(trace)
+    }
+    
+    /// Render `source[range]` piece by piece into `trace`: text as is, output
+    /// tags one at a time, loop bodies once per item and conditional bodies
+    /// when their condition holds
+    fn trace_range(
+        &mut self,
+        source: &str,
+        range: std::ops::Range<usize>,
+        context: &TemplateContext,
+        previous: Option<&TemplateContext>,
+        scope: &mut Vec<SegmentScope>,
+        trace: &mut RenderTrace,
+    ) -> TemplateResult<()> {
+        let end = range.end;
+        let mut pos = range.start;
+        
+        while pos < end {
+            let Some(relative_start) = source[pos..end].find("{{") else {
+                push_text_segment(source, pos..end, scope, trace);
+                break;
+            };
+            let tag_start = pos + relative_start;
+            push_text_segment(source, pos..tag_start, scope, trace);
+            
+            let tag_end = if source[tag_start..end].starts_with("{{#--") {
+                source[tag_start..end].find("--}}").map(|close| tag_start + close + 4)
+            } else {
+                source[tag_start..end].find("}}").map(|close| tag_start + close + 2)
+            };
+            let Some(tag_end) = tag_end else {
+                push_text_segment(source, tag_start..end, scope, trace);
+                break;
+            };
+            let directive = source[tag_start + 2..tag_end - 2].trim();
+            let (line, _) = find_line_column(source, tag_start);
+            pos = tag_end;
+            
+            if directive.starts_with('!') || directive.starts_with('/') {
+                continue;
+            }
+            if let Some(loop_def) = directive.strip_prefix("for ") {
+                let body_end = tag_end + self.find_matching_for_end(&source[tag_end..end])?;
+                pos = body_end + "{{/for}}".len();
+                let (item_var, array_var) = loop_def.split_once(" in ")
+                    .map(|(item, array)| (item.trim(), array.trim()))
+                    .ok_or_else(|| TemplateError::Parse("Invalid for loop syntax".to_string()))?;
+                let (body, empty) = split_loop_empty(&source[tag_end..body_end]);
+                let array = self.loop_source(array_var, context)?;
+                if let Some(array) = &array {
+                    trace.record_variable(array_var, self.surfaced_value(array_var, array, |array| self.template_value_to_string(array)));
+                }
+                let items = match array.as_deref() {
+                    Some(TemplateValue::Array(items)) => items.as_slice(),
+                    _ => &[],
+                };
+                // Nothing to iterate: the {{empty}} section renders instead
+                if items.is_empty() {
+                    if let Some(empty) = empty {
+                        scope.push(SegmentScope { directive: "empty".to_string(), line, iteration: None });
+                        self.trace_range(source, body_end - empty.len()..body_end, context, previous, scope, trace)?;
+                        scope.pop();
+                    }
+                    continue;
+                }
+                let body_end = tag_end + body.len();
+                
+                let shared_context = Arc::new(context.clone());
+                let mut previous_context: Option<TemplateContext> = None;
+                for (index, item) in items.iter().enumerate() {
+                    let mut loop_context = TemplateContext::layered(Arc::clone(&shared_context));
+                    loop_context.set(item_var, item.clone());
+                    scope.push(SegmentScope { directive: format!("for {}", loop_def.trim()), line, iteration: Some(index + 1) });
+                    self.trace_range(source, tag_end..body_end, &loop_context, previous_context.as_ref(), scope, trace)?;
+                    scope.pop();
+                    previous_context = Some(loop_context);
+                }
+                continue;
+            }
+            if directive == "try" {
+                let boundary = find_error_boundaries(&source[tag_start..end])?.into_iter().next()
+                    .ok_or_else(|| TemplateError::Parse("Missing {{/try}} directive".to_string()))?;
+                pos = tag_start + boundary.end;
+                let (segment_count, scope_depth) = (trace.segments.len(), scope.len());
+                scope.push(SegmentScope { directive: "try".to_string(), line, iteration: None });
+                let attempt = self.trace_range(source, tag_start + boundary.body.start..tag_start + boundary.body.end, context, previous, scope, trace);
+                scope.truncate(scope_depth);
+                match attempt {
+                    Ok(()) => {}
+                    Err(error) if !is_rescuable(&error) => return Err(error),
+                    Err(error) => {
+                        // The failed body leaves no segments; the rescue body replaces them
+                        trace.segments.truncate(segment_count);
+                        let rescue_context = rescue_context(context, &error);
+                        self.record_rescued(error);
+                        if let Some(rescue) = boundary.rescue {
+                            scope.push(SegmentScope { directive: "rescue".to_string(), line, iteration: None });
+                            self.trace_range(source, tag_start + rescue.start..tag_start + rescue.end, &rescue_context, previous, scope, trace)?;
+                            scope.pop();
+                        }
+                    }
+                }
+                continue;
+            }
+            if let Some(keyword) = ["if", "unless", "ifchanged", "macro", "block", "capture"].into_iter().find(|keyword| {
+                directive.strip_prefix(keyword).is_some_and(|rest| rest.starts_with(char::is_whitespace))
+            }) {
+                let body_end = find_block_end(&source[tag_end..end], keyword)
+                    .map(|offset| tag_end + offset)
+                    .ok_or_else(|| TemplateError::Parse(format!("Missing {{{{/{}}}}} directive", keyword)))?;
+                pos = body_end + keyword.len() + 5;
+                match keyword {
+                    "if" | "unless" | "ifchanged" => {
+                        let argument = directive[keyword.len()..].trim();
+                        let renders = match keyword {
+                            "if" => self.evaluate_condition(argument, context)?,
+                            "unless" => !self.evaluate_condition(argument, context)?,
+                            _ => self.value_changed(argument, context, previous)?,
+                        };
+                        if let Some(branch) = self.taken_branch(source, tag_end..body_end, keyword, renders, context)? {
+                            scope.push(SegmentScope { directive: format!("{} {}", keyword, argument), line, iteration: None });
+                            self.trace_range(source, branch, context, previous, scope, trace)?;
+                            scope.pop();
+                        }
+                    }
+                    "block" => self.trace_range(source, tag_end..body_end, context, previous, scope, trace)?,
+                    // Macro definitions produce no output, captures none where they are written
+                    _ => {}
+                }
+                continue;
+            }
+            
+            let tag = &source[tag_start..tag_end];
+            let output = self.render_string(tag, context)?;
+            let kind = if directive.starts_with("include ") { SegmentKind::Include } else { SegmentKind::Output };
+            if kind == SegmentKind::Output {
+                let path = directive.trim_start_matches('&').split('|').next().unwrap_or_default().trim();
+                if is_variable_path(path) {
+                    let value = self.resolve_variable_from_context(path, context);
+                    trace.record_variable(path, self.surfaced_value(path, &value, |value| self.template_value_to_string(value)));
+                }
+            }
+            let (line, column) = find_line_column(source, tag_start);
+            trace.segments.push(RenderSegment { kind, source: tag.to_string(), line, column, scope: scope.clone(), output });
+        }
+        Ok(())
+    }
+    
+    // =============================================================================
+    // v0.6.0 HTML Sanitizer
+    // =============================================================================
+    
+    /// Choose what the `sanitize` filter keeps.
+    /// 
+    /// `{{post.body|sanitize}}` keeps the allowed tags and attributes of
+    /// user-written HTML, removes everything else and is not escaped again.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, TemplateContext, SanitizerPolicy};
+    /// 
+    /// let mut engine = TemplateEngine::new(".");
+    /// engine.configure_sanitizer(SanitizerPolicy::default().allow_tag("blockquote"));
+    /// let mut context = TemplateContext::new();
+    /// context.set_string("body", "<blockquote onclick=\"steal()\">Hi <b>there</b></blockquote>");
+    /// assert_eq!(engine.render_string("{{body|sanitize}}", &context)?, "<blockquote>Hi there</blockquote>");
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn configure_sanitizer(&mut self, policy: SanitizerPolicy) {
+        self.sanitizer_policy = policy;
+    }
+    
+    /// Get the policy of the `sanitize` filter
+    pub fn get_sanitizer_policy(&self) -> &SanitizerPolicy {
+        &self.sanitizer_policy
+    }
+    
+    // =============================================================================
+    // v0.6.0 Template Roots
+    // =============================================================================
+    
+    /// Resolve templates from an additional directory, with priority 0.
+    /// 
+    /// The engine's own template directory is the root named `"app"`, also
+    /// with priority 0; among equal priorities it is searched first, then the
+    /// other roots in the order they were added.
+    pub fn add_template_root(&mut self, root: TemplateRoot) {
+        self.add_template_root_with_priority(root, 0);
+    }
+    
+    /// Resolve templates from an additional directory. Roots with a higher
+    /// priority are searched first and shadow same-named templates of the
+    /// others; `{{include "name" from "root"}}` still reaches a shadowed
+    /// template. Adding a root with an existing name replaces it.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{TemplateEngine, TemplateRoot};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// engine.add_template_root_with_priority(
+    ///     TemplateRoot::new("vendor", "vendor/design-system").with_namespace("ds"),
+    ///     -10,
+    /// );
+    /// 
+    /// // templates/ds/button.html if it exists, vendor/design-system/button.html otherwise
+    /// println!("{}", engine.explain_resolution("ds::button.html"));
+    /// ```
+    pub fn add_template_root_with_priority(&mut self, root: TemplateRoot, priority: i32) {
+        self.template_roots.retain(|(existing, _)| existing.name() != root.name());
+        self.template_roots.push((root, priority));
+        
+        // Any cached template may now resolve to another file
+        self.cache.clear();
+        self.bytecode_cache.clear();
+        self.resolved_paths.clear();
+    }
+    
+    /// List the roots searched for a template, in order, and which file wins
+    pub fn explain_resolution(&self, name: &str) -> ResolutionTrace {
+        self.trace_resolution(name, None)
+    }
+    
+    fn trace_resolution(&self, name: &str, from: Option<&str>) -> ResolutionTrace {
+        let (namespace, relative_name) = split_namespace(name);
+        let app_root = TemplateRoot::new(APP_ROOT_NAME, &self.template_dir);
+        let mut roots: Vec<(&TemplateRoot, i32)> = std::iter::once((&app_root, 0))
+            .chain(self.template_roots.iter().map(|(root, priority)| (root, *priority)))
+            .collect();
+        // Stable, so equal priorities keep registration order
+        roots.sort_by_key(|(_, priority)| std::cmp::Reverse(*priority));
+        
+        let candidates = roots.into_iter()
+            .filter(|(root, _)| from.map_or(true, |from| root.name() == from))
+            .filter_map(|(root, priority)| {
+                let path = root.candidate_path(namespace, relative_name)?;
+                Some(ResolutionCandidate {
+                    root: root.name().to_string(),
+                    priority,
+                    exists: path.is_file(),
+                    path,
+                })
+            })
+            .collect();
+        
+        ResolutionTrace {
+            name: name.to_string(),
+            namespace: namespace.map(str::to_string),
+            from: from.map(str::to_string),
+            candidates,
+        }
+    }
+    
+    /// File a template name resolves to; the first candidate when none exists
+    fn resolve_template_path(&self, name: &str, from: Option<&str>) -> TemplateResult<PathBuf> {
+        if let Some(from) = from {
+            let known = from == APP_ROOT_NAME || self.template_roots.iter().any(|(root, _)| root.name() == from);
+            if !known {
+                return Err(TemplateError::Template(format!("Unknown template root '{}' in include of '{}'", from, name)));
+            }
+        }
+        
+        let trace = self.trace_resolution(name, from);
+        let Some(winner) = trace.winner() else {
+            return Ok(trace.candidates.into_iter().next()
+                .map(|candidate| candidate.path)
+                .unwrap_or_else(|| Path::new(&self.template_dir).join(name)));
+        };
+        
+        // Symlinks must not lead out of the root
+        let root_dir = if winner.root == APP_ROOT_NAME {
+            Path::new(&self.template_dir)
+        } else {
+            self.template_roots.iter()
+                .find(|(root, _)| root.name() == winner.root)
+                .map(|(root, _)| root.dir())
+                .unwrap_or(Path::new(&self.template_dir))
+        };
+        let inside_root = match (root_dir.canonicalize(), winner.path.canonicalize()) {
+            (Ok(root_dir), Ok(path)) => path.starts_with(root_dir),
+            _ => false,
+        };
+        if !inside_root {
+            return Err(PathViolation::new(PathRule::OutsideRoot, name).into());
+        }
+        Ok(winner.path.clone())
+    }
+    
+    // =============================================================================
+    // v0.6.0 Precompiled Templates
+    // =============================================================================
+    
+    /// Check template syntax without rendering: unclosed tags and blocks,
+    /// mismatched closing tags, malformed directives and unknown filters.
+    /// 
+    /// This is the check `runic_template!` runs at compile time. Filters
+    /// registered with `register_filter` count as known here.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::TemplateEngine;
+    /// 
+    /// let engine = TemplateEngine::new("templates");
+    /// assert!(engine.check_syntax("Hello {{name|upper}}").is_ok());
+    /// assert!(engine.check_syntax("{{if ready}}Go").is_err());
+    /// ```
+    pub fn check_syntax(&self, template: &str) -> TemplateResult<()> {
+        let custom_filters: Vec<&str> = self.custom_filters.keys()
+            .chain(self.pipelines.keys())
+            .map(String::as_str)
+            .chain(self.project_config.iter().flat_map(|config| config.filters.iter().map(|filter| filter.name.as_str())))
+            .collect();
+        match parse_template(template, &custom_filters) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(TemplateError::ParseWithLocation {
+                message: error.message,
+                line: error.line,
+                column: error.column,
+                template_name: None,
+                context_lines: extract_context_lines(template, error.line, 2),
+            }),
+        }
+    }
+    
+    /// Render a template checked and compiled by the `runic_template!` macro
+    /// (requires the `macros` feature to create one).
+    /// 
+    /// Templates made only of text and output expressions run straight from
+    /// their instruction list; templates with block directives, includes,
+    /// translations or registered macros go through the regular pipeline.
+    /// The output is the same as `render_string` on the template source.
+    /// 
+    /// # Example
+    /// ```rust,ignore
+    /// use mystical_runic::{runic_template, PrecompiledTemplate, TemplateEngine, TemplateContext};
+    /// 
+    /// const GREETING: PrecompiledTemplate = runic_template!("Hello {{name|upper}}");
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// let mut context = TemplateContext::new();
+    /// context.set_string("name", "world");
+    /// assert_eq!(engine.render_precompiled(&GREETING, &context)?, "Hello WORLD");
+    /// ```
+    pub fn render_precompiled(&mut self, template: &PrecompiledTemplate, context: &TemplateContext) -> TemplateResult<String> {
+        let instructions = template.instructions();
+        // Strict variables locate missing variables in the pipeline's source
+        let needs_pipeline = self.has_macros() || self.strict_variables
+            || instructions.iter().any(|instruction| matches!(instruction, PrecompiledInstruction::Directive(_)));
+        if needs_pipeline {
+            return self.render_string(template.source(), context);
+        }
+        
+        let mut output = String::with_capacity(template.source().len());
+        for instruction in instructions {
+            match instruction {
+                PrecompiledInstruction::Text(text) => output.push_str(text),
+                PrecompiledInstruction::Output(expression) => self.write_expression(expression, false, context, &mut output)?,
+                PrecompiledInstruction::RawOutput(expression) => self.write_expression(expression, true, context, &mut output)?,
+                PrecompiledInstruction::Directive(_) => unreachable!("directives are rendered by the pipeline"),
+            }
+        }
+        self.finish_output(&mut output)?;
+        Ok(output)
+    }
+    
+    // =============================================================================
+    // v0.6.0 Raw Output Audit
+    // =============================================================================
+    
+    /// List every unescaped output site (`{{& var}}`, `|safe`, `|markdown`,
+    /// `|highlight`) in the templates of the template directory, with its
+    /// location and whether the expression looks user-controlled.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::TemplateEngine;
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// for site in engine.audit_raw_output()? {
+    ///     println!("{}:{}:{} {} ({})", site.template_name, site.line, site.column, site.expression, site.reason);
+    /// }
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn audit_raw_output(&mut self) -> TemplateResult<Vec<RawOutputSite>> {
+        let mut sites = Vec::new();
+        for template_name in self.list_available_templates()? {
+            let content = self.load_template_arc(&template_name)?;
+            sites.extend(scan_raw_output(&template_name, &content, &self.html_filter_names()));
+        }
+        Ok(sites)
+    }
+    
+    /// Lint every template in the template directory.
+    /// 
+    /// Reports directive structure and unknown filter problems, plus an
+    /// `info` diagnostic (code `raw-output`) for each unescaped output site.
+    /// Variables are not checked since there is no context.
+    #[cfg(feature = "lsp")]
+    pub fn lint_all(&mut self) -> TemplateResult<BTreeMap<String, Vec<Diagnostic>>> {
+        let mut results = BTreeMap::new();
+        for template_name in self.list_available_templates()? {
+            let content = self.load_template_arc(&template_name)?;
+            let mut diagnostics = self.collect_diagnostics(&content, None);
+            
+            for site in scan_raw_output(&template_name, &content, &self.html_filter_names()) {
+                let mut message = format!("Unescaped output of '{}' ({})", site.expression, site.reason);
+                if site.likely_user_controlled {
+                    message.push_str("; the expression looks user-controlled");
+                }
+                diagnostics.push(Diagnostic::new(&message, "info", site.line, site.column).with_code("raw-output"));
+            }
+            
+            results.insert(template_name, diagnostics);
+        }
+        Ok(results)
+    }
+    
+    // =============================================================================
+    // v0.6.0 Asset Requirements
+    // =============================================================================
+    
+    /// Register the tags emitted for an asset required with `{{needs "name"}}`.
+    /// 
+    /// `{{required_assets "css"}}` and `{{required_assets "js"}}` are replaced
+    /// after the whole template tree has rendered, so a layout's `<head>` can
+    /// list what components deeper in the page asked for. Each asset is emitted
+    /// once, in first-needed order. Pass an empty string for a missing kind.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// engine.register_asset(
+    ///     "carousel",
+    ///     "<link rel=\"stylesheet\" href=\"/carousel.css\">",
+    ///     "<script src=\"/carousel.js\"></script>",
+    /// );
+    /// 
+    /// let template = "<head>{{required_assets \"css\"}}</head>{{needs \"carousel\"}}<div class=\"carousel\"></div>";
+    /// let result = engine.render_string(template, &TemplateContext::new()).unwrap();
+    /// assert_eq!(result, "<head><link rel=\"stylesheet\" href=\"/carousel.css\"></head><div class=\"carousel\"></div>");
+    /// ```
+    pub fn register_asset(&mut self, name: &str, css: &str, js: &str) {
+        self.assets.insert(name.to_string(), AssetTags {
+            css: css.to_string(),
+            js: js.to_string(),
+        });
+    }
+    
+    /// Replace the deferred asset markers in a rendered document
+    fn resolve_asset_markers(&self, buf: &mut String) -> TemplateResult<()> {
+        let markers = deferred_markers(buf, self.marker_key, &['+', '@']);
+        
+        // Deduplicated, in first-needed order
+        let mut needed: Vec<&str> = Vec::new();
+        for (_, kind, name) in &markers {
+            if *kind == '+' && !needed.contains(name) {
+                if self.strict_mode && !self.assets.contains_key(*name) {
+                    return Err(TemplateError::Template(format!("Asset '{}' is not registered", name)));
+                }
+                needed.push(name);
+            }
+        }
+        
+        let mut output = String::with_capacity(buf.len());
+        let mut pos = 0;
+        for (range, kind, value) in &markers {
+            output.push_str(&buf[pos..range.start]);
+            pos = range.end;
+            if *kind == '@' {
+                let tags: Vec<&str> = needed.iter()
+                    .filter_map(|name| self.assets.get(*name))
+                    .map(|asset| if *value == "css" { asset.css.as_str() } else { asset.js.as_str() })
+                    .filter(|tag| !tag.is_empty())
+                    .collect();
+                output.push_str(&tags.join("\n"));
+            }
+        }
+        output.push_str(&buf[pos..]);
+        
+        buf.clear();
+        buf.push_str(&output);
+        Ok(())
+    }
+    
+    // =============================================================================
+    // v0.6.0 Nesting Limits
+    // =============================================================================
+    
+    /// Set the maximum nesting depth of `for`/`if`/`macro`/`block` directives.
+    /// Deeper templates fail to render with a located parse error. Defaults to 128.
+    pub fn set_max_nesting_depth(&mut self, depth: usize) {
+        self.max_nesting_depth = depth.max(1);
+    }
+    
+    /// Get the maximum directive nesting depth
+    pub fn get_max_nesting_depth(&self) -> usize {
+        self.max_nesting_depth
+    }
+    
+    /// Set how deep `{{include}}` directives may nest, templates including
+    /// templates that include others. Deeper includes fail the render, as
+    /// circular includes always do. Defaults to 64.
+    pub fn set_max_include_depth(&mut self, depth: usize) {
+        self.max_include_depth = depth.max(1);
+    }
+    
+    /// Get the maximum include depth
+    pub fn get_max_include_depth(&self) -> usize {
+        self.max_include_depth
+    }
+    
+    /// Set how many integers a `{{for i in 1..n}}` range or a `range()`
+    /// call may produce. Longer ranges fail the render instead of filling
+    /// memory. Defaults to 1,000,000.
+    pub fn set_max_range_items(&mut self, items: usize) {
+        self.max_range_items = items;
+    }
+    
+    /// Get the maximum number of range items
+    pub fn get_max_range_items(&self) -> usize {
+        self.max_range_items
+    }
+    
+    /// Reject templates whose directives nest deeper than `max_nesting_depth`.
+    /// 
+    /// A single linear scan, so adversarial input fails fast instead of
+    /// driving the block matchers and the recursive loop renderer.
+    fn check_nesting_depth(&self, template: &str) -> TemplateResult<()> {
+        let mut depth = 0usize;
+        let mut pos = 0;
+        
+        while let Some(relative_start) = template[pos..].find("{{") {
+            let start = pos + relative_start;
+            // Unclosed directives are reported by the stage that processes them
+            let Some(end) = template[start..].find("}}") else { break };
+            pos = start + end + 2;
+            
+            let directive = template[start + 2..start + end].trim_start();
+            if ["for ", "if ", "unless ", "ifchanged ", "macro ", "block "].iter().any(|open| directive.starts_with(open)) {
+                depth += 1;
+                if depth > self.max_nesting_depth {
+                    let (line, column) = find_line_column(template, start);
+                    return Err(TemplateError::ParseWithLocation {
+                        message: format!(
+                            "Maximum nesting depth of {} exceeded by '{{{{{}}}}}'",
+                            self.max_nesting_depth, directive.trim_end()
+                        ),
+                        line,
+                        column,
+                        template_name: None,
+                        context_lines: extract_context_lines(template, line, 2),
+                    });
+                }
+            } else if ["/for", "/if", "/unless", "/macro", "/block"].iter().any(|close| directive.starts_with(close)) {
+                depth = depth.saturating_sub(1);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    // =============================================================================
+    // v0.6.0 Multi-document Rendering
+    // =============================================================================
+    
+    /// Set the maximum number of worker threads used by
+    /// [`render_many`](Self::render_many). Defaults to the available parallelism.
+    pub fn set_render_workers(&mut self, workers: usize) {
+        self.render_workers = workers.max(1);
+    }
+    
+    /// Get the maximum number of worker threads used by `render_many`
+    pub fn get_render_workers(&self) -> usize {
+        self.render_workers
+    }
+    
+    /// Render one template against many contexts (mail-merge style).
+    /// 
+    /// The template and its layouts are loaded and resolved once, each worker
+    /// reuses a single output buffer, and the contexts are split across at most
+    /// [`get_render_workers`](Self::get_render_workers) threads. Results keep
+    /// the order of `contexts`; a failing context only fails its own entry.
+    /// The outer error is reserved for problems loading the template itself.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// let recipients: Vec<TemplateContext> = (0..3).map(|i| {
+    ///     let mut context = TemplateContext::new();
+    ///     context.set_number("id", i);
+    ///     context
+    /// }).collect();
+    /// 
+    /// for result in engine.render_many("letter.html", &recipients)? {
+    ///     println!("{}", result?);
+    /// }
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn render_many(&mut self, template_name: &str, contexts: &[TemplateContext]) -> TemplateResult<Vec<TemplateResult<String>>> {
+        let source = self.prepare_shared_source(template_name)?;
+        let workers = self.render_workers.min(contexts.len()).max(1);
+        if workers == 1 {
+            return Ok(self.render_batch(template_name, source.as_ref(), contexts));
+        }
+        
+        let chunk_size = contexts.len().div_ceil(workers);
+        let engine: &TemplateEngine = self;
+        let source = source.as_ref();
+        let results = thread::scope(|scope| {
+            let handles: Vec<_> = contexts.chunks(chunk_size).map(|chunk| {
+                let mut worker = engine.clone();
+                let handle = scope.spawn(move || worker.render_batch(template_name, source, chunk));
+                (handle, chunk.len())
+            }).collect();
+            
+            let mut results = Vec::with_capacity(contexts.len());
+            for (handle, len) in handles {
+                match handle.join() {
+                    Ok(batch) => results.extend(batch),
+                    Err(_) => results.extend((0..len).map(|_| Err(TemplateError::Render("Thread panic".to_string())))),
+                }
+            }
+            results
+        });
+        
+        Ok(results)
+    }
+    
+    /// Render one template against contexts from an iterator, on the calling
+    /// thread, with the same load-once and buffer reuse as [`render_many`](Self::render_many).
+    pub fn render_many_iter<I, C>(&mut self, template_name: &str, contexts: I) -> TemplateResult<Vec<TemplateResult<String>>>
+    where
+        I: IntoIterator<Item = C>,
+        C: std::borrow::Borrow<TemplateContext>,
+    {
+        let source = self.prepare_shared_source(template_name)?;
+        let mut buffer = String::new();
+        Ok(contexts.into_iter()
+            .map(|context| self.render_with_buffer(template_name, source.as_ref(), context.borrow(), &mut buffer))
+            .collect())
+    }
+    
+    /// Template source shared by every context, or `None` when the layout is
+    /// chosen per context (`{{extends var}}`)
+    fn prepare_shared_source(&mut self, template_name: &str) -> TemplateResult<Option<Arc<str>>> {
+        let source = self.prepare_template_source(template_name, None)?;
+        #[cfg(feature = "inheritance")]
+        {
+            let mut current = self.layout_processor.templates.get(template_name);
+            while let Some(layout) = current {
+                if layout.extends_variable.is_some() {
+                    return Ok(None);
+                }
+                current = layout.extends.as_ref().and_then(|parent| self.layout_processor.templates.get(parent));
+            }
+        }
+        Ok(Some(source))
+    }
+    
+    fn render_batch(&mut self, template_name: &str, source: Option<&Arc<str>>, contexts: &[TemplateContext]) -> Vec<TemplateResult<String>> {
+        let mut buffer = String::new();
+        contexts.iter()
+            .map(|context| self.render_with_buffer(template_name, source, context, &mut buffer))
+            .collect()
+    }
+    
+    /// Render one context like [`render_into`](Self::render_into), on the
+    /// shared source when there is one
+    fn render_with_buffer(&mut self, template_name: &str, source: Option<&Arc<str>>, context: &TemplateContext, buffer: &mut String) -> TemplateResult<String> {
+        self.render_template_into(template_name, source, context, buffer, None)?;
+        Ok(buffer.as_str().to_owned())
+    }
+    
+    // =============================================================================
+    // v0.6.0 Expression-only Rendering
+    // =============================================================================
+    
+    /// Render an untrusted snippet (e.g. a customer-defined email subject)
+    /// allowing only interpolation, dot paths, allow-listed filters and `{{t "key"}}`.
+    /// 
+    /// Uses [`ExpressionOptions::default`]. Never touches the template directory.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, TemplateContext, TemplateValue};
+    /// use std::collections::HashMap;
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// let mut context = TemplateContext::new();
+    /// let mut order = HashMap::new();
+    /// order.insert("id".to_string(), TemplateValue::Number(42));
+    /// context.set("order", TemplateValue::Object(order));
+    /// 
+    /// let subject = engine.render_expression_only("Order {{order.id}} shipped", &context)?;
+    /// assert_eq!(subject, "Order 42 shipped");
+    /// assert!(engine.render_expression_only("{{include \"secret.html\"}}", &context).is_err());
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn render_expression_only(&self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
+        self.render_expression_only_with(template, context, &ExpressionOptions::default())
+    }
+    
+    /// Render an untrusted snippet in expression-only mode with custom options.
+    /// 
+    /// Any other directive (`if`, `for`, `include`, `macro`, `extends`, raw
+    /// output, helper calls, ...) or a filter outside the allow-list is rejected
+    /// with [`TemplateError::Security`] naming the construct and its position.
+    /// Output longer than `max_output_length` fails with
+    /// [`TemplateError::ResourceExhaustion`].
+    pub fn render_expression_only_with(&self, template: &str, context: &TemplateContext, options: &ExpressionOptions) -> TemplateResult<String> {
+        self.render_expression_snippet(template, context, options, true)
+    }
+    
+    fn render_expression_snippet(&self, template: &str, context: &TemplateContext, options: &ExpressionOptions, allow_translations: bool) -> TemplateResult<String> {
+        let mut output = String::new();
+        let mut pos = 0;
+        
+        while let Some(relative_start) = template[pos..].find("{{") {
+            let start = pos + relative_start;
+            output.push_str(&template[pos..start]);
+            
+            let end = template[start..].find("}}")
+                .ok_or_else(|| {
+                    let (line, column) = find_line_column(template, start);
+                    TemplateError::Parse(format!("Unclosed directive at line {}, column {}", line, column))
+                })?;
+            let directive = template[start + 2..start + end].trim();
+            let forbidden = |construct: &str| {
+                let (line, column) = find_line_column(template, start);
+                TemplateError::Security(format!(
+                    "{} is not allowed in expression-only mode: '{{{{{}}}}}' at line {}, column {}",
+                    construct, directive, line, column
+                ))
+            };
+            
+            if directive.starts_with('!') {
+                // Plain comments render nothing
+            } else if let Some(args) = directive.strip_prefix("t ") {
+                if !allow_translations {
+                    return Err(forbidden("Nested translation"));
+                }
+                let key = args.split_whitespace().next().unwrap_or_default()
+                    .trim_matches('"').trim_matches('\'');
+                #[cfg(feature = "i18n")]
+                output.push_str(&self.render_expression_snippet(&self.get_translation(key), context, options, false)?);
+                #[cfg(not(feature = "i18n"))]
+                return Err(feature_disabled(&format!("{{{{t \"{}\"}}}}", key), "i18n"));
+            } else {
+                if !is_variable_path(directive.split('|').next().unwrap_or_default().trim()) {
+                    return Err(forbidden(&forbidden_construct(directive)));
+                }
+                for filter_expr in directive.split('|').skip(1) {
+                    let filter_name = filter_expr.split(':').next().unwrap_or_default().trim();
+                    if !options.allowed_filters.iter().any(|allowed| allowed == filter_name) {
+                        return Err(forbidden(&format!("Filter '{}'", filter_name)));
+                    }
+                }
+                
+                let value = self.get_variable_value(directive, context)?;
+                output.push_str(&html_escape(&value));
+            }
+            
+            if output.len() > options.max_output_length {
+                return Err(TemplateError::ResourceExhaustion {
+                    resource_type: "expression output length".to_string(),
+                    current_usage: output.len() as u64,
+                    max_allowed: options.max_output_length as u64,
+                });
+            }
+            pos = start + end + 2;
+        }
+        
+        output.push_str(&template[pos..]);
+        if output.len() > options.max_output_length {
+            return Err(TemplateError::ResourceExhaustion {
+                resource_type: "expression output length".to_string(),
+                current_usage: output.len() as u64,
+                max_allowed: options.max_output_length as u64,
+            });
+        }
+        Ok(output)
+    }
+    
+    // =============================================================================
+    // v0.6.0 Context Usage
+    // =============================================================================
+    
+    /// Statically list the context keys and dot paths a template references,
+    /// following quoted includes and parent layouts.
+    /// 
+    /// Loop variables and macro parameters are not reported. Dynamic
+    /// include/extends targets can't be followed statically; use
+    /// [`render_with_usage`](Self::render_with_usage) to capture those.
+    /// 
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{TemplateEngine, TemplateContext};
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// let usage = engine.analyze_context_usage("page.html")?;
+    /// let context = TemplateContext::new();
+    /// let cache_key = usage.cache_key(&context);
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn analyze_context_usage(&mut self, template_name: &str) -> TemplateResult<ContextUsage> {
+        let mut usage = ContextUsage::new();
+        let mut pending = vec![template_name.to_string()];
+        let mut visited = HashSet::new();
+        
+        while let Some(name) = pending.pop() {
+            if !visited.insert(name.clone()) {
+                continue;
+            }
+            let content = self.load_template_arc(&name)?;
+            let scan = scan_template(&content);
+            usage.merge(&scan.usage);
+            pending.extend(scan.includes);
+            
+            #[cfg(feature = "inheritance")]
+            if let Some(parent_name) = self.layout_processor.parse_template(&name, Arc::clone(&content))?.extends.clone() {
+                pending.push(parent_name);
+            }
+        }
+        
+        Ok(usage)
+    }
+    
+    /// Render a template and record the context paths actually read,
+    /// including those reached through dynamic includes and layouts.
+    /// 
+    /// Only paths rooted at keys present in `context` are reported.
+    pub fn render_with_usage(&mut self, template_name: &str, context: &TemplateContext) -> TemplateResult<(String, ContextUsage)> {
+        let reads = Arc::new(Mutex::new(ContextUsage::new()));
+        let previous = self.context_reads.replace(Arc::clone(&reads));
+        let result = self.render(template_name, context);
+        self.context_reads = previous;
+        let output = result?;
+        
+        let recorded = reads.lock().map(|usage| usage.clone()).unwrap_or_default();
+        let mut usage = ContextUsage::new();
+        for path in &recorded.paths {
+            let root = path.split('.').next().unwrap_or_default();
+            if context.get(root).is_some() {
+                usage.add_path(path);
+            }
+        }
+        
+        Ok((output, usage))
+    }
+    
+    /// Note a context read while `render_with_usage` is recording
+    fn record_read(&self, path: &str) {
+        if let Some(reads) = &self.context_reads {
+            if let Ok(mut usage) = reads.lock() {
+                usage.add_path(path.trim());
+            }
+        }
+    }
+    
+    // =============================================================================
+    // v0.6.0 Cache Warmup
+    // =============================================================================
+    
+    /// Load and validate every template in the template directory ahead of time.
+    /// 
+    /// Each `.html`/`.htm` file (subdirectories included) is loaded into the
+    /// template cache, its inheritance chain is parsed and resolved, and it is
+    /// compiled to bytecode (stored in the bytecode cache when enabled).
+    /// Templates that fail are recorded in the report; the rest are still warmed.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::TemplateEngine;
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// let report = engine.warm_up()?;
+    /// for (template, error) in &report.failed_templates {
+    ///     eprintln!("{} failed to warm up: {}", template, error);
+    /// }
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn warm_up(&mut self) -> TemplateResult<WarmupReport> {
+        self.warm_up_matching(None)
+    }
+    
+    /// Warm up only the templates matching `pattern`.
+    /// 
+    /// A pattern containing `*` is treated as a glob (`*` matches any run of
+    /// characters, including `/`); otherwise it is a name prefix such as `"emails/"`.
+    pub fn warm_up_filtered(&mut self, pattern: &str) -> TemplateResult<WarmupReport> {
+        self.warm_up_matching(Some(pattern))
+    }
+    
+    fn warm_up_matching(&mut self, pattern: Option<&str>) -> TemplateResult<WarmupReport> {
+        let start = Instant::now();
+        let mut report = WarmupReport::new();
+        
+        for template_name in self.list_available_templates()? {
+            if let Some(pattern) = pattern {
+                if !template_matches_pattern(&template_name, pattern) {
+                    continue;
+                }
+            }
+            
+            match self.warm_up_template(&template_name) {
+                Ok(()) => report.templates_processed.push(template_name),
+                Err(e) => report.failed_templates.push((template_name, e.to_string())),
+            }
+        }
+        
+        report.duration = start.elapsed();
+        Ok(report)
+    }
+    
+    /// Load, parse and compile a single template into the caches
+    fn warm_up_template(&mut self, template_name: &str) -> TemplateResult<()> {
+        let content = self.load_template_arc(template_name)?;
+        
+        // Parse and resolve the layout chain so renders find it ready
+        self.prepare_template_source(template_name, None)?;
+        
+        let instructions = self.compiler.compile(&content)?;
+        if self.bytecode_cache_enabled {
+            self.bytecode_cache.insert(
+                template_name.to_string(),
+                CompiledTemplate::new(template_name.to_string(), instructions),
+            );
+        }
+        
+        Ok(())
+    }
+    
+    // =============================================================================
+    // v0.5.1 Advanced Performance Features
+    // =============================================================================
+    
+    /// Enable advanced performance monitoring and metrics collection.
+    /// 
+    /// When enabled, the engine will track:
+    /// - Template compilation times and frequency
+    /// - Rendering performance statistics
+    /// - Memory usage patterns
+    /// - Cache hit/miss ratios
+    /// 
+    /// # Performance Impact
+    /// Monitoring has minimal overhead (~1-2% performance cost)
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::TemplateEngine;
+    /// 
+    /// let mut engine = TemplateEngine::new("templates");
+    /// engine.enable_performance_monitoring();
+    /// 
+    /// // Now all rendering operations will be monitored
+    /// ```
+    pub fn enable_performance_monitoring(&mut self) {
+        self.performance_monitoring_enabled = true;
+        self.memory_usage_tracking = true;
+    }
+    
+    /// Disable performance monitoring to maximize rendering speed.
+    pub fn disable_performance_monitoring(&mut self) {
+        self.performance_monitoring_enabled = false;
+        self.memory_usage_tracking = false;
+        self.compilation_stats.clear();
+        self.render_stats.clear();
+    }
+    
+    /// Check if performance monitoring is enabled
+    pub fn is_performance_monitoring_enabled(&self) -> bool {
+        self.performance_monitoring_enabled
+    }
+    
+    /// Get comprehensive performance statistics.
+    /// 
+    /// Returns detailed metrics about template processing including:
+    /// - Average compilation times
+    /// - Rendering performance per template
+    /// - Cache effectiveness
+    /// - Memory usage trends
+    /// 
+    /// # Returns
+    /// A structured performance report with actionable insights
+    pub fn get_performance_statistics(&self) -> PerformanceReport {
+        let mut report = PerformanceReport::new();
+        
+        // Calculate compilation statistics
+        for (template_name, (compile_count, _last_time)) in &self.compilation_stats {
+            report.add_compilation_stat(template_name.clone(), *compile_count);
+        }
+        
+        // Calculate rendering statistics
+        for (template_name, render_times) in &self.render_stats {
+            if !render_times.is_empty() {
+                let avg_time = render_times.iter().sum::<u64>() / render_times.len() as u64;
+                let min_time = *render_times.iter().min().unwrap_or(&0);
+                let max_time = *render_times.iter().max().unwrap_or(&0);
+                
+                report.add_render_stat(template_name.clone(), avg_time, min_time, max_time, render_times.len());
+            }
+        }
+        
+        // Cache statistics
+        report.cache_size = self.cache.len();
+        report.cache_bytes = self.template_source_bytes();
+        report.bytecode_cache_size = self.bytecode_cache.len();
+        report.bytecode_cache_enabled = self.bytecode_cache_enabled;
+        
+        report
+    }
+    
+    /// Record rendering time for performance monitoring
+    #[allow(dead_code)]
+    fn record_render_time(&mut self, template_name: &str, render_time_nanos: u64) {
+        if self.performance_monitoring_enabled {
+            self.render_stats
+                .entry(template_name.to_string())
+                .or_default()
+                .push(render_time_nanos);
+                
+            // Keep only last 100 measurements to prevent memory bloat
+            let stats = self.render_stats.get_mut(template_name).unwrap();
+            if stats.len() > 100 {
+                stats.drain(0..stats.len() - 100);
+            }
+        }
+    }
+    
+    /// Record compilation statistics
+    #[allow(dead_code)]
+    fn record_compilation(&mut self, template_name: &str) {
+        if self.performance_monitoring_enabled {
+            let entry = self.compilation_stats
+                .entry(template_name.to_string())
+                .or_insert((0, std::time::Instant::now()));
+            entry.0 += 1;
+            entry.1 = std::time::Instant::now();
+        }
+    }
+    
+    /// Optimize cache based on usage patterns.
+    /// 
+    /// Analyzes template usage and optimizes cache allocation:
+    /// - Promotes frequently used templates to bytecode cache
+    /// - Evicts least recently used templates when memory pressure exists
+    /// - Adjusts cache sizes based on usage patterns
+    /// 
+    /// # Returns
+    /// Number of optimizations performed
+    pub fn optimize_cache(&mut self) -> usize {
+        let mut optimizations = 0;
+        
+        // Enable bytecode cache if we have frequent compilation
+        if !self.bytecode_cache_enabled {
+            let frequent_templates: Vec<_> = self.compilation_stats
+                .iter()
+                .filter(|(_, (count, _))| *count >= 5) // Compiled 5+ times
+                .map(|(name, _)| name.clone())
+                .collect();
+                
+            if !frequent_templates.is_empty() {
+                self.enable_bytecode_cache(true);
+                optimizations += 1;
+            }
+        }
+        
+        // Clear old render statistics to free memory
+        if self.render_stats.len() > 50 {
+            // Keep only the 25 most recently used templates
+            let mut templates_by_usage: Vec<_> = self.render_stats
+                .iter()
+                .map(|(name, times)| (name.clone(), times.len()))
+                .collect();
+            
+            templates_by_usage.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            templates_by_usage.truncate(25);
+            
+            let keep_templates: std::collections::HashSet<String> = 
+                templates_by_usage.into_iter().map(|(name, _)| name).collect();
+            
+            self.render_stats.retain(|name, _| keep_templates.contains(name));
+            optimizations += 1;
+        }
+        
+        optimizations
+    }
+}
+
+/// Match a template name against a warm-up filter: a `*` glob or a plain prefix
+fn template_matches_pattern(name: &str, pattern: &str) -> bool {
+    if !pattern.contains('*') {
+        return name.starts_with(pattern);
+    }
+    
+    let segments: Vec<&str> = pattern.split('*').collect();
+    let first = segments[0];
+    let last = segments[segments.len() - 1];
+    if !name.starts_with(first) || name.len() < first.len() + last.len() {
+        return false;
+    }
+    
+    // Match the middle segments greedily left to right, then require the suffix
+    let mut remaining = &name[first.len()..];
+    for segment in &segments[1..segments.len() - 1] {
+        match remaining.find(segment) {
+            Some(pos) => remaining = &remaining[pos + segment.len()..],
+            None => return false,
+        }
+    }
+    remaining.ends_with(last)
+}
+
+/// Escape text so it can be safely embedded inside an HTML comment.
+///
+/// Markup characters are HTML-escaped and every `--` sequence is broken up,
+/// so the content can never terminate the surrounding `<!-- -->`.
+fn escape_html_comment(text: &str) -> String {
+    let mut escaped = html_escape(text);
+    while escaped.contains("--") {
+        escaped = escaped.replace("--", "- -");
+    }
+    escaped
+}
+
+/// Append `text` to `out`, HTML escaped when `escape` is set
+fn write_text(text: &str, escape: bool, out: &mut String) {
+    if escape {
+        html_escape_into(text, out);
+    } else {
+        out.push_str(text);
+    }
+}
+
+/// Render a filter result as text; arrays, objects and null render as empty strings.
+pub(crate) fn filter_value_to_string(value: &TemplateValue) -> String {
+    match value {
+        TemplateValue::String(s) => s.clone(),
+        TemplateValue::Number(n) => n.to_string(),
+        TemplateValue::Float(f) => f.to_string(),
+        TemplateValue::Bool(b) => b.to_string(),
+        TemplateValue::Array(_) | TemplateValue::Object(_) | TemplateValue::Null => String::new(),
+    }
+}
+
+/// Nanoseconds since `started`
+fn elapsed_nanos(started: Instant) -> u64 {
+    started.elapsed().as_nanos() as u64
+}
+
+/// Whether `name` can be written as an HTML attribute name by a form helper
+fn is_attribute_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == ':')
+}
+
+/// Write ` key="value"` escaped; booleans write ` key` or nothing
+fn write_form_attribute(key: &str, value: &TemplateValue, out: &mut String) {
+    match value {
+        TemplateValue::Bool(false) => {}
+        TemplateValue::Bool(true) => {
+            out.push(' ');
+            out.push_str(key);
+        }
+        value => {
+            out.push(' ');
+            out.push_str(key);
+            out.push_str("=\"");
+            html_escape_into(&filter_value_to_string(value), out);
+            out.push('"');
+        }
+    }
+}
+
+/// `select` options as `value`/`label`/`selected` objects; `selected` holds
+/// one value or an array of them, compared as text
+fn form_options(options: Option<&TemplateValue>, selected: Option<&TemplateValue>) -> Vec<TemplateValue> {
+    let selected: Vec<String> = match selected {
+        Some(TemplateValue::Array(values)) => values.iter().map(filter_value_to_string).collect(),
+        Some(value) => vec![filter_value_to_string(value)],
+        None => Vec::new(),
+    };
+    array_items(options).iter()
+        .map(|option| {
+            let (value, label) = match option {
+                TemplateValue::Object(fields) => {
+                    let value = fields.get("value").map(filter_value_to_string).unwrap_or_default();
+                    let label = fields.get("label").map(filter_value_to_string).unwrap_or_else(|| value.clone());
+                    (value, label)
+                }
+                scalar => (filter_value_to_string(scalar), filter_value_to_string(scalar)),
+            };
+            let is_selected = selected.contains(&value);
+            TemplateValue::Object(HashMap::from([
+                ("value".to_string(), TemplateValue::String(value)),
+                ("label".to_string(), TemplateValue::String(label)),
+                ("selected".to_string(), TemplateValue::Bool(is_selected)),
+            ]))
+        })
+        .collect()
+}
+
+/// Items of an array value; nothing for other values
+fn array_items(value: Option<&TemplateValue>) -> &[TemplateValue] {
+    match value {
+        Some(TemplateValue::Array(items)) => items,
+        _ => &[],
+    }
+}
+
+/// Messages of `field` in an object of field -> message or messages, or in
+/// a plain list of messages
+fn field_messages(errors: Option<&TemplateValue>, field: &str) -> Vec<TemplateValue> {
+    let messages = match errors {
+        Some(TemplateValue::Object(fields)) => fields.get(field),
+        errors => errors,
+    };
+    match messages {
+        Some(TemplateValue::Array(messages)) => messages.iter()
+            .filter(|message| !filter_value_to_string(message).is_empty())
+            .cloned()
+            .collect(),
+        Some(TemplateValue::String(message)) if !message.is_empty() => vec![TemplateValue::String(message.clone())],
+        _ => Vec::new(),
+    }
+}
+
+/// Golden-ratio increment of the SplitMix64 sequence
+const SPLITMIX_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// SplitMix64 output for `state`
+pub(crate) fn splitmix64(state: u64) -> u64 {
+    let mut z = state.wrapping_add(SPLITMIX_GAMMA);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// `timeago` text for a timestamp `elapsed` seconds in the past (negative: in the future)
+fn format_time_ago(elapsed: i64) -> String {
+    const UNITS: [(&str, u64); 6] = [
+        ("year", 365 * 86_400),
+        ("month", 30 * 86_400),
+        ("day", 86_400),
+        ("hour", 3_600),
+        ("minute", 60),
+        ("second", 1),
+    ];
+    let seconds = elapsed.unsigned_abs();
+    if seconds < 10 {
+        return "just now".to_string();
+    }
+    let (unit, length) = UNITS.iter().find(|(_, length)| seconds >= *length).copied().unwrap_or(UNITS[5]);
+    let count = seconds / length;
+    let plural = if count == 1 { "" } else { "s" };
+    if elapsed > 0 {
+        format!("{} {}{} ago", count, unit, plural)
+    } else {
+        format!("in {} {}{}", count, unit, plural)
+    }
+}
+
+/// `duration` filter: whole seconds as up to `units` consecutive units from
+/// the largest one, skipping zeros, `3d 4h 12m`
+fn format_duration(seconds: f64, units: usize, label: impl Fn(&str, &str) -> String) -> String {
+    const UNITS: [(&str, &str, u128); 4] = [
+        ("days", "d", 86_400),
+        ("hours", "h", 3_600),
+        ("minutes", "m", 60),
+        ("seconds", "s", 1),
+    ];
+    // Float to integer casts saturate, so huge values can't overflow
+    let mut remaining = seconds.abs().trunc() as u128;
+    let sign = if seconds <= -1.0 { "-" } else { "" };
+    let largest = UNITS.iter().position(|(_, _, length)| remaining >= *length).unwrap_or(UNITS.len() - 1);
+    
+    let mut parts = Vec::new();
+    for (key, short, length) in UNITS.iter().skip(largest).take(units.max(1)) {
+        let count = remaining / length;
+        remaining %= length;
+        if count > 0 || (parts.is_empty() && remaining == 0) {
+            parts.push(format!("{}{}", count, label(key, short)));
+        }
+    }
+    format!("{}{}", sign, parts.join(" "))
+}
+
+/// `filesize` filter: bytes in binary (1024) units, `1023 B`, `1.0 KiB`
+fn format_file_size(bytes: f64, decimals: usize, label: impl Fn(&str, &str) -> String) -> String {
+    const UNITS: [(&str, &str); 7] = [
+        ("bytes", "B"), ("kib", "KiB"), ("mib", "MiB"), ("gib", "GiB"),
+        ("tib", "TiB"), ("pib", "PiB"), ("eib", "EiB"),
+    ];
+    let sign = if bytes <= -1.0 { "-" } else { "" };
+    let bytes = bytes.abs().trunc();
+    if bytes < 1024.0 {
+        return format!("{}{} {}", sign, bytes, label(UNITS[0].0, UNITS[0].1));
+    }
+    
+    let mut unit = 0;
+    let mut scaled = bytes;
+    let factor = 10_f64.powi(decimals.min(15) as i32);
+    // Move up while the value, once rounded, would reach 1024 (`1024.0 KiB`)
+    while unit < UNITS.len() - 1 && (scaled * factor).round() / factor >= 1024.0 {
+        scaled /= 1024.0;
+        unit += 1;
+    }
+    format!("{}{:.*} {}", sign, decimals, scaled, label(UNITS[unit].0, UNITS[unit].1))
+}
+
+/// Coerce a value to an integer, truncating fractional parts.
+fn coerce_to_int(value: &TemplateValue) -> Option<i64> {
+    let float_to_int = |f: f64| {
+        let truncated = f.trunc();
+        (truncated.is_finite() && truncated >= i64::MIN as f64 && truncated <= i64::MAX as f64)
+            .then_some(truncated as i64)
+    };
+    match value {
+        TemplateValue::Number(n) => Some(*n),
+        TemplateValue::Float(f) => float_to_int(*f),
+        TemplateValue::Bool(b) => Some(i64::from(*b)),
+        TemplateValue::String(s) => {
+            let s = s.trim();
+            s.parse::<i64>().ok().or_else(|| s.parse::<f64>().ok().and_then(float_to_int))
+        }
+        TemplateValue::Array(_) | TemplateValue::Object(_) | TemplateValue::Null => None,
+    }
+}
+
+/// A number read from a value for comparisons
+#[derive(Clone, Copy)]
+enum ComparableNumber {
+    Int(i64),
+    Float(f64),
+}
+
+impl ComparableNumber {
+    /// Integers compare exactly, anything involving a float as floats
+    fn compare(self, other: Self) -> Option<cmp::Ordering> {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => Some(a.cmp(&b)),
+            (a, b) => a.as_float().partial_cmp(&b.as_float()),
+        }
+    }
+    
+    fn as_float(self) -> f64 {
+        match self {
+            Self::Int(n) => n as f64,
+            Self::Float(f) => f,
+        }
+    }
+}
+
+/// Numbers, floats and strings holding a finite number, for comparisons
+fn comparable_number(value: &TemplateValue) -> Option<ComparableNumber> {
+    match value {
+        TemplateValue::Number(n) => Some(ComparableNumber::Int(*n)),
+        TemplateValue::Float(f) => Some(ComparableNumber::Float(*f)),
+        TemplateValue::String(s) => {
+            let s = s.trim();
+            s.parse::<i64>().ok().map(ComparableNumber::Int)
+                .or_else(|| s.parse::<f64>().ok().filter(|f| f.is_finite()).map(ComparableNumber::Float))
+        }
+        _ => None,
+    }
+}
+
+/// The value at the `key` dot path of an item, `""` when it has none
+fn item_key<'v>(item: &'v TemplateValue, key: Option<&[&str]>) -> &'v TemplateValue {
+    static MISSING: TemplateValue = TemplateValue::String(String::new());
+    match key {
+        Some(path) => lookup_nested(item, path).unwrap_or(&MISSING),
+        None => item,
+    }
+}
+
+/// Order of the `sort` filter: numbers and numeric strings first, by value,
+/// then other strings by text, then every other value in its original order
+fn sort_order(left: &TemplateValue, right: &TemplateValue) -> cmp::Ordering {
+    let rank = |value: &TemplateValue| match value {
+        _ if comparable_number(value).is_some() => 0,
+        TemplateValue::String(_) => 1,
+        _ => 2,
+    };
+    match (comparable_number(left), comparable_number(right), left, right) {
+        (Some(ComparableNumber::Int(a)), Some(ComparableNumber::Int(b)), ..) => a.cmp(&b),
+        (Some(a), Some(b), ..) => a.as_float().total_cmp(&b.as_float()),
+        (None, None, TemplateValue::String(a), TemplateValue::String(b)) => a.cmp(b),
+        _ => rank(left).cmp(&rank(right)),
+    }
+}
+
+/// Kind of a value in comparison errors
+fn comparison_kind(value: &TemplateValue) -> &'static str {
+    match value {
+        TemplateValue::String(s) if s.is_empty() => "an empty or missing value",
+        TemplateValue::String(_) => "a string",
+        TemplateValue::Number(_) | TemplateValue::Float(_) => "a number",
+        TemplateValue::Bool(_) => "a boolean",
+        TemplateValue::Array(_) => "an array",
+        TemplateValue::Object(_) => "an object",
+        TemplateValue::Null => "null",
+    }
+}
+
+/// Coerce a value to a finite float.
+pub(crate) fn coerce_to_float(value: &TemplateValue) -> Option<f64> {
+    let result = match value {
+        TemplateValue::Number(n) => Some(*n as f64),
+        TemplateValue::Float(f) => Some(*f),
+        TemplateValue::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+        TemplateValue::String(s) => s.trim().parse::<f64>().ok(),
+        TemplateValue::Array(_) | TemplateValue::Object(_) | TemplateValue::Null => None,
+    };
+    result.filter(|f| f.is_finite())
+}
+
+/// How bare boolean values are rendered, see [`TemplateEngine::set_bool_format`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum BoolFormat {
+    /// `true` / `false` (default)
+    #[default]
+    TrueFalse,
+    /// "Yes" / "No", or the `yes` / `no` translations of the current locale
+    YesNo,
+    /// Render nothing
+    Empty,
+    /// Custom words for true and false
+    Custom(String, String),
+}
+
+/// Kinds of values without an obvious text form, see [`TemplateEngine::set_value_formatter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueKind {
+    /// `TemplateValue::Array`
+    Array,
+    /// `TemplateValue::Object`
+    Object,
+    /// `TemplateValue::Bool`
+    Bool,
+    /// A missing variable or property
+    Null,
+}
+
+impl fmt::Display for ValueKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Array => "array",
+            Self::Object => "object",
+            Self::Bool => "boolean",
+            Self::Null => "null",
+        };
+        f.write_str(name)
+    }
+}
+
+/// How values of a [`ValueKind`] are rendered, see [`TemplateEngine::set_value_formatter`].
+#[derive(Debug, Clone)]
+pub enum ValueFormatter {
+    /// Render nothing
+    Empty,
+    /// Compact JSON-like dump: `["a","b"]`, `{"id":1}`, `null`
+    Debug,
+    /// Array items (or object `key: value` pairs, sorted by key) joined by a separator
+    Join(String),
+    /// Custom rendering; missing values are passed as an empty string
+    Custom(fn(&TemplateValue) -> String),
+}
+
+impl ValueFormatter {
+    /// Render a value with this formatter
+    pub fn format(&self, value: &TemplateValue) -> String {
+        match self {
+            Self::Empty => String::new(),
+            Self::Debug => debug_value(value),
+            Self::Join(separator) => match value {
+                TemplateValue::Array(items) => items.iter()
+                    .map(join_item)
+                    .collect::<Vec<_>>()
+                    .join(separator),
+                TemplateValue::Object(object) => {
+                    let mut keys: Vec<&String> = object.keys().collect();
+                    keys.sort();
+                    keys.into_iter()
+                        .map(|key| format!("{}: {}", key, join_item(&object[key])))
+                        .collect::<Vec<_>>()
+                        .join(separator)
+                }
+                scalar => filter_value_to_string(scalar),
+            },
+            Self::Custom(format) => format(value),
+        }
+    }
+    
+    /// Render a missing value with this formatter
+    fn format_missing(&self) -> String {
+        match self {
+            Self::Debug => "null".to_string(),
+            Self::Custom(format) => format(&TemplateValue::String(String::new())),
+            Self::Empty | Self::Join(_) => String::new(),
+        }
+    }
+}
+
+impl From<fn(&TemplateValue) -> String> for ValueFormatter {
+    fn from(format: fn(&TemplateValue) -> String) -> Self {
+        Self::Custom(format)
+    }
+}
+
+/// Item of a joined array: scalars as text, nested arrays and objects as `Debug`
+fn join_item(value: &TemplateValue) -> String {
+    match value {
+        TemplateValue::Array(_) | TemplateValue::Object(_) => debug_value(value),
+        scalar => filter_value_to_string(scalar),
+    }
+}
+
+/// Compact JSON-like rendering used by [`ValueFormatter::Debug`]
+fn debug_value(value: &TemplateValue) -> String {
+    fn quote(text: &str) -> String {
+        let mut quoted = String::with_capacity(text.len() + 2);
+        quoted.push('"');
+        for c in text.chars() {
+            match c {
+                '"' => quoted.push_str("\\\""),
+                '\\' => quoted.push_str("\\\\"),
+                '\n' => quoted.push_str("\\n"),
+                '\r' => quoted.push_str("\\r"),
+                '\t' => quoted.push_str("\\t"),
+                c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    match value {
+        TemplateValue::String(s) => quote(s),
+        TemplateValue::Array(items) => {
+            format!("[{}]", items.iter().map(debug_value).collect::<Vec<_>>().join(","))
+        }
+        TemplateValue::Object(object) => {
+            let mut keys: Vec<&String> = object.keys().collect();
+            keys.sort();
+            let pairs: Vec<String> = keys.into_iter()
+                .map(|key| format!("{}:{}", quote(key), debug_value(&object[key])))
+                .collect();
+            format!("{{{}}}", pairs.join(","))
+        }
+        scalar => filter_value_to_string(scalar),
+    }
+}
+
+/// Restrictions for [`TemplateEngine::render_expression_only_with`].
+#[derive(Debug, Clone)]
+pub struct ExpressionOptions {
+    /// Filters that snippets may use
+    pub allowed_filters: Vec<String>,
+    /// Maximum rendered length in bytes
+    pub max_output_length: usize,
+}
+
+impl Default for ExpressionOptions {
+    /// Text and number formatting filters only (no HTML-producing filters),
+    /// with output capped at 10 000 bytes.
+    fn default() -> Self {
+        let allowed_filters = [
+            "upper", "lower", "capitalize", "truncate", "strip", "slugify",
+            "currency", "date", "round", "add", "multiply", "divide", "percentage",
+            "duration", "filesize", "percent", "length", "count_chars", "count_bytes", "int", "float", "bool", "string", "yesno", "translate", "t", "default",
+            "join", "first", "last", "slice", "sort", "reverse", "unique", "escape", "e",
+        ];
+        Self {
+            allowed_filters: allowed_filters.iter().map(|name| name.to_string()).collect(),
+            max_output_length: 10_000,
+        }
+    }
+}
+
+/// Result of [`TemplateEngine::warm_up`]: what was cached and what failed.
+#[derive(Debug, Clone)]
+pub struct WarmupReport {
+    /// Templates that were loaded and compiled successfully
+    pub templates_processed: Vec<String>,
+    /// Templates that failed, with the error message for each
+    pub failed_templates: Vec<(String, String)>,
+    /// Total time spent warming up
+    pub duration: Duration,
+}
+
+impl WarmupReport {
+    fn new() -> Self {
+        Self {
+            templates_processed: Vec::new(),
+            failed_templates: Vec::new(),
+            duration: Duration::ZERO,
+        }
+    }
+    
+    /// Whether every template warmed up without errors
+    pub fn is_success(&self) -> bool {
+        self.failed_templates.is_empty()
+    }
+    
+    /// Get human-readable warmup summary
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "🔥 Warmed up {} templates in {:.2}ms ({} failed)\n",
+            self.templates_processed.len(),
+            self.duration.as_secs_f64() * 1000.0,
+            self.failed_templates.len()
+        );
+        for (template, error) in &self.failed_templates {
+            summary.push_str(&format!("  - {}: {}\n", template, error));
+        }
+        summary
+    }
+}
+
+/// Comprehensive performance report with actionable insights.
+#[derive(Debug, Clone)]
+pub struct PerformanceReport {
+    /// Template compilation statistics
+    pub compilation_stats: HashMap<String, u64>,
+    /// Template rendering statistics (template_name -> (avg_ns, min_ns, max_ns, count))
+    pub render_stats: HashMap<String, (u64, u64, u64, usize)>,
+    /// Current template cache size
+    pub cache_size: usize,
+    /// Bytes of template source held by the template cache and packs,
+    /// sources shared between entries counted once
+    pub cache_bytes: usize,
+    /// Current bytecode cache size
+    pub bytecode_cache_size: usize,
+    /// Whether bytecode cache is enabled
+    pub bytecode_cache_enabled: bool,
+}
+
+impl PerformanceReport {
+    fn new() -> Self {
+        Self {
+            compilation_stats: HashMap::new(),
+            render_stats: HashMap::new(),
+            cache_size: 0,
+            cache_bytes: 0,
+            bytecode_cache_size: 0,
+            bytecode_cache_enabled: false,
+        }
+    }
+    
+    fn add_compilation_stat(&mut self, template_name: String, compile_count: u64) {
+        self.compilation_stats.insert(template_name, compile_count);
+    }
+    
+    fn add_render_stat(&mut self, template_name: String, avg_time: u64, min_time: u64, max_time: u64, count: usize) {
+        self.render_stats.insert(template_name, (avg_time, min_time, max_time, count));
+    }
+    
+    /// Get human-readable performance summary
+    pub fn summary(&self) -> String {
+        let mut summary = String::new();
+        summary.push_str("🔮 Performance Report\n");
+        summary.push_str("==================\n\n");
+        
+        summary.push_str("📊 Cache Status:\n");
+        summary.push_str(&format!("  - Template cache: {} entries, {} bytes\n", self.cache_size, self.cache_bytes));
+        summary.push_str(&format!("  - Bytecode cache: {} entries {}\n", 
+            self.bytecode_cache_size,
+            if self.bytecode_cache_enabled { "✅" } else { "❌ (disabled)" }));
+        summary.push('\n');
+        
+        if !self.render_stats.is_empty() {
+            summary.push_str("⚡ Top Performing Templates:\n");
+            let mut sorted_renders: Vec<_> = self.render_stats.iter().collect();
+            sorted_renders.sort_by(|a, b| a.1.0.cmp(&b.1.0).then_with(|| a.0.cmp(b.0))); // Sort by avg time, then name
+            
+            for (name, (avg_ns, min_ns, max_ns, count)) in sorted_renders.iter().take(5) {
+                summary.push_str(&format!("  - {}: {:.2}ms avg ({:.2}-{:.2}ms, {} renders)\n",
+                    name,
+                    *avg_ns as f64 / 1_000_000.0,
+                    *min_ns as f64 / 1_000_000.0,
+                    *max_ns as f64 / 1_000_000.0,
+                    count
+                ));
+            }
+        }
+        
+        summary
+    }
+}
+
+/// Tags emitted for a registered asset
+#[derive(Debug, Clone)]
+struct AssetTags {
+    css: String,
+    js: String,
+}
+
+/// Random key of the deferred markers of a render
+fn new_marker_key() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+/// Write a deferred asset marker (`+name` for a requirement, `@kind` for a placeholder)
+fn write_asset_marker(out: &mut String, key: u64, kind: char, value: &str) {
+    // Formatting into a String can't fail
+    let _ = fmt::Write::write_fmt(out, format_args!("{}{:016x}{}{}{}", ASSET_MARKER_OPEN, key, kind, value, ASSET_MARKER_CLOSE));
+}
+
+/// Byte range, kind and value of every deferred marker of one of `kinds` in
+/// `text` written with `key`; the marker characters of values are skipped
+fn deferred_markers<'a>(text: &'a str, key: u64, kinds: &[char]) -> Vec<(std::ops::Range<usize>, char, &'a str)> {
+    let mut markers = Vec::new();
+    let mut pos = 0;
+    while let Some(relative_start) = text[pos..].find(ASSET_MARKER_OPEN) {
+        let start = pos + relative_start;
+        let body_start = start + ASSET_MARKER_OPEN.len_utf8();
+        let Some(length) = text[body_start..].find(ASSET_MARKER_CLOSE) else { break };
+        let end = body_start + length + ASSET_MARKER_CLOSE.len_utf8();
+        let keyed = text[body_start..body_start + length].get(16..)
+            .filter(|_| u64::from_str_radix(&text[body_start..body_start + 16], 16).ok() == Some(key));
+        let Some(body) = keyed else {
+            // A forged opening character may precede a real marker
+            pos = body_start;
+            continue;
+        };
+        pos = end;
+        
+        let mut chars = body.chars();
+        if let Some(kind) = chars.next().filter(|kind| kinds.contains(kind)) {
+            markers.push((start..end, kind, chars.as_str()));
+        }
+    }
+    markers
+}
+
+/// Call a registered filter, turning a panic into a [`TemplateError::FilterError`]
+fn call_filter(name: &str, filter: &FilterFunction, value: &str, args: &[&str]) -> TemplateResult<String> {
+    panic::catch_unwind(AssertUnwindSafe(|| filter(value, args))).unwrap_or_else(|payload| {
+        Err(TemplateError::FilterError {
+            filter_name: name.to_string(),
+            value: value.to_string(),
+            message: format!("filter panicked: {}", panic_message(payload.as_ref())),
+        })
+    })
+}
+
+/// Call a registered helper, turning a panic into a [`TemplateError::HelperError`]
+fn call_helper(name: &str, helper: &HelperFunction, args: &[TemplateValue]) -> TemplateResult<TemplateValue> {
+    panic::catch_unwind(AssertUnwindSafe(|| helper(args))).unwrap_or_else(|payload| {
+        Err(TemplateError::HelperError {
+            helper_name: name.to_string(),
+            message: format!("helper panicked: {}", panic_message(payload.as_ref())),
+        })
+    })
+}
+
+/// Message of a caught panic: the `panic!` text, or a placeholder for other payloads
+pub(crate) fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload.downcast_ref::<&str>().copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("non-string panic payload")
+}
+
+/// How to name a directive that isn't a variable path in an error
+fn forbidden_construct(directive: &str) -> String {
+    if directive.contains('(') {
+        "Helper call".to_string()
+    } else if directive.starts_with('&') {
+        "Raw output".to_string()
+    } else {
+        let keyword = directive.split_whitespace().next().unwrap_or_default();
+        format!("Directive '{}'", keyword)
+    }
+}
+
+/// Steps of pipeline `name` with other pipelines expanded in place;
+/// `chain` holds the pipelines being expanded, to report cycles
+fn expand_pipeline(name: &str, definitions: &HashMap<String, String>, chain: &mut Vec<String>) -> TemplateResult<Vec<String>> {
+    if chain.iter().any(|seen| seen == name) {
+        chain.push(name.to_string());
+        return Err(TemplateError::Template(format!("Pipeline cycle: {}", chain.join(" -> "))));
+    }
+    chain.push(name.to_string());
+    
+    let mut steps = Vec::new();
+    for step in definitions[name].split('|').map(str::trim).filter(|step| !step.is_empty()) {
+        let step_name = step.split(':').next().unwrap_or_default().trim();
+        if definitions.contains_key(step_name) {
+            let args: Vec<&str> = step.split(':').skip(1).map(str::trim).collect();
+            for nested in expand_pipeline(step_name, definitions, chain)? {
+                steps.push(substitute_pipeline_arguments(&nested, &args).into_owned());
+            }
+        } else {
+            steps.push(step.to_string());
+        }
+    }
+    
+    chain.pop();
+    Ok(steps)
+}
+
+/// Replace `$1`, `$2`, ... arguments of a filter step with pipeline
+/// arguments, dropping those that weren't given
+fn substitute_pipeline_arguments<'a>(step: &'a str, args: &[&str]) -> Cow<'a, str> {
+    if !step.contains('$') {
+        return Cow::Borrowed(step);
+    }
+    let mut parts = step.split(':');
+    let mut substituted = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        let value = match part.trim().strip_prefix('$').and_then(|index| index.parse::<usize>().ok()) {
+            Some(index) => index.checked_sub(1).and_then(|index| args.get(index)).copied(),
+            None => Some(part),
+        };
+        if let Some(value) = value {
+            substituted.push(':');
+            substituted.push_str(value);
+        }
+    }
+    Cow::Owned(substituted)
+}
+
+/// Apply `trim_blocks` (drop the newline after a block tag) and
+/// `lstrip_blocks` (drop the spaces and tabs from the start of a line to a
+/// block tag) to a template source, and the `{{- tag -}}` whitespace control
+/// markers of any tag, which are removed
+fn trim_block_tags(source: &str, trim: bool, lstrip: bool) -> Cow<'_, str> {
+    let has_markers = source.contains("{{-") || source.contains("-}}");
+    if !(trim || lstrip || has_markers) || !source.contains("{{") {
+        return Cow::Borrowed(source);
+    }
+    
+    let mut out = String::with_capacity(source.len());
+    let mut pos = 0;
+    // Whether `pos` is at the start of a line
+    let mut line_start = true;
+    // Whether the previous tag trims the whitespace after it
+    let mut trim_next = false;
+    while let Some(relative_start) = source[pos..].find("{{") {
+        let start = pos + relative_start;
+        let rest = &source[start..];
+        // Visible comments may contain `}}`, so they close on `--}}`
+        let close = if rest.starts_with("{{#--") || rest.starts_with("{{-#--") {
+            rest.find("--}}").map(|close| close + 2)
+        } else {
+            rest.find("}}")
+        };
+        let Some(close) = close else { break };
+        let end = start + close + 2;
+        let (trim_before, inner, trim_after) = split_trim_markers(&rest[2..close]);
+        let block_tag = is_block_tag(inner.trim());
+        
+        let mut text = &source[pos..start];
+        if trim_next {
+            let trimmed = trim_after_marker(text);
+            line_start |= text[..text.len() - trimmed.len()].ends_with('\n');
+            text = trimmed;
+        }
+        if trim_before {
+            text = text.trim_end();
+        } else if block_tag && lstrip {
+            let indentation_start = match text.rfind('\n') {
+                Some(newline) => Some(newline + 1),
+                None => line_start.then_some(0),
+            };
+            if let Some(indentation_start) = indentation_start.filter(|&i| text[i..].chars().all(|c| c == ' ' || c == '\t')) {
+                text = &text[..indentation_start];
+            }
+        }
+        out.push_str(text);
+        out.push_str("{{");
+        // `{{- for item in items -}}` reads as `{{for item in items}}`
+        out.push_str(if trim_before || trim_after { inner.trim() } else { inner });
+        out.push_str("}}");
+        pos = end;
+        
+        line_start = false;
+        trim_next = trim_after;
+        if block_tag && trim && !trim_after {
+            if let Some(newline) = ["\r\n", "\n"].iter().find(|newline| source[pos..].starts_with(**newline)) {
+                pos += newline.len();
+                line_start = true;
+            }
+        }
+    }
+    let tail = &source[pos..];
+    out.push_str(if trim_next { trim_after_marker(tail) } else { tail });
+    Cow::Owned(out)
+}
+
+/// Tags whose surrounding whitespace `trim_blocks`/`lstrip_blocks` remove
+fn is_block_tag(directive: &str) -> bool {
+    const BLOCK_KEYWORDS: [&str; 11] = [
+        "if", "unless", "ifchanged", "for", "block", "macro", "include", "extends", "t_scope", "try", "capture",
+    ];
+    if let Some(closed) = directive.strip_prefix('/') {
+        return BLOCK_KEYWORDS.contains(&closed.trim());
+    }
+    if directive.starts_with('!') || directive == "try" || directive == "rescue" || else_condition(directive).is_some() {
+        return true;
+    }
+    directive.split_once(char::is_whitespace)
+        .is_some_and(|(keyword, _)| BLOCK_KEYWORDS.contains(&keyword))
+}
+
+/// A loop or `{{ifchanged}}` block found in a section
+struct SectionBlock<'a> {
+    /// Offset of the opening tag
+    start: usize,
+    kind: SectionBlockKind<'a>,
+    body: std::ops::Range<usize>,
+    /// Offset just past the closing tag
+    end: usize,
+}
+
+/// What a [`SectionBlock`] renders
+enum SectionBlockKind<'a> {
+    /// `{{for item in items}}`: the item variable and the items expression
+    Loop(&'a str, &'a str),
+    /// `{{ifchanged expression}}`
+    IfChanged(&'a str),
+}
+
+/// A `{{table}}` column: `key`, `key:Label`, `key|filter` or `key:Label|filter`
+struct TableColumn {
+    key: String,
+    label: String,
+    /// The key followed by the column filters, `price|currency`
+    expression: String,
+}
+
+impl TableColumn {
+    fn parse(spec: &str) -> Self {
+        let spec = spec.trim();
+        let (head, filters) = match spec.split_once('|') {
+            Some((head, filters)) => (head, Some(filters)),
+            None => (spec, None),
+        };
+        let (key, label) = match head.split_once(':') {
+            Some((key, label)) => (key.trim(), label.trim().to_string()),
+            None => (head.trim(), humanize_key(head.trim())),
+        };
+        let expression = match filters {
+            Some(filters) => format!("{}|{}", key, filters),
+            None => key.to_string(),
+        };
+        Self { key: key.to_string(), label, expression }
+    }
+}
+
+/// Header for a column without a label: `first_name` -> `First name`
+fn humanize_key(key: &str) -> String {
+    let words = key.replace(['_', '.'], " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Whitespace separated arguments of a directive, quoted values and calls
+/// kept whole (`a b="c d" e=f(g, h)`)
+pub(crate) fn split_directive_arguments(args: &str) -> Vec<&str> {
+    let mut arguments = Vec::new();
+    let mut start = None;
+    let mut quote = None;
+    let mut depth = 0usize;
+    for (index, c) in args.char_indices() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                start.get_or_insert(index);
+            }
+            None if c == '(' || c == ')' => {
+                depth = if c == '(' { depth + 1 } else { depth.saturating_sub(1) };
+                start.get_or_insert(index);
+            }
+            None if c.is_whitespace() && depth == 0 => {
+                if let Some(begin) = start.take() {
+                    arguments.push(&args[begin..index]);
+                }
+            }
+            None => {
+                start.get_or_insert(index);
+            }
+        }
+    }
+    if let Some(begin) = start {
+        arguments.push(&args[begin..]);
+    }
+    arguments
+}
+
+/// Whitespace separated quoted arguments of a directive (`"a" 'b'`)
+fn quoted_arguments<'a>(args: &'a str, directive: &str) -> TemplateResult<Vec<&'a str>> {
+    args.split_whitespace()
+        .map(|arg| {
+            let quoted = arg.len() >= 2
+                && ((arg.starts_with('"') && arg.ends_with('"')) || (arg.starts_with('\'') && arg.ends_with('\'')));
+            if quoted {
+                Ok(&arg[1..arg.len() - 1])
+            } else {
+                Err(TemplateError::Parse(format!("Expected a quoted name in {{{{{} ...}}}}, found {}", directive, arg)))
+            }
+        })
+        .collect()
+}
+
+/// Follow a property/index path through nested objects and arrays
+fn lookup_nested<'a>(value: &'a TemplateValue, parts: &[&str]) -> Option<&'a TemplateValue> {
+    let mut current = value;
+    for part in parts {
+        current = match current {
+            TemplateValue::Object(obj) => obj.get(*part)?,
+            // Support array indexing with numeric strings
+            TemplateValue::Array(arr) => arr.get(part.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+/// Split a template cache key, `name` or `name from root`
+fn split_cache_key(key: &str) -> (&str, Option<&str>) {
+    match key.rsplit_once(" from ") {
+        Some((name, root)) => (name, Some(root)),
+        None => (key, None),
+    }
+}
+
+/// Modification time and size of a template file
+type FileStamp = (SystemTime, u64);
+
+fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+/// Cache keys of the templates `content` includes or extends by literal name
+fn static_dependencies(content: &str) -> Vec<String> {
+    static_dependency_sites(content).into_iter()
+        .map(|(_, name, from)| match from {
+            Some(root) => format!("{} from {}", name, root),
+            None => name.to_string(),
+        })
+        .collect()
+}
+
+/// Rewrite the quoted include and extends targets of `source` that name a
+/// template of the pack
+fn rename_pack_references(source: &str, pack: &BTreeMap<String, String>, rename: impl Fn(&str) -> String) -> String {
+    let mut renamed = String::with_capacity(source.len());
+    let mut sites = static_dependency_sites(source);
+    sites.sort_by_key(|(offset, _, _)| *offset);
+    let mut pos = 0;
+    for (offset, name, from) in sites {
+        if from.is_some() || !pack.contains_key(name) {
+            continue;
+        }
+        // The target is the first quoted text after the directive keyword
+        let Some(quote) = source[offset..].find(['"', '\'']).map(|quote| offset + quote) else { continue };
+        renamed.push_str(&source[pos..quote + 1]);
+        renamed.push_str(&rename(name));
+        pos = quote + 1 + name.len();
+    }
+    renamed.push_str(&source[pos..]);
+    renamed
+}
+
+/// Quoted `{{include}}`/`{{extends}}` targets with the offset of their
+/// directive and the root they are taken from
+fn static_dependency_sites(content: &str) -> Vec<(usize, &str, Option<&str>)> {
+    let mut sites = Vec::new();
+    for keyword in ["{{include ", "{{extends "] {
+        let mut pos = 0;
+        while let Some(start) = content[pos..].find(keyword) {
+            let start = pos + start;
+            let rest = &content[start + keyword.len()..];
+            let Some(end) = rest.find("}}") else { break };
+            let (target, from) = split_include_source(rest[..end].trim());
+            if target.starts_with(['"', '\'']) {
+                sites.push((start, target.trim_matches('"').trim_matches('\''), from));
+            }
+            pos = start + keyword.len() + end;
+        }
+    }
+    sites
+}
+
+/// Split `"name" from "root"` into the include target and the root name
+pub(crate) fn split_include_source(directive: &str) -> (&str, Option<&str>) {
+    let directive = split_include_scope(directive).0;
+    match directive.rsplit_once(" from ") {
+        Some((target, root)) if root.trim().starts_with(['"', '\'']) => {
+            (target.trim(), Some(root.trim().trim_matches('"').trim_matches('\'')))
+        }
+        _ => (directive, None),
+    }
+}
+
+/// Split `"card.html" with title=product.name only` into the included
+/// template (with its `from` root), the byte range of the `with` arguments
+/// in `directive`, and whether `only` isolates it from the outer context
+pub(crate) fn split_include_scope(directive: &str) -> (&str, Option<std::ops::Range<usize>>, bool) {
+    let (directive, only) = match directive.strip_suffix("only") {
+        Some(rest) if rest.ends_with(char::is_whitespace) => (rest.trim_end(), true),
+        _ => (directive, false),
+    };
+    // A quoted name may itself contain ` with `
+    let name_end = match directive.chars().next() {
+        Some(quote @ ('"' | '\'')) => directive[1..].find(quote).map_or(directive.len(), |end| end + 2),
+        _ => 0,
+    };
+    match directive[name_end..].find(" with ") {
+        Some(offset) => {
+            let with = name_end + offset;
+            (directive[..with].trim_end(), Some(with + " with ".len()..directive.len()), only)
+        }
+        None => (directive, None, only),
+    }
+}
+
+/// Root of an argument's variable when it is not set in `context`;
+/// function calls are taken as resolvable
+fn unset_argument_root<'a>(value: &ArgumentValue<'a>, context: &TemplateContext) -> Option<&'a str> {
+    let ArgumentValue::Expression(expression) = value else { return None };
+    let root = expression.split(['|', '.']).next().unwrap_or_default().trim();
+    (!expression.contains('(') && context.get(root).is_none()).then_some(root)
+}
+
+/// Record literal template text as a segment of a render trace
+fn push_text_segment(source: &str, range: std::ops::Range<usize>, scope: &[SegmentScope], trace: &mut RenderTrace) {
+    if range.is_empty() {
+        return;
+    }
+    let (line, column) = find_line_column(source, range.start);
+    let text = source[range].to_string();
+    trace.segments.push(RenderSegment {
+        kind: SegmentKind::Text,
+        source: text.clone(),
+        line,
+        column,
+        scope: scope.to_vec(),
+        output: text,
+    });
+}
+
+/// Offset of the `{{/keyword}}` closing the block whose body starts `content`
+fn find_block_end(content: &str, keyword: &str) -> Option<usize> {
+    let open = format!("{{{{{} ", keyword);
+    let close = format!("{{{{/{}}}}}", keyword);
+    let mut depth = 1;
+    let mut pos = 0;
+    loop {
+        let next_close = pos + content[pos..].find(&close)?;
+        match content[pos..next_close].find(&open) {
+            Some(next_open) => {
+                depth += 1;
+                pos += next_open + open.len();
+            }
+            None => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(next_close);
+                }
+                pos = next_close + close.len();
+            }
+        }
+    }
+}
+
+/// Start, tag end and condition of the first `{{else}}`, `{{elseif}}` or
+/// `{{else if}}` tag of an `{{if}}` block `body` that is not inside a
+/// nested block
+fn find_branch(body: &str) -> Option<(usize, usize, Option<&str>)> {
+    let mut depth = 0usize;
+    let mut pos = 0;
+    while let Some(relative_start) = body[pos..].find("{{") {
+        let start = pos + relative_start;
+        let rest = &body[start..];
+        if depth == 0 {
+            let branch = rest.find("}}").and_then(|end| else_condition(rest[2..end].trim()).map(|condition| (end + 2, condition)));
+            if let Some((end, condition)) = branch {
+                return Some((start, start + end, condition));
+            }
+        }
+        if ["{{if ", UNLESS_TAG, IFCHANGED_TAG, "{{for "].iter().any(|open| rest.starts_with(open)) {
+            depth += 1;
+        } else if ["{{/if}}", "{{/unless}}", "{{/ifchanged}}", "{{/for}}"].iter().any(|close| rest.starts_with(close)) {
+            depth = depth.saturating_sub(1);
+        }
+        pos = start + 2;
+    }
+    None
+}
+
+/// Start and end of the first `{{set}}` tag or `{{capture}}` block, up to
+/// its `{{/capture}}`, of `template` from `from` on that is not inside a
+/// conditional or a loop
+fn find_assignment(template: &str, from: usize) -> TemplateResult<Option<(usize, usize)>> {
+    let mut depth = 0usize;
+    let mut pos = from;
+    while let Some(relative_start) = template[pos..].find("{{") {
+        let start = pos + relative_start;
+        let rest = &template[start..];
+        if depth == 0 && rest.starts_with(SET_TAG) {
+            let end = rest.find("}}")
+                .ok_or_else(|| TemplateError::Parse("Unclosed set directive".to_string()))?;
+            return Ok(Some((start, start + end + 2)));
+        }
+        if depth == 0 && rest.starts_with(CAPTURE_TAG) {
+            let tag_end = rest.find("}}")
+                .ok_or_else(|| TemplateError::Parse("Unclosed capture directive".to_string()))? + 2;
+            let body_end = find_block_end(&rest[tag_end..], "capture")
+                .ok_or_else(|| TemplateError::Parse("Missing {{/capture}} directive".to_string()))?;
+            return Ok(Some((start, start + tag_end + body_end + CAPTURE_END_TAG.len())));
+        }
+        if ["{{if ", UNLESS_TAG, IFCHANGED_TAG, "{{for "].iter().any(|open| rest.starts_with(open)) {
+            depth += 1;
+        } else if ["{{/if}}", "{{/unless}}", "{{/ifchanged}}", "{{/for}}"].iter().any(|close| rest.starts_with(close)) {
+            depth = depth.saturating_sub(1);
+        }
+        pos = start + 2;
+    }
+    Ok(None)
+}
+
+/// Split a `{{for}}` loop body at its `{{empty}}` tag, when it has one that
+/// is not inside a nested block, into the body rendered for each item and
+/// the section rendered when there is nothing to iterate
+fn split_loop_empty(body: &str) -> (&str, Option<&str>) {
+    let mut depth = 0usize;
+    let mut pos = 0;
+    while let Some(relative_start) = body[pos..].find("{{") {
+        let start = pos + relative_start;
+        let rest = &body[start..];
+        if depth == 0 {
+            if let Some(end) = rest.find("}}").filter(|end| rest[2..*end].trim() == "empty") {
+                return (&body[..start], Some(&body[start + end + 2..]));
+            }
+        }
+        if ["{{if ", UNLESS_TAG, IFCHANGED_TAG, "{{for "].iter().any(|open| rest.starts_with(open)) {
+            depth += 1;
+        } else if ["{{/if}}", "{{/unless}}", "{{/ifchanged}}", "{{/for}}"].iter().any(|close| rest.starts_with(close)) {
+            depth = depth.saturating_sub(1);
+        }
+        pos = start + 2;
+    }
+    (body, None)
+}
+
+/// Condition of an `{{elseif condition}}` or `{{else if condition}}`
+/// directive, `Some(None)` for a plain `{{else}}`; `None` for other directives
+pub(crate) fn else_condition(directive: &str) -> Option<Option<&str>> {
+    if directive == "else" {
+        return Some(None);
+    }
+    directive.strip_prefix("elseif").or_else(|| directive.strip_prefix("else if"))
+        .filter(|condition| condition.starts_with(char::is_whitespace) && !condition.trim().is_empty())
+        .map(|condition| Some(condition.trim()))
+}
+
+/// `condition` without its leading `!` and `not` negations and the
+/// parentheses around it, and whether an odd number of negations was removed
+pub(crate) fn strip_negations(mut condition: &str) -> (&str, bool) {
+    let mut negated = false;
+    loop {
+        condition = unwrap_parens(condition.trim());
+        let rest = match condition.strip_prefix('!') {
+            // `!= value` is a comparison with a missing left side
+            Some(rest) if !rest.starts_with('=') => rest,
+            _ => match condition.strip_prefix("not").filter(|rest| rest.starts_with(|c: char| c.is_whitespace() || c == '(')) {
+                Some(rest) => rest,
+                None => return (condition, negated),
+            },
+        };
+        condition = rest;
+        negated = !negated;
+    }
+}
+
+/// What an `is` test of a condition checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ValueTest {
+    /// `is defined`: the variable or property is present, null included
+    Defined,
+    /// `is null`: the variable or property is present and null
+    Null,
+}
+
+/// Split `user.middle_name is defined` or `avatar is not null` into the
+/// tested expression, the test and whether it is negated
+pub(crate) fn split_value_test(condition: &str) -> Option<(&str, ValueTest, bool)> {
+    let (expr, test) = condition.rsplit_once(" is ")?;
+    let (test, negated) = match test.trim().strip_prefix("not ") {
+        Some(test) => (test.trim(), true),
+        None => (test.trim(), false),
+    };
+    let test = match test {
+        "defined" => ValueTest::Defined,
+        "null" => ValueTest::Null,
+        _ => return None,
+    };
+    Some((expr.trim(), test, negated))
+}
+
+/// `expression` without the parentheses enclosing all of it, as in `(count > 5)`
+fn unwrap_parens(mut expression: &str) -> &str {
+    while let Some(inner) = expression.strip_prefix('(').and_then(|rest| rest.strip_suffix(')')) {
+        let mut depth = 0;
+        let mut quote = None;
+        // `(a) == (b)` closes its first parenthesis before the end
+        let encloses = inner.chars().all(|c| {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'') => quote = Some(c),
+                (None, '(') => depth += 1,
+                (None, ')') => depth -= 1,
+                _ => {}
+            }
+            depth >= 0
+        });
+        if !encloses {
+            break;
+        }
+        expression = inner.trim();
+    }
+    expression
+}
+
+/// Whether the inside of a tag, between `{{` and `}}`, is `{{super}}`
+pub(crate) fn is_super_tag(inner: &str) -> bool {
+    split_trim_markers(inner).1.trim() == "super"
+}
+
+/// Reject `{{super}}` tags that are not inside a `{{block}}`, where there
+/// is no parent block content for them to stand for
+pub(crate) fn check_super_placement(template: &str) -> TemplateResult<()> {
+    if !template.contains("super") {
+        return Ok(());
+    }
+    let mut depth = 0usize;
+    let mut pos = 0;
+    while let Some(relative_start) = template[pos..].find("{{") {
+        let start = pos + relative_start;
+        // Unclosed directives are reported by the stage that processes them
+        let Some(end) = template[start..].find("}}") else { break };
+        pos = start + end + 2;
+        
+        let inner = &template[start + 2..start + end];
+        let directive = split_trim_markers(inner).1.trim();
+        if directive.starts_with("block ") {
+            depth += 1;
+        } else if directive.starts_with("/block") {
+            depth = depth.saturating_sub(1);
+        } else if depth == 0 && is_super_tag(inner) {
+            let (line, column) = find_line_column(template, start);
+            return Err(TemplateError::Parse(format!(
+                "Unexpected {{{{super}}}} outside a {{{{block}}}} at line {}, column {}: {{{{super}}}} stands for the parent block's content",
+                line, column
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Bounds of a `start..end` or `start..=end` loop range, each an integer
+/// literal or a variable path, and whether the end is included
+pub(crate) fn split_range(expression: &str) -> Option<(&str, &str, bool)> {
+    let (start, end) = expression.split_once("..")?;
+    let (end, inclusive) = match end.strip_prefix('=') {
+        Some(end) => (end, true),
+        None => (end, false),
+    };
+    let (start, end) = (start.trim(), end.trim());
+    let is_bound = |bound: &str| bound.parse::<i64>().is_ok() || is_variable_path(bound);
+    (is_bound(start) && is_bound(end)).then_some((start, end, inclusive))
+}
+
+/// `user.name`-style paths, as opposed to literals and helper calls
+pub(crate) fn is_variable_path(expression: &str) -> bool {
+    expression.split('.').all(|part| {
+        let mut chars = part.chars();
+        chars.next().is_some_and(|first| first.is_alphabetic() || first == '_')
+            && chars.all(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+/// Name and argument range of `expression` when it is a single `name(...)`
+/// call, the parenthesis after the name closing at the end
+fn split_call(expression: &str) -> Option<(&str, std::ops::Range<usize>)> {
+    let open = expression.find('(')?;
+    let name = expression[..open].trim();
+    if !is_variable_path(name) || !expression.ends_with(')') {
+        return None;
+    }
+    let mut depth = 0;
+    let mut quote = None;
+    for (index, c) in expression.char_indices().skip_while(|(index, _)| *index < open) {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => {
+                depth -= 1;
+                if depth == 0 {
+                    return (index == expression.len() - 1).then_some((name, open + 1..index));
                 }
-                
-                current_pos = abs_end;
-            } else {
-                break;
             }
+            _ => {}
         }
-        
-        ("".to_string(), "unknown".to_string())
     }
-    
-    /// Check if a filter is known/built-in
-    fn is_known_filter(&self, filter_name: &str) -> bool {
-        let known_filters = [
-            "upper", "lower", "currency", "truncate", "round", 
-            "add", "multiply", "divide", "percentage"
-        ];
-        
-        known_filters.contains(&filter_name) || self.custom_filters.contains_key(filter_name)
+    // An unterminated string runs to the end, for the argument parser to report
+    quote.map(|_| (name, open + 1..expression.len() - 1))
+}
+
+/// Split a `name=value` macro argument; quoted text containing `=` is positional
+pub(crate) fn split_named_argument(arg: &str) -> Option<(&str, &str)> {
+    let (name, value) = arg.split_once('=')?;
+    let name = name.trim();
+    (!name.contains('.') && is_variable_path(name)).then(|| (name, value.trim()))
+}
+
+/// Template location of output byte `offset`, and `message` with where the
+/// tag is when it is reported at its enclosing conditional
+#[cfg(feature = "lsp")]
+fn locate_in_trace(trace: &RenderTrace, offset: usize, message: String) -> (usize, usize, String) {
+    let mut segment_start = 0;
+    for segment in &trace.segments {
+        let segment_end = segment_start + segment.output.len();
+        if offset < segment_end {
+            let (mut line, mut column) = (segment.line, segment.column);
+            if segment.kind == SegmentKind::Text {
+                let (inner_line, inner_column) = find_line_column(&segment.output, offset - segment_start);
+                if inner_line == 1 {
+                    column += inner_column - 1;
+                } else {
+                    line += inner_line - 1;
+                    column = inner_column;
+                }
+            }
+            return match segment.scope.iter().rev().find(|scope| scope.directive.starts_with("if ")) {
+                Some(conditional) => (
+                    conditional.line,
+                    1,
+                    format!("{} (line {}, column {}, inside {{{{{}}}}})", message, line, column, conditional.directive),
+                ),
+                None => (line, column, message),
+            };
+        }
+        segment_start = segment_end;
     }
+    let (line, column) = find_line_column(&trace.output, offset);
+    (line, column, message)
+}
+
+/// `paginate(items, page, per_page[, window])`: the page's slice of `items`
+/// and the numbers for its links. `page` may be a numeric string; out of
+/// range pages are clamped, or fail in strict mode. With `window`, `pages`
+/// holds at most that many page numbers around the current one.
+fn paginate(arguments: &[TemplateValue], strict: bool) -> TemplateResult<TemplateValue> {
+    let usage = || TemplateError::Render("paginate expects (items, page, per_page) or (items, page, per_page, window)".to_string());
+    let (items, page, per_page, window) = match arguments {
+        [items, page, per_page] => (items, page, per_page, None),
+        [items, page, per_page, window] => (items, page, per_page, Some(window)),
+        _ => return Err(usage()),
+    };
+    let number = |value: &TemplateValue, what: &str| -> TemplateResult<i64> {
+        let number = match value {
+            TemplateValue::Number(number) => Some(*number),
+            TemplateValue::Float(number) => Some(*number as i64),
+            TemplateValue::String(text) => text.trim().parse().ok(),
+            _ => None,
+        };
+        match number {
+            Some(number) => Ok(number),
+            None if strict => Err(TemplateError::Render(format!("paginate: {} must be a number", what))),
+            None => Ok(1),
+        }
+    };
     
-    // =============================================================================
-    // v0.5.1 Advanced Performance Features
-    // =============================================================================
+    let items = match items {
+        TemplateValue::Array(items) => items.as_slice(),
+        _ if strict => return Err(TemplateError::Render("paginate: items must be an array".to_string())),
+        _ => &[],
+    };
+    let per_page = number(per_page, "per_page")?;
+    if per_page < 1 && strict {
+        return Err(TemplateError::Render(format!("paginate: per_page must be at least 1, got {}", per_page)));
+    }
+    let per_page = per_page.max(1) as usize;
     
-    /// Enable advanced performance monitoring and metrics collection.
-    /// 
-    /// When enabled, the engine will track:
-    /// - Template compilation times and frequency
-    /// - Rendering performance statistics
-    /// - Memory usage patterns
-    /// - Cache hit/miss ratios
-    /// 
-    /// # Performance Impact
-    /// Monitoring has minimal overhead (~1-2% performance cost)
-    /// 
-    /// # Example
-    /// ```rust
-    /// use mystical_runic::TemplateEngine;
-    /// 
-    /// let mut engine = TemplateEngine::new("templates");
-    /// engine.enable_performance_monitoring();
-    /// 
-    /// // Now all rendering operations will be monitored
-    /// ```
-    pub fn enable_performance_monitoring(&mut self) {
-        self.performance_monitoring_enabled = true;
-        self.memory_usage_tracking = true;
+    // An empty list still has one (empty) page
+    let total_pages = items.len().div_ceil(per_page).max(1);
+    let requested = number(page, "page")?;
+    if strict && !(1..=total_pages as i64).contains(&requested) {
+        return Err(TemplateError::Render(format!("paginate: page {} is out of range 1..={}", requested, total_pages)));
     }
+    let page = requested.clamp(1, total_pages as i64) as usize;
     
-    /// Disable performance monitoring to maximize rendering speed.
-    pub fn disable_performance_monitoring(&mut self) {
-        self.performance_monitoring_enabled = false;
-        self.memory_usage_tracking = false;
-        self.compilation_stats.clear();
-        self.render_stats.clear();
+    let (mut first, mut last) = (1, total_pages);
+    if let Some(window) = window {
+        let window = number(window, "window")?.max(1) as usize;
+        first = page.saturating_sub(window / 2).max(1);
+        last = (first + window - 1).min(total_pages);
+        first = (last + 1).saturating_sub(window).max(1);
     }
     
-    /// Get comprehensive performance statistics.
-    /// 
-    /// Returns detailed metrics about template processing including:
-    /// - Average compilation times
-    /// - Rendering performance per template
-    /// - Cache effectiveness
-    /// - Memory usage trends
-    /// 
-    /// # Returns
-    /// A structured performance report with actionable insights
-    pub fn get_performance_statistics(&self) -> PerformanceReport {
-        let mut report = PerformanceReport::new();
-        
-        // Calculate compilation statistics
-        for (template_name, (compile_count, _last_time)) in &self.compilation_stats {
-            report.add_compilation_stat(template_name.clone(), *compile_count);
+    let start = ((page - 1) * per_page).min(items.len());
+    let end = (start + per_page).min(items.len());
+    let page_number = |number: usize| TemplateValue::Number(number as i64);
+    let fields = [
+        ("items", TemplateValue::Array(items[start..end].to_vec())),
+        ("page", page_number(page)),
+        ("per_page", page_number(per_page)),
+        ("total_pages", page_number(total_pages)),
+        ("total_items", page_number(items.len())),
+        ("has_prev", TemplateValue::Bool(page > 1)),
+        ("has_next", TemplateValue::Bool(page < total_pages)),
+        ("prev_page", page_number(page.saturating_sub(1).max(1))),
+        ("next_page", page_number((page + 1).min(total_pages))),
+        ("pages", TemplateValue::Array((first..=last).map(page_number).collect())),
+    ];
+    Ok(TemplateValue::Object(fields.into_iter().map(|(name, value)| (name.to_string(), value)).collect()))
+}
+
+/// Arguments of a `name(...)` macro call
+#[cfg(feature = "template-macros")]
+fn macro_call_arguments(call_content: &str) -> TemplateResult<Vec<Argument<'_>>> {
+    match (call_content.find('('), call_content.rfind(')')) {
+        (Some(open), Some(close)) if open < close => parse_arguments(call_content, open + 1..close, Separator::Comma),
+        _ => Err(TemplateError::Parse("Invalid macro call syntax".to_string())),
+    }
+}
+
+/// Filter steps of `template` whose arguments don't fit the filter, as the
+/// tag's byte offset and the problem
+fn filter_argument_problems(template: &str) -> Vec<(usize, String)> {
+    let mut problems = Vec::new();
+    let mut pos = 0;
+    while let Some(relative_start) = template[pos..].find("{{") {
+        let start = pos + relative_start;
+        let Some(end) = template[start..].find("}}") else { break };
+        pos = start + end + 2;
+        
+        let directive = template[start + 2..start + end].trim();
+        if directive.starts_with(['!', '#', '/']) || directive.starts_with("macro ") {
+            continue;
         }
-        
-        // Calculate rendering statistics
-        for (template_name, render_times) in &self.render_stats {
-            if !render_times.is_empty() {
-                let avg_time = render_times.iter().sum::<u64>() / render_times.len() as u64;
-                let min_time = *render_times.iter().min().unwrap_or(&0);
-                let max_time = *render_times.iter().max().unwrap_or(&0);
-                
-                report.add_render_stat(template_name.clone(), avg_time, min_time, max_time, render_times.len());
+        for step in split_unquoted(directive, '|').into_iter().skip(1) {
+            // In conditions the filter ends where the comparison starts
+            let step = split_directive_arguments(step).first().copied().unwrap_or_default();
+            if let Some(problem) = check_filter_arguments(step) {
+                problems.push((start, problem));
             }
         }
-        
-        // Cache statistics
-        report.cache_size = self.cache.len();
-        report.bytecode_cache_size = self.bytecode_cache.len();
-        report.bytecode_cache_enabled = self.bytecode_cache_enabled;
-        
-        report
     }
-    
-    /// Record rendering time for performance monitoring
-    #[allow(dead_code)]
-    fn record_render_time(&mut self, template_name: &str, render_time_nanos: u64) {
-        if self.performance_monitoring_enabled {
-            self.render_stats
-                .entry(template_name.to_string())
-                .or_default()
-                .push(render_time_nanos);
-                
-            // Keep only last 100 measurements to prevent memory bloat
-            let stats = self.render_stats.get_mut(template_name).unwrap();
-            if stats.len() > 100 {
-                stats.drain(0..stats.len() - 100);
+    problems
+}
+
+/// Split on `separator` outside quotes
+pub(crate) fn split_unquoted(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == separator => {
+                parts.push(&text[start..index]);
+                start = index + c.len_utf8();
             }
+            None => {}
         }
     }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// A `{{try}}` block: tag offsets and the ranges of its bodies
+struct ErrorBoundary {
+    /// Offset of `{{try}}`
+    start: usize,
+    body: std::ops::Range<usize>,
+    rescue: Option<std::ops::Range<usize>>,
+    /// Offset just past `{{/try}}`
+    end: usize,
+}
+
+/// The outermost `{{try}}` blocks of `template` that aren't inside a `{{for}}`
+/// loop, in source order
+fn find_error_boundaries(template: &str) -> TemplateResult<Vec<ErrorBoundary>> {
+    let mut boundaries = Vec::new();
+    let mut loop_depth = 0usize;
+    // Depth of nested `{{try}}` tags and the outermost block, filled in as its tags are found
+    let mut try_depth = 0usize;
+    let mut open: Option<ErrorBoundary> = None;
+    let mut pos = 0;
     
-    /// Record compilation statistics
-    #[allow(dead_code)]
-    fn record_compilation(&mut self, template_name: &str) {
-        if self.performance_monitoring_enabled {
-            let entry = self.compilation_stats
-                .entry(template_name.to_string())
-                .or_insert((0, std::time::Instant::now()));
-            entry.0 += 1;
-            entry.1 = std::time::Instant::now();
+    while let Some(relative_start) = template[pos..].find("{{") {
+        let start = pos + relative_start;
+        let Some(relative_end) = template[start..].find("}}") else { break };
+        let end = start + relative_end + 2;
+        pos = end;
+        
+        match template[start + 2..end - 2].trim() {
+            "try" if try_depth > 0 => try_depth += 1,
+            "try" if loop_depth == 0 => {
+                try_depth = 1;
+                open = Some(ErrorBoundary { start, body: end..end, rescue: None, end });
+            }
+            "rescue" if try_depth == 1 => match &mut open {
+                Some(boundary) if boundary.rescue.is_none() => {
+                    boundary.body.end = start;
+                    boundary.rescue = Some(end..end);
+                }
+                _ => return Err(TemplateError::Parse("Duplicate {{rescue}} in {{try}} block".to_string())),
+            },
+            "/try" if try_depth > 1 => try_depth -= 1,
+            "/try" if try_depth == 1 => {
+                try_depth = 0;
+                if let Some(mut boundary) = open.take() {
+                    match &mut boundary.rescue {
+                        Some(rescue) => rescue.end = start,
+                        None => boundary.body.end = start,
+                    }
+                    boundary.end = end;
+                    boundaries.push(boundary);
+                }
+            }
+            "rescue" | "/try" if try_depth == 0 && loop_depth == 0 => {
+                return Err(TemplateError::Parse(format!("Unexpected {} outside a {{{{try}}}} block", &template[start..end])));
+            }
+            "/for" if try_depth == 0 => loop_depth = loop_depth.saturating_sub(1),
+            directive if try_depth == 0 && directive.starts_with("for ") => loop_depth += 1,
+            _ => {}
         }
     }
     
-    /// Optimize cache based on usage patterns.
-    /// 
-    /// Analyzes template usage and optimizes cache allocation:
-    /// - Promotes frequently used templates to bytecode cache
-    /// - Evicts least recently used templates when memory pressure exists
-    /// - Adjusts cache sizes based on usage patterns
-    /// 
-    /// # Returns
-    /// Number of optimizations performed
-    pub fn optimize_cache(&mut self) -> usize {
-        let mut optimizations = 0;
-        
-        // Enable bytecode cache if we have frequent compilation
-        if !self.bytecode_cache_enabled {
-            let frequent_templates: Vec<_> = self.compilation_stats
-                .iter()
-                .filter(|(_, (count, _))| *count >= 5) // Compiled 5+ times
-                .map(|(name, _)| name.clone())
-                .collect();
-                
-            if !frequent_templates.is_empty() {
-                self.enable_bytecode_cache(true);
-                optimizations += 1;
-            }
-        }
-        
-        // Clear old render statistics to free memory
-        if self.render_stats.len() > 50 {
-            // Keep only the 25 most recently used templates
-            let mut templates_by_usage: Vec<_> = self.render_stats
-                .iter()
-                .map(|(name, times)| (name.clone(), times.len()))
-                .collect();
-            
-            templates_by_usage.sort_by(|a, b| b.1.cmp(&a.1));
-            templates_by_usage.truncate(25);
-            
-            let keep_templates: std::collections::HashSet<String> = 
-                templates_by_usage.into_iter().map(|(name, _)| name).collect();
-            
-            self.render_stats.retain(|name, _| keep_templates.contains(name));
-            optimizations += 1;
-        }
-        
-        optimizations
+    if try_depth > 0 {
+        return Err(TemplateError::Parse("Missing {{/try}} directive".to_string()));
     }
+    Ok(boundaries)
 }
 
-/// Comprehensive performance report with actionable insights.
-#[derive(Debug, Clone)]
-pub struct PerformanceReport {
-    /// Template compilation statistics
-    pub compilation_stats: HashMap<String, u64>,
-    /// Template rendering statistics (template_name -> (avg_ns, min_ns, max_ns, count))
-    pub render_stats: HashMap<String, (u64, u64, u64, usize)>,
-    /// Current template cache size
-    pub cache_size: usize,
-    /// Current bytecode cache size
-    pub bytecode_cache_size: usize,
-    /// Whether bytecode cache is enabled
-    pub bytecode_cache_enabled: bool,
+/// `context` with the rescued `error` set for the rescue body
+fn rescue_context(context: &TemplateContext, error: &TemplateError) -> TemplateContext {
+    let mut rescue_context = TemplateContext::layered(Arc::new(context.clone()));
+    rescue_context.set("error", TemplateValue::Object(HashMap::from([
+        ("message".to_string(), TemplateValue::String(error.to_string())),
+        ("kind".to_string(), TemplateValue::String(error.kind().to_string())),
+    ])));
+    rescue_context
 }
 
-impl PerformanceReport {
-    fn new() -> Self {
-        Self {
-            compilation_stats: HashMap::new(),
-            render_stats: HashMap::new(),
-            cache_size: 0,
-            bytecode_cache_size: 0,
-            bytecode_cache_enabled: false,
+/// Errors a `{{try}}` block may rescue: everything but security violations and cancellation
+fn is_rescuable(error: &TemplateError) -> bool {
+    match error {
+        TemplateError::Security(_) | TemplateError::SecurityViolation { .. } | TemplateError::Cancelled => false,
+        TemplateError::NestedTemplateError { root_error, .. } => is_rescuable(root_error),
+        TemplateError::Backtraced { error, .. } => is_rescuable(error),
+        _ => true,
+    }
+}
+
+/// Props and slots a component accepts; `None` when it doesn't declare them
+#[derive(Default)]
+struct ComponentSignature {
+    /// Prop name and default expression (`None` when the prop is required)
+    props: Option<Vec<(String, Option<String>)>>,
+    /// Slot name and whether it is required
+    slots: Option<Vec<(String, bool)>>,
+}
+
+/// Declarations of `{{props label variant="primary"}}` and `{{slots body footer?}}` tags
+fn component_signature(source: &str) -> ComponentSignature {
+    let mut signature = ComponentSignature::default();
+    let mut pos = 0;
+    while let Some(relative_start) = source[pos..].find("{{") {
+        let start = pos + relative_start + 2;
+        let Some(end) = source[start..].find("}}") else { break };
+        let directive = source[start..start + end].trim();
+        pos = start + end + 2;
+        
+        if let Some(args) = directive.strip_prefix("props ") {
+            let props = signature.props.get_or_insert_with(Vec::new);
+            for arg in split_directive_arguments(args) {
+                match split_named_argument(arg) {
+                    Some((prop, default)) => props.push((prop.to_string(), Some(default.to_string()))),
+                    None => props.push((arg.to_string(), None)),
+                }
+            }
+        } else if let Some(args) = directive.strip_prefix("slots ") {
+            let slots = signature.slots.get_or_insert_with(Vec::new);
+            for arg in split_directive_arguments(args) {
+                match arg.strip_suffix('?') {
+                    Some(slot) => slots.push((slot.to_string(), false)),
+                    None => slots.push((arg.to_string(), true)),
+                }
+            }
         }
     }
-    
-    fn add_compilation_stat(&mut self, template_name: String, compile_count: u64) {
-        self.compilation_stats.insert(template_name, compile_count);
+    signature
+}
+
+/// Check the props and slots given to a component against its declarations
+fn check_component_call(
+    component: &str,
+    signature: &ComponentSignature,
+    props: &TemplateContext,
+    slots: &HashMap<String, String>,
+) -> TemplateResult<()> {
+    let mut missing_props = Vec::new();
+    let mut unknown_props = Vec::new();
+    if let Some(declared) = &signature.props {
+        missing_props = declared.iter()
+            .filter(|(prop, default)| default.is_none() && !props.contains(prop))
+            .map(|(prop, _)| prop.clone())
+            .collect();
+        unknown_props = props.visible_variables().into_keys()
+            .filter(|prop| !declared.iter().any(|(declared, _)| declared == prop))
+            .map(str::to_string)
+            .collect();
+        unknown_props.sort();
     }
     
-    fn add_render_stat(&mut self, template_name: String, avg_time: u64, min_time: u64, max_time: u64, count: usize) {
-        self.render_stats.insert(template_name, (avg_time, min_time, max_time, count));
+    let mut missing_slots = Vec::new();
+    let mut unknown_slots = Vec::new();
+    if let Some(declared) = &signature.slots {
+        missing_slots = declared.iter()
+            .filter(|(slot, required)| *required && !slots.contains_key(slot))
+            .map(|(slot, _)| slot.clone())
+            .collect();
+        unknown_slots = slots.keys()
+            .filter(|slot| !declared.iter().any(|(declared, _)| declared == *slot))
+            .cloned()
+            .collect();
+        unknown_slots.sort();
     }
     
-    /// Get human-readable performance summary
-    pub fn summary(&self) -> String {
-        let mut summary = String::new();
-        summary.push_str("🔮 Performance Report\n");
-        summary.push_str("==================\n\n");
-        
-        summary.push_str("📊 Cache Status:\n");
-        summary.push_str(&format!("  - Template cache: {} entries\n", self.cache_size));
-        summary.push_str(&format!("  - Bytecode cache: {} entries {}\n", 
-            self.bytecode_cache_size,
-            if self.bytecode_cache_enabled { "✅" } else { "❌ (disabled)" }));
-        summary.push('\n');
-        
-        if !self.render_stats.is_empty() {
-            summary.push_str("⚡ Top Performing Templates:\n");
-            let mut sorted_renders: Vec<_> = self.render_stats.iter().collect();
-            sorted_renders.sort_by(|a, b| a.1.0.cmp(&b.1.0)); // Sort by avg time
-            
-            for (name, (avg_ns, min_ns, max_ns, count)) in sorted_renders.iter().take(5) {
-                summary.push_str(&format!("  - {}: {:.2}ms avg ({:.2}-{:.2}ms, {} renders)\n",
-                    name,
-                    *avg_ns as f64 / 1_000_000.0,
-                    *min_ns as f64 / 1_000_000.0,
-                    *max_ns as f64 / 1_000_000.0,
-                    count
-                ));
-            }
-        }
-        
-        summary
+    if missing_props.is_empty() && unknown_props.is_empty() && missing_slots.is_empty() && unknown_slots.is_empty() {
+        return Ok(());
+    }
+    Err(TemplateError::ComponentError {
+        component: component.to_string(),
+        missing_props,
+        unknown_props,
+        missing_slots,
+        unknown_slots,
+    })
+}
+
+/// Whether the slot named by `{{has_slot "name"}}` was passed to `render_component`
+fn has_slot(args: &str, context: &TemplateContext) -> TemplateResult<bool> {
+    match quoted_arguments(args, "has_slot")?.as_slice() {
+        [slot] => Ok(matches!(context.get("slot"), Some(TemplateValue::Object(slots)) if slots.contains_key(*slot))),
+        _ => Err(TemplateError::Parse("{{has_slot ...}} takes one quoted slot name".to_string())),
     }
-}
\ No newline at end of file
+}
+
+/// Error for a directive whose cargo feature is disabled
+#[cfg(not(all(feature = "i18n", feature = "template-macros", feature = "inheritance")))]
+fn feature_disabled(directive: &str, feature: &str) -> TemplateError {
+    TemplateError::Template(format!(
+        "'{}' needs the `{}` feature of mystical-runic, which is disabled", directive, feature
+    ))
+}
@@ -71,6 +71,227 @@ pub enum TemplateError {
         root_error: Box<TemplateError>,
         current_template: String,
     },
+    
+    // v0.6.0 Value Coercion
+    /// A filter could not convert its input (raised in strict mode only)
+    FilterError {
+        filter_name: String,
+        value: String,
+        message: String,
+    },
+    
+    /// A registered helper panicked
+    HelperError {
+        helper_name: String,
+        message: String,
+    },
+    
+    // v0.6.0 Template Validation
+    /// Templates of a directory failed validation
+    InvalidTemplates {
+        template_dir: String,
+        /// Number of templates checked
+        checked: usize,
+        /// Broken template and its first problem, in name order
+        failures: Vec<(String, String)>,
+    },
+    
+    // v0.6.0 Offline Rendering
+    /// An offline render needed a template that isn't cached
+    OfflineMiss {
+        name: String,
+    },
+    
+    // v0.6.0 Render Cancellation
+    /// The render's cancellation token was cancelled
+    Cancelled,
+    
+    // v0.6.0 Components
+    /// `render_component` got props or slots its declarations don't allow
+    ComponentError {
+        component: String,
+        /// Required props that weren't given
+        missing_props: Vec<String>,
+        /// Given props the component doesn't declare
+        unknown_props: Vec<String>,
+        /// Required slots that weren't given
+        missing_slots: Vec<String>,
+        /// Given slots the component doesn't declare
+        unknown_slots: Vec<String>,
+    },
+    
+    // v0.6.0 Template Assertions
+    /// An `{{assert condition "message"}}` condition was false
+    AssertionFailed {
+        message: String,
+        /// Condition source text
+        condition: String,
+        /// Variables of the condition and their values when it failed
+        values: Vec<(String, String)>,
+        /// Included template holding the assertion, `None` for the rendered template
+        template_name: Option<String>,
+        line: usize,
+        column: usize,
+    },
+    
+    // v0.6.0 Output Encoding
+    /// Rendered output holds a character the output encoding can't represent
+    Encoding {
+        encoding: String,
+        character: char,
+        /// Position of the character in the rendered output
+        line: usize,
+        column: usize,
+    },
+    
+    // v0.6.0 Template Backtraces
+    /// An error raised inside an include, layout, loop or macro call of a
+    /// rendered template, and the template call stack it was raised in
+    Backtraced {
+        error: Box<TemplateError>,
+        /// Outermost first: the rendered template down to the failing directive
+        backtrace: Vec<TemplateFrame>,
+    },
+}
+
+impl TemplateError {
+    /// Short name of the error, as `{{error.kind}}` shows it in a `{{rescue}}` body:
+    /// `"io"`, `"template"`, `"parse"`, `"runtime"`, `"render"`, `"security"`,
+    /// `"rate_limit"`, `"resource_exhaustion"`, `"template_not_found"`,
+    /// `"variable_not_found"`, `"filter"`, `"helper"`, `"invalid_templates"`,
+    /// `"offline_miss"`, `"cancelled"`, `"component"`, `"assertion"` or
+    /// `"encoding"`. Nested template and backtraced
+    /// errors report the kind of their root error.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TemplateError::Io(_) => "io",
+            TemplateError::Template(_) => "template",
+            TemplateError::Parse(_) | TemplateError::ParseWithLocation { .. } => "parse",
+            TemplateError::Runtime(_) => "runtime",
+            TemplateError::Render(_) => "render",
+            TemplateError::Security(_) | TemplateError::SecurityViolation { .. } => "security",
+            TemplateError::RateLimit { .. } => "rate_limit",
+            TemplateError::ResourceExhaustion { .. } => "resource_exhaustion",
+            TemplateError::TemplateNotFoundWithSuggestions { .. } => "template_not_found",
+            TemplateError::VariableNotFoundWithSuggestions { .. } => "variable_not_found",
+            TemplateError::NestedTemplateError { root_error, .. } => root_error.kind(),
+            TemplateError::FilterError { .. } => "filter",
+            TemplateError::HelperError { .. } => "helper",
+            TemplateError::InvalidTemplates { .. } => "invalid_templates",
+            TemplateError::OfflineMiss { .. } => "offline_miss",
+            TemplateError::Cancelled => "cancelled",
+            TemplateError::ComponentError { .. } => "component",
+            TemplateError::AssertionFailed { .. } => "assertion",
+            TemplateError::Encoding { .. } => "encoding",
+            TemplateError::Backtraced { error, .. } => error.kind(),
+        }
+    }
+    
+    /// Template call stack the error was raised in, outermost first: the
+    /// rendered page, its layout, includes, loop iterations and macro calls.
+    /// Empty for errors that don't carry one: errors of `render_string`,
+    /// errors raised outside any nested directive, security violations,
+    /// cancellation and offline misses.
+    /// 
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{FrameKind, TemplateEngine, TemplateContext};
+    /// 
+    /// let dir = std::env::temp_dir().join(format!("runic_backtrace_doc_{}", std::process::id()));
+    /// std::fs::create_dir_all(&dir)?;
+    /// std::fs::write(dir.join("page.html"), "<h1>Cart</h1>\n{{include \"row.html\"}}")?;
+    /// std::fs::write(dir.join("row.html"), "{{price|int}}")?;
+    /// 
+    /// let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+    /// engine.enable_strict_mode();
+    /// let mut context = TemplateContext::new();
+    /// context.set_string("price", "n/a");
+    /// let error = engine.render("page.html", &context).unwrap_err();
+    /// 
+    /// let backtrace = error.template_backtrace();
+    /// assert_eq!(backtrace[1].kind, FrameKind::Include);
+    /// assert_eq!((backtrace[1].template.as_str(), backtrace[1].line), ("page.html", 2));
+    /// assert!(error.to_string().contains("Template backtrace"));
+    /// # std::fs::remove_dir_all(&dir)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn template_backtrace(&self) -> &[TemplateFrame] {
+        match self {
+            TemplateError::Backtraced { backtrace, .. } => backtrace,
+            _ => &[],
+        }
+    }
+    
+    /// The error without its template backtrace
+    pub fn root_cause(&self) -> &TemplateError {
+        match self {
+            TemplateError::Backtraced { error, .. } => error.root_cause(),
+            error => error,
+        }
+    }
+    
+    /// Add the frame the error was raised in, outside those it already has
+    pub(crate) fn within(self, frame: TemplateFrame) -> Self {
+        match self {
+            TemplateError::Backtraced { error, mut backtrace } => {
+                backtrace.insert(0, frame);
+                TemplateError::Backtraced { error, backtrace }
+            }
+            error => TemplateError::Backtraced { error: Box::new(error), backtrace: vec![frame] },
+        }
+    }
+}
+
+/// What a [`TemplateFrame`] was rendering
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    /// The rendered template
+    Template,
+    /// `{{extends "..."}}`
+    Layout,
+    /// `{{block name}}`
+    Block,
+    /// `{{include "..."}}`
+    Include,
+    /// `{{for item in items}}`, at its 1-based iteration
+    Loop { iteration: usize },
+    /// `{{if condition}}`, `{{unless condition}}` or `{{ifchanged expression}}`
+    Condition,
+    /// A macro call, `{{button("Save")}}`
+    Macro,
+    /// Any other output tag, `{{price|currency}}`
+    Output,
+}
+
+/// One level of a template backtrace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateFrame {
+    pub kind: FrameKind,
+    /// Template whose source holds the directive
+    pub template: String,
+    /// Directive text without its braces, e.g. `include "cart.html"`
+    pub directive: String,
+    /// 1-based line of the directive in that template
+    pub line: usize,
+}
+
+impl TemplateFrame {
+    pub(crate) fn new(kind: FrameKind, template: &str, directive: &str, line: usize) -> Self {
+        Self { kind, template: template.to_string(), directive: directive.to_string(), line }
+    }
+}
+
+impl fmt::Display for TemplateFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.kind == FrameKind::Template {
+            return write!(f, "rendering {}", self.template);
+        }
+        write!(f, "{}:{}: {{{{{}}}}}", self.template, self.line, self.directive)?;
+        if let FrameKind::Loop { iteration } = self.kind {
+            write!(f, ", iteration {}", iteration)?;
+        }
+        Ok(())
+    }
 }
 
 impl fmt::Display for TemplateError {
@@ -234,6 +455,96 @@ impl fmt::Display for TemplateError {
                 writeln!(f, "Usage: {} / Limit: {}", current_usage, max_allowed)?;
                 Ok(())
             },
+            
+            // v0.6.0 Value Coercion
+            TemplateError::FilterError {
+                filter_name,
+                value,
+                message,
+            } => {
+                write!(f, "Filter error in '{}': {} (value: \"{}\")", filter_name, message, value)
+            },
+            
+            TemplateError::HelperError { helper_name, message } => {
+                write!(f, "Helper error in '{}': {}", helper_name, message)
+            },
+            
+            // v0.6.0 Template Validation
+            TemplateError::InvalidTemplates {
+                template_dir,
+                checked,
+                failures,
+            } => {
+                write!(f, "{} of {} templates in '{}' failed validation:", failures.len(), checked, template_dir)?;
+                for (template, problem) in failures {
+                    write!(f, "\n  {}:{}", template, problem)?;
+                }
+                Ok(())
+            },
+            
+            // v0.6.0 Offline Rendering
+            TemplateError::OfflineMiss { name } => {
+                write!(f, "Template '{}' is not cached and offline rendering never reads from disk", name)
+            },
+            
+            // v0.6.0 Render Cancellation
+            TemplateError::Cancelled => write!(f, "Render cancelled"),
+            
+            // v0.6.0 Components
+            TemplateError::ComponentError {
+                component,
+                missing_props,
+                unknown_props,
+                missing_slots,
+                unknown_slots,
+            } => {
+                write!(f, "Component '{}' was rendered with", component)?;
+                let problems = [
+                    ("missing props", missing_props),
+                    ("unknown props", unknown_props),
+                    ("missing slots", missing_slots),
+                    ("unknown slots", unknown_slots),
+                ];
+                let mut separator = " ";
+                for (problem, names) in problems.iter().filter(|(_, names)| !names.is_empty()) {
+                    write!(f, "{}{} '{}'", separator, problem, names.join("', '"))?;
+                    separator = "; ";
+                }
+                Ok(())
+            },
+            
+            // v0.6.0 Template Assertions
+            TemplateError::AssertionFailed { message, condition, values, template_name, line, column } => {
+                write!(f, "Assertion failed")?;
+                if let Some(template_name) = template_name {
+                    write!(f, " in '{}'", template_name)?;
+                }
+                write!(f, " at line {}, column {}: {} ({}", line, column, message, condition)?;
+                let mut separator = ", where ";
+                for (name, value) in values {
+                    write!(f, "{}{} = {}", separator, name, value)?;
+                    separator = ", ";
+                }
+                write!(f, ")")
+            },
+            
+            // v0.6.0 Output Encoding
+            TemplateError::Encoding { encoding, character, line, column } => {
+                write!(
+                    f,
+                    "Character '{}' (U+{:04X}) at output line {}, column {} cannot be encoded as {}",
+                    character, *character as u32, line, column, encoding
+                )
+            },
+            
+            // v0.6.0 Template Backtraces
+            TemplateError::Backtraced { error, backtrace } => {
+                write!(f, "{}\n\nTemplate backtrace (outermost first):", error)?;
+                for frame in backtrace {
+                    write!(f, "\n  {}", frame)?;
+                }
+                Ok(())
+            },
         }
     }
 }
@@ -0,0 +1,210 @@
+//! Test data factories for v0.6.0
+//!
+//! Realistic fixtures are nested objects and arrays; building them from
+//! `HashMap`s takes a screen of code per fixture. The factories build the
+//! same [`TemplateValue`] graphs declaratively:
+//!
+//! ```rust
+//! use mystical_runic::ValueFactory;
+//!
+//! let context = ValueFactory::object()
+//!     .string("title", "Team")
+//!     .value("users", ValueFactory::repeat(100, |i, user| user
+//!         .string("name", format!("User {}", i))
+//!         .int_range("score", 0..100, 7)
+//!         .lorem("bio", 30)
+//!         .array("tags", |tags| tags.push_string("admin"))))
+//!     .build_context();
+//! # assert!(context.contains("users"));
+//! ```
+//!
+//! The fillers ([`lorem`](ValueFactory::lorem), [`int_range`](ValueFactory::int_range))
+//! are pseudo-random but deterministic: the same seed and key always give
+//! the same value, so load-test fixtures and snapshots are reproducible.
+//! Every object has a seed, 0 by default; objects made by
+//! [`repeat`](ValueFactory::repeat) are seeded with their index.
+
+use crate::context::TemplateContext;
+use crate::engine::splitmix64;
+use crate::value::TemplateValue;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Words of the [`lorem`](ValueFactory::lorem) filler
+const LOREM_WORDS: [&str; 48] = [
+    "lorem", "ipsum", "dolor", "sit", "amet", "consectetur", "adipiscing", "elit", "sed", "do",
+    "eiusmod", "tempor", "incididunt", "ut", "labore", "et", "dolore", "magna", "aliqua", "enim",
+    "ad", "minim", "veniam", "quis", "nostrud", "exercitation", "ullamco", "laboris", "nisi", "aliquip",
+    "ex", "ea", "commodo", "consequat", "duis", "aute", "irure", "in", "reprehenderit", "voluptate",
+    "velit", "esse", "cillum", "fugiat", "nulla", "pariatur", "excepteur", "sint",
+];
+
+/// Builds a [`TemplateValue::Object`], see the [module documentation](self)
+#[derive(Debug, Clone, Default)]
+pub struct ValueFactory {
+    fields: HashMap<String, TemplateValue>,
+    seed: u64,
+}
+
+impl ValueFactory {
+    /// An empty object
+    pub fn object() -> Self {
+        Self::default()
+    }
+
+    /// An array of `count` objects, each built by `build` from its index and
+    /// an empty object seeded with that index
+    pub fn repeat(count: usize, mut build: impl FnMut(usize, ValueFactory) -> ValueFactory) -> TemplateValue {
+        TemplateValue::Array((0..count).map(|i| build(i, Self::object().seed(i as u64)).build()).collect())
+    }
+
+    /// Seed of the fillers of this object
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Set a field to any value
+    pub fn value(mut self, key: &str, value: TemplateValue) -> Self {
+        self.fields.insert(key.to_string(), value);
+        self
+    }
+
+    /// Set a string field
+    pub fn string(self, key: &str, value: impl Into<String>) -> Self {
+        self.value(key, TemplateValue::String(value.into()))
+    }
+
+    /// Set an integer field
+    pub fn number(self, key: &str, value: i64) -> Self {
+        self.value(key, TemplateValue::Number(value))
+    }
+
+    /// Set a float field
+    pub fn float(self, key: &str, value: f64) -> Self {
+        self.value(key, TemplateValue::Float(value))
+    }
+
+    /// Set a boolean field
+    pub fn bool(self, key: &str, value: bool) -> Self {
+        self.value(key, TemplateValue::Bool(value))
+    }
+
+    /// Set a field to a nested object
+    pub fn nested(self, key: &str, build: impl FnOnce(ValueFactory) -> ValueFactory) -> Self {
+        let seed = self.field_seed(key, 0);
+        self.value(key, build(Self::object().seed(seed)).build())
+    }
+
+    /// Set a field to an array
+    pub fn array(self, key: &str, build: impl FnOnce(ArrayFactory) -> ArrayFactory) -> Self {
+        self.value(key, build(ArrayFactory::new()).build())
+    }
+
+    /// Set a string field to `words` lorem ipsum words, as a sentence
+    pub fn lorem(self, key: &str, words: usize) -> Self {
+        let mut state = self.field_seed(key, 0);
+        let mut text = String::new();
+        for index in 0..words {
+            state = splitmix64(state);
+            let word = LOREM_WORDS[(state % LOREM_WORDS.len() as u64) as usize];
+            if index == 0 {
+                let mut chars = word.chars();
+                text.extend(chars.next().map(|first| first.to_ascii_uppercase()));
+                text.push_str(chars.as_str());
+            } else {
+                text.push(' ');
+                text.push_str(word);
+            }
+        }
+        if words > 0 {
+            text.push('.');
+        }
+        self.string(key, text)
+    }
+
+    /// Set an integer field to a number of `range` picked by `seed`, mixed
+    /// with the object's seed; an empty range gives its start
+    pub fn int_range(self, key: &str, range: Range<i64>, seed: u64) -> Self {
+        let span = range.end.saturating_sub(range.start);
+        let value = if span <= 0 {
+            range.start
+        } else {
+            range.start + (splitmix64(self.field_seed(key, seed)) % span as u64) as i64
+        };
+        self.number(key, value)
+    }
+
+    /// The object
+    pub fn build(self) -> TemplateValue {
+        TemplateValue::Object(self.fields)
+    }
+
+    /// A context holding every field as a variable
+    pub fn build_context(self) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        for (key, value) in self.fields {
+            context.set(&key, value);
+        }
+        context
+    }
+
+    /// Seed of the filler of `key`: the object seed, the key and `salt`
+    fn field_seed(&self, key: &str, salt: u64) -> u64 {
+        // FNV-1a, stable across platforms and releases unlike the std hasher
+        let hash = key.bytes().fold(0xCBF2_9CE4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01B3)
+        });
+        splitmix64(splitmix64(self.seed ^ hash) ^ salt)
+    }
+}
+
+/// Builds a [`TemplateValue::Array`] for [`ValueFactory::array`]
+#[derive(Debug, Clone, Default)]
+pub struct ArrayFactory {
+    items: Vec<TemplateValue>,
+}
+
+impl ArrayFactory {
+    /// An empty array
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append any value
+    pub fn push(mut self, value: TemplateValue) -> Self {
+        self.items.push(value);
+        self
+    }
+
+    /// Append a string
+    pub fn push_string(self, value: impl Into<String>) -> Self {
+        self.push(TemplateValue::String(value.into()))
+    }
+
+    /// Append an integer
+    pub fn push_number(self, value: i64) -> Self {
+        self.push(TemplateValue::Number(value))
+    }
+
+    /// Append a float
+    pub fn push_float(self, value: f64) -> Self {
+        self.push(TemplateValue::Float(value))
+    }
+
+    /// Append a boolean
+    pub fn push_bool(self, value: bool) -> Self {
+        self.push(TemplateValue::Bool(value))
+    }
+
+    /// Append an object
+    pub fn push_object(self, build: impl FnOnce(ValueFactory) -> ValueFactory) -> Self {
+        let seed = self.items.len() as u64;
+        self.push(build(ValueFactory::object().seed(seed)).build())
+    }
+
+    /// The array
+    pub fn build(self) -> TemplateValue {
+        TemplateValue::Array(self.items)
+    }
+}
@@ -0,0 +1,223 @@
+//! Expression functions for v0.6.0
+//!
+//! A small standard library under namespaced names, callable wherever a
+//! helper is: output tags, conditions, `{{for}}` and `{{set}}` expressions
+//! and filter arguments.
+//!
+//! | Function | Result |
+//! |----------|--------|
+//! | `math.min(a, b, ...)` / `math.max(a, b, ...)` | Smallest / largest number; one array argument compares its items |
+//! | `math.abs(x)` | Absolute value |
+//! | `math.ceil(x)` / `math.floor(x)` | Integer rounded up / down |
+//! | `str.contains(s, sub)` | Whether `s` contains `sub` |
+//! | `str.split(s, sep)` | Array of the parts of `s`; an empty `sep` splits characters |
+//! | `list.sum(items)` | Sum of the numbers of `items` |
+//! | `list.unique(items)` | `items` without repeated values, first ones kept |
+//! | `list.zip(a, b)` | Array of `[a[i], b[i]]` pairs, as long as the shorter array |
+//! | `range(end)` / `range(start, end, step)` | Integers from `start` (0) up to `end`, excluded, by `step` (1) |
+//!
+//! Integers stay integers: `math.max(1, 2)` is `2` and `list.sum` of
+//! integers is an integer; any float makes the result a float. Helpers
+//! registered with [`register_helper`](crate::TemplateEngine::register_helper)
+//! come first, so `register_helper("str.titlecase", ...)` adds a function to
+//! the `str` namespace and `register_helper("math.min", ...)` replaces one.
+//! Ranges longer than
+//! [`set_max_range_items`](crate::TemplateEngine::set_max_range_items)
+//! fail instead of filling memory.
+
+use crate::engine::{coerce_to_float, filter_value_to_string};
+use crate::value::TemplateValue;
+use crate::{TemplateError, TemplateResult};
+
+/// Every function and its signature, for completions and diagnostics
+pub(crate) const FUNCTIONS: [(&str, &str); 11] = [
+    ("math.min", "math.min(a, b, ...)"),
+    ("math.max", "math.max(a, b, ...)"),
+    ("math.abs", "math.abs(x)"),
+    ("math.ceil", "math.ceil(x)"),
+    ("math.floor", "math.floor(x)"),
+    ("str.contains", "str.contains(s, sub)"),
+    ("str.split", "str.split(s, sep)"),
+    ("list.sum", "list.sum(items)"),
+    ("list.unique", "list.unique(items)"),
+    ("list.zip", "list.zip(a, b)"),
+    ("range", "range(start, end, step)"),
+];
+
+/// Whether `name` is a standard library function
+pub(crate) fn is_function(name: &str) -> bool {
+    FUNCTIONS.iter().any(|(function, _)| *function == name)
+}
+
+/// Call function `name`; `equal` compares values for `list.unique`, as `==`
+/// does in conditions, and `range` returns at most `max_range_items` items
+pub(crate) fn call(
+    name: &str,
+    args: &[TemplateValue],
+    equal: impl Fn(&TemplateValue, &TemplateValue) -> bool,
+    max_range_items: usize,
+) -> TemplateResult<TemplateValue> {
+    let usage = || {
+        let signature = FUNCTIONS.iter().find(|(function, _)| *function == name).map_or(name, |(_, signature)| signature);
+        TemplateError::Render(format!("{} expects {}, got {} argument{}", name, signature, args.len(), if args.len() == 1 { "" } else { "s" }))
+    };
+    match (name, args) {
+        ("math.min" | "math.max", [TemplateValue::Array(items)]) => extremum(name, items),
+        ("math.min" | "math.max", [_, ..]) => extremum(name, args),
+        ("math.abs", [value]) => match value {
+            TemplateValue::Number(number) => Ok(TemplateValue::Number(number.saturating_abs())),
+            value => Ok(TemplateValue::Float(number(name, value)?.abs())),
+        },
+        ("math.ceil" | "math.floor", [value]) => match value {
+            TemplateValue::Number(number) => Ok(TemplateValue::Number(*number)),
+            value => {
+                let number = number(name, value)?;
+                let rounded = if name == "math.ceil" { number.ceil() } else { number.floor() };
+                Ok(TemplateValue::Number(rounded as i64))
+            }
+        },
+        ("str.contains", [text, part]) => {
+            Ok(TemplateValue::Bool(filter_value_to_string(text).contains(&filter_value_to_string(part))))
+        }
+        ("str.split", [text, separator]) => {
+            let (text, separator) = (filter_value_to_string(text), filter_value_to_string(separator));
+            let parts: Vec<TemplateValue> = if separator.is_empty() {
+                text.chars().map(|c| TemplateValue::String(c.to_string())).collect()
+            } else {
+                text.split(separator.as_str()).map(|part| TemplateValue::String(part.to_string())).collect()
+            };
+            Ok(TemplateValue::Array(parts))
+        }
+        ("list.sum", [items]) => {
+            let items = array(name, items)?;
+            if items.iter().all(|item| matches!(item, TemplateValue::Number(_))) {
+                let sum = items.iter().try_fold(0i64, |sum, item| match item {
+                    TemplateValue::Number(number) => sum.checked_add(*number),
+                    _ => None,
+                });
+                if let Some(sum) = sum {
+                    return Ok(TemplateValue::Number(sum));
+                }
+            }
+            let mut sum = 0.0;
+            for item in items {
+                sum += number(name, item)?;
+            }
+            Ok(TemplateValue::Float(sum))
+        }
+        ("list.unique", [items]) => {
+            let mut unique: Vec<TemplateValue> = Vec::new();
+            for item in array(name, items)? {
+                if !unique.iter().any(|kept| equal(kept, item)) {
+                    unique.push(item.clone());
+                }
+            }
+            Ok(TemplateValue::Array(unique))
+        }
+        ("list.zip", [left, right]) => {
+            let pairs = array(name, left)?.iter().zip(array(name, right)?)
+                .map(|(left, right)| TemplateValue::Array(vec![left.clone(), right.clone()]))
+                .collect();
+            Ok(TemplateValue::Array(pairs))
+        }
+        ("range", [_] | [_, _] | [_, _, _]) => {
+            let mut bounds = [0, 0, 1];
+            let first = if args.len() == 1 { 1 } else { 0 };
+            for (bound, arg) in bounds[first..].iter_mut().zip(args) {
+                *bound = integer(arg)
+                    .ok_or_else(|| TemplateError::Render(format!("range expects integers, got {}", describe(arg))))?;
+            }
+            let [start, end, step] = bounds;
+            integer_range(start, end, step, false, max_range_items)
+        }
+        _ => Err(usage()),
+    }
+}
+
+/// Integers from `start` up to `end`, included or not, by `step`; a
+/// negative step counts down. Fails when there are more than `limit`.
+pub(crate) fn integer_range(start: i64, end: i64, step: i64, inclusive: bool, limit: usize) -> TemplateResult<TemplateValue> {
+    if step == 0 {
+        return Err(TemplateError::Render("range step cannot be 0".to_string()));
+    }
+    let distance = if step > 0 { end as i128 - start as i128 } else { start as i128 - end as i128 };
+    let count = match distance {
+        distance if distance < 0 || (distance == 0 && !inclusive) => 0,
+        distance if inclusive => distance / step.unsigned_abs() as i128 + 1,
+        distance => (distance - 1) / step.unsigned_abs() as i128 + 1,
+    };
+    if count > limit as i128 {
+        return Err(TemplateError::Render(format!(
+            "range of {} items exceeds the limit of {} items", count, limit
+        )));
+    }
+    // The items lie between the bounds, but `index * step` may not fit an i64
+    (0..count)
+        .map(|index| {
+            i64::try_from(start as i128 + index * step as i128)
+                .map(TemplateValue::Number)
+                .map_err(|_| TemplateError::Render(format!("range item {} does not fit a 64-bit integer", index)))
+        })
+        .collect::<TemplateResult<Vec<_>>>()
+        .map(TemplateValue::Array)
+}
+
+/// An integer argument: a number, a float without fraction or an integer string
+pub(crate) fn integer(value: &TemplateValue) -> Option<i64> {
+    match value {
+        TemplateValue::Number(number) => Some(*number),
+        TemplateValue::Float(number) if number.fract() == 0.0 && number.abs() < i64::MAX as f64 => Some(*number as i64),
+        TemplateValue::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// `math.min` or `math.max` of `values`, an integer when they all are
+fn extremum(name: &str, values: &[TemplateValue]) -> TemplateResult<TemplateValue> {
+    if values.is_empty() {
+        return Err(TemplateError::Render(format!("{} of an empty array", name)));
+    }
+    let pick_max = name == "math.max";
+    if values.iter().all(|value| matches!(value, TemplateValue::Number(_))) {
+        let numbers = values.iter().filter_map(|value| match value {
+            TemplateValue::Number(number) => Some(*number),
+            _ => None,
+        });
+        let result = if pick_max { numbers.max() } else { numbers.min() };
+        return Ok(TemplateValue::Number(result.unwrap_or_default()));
+    }
+    let mut result = number(name, &values[0])?;
+    for value in &values[1..] {
+        let value = number(name, value)?;
+        result = if pick_max { result.max(value) } else { result.min(value) };
+    }
+    Ok(TemplateValue::Float(result))
+}
+
+/// A numeric argument: a number, a float or a numeric string
+fn number(name: &str, value: &TemplateValue) -> TemplateResult<f64> {
+    coerce_to_float(value)
+        .filter(|_| !matches!(value, TemplateValue::Bool(_)))
+        .ok_or_else(|| TemplateError::Render(format!("{} expects numbers, got {}", name, describe(value))))
+}
+
+/// An array argument
+fn array<'a>(name: &str, value: &'a TemplateValue) -> TemplateResult<&'a [TemplateValue]> {
+    match value {
+        TemplateValue::Array(items) => Ok(items),
+        value => Err(TemplateError::Render(format!("{} expects an array, got {}", name, describe(value)))),
+    }
+}
+
+/// How a value is named in an error
+fn describe(value: &TemplateValue) -> String {
+    match value {
+        TemplateValue::String(text) => format!("\"{}\"", text),
+        TemplateValue::Number(number) => number.to_string(),
+        TemplateValue::Float(number) => number.to_string(),
+        TemplateValue::Bool(flag) => flag.to_string(),
+        TemplateValue::Array(_) => "an array".to_string(),
+        TemplateValue::Object(_) => "an object".to_string(),
+        TemplateValue::Null => "null".to_string(),
+    }
+}
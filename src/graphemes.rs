@@ -0,0 +1,91 @@
+//! Grapheme clusters for v0.6.0
+//!
+//! `length`, `truncate` and `truncate_html` count what a reader sees as one
+//! character: `é` written as `e` and a combining accent, a family emoji
+//! joined with zero width joiners, or a flag made of two regional
+//! indicators all count once.
+//!
+//! This approximates the extended grapheme clusters of UAX #29 without the
+//! Unicode tables. A cluster keeps together a carriage return and line
+//! feed, a character and the combining marks, variation selectors, skin
+//! tone modifiers and emoji tags that follow it, characters joined by a
+//! zero width joiner, and pairs of regional indicators. Hangul jamo
+//! sequences and spacing marks of Indic scripts are split per character.
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+/// Marks that extend the cluster before them
+fn is_extend(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F     // Combining diacritical marks
+        | 0x0483..=0x0489   // Cyrillic
+        | 0x0591..=0x05BD | 0x05BF | 0x05C1..=0x05C2 | 0x05C4..=0x05C5 | 0x05C7 // Hebrew points
+        | 0x0610..=0x061A | 0x064B..=0x065F | 0x0670 // Arabic
+        | 0x0900..=0x0903 | 0x093A..=0x093C | 0x093E..=0x094F | 0x0951..=0x0957 | 0x0962..=0x0963 // Devanagari
+        | 0x0E31 | 0x0E34..=0x0E3A | 0x0E47..=0x0E4E // Thai
+        | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF // Combining marks extended and supplement
+        | 0x200C | 0x200D   // Zero width non-joiner and joiner
+        | 0x20D0..=0x20FF   // Combining marks for symbols, keycaps
+        | 0xFE00..=0xFE0F   // Variation selectors
+        | 0xFE20..=0xFE2F   // Combining half marks
+        | 0x1F3FB..=0x1F3FF // Skin tone modifiers
+        | 0xE0020..=0xE007F // Tags of subdivision flags
+        | 0xE0100..=0xE01EF // Variation selectors supplement
+    )
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c as u32, 0x1F1E6..=0x1F1FF)
+}
+
+/// Iterator over the grapheme clusters of a string
+pub(crate) struct Graphemes<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Graphemes<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let mut chars = self.rest.char_indices();
+        let (_, first) = chars.next()?;
+        let mut previous = first;
+        // Regional indicators in the cluster so far
+        let mut indicators = usize::from(is_regional_indicator(first));
+        let mut end = self.rest.len();
+        for (index, c) in chars {
+            let joined = (previous == '\r' && c == '\n')
+                || is_extend(c)
+                || previous == ZERO_WIDTH_JOINER
+                || (is_regional_indicator(c) && is_regional_indicator(previous) && indicators % 2 == 1);
+            if !joined {
+                end = index;
+                break;
+            }
+            if is_regional_indicator(c) {
+                indicators += 1;
+            }
+            previous = c;
+        }
+        let (cluster, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some(cluster)
+    }
+}
+
+/// The grapheme clusters of `text`
+pub(crate) fn graphemes(text: &str) -> Graphemes<'_> {
+    Graphemes { rest: text }
+}
+
+/// Number of grapheme clusters in `text`
+pub(crate) fn grapheme_count(text: &str) -> usize {
+    graphemes(text).count()
+}
+
+/// The first `limit` grapheme clusters of `text`, or `None` when it has no more than that
+pub(crate) fn grapheme_prefix(text: &str, limit: usize) -> Option<&str> {
+    let mut clusters = graphemes(text);
+    let kept: usize = clusters.by_ref().take(limit).map(str::len).sum();
+    clusters.next().map(|_| &text[..kept])
+}
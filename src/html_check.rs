@@ -0,0 +1,115 @@
+//! Tag balance check of rendered HTML for v0.6.0
+//!
+//! A lightweight pass over the output, not an HTML parser: it pairs the
+//! opening and closing tags of non-void elements and reports the ones left
+//! unclosed, closed out of order or closed without being opened. Comments,
+//! CDATA sections and doctypes are skipped, and the content of `script`,
+//! `style`, `textarea`, `title` and `pre` elements is not looked into.
+//! Elements whose end tag HTML lets you omit (`p`, `li`, `td`, ...) are
+//! never reported as unclosed.
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr",
+];
+
+const OPTIONAL_END_ELEMENTS: &[&str] = &[
+    "body", "caption", "colgroup", "dd", "dt", "head", "html", "li", "optgroup", "option", "p", "rp", "rt",
+    "tbody", "td", "tfoot", "th", "thead", "tr",
+];
+
+/// Elements whose content is skipped up to their end tag
+const OPAQUE_ELEMENTS: &[&str] = &["script", "style", "textarea", "title", "pre"];
+
+/// A tag balance problem at a byte offset of the output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TagProblem {
+    pub offset: usize,
+    pub message: String,
+}
+
+/// Problems of `html`, in output order
+pub(crate) fn check_tag_balance(html: &str) -> Vec<TagProblem> {
+    let mut problems = Vec::new();
+    let mut open: Vec<(String, usize)> = Vec::new();
+    let mut pos = 0;
+
+    while let Some(relative_start) = html[pos..].find('<') {
+        let start = pos + relative_start;
+        let rest = &html[start..];
+        let skipped_until = if rest.starts_with("<!--") {
+            Some("-->")
+        } else if rest.starts_with("<![CDATA[") {
+            Some("]]>")
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            Some(">")
+        } else {
+            None
+        };
+        if let Some(terminator) = skipped_until {
+            pos = rest.find(terminator).map_or(html.len(), |end| start + end + terminator.len());
+            continue;
+        }
+
+        let closing = rest.starts_with("</");
+        let name_start = start + if closing { 2 } else { 1 };
+        let name_end = html[name_start..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == ':'))
+            .map_or(html.len(), |length| name_start + length);
+        if !html[name_start..name_end].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            // A lone `<`, as in `a < b`
+            pos = start + 1;
+            continue;
+        }
+        let name = html[name_start..name_end].to_ascii_lowercase();
+        let Some(end) = tag_end(html, name_end) else { break };
+        pos = end;
+
+        if closing {
+            match open.iter().rposition(|(open_name, _)| *open_name == name) {
+                Some(index) => {
+                    for (inner, offset) in open.drain(index + 1..) {
+                        if !OPTIONAL_END_ELEMENTS.contains(&inner.as_str()) {
+                            problems.push(TagProblem { offset, message: format!("<{}> is not closed before </{}>", inner, name) });
+                        }
+                    }
+                    open.pop();
+                }
+                None => problems.push(TagProblem { offset: start, message: format!("</{}> has no matching opening tag", name) }),
+            }
+            continue;
+        }
+
+        if html[..end - 1].ends_with('/') || VOID_ELEMENTS.contains(&name.as_str()) {
+            continue;
+        }
+        if OPAQUE_ELEMENTS.contains(&name.as_str()) {
+            // Resume at the end tag, which then closes the element as usual
+            let end_tag = format!("</{}", name);
+            pos = html[pos..].to_ascii_lowercase().find(&end_tag).map_or(html.len(), |close| pos + close);
+        }
+        open.push((name, start));
+    }
+
+    for (name, offset) in open {
+        if !OPTIONAL_END_ELEMENTS.contains(&name.as_str()) {
+            problems.push(TagProblem { offset, message: format!("<{}> is never closed", name) });
+        }
+    }
+    problems.sort_by_key(|problem| problem.offset);
+    problems
+}
+
+/// Offset just past the `>` ending a tag whose attributes start at `from`
+fn tag_end(html: &str, from: usize) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in html[from..].char_indices() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '>' => return Some(from + index + 1),
+            None => {}
+        }
+    }
+    None
+}
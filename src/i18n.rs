@@ -0,0 +1,498 @@
+//! Translation key extraction for v0.6.0
+//!
+//! Lists the translation keys each template uses so translators know what
+//! every locale needs, and which table entries no template uses anymore.
+//! Also flattens nested catalogs, resolves `{{t_scope}}` key prefixes and
+//! implements the QA translation modes (missing key markers and
+//! pseudo-localization) and the versioned store translations are swapped in.
+
+use crate::error::{TemplateError, TemplateResult};
+use crate::suggestions::{extract_context_lines, find_line_column};
+use crate::value::TemplateValue;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+/// Directive a translation key was found in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranslationKind {
+    /// `{{t "key" name=value}}`
+    Translate,
+    /// One of the forms of `{{plural count "singular" "plural"}}`
+    Plural,
+}
+
+/// One use of a translation key in a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranslationKeyUsage {
+    /// Template the key is used in
+    pub template_name: String,
+    /// Translation key (the form itself for `{{plural}}`)
+    pub key: String,
+    /// Directive the key was found in
+    pub kind: TranslationKind,
+    /// Named arguments of `{{t}}` (`name` in `name=user.name`), or the count expression of `{{plural}}`
+    pub arguments: Vec<String>,
+    /// Line of the directive (1-based)
+    pub line: usize,
+    /// Column of the directive (1-based)
+    pub column: usize,
+    /// Configured locale -> whether its table has the key
+    pub locales: BTreeMap<String, bool>,
+}
+
+impl TranslationKeyUsage {
+    /// Configured locales whose table lacks the key
+    pub fn missing_locales(&self) -> Vec<&str> {
+        self.locales.iter()
+            .filter(|(_, present)| !**present)
+            .map(|(locale, _)| locale.as_str())
+            .collect()
+    }
+}
+
+/// Translation coverage of a template directory, from
+/// [`TemplateEngine::translation_report`](crate::TemplateEngine::translation_report)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranslationReport {
+    /// Every key usage, in template order
+    pub usages: Vec<TranslationKeyUsage>,
+    /// Locale -> keys used by templates but missing from its table, sorted
+    pub missing: BTreeMap<String, Vec<String>>,
+    /// Locale -> table entries no template uses, sorted
+    pub unused: BTreeMap<String, Vec<String>>,
+}
+
+impl TranslationReport {
+    pub(crate) fn new(usages: Vec<TranslationKeyUsage>, translations: &TranslationSnapshot) -> Self {
+        let used: BTreeSet<&str> = usages.iter().map(|usage| usage.key.as_str()).collect();
+        let mut missing = BTreeMap::new();
+        let mut unused = BTreeMap::new();
+
+        for (locale, table) in translations.iter() {
+            let missing_keys: Vec<String> = used.iter()
+                .filter(|key| !table.contains_key(**key))
+                .map(|key| key.to_string())
+                .collect();
+            if !missing_keys.is_empty() {
+                missing.insert(locale.clone(), missing_keys);
+            }
+
+            let mut unused_keys: Vec<String> = table.keys()
+                .filter(|key| !used.contains(key.as_str()))
+                .cloned()
+                .collect();
+            unused_keys.sort();
+            if !unused_keys.is_empty() {
+                unused.insert(locale.clone(), unused_keys);
+            }
+        }
+
+        Self { usages, missing, unused }
+    }
+
+    /// Whether every used key exists in every locale and every entry is used
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.unused.is_empty()
+    }
+}
+
+impl fmt::Display for TranslationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.missing.is_empty() {
+            writeln!(f, "Missing translations: none")?;
+        } else {
+            writeln!(f, "Missing translations:")?;
+            for (locale, keys) in &self.missing {
+                for key in keys {
+                    let locations: Vec<String> = self.usages.iter()
+                        .filter(|usage| &usage.key == key)
+                        .map(|usage| format!("{}:{}:{}", usage.template_name, usage.line, usage.column))
+                        .collect();
+                    writeln!(f, "  {}: {} ({})", locale, key, locations.join(", "))?;
+                }
+            }
+        }
+
+        if self.unused.is_empty() {
+            writeln!(f, "Unused translations: none")?;
+        } else {
+            writeln!(f, "Unused translations:")?;
+            for (locale, keys) in &self.unused {
+                for key in keys {
+                    writeln!(f, "  {}: {}", locale, key)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How translations are rendered, see
+/// [`TemplateEngine::set_translation_mode`](crate::TemplateEngine::set_translation_mode)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TranslationMode {
+    /// Missing keys fall back as usual: `{{t}}` renders the key, the
+    /// `translate` filter its value and locale defaults their English word
+    #[default]
+    Normal,
+    /// Missing keys render as `⟦key⟧`
+    MarkMissing,
+    /// Found translations are pseudo-localized, `Save` as `⟪Šávé~~⟫`, and missing keys
+    /// render as `⟦key⟧`, so hardcoded text is the only plain text left
+    PseudoLocalize,
+}
+
+/// A translation key a render looked up without finding it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingTranslation {
+    pub key: String,
+    /// Locale the key was looked up in; `None` when no locale was set
+    pub locale: Option<String>,
+    /// Times the render looked it up
+    pub count: usize,
+}
+
+/// One published version of a locale's translations, see
+/// [`TemplateEngine::swap_translations`](crate::TemplateEngine::swap_translations)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TranslationVersion(u64);
+
+impl TranslationVersion {
+    /// Versions of each locale count up from 1
+    pub fn number(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for TranslationVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+/// Versions of a locale kept for `rollback_translations`
+pub(crate) const TRANSLATION_HISTORY_LEN: usize = 8;
+
+/// Translation tables of every locale at one point in time; a render reads
+/// a single snapshot from start to end
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TranslationSnapshot {
+    locales: HashMap<String, (TranslationVersion, Arc<HashMap<String, String>>)>,
+}
+
+impl TranslationSnapshot {
+    /// Table of `locale`
+    pub(crate) fn get(&self, locale: &str) -> Option<&HashMap<String, String>> {
+        self.locales.get(locale).map(|(_, table)| table.as_ref())
+    }
+
+    /// Every locale with its table
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&String, &HashMap<String, String>)> {
+        self.locales.iter().map(|(locale, (_, table))| (locale, table.as_ref()))
+    }
+
+    /// Version of `locale` in this snapshot
+    pub(crate) fn version(&self, locale: &str) -> Option<TranslationVersion> {
+        self.locales.get(locale).map(|(version, _)| *version)
+    }
+}
+
+/// Published versions of one locale
+#[derive(Debug, Default)]
+struct LocaleHistory {
+    last: u64,
+    /// The latest `TRANSLATION_HISTORY_LEN` versions, oldest first
+    versions: VecDeque<(TranslationVersion, Arc<HashMap<String, String>>)>,
+}
+
+/// Translations shared by an engine and its clones. Swaps replace the
+/// current snapshot in one step: renders that already took theirs keep it.
+#[derive(Debug, Default)]
+pub(crate) struct TranslationStore {
+    current: RwLock<Arc<TranslationSnapshot>>,
+    /// Also serializes writers, so no swap is lost
+    history: Mutex<HashMap<String, LocaleHistory>>,
+}
+
+impl TranslationStore {
+    /// The current translations of every locale
+    pub(crate) fn snapshot(&self) -> Arc<TranslationSnapshot> {
+        Arc::clone(&self.current.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Publish `table` as the next version of `locale`
+    pub(crate) fn swap(&self, locale: &str, table: HashMap<String, String>) -> TranslationVersion {
+        let mut history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        let locale_history = history.entry(locale.to_string()).or_default();
+        locale_history.last += 1;
+        let version = TranslationVersion(locale_history.last);
+        let table = Arc::new(table);
+        locale_history.versions.push_back((version, Arc::clone(&table)));
+        if locale_history.versions.len() > TRANSLATION_HISTORY_LEN {
+            locale_history.versions.pop_front();
+        }
+        self.publish(locale, version, table);
+        version
+    }
+
+    /// Publish a version of `locale` again
+    pub(crate) fn rollback(&self, locale: &str, version: TranslationVersion) -> TemplateResult<()> {
+        let history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        let table = history.get(locale)
+            .and_then(|history| history.versions.iter().find(|(kept, _)| *kept == version))
+            .map(|(_, table)| Arc::clone(table))
+            .ok_or_else(|| TemplateError::Template(format!(
+                "Cannot roll locale '{}' back to translations {}: only the last {} versions are kept",
+                locale, version, TRANSLATION_HISTORY_LEN
+            )))?;
+        self.publish(locale, version, table);
+        Ok(())
+    }
+
+    fn publish(&self, locale: &str, version: TranslationVersion, table: Arc<HashMap<String, String>>) {
+        let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+        let mut snapshot = TranslationSnapshot::clone(&current);
+        snapshot.locales.insert(locale.to_string(), (version, table));
+        *current = Arc::new(snapshot);
+    }
+}
+
+/// Pseudo-localize a translation: letters get accents, the text is padded
+/// about 30% longer with `~` and wrapped in `⟪⟫`. Placeholders (`{name}`,
+/// `{{name}}`), tags and character references are kept as written.
+pub(crate) fn pseudo_localize(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() * 2);
+    out.push('⟪');
+    let mut letters = 0usize;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let kept = match c {
+            '{' if rest.starts_with("{{") => rest.find("}}").map(|end| end + 2),
+            '{' => rest.find('}').map(|end| end + 1),
+            '<' => rest.find('>').map(|end| end + 1),
+            '&' => rest.find(';').filter(|&end| rest[1..end].chars().all(|c| c.is_ascii_alphanumeric() || c == '#')).map(|end| end + 1),
+            _ => None,
+        };
+        if let Some(length) = kept {
+            out.push_str(&rest[..length]);
+            rest = &rest[length..];
+            continue;
+        }
+        if c.is_alphanumeric() {
+            letters += 1;
+        }
+        out.push(accented(c));
+        rest = &rest[c.len_utf8()..];
+    }
+    for _ in 0..(letters * 3).div_ceil(10) {
+        out.push('~');
+    }
+    out.push('⟫');
+    out
+}
+
+fn accented(c: char) -> char {
+    const ACCENTS: [(char, char); 28] = [
+        ('a', 'á'), ('c', 'ç'), ('e', 'é'), ('i', 'í'), ('n', 'ñ'), ('o', 'ó'), ('s', 'š'),
+        ('u', 'ú'), ('y', 'ý'), ('z', 'ž'), ('g', 'ĝ'), ('h', 'ĥ'), ('j', 'ĵ'), ('w', 'ŵ'),
+        ('A', 'Å'), ('C', 'Ç'), ('E', 'É'), ('I', 'Î'), ('N', 'Ñ'), ('O', 'Ö'), ('S', 'Š'),
+        ('U', 'Ü'), ('Y', 'Ý'), ('Z', 'Ž'), ('G', 'Ĝ'), ('H', 'Ĥ'), ('J', 'Ĵ'), ('W', 'Ŵ'),
+    ];
+    ACCENTS.iter().find(|(plain, _)| *plain == c).map_or(c, |(_, accented)| *accented)
+}
+
+/// Find the translation key usages of one template source; `locales` is
+/// left for the engine to fill in
+pub(crate) fn scan_translation_keys(template_name: &str, content: &str) -> Vec<TranslationKeyUsage> {
+    let mut usages = Vec::new();
+    let mut scopes: Vec<String> = Vec::new();
+    let mut pos = 0;
+
+    while let Some(relative_start) = content[pos..].find("{{") {
+        let start = pos + relative_start;
+        let Some(end) = content[start..].find("}}") else { break };
+        pos = start + end + 2;
+
+        let directive = content[start + 2..start + end].trim();
+        let (line, column) = find_line_column(content, start);
+        let usage = |key: &str, kind, arguments| TranslationKeyUsage {
+            template_name: template_name.to_string(),
+            key: key.to_string(),
+            kind,
+            arguments,
+            line,
+            column,
+            locales: BTreeMap::new(),
+        };
+
+        if let Some(args) = directive.strip_prefix("t_scope ") {
+            scopes.push(scoped_key(&scopes, split_key(args.trim()).0));
+        } else if directive == "/t_scope" {
+            scopes.pop();
+        } else if let Some(args) = directive.strip_prefix("t ") {
+            let (key, rest) = split_key(args.trim());
+            if key.is_empty() {
+                continue;
+            }
+            let arguments = rest.split_whitespace()
+                .filter_map(|argument| argument.split_once('='))
+                .map(|(name, _)| name.to_string())
+                .collect();
+            usages.push(usage(&scoped_key(&scopes, key), TranslationKind::Translate, arguments));
+        } else if let Some(args) = directive.strip_prefix("plural ") {
+            let parts: Vec<&str> = args.split_whitespace().collect();
+            if let [count, singular, plural] = parts[..] {
+                for form in [singular, plural] {
+                    let form = form.trim_matches('"').trim_matches('\'');
+                    usages.push(usage(form, TranslationKind::Plural, vec![count.to_string()]));
+                }
+            }
+        }
+    }
+
+    usages
+}
+
+/// Split `"key" name=value` into the key and the remaining arguments
+pub(crate) fn split_key(args: &str) -> (&str, &str) {
+    if let Some(quote) = args.chars().next().filter(|first| *first == '"' || *first == '\'') {
+        if let Some(close) = args[1..].find(quote) {
+            return (&args[1..close + 1], &args[close + 2..]);
+        }
+    }
+    args.split_once(char::is_whitespace).unwrap_or((args, ""))
+}
+
+/// `key` under the innermost of the open `{{t_scope}}` prefixes
+fn scoped_key(scopes: &[String], key: &str) -> String {
+    match scopes.last() {
+        Some(prefix) => format!("{}.{}", prefix, key),
+        None => key.to_string(),
+    }
+}
+
+/// Remove the `{{t_scope "prefix"}}...{{/t_scope}}` blocks of a template,
+/// prefixing the keys of the `{{t}}` directives they enclose
+///
+/// Scopes nest: `{{t "title"}}` inside `{{t_scope "nav"}}{{t_scope "menu"}}`
+/// becomes `{{t "nav.menu.title"}}`. The rewrite is lexical, so templates
+/// included from a scope and macros called in it keep their own keys.
+pub(crate) fn resolve_translation_scopes(template: &str) -> TemplateResult<String> {
+    let mut result = String::with_capacity(template.len());
+    let mut scopes: Vec<(String, usize)> = Vec::new();
+    let mut pos = 0;
+
+    while let Some(relative_start) = template[pos..].find("{{") {
+        let start = pos + relative_start;
+        result.push_str(&template[pos..start]);
+        let Some(end) = template[start..].find("}}") else {
+            pos = start;
+            break;
+        };
+        pos = start + end + 2;
+        let tag = &template[start..pos];
+        let directive = tag[2..tag.len() - 2].trim();
+
+        if let Some(args) = directive.strip_prefix("t_scope ") {
+            let prefix = split_key(args.trim()).0;
+            if prefix.is_empty() || !args.trim().starts_with(['"', '\'']) {
+                return Err(scope_error(template, start, format!("Malformed '{}': expected '{{{{t_scope \"prefix\"}}}}'", tag)));
+            }
+            let scopes_so_far: Vec<String> = scopes.iter().map(|(prefix, _)| prefix.clone()).collect();
+            scopes.push((scoped_key(&scopes_so_far, prefix), start));
+        } else if directive == "/t_scope" {
+            if scopes.pop().is_none() {
+                return Err(scope_error(template, start, "Unexpected closing tag '{{/t_scope}}'".to_string()));
+            }
+        } else if let (Some(args), Some((prefix, _))) = (directive.strip_prefix("t "), scopes.last()) {
+            let args = args.trim();
+            let (key, rest) = split_key(args);
+            let quote = args.chars().next().filter(|first| *first == '"' || *first == '\'').unwrap_or('"');
+            result.push_str(&format!("{{{{t {q}{}.{}{q}", prefix, key, q = quote));
+            if !rest.trim().is_empty() {
+                result.push(' ');
+                result.push_str(rest.trim());
+            }
+            result.push_str("}}");
+        } else {
+            result.push_str(tag);
+        }
+    }
+    result.push_str(&template[pos..]);
+
+    if let Some((prefix, offset)) = scopes.pop() {
+        return Err(scope_error(template, offset, format!("Unclosed translation scope '{}': missing '{{{{/t_scope}}}}'", prefix)));
+    }
+    Ok(result)
+}
+
+fn scope_error(template: &str, offset: usize, message: String) -> TemplateError {
+    let (line, column) = find_line_column(template, offset);
+    TemplateError::ParseWithLocation {
+        message,
+        line,
+        column,
+        template_name: None,
+        context_lines: extract_context_lines(template, line, 2),
+    }
+}
+
+/// Flatten a nested translation catalog into dotted keys
+///
+/// Object keys are sorted first so a conflict is always reported the same way.
+pub(crate) fn flatten_translation_catalog(locale: &str, catalog: &TemplateValue) -> TemplateResult<HashMap<String, String>> {
+    let TemplateValue::Object(_) = catalog else {
+        return Err(TemplateError::Template(format!(
+            "Translation catalog of locale '{}' must be an object", locale
+        )));
+    };
+    let mut flat = HashMap::new();
+    let mut spellings = HashMap::new();
+    flatten_entries(locale, &mut Vec::new(), catalog, &mut flat, &mut spellings)?;
+    Ok(flat)
+}
+
+fn flatten_entries<'a>(
+    locale: &str,
+    path: &mut Vec<&'a str>,
+    value: &'a TemplateValue,
+    flat: &mut HashMap<String, String>,
+    spellings: &mut HashMap<String, String>,
+) -> TemplateResult<()> {
+    let text = match value {
+        TemplateValue::Object(entries) => {
+            let mut keys: Vec<&String> = entries.keys().collect();
+            keys.sort();
+            for key in keys {
+                path.push(key);
+                flatten_entries(locale, path, &entries[key], flat, spellings)?;
+                path.pop();
+            }
+            return Ok(());
+        }
+        TemplateValue::Array(_) => {
+            return Err(TemplateError::Template(format!(
+                "Translation '{}' of locale '{}' is an array; expected a string or an object",
+                path.join("."), locale
+            )));
+        }
+        TemplateValue::String(text) => text.clone(),
+        TemplateValue::Bool(flag) => flag.to_string(),
+        TemplateValue::Number(number) => number.to_string(),
+        TemplateValue::Float(number) => number.to_string(),
+        // A null translation is left missing
+        TemplateValue::Null => return Ok(()),
+    };
+
+    let key = path.join(".");
+    let spelling = path.iter().map(|part| format!("'{}'", part)).collect::<Vec<_>>().join(" > ");
+    if let Some(previous) = spellings.insert(key.clone(), spelling.clone()) {
+        return Err(TemplateError::Template(format!(
+            "Translation key '{}' of locale '{}' is defined twice: as {} and as {}",
+            key, locale, previous, spelling
+        )));
+    }
+    flat.insert(key, text);
+    Ok(())
+}
@@ -0,0 +1,205 @@
+//! Include and layout resolution trees for v0.6.0
+//!
+//! [`TemplateEngine::render_with_trace`](crate::TemplateEngine::render_with_trace)
+//! records which templates a render pulled in, whether each came from the
+//! cache or the disk and how long loading and parsing it took.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::time::Duration;
+
+/// How a template was reached from its parent node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncludeRelation {
+    /// The rendered template itself
+    Root,
+    /// `{{include "..."}}`
+    Include,
+    /// `{{extends "..."}}`
+    Extends,
+}
+
+/// Where a traced template's source came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadSource {
+    /// The template cache (or, for layouts, the already parsed layout)
+    Cache,
+    /// The template file, read during this render
+    Disk,
+    /// A template registered in memory by `import_pack`
+    Registered,
+}
+
+/// One template of an [`IncludeTree`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludeNode {
+    /// Template name as it was requested
+    pub name: String,
+    pub relation: IncludeRelation,
+    /// `None` for inline template strings, which are never loaded
+    pub source: Option<LoadSource>,
+    /// Time spent loading the source
+    pub load_nanos: u64,
+    /// Time spent parsing its layout structure (`{{extends}}`/`{{block}}`);
+    /// zero for includes, which are spliced in without a separate parse
+    pub parse_nanos: u64,
+    /// Templates it includes or extends, in resolution order
+    pub children: Vec<IncludeNode>,
+}
+
+/// Templates loaded by one render, as a tree rooted at the rendered template.
+///
+/// Layout parents are nested under the template extending them. Includes
+/// hang under the template whose source holds them once layouts are
+/// merged, so includes written in a layout appear under the page; includes
+/// inside a loop appear once per iteration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludeTree {
+    pub root: IncludeNode,
+}
+
+impl IncludeNode {
+    fn new(name: &str, relation: IncludeRelation) -> Self {
+        Self {
+            name: name.to_string(),
+            relation,
+            source: None,
+            load_nanos: 0,
+            parse_nanos: 0,
+            children: Vec::new(),
+        }
+    }
+
+    /// This node and every node below it, depth first
+    pub fn iter(&self) -> impl Iterator<Item = &IncludeNode> {
+        let mut pending = vec![self];
+        std::iter::from_fn(move || {
+            let node = pending.pop()?;
+            pending.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+
+    fn write_text(&self, depth: usize, out: &mut String) {
+        let relation = match self.relation {
+            IncludeRelation::Root => "",
+            IncludeRelation::Include => "include ",
+            IncludeRelation::Extends => "extends ",
+        };
+        let source = match self.source {
+            Some(LoadSource::Cache) => "cache",
+            Some(LoadSource::Disk) => "disk",
+            Some(LoadSource::Registered) => "registered",
+            None => "inline",
+        };
+        out.push_str(&format!(
+            "{:indent$}{}{} [{}] load {:?}",
+            "", relation, self.name, source, Duration::from_nanos(self.load_nanos), indent = depth * 2
+        ));
+        if self.parse_nanos > 0 {
+            out.push_str(&format!(", parse {:?}", Duration::from_nanos(self.parse_nanos)));
+        }
+        out.push('\n');
+        for child in &self.children {
+            child.write_text(depth + 1, out);
+        }
+    }
+}
+
+impl IncludeTree {
+    /// Levels of the tree; 1 when the template includes nothing
+    pub fn depth(&self) -> usize {
+        fn depth(node: &IncludeNode) -> usize {
+            1 + node.children.iter().map(depth).max().unwrap_or(0)
+        }
+        depth(&self.root)
+    }
+
+    /// Names of the loaded templates, sorted and without repeats
+    pub fn unique_templates(&self) -> Vec<&str> {
+        let names: BTreeSet<&str> = self.root.iter()
+            .filter(|node| node.source.is_some())
+            .map(|node| node.name.as_str())
+            .collect();
+        names.into_iter().collect()
+    }
+
+    /// Indented report, one template per line:
+    ///
+    /// ```text
+    /// page.html [disk] load 41µs, parse 3µs
+    ///   extends base.html [disk] load 18µs, parse 2µs
+    ///   include nav.html [cache] load 1µs
+    /// ```
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        self.root.write_text(0, &mut out);
+        out
+    }
+}
+
+impl fmt::Display for IncludeTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_text())
+    }
+}
+
+/// Builds an [`IncludeTree`] while a render runs
+#[derive(Debug)]
+pub(crate) struct IncludeRecorder {
+    root: IncludeNode,
+    /// Child indexes leading from the root to the open node
+    open: Vec<usize>,
+}
+
+impl IncludeRecorder {
+    pub(crate) fn new(root: &str) -> Self {
+        Self {
+            root: IncludeNode::new(root, IncludeRelation::Root),
+            open: Vec::new(),
+        }
+    }
+
+    fn current(&mut self) -> &mut IncludeNode {
+        let mut node = &mut self.root;
+        for &index in &self.open {
+            node = &mut node.children[index];
+        }
+        node
+    }
+
+    /// Start a child of the open node; it stays open until `close`
+    pub(crate) fn open(&mut self, name: &str, relation: IncludeRelation) {
+        let current = self.current();
+        current.children.push(IncludeNode::new(name, relation));
+        let index = current.children.len() - 1;
+        self.open.push(index);
+    }
+
+    pub(crate) fn close(&mut self) {
+        self.open.pop();
+    }
+
+    /// Note how the open node's source was loaded; loads of other templates are ignored
+    pub(crate) fn loaded(&mut self, name: &str, source: LoadSource, nanos: u64) {
+        let node = self.current();
+        if node.name == name && node.source.is_none() {
+            node.source = Some(source);
+            node.load_nanos = nanos;
+        }
+    }
+
+    /// Add layout parsing time to the open node
+    #[cfg_attr(not(feature = "inheritance"), allow(dead_code))]
+    pub(crate) fn parsed(&mut self, name: &str, nanos: u64) {
+        let node = self.current();
+        if node.name == name {
+            node.parse_nanos += nanos;
+        }
+    }
+
+    /// The tree recorded so far
+    pub(crate) fn tree(&self) -> IncludeTree {
+        IncludeTree { root: self.root.clone() }
+    }
+}
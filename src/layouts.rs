@@ -1,7 +1,10 @@
 //! Template layouts and inheritance system for v0.2.0
 
+use crate::engine::{check_super_placement, is_super_tag, is_variable_path};
 use crate::error::{TemplateError, TemplateResult};
 use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
 
 /// Block definition for template inheritance
 #[derive(Debug, Clone)]
@@ -17,8 +20,11 @@ pub struct Block {
 #[derive(Debug, Clone)]
 pub struct LayoutInfo {
     pub extends: Option<String>, // Parent template name
+    pub extends_variable: Option<String>, // Context variable naming the parent ({{extends var}})
     pub blocks: HashMap<String, Block>,
-    pub content: String,
+    /// Source without its `{{extends}}` tag; shares the cached source when
+    /// there is none
+    pub content: Arc<str>,
 }
 
 /// Template layout processor
@@ -35,56 +41,77 @@ impl LayoutProcessor {
     }
     
     /// Parse template and extract layout information
-    pub fn parse_template(&mut self, name: &str, content: &str) -> TemplateResult<LayoutInfo> {
+    pub fn parse_template(&mut self, name: &str, content: impl Into<Arc<str>>) -> TemplateResult<&LayoutInfo> {
+        let content = content.into();
+        check_super_placement(&content)?;
         let mut layout_info = LayoutInfo {
             extends: None,
+            extends_variable: None,
             blocks: HashMap::new(),
-            content: content.to_string(),
+            content: Arc::clone(&content),
         };
         
         // Check for {{extends}} directive
-        if let Some(extends_match) = self.find_extends_directive(content) {
-            layout_info.extends = Some(extends_match);
+        if let Some(extends_match) = self.find_extends_directive(&content) {
+            // Unquoted names are resolved from the render context by the engine
+            if extends_match.starts_with('"') || extends_match.starts_with('\'') {
+                layout_info.extends = Some(extends_match.trim_matches('"').trim_matches('\'').to_string());
+            } else {
+                layout_info.extends_variable = Some(extends_match);
+            }
             // Remove extends directive from content
-            layout_info.content = self.remove_extends_directive(content);
+            layout_info.content = self.remove_extends_directive(&content).into();
         }
         
         // Extract blocks from template
         layout_info.blocks = self.extract_blocks(&layout_info.content)?;
         
-        // Cache the layout info
-        self.templates.insert(name.to_string(), layout_info.clone());
-        
-        Ok(layout_info)
+        // Cache the layout info, reusing the key when parsed again
+        if self.templates.contains_key(name) {
+            let cached = self.templates.get_mut(name).expect("layout was just found");
+            *cached = layout_info;
+            return Ok(cached);
+        }
+        Ok(self.templates.entry(name.to_string()).or_insert(layout_info))
     }
     
-    /// Resolve inheritance chain and merge blocks
+    /// Resolve inheritance chain and merge blocks.
+    /// 
+    /// The most derived override of a block wins; its `{{super}}` is the
+    /// override one level up, down to the root layout's default content.
     pub fn resolve_inheritance(&self, template_name: &str) -> TemplateResult<String> {
-        let layout_info = self.templates.get(template_name)
+        let mut current = self.templates.get(template_name)
             .ok_or_else(|| TemplateError::Template(format!("Template '{}' not found", template_name)))?;
         
-        
-        if let Some(parent_name) = &layout_info.extends {
-            // Get parent template
-            let parent_layout = self.templates.get(parent_name)
+        // Overrides of every template below the root layout, most derived first
+        let mut overrides = Vec::new();
+        while let Some(parent_name) = &current.extends {
+            if overrides.len() > self.templates.len() {
+                return Err(TemplateError::Template(format!(
+                    "Circular template inheritance: '{}' extends itself through its parents", template_name
+                )));
+            }
+            overrides.push(&current.blocks);
+            current = self.templates.get(parent_name)
                 .ok_or_else(|| TemplateError::Template(format!("Parent template '{}' not found", parent_name)))?;
-            
-            // Start with parent template content
-            let mut resolved_content = parent_layout.content.clone();
-            
-            // Recursively resolve parent inheritance
-            if parent_layout.extends.is_some() {
-                resolved_content = self.resolve_inheritance(parent_name)?;
+        }
+        
+        self.merge_blocks(&current.content, &overrides)
+    }
+    
+    /// Whether a block tag of the template declares parameters
+    pub fn has_block_parameters(&self, template_name: &str) -> bool {
+        let Some(layout) = self.templates.get(template_name) else { return false };
+        let mut pos = 0;
+        while let Some(block_start) = layout.content[pos..].find("{{block ") {
+            let tag_start = pos + block_start + 8;
+            let Some(tag_end) = layout.content[tag_start..].find("}}") else { break };
+            if layout.content[tag_start..tag_start + tag_end].contains('=') {
+                return true;
             }
-            
-            // Replace blocks in parent with child blocks
-            resolved_content = self.merge_blocks(&resolved_content, &layout_info.blocks, &parent_layout.blocks)?;
-            
-            Ok(resolved_content)
-        } else {
-            // No inheritance, just replace blocks with their content
-            self.merge_blocks(&layout_info.content, &layout_info.blocks, &HashMap::new())
+            pos = tag_start + tag_end;
         }
+        false
     }
     
     /// Find {{extends "template"}} directive
@@ -92,8 +119,7 @@ impl LayoutProcessor {
         if let Some(start) = content.find("{{extends ") {
             if let Some(end) = content[start..].find("}}") {
                 let directive = &content[start + 10..start + end];
-                let template_name = directive.trim().trim_matches('"').trim_matches('\'');
-                return Some(template_name.to_string());
+                return Some(directive.trim().to_string());
             }
         }
         None
@@ -111,180 +137,203 @@ impl LayoutProcessor {
         content.to_string()
     }
     
-    /// Extract {{block name}}...{{/block}} definitions
+    /// Extract {{block name}}...{{/block}} definitions, nested ones included,
+    /// so a child can override a block at any depth of its parent
     fn extract_blocks(&self, content: &str) -> TemplateResult<HashMap<String, Block>> {
         let mut blocks = HashMap::new();
-        let mut pos = 0;
-        
-        while let Some(block_start) = content[pos..].find("{{block ") {
-            let absolute_start = pos + block_start;
-            
-            // Find end of opening tag
-            let tag_end = content[absolute_start..].find("}}")
-                .ok_or_else(|| TemplateError::Parse("Unclosed block directive".to_string()))?;
-            
-            let block_name = content[absolute_start + 8..absolute_start + tag_end].trim();
-            let content_start = absolute_start + tag_end + 2;
-            
-            // Find matching {{/block}}
-            let block_end = self.find_matching_block_end(&content[content_start..], block_name)?;
-            let absolute_end = content_start + block_end;
-            
-            let block_content = content[content_start..absolute_end].trim().to_string();
-            
-            blocks.insert(block_name.to_string(), Block {
-                name: block_name.to_string(),
-                content: block_content,
+        for span in scan_blocks(content)? {
+            blocks.insert(span.name.to_string(), Block {
+                name: span.name.to_string(),
+                content: content[span.content].trim().to_string(),
                 parent_content: None,
             });
-            
-            // Calculate how far to skip - need to skip past the entire {{/block}} tag
-            let end_tag_len = if content[absolute_end..].starts_with(&format!("{{{{/block {}}}}}", block_name)) {
-                block_name.len() + 10 // {{/block name}}
-            } else {
-                9 // {{/block}}
-            };
-            pos = absolute_end + end_tag_len;
         }
         Ok(blocks)
     }
     
-    /// Find matching {{/block}} for a given block, handling nested blocks properly
-    fn find_matching_block_end(&self, content: &str, block_name: &str) -> TemplateResult<usize> {
-        let end_tag = format!("{{{{/block {}}}}}", block_name);
-        let simple_end_tag = "{{/block}}";
-        
-        let mut pos = 0;
-        let mut nesting_level = 1; // We're already inside one block
+    /// Replace the blocks of a template with their overrides, most derived
+    /// first, binding the parameters each block tag declares
+    fn merge_blocks(&self, template_content: &str, overrides: &[&HashMap<String, Block>]) -> TemplateResult<String> {
+        let mut result = template_content.to_string();
         
-        while pos < content.len() {
-            // Look for either an opening or closing block
-            let remaining = &content[pos..];
+        // Blocks nested in a replaced block are merged with its content
+        let spans = scan_blocks(template_content)?;
+        for span in spans.iter().rev().filter(|span| span.depth == 0) {
+            let default_content = template_content[span.content.clone()].trim();
             
-            if let Some(open_pos) = remaining.find("{{block ") {
-                if let Some(close_pos) = remaining.find(simple_end_tag) {
-                    let abs_open = pos + open_pos;
-                    let abs_close = pos + close_pos;
-                    
-                    if abs_open < abs_close {
-                        // Opening block comes first - increase nesting
-                        nesting_level += 1;
-                        pos = abs_open + 8; // Skip past "{{block "
-                    } else {
-                        // Closing block comes first - decrease nesting
-                        nesting_level -= 1;
-                        if nesting_level == 0 {
-                            return Ok(abs_close);
-                        }
-                        pos = abs_close + simple_end_tag.len();
-                    }
-                } else if let Some(close_pos) = remaining.find(&end_tag) {
-                    // Found specific closing tag
-                    let abs_close = pos + close_pos;
-                    nesting_level -= 1;
-                    if nesting_level == 0 {
-                        return Ok(abs_close);
-                    }
-                    pos = abs_close + end_tag.len();
-                } else {
-                    // Found opening but no closing - error
-                    break;
-                }
-            } else if let Some(close_pos) = remaining.find(simple_end_tag) {
-                // Only closing blocks remain
-                let abs_close = pos + close_pos;
-                nesting_level -= 1;
-                if nesting_level == 0 {
-                    return Ok(abs_close);
+            // Apply overrides from the root down, each {{super}} being the content so far
+            let mut replacement_content = self.process_super_directive(default_content, "");
+            for blocks in overrides.iter().rev() {
+                if let Some(child_block) = blocks.get(span.name) {
+                    replacement_content = self.process_super_directive(&child_block.content, &replacement_content);
                 }
-                pos = abs_close + simple_end_tag.len();
-            } else if let Some(close_pos) = remaining.find(&end_tag) {
-                // Only specific closing blocks remain
-                let abs_close = pos + close_pos;
-                nesting_level -= 1;
-                if nesting_level == 0 {
-                    return Ok(abs_close);
-                }
-                pos = abs_close + end_tag.len();
-            } else {
-                // No more blocks found
-                break;
             }
+            // Blocks nested in the chosen content can be overridden too
+            replacement_content = self.merge_blocks(&replacement_content, overrides)?;
+            replacement_content = bind_block_parameters(&replacement_content, &span.parameters);
+            
+            result.replace_range(span.start..span.end, &replacement_content);
         }
         
-        Err(TemplateError::Parse(format!("Missing {{{{/block}}}} for block '{}'", block_name)))
+        Ok(result)
     }
     
-    /// Merge child blocks with parent template
-    fn merge_blocks(
-        &self, 
-        template_content: &str, 
-        child_blocks: &HashMap<String, Block>,
-        _parent_blocks: &HashMap<String, Block>
-    ) -> TemplateResult<String> {
-        let mut result = template_content.to_string();
-        
-        // Find and replace all blocks from end to start to avoid position invalidation
-        let mut blocks_to_replace = Vec::new();
+    /// Replace the `{{super}}` tags of block content, `{{ super }}` and
+    /// whitespace control markers included, with the parent block content
+    fn process_super_directive(&self, content: &str, parent_content: &str) -> String {
+        let mut result = String::with_capacity(content.len() + parent_content.len());
         let mut pos = 0;
+        while let Some(relative_start) = content[pos..].find("{{") {
+            let start = pos + relative_start;
+            let Some(end) = content[start..].find("}}") else { break };
+            if is_super_tag(&content[start + 2..start + end]) {
+                result.push_str(&content[pos..start]);
+                result.push_str(parent_content);
+            } else {
+                result.push_str(&content[pos..start + end + 2]);
+            }
+            pos = start + end + 2;
+        }
+        result.push_str(&content[pos..]);
+        result
+    }
+}
+
+/// A `{{block}}` definition and where its tags sit in the template
+#[derive(Debug)]
+struct BlockSpan<'a> {
+    name: &'a str,
+    parameters: Vec<(String, String)>,
+    /// Offset of the opening tag
+    start: usize,
+    /// Body between the opening and the closing tag
+    content: Range<usize>,
+    /// Offset just past the closing tag
+    end: usize,
+    /// Number of blocks enclosing this one
+    depth: usize,
+}
+
+/// Find the `{{block}}` definitions of a template in source order, nested
+/// ones included. Each `{{/block}}` or `{{/block name}}` closes the
+/// innermost open block, which a closing name must match.
+fn scan_blocks(content: &str) -> TemplateResult<Vec<BlockSpan<'_>>> {
+    let mut spans = Vec::new();
+    // Open blocks, their body and closing tag not found yet
+    let mut open: Vec<BlockSpan> = Vec::new();
+    let mut pos = 0;
+    
+    while let Some(relative_start) = content[pos..].find("{{") {
+        let start = pos + relative_start;
+        let rest = &content[start..];
+        pos = start + 2;
         
-        // First pass: find all block positions
-        while let Some(block_start) = result[pos..].find("{{block ") {
-            let absolute_start = pos + block_start;
-            
-            // Find end of opening tag
-            let tag_end = result[absolute_start..].find("}}")
+        if let Some(tag) = rest.strip_prefix("{{block ") {
+            let tag_end = tag.find("}}")
                 .ok_or_else(|| TemplateError::Parse("Unclosed block directive".to_string()))?;
-            
-            let block_directive = &result[absolute_start + 8..absolute_start + tag_end];
-            let parts: Vec<&str> = block_directive.split_whitespace().collect();
-            let block_name = parts[0];
-            
-            // Find content between opening and closing tags
-            let content_start = absolute_start + tag_end + 2;
-            let block_content_end = self.find_matching_block_end(&result[content_start..], block_name)?;
-            let absolute_content_end = content_start + block_content_end;
-            let default_content = result[content_start..absolute_content_end].trim().to_string();
-            
-            // Find end of closing tag - absolute_content_end points to START of closing tag
-            let closing_tag_text = &result[absolute_content_end..];
-            let end_tag_end = if closing_tag_text.starts_with(&format!("{{{{/block {}}}}}", block_name)) {
-                absolute_content_end + format!("{{{{/block {}}}}}", block_name).len()
-            } else if closing_tag_text.starts_with("{{/block}}") {
-                absolute_content_end + "{{/block}}".len()
-            } else {
+            let (name, parameters) = parse_block_tag(&tag[..tag_end])?;
+            pos = start + 8 + tag_end + 2;
+            open.push(BlockSpan { name, parameters, start, content: pos..pos, end: pos, depth: open.len() });
+        } else if let Some(tag) = rest.strip_prefix("{{/block") {
+            let Some(tag_end) = tag.find("}}") else { continue };
+            let closing = &tag[..tag_end];
+            // `{{/blocks}}` is some other tag
+            if !closing.is_empty() && !closing.starts_with(char::is_whitespace) {
+                continue;
+            }
+            let Some(mut span) = open.pop() else {
                 return Err(TemplateError::Parse(format!(
-                    "Invalid block end position for '{}'. Found: '{}'", 
-                    block_name, 
-                    &closing_tag_text[..closing_tag_text.len().min(20)]
+                    "Unexpected '{{{{/block{}}}}}' without an open {{{{block}}}}", closing
                 )));
             };
-            
-            // Determine replacement content
-            let replacement_content = if let Some(child_block) = child_blocks.get(block_name) {
-                // Use child block content, process {{super}} if present
-                self.process_super_directive(&child_block.content, &default_content)
-            } else {
-                // Use default content from parent template
-                default_content
-            };
-            
-            blocks_to_replace.push((absolute_start, end_tag_end, replacement_content));
-            pos = end_tag_end;
+            if !closing.trim().is_empty() && closing.trim() != span.name {
+                return Err(TemplateError::Parse(format!(
+                    "'{{{{/block {}}}}}' does not match '{{{{block {}}}}}'", closing.trim(), span.name
+                )));
+            }
+            pos = start + 8 + tag_end + 2;
+            span.content.end = start;
+            span.end = pos;
+            spans.push(span);
         }
-        
-        // Second pass: replace blocks from end to start
-        blocks_to_replace.reverse();
-        for (start, end, replacement) in blocks_to_replace {
-            result.replace_range(start..end, &replacement);
+    }
+    
+    if let Some(span) = open.pop() {
+        return Err(TemplateError::Parse(format!("Missing {{{{/block}}}} for block '{}'", span.name)));
+    }
+    spans.sort_by_key(|span| span.start);
+    Ok(spans)
+}
+
+/// Split `name param=path ...` into the block name and its parameters
+fn parse_block_tag(tag: &str) -> TemplateResult<(&str, Vec<(String, String)>)> {
+    let mut parts = tag.split_whitespace();
+    let name = parts.next().unwrap_or_default();
+    let mut parameters = Vec::new();
+    for part in parts {
+        match part.split_once('=') {
+            Some((param, path)) if !param.contains('.') && is_variable_path(param) && is_variable_path(path) => {
+                parameters.push((param.to_string(), path.to_string()));
+            }
+            _ => return Err(TemplateError::Parse(format!(
+                "Invalid parameter '{}' of block '{}': expected name=variable.path", part, name
+            ))),
         }
-        
-        Ok(result)
+    }
+    Ok((name, parameters))
+}
+
+/// Point the block parameters used in `content` at the paths they were
+/// declared with, so they read the context the parent renders with
+fn bind_block_parameters(content: &str, parameters: &[(String, String)]) -> String {
+    if parameters.is_empty() {
+        return content.to_string();
     }
     
-    /// Process {{super}} directive in block content
-    fn process_super_directive(&self, content: &str, parent_content: &str) -> String {
-        content.replace("{{super}}", parent_content)
+    let mut result = String::with_capacity(content.len());
+    let mut pos = 0;
+    while let Some(relative_start) = content[pos..].find("{{") {
+        let tag_start = pos + relative_start + 2;
+        let Some(tag_end) = content[tag_start..].find("}}") else { break };
+        result.push_str(&content[pos..tag_start]);
+        bind_tag_parameters(&content[tag_start..tag_start + tag_end], parameters, &mut result);
+        pos = tag_start + tag_end;
+    }
+    result.push_str(&content[pos..]);
+    result
+}
+
+/// Replace the parameter names that start a variable path in one tag;
+/// quoted text, properties, filter names and `name=` arguments are kept
+fn bind_tag_parameters(tag: &str, parameters: &[(String, String)], out: &mut String) {
+    let mut quote = None;
+    let mut previous = ' ';
+    let mut chars = tag.char_indices().peekable();
+    
+    while let Some((start, c)) = chars.next() {
+        if let Some(open) = quote {
+            if c == open {
+                quote = None;
+            }
+        } else if c == '"' || c == '\'' {
+            quote = Some(c);
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start + c.len_utf8();
+            while let Some(&(index, next)) = chars.peek().filter(|(_, next)| next.is_alphanumeric() || *next == '_') {
+                end = index + next.len_utf8();
+                chars.next();
+            }
+            let word = &tag[start..end];
+            let follows = chars.peek().map_or(' ', |(_, next)| *next);
+            let path = parameters.iter()
+                .find(|(param, _)| param == word)
+                .filter(|_| !matches!(previous, '.' | '|' | '/') && !matches!(follows, '=' | '('));
+            out.push_str(path.map_or(word, |(_, path)| path.as_str()));
+            previous = word.chars().last().unwrap_or(' ');
+            continue;
+        }
+        out.push(c);
+        previous = c;
     }
 }
 
@@ -319,6 +368,27 @@ mod tests {
         assert_eq!(layout.blocks.len(), 1);
         assert!(layout.blocks.contains_key("content"));
     }
+
+    #[test]
+    fn test_parse_template_with_dynamic_extends() {
+        let mut processor = LayoutProcessor::new();
+        let content = "{{extends page.layout}}{{block content}}Child{{/block}}";
+
+        let layout = processor.parse_template("child.html", content).unwrap();
+        assert!(layout.extends.is_none());
+        assert_eq!(layout.extends_variable, Some("page.layout".to_string()));
+        assert!(layout.blocks.contains_key("content"));
+    }
+
+    #[test]
+    fn test_block_parameters_bind_variable_roots_only() {
+        let parameters = vec![("title".to_string(), "page.title".to_string())];
+        let content = "<h1>{{title|upper}}</h1>{{if title}}{{title.length}}{{/if}}{{t \"k\" title=user.name}}{{item.title}}\"title\"{{\"title\"}}";
+        assert_eq!(
+            bind_block_parameters(content, &parameters),
+            "<h1>{{page.title|upper}}</h1>{{if page.title}}{{page.title.length}}{{/if}}{{t \"k\" title=user.name}}{{item.title}}\"title\"{{\"title\"}}"
+        );
+    }
     
     #[test]
     fn test_super_directive() {
@@ -26,17 +26,63 @@
 //! |---------|--------|-------------|
 //! | **Variables** | `{{name}}` | HTML-escaped variable output |
 //! | **Raw HTML** | `{{& html}}` | Unescaped HTML output (use carefully) |
-//! | **Conditionals** | `{{if condition}}...{{/if}}` | Conditional rendering with comparison operators |
-//! | **Loops** | `{{for item in items}}...{{/for}}` | Iterate over arrays and nested structures |
+//! | **Conditionals** | `{{if condition}}...{{elseif condition}}...{{else}}...{{/if}}` `{{if !user.verified}}` | Conditional rendering with comparison operators and `!` / `not` negation |
+//! | **Loops** | `{{for item in items}}...{{empty}}...{{/for}}` | Iterate over arrays and nested structures, with an optional section for nothing to iterate |
+//! | **Ranges** | `{{for i in 1..=5}}` `{{for i in range(0, n, 2)}}` | Loop over integers, up to a configurable number of items |
 //! | **Deep Access** | `{{user.profile.name}}` | Unlimited depth object property access |
 //! | **Includes** | `{{include "template.html"}}` | Template composition and reuse |
+//! | **Scoped Includes** | `{{include "card.html" with title=product.name price=product.price}}` `{{include "badge.html" with label="New" only}}` | Named arguments for a partial; `only` isolates it from the outer context |
+//! | **Shadowed Includes** | `{{include "ds::button.html" from "vendor"}}` | Reach a template shadowed by a higher-priority root |
+//! | **Assets** | `{{needs "carousel"}}` / `{{required_assets "css"}}` | Deduplicated CSS/JS tags gathered from the whole page |
 //! | **Comments** | `{{! comment }}` | Template comments (not rendered) |
+//! | **Visible Comments** | `{{#-- note --}}` | Rendered as an escaped HTML comment |
 //! | **Filters** | `{{value\|upper\|truncate:10}}` | Transform output with filter chains |
+//! | **Sanitizing** | `{{post.body\|sanitize}}` | Allow-listed tags and attributes from user-written HTML |
+//! | **HTML Truncation** | `{{post.body\|sanitize\|truncate_html:140}}` | Shorten markup by visible text, keeping tags balanced |
+//! | **Dashboard Formatting** | `{{uptime\|duration}}` `{{bytes\|filesize}}` `{{ratio\|percent:1}}` | `3d 4h 12m`, `1.4 GiB`, `87.5%`, with `units.*_short` translated labels |
+//! | **Defaults** | `{{user.nickname\|default:"Anonymous"}}` `{{stock\|default:0}}` `engine.set_strict_variables(true)` | Fallback for empty or missing values; strict variables fail the render on a missing variable, with its line and column |
+//! | **Array Filters** | `{{tags\|join:", "}}` `{{items\|first}}` `{{items\|last}}` `{{items\|slice:0:3\|join:" · "}}` | Print lists without a loop; items stay typed through the chain |
+//! | **Sorted Loops** | `{{for product in products\|sort:"price":desc}}` `{{for tag in tags\|unique\|sort}}` `{{items\|reverse\|join}}` | `sort` (stable, by an optional dot-path key), `reverse` and `unique` filters, in loop sources and output tags |
+//! | **Escaping Filters** | `{{html\|safe}}` `{{body\|markdown\|e}}` `engine.register_html_filter("linkify", ...)` | The last `safe`, `escape`/`e` or HTML-producing filter of a tag decides whether its output is escaped |
+//! | **Context Building** | `context.set("age", 30)` `runic_context!{ "user" => { "name" => "Alex" }, "tags" => ["a", "b"] }` | `From` conversions into `TemplateValue` and a macro building a whole context |
+//! | **Null Values** | `{{if user.middle_name is defined}}` `{{if avatar is null}}` | `TemplateValue::Null` renders empty and is falsy; `is defined` and `is null` tell missing, null and empty apart |
+//! | **Coercion** | `{{if qty\|int > 3}}` | Convert values with `int`, `float`, `bool`, `string` |
 //! | **Macros** | `{{macro name(params)}}...{{/macro}}` | Reusable template components |
 //! | **Inheritance** | `{{extend "layout.html"}}` | Template inheritance system |
 //! | **Blocks** | `{{block content}}...{{/block}}` | Replaceable content blocks |
+//! | **Block Parameters** | `{{block header title=page.title}}` | Values a block's overrides read as `{{title}}` |
 //! | **I18n** | `{{t "key" name=user}}` | Internationalization with variables |
+//! | **Translation Scopes** | `{{t_scope "nav"}}{{t "products"}}{{/t_scope}}` | Key prefix for the enclosed lookups |
 //! | **Pluralization** | `{{plural count "item" "items"}}` | Smart plural forms |
+//! | **Tables** | `{{table rows columns="name,price:Price\|currency"}}` | HTML tables from arrays of objects |
+//! | **Form Helpers** | `{{input "email" value=form.email}}` `{{select "country" options=countries selected=form.country}}` `{{checkbox "subscribe" checked=form.subscribe}}` `{{field_errors "email" errors=form.errors}}` | Escaped, pre-filled form fields; a component of the same name overrides the markup |
+//! | **Error Boundaries** | `{{try}}...{{rescue}}{{error.message}}{{/try}}` | Fallback output when a block fails (security errors always fail) |
+//! | **Components** | `{{props label variant="primary"}}{{slots body footer?}}{{slot.body}}{{if has_slot "footer"}}` | Declared props and slots for `render_component` |
+//! | **Assertions** | `{{assert items\|length <= 100 "too many items"}}` | Fail the render, or record a warning with `set_soft_assertions` |
+//! | **Layout Selection** | `{{extends layout_name}}` `engine.render_with_layout("page.html", &context, Some("bare.html"))` | Pick the parent layout at render time, or render the blocks without one |
+//! | **Assignments** | `{{set total = price\|multiply:quantity}}{{total}}` | Visible for the rest of the template, loop iteration or block; reassignment overwrites, loop-body sets don't leak |
+//! | **Captures** | `{{capture sidebar}}...{{/capture}}` `{{sidebar}}` | Render a section once, loops and includes included, and output it wherever needed |
+//! | **Pagination** | `{{set pg = paginate(items, page, 20)}}{{for item in pg.items}}` `{{if pg.has_next}}` | Page slice, totals and (windowed) page numbers; `{{set}}` keeps helper values |
+//! | **HTML Validation** | `engine.set_validate_html(true)` | Unbalanced tags in debug-build output as diagnostics located in the template (`lsp` feature) |
+//! | **Profiling** | `engine.profile_render("page.html", &context)?.to_speedscope_json()` | Self and total time per directive, loop counts, flame graphs for speedscope |
+//! | **Template Coverage** | `engine.enable_coverage(true)` `engine.coverage_report().to_lcov()` | Which directives, branches and loop bodies the test suite's renders ran, per template, as LCOV or an HTML page |
+//! | **Semantic HTML Diff** | `diff::html_semantic_diff(old, new, &options)` `SnapshotOptions::new().compare_html(options)` | Element, text and attribute changes located by path, ignoring attribute order, whitespace and comments |
+//! | **Translation Modes** | `engine.set_translation_mode(TranslationMode::PseudoLocalize)` `engine.missing_translations()` | `⟦key⟧` markers for missing keys, pseudo-localized text with placeholders kept, per-render modes |
+//! | **Unless / Ifchanged** | `{{unless cart.empty}}...{{/unless}}` `{{for txn in txns}}{{ifchanged txn.date}}<h3>{{txn.date}}</h3>{{/ifchanged}}{{/for}}` | Negated conditionals, and group headers rendered when a value changes between loop items |
+//! | **Character Counts** | `{{if tweet\|length > 280}}` `{{name\|truncate:20}}` `{{body\|count_bytes}}` | `length` and `truncate` count grapheme clusters, so accents, emoji and flags count once; `count_chars` and `count_bytes` count code points and bytes |
+//! | **Translation Hot Swap** | `let version = engine.swap_translations("fr", catalog)` `engine.rollback_translations("fr", version)?` | Replace one locale's translations while renders run; each render keeps the versions it started with |
+//! | **Project Configuration** | `engine.load_project_config("runic.toml")` `engine.generate_project_config("runic.toml")` | Template roots, strict mode and the custom filter and helper names editor diagnostics accept |
+//! | **Render Queue** | `queue.push("index.html", context, Priority::High)` `queue.run(8, Some(deadline))` | Priority-ordered batch rendering on a bounded worker pool; renders left at the deadline are skipped and reported |
+//! | **Test Data Factories** | `ValueFactory::object().string("name", "Ada").array("tags", \|a\| a.push_string("admin")).build()` `ValueFactory::repeat(100, \|i, user\| user.lorem("bio", 30))` | Nested fixtures without `HashMap` code, with seeded lorem ipsum and number fillers that stay the same run after run |
+//! | **Raw Includes** | `{{include_raw "icons/sprite.svg"}}` `engine.render_to_writer("index.html", &context, &mut file)` | Files put into the output as is; large ones are streamed from disk to the writer and can stay out of the template cache
+//! | **Streaming Renders** | `engine.render_string_to_writer("{{for row in rows}}…{{/for}}", &context, &mut file)` | Top-level loop iterations go to the writer as they are rendered instead of building the whole output first
+//! | **Stream Flushing** | `{{flush}}` `engine.set_stream_flush_on(FlushOn::LoopIteration)` `engine.set_stream_chunk_size(Some(16 * 1024))` | Flush the writer of `render_to_writer` after `</head>`, after each loop iteration or every N bytes; web framework streams send one chunk per flush
+//! | **Provenance Markers** | `engine.set_provenance(ProvenanceOptions::default())` `{{no_provenance}}` | A "generated from templates/x.html, do not edit" comment atop rendered HTML, YAML, JS and other files, placed after XML declarations and shebangs
+//! | **Template Backtraces** | `engine.render("page.html", &context).unwrap_err().template_backtrace()` | Errors raised in includes, layouts, loop iterations and macro calls list the template call stack, outermost first |
+//! | **Clock & IDs** | `{{now()}}` `{{nonce()}}` `{{published_at\|timeago}}` | Fixed by `set_fixed_time` and `set_deterministic` for reproducible builds |
+//! | **Unique IDs** | `{{set id = uid("email-field")}}` `<label for="{{id}}">` `<input id="{{id}}">` `{{uid}}` | `email-field-1`, `email-field-2`, ... per prefix, counted from 1 in every render, so each macro call gets its own id/for and aria-labelledby pairs |
+//! | **Expression Functions** | `{{if str.contains(title, "draft")}}` `{{for pair in list.zip(names, roles)}}` `{{set total = list.sum(prices)}}` `{{title\|truncate:math.min(limit, 40)}}` | `math.*`, `str.*` and `list.*` functions wherever a helper call fits; `register_helper("str.titlecase", ...)` adds to a namespace |
+//! | **Whitespace Control** | `{{- for item in items -}}` `{{- name}}` `{{! note -}}` | `{{-` trims the whitespace before any tag, `-}}` the whitespace after it up to one newline |
 //!
 //! ## 📖 Quick Start Guide
 //!
@@ -58,15 +104,41 @@
 //!
 //! | Feature | Description | Dependencies |
 //! |---------|-------------|--------------|
-//! | `async` | Asynchronous template rendering with Tokio | `tokio`, `futures` |
+//! | `inheritance` | `{{extends}}` / `{{block}}` layouts (default) | None |
+//! | `i18n` | `{{t}}`, `{{t_scope}}` and translation catalogs (default) | None |
+//! | `template-macros` | `{{macro}}` definitions and calls (default) | None |
+//! | `lsp` | Completions, hover, diagnostics, highlighting and `lint_all` | None |
+//! | `debug-tools` | `render_string_with_debug` execution traces | None |
+//! | `suggestions` | "Did you mean" hints for missing templates | None |
+//! | `async` | Asynchronous template rendering with Tokio, and `render_stream` yielding chunks as they render | `tokio`, `futures`, `bytes` |
 //! | `axum-integration` | Direct Axum framework support | `axum`, `async` |
 //! | `warp-integration` | Direct Warp framework support | `warp`, `async` |
 //! | `actix-integration` | Direct Actix-web framework support | `actix-web`, `async` |
 //! | `web-frameworks` | All web framework integrations | All above web features |
 //! | `wasm` | WebAssembly browser compatibility | `wasm-bindgen`, `js-sys`, `web-sys` |
 //! | `cli` | Command-line tools and utilities | `clap`, `serde`, `serde_json`, `toml` |
+//! | `macros` | `runic_template!` compile-time template checking | `mystical-runic-macros` |
 //! | `full` | All ecosystem integration features | All optional features |
 //!
+//! The template language features are on by default; `default-features = false`
+//! gives the core engine (variables, conditionals, loops, includes, filters).
+//! A template that uses a disabled directive fails to render with a
+//! [`TemplateError::Template`] naming the missing feature. Template macros are
+//! behind `template-macros` because `macros` already names the proc-macro crate.
+//!
+//! Stripped release binary rendering one string (rustc 1.95, x86_64 Linux):
+//!
+//! | Features | Size | Delta |
+//! |----------|------|-------|
+//! | none (core only) | 768 KiB | |
+//! | `inheritance` | 774 KiB | +6 KiB |
+//! | `i18n` | 793 KiB | +25 KiB |
+//! | `template-macros` | 841 KiB | +73 KiB |
+//! | default | 874 KiB | +106 KiB |
+//! | default + `lsp`, `debug-tools`, `suggestions` | 878 KiB | +4 KiB |
+//!
+//! The tooling features only cost space once their methods are called.
+//!
 //! ## 🧙‍♂️ Usage Examples - Choose Your Style
 //!
 //! ### Conventional Style
@@ -104,6 +176,9 @@
 //! ).unwrap();
 //! ```
 //!
+//! The [`mystical`] module maps the whole API, methods included
+//! (`carve_runes`, `enchant_filter`, `inscribe`, ...).
+//!
 //! ## 🚀 Performance & Security
 //!
 //! **Performance Features:**
@@ -115,7 +190,10 @@
 //!
 //! **Security Features:**
 //! - HTML escaping by default (XSS prevention)
-//! - Path traversal protection (`../` and absolute path blocking)
+//! - Path traversal protection: template names are checked against a rule set
+//!   covering `../`, absolute, UNC and drive paths, Windows device names and
+//!   alternate data streams, on every platform
+//! - Public templates: `render_public` only serves what `runic.manifest` lists
 //! - Template injection prevention
 //! - Memory exhaustion protection
 //! - Input validation and sanitization
@@ -191,24 +269,97 @@ mod context;
 mod value;
 mod utils;
 mod bytecode;
+#[cfg(feature = "inheritance")]
 mod layouts;
+#[cfg(feature = "debug-tools")]
 mod debug;
 mod suggestions;
+#[cfg(feature = "lsp")]
 mod lsp;
 mod async_engine;
 mod web_frameworks;
 mod wasm_support;
 mod cli;
 mod ecosystem;
+mod usage;
+mod profile;
+mod audit;
+use mystical_runic_syntax as syntax;
+mod precompiled;
+mod roots;
+mod manifest;
+mod project;
+mod provenance;
+mod raw_include;
+mod factory;
+mod streaming;
+mod functions;
+mod coverage;
+mod include_trace;
+mod encoding;
+mod pack;
+mod arguments;
+#[cfg(feature = "lsp")]
+mod html_check;
+mod security;
+mod sanitize;
+mod graphemes;
+mod redaction;
+mod cancellation;
+mod render_diff;
+mod render_profile;
+mod shared_engine;
+mod render_queue;
+#[cfg(feature = "i18n")]
+mod i18n;
+mod testing;
+
+pub mod ast;
+pub mod diff;
+pub mod mystical;
 
 // 🏢 Conventional names for standard development environments
-pub use error::{TemplateError, TemplateResult};
+pub use error::{TemplateError, TemplateResult, TemplateFrame, FrameKind};
 pub use engine::TemplateEngine;
 pub use context::TemplateContext;
 pub use value::TemplateValue;
 pub use engine::FilterFunction;
 pub use engine::HelperFunction;
+pub use engine::WarmupReport;
+pub use engine::PerformanceReport;
+pub use engine::BoolFormat;
+pub use engine::{ValueKind, ValueFormatter};
+pub use engine::ExpressionOptions;
+pub use sanitize::SanitizerPolicy;
+pub use security::path::{PathRule, PathViolation, check_template_name};
+pub use redaction::{Redact, RedactionFunction};
+pub use cancellation::CancellationToken;
+pub use shared_engine::SharedTemplateEngine;
+pub use render_queue::{RenderQueue, Priority, QueueReport, QueueItem, QueueOutcome, QueueProgress};
+pub use testing::{SnapshotOptions, SnapshotOutcome, check_snapshot, assert_snapshot};
+pub use factory::{ValueFactory, ArrayFactory};
+#[cfg(feature = "i18n")]
+pub use i18n::{TranslationKeyUsage, TranslationKind, TranslationReport, TranslationMode, MissingTranslation, TranslationVersion};
+pub use render_diff::{RenderDiff, DiffRegion, DiffLine, RenderSegment, SegmentKind, SegmentScope, VariableDiff};
+pub use render_profile::{ProfileResult, ProfileSpan, SpanKind, HotSpot};
+pub use coverage::{CoverageReport, TemplateCoverage, CoverageSite, CoverageKind};
+pub use usage::ContextUsage;
+pub use profile::{EngineConfig, Profile, TemplateEngineBuilder};
+pub use audit::{RawOutputSite, RawOutputReason};
+pub use precompiled::{PrecompiledTemplate, PrecompiledInstruction};
+pub use bytecode::{CompiledTemplate, BytecodeInstruction};
+pub use roots::{TemplateRoot, ResolutionTrace, ResolutionCandidate};
+pub use manifest::{TemplateManifest, PublicTemplate};
+pub use project::{ProjectConfig, ProjectSymbol, PROJECT_CONFIG_FILE_NAME};
+pub use provenance::{ProvenanceOptions, CommentStyle};
+pub use raw_include::RawIncludeOptions;
+pub use streaming::FlushOn;
+pub use include_trace::{IncludeTree, IncludeNode, IncludeRelation, LoadSource};
+pub use encoding::{OutputEncoding, CharEncoder};
+pub use pack::PACK_FORMAT_VERSION;
+#[cfg(feature = "debug-tools")]
 pub use debug::{DebugInfo, DebugRenderResult, ExecutionStep, PerformanceMetrics};
+#[cfg(feature = "lsp")]
 pub use lsp::{LspParseResult, TemplateBlock, CompletionItem, SyntaxToken, Diagnostic, HoverInfo, DefinitionInfo};
 
 // 🚀 v0.5.0 Ecosystem Integration exports
@@ -217,6 +368,10 @@ pub use lsp::{LspParseResult, TemplateBlock, CompletionItem, SyntaxToken, Diagno
 #[cfg(feature = "async")]
 pub use async_engine::AsyncTemplateEngine;
 
+/// Shared base context for per-request layered contexts (requires `async` feature)
+#[cfg(feature = "async")]
+pub use web_frameworks::SharedTemplateContext;
+
 /// Axum web framework integration (requires `axum-integration` feature)
 #[cfg(feature = "axum-integration")]
 pub use web_frameworks::axum_integration::{AxumTemplateEngine, TemplateResponseError};
@@ -235,18 +390,63 @@ pub use wasm_support::{WasmTemplateEngine, WasmRuneEngine};
 
 /// Command-line tools and utilities (requires `cli` feature)
 #[cfg(feature = "cli")]
-pub use cli::{Cli, Commands, CliConfig, TemplateWatcher, process_template, process_files, batch_process, load_config};
+pub use cli::{Cli, Commands, CliConfig, TemplateWatcher, process_template, process_files, batch_process, load_config, audit_templates, diff_templates, profile_template, i18n_report, preview_in_browser, PreviewOptions, PreviewReload, PreviewHandle, BrowserLauncher, SystemBrowser};
+
+/// Compile-time checked templates (requires `macros` feature)
+///
+/// `runic_template!("...")` fails the build on unclosed tags or blocks,
+/// malformed directives and unknown filters, reporting the line and column.
+/// Custom filters can't be checked at compile time: list their names with
+/// `runic_template!("{{price|money}}", filters = ["money"])` and they are
+/// assumed to be registered on the engine that renders the template.
+#[cfg(feature = "macros")]
+pub use mystical_runic_macros::runic_template;
 
 pub use ecosystem::{EcosystemCompatibility, EcosystemTemplateEngine};
 
 // 🔮 Mystical aliases for the enlightened practitioners of ancient coding arts
-pub use error::{TemplateError as RuneError, TemplateResult as RuneResult};
+pub use error::{TemplateError as RuneError, TemplateResult as RuneResult, TemplateFrame as ScarStep, FrameKind as ScarStepKind};
 pub use engine::TemplateEngine as RuneEngine;
 pub use context::TemplateContext as RuneScroll;
 pub use value::TemplateValue as RuneSymbol;
 pub use engine::FilterFunction as MysticFilter;
 pub use engine::HelperFunction as AncientHelper;
+pub use engine::WarmupReport as RuneAwakening;
+pub use engine::PerformanceReport as RuneChronicle;
+pub use engine::BoolFormat as RuneVerdict;
+pub use engine::{ValueKind as RuneEssence, ValueFormatter as RuneGuise};
+pub use engine::ExpressionOptions as RuneWard;
+pub use sanitize::SanitizerPolicy as RunePurifier;
+pub use security::path::{PathRule as WardRule, PathViolation as WardBreach, check_template_name as test_rune_name};
+pub use redaction::{Redact as RuneVeil, RedactionFunction as VeilingRite};
+pub use cancellation::CancellationToken as SeveranceCharm;
+pub use shared_engine::SharedTemplateEngine as SharedRuneEngine;
+pub use render_queue::{RenderQueue as RitualQueue, Priority as RitualUrgency, QueueReport as RitualChronicle, QueueItem as RitualRecord, QueueOutcome as RitualFate, QueueProgress as RitualTidings};
+pub use testing::{SnapshotOptions as MirrorRite, SnapshotOutcome as MirrorVerdict, check_snapshot as consult_mirror, assert_snapshot as demand_reflection};
+pub use factory::{ValueFactory as SymbolForge, ArrayFactory as SymbolChain};
+pub use crate::assert_template_snapshot as assert_scroll_reflection;
+#[cfg(feature = "i18n")]
+pub use i18n::{TranslationKeyUsage as TongueMark, TranslationKind as TongueForm, TranslationReport as TongueLedger, TranslationMode as TongueGuise, MissingTranslation as SilentTongue, TranslationVersion as TongueEdition};
+pub use render_diff::{RenderDiff as RuneDivergence, DiffRegion as DivergentPassage, DiffLine as DivergentLine, RenderSegment as CarvedFragment, SegmentKind as FragmentKind, SegmentScope as FragmentScope, VariableDiff as DivergentSymbol};
+pub use render_profile::{ProfileResult as CarvingOmen, ProfileSpan as OmenThread, SpanKind as OmenThreadKind, HotSpot as BurningGlyph};
+pub use coverage::{CoverageReport as RuneSurvey, TemplateCoverage as ScrollSurvey, CoverageSite as SurveyedRune, CoverageKind as SurveyedRuneKind};
+pub use usage::ContextUsage as RuneFootprint;
+pub use profile::{EngineConfig as RuneAttunement, Profile as RuneAspect, TemplateEngineBuilder as RuneForge};
+pub use audit::{RawOutputSite as RuneBreach, RawOutputReason as RuneBreachCause};
+pub use precompiled::{PrecompiledTemplate as SealedRune, PrecompiledInstruction as SealedGlyph};
+pub use bytecode::{CompiledTemplate as RuneCodex, BytecodeInstruction as RuneSigil};
+pub use roots::{TemplateRoot as RuneSanctum, ResolutionTrace as SummoningTrace, ResolutionCandidate as SummoningCandidate};
+pub use manifest::{TemplateManifest as RuneProclamation, PublicTemplate as ProclaimedScroll};
+pub use project::{ProjectConfig as CovenCharter, ProjectSymbol as CharterGlyph, PROJECT_CONFIG_FILE_NAME as CHARTER_FILE_NAME};
+pub use provenance::{ProvenanceOptions as OriginMark, CommentStyle as MarkScript};
+pub use raw_include::RawIncludeOptions as UnboundScrollRite;
+pub use streaming::FlushOn as StreamTide;
+pub use include_trace::{IncludeTree as RuneLineage, IncludeNode as LineageBranch, IncludeRelation as LineageBond, LoadSource as LineageWell};
+pub use encoding::{OutputEncoding as RuneScript, CharEncoder as GlyphScribe};
+pub use pack::PACK_FORMAT_VERSION as GRIMOIRE_FORMAT_VERSION;
+#[cfg(feature = "debug-tools")]
 pub use debug::{DebugInfo as RuneTrace, DebugRenderResult as RuneDivination, ExecutionStep as RuneStep, PerformanceMetrics as RuneMetrics};
+#[cfg(feature = "lsp")]
 pub use lsp::{LspParseResult as RunicLore, TemplateBlock as RunicBlock, CompletionItem as RunicCompletion, SyntaxToken as RunicToken, Diagnostic as RunicDiagnostic, HoverInfo as RunicWisdom, DefinitionInfo as RunicOrigin};
 
 // 🔮 v0.5.0 Mystical ecosystem aliases
@@ -255,6 +455,10 @@ pub use lsp::{LspParseResult as RunicLore, TemplateBlock as RunicBlock, Completi
 #[cfg(feature = "async")]
 pub use async_engine::AsyncTemplateEngine as AsyncRuneEngine;
 
+/// Shared rune scroll (mystical alias, requires `async` feature)
+#[cfg(feature = "async")]
+pub use web_frameworks::SharedTemplateContext as SharedRuneScroll;
+
 /// Axum rune engine (mystical alias, requires `axum-integration` feature)
 #[cfg(feature = "axum-integration")]
 pub use web_frameworks::axum_integration::{AxumTemplateEngine as AxumRuneEngine, TemplateResponseError as RuneResponseError};
@@ -273,6 +477,14 @@ pub use wasm_support::{WasmTemplateEngine as WasmRuneEngineTrait, WasmRuneEngine
 
 /// Command-line runic tools (mystical aliases, requires `cli` feature)
 #[cfg(feature = "cli")]
-pub use cli::{Cli as RunicCli, Commands as RunicCommands, CliConfig as RunicConfig, TemplateWatcher as RuneWatcher};
+pub use cli::{Cli as RunicCli, Commands as RunicCommands, CliConfig as RunicConfig, TemplateWatcher as RuneWatcher, PreviewOptions as ScryingOptions, PreviewReload as ScryingRenewal, PreviewHandle as ScryingGlass, BrowserLauncher as ScryingLauncher, SystemBrowser as SystemScryer};
+
+/// Command-line runic rituals (mystical aliases, requires `cli` feature)
+#[cfg(feature = "cli")]
+pub use cli::{process_template as transmute_scroll, process_files as transmute_scrolls, batch_process as mass_transmutation, load_config as read_attunement, audit_templates as seek_breaches, diff_templates as seek_divergence, profile_template as read_scroll_omens, i18n_report as tally_tongues, preview_in_browser as scry_in_browser};
+
+/// Rune sealing at compile time (mystical alias, requires `macros` feature)
+#[cfg(feature = "macros")]
+pub use mystical_runic_macros::runic_template as seal_rune;
 
 pub use ecosystem::{EcosystemCompatibility as RunicCompatibility, EcosystemTemplateEngine as EcosystemRuneEngine};
\ No newline at end of file
@@ -0,0 +1,117 @@
+//! Template manifest for v0.6.0
+//!
+//! A `runic.manifest` file in the template directory lists the templates
+//! the web layer may render by name with
+//! [`TemplateEngine::render_public`](crate::TemplateEngine::render_public);
+//! partials and layouts left out of it stay internal. One template per line:
+//!
+//! ```text
+//! # Pages served by name
+//! pages/about.html
+//! home -> pages/home.html cache_ttl=300
+//! feed -> feeds/atom.xml autoescape=off provenance=off
+//! accueil -> pages/home.html locale=fr
+//! ```
+//!
+//! A line is a public name, optionally followed by `-> template` when the
+//! name is an alias, then `key=value` options: `autoescape` (`on` or `off`),
+//! `cache_ttl` (seconds the cached source is kept), `locale` and
+//! `provenance` (`off` leaves out the marker of
+//! [`set_provenance`](crate::TemplateEngine::set_provenance)).
+//! Templates must pass [`check_template_name`](crate::check_template_name).
+
+use crate::security::path::check_template_name;
+use crate::{TemplateError, TemplateResult};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// File the manifest is read from, relative to the template directory
+pub const MANIFEST_FILE_NAME: &str = "runic.manifest";
+
+/// A template the manifest makes public
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicTemplate {
+    /// Template rendered for the public name
+    pub template: String,
+    /// Whether variables are HTML-escaped (`autoescape=off` turns it off)
+    pub autoescape: bool,
+    /// How long the cached source is used before the file is read again
+    pub cache_ttl: Option<Duration>,
+    /// Locale the template is always rendered in
+    pub locale: Option<String>,
+    /// Whether the provenance marker is added (`provenance=off` leaves it out)
+    pub provenance: bool,
+}
+
+/// Public templates listed by a `runic.manifest` file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TemplateManifest {
+    entries: BTreeMap<String, PublicTemplate>,
+}
+
+impl TemplateManifest {
+    /// Parse a manifest; errors name the offending line
+    pub fn parse(source: &str) -> TemplateResult<Self> {
+        let mut entries = BTreeMap::new();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| {
+                TemplateError::Parse(format!("{} line {}: {}", MANIFEST_FILE_NAME, index + 1, message))
+            };
+
+            let mut words = line.split_whitespace().peekable();
+            let name = words.next().unwrap_or_default();
+            let template = match words.peek() {
+                Some(&"->") => {
+                    words.next();
+                    words.next().ok_or_else(|| error(format!("missing template after '{} ->'", name)))?
+                }
+                _ => name,
+            };
+            check_template_name(template)
+                .map_err(|violation| TemplateError::Security(format!("{} line {}: {}", MANIFEST_FILE_NAME, index + 1, violation)))?;
+            let mut entry = PublicTemplate {
+                template: template.to_string(),
+                autoescape: true,
+                cache_ttl: None,
+                locale: None,
+                provenance: true,
+            };
+            for option in words {
+                match option.split_once('=') {
+                    Some(("autoescape", "on")) => entry.autoescape = true,
+                    Some(("autoescape", "off")) => entry.autoescape = false,
+                    Some(("cache_ttl", seconds)) => {
+                        let seconds = seconds.parse()
+                            .map_err(|_| error(format!("cache_ttl must be a number of seconds, found '{}'", seconds)))?;
+                        entry.cache_ttl = Some(Duration::from_secs(seconds));
+                    }
+                    Some(("locale", locale)) if !locale.is_empty() => entry.locale = Some(locale.to_string()),
+                    Some(("provenance", "on")) => entry.provenance = true,
+                    Some(("provenance", "off")) => entry.provenance = false,
+                    _ => return Err(error(format!(
+                        "unknown option '{}'; expected autoescape=on|off, cache_ttl=seconds, locale=code or provenance=on|off", option
+                    ))),
+                }
+            }
+
+            if entries.insert(name.to_string(), entry).is_some() {
+                return Err(error(format!("'{}' is listed twice", name)));
+            }
+        }
+        Ok(Self { entries })
+    }
+
+    /// The template behind a public name
+    pub fn get(&self, name: &str) -> Option<&PublicTemplate> {
+        self.entries.get(name)
+    }
+
+    /// Public names, sorted
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+}
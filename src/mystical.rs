@@ -0,0 +1,656 @@
+//! # 🔮 The mystical API
+//!
+//! Everything the conventional API offers, under mystical names. Types are
+//! aliases of the conventional ones and methods are thin wrappers provided by
+//! extension traits, so both styles can be mixed freely.
+//!
+//! ```rust
+//! use mystical_runic::mystical::*;
+//!
+//! let mut engine = RuneEngine::awaken("sacred_scrolls");
+//! engine.enchant_filter("shout", |value, _args| Ok(format!("{}!", value.to_uppercase())));
+//!
+//! let mut scroll = RuneScroll::blank_scroll();
+//! scroll.inscribe_string("hero", "ferris");
+//!
+//! let result = engine.carve_runes("Hail {{hero|shout}}", &scroll).unwrap();
+//! assert_eq!(result, "Hail FERRIS!");
+//! ```
+//!
+//! ## Types
+//!
+//! | Conventional | Mystical |
+//! |--------------|----------|
+//! | `TemplateEngine` | `RuneEngine` |
+//! | `TemplateContext` | `RuneScroll` |
+//! | `TemplateValue` | `RuneSymbol` |
+//! | `TemplateError` / `TemplateResult` | `RuneError` / `RuneResult` |
+//! | `TemplateFrame` / `FrameKind` | `ScarStep` / `ScarStepKind` |
+//! | `FilterFunction` / `HelperFunction` | `MysticFilter` / `AncientHelper` |
+//! | `WarmupReport` | `RuneAwakening` |
+//! | `BoolFormat` | `RuneVerdict` |
+//! | `ValueKind` / `ValueFormatter` | `RuneEssence` / `RuneGuise` |
+//! | `ExpressionOptions` | `RuneWard` |
+//! | `SanitizerPolicy` | `RunePurifier` |
+//! | `PathRule` / `PathViolation` / `check_template_name` | `WardRule` / `WardBreach` / `test_rune_name` |
+//! | `Redact` / `RedactionFunction` | `RuneVeil` / `VeilingRite` |
+//! | `CancellationToken` | `SeveranceCharm` |
+//! | `SharedTemplateEngine` | `SharedRuneEngine` |
+//! | `RenderQueue` / `Priority` / `QueueReport` | `RitualQueue` / `RitualUrgency` / `RitualChronicle` |
+//! | `QueueItem` / `QueueOutcome` / `QueueProgress` | `RitualRecord` / `RitualFate` / `RitualTidings` |
+//! | `SnapshotOptions` / `SnapshotOutcome` | `MirrorRite` / `MirrorVerdict` |
+//! | `ValueFactory` / `ArrayFactory` | `SymbolForge` / `SymbolChain` |
+//! | `check_snapshot` / `assert_snapshot` / `assert_template_snapshot!` | `consult_mirror` / `demand_reflection` / `assert_scroll_reflection!` |
+//! | `TranslationKeyUsage` / `TranslationKind` / `TranslationReport` | `TongueMark` / `TongueForm` / `TongueLedger` |
+//! | `TranslationMode` / `MissingTranslation` / `TranslationVersion` | `TongueGuise` / `SilentTongue` / `TongueEdition` |
+//! | `RenderDiff` / `DiffRegion` / `DiffLine` / `VariableDiff` | `RuneDivergence` / `DivergentPassage` / `DivergentLine` / `DivergentSymbol` |
+//! | `RenderSegment` / `SegmentKind` / `SegmentScope` | `CarvedFragment` / `FragmentKind` / `FragmentScope` |
+//! | `ProfileResult` / `ProfileSpan` / `SpanKind` / `HotSpot` | `CarvingOmen` / `OmenThread` / `OmenThreadKind` / `BurningGlyph` |
+//! | `CoverageReport` / `TemplateCoverage` / `CoverageSite` / `CoverageKind` | `RuneSurvey` / `ScrollSurvey` / `SurveyedRune` / `SurveyedRuneKind` |
+//! | `ContextUsage` | `RuneFootprint` |
+//! | `EngineConfig` / `Profile` / `TemplateEngineBuilder` | `RuneAttunement` / `RuneAspect` / `RuneForge` |
+//! | `RawOutputSite` / `RawOutputReason` | `RuneBreach` / `RuneBreachCause` |
+//! | `PrecompiledTemplate` / `PrecompiledInstruction` | `SealedRune` / `SealedGlyph` |
+//! | `CompiledTemplate` / `BytecodeInstruction` | `RuneCodex` / `RuneSigil` |
+//! | `PerformanceReport` | `RuneChronicle` |
+//! | `TemplateRoot` / `ResolutionTrace` / `ResolutionCandidate` | `RuneSanctum` / `SummoningTrace` / `SummoningCandidate` |
+//! | `TemplateManifest` / `PublicTemplate` | `RuneProclamation` / `ProclaimedScroll` |
+//! | `ProjectConfig` / `ProjectSymbol` / `PROJECT_CONFIG_FILE_NAME` | `CovenCharter` / `CharterGlyph` / `CHARTER_FILE_NAME` |
+//! | `ProvenanceOptions` / `CommentStyle` | `OriginMark` / `MarkScript` |
+//! | `RawIncludeOptions` | `UnboundScrollRite` |
+//! | `FlushOn` | `StreamTide` |
+//! | `IncludeTree` / `IncludeNode` / `IncludeRelation` / `LoadSource` | `RuneLineage` / `LineageBranch` / `LineageBond` / `LineageWell` |
+//! | `OutputEncoding` / `CharEncoder` | `RuneScript` / `GlyphScribe` |
+//! | `PACK_FORMAT_VERSION` | `GRIMOIRE_FORMAT_VERSION` |
+//! | `DebugInfo` / `DebugRenderResult` / `ExecutionStep` / `PerformanceMetrics` | `RuneTrace` / `RuneDivination` / `RuneStep` / `RuneMetrics` |
+//! | `LspParseResult` / `TemplateBlock` / `CompletionItem` / `SyntaxToken` | `RunicLore` / `RunicBlock` / `RunicCompletion` / `RunicToken` |
+//! | `Diagnostic` / `HoverInfo` / `DefinitionInfo` | `RunicDiagnostic` / `RunicWisdom` / `RunicOrigin` |
+//! | `EcosystemCompatibility` / `EcosystemTemplateEngine` | `RunicCompatibility` / `EcosystemRuneEngine` |
+//!
+//! Feature-gated types (`AsyncTemplateEngine`, `SharedTemplateContext`, the
+//! web framework engines, WASM and CLI items) are re-exported here under
+//! their mystical names when their feature is enabled.
+//!
+//! ## Engine verbs ([`RuneEngineExt`])
+//!
+//! | Conventional | Mystical |
+//! |--------------|----------|
+//! | `new` / `new_validated` / `new_wasm` / `builder` | `awaken` / `awaken_tested` / `awaken_in_browser` / `forge` |
+//! | `render` / `render_into` | `carve_scroll` / `carve_scroll_into` |
+//! | `render_string` / `render_string_into` | `carve_runes` / `carve_runes_into` |
+//! | `render_parallel` | `carve_scrolls_in_unison` |
+//! | `render_many` / `render_many_iter` | `carve_many_scrolls` / `carve_scroll_stream` |
+//! | `render_with_usage` | `carve_scroll_with_footprint` |
+//! | `render_offline` | `carve_from_memory` |
+//! | `render_with_cancel` | `carve_until_severed` |
+//! | `render_with_rescued_errors` | `carve_scroll_with_scars` |
+//! | `render_component` / `register_component` | `invoke_familiar` / `bind_familiar` |
+//! | `parse` | `dissect_runes` |
+//! | `render_expression_only` / `render_expression_only_with` | `carve_plain_runes` / `carve_warded_runes` |
+//! | `render_precompiled` / `check_syntax` | `carve_sealed_rune` / `test_runes` |
+//! | `render_string_with_debug` | `divine_runes` |
+//! | `render_v040` / `render_string_v040` | `carve_scroll_v040` / `carve_runes_v040` |
+//! | `load_template` / `load_template_arc` / `load_template_mmap` | `summon_scroll` / `summon_shared_scroll` / `summon_scroll_mapped` |
+//! | `is_template_cached` / `get_cache_size` | `is_scroll_remembered` / `scroll_vault_size` |
+//! | `set_template_cache_capacity` / `get_template_cache_capacity` | `limit_scroll_vault` / `scroll_vault_limit` |
+//! | `optimize_cache` | `purify_vault` |
+//! | `get_template_dir` | `scroll_sanctum` |
+//! | `add_template_root` / `add_template_root_with_priority` / `explain_resolution` | `open_sanctum` / `open_sanctum_with_precedence` / `trace_summoning` |
+//! | `render_public` / `list_public_templates` / `template_manifest` | `carve_proclaimed_scroll` / `proclaimed_scrolls` / `read_proclamation` |
+//! | `load_project_config` / `project_config` / `generate_project_config` | `read_coven_charter` / `coven_charter` / `inscribe_coven_charter` |
+//! | `set_provenance` / `clear_provenance` / `provenance` | `mark_origins` / `cease_marking_origins` / `origin_mark` |
+//! | `set_raw_include_options` / `raw_include_options` / `render_to_writer` / `render_string_to_writer` | `bind_unbound_scrolls` / `unbound_scroll_rite` / `carve_scroll_into_stream` / `carve_runes_into_stream` |
+//! | `set_stream_chunk_size` / `set_stream_flush_on` / `stream_chunk_size` / `stream_flush_on` | `measure_stream_draught` / `set_stream_tide` / `stream_draught` / `stream_tide` |
+//! | `set_stream_buffer_size` / `stream_buffer_size` | `dam_stream` / `stream_dam` |
+//! | `render_with_trace` | `carve_scroll_with_lineage` |
+//! | `render_bytes` / `render_bytes_into` | `carve_scroll_in_script` / `carve_scroll_in_script_into` |
+//! | `export_pack` / `import_pack` | `bind_grimoire` / `open_grimoire` |
+//! | `set_soft_assertions` / `render_with_failed_assertions` / `render_string_with_failed_assertions` | `soften_oaths` / `carve_scroll_with_broken_oaths` / `carve_runes_with_broken_oaths` |
+//! | `render_with_layout` | `carve_scroll_in_mantle` |
+//! | `set_validate_html` / `is_validate_html_enabled` / `html_diagnostics` | `guard_the_seals` / `are_seals_guarded` / `broken_seals` |
+//! | `register_filter` / `register_pipeline` / `register_helper` / `register_asset` | `enchant_filter` / `weave_enchantments` / `bind_helper` / `bind_talisman` |
+//! | `register_html_filter` | `enchant_glyph_filter` |
+//! | `get_macro_count` | `spell_count` |
+//! | `set_translations` / `set_locale` / `get_translation` | `inscribe_tongues` / `choose_tongue` / `translate_rune` |
+//! | `set_translations_nested` | `inscribe_tongue_tree` |
+//! | `swap_translations` / `rollback_translations` / `translation_version` | `exchange_tongues` / `recall_tongues` / `tongue_edition` |
+//! | `extract_translation_keys` / `extract_all_translation_keys` / `translation_report` | `gather_tongue_marks` / `gather_all_tongue_marks` / `weigh_tongues` |
+//! | `set_translation_mode` / `get_translation_mode` / `missing_translations` | `don_tongue_guise` / `tongue_guise` / `silent_tongues` |
+//! | `render_with_translation_mode` / `render_string_with_translation_mode` | `carve_scroll_in_tongue_guise` / `carve_runes_in_tongue_guise` |
+//! | `allow_dynamic_includes` | `permit_summoning` |
+//! | `enable_strict_mode` / `disable_strict_mode` / `is_strict_mode_enabled` | `swear_strict_oath` / `release_strict_oath` / `is_strict_oath_sworn` |
+//! | `set_strict_variables` / `is_strict_variables_enabled` | `forbid_unbound_runes` / `are_unbound_runes_forbidden` |
+//! | `set_bool_format` / `get_bool_format` | `set_verdict` / `verdict` |
+//! | `set_value_formatter` / `clear_value_formatter` / `get_value_formatter` | `set_guise` / `shed_guise` / `guise` |
+//! | `configure_sanitizer` / `get_sanitizer_policy` | `attune_purifier` / `purifier` |
+//! | `set_redaction` | `veil_secrets` |
+//! | `set_deterministic` / `set_fixed_time` | `bind_fate` / `freeze_time` |
+//! | `set_strip_visible_comments` / `is_strip_visible_comments_enabled` | `veil_whispers` / `are_whispers_veiled` |
+//! | `set_trim_blocks` / `is_trim_blocks_enabled` | `shave_block_tails` / `are_block_tails_shaved` |
+//! | `set_lstrip_blocks` / `is_lstrip_blocks_enabled` | `shave_block_margins` / `are_block_margins_shaved` |
+//! | `set_minify_output` / `is_minify_output_enabled` | `compress_runes` / `are_runes_compressed` |
+//! | `set_max_nesting_depth` / `get_max_nesting_depth` | `limit_rune_depth` / `rune_depth_limit` |
+//! | `set_max_include_depth` / `get_max_include_depth` | `limit_summoning_depth` / `summoning_depth_limit` |
+//! | `set_max_range_items` / `get_max_range_items` | `limit_chant_count` / `chant_count_limit` |
+//! | `set_render_workers` / `get_render_workers` | `set_scribe_count` / `scribe_count` |
+//! | `compile_to_bytecode` / `compile_to_bytecode_uncached` | `transmute_to_codex` / `transmute_to_codex_afresh` |
+//! | `compile_templates_parallel` / `render_compiled` / `render_compiled_parallel` | `transmute_in_unison` / `recite_codex` / `recite_codices_in_unison` |
+//! | `enable_bytecode_cache` / `is_bytecode_cache_enabled` / `is_bytecode_cached` | `keep_codices` / `are_codices_kept` / `is_codex_remembered` |
+//! | `enable_debug_mode` / `disable_debug_mode` / `is_debug_enabled` | `open_third_eye` / `close_third_eye` / `is_third_eye_open` |
+//! | `enable_hot_reload` / `disable_hot_reload` / `is_hot_reload_enabled` | `quicken_scrolls` / `still_scrolls` / `are_scrolls_quickened` |
+//! | `reload_changed_templates` / `reload_templates` | `rekindle_changed_scrolls` / `rekindle_scrolls` |
+//! | `enable_performance_monitoring` / `disable_performance_monitoring` | `watch_the_stars` / `ignore_the_stars` |
+//! | `is_performance_monitoring_enabled` / `get_performance_statistics` | `are_stars_watched` / `read_the_stars` |
+//! | `get_wasm_console_logging` / `set_wasm_console_logging` | `is_browser_whispering` / `set_browser_whispering` |
+//! | `parse_for_lsp` / `get_completions_at_position` | `read_runic_lore` / `foresee_runes` |
+//! | `tokenize_for_syntax_highlighting` / `get_syntax_theme_info` | `illuminate_runes` / `illumination_palette` |
+//! | `get_diagnostics_for_editor` / `get_hover_info_at_position` / `get_definition_at_position` | `scry_flaws` / `seek_wisdom` / `trace_origin` |
+//! | `lint_all` / `audit_raw_output` | `scry_all_scrolls` / `seek_breaches` |
+//! | `validate_templates` | `test_all_scrolls` |
+//! | `diff_renders` / `diff_render_strings` / `diff_contexts` | `compare_carvings` / `compare_rune_carvings` / `compare_scroll_carvings` |
+//! | `profile_render` | `read_carving_omens` |
+//! | `enable_coverage` / `is_coverage_enabled` / `coverage_report` | `survey_runes` / `is_surveying` / `read_survey` |
+//! | `analyze_context_usage` | `trace_footprint` |
+//! | `warm_up` / `warm_up_filtered` | `awaken_scrolls` / `awaken_chosen_scrolls` |
+//! | `apply_profile` / `apply_config` / `config` | `take_aspect` / `attune` / `attunement` |
+//!
+//! ## Scroll verbs ([`RuneScrollExt`])
+//!
+//! | Conventional | Mystical |
+//! |--------------|----------|
+//! | `new` / `layered` / `base` | `blank_scroll` / `unroll_over` / `underlying_scroll` |
+//! | `set` / `set_string` / `set_bool` / `set_number` / `set_float` | `inscribe` / `inscribe_string` / `inscribe_bool` / `inscribe_number` / `inscribe_float` |
+//! | `set_nested_object` | `inscribe_nested` |
+//! | `get` / `get_string` / `contains` | `divine` / `divine_string` / `bears` |
+//!
+//! `awaken_in_browser`, `scroll_vault_size`, `spell_count` and the browser whispering verbs need
+//! the `wasm` feature, like their conventional counterparts. In the same way the tongue verbs
+//! need `i18n`, `divine_runes` needs `debug-tools` and the runic lore verbs need `lsp`.
+//!
+//! [`ENGINE_VERBS`] and [`SCROLL_VERBS`] list the same mappings; a test
+//! checks that every public engine and context method appears in them.
+
+#[cfg(feature = "lsp")]
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub use crate::{
+    RuneEngine, RuneScroll, RuneSymbol, RuneError, RuneResult, ScarStep, ScarStepKind, MysticFilter, AncientHelper,
+    RuneAwakening, RuneVerdict, RuneEssence, RuneGuise, RuneWard, RunePurifier, RuneVeil, VeilingRite, SeveranceCharm, SharedRuneEngine, RitualQueue, RitualUrgency, RitualChronicle, RitualRecord, RitualFate, RitualTidings, MirrorRite, MirrorVerdict, SymbolForge, SymbolChain, consult_mirror, demand_reflection, assert_scroll_reflection, RuneFootprint, RuneAttunement, RuneAspect, RuneForge,
+    RuneDivergence, DivergentPassage, DivergentLine, DivergentSymbol, CarvedFragment, FragmentKind, FragmentScope,
+    CarvingOmen, OmenThread, OmenThreadKind, BurningGlyph, RuneSurvey, ScrollSurvey, SurveyedRune, SurveyedRuneKind,
+    RuneBreach, RuneBreachCause, SealedRune, SealedGlyph, RuneCodex, RuneSigil, RuneChronicle,
+    RuneSanctum, SummoningTrace, SummoningCandidate, RuneProclamation, ProclaimedScroll, CovenCharter, CharterGlyph, CHARTER_FILE_NAME, OriginMark, MarkScript, UnboundScrollRite, StreamTide,
+    RuneLineage, LineageBranch, LineageBond, LineageWell, RuneScript, GlyphScribe, GRIMOIRE_FORMAT_VERSION,
+    RunicCompatibility, EcosystemRuneEngine,
+};
+
+#[cfg(feature = "i18n")]
+pub use crate::{TongueMark, TongueForm, TongueLedger, TongueGuise, SilentTongue, TongueEdition};
+
+#[cfg(feature = "debug-tools")]
+pub use crate::{RuneTrace, RuneDivination, RuneStep, RuneMetrics};
+
+#[cfg(feature = "lsp")]
+pub use crate::{RunicLore, RunicBlock, RunicCompletion, RunicToken, RunicDiagnostic, RunicWisdom, RunicOrigin};
+
+#[cfg(feature = "async")]
+pub use crate::{AsyncRuneEngine, SharedRuneScroll};
+
+#[cfg(feature = "axum-integration")]
+pub use crate::{AxumRuneEngine, RuneResponseError};
+
+#[cfg(feature = "warp-integration")]
+pub use crate::WarpRuneEngine;
+
+#[cfg(feature = "actix-integration")]
+pub use crate::ActixRuneEngine;
+
+#[cfg(feature = "wasm")]
+pub use crate::{WasmRuneEngineTrait, BrowserRuneEngine};
+
+#[cfg(feature = "cli")]
+pub use crate::{RunicCli, RunicCommands, RunicConfig, RuneWatcher, ScryingOptions, ScryingRenewal, ScryingGlass, ScryingLauncher, SystemScryer, transmute_scroll, transmute_scrolls, mass_transmutation, read_attunement, seek_breaches, seek_divergence, read_scroll_omens, tally_tongues, scry_in_browser};
+
+#[cfg(feature = "macros")]
+pub use crate::seal_rune;
+
+/// Declare an extension trait whose methods forward to inherent methods,
+/// along with the table mapping each conventional method to its verb
+macro_rules! mystical_verbs {
+    (
+        $(#[$trait_attr:meta])*
+        pub trait $trait_name:ident for $target:ident;
+        $(#[$table_attr:meta])*
+        pub const $table:ident;
+        $(
+            $(#[$verb_attr:meta])*
+            fn $verb:ident [$($signature:tt)*] => $method:ident($($arg:tt)*);
+        )*
+    ) => {
+        $(#[$trait_attr])*
+        pub trait $trait_name {
+            $(
+                $(#[$verb_attr])*
+                fn $verb $($signature)*;
+            )*
+        }
+
+        impl $trait_name for $target {
+            $(
+                $(#[$verb_attr])*
+                fn $verb $($signature)* {
+                    $target::$method($($arg)*)
+                }
+            )*
+        }
+
+        $(#[$table_attr])*
+        pub const $table: &[(&str, &str)] = &[$((stringify!($method), stringify!($verb))),*];
+    };
+}
+
+mystical_verbs! {
+    /// Mystical verbs for [`RuneEngine`]
+    pub trait RuneEngineExt for RuneEngine;
+    /// Conventional engine method → mystical verb
+    pub const ENGINE_VERBS;
+
+    /// Awaken an engine over a directory of scrolls (`new`)
+    fn awaken [(template_dir: &str) -> Self] => new(template_dir);
+    /// Awaken an engine for the browser (`new_wasm`)
+    #[cfg(feature = "wasm")]
+    fn awaken_in_browser [(template_dir: &str) -> Self] => new_wasm(template_dir);
+    /// Awaken an engine whose scrolls all passed the trial (`new_validated`)
+    fn awaken_tested [(template_dir: &str) -> RuneResult<RuneEngine>] => new_validated(template_dir);
+    /// Start forging an engine (`builder`)
+    fn forge [() -> RuneForge] => builder();
+    /// Carve a scroll from the template directory (`render`)
+    fn carve_scroll [(&mut self, template_name: &str, scroll: &RuneScroll) -> RuneResult<String>] => render(self, template_name, scroll);
+    /// Carve a scroll into a reusable buffer (`render_into`)
+    fn carve_scroll_into [(&mut self, template_name: &str, scroll: &RuneScroll, buf: &mut String) -> RuneResult<()>] => render_into(self, template_name, scroll, buf);
+    /// Carve runes from a template string (`render_string`)
+    fn carve_runes [(&mut self, template: &str, scroll: &RuneScroll) -> RuneResult<String>] => render_string(self, template, scroll);
+    /// Carve runes into a reusable buffer (`render_string_into`)
+    fn carve_runes_into [(&mut self, template: &str, scroll: &RuneScroll, buf: &mut String) -> RuneResult<()>] => render_string_into(self, template, scroll, buf);
+    /// Carve several scrolls with one scroll of values (`render_parallel`)
+    fn carve_scrolls_in_unison [(&mut self, template_names: &[String], scroll: &RuneScroll) -> RuneResult<Vec<String>>] => render_parallel(self, template_names, scroll);
+    /// Carve one template for many scrolls (`render_many`)
+    fn carve_many_scrolls [(&mut self, template_name: &str, scrolls: &[RuneScroll]) -> RuneResult<Vec<RuneResult<String>>>] => render_many(self, template_name, scrolls);
+    /// Carve one template for a stream of scrolls (`render_many_iter`)
+    fn carve_scroll_stream [<I, C>(&mut self, template_name: &str, scrolls: I) -> RuneResult<Vec<RuneResult<String>>> where I: IntoIterator<Item = C>, C: std::borrow::Borrow<RuneScroll>] => render_many_iter(self, template_name, scrolls);
+    /// Carve a scroll and record which values it read (`render_with_usage`)
+    fn carve_scroll_with_footprint [(&mut self, template_name: &str, scroll: &RuneScroll) -> RuneResult<(String, RuneFootprint)>] => render_with_usage(self, template_name, scroll);
+    /// Carve a remembered scroll without reaching for the sanctum (`render_offline`)
+    fn carve_from_memory [(&mut self, template_name: &str, scroll: &RuneScroll) -> RuneResult<String>] => render_offline(self, template_name, scroll);
+    /// Carve a scroll, stopping when the charm is severed (`render_with_cancel`)
+    fn carve_until_severed [(&mut self, template_name: &str, scroll: &RuneScroll, charm: &SeveranceCharm) -> RuneResult<String>] => render_with_cancel(self, template_name, scroll, charm);
+    /// Carve a scroll and gather the flaws its wards absorbed (`render_with_rescued_errors`)
+    fn carve_scroll_with_scars [(&mut self, template_name: &str, scroll: &RuneScroll) -> RuneResult<(String, Vec<RuneError>)>] => render_with_rescued_errors(self, template_name, scroll);
+    /// Invoke a familiar with its traits and vessels (`render_component`)
+    fn invoke_familiar [(&mut self, name: &str, traits: RuneScroll, vessels: HashMap<String, String>) -> RuneResult<String>] => render_component(self, name, traits, vessels);
+    /// Bind a scroll as a familiar (`register_component`)
+    fn bind_familiar [(&mut self, name: &str, template_name: &str)] => register_component(self, name, template_name);
+    /// Lay a scroll's runes bare as a syntax tree (`parse`)
+    fn dissect_runes [(&mut self, scroll_or_name: &str) -> RuneResult<crate::ast::Template>] => parse(self, scroll_or_name);
+    /// Carve plain variable runes only (`render_expression_only`)
+    fn carve_plain_runes [(&self, template: &str, scroll: &RuneScroll) -> RuneResult<String>] => render_expression_only(self, template, scroll);
+    /// Carve plain variable runes behind a ward (`render_expression_only_with`)
+    fn carve_warded_runes [(&self, template: &str, scroll: &RuneScroll, ward: &RuneWard) -> RuneResult<String>] => render_expression_only_with(self, template, scroll, ward);
+    /// Carve a rune sealed at compile time (`render_precompiled`)
+    fn carve_sealed_rune [(&mut self, template: &SealedRune, scroll: &RuneScroll) -> RuneResult<String>] => render_precompiled(self, template, scroll);
+    /// Test the runes of a template without carving them (`check_syntax`)
+    fn test_runes [(&self, template: &str) -> RuneResult<()>] => check_syntax(self, template);
+    /// Carve runes while tracing every step (`render_string_with_debug`)
+    #[cfg(feature = "debug-tools")]
+    fn divine_runes [(&mut self, template: &str, scroll: &RuneScroll) -> RuneResult<RuneDivination>] => render_string_with_debug(self, template, scroll);
+    /// Carve a scroll with the v0.4.0 feature set (`render_v040`)
+    fn carve_scroll_v040 [(&mut self, template_name: &str, scroll: &RuneScroll) -> RuneResult<String>] => render_v040(self, template_name, scroll);
+    /// Carve runes with the v0.4.0 feature set (`render_string_v040`)
+    fn carve_runes_v040 [(&mut self, template: &str, scroll: &RuneScroll) -> RuneResult<String>] => render_string_v040(self, template, scroll);
+    /// Summon a scroll from the template directory (`load_template`)
+    fn summon_scroll [(&mut self, name: &str) -> RuneResult<String>] => load_template(self, name);
+    /// Summon a scroll shared with the cache (`load_template_arc`)
+    fn summon_shared_scroll [(&mut self, name: &str) -> RuneResult<Arc<str>>] => load_template_arc(self, name);
+    /// Summon a scroll through a memory map (`load_template_mmap`)
+    fn summon_scroll_mapped [(&mut self, name: &str) -> RuneResult<String>] => load_template_mmap(self, name);
+    /// Is the scroll held in the vault? (`is_template_cached`)
+    fn is_scroll_remembered [(&self, name: &str) -> bool] => is_template_cached(self, name);
+    /// Number of scrolls in the vault (`get_cache_size`)
+    #[cfg(feature = "wasm")]
+    fn scroll_vault_size [(&self) -> usize] => get_cache_size(self);
+    /// Limit the scroll vault (`set_template_cache_capacity`)
+    fn limit_scroll_vault [(&mut self, capacity: Option<usize>)] => set_template_cache_capacity(self, capacity);
+    /// Limit of the scroll vault (`get_template_cache_capacity`)
+    fn scroll_vault_limit [(&self) -> Option<usize>] => get_template_cache_capacity(self);
+    /// Cleanse the vaults (`optimize_cache`)
+    fn purify_vault [(&mut self) -> usize] => optimize_cache(self);
+    /// Directory the scrolls are summoned from (`get_template_dir`)
+    fn scroll_sanctum [(&self) -> &str] => get_template_dir(self);
+    /// Open another sanctum of scrolls (`add_template_root`)
+    fn open_sanctum [(&mut self, sanctum: RuneSanctum)] => add_template_root(self, sanctum);
+    /// Open another sanctum with a precedence (`add_template_root_with_priority`)
+    fn open_sanctum_with_precedence [(&mut self, sanctum: RuneSanctum, precedence: i32)] => add_template_root_with_priority(self, sanctum, precedence);
+    /// Trace which sanctum a scroll is summoned from (`explain_resolution`)
+    fn trace_summoning [(&self, name: &str) -> SummoningTrace] => explain_resolution(self, name);
+    /// Carve a scroll the proclamation makes public (`render_public`)
+    fn carve_proclaimed_scroll [(&mut self, name: &str, scroll: &RuneScroll) -> RuneResult<String>] => render_public(self, name, scroll);
+    /// Public names of the proclamation (`list_public_templates`)
+    fn proclaimed_scrolls [(&mut self) -> RuneResult<Vec<String>>] => list_public_templates(self);
+    /// Read the proclamation of the sanctum (`template_manifest`)
+    fn read_proclamation [(&mut self) -> RuneResult<&RuneProclamation>] => template_manifest(self);
+    /// Heed the coven charter of a project (`load_project_config`)
+    fn read_coven_charter [(&mut self, path: impl AsRef<std::path::Path>) -> RuneResult<()>] => load_project_config(self, path);
+    /// The coven charter heeded (`project_config`)
+    fn coven_charter [(&self) -> Option<&CovenCharter>] => project_config(self);
+    /// Inscribe the charter this engine follows (`generate_project_config`)
+    fn inscribe_coven_charter [(&self, path: impl AsRef<std::path::Path>) -> RuneResult<CovenCharter>] => generate_project_config(self, path);
+    /// Mark carved scrolls with the scroll they came from (`set_provenance`)
+    fn mark_origins [(&mut self, mark: OriginMark)] => set_provenance(self, mark);
+    /// Stop marking carved scrolls (`clear_provenance`)
+    fn cease_marking_origins [(&mut self)] => clear_provenance(self);
+    /// The origin mark carved atop scrolls (`provenance`)
+    fn origin_mark [(&self) -> Option<&OriginMark>] => provenance(self);
+    /// Set how unbound scrolls are summoned (`set_raw_include_options`)
+    fn bind_unbound_scrolls [(&mut self, rite: UnboundScrollRite)] => set_raw_include_options(self, rite);
+    /// How unbound scrolls are summoned (`raw_include_options`)
+    fn unbound_scroll_rite [(&self) -> &UnboundScrollRite] => raw_include_options(self);
+    /// Carve a scroll into a stream, pouring large unbound scrolls straight through (`render_to_writer`)
+    fn carve_scroll_into_stream [(&mut self, name: &str, scroll: &RuneScroll, stream: &mut impl std::io::Write) -> RuneResult<()>] => render_to_writer(self, name, scroll, stream);
+    /// Carve runes into a stream as they are carved (`render_string_to_writer`)
+    fn carve_runes_into_stream [(&mut self, runes: &str, scroll: &RuneScroll, stream: &mut impl std::io::Write) -> RuneResult<()>] => render_string_to_writer(self, runes, scroll, stream);
+    /// Pour the stream onward every time this many bytes gathered (`set_stream_chunk_size`)
+    fn measure_stream_draught [(&mut self, bytes: Option<usize>)] => set_stream_chunk_size(self, bytes);
+    /// Choose where the stream is poured onward (`set_stream_flush_on`)
+    fn set_stream_tide [(&mut self, tide: StreamTide)] => set_stream_flush_on(self, tide);
+    /// Bytes gathered before the stream is poured onward (`stream_chunk_size`)
+    fn stream_draught [(&self) -> Option<usize>] => stream_chunk_size(self);
+    /// Where the stream is poured onward (`stream_flush_on`)
+    fn stream_tide [(&self) -> StreamTide] => stream_flush_on(self);
+    /// Let the stream run at most this many draughts ahead (`set_stream_buffer_size`)
+    fn dam_stream [(&mut self, chunks: usize)] => set_stream_buffer_size(self, chunks);
+    /// Draughts the stream may run ahead (`stream_buffer_size`)
+    fn stream_dam [(&self) -> usize] => stream_buffer_size(self);
+    /// Carve a scroll and trace the lineage of scrolls it summoned (`render_with_trace`)
+    fn carve_scroll_with_lineage [(&mut self, name: &str, scroll: &RuneScroll) -> RuneResult<(String, RuneLineage)>] => render_with_trace(self, name, scroll);
+    /// Carve a scroll and inscribe it in another script (`render_bytes`)
+    fn carve_scroll_in_script [(&mut self, name: &str, scroll: &RuneScroll, script: RuneScript) -> RuneResult<Vec<u8>>] => render_bytes(self, name, scroll, script);
+    /// Carve a scroll in another script into a reusable buffer (`render_bytes_into`)
+    fn carve_scroll_in_script_into [(&mut self, name: &str, scroll: &RuneScroll, script: &RuneScript, buf: &mut Vec<u8>) -> RuneResult<()>] => render_bytes_into(self, name, scroll, script, buf);
+    /// Bind scrolls and their lineage into a grimoire (`export_pack`)
+    fn bind_grimoire [(&mut self, scrolls: &[&str], path: impl AsRef<std::path::Path>) -> RuneResult<()>] => export_pack(self, scrolls, path);
+    /// Open a grimoire, optionally under a namespace (`import_pack`)
+    fn open_grimoire [(&mut self, path: impl AsRef<std::path::Path>, namespace: Option<&str>) -> RuneResult<Vec<String>>] => import_pack(self, path, namespace);
+    /// Let broken oaths be noted instead of halting the carving (`set_soft_assertions`)
+    fn soften_oaths [(&mut self, enabled: bool)] => set_soft_assertions(self, enabled);
+    /// Carve a scroll and gather the oaths it broke (`render_with_failed_assertions`)
+    fn carve_scroll_with_broken_oaths [(&mut self, template_name: &str, scroll: &RuneScroll) -> RuneResult<(String, Vec<RuneError>)>] => render_with_failed_assertions(self, template_name, scroll);
+    /// Carve runes and gather the oaths they broke (`render_string_with_failed_assertions`)
+    fn carve_runes_with_broken_oaths [(&mut self, template: &str, scroll: &RuneScroll) -> RuneResult<(String, Vec<RuneError>)>] => render_string_with_failed_assertions(self, template, scroll);
+    /// Carve a scroll in the mantle of your choosing, or in none (`render_with_layout`)
+    fn carve_scroll_in_mantle [(&mut self, template_name: &str, scroll: &RuneScroll, mantle: Option<&str>) -> RuneResult<String>] => render_with_layout(self, template_name, scroll, mantle);
+    /// Watch carvings for seals opened and never closed (`set_validate_html`)
+    #[cfg(feature = "lsp")]
+    fn guard_the_seals [(&mut self, guarded: bool)] => set_validate_html(self, guarded);
+    /// Whether carvings are watched for broken seals (`is_validate_html_enabled`)
+    #[cfg(feature = "lsp")]
+    fn are_seals_guarded [(&self) -> bool] => is_validate_html_enabled(self);
+    /// Seals the last carving left broken (`html_diagnostics`)
+    #[cfg(feature = "lsp")]
+    fn broken_seals [(&self) -> &[RunicDiagnostic]] => html_diagnostics(self);
+    /// Enchant a custom filter (`register_filter`)
+    fn enchant_filter [<F>(&mut self, name: &str, filter: F) where F: Fn(&str, &[&str]) -> RuneResult<String> + Send + Sync + 'static] => register_filter(self, name, filter);
+    /// Enchant a custom filter that carves HTML (`register_html_filter`)
+    fn enchant_glyph_filter [<F>(&mut self, name: &str, filter: F) where F: Fn(&str, &[&str]) -> RuneResult<String> + Send + Sync + 'static] => register_html_filter(self, name, filter);
+    /// Weave a chain of filters into one named enchantment (`register_pipeline`)
+    fn weave_enchantments [(&mut self, name: &str, chain: &str) -> RuneResult<()>] => register_pipeline(self, name, chain);
+    /// Bind a helper function (`register_helper`)
+    fn bind_helper [<F>(&mut self, name: &str, helper: F) where F: Fn(&[RuneSymbol]) -> RuneResult<RuneSymbol> + Send + Sync + 'static] => register_helper(self, name, helper);
+    /// Bind the CSS and JS tags of an asset (`register_asset`)
+    fn bind_talisman [(&mut self, name: &str, css: &str, js: &str)] => register_asset(self, name, css, js);
+    /// Number of known spells (`get_macro_count`)
+    #[cfg(all(feature = "wasm", feature = "template-macros"))]
+    fn spell_count [(&self) -> usize] => get_macro_count(self);
+    /// Inscribe the translations of a tongue (`set_translations`)
+    #[cfg(feature = "i18n")]
+    fn inscribe_tongues [(&mut self, locale: &str, translations: HashMap<String, String>)] => set_translations(self, locale, translations);
+    /// Inscribe the translations of a tongue from a nested catalog (`set_translations_nested`)
+    #[cfg(feature = "i18n")]
+    fn inscribe_tongue_tree [(&mut self, locale: &str, catalog: RuneSymbol) -> RuneResult<()>] => set_translations_nested(self, locale, catalog);
+    /// Exchange the translations of a tongue in one stroke (`swap_translations`)
+    #[cfg(feature = "i18n")]
+    fn exchange_tongues [(&self, locale: &str, translations: HashMap<String, String>) -> TongueEdition] => swap_translations(self, locale, translations);
+    /// Recall an earlier edition of a tongue (`rollback_translations`)
+    #[cfg(feature = "i18n")]
+    fn recall_tongues [(&self, locale: &str, edition: TongueEdition) -> RuneResult<()>] => rollback_translations(self, locale, edition);
+    /// The current edition of a tongue (`translation_version`)
+    #[cfg(feature = "i18n")]
+    fn tongue_edition [(&self, locale: &str) -> Option<TongueEdition>] => translation_version(self, locale);
+    /// Choose the current tongue (`set_locale`)
+    #[cfg(feature = "i18n")]
+    fn choose_tongue [(&mut self, locale: &str)] => set_locale(self, locale);
+    /// Translate a rune into the current tongue (`get_translation`)
+    #[cfg(feature = "i18n")]
+    fn translate_rune [(&self, key: &str) -> String] => get_translation(self, key);
+    /// Gather the tongue marks of a scroll (`extract_translation_keys`)
+    #[cfg(feature = "i18n")]
+    fn gather_tongue_marks [(&mut self, template_name: &str) -> RuneResult<Vec<TongueMark>>] => extract_translation_keys(self, template_name);
+    /// Gather the tongue marks of every scroll (`extract_all_translation_keys`)
+    #[cfg(feature = "i18n")]
+    fn gather_all_tongue_marks [(&mut self) -> RuneResult<Vec<TongueMark>>] => extract_all_translation_keys(self);
+    /// Weigh every tongue against the scrolls (`translation_report`)
+    #[cfg(feature = "i18n")]
+    fn weigh_tongues [(&mut self) -> RuneResult<TongueLedger>] => translation_report(self);
+    /// Don a guise for every tongue (`set_translation_mode`)
+    #[cfg(feature = "i18n")]
+    fn don_tongue_guise [(&mut self, guise: TongueGuise)] => set_translation_mode(self, guise);
+    /// The guise tongues wear (`get_translation_mode`)
+    #[cfg(feature = "i18n")]
+    fn tongue_guise [(&self) -> TongueGuise] => get_translation_mode(self);
+    /// Carve a scroll with tongues in another guise (`render_with_translation_mode`)
+    #[cfg(feature = "i18n")]
+    fn carve_scroll_in_tongue_guise [(&mut self, template_name: &str, scroll: &RuneScroll, guise: TongueGuise) -> RuneResult<String>] => render_with_translation_mode(self, template_name, scroll, guise);
+    /// Carve runes with tongues in another guise (`render_string_with_translation_mode`)
+    #[cfg(feature = "i18n")]
+    fn carve_runes_in_tongue_guise [(&mut self, template: &str, scroll: &RuneScroll, guise: TongueGuise) -> RuneResult<String>] => render_string_with_translation_mode(self, template, scroll, guise);
+    /// Tongues the last carving found silent (`missing_translations`)
+    #[cfg(feature = "i18n")]
+    fn silent_tongues [(&self) -> Vec<SilentTongue>] => missing_translations(self);
+    /// Permit summoning scrolls named by variables (`allow_dynamic_includes`)
+    fn permit_summoning [<I, S>(&mut self, patterns: I) where I: IntoIterator<Item = S>, S: AsRef<str>] => allow_dynamic_includes(self, patterns);
+    /// Swear the strict oath (`enable_strict_mode`)
+    fn swear_strict_oath [(&mut self)] => enable_strict_mode(self);
+    /// Release the strict oath (`disable_strict_mode`)
+    fn release_strict_oath [(&mut self)] => disable_strict_mode(self);
+    /// Is the strict oath sworn? (`is_strict_mode_enabled`)
+    fn is_strict_oath_sworn [(&self) -> bool] => is_strict_mode_enabled(self);
+    /// Forbid carving unbound runes (`set_strict_variables`)
+    fn forbid_unbound_runes [(&mut self, enabled: bool)] => set_strict_variables(self, enabled);
+    /// Are unbound runes forbidden? (`is_strict_variables_enabled`)
+    fn are_unbound_runes_forbidden [(&self) -> bool] => is_strict_variables_enabled(self);
+    /// Choose how bare booleans are spoken (`set_bool_format`)
+    fn set_verdict [(&mut self, verdict: RuneVerdict)] => set_bool_format(self, verdict);
+    /// How bare booleans are spoken (`get_bool_format`)
+    fn verdict [(&self) -> &RuneVerdict] => get_bool_format(self);
+    /// Choose what the `sanitize` filter keeps (`configure_sanitizer`)
+    fn attune_purifier [(&mut self, purifier: RunePurifier)] => configure_sanitizer(self, purifier);
+    /// What the `sanitize` filter keeps (`get_sanitizer_policy`)
+    fn purifier [(&self) -> &RunePurifier] => get_sanitizer_policy(self);
+    /// Choose how scroll values are veiled outside the carving (`set_redaction`)
+    fn veil_secrets [<F>(&mut self, veil: F) where F: Fn(&str, &RuneSymbol) -> RuneVeil + Send + Sync + 'static] => set_redaction(self, veil);
+    /// Bind fate to a seed so every carving repeats (`set_deterministic`)
+    fn bind_fate [(&mut self, enabled: bool, seed: u64)] => set_deterministic(self, enabled, seed);
+    /// Freeze the hour the runes read (`set_fixed_time`)
+    fn freeze_time [(&mut self, time: std::time::SystemTime)] => set_fixed_time(self, time);
+    /// Choose the guise of bare values of an essence (`set_value_formatter`)
+    fn set_guise [(&mut self, essence: RuneEssence, guise: impl Into<RuneGuise>)] => set_value_formatter(self, essence, guise);
+    /// Shed the guise of an essence (`clear_value_formatter`)
+    fn shed_guise [(&mut self, essence: RuneEssence)] => clear_value_formatter(self, essence);
+    /// The guise of an essence (`get_value_formatter`)
+    fn guise [(&self, essence: RuneEssence) -> Option<&RuneGuise>] => get_value_formatter(self, essence);
+    /// Veil or reveal visible comments (`set_strip_visible_comments`)
+    fn veil_whispers [(&mut self, veiled: bool)] => set_strip_visible_comments(self, veiled);
+    /// Are visible comments veiled? (`is_strip_visible_comments_enabled`)
+    fn are_whispers_veiled [(&self) -> bool] => is_strip_visible_comments_enabled(self);
+    /// Shave the line break after block runes (`set_trim_blocks`)
+    fn shave_block_tails [(&mut self, enabled: bool)] => set_trim_blocks(self, enabled);
+    /// Are block rune tails shaved? (`is_trim_blocks_enabled`)
+    fn are_block_tails_shaved [(&self) -> bool] => is_trim_blocks_enabled(self);
+    /// Shave the indentation before block runes (`set_lstrip_blocks`)
+    fn shave_block_margins [(&mut self, enabled: bool)] => set_lstrip_blocks(self, enabled);
+    /// Are block rune margins shaved? (`is_lstrip_blocks_enabled`)
+    fn are_block_margins_shaved [(&self) -> bool] => is_lstrip_blocks_enabled(self);
+    /// Compress the carved output (`set_minify_output`)
+    fn compress_runes [(&mut self, compressed: bool)] => set_minify_output(self, compressed);
+    /// Is the carved output compressed? (`is_minify_output_enabled`)
+    fn are_runes_compressed [(&self) -> bool] => is_minify_output_enabled(self);
+    /// Limit how deep runes may nest (`set_max_nesting_depth`)
+    fn limit_rune_depth [(&mut self, depth: usize)] => set_max_nesting_depth(self, depth);
+    /// How deep runes may nest (`get_max_nesting_depth`)
+    fn rune_depth_limit [(&self) -> usize] => get_max_nesting_depth(self);
+    /// Limit how deep scrolls may summon scrolls (`set_max_include_depth`)
+    fn limit_summoning_depth [(&mut self, depth: usize)] => set_max_include_depth(self, depth);
+    /// How deep scrolls may summon scrolls (`get_max_include_depth`)
+    fn summoning_depth_limit [(&self) -> usize] => get_max_include_depth(self);
+    /// Limit how many numbers a range may chant (`set_max_range_items`)
+    fn limit_chant_count [(&mut self, items: usize)] => set_max_range_items(self, items);
+    /// How many numbers a range may chant (`get_max_range_items`)
+    fn chant_count_limit [(&self) -> usize] => get_max_range_items(self);
+    /// Number of scribes carving many scrolls (`set_render_workers`)
+    fn set_scribe_count [(&mut self, scribes: usize)] => set_render_workers(self, scribes);
+    /// Number of scribes carving many scrolls (`get_render_workers`)
+    fn scribe_count [(&self) -> usize] => get_render_workers(self);
+    /// Transmute a scroll into a codex (`compile_to_bytecode`)
+    fn transmute_to_codex [(&mut self, template_name: &str) -> RuneResult<RuneCodex>] => compile_to_bytecode(self, template_name);
+    /// Transmute a scroll, ignoring the codex vault (`compile_to_bytecode_uncached`)
+    fn transmute_to_codex_afresh [(&mut self, template_name: &str) -> RuneResult<RuneCodex>] => compile_to_bytecode_uncached(self, template_name);
+    /// Transmute several scrolls at once (`compile_templates_parallel`)
+    fn transmute_in_unison [(&mut self, template_names: &[String]) -> RuneResult<Vec<RuneCodex>>] => compile_templates_parallel(self, template_names);
+    /// Recite a codex (`render_compiled`)
+    fn recite_codex [(&self, codex: &RuneCodex, scroll: &RuneScroll) -> RuneResult<String>] => render_compiled(self, codex, scroll);
+    /// Recite several codices at once (`render_compiled_parallel`)
+    fn recite_codices_in_unison [(&self, codices: &[RuneCodex], scroll: &RuneScroll) -> RuneResult<Vec<String>>] => render_compiled_parallel(self, codices, scroll);
+    /// Keep codices in their vault (`enable_bytecode_cache`)
+    fn keep_codices [(&mut self, kept: bool)] => enable_bytecode_cache(self, kept);
+    /// Are codices kept? (`is_bytecode_cache_enabled`)
+    fn are_codices_kept [(&self) -> bool] => is_bytecode_cache_enabled(self);
+    /// Is the codex of a scroll kept? (`is_bytecode_cached`)
+    fn is_codex_remembered [(&self, template_name: &str) -> bool] => is_bytecode_cached(self, template_name);
+    /// Open the third eye (`enable_debug_mode`)
+    fn open_third_eye [(&mut self)] => enable_debug_mode(self);
+    /// Close the third eye (`disable_debug_mode`)
+    fn close_third_eye [(&mut self)] => disable_debug_mode(self);
+    /// Is the third eye open? (`is_debug_enabled`)
+    fn is_third_eye_open [(&self) -> bool] => is_debug_enabled(self);
+    /// Let scrolls change while the engine lives (`enable_hot_reload`)
+    fn quicken_scrolls [(&mut self)] => enable_hot_reload(self);
+    /// Stop watching scrolls for changes (`disable_hot_reload`)
+    fn still_scrolls [(&mut self)] => disable_hot_reload(self);
+    /// Are scrolls watched for changes? (`is_hot_reload_enabled`)
+    fn are_scrolls_quickened [(&self) -> bool] => is_hot_reload_enabled(self);
+    /// Rekindle the scrolls that changed, with those built on them (`reload_changed_templates`)
+    fn rekindle_changed_scrolls [(&mut self) -> Vec<String>] => reload_changed_templates(self);
+    /// Rekindle scrolls and those built on them (`reload_templates`)
+    fn rekindle_scrolls [<S: AsRef<str>>(&mut self, names: &[S]) -> Vec<String>] => reload_templates(self, names);
+    /// Start watching the stars (`enable_performance_monitoring`)
+    fn watch_the_stars [(&mut self)] => enable_performance_monitoring(self);
+    /// Stop watching the stars (`disable_performance_monitoring`)
+    fn ignore_the_stars [(&mut self)] => disable_performance_monitoring(self);
+    /// Are the stars watched? (`is_performance_monitoring_enabled`)
+    fn are_stars_watched [(&self) -> bool] => is_performance_monitoring_enabled(self);
+    /// Read what the stars recorded (`get_performance_statistics`)
+    fn read_the_stars [(&self) -> RuneChronicle] => get_performance_statistics(self);
+    /// Does the browser console whisper? (`get_wasm_console_logging`)
+    #[cfg(feature = "wasm")]
+    fn is_browser_whispering [(&self) -> bool] => get_wasm_console_logging(self);
+    /// Let the browser console whisper (`set_wasm_console_logging`)
+    #[cfg(feature = "wasm")]
+    fn set_browser_whispering [(&mut self, whispering: bool)] => set_wasm_console_logging(self, whispering);
+    /// Read the lore of a template for editors (`parse_for_lsp`)
+    #[cfg(feature = "lsp")]
+    fn read_runic_lore [(&mut self, template_content: &str, file_path: &str) -> RuneResult<RunicLore>] => parse_for_lsp(self, template_content, file_path);
+    /// Foresee the runes that may follow (`get_completions_at_position`)
+    #[cfg(feature = "lsp")]
+    fn foresee_runes [(&mut self, template: &str, position: usize, scroll: &RuneScroll) -> RuneResult<Vec<RunicCompletion>>] => get_completions_at_position(self, template, position, scroll);
+    /// Illuminate the runes of a template (`tokenize_for_syntax_highlighting`)
+    #[cfg(feature = "lsp")]
+    fn illuminate_runes [(&mut self, template: &str) -> RuneResult<Vec<RunicToken>>] => tokenize_for_syntax_highlighting(self, template);
+    /// Colours of the illumination (`get_syntax_theme_info`)
+    #[cfg(feature = "lsp")]
+    fn illumination_palette [(&self) -> RuneResult<HashMap<String, String>>] => get_syntax_theme_info(self);
+    /// Scry the flaws of a template (`get_diagnostics_for_editor`)
+    #[cfg(feature = "lsp")]
+    fn scry_flaws [(&mut self, template: &str, scroll: &RuneScroll) -> RuneResult<Vec<RunicDiagnostic>>] => get_diagnostics_for_editor(self, template, scroll);
+    /// Seek wisdom about a rune (`get_hover_info_at_position`)
+    #[cfg(feature = "lsp")]
+    fn seek_wisdom [(&mut self, template: &str, position: usize, scroll: &RuneScroll) -> RuneResult<RunicWisdom>] => get_hover_info_at_position(self, template, position, scroll);
+    /// Trace a rune to its origin (`get_definition_at_position`)
+    #[cfg(feature = "lsp")]
+    fn trace_origin [(&mut self, template: &str, position: usize) -> RuneResult<RunicOrigin>] => get_definition_at_position(self, template, position);
+    /// Scry the flaws of every scroll (`lint_all`)
+    #[cfg(feature = "lsp")]
+    fn scry_all_scrolls [(&mut self) -> RuneResult<BTreeMap<String, Vec<RunicDiagnostic>>>] => lint_all(self);
+    /// Seek every unescaped output (`audit_raw_output`)
+    fn seek_breaches [(&mut self) -> RuneResult<Vec<RuneBreach>>] => audit_raw_output(self);
+    /// Put every scroll to the trial, skipping the `ignore` patterns (`validate_templates`)
+    fn test_all_scrolls [(&mut self, ignore: &[&str]) -> RuneResult<()>] => validate_templates(self, ignore);
+    /// Compare the carvings of two scrolls (`diff_renders`)
+    fn compare_carvings [(&mut self, template_a: &str, template_b: &str, scroll: &RuneScroll) -> RuneResult<RuneDivergence>] => diff_renders(self, template_a, template_b, scroll);
+    /// Compare the carvings of two runes (`diff_render_strings`)
+    fn compare_rune_carvings [(&mut self, template_a: &str, template_b: &str, scroll: &RuneScroll) -> RuneResult<RuneDivergence>] => diff_render_strings(self, template_a, template_b, scroll);
+    /// Compare the carvings of one template with two scrolls (`diff_contexts`)
+    fn compare_scroll_carvings [(&mut self, template_name: &str, scroll_a: &RuneScroll, scroll_b: &RuneScroll) -> RuneResult<RuneDivergence>] => diff_contexts(self, template_name, scroll_a, scroll_b);
+    /// Carve a scroll and time each of its runes (`profile_render`)
+    fn read_carving_omens [(&mut self, template_name: &str, scroll: &RuneScroll) -> RuneResult<CarvingOmen>] => profile_render(self, template_name, scroll);
+    /// Survey, from now on, which runes of the carved scrolls awaken (`enable_coverage`)
+    fn survey_runes [(&mut self, enabled: bool)] => enable_coverage(self, enabled);
+    /// Is the survey under way? (`is_coverage_enabled`)
+    fn is_surveying [(&self) -> bool] => is_coverage_enabled(self);
+    /// Read the survey of the carved scrolls (`coverage_report`)
+    fn read_survey [(&self) -> RuneSurvey] => coverage_report(self);
+    /// Trace the values a scroll needs (`analyze_context_usage`)
+    fn trace_footprint [(&mut self, template_name: &str) -> RuneResult<RuneFootprint>] => analyze_context_usage(self, template_name);
+    /// Awaken every scroll ahead of time (`warm_up`)
+    fn awaken_scrolls [(&mut self) -> RuneResult<RuneAwakening>] => warm_up(self);
+    /// Awaken the scrolls matching a pattern (`warm_up_filtered`)
+    fn awaken_chosen_scrolls [(&mut self, pattern: &str) -> RuneResult<RuneAwakening>] => warm_up_filtered(self, pattern);
+    /// Take on an aspect (`apply_profile`)
+    fn take_aspect [(&mut self, aspect: RuneAspect)] => apply_profile(self, aspect);
+    /// Attune the engine (`apply_config`)
+    fn attune [(&mut self, attunement: &RuneAttunement)] => apply_config(self, attunement);
+    /// Current attunement (`config`)
+    fn attunement [(&self) -> RuneAttunement] => config(self);
+}
+
+mystical_verbs! {
+    /// Mystical verbs for [`RuneScroll`]
+    pub trait RuneScrollExt for RuneScroll;
+    /// Conventional context method → mystical verb
+    pub const SCROLL_VERBS;
+
+    /// Unroll a blank scroll (`new`)
+    fn blank_scroll [() -> Self] => new();
+    /// Unroll a scroll over a shared one (`layered`)
+    fn unroll_over [(base: Arc<RuneScroll>) -> Self] => layered(base);
+    /// The scroll this one was unrolled over (`base`)
+    fn underlying_scroll [(&self) -> Option<&Arc<RuneScroll>>] => base(self);
+    /// Inscribe a symbol (`set`)
+    fn inscribe [(&mut self, name: &str, symbol: impl Into<RuneSymbol>)] => set(self, name, symbol);
+    /// Inscribe a string (`set_string`)
+    fn inscribe_string [(&mut self, name: &str, value: &str)] => set_string(self, name, value);
+    /// Inscribe a boolean (`set_bool`)
+    fn inscribe_bool [(&mut self, name: &str, value: bool)] => set_bool(self, name, value);
+    /// Inscribe an integer (`set_number`)
+    fn inscribe_number [(&mut self, name: &str, value: i64)] => set_number(self, name, value);
+    /// Inscribe a float (`set_float`)
+    fn inscribe_float [(&mut self, name: &str, value: f64)] => set_float(self, name, value);
+    /// Inscribe nested objects (`set_nested_object`)
+    fn inscribe_nested [(&mut self, name: &str, structure: Vec<(&str, Vec<(&str, &str)>)>)] => set_nested_object(self, name, structure);
+    /// Divine a symbol (`get`)
+    fn divine [(&self, name: &str) -> Option<&RuneSymbol>] => get(self, name);
+    /// Divine a symbol as a string (`get_string`)
+    fn divine_string [(&self, name: &str) -> Option<String>] => get_string(self, name);
+    /// Does the scroll bear a symbol? (`contains`)
+    fn bears [(&self, name: &str) -> bool] => contains(self, name);
+}
@@ -0,0 +1,115 @@
+//! Template packs for v0.6.0
+//!
+//! A pack bundles templates, the templates they include or extend and the
+//! translations they use into one file, written by
+//! [`TemplateEngine::export_pack`](crate::TemplateEngine::export_pack) and
+//! registered in another engine by
+//! [`TemplateEngine::import_pack`](crate::TemplateEngine::import_pack).
+//!
+//! The file is the `RUNEPACK` magic, the format version as a little-endian
+//! `u32`, then records: a tag byte followed by two strings, each a
+//! little-endian `u32` byte length and UTF-8 bytes.
+//!
+//! | Tag | First string | Second string |
+//! |-----|--------------|---------------|
+//! | `M` | manifest key (`engine_version`, `entry`) | value |
+//! | `T` | template name | source |
+//! | `L` | `locale:key` | translation |
+
+use crate::{TemplateError, TemplateResult};
+use std::collections::BTreeMap;
+
+/// Version of the pack format written by this release; other versions are
+/// rejected on import
+pub const PACK_FORMAT_VERSION: u32 = 1;
+
+const PACK_MAGIC: &[u8; 8] = b"RUNEPACK";
+
+/// Contents of a pack file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct TemplatePack {
+    /// Crate version that exported the pack
+    pub engine_version: String,
+    /// Templates named in the export, before their dependencies were added
+    pub entries: Vec<String>,
+    /// Template name -> source
+    pub templates: BTreeMap<String, String>,
+    /// Locale -> key -> translation
+    pub translations: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl TemplatePack {
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(PACK_MAGIC);
+        bytes.extend_from_slice(&PACK_FORMAT_VERSION.to_le_bytes());
+
+        let mut record = |tag: u8, first: &str, second: &str| {
+            bytes.push(tag);
+            for text in [first, second] {
+                bytes.extend_from_slice(&(text.len() as u32).to_le_bytes());
+                bytes.extend_from_slice(text.as_bytes());
+            }
+        };
+        record(b'M', "engine_version", &self.engine_version);
+        for entry in &self.entries {
+            record(b'M', "entry", entry);
+        }
+        for (name, source) in &self.templates {
+            record(b'T', name, source);
+        }
+        for (locale, table) in &self.translations {
+            for (key, text) in table {
+                record(b'L', &format!("{}:{}", locale, key), text);
+            }
+        }
+        bytes
+    }
+
+    pub(crate) fn from_bytes(bytes: &[u8]) -> TemplateResult<Self> {
+        let corrupt = |message: &str| TemplateError::Template(format!("Corrupt template pack: {}", message));
+        let rest = bytes.strip_prefix(PACK_MAGIC.as_slice())
+            .ok_or_else(|| TemplateError::Template("Not a template pack".to_string()))?;
+        let (version, mut rest) = split_u32(rest).ok_or_else(|| corrupt("missing format version"))?;
+        if version != PACK_FORMAT_VERSION {
+            return Err(TemplateError::Template(format!(
+                "Template pack format version {} is not supported; this engine reads version {}",
+                version, PACK_FORMAT_VERSION
+            )));
+        }
+
+        let mut pack = TemplatePack::default();
+        while let Some((&tag, after_tag)) = rest.split_first() {
+            let (first, after_first) = split_string(after_tag).ok_or_else(|| corrupt("truncated record"))?;
+            let (second, after_second) = split_string(after_first).ok_or_else(|| corrupt("truncated record"))?;
+            rest = after_second;
+            match (tag, first) {
+                (b'M', "engine_version") => pack.engine_version = second.to_string(),
+                (b'M', "entry") => pack.entries.push(second.to_string()),
+                // Later format revisions may add manifest keys
+                (b'M', _) => {}
+                (b'T', name) => {
+                    pack.templates.insert(name.to_string(), second.to_string());
+                }
+                (b'L', locale_key) => {
+                    let (locale, key) = locale_key.split_once(':').ok_or_else(|| corrupt("translation without a locale"))?;
+                    pack.translations.entry(locale.to_string()).or_default().insert(key.to_string(), second.to_string());
+                }
+                _ => return Err(corrupt(&format!("unknown record tag {:?}", tag as char))),
+            }
+        }
+        Ok(pack)
+    }
+}
+
+fn split_u32(bytes: &[u8]) -> Option<(u32, &[u8])> {
+    let number = bytes.get(..4)?.try_into().ok()?;
+    Some((u32::from_le_bytes(number), &bytes[4..]))
+}
+
+fn split_string(bytes: &[u8]) -> Option<(&str, &[u8])> {
+    let (length, rest) = split_u32(bytes)?;
+    let length = usize::try_from(length).ok()?;
+    let text = std::str::from_utf8(rest.get(..length)?).ok()?;
+    Some((text, &rest[length..]))
+}
@@ -0,0 +1,51 @@
+//! Precompiled templates for v0.6.0
+//!
+//! Built at compile time by the `runic_template!` macro (requires the
+//! `macros` feature) and rendered with
+//! [`TemplateEngine::render_precompiled`](crate::TemplateEngine::render_precompiled).
+
+/// One step of a precompiled template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrecompiledInstruction {
+    /// Literal text
+    Text(&'static str),
+    /// `{{expression}}`, HTML-escaped unless a filter produces HTML
+    Output(&'static str),
+    /// `{{& expression}}`
+    RawOutput(&'static str),
+    /// Any other tag; the template is rendered by the regular pipeline
+    Directive(&'static str),
+}
+
+/// A template whose syntax was checked at compile time
+///
+/// Create one with `runic_template!`:
+///
+/// ```rust,ignore
+/// use mystical_runic::{runic_template, PrecompiledTemplate};
+///
+/// const CARD: PrecompiledTemplate = runic_template!("<h2>{{title|capitalize}}</h2>");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrecompiledTemplate {
+    source: &'static str,
+    instructions: &'static [PrecompiledInstruction],
+}
+
+impl PrecompiledTemplate {
+    /// Used by the `runic_template!` expansion; the instructions must match the source
+    #[doc(hidden)]
+    pub const fn new(source: &'static str, instructions: &'static [PrecompiledInstruction]) -> Self {
+        Self { source, instructions }
+    }
+
+    /// The template source
+    pub const fn source(&self) -> &'static str {
+        self.source
+    }
+
+    /// The compiled instructions; comments are already removed
+    pub const fn instructions(&self) -> &'static [PrecompiledInstruction] {
+        self.instructions
+    }
+}
@@ -0,0 +1,336 @@
+//! Engine profiles and builder for v0.6.0
+//!
+//! Switch every development/production setting in one call, then override
+//! individual settings as usual.
+
+use crate::engine::TemplateEngine;
+use crate::error::TemplateResult;
+
+/// Template cache capacity used by the development profile
+const DEVELOPMENT_CACHE_CAPACITY: usize = 16;
+
+/// Snapshot of the engine settings a profile controls
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineConfig {
+    /// Debug mode (`enable_debug_mode`)
+    pub debug_mode: bool,
+    /// Hot reload of changed templates (`enable_hot_reload`)
+    pub hot_reload: bool,
+    /// Bytecode caching (`enable_bytecode_cache`)
+    pub bytecode_cache: bool,
+    /// Template source cache capacity, `None` for unbounded (`set_template_cache_capacity`)
+    pub template_cache_capacity: Option<usize>,
+    /// Strict mode errors for invalid coercions (`enable_strict_mode`)
+    pub strict_mode: bool,
+    /// Output minification (`set_minify_output`)
+    pub minify_output: bool,
+    /// Drop `{{#-- ... --}}` comments (`set_strip_visible_comments`)
+    pub strip_visible_comments: bool,
+    /// Render and compilation statistics (`enable_performance_monitoring`)
+    pub performance_monitoring: bool,
+}
+
+impl Default for EngineConfig {
+    /// The settings of a freshly created `TemplateEngine`
+    fn default() -> Self {
+        Self {
+            debug_mode: false,
+            hot_reload: false,
+            bytecode_cache: false,
+            template_cache_capacity: None,
+            strict_mode: false,
+            minify_output: false,
+            strip_visible_comments: false,
+            performance_monitoring: false,
+        }
+    }
+}
+
+/// Preset engine configurations
+///
+/// | Setting | `Development` | `Production` |
+/// |---------|---------------|--------------|
+/// | `debug_mode` | on | off |
+/// | `hot_reload` | on | off |
+/// | `bytecode_cache` | off | on |
+/// | `template_cache_capacity` | 16 templates | unbounded |
+/// | `strict_mode` | off (lenient) | on |
+/// | `minify_output` | off (pretty) | on |
+/// | `strip_visible_comments` | off | on |
+/// | `performance_monitoring` | off | on |
+///
+/// Applying a profile sets every setting in the table, so profiles can be
+/// switched back and forth. Other settings (locale, filters, assets, ...)
+/// are left alone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Profile {
+    /// Fast feedback while editing templates
+    Development,
+    /// Cached, minified and strict output
+    Production,
+    /// Any other combination
+    Custom(EngineConfig),
+}
+
+impl Profile {
+    /// The settings this profile applies
+    pub fn config(&self) -> EngineConfig {
+        match self {
+            Profile::Development => EngineConfig {
+                debug_mode: true,
+                hot_reload: true,
+                bytecode_cache: false,
+                template_cache_capacity: Some(DEVELOPMENT_CACHE_CAPACITY),
+                strict_mode: false,
+                minify_output: false,
+                strip_visible_comments: false,
+                performance_monitoring: false,
+            },
+            Profile::Production => EngineConfig {
+                debug_mode: false,
+                hot_reload: false,
+                bytecode_cache: true,
+                template_cache_capacity: None,
+                strict_mode: true,
+                minify_output: true,
+                strip_visible_comments: true,
+                performance_monitoring: true,
+            },
+            Profile::Custom(config) => config.clone(),
+        }
+    }
+}
+
+impl TemplateEngine {
+    /// Start building an engine
+    ///
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{TemplateEngine, Profile};
+    ///
+    /// let engine = TemplateEngine::builder()
+    ///     .template_dir("templates")
+    ///     .profile(Profile::Production)
+    ///     .strict_mode(false)
+    ///     .build();
+    ///
+    /// assert!(engine.is_minify_output_enabled());
+    /// assert!(!engine.is_strict_mode_enabled());
+    /// ```
+    pub fn builder() -> TemplateEngineBuilder {
+        TemplateEngineBuilder::new()
+    }
+
+    /// Apply every setting of a profile; each one can still be changed afterwards
+    pub fn apply_profile(&mut self, profile: Profile) {
+        self.apply_config(&profile.config());
+    }
+
+    /// Apply an engine configuration
+    pub fn apply_config(&mut self, config: &EngineConfig) {
+        if config.debug_mode {
+            self.enable_debug_mode();
+        } else {
+            self.disable_debug_mode();
+        }
+        if config.hot_reload {
+            self.enable_hot_reload();
+        } else {
+            self.disable_hot_reload();
+        }
+        self.enable_bytecode_cache(config.bytecode_cache);
+        self.set_template_cache_capacity(config.template_cache_capacity);
+        if config.strict_mode {
+            self.enable_strict_mode();
+        } else {
+            self.disable_strict_mode();
+        }
+        self.set_minify_output(config.minify_output);
+        self.set_strip_visible_comments(config.strip_visible_comments);
+        if config.performance_monitoring {
+            self.enable_performance_monitoring();
+        } else {
+            self.disable_performance_monitoring();
+        }
+    }
+
+    /// Current values of the settings profiles control
+    pub fn config(&self) -> EngineConfig {
+        EngineConfig {
+            debug_mode: self.is_debug_enabled(),
+            hot_reload: self.is_hot_reload_enabled(),
+            bytecode_cache: self.is_bytecode_cache_enabled(),
+            template_cache_capacity: self.get_template_cache_capacity(),
+            strict_mode: self.is_strict_mode_enabled(),
+            minify_output: self.is_minify_output_enabled(),
+            strip_visible_comments: self.is_strip_visible_comments_enabled(),
+            performance_monitoring: self.is_performance_monitoring_enabled(),
+        }
+    }
+}
+
+/// Builder for [`TemplateEngine`]
+///
+/// Settings are applied in call order, so a setting called after
+/// [`profile`](Self::profile) overrides the profile's value.
+#[derive(Debug, Clone)]
+pub struct TemplateEngineBuilder {
+    template_dir: String,
+    config: EngineConfig,
+    trim_blocks: bool,
+    lstrip_blocks: bool,
+    strict_variables: bool,
+    validate: bool,
+    validation_ignore: Vec<String>,
+}
+
+impl TemplateEngineBuilder {
+    /// Create a builder with the default settings and the current directory
+    pub fn new() -> Self {
+        Self {
+            template_dir: ".".to_string(),
+            config: EngineConfig::default(),
+            trim_blocks: false,
+            lstrip_blocks: false,
+            strict_variables: false,
+            validate: false,
+            validation_ignore: Vec::new(),
+        }
+    }
+
+    /// Directory templates are loaded from
+    pub fn template_dir(mut self, template_dir: &str) -> Self {
+        self.template_dir = template_dir.to_string();
+        self
+    }
+
+    /// Apply a profile, replacing every setting it controls
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.config = profile.config();
+        self
+    }
+
+    /// Enable or disable debug mode
+    pub fn debug_mode(mut self, enabled: bool) -> Self {
+        self.config.debug_mode = enabled;
+        self
+    }
+
+    /// Enable or disable hot reload
+    pub fn hot_reload(mut self, enabled: bool) -> Self {
+        self.config.hot_reload = enabled;
+        self
+    }
+
+    /// Enable or disable bytecode caching
+    pub fn bytecode_cache(mut self, enabled: bool) -> Self {
+        self.config.bytecode_cache = enabled;
+        self
+    }
+
+    /// Limit the template source cache
+    pub fn template_cache_capacity(mut self, capacity: Option<usize>) -> Self {
+        self.config.template_cache_capacity = capacity;
+        self
+    }
+
+    /// Enable or disable strict mode
+    pub fn strict_mode(mut self, enabled: bool) -> Self {
+        self.config.strict_mode = enabled;
+        self
+    }
+
+    /// Enable or disable output minification
+    pub fn minify_output(mut self, enabled: bool) -> Self {
+        self.config.minify_output = enabled;
+        self
+    }
+
+    /// Strip or keep visible comments
+    pub fn strip_visible_comments(mut self, enabled: bool) -> Self {
+        self.config.strip_visible_comments = enabled;
+        self
+    }
+
+    /// Enable or disable performance monitoring
+    pub fn performance_monitoring(mut self, enabled: bool) -> Self {
+        self.config.performance_monitoring = enabled;
+        self
+    }
+
+    /// Remove the newline after block tags (see [`TemplateEngine::set_trim_blocks`])
+    pub fn trim_blocks(mut self, enabled: bool) -> Self {
+        self.trim_blocks = enabled;
+        self
+    }
+
+    /// Remove the indentation before block tags (see [`TemplateEngine::set_lstrip_blocks`])
+    pub fn lstrip_blocks(mut self, enabled: bool) -> Self {
+        self.lstrip_blocks = enabled;
+        self
+    }
+
+    /// Fail renders that output missing variables (see [`TemplateEngine::set_strict_variables`])
+    pub fn strict_variables(mut self, enabled: bool) -> Self {
+        self.strict_variables = enabled;
+        self
+    }
+
+    /// Check every template when building (see [`TemplateEngine::validate_templates`])
+    pub fn validate_on_build(mut self, enabled: bool) -> Self {
+        self.validate = enabled;
+        self
+    }
+
+    /// Skip templates matching `pattern` (`"drafts/"`, `"*.wip.html"`) when validating
+    pub fn validation_ignore(mut self, pattern: &str) -> Self {
+        self.validation_ignore.push(pattern.to_string());
+        self
+    }
+
+    /// Create the engine
+    ///
+    /// # Panics
+    /// With [`validate_on_build`](Self::validate_on_build), when a template is
+    /// broken; use [`try_build`](Self::try_build) to handle the error instead.
+    pub fn build(self) -> TemplateEngine {
+        match self.try_build() {
+            Ok(engine) => engine,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Create the engine, validating its templates first when
+    /// [`validate_on_build`](Self::validate_on_build) is set
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use mystical_runic::{TemplateEngine, Profile};
+    ///
+    /// let engine = TemplateEngine::builder()
+    ///     .template_dir("templates")
+    ///     .profile(Profile::Production)
+    ///     .validate_on_build(true)
+    ///     .validation_ignore("drafts/")
+    ///     .try_build()?;
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn try_build(self) -> TemplateResult<TemplateEngine> {
+        let mut engine = TemplateEngine::new(&self.template_dir);
+        engine.apply_config(&self.config);
+        engine.set_trim_blocks(self.trim_blocks);
+        engine.set_lstrip_blocks(self.lstrip_blocks);
+        engine.set_strict_variables(self.strict_variables);
+        if self.validate {
+            let ignore: Vec<&str> = self.validation_ignore.iter().map(String::as_str).collect();
+            engine.validate_templates(&ignore)?;
+        }
+        Ok(engine)
+    }
+}
+
+impl Default for TemplateEngineBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,305 @@
+//! Project configuration for v0.6.0
+//!
+//! A `runic.toml` file tells editor tooling what an application sets up in
+//! code: the template directory and roots, the names of its custom filters
+//! and helpers, and strict mode. With
+//! [`TemplateEngine::load_project_config`](crate::TemplateEngine::load_project_config)
+//! the LSP methods stop reporting the project's own filters and helpers as
+//! unknown; [`TemplateEngine::generate_project_config`](crate::TemplateEngine::generate_project_config)
+//! writes the file for an engine configured in code.
+//!
+//! The file is a small subset of TOML, read without dependencies:
+//!
+//! ```toml
+//! # runic.toml
+//! template_dir = "templates"
+//! delimiters = ["{{", "}}"]
+//! strict = true
+//! features = ["i18n", "lsp"]
+//! filters = ["money(currency)", "initials"]
+//! helpers = ["asset_url(path)"]
+//!
+//! [roots]
+//! vendor = "vendor/design-system"
+//! ```
+//!
+//! Values are quoted strings, `true`/`false` or one-line arrays of strings.
+//! Filters and helpers are names, optionally followed by their parameters.
+//! Paths are relative to the directory holding the file.
+
+use crate::{TemplateError, TemplateResult};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+/// File the project configuration is read from, at the project root
+pub const PROJECT_CONFIG_FILE_NAME: &str = "runic.toml";
+
+/// Settings of a `runic.toml` file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectConfig {
+    /// Template directory
+    pub template_dir: Option<PathBuf>,
+    /// Additional template roots by name
+    pub roots: BTreeMap<String, PathBuf>,
+    /// Custom filters registered in code
+    pub filters: Vec<ProjectSymbol>,
+    /// Helpers registered in code
+    pub helpers: Vec<ProjectSymbol>,
+    /// Tag delimiters; only `{{` and `}}` are supported
+    pub delimiters: (String, String),
+    /// Whether strict mode is on; `None` leaves the engine's setting
+    pub strict: Option<bool>,
+    /// Cargo features the project enables, `i18n`, `lsp`, ...
+    pub features: Vec<String>,
+}
+
+impl Default for ProjectConfig {
+    fn default() -> Self {
+        Self {
+            template_dir: None,
+            roots: BTreeMap::new(),
+            filters: Vec::new(),
+            helpers: Vec::new(),
+            delimiters: ("{{".to_string(), "}}".to_string()),
+            strict: None,
+            features: Vec::new(),
+        }
+    }
+}
+
+/// A filter or helper name, with its parameters when the configuration
+/// lists them: `money(currency)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectSymbol {
+    pub name: String,
+    pub parameters: Option<Vec<String>>,
+}
+
+impl ProjectSymbol {
+    /// A symbol without a signature
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), parameters: None }
+    }
+
+    /// Parse `name` or `name(param, other)`
+    fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim();
+        let (name, parameters) = match spec.split_once('(') {
+            Some((name, rest)) => {
+                let parameters = rest.strip_suffix(')')?.split(',')
+                    .map(str::trim)
+                    .filter(|parameter| !parameter.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                (name.trim(), Some(parameters))
+            }
+            None => (spec, None),
+        };
+        let identifier = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+        identifier.then(|| Self { name: name.to_string(), parameters })
+    }
+
+    /// `name(param, other)`, or just the name without a signature
+    pub fn signature(&self) -> String {
+        match &self.parameters {
+            Some(parameters) => format!("{}({})", self.name, parameters.join(", ")),
+            None => self.name.clone(),
+        }
+    }
+}
+
+impl ProjectConfig {
+    /// Parse a `runic.toml` file; errors name the offending line
+    pub fn parse(source: &str) -> TemplateResult<Self> {
+        let mut config = Self::default();
+        let mut in_roots = false;
+        for (index, line) in source.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: String| {
+                TemplateError::Parse(format!("{} line {}: {}", PROJECT_CONFIG_FILE_NAME, index + 1, message))
+            };
+
+            if let Some(section) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+                in_roots = match section.trim() {
+                    "roots" => true,
+                    other => return Err(error(format!("unknown section '[{}]'; expected [roots]", other))),
+                };
+                continue;
+            }
+            let (key, value) = line.split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| error(format!("expected 'key = value', found '{}'", line)))?;
+
+            if in_roots {
+                let dir = parse_string(value).ok_or_else(|| error(format!("root '{}' must be a quoted directory", key)))?;
+                config.roots.insert(key.to_string(), PathBuf::from(dir));
+                continue;
+            }
+            match key {
+                "template_dir" => {
+                    let dir = parse_string(value).ok_or_else(|| error("template_dir must be a quoted directory".to_string()))?;
+                    config.template_dir = Some(PathBuf::from(dir));
+                }
+                "strict" => {
+                    config.strict = match value {
+                        "true" => Some(true),
+                        "false" => Some(false),
+                        _ => return Err(error(format!("strict must be true or false, found '{}'", value))),
+                    };
+                }
+                "delimiters" => {
+                    config.delimiters = match parse_array(value).as_deref() {
+                        Some([open, close]) => (open.clone(), close.clone()),
+                        _ => return Err(error("delimiters must be an array of two strings".to_string())),
+                    };
+                    if config.delimiters.0 != "{{" || config.delimiters.1 != "}}" {
+                        return Err(error(format!(
+                            "delimiters \"{}\" and \"{}\" are not supported; templates use {{{{ and }}}}",
+                            config.delimiters.0, config.delimiters.1
+                        )));
+                    }
+                }
+                "features" => {
+                    config.features = parse_array(value).ok_or_else(|| error("features must be an array of strings".to_string()))?;
+                }
+                "filters" | "helpers" => {
+                    let symbols = parse_array(value)
+                        .ok_or_else(|| error(format!("{} must be an array of strings", key)))?
+                        .iter()
+                        .map(|spec| ProjectSymbol::parse(spec)
+                            .ok_or_else(|| error(format!("'{}' is not a name or 'name(params)'", spec))))
+                        .collect::<TemplateResult<Vec<_>>>()?;
+                    if key == "filters" {
+                        config.filters = symbols;
+                    } else {
+                        config.helpers = symbols;
+                    }
+                }
+                _ => return Err(error(format!(
+                    "unknown key '{}'; expected template_dir, delimiters, strict, features, filters or helpers", key
+                ))),
+            }
+        }
+        Ok(config)
+    }
+
+    /// The listed filter named `name`
+    pub fn filter(&self, name: &str) -> Option<&ProjectSymbol> {
+        self.filters.iter().find(|filter| filter.name == name)
+    }
+
+    /// The listed helper named `name`
+    pub fn helper(&self, name: &str) -> Option<&ProjectSymbol> {
+        self.helpers.iter().find(|helper| helper.name == name)
+    }
+}
+
+/// Writes the configuration in the format [`ProjectConfig::parse`] reads
+impl fmt::Display for ProjectConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# {}", PROJECT_CONFIG_FILE_NAME)?;
+        if let Some(dir) = &self.template_dir {
+            writeln!(f, "template_dir = {}", quote(&dir.to_string_lossy()))?;
+        }
+        writeln!(f, "delimiters = [{}, {}]", quote(&self.delimiters.0), quote(&self.delimiters.1))?;
+        if let Some(strict) = self.strict {
+            writeln!(f, "strict = {}", strict)?;
+        }
+        writeln!(f, "features = [{}]", quote_all(self.features.iter().cloned()))?;
+        writeln!(f, "filters = [{}]", quote_all(self.filters.iter().map(ProjectSymbol::signature)))?;
+        writeln!(f, "helpers = [{}]", quote_all(self.helpers.iter().map(ProjectSymbol::signature)))?;
+        if !self.roots.is_empty() {
+            writeln!(f, "\n[roots]")?;
+            for (name, dir) in &self.roots {
+                writeln!(f, "{} = {}", name, quote(&dir.to_string_lossy()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The line without a `#` comment outside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// A quoted string value, `\"` and `\\` unescaped
+fn parse_string(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut text = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                'n' => text.push('\n'),
+                't' => text.push('\t'),
+                escaped @ ('"' | '\\') => text.push(escaped),
+                _ => return None,
+            },
+            '"' => return None,
+            _ => text.push(c),
+        }
+    }
+    Some(text)
+}
+
+/// A one-line array of quoted strings
+fn parse_array(value: &str) -> Option<Vec<String>> {
+    let inner = value.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let mut items = Vec::new();
+    let mut rest = inner;
+    while !rest.is_empty() {
+        if !rest.starts_with('"') {
+            return None;
+        }
+        // The closing quote is the first one not escaped
+        let mut end = None;
+        let mut escaped = false;
+        for (index, c) in rest.char_indices().skip(1) {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => {
+                    end = Some(index);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let end = end?;
+        items.push(parse_string(&rest[..=end])?);
+        rest = rest[end + 1..].trim_start();
+        rest = match rest.strip_prefix(',') {
+            Some(after) => after.trim_start(),
+            None if rest.is_empty() => rest,
+            None => return None,
+        };
+    }
+    Some(items)
+}
+
+/// `value` as a quoted string
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n").replace('\t', "\\t"))
+}
+
+/// `values` as the items of an array
+fn quote_all(values: impl Iterator<Item = String>) -> String {
+    values.map(|value| quote(&value)).collect::<Vec<_>>().join(", ")
+}
@@ -0,0 +1,146 @@
+//! Provenance markers for v0.6.0
+//!
+//! Generated files committed to a repository should say where they come
+//! from. With [`TemplateEngine::set_provenance`](crate::TemplateEngine::set_provenance)
+//! every named render of a template whose extension has a comment style
+//! starts with a marker such as
+//!
+//! ```text
+//! <!-- Generated from pages/home.html by mystical-runic 0.5.3 at 1700000000; do not edit -->
+//! ```
+//!
+//! The marker goes after an XML declaration or a `#!` shebang line, which
+//! must stay first. A template opts out with `{{no_provenance}}` in its
+//! source or layouts, or with `provenance=off` in the manifest.
+
+use std::collections::BTreeMap;
+
+/// Tag that keeps the marker out of a template's output
+pub(crate) const NO_PROVENANCE_TAG: &str = "{{no_provenance}}";
+
+/// How a file type writes comments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommentStyle {
+    /// Each line starts with the prefix: `#` for YAML, `//` for JavaScript
+    Line(String),
+    /// The text sits between two delimiters: `<!--` and `-->` for HTML
+    Block(String, String),
+}
+
+impl CommentStyle {
+    /// Comment lines starting with `prefix`
+    pub fn line(prefix: &str) -> Self {
+        Self::Line(prefix.to_string())
+    }
+
+    /// Comment between `open` and `close`
+    pub fn block(open: &str, close: &str) -> Self {
+        Self::Block(open.to_string(), close.to_string())
+    }
+
+    /// `text` as a comment, ending with a newline
+    fn comment(&self, text: &str) -> String {
+        match self {
+            Self::Line(prefix) => text.lines().map(|line| format!("{} {}\n", prefix, line)).collect(),
+            // The close delimiter would end the comment early
+            Self::Block(open, close) => format!("{} {} {}\n", open, text.replace(close.as_str(), ""), close),
+        }
+    }
+}
+
+/// Which renders carry a provenance marker and what it says, see the
+/// [module documentation](self)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProvenanceOptions {
+    /// Marker text; `{{template}}`, `{{version}}` and `{{timestamp}}` (Unix
+    /// seconds on the engine clock, fixed in deterministic mode) are replaced
+    pub marker: String,
+    /// File extension (lowercase, without the dot) -> comment style; other
+    /// templates get no marker
+    pub comment_styles: BTreeMap<String, CommentStyle>,
+}
+
+impl Default for ProvenanceOptions {
+    /// HTML and XML comments for `html`, `htm`, `xml` and `svg`, `#` for
+    /// `yaml`, `yml`, `toml`, `conf`, `ini` and `sh`, `//` for `js`, `mjs`
+    /// and `ts`, and `/* */` for `css`.
+    fn default() -> Self {
+        let mut comment_styles = BTreeMap::new();
+        for extension in ["html", "htm", "xml", "svg"] {
+            comment_styles.insert(extension.to_string(), CommentStyle::block("<!--", "-->"));
+        }
+        for extension in ["yaml", "yml", "toml", "conf", "ini", "sh"] {
+            comment_styles.insert(extension.to_string(), CommentStyle::line("#"));
+        }
+        for extension in ["js", "mjs", "ts"] {
+            comment_styles.insert(extension.to_string(), CommentStyle::line("//"));
+        }
+        comment_styles.insert("css".to_string(), CommentStyle::block("/*", "*/"));
+        Self {
+            marker: "Generated from {{template}} by mystical-runic {{version}} at {{timestamp}}; do not edit".to_string(),
+            comment_styles,
+        }
+    }
+}
+
+impl ProvenanceOptions {
+    /// Replace the marker text
+    pub fn marker(mut self, marker: &str) -> Self {
+        self.marker = marker.to_string();
+        self
+    }
+
+    /// Comment templates ending in `.extension` with `style`
+    pub fn comment_style(mut self, extension: &str, style: CommentStyle) -> Self {
+        self.comment_styles.insert(extension.trim_start_matches('.').to_ascii_lowercase(), style);
+        self
+    }
+
+    /// Stop marking templates ending in `.extension`
+    pub fn without_extension(mut self, extension: &str) -> Self {
+        self.comment_styles.remove(&extension.trim_start_matches('.').to_ascii_lowercase());
+        self
+    }
+
+    /// Comment style of a template, by its extension
+    pub fn style_for(&self, template_name: &str) -> Option<&CommentStyle> {
+        let (_, extension) = template_name.rsplit_once('.')?;
+        if extension.contains('/') {
+            return None;
+        }
+        self.comment_styles.get(&extension.to_ascii_lowercase())
+    }
+
+    /// Put the marker of `template_name` into `output`, after any prolog
+    pub(crate) fn insert_marker(&self, template_name: &str, timestamp: i64, output: &mut String) {
+        let Some(style) = self.style_for(template_name) else { return };
+        let text = self.marker
+            .replace("{{template}}", template_name)
+            .replace("{{version}}", env!("CARGO_PKG_VERSION"))
+            .replace("{{timestamp}}", &timestamp.to_string());
+        let mut comment = style.comment(&text);
+        let at = prolog_len(output);
+        // `<?xml ...?>` or `#!...` without a line break of its own
+        if at > 0 && !output[..at].ends_with(['\n', '\u{FEFF}']) {
+            comment.insert(0, '\n');
+        }
+        output.insert_str(at, &comment);
+    }
+}
+
+/// Length of what must stay at the very start of `output`: a byte order
+/// mark, then an XML declaration or a shebang line with its line break
+fn prolog_len(output: &str) -> usize {
+    let bom = if output.starts_with('\u{FEFF}') { '\u{FEFF}'.len_utf8() } else { 0 };
+    let rest = &output[bom..];
+    let end = if rest.starts_with("<?xml") {
+        rest.find("?>").map(|close| close + 2)
+    } else if rest.starts_with("#!") {
+        Some(rest.find('\n').unwrap_or(rest.len()))
+    } else {
+        None
+    };
+    let Some(end) = end else { return bom };
+    let line_break = ["\r\n", "\n"].into_iter().find(|line_break| rest[end..].starts_with(line_break)).map_or(0, str::len);
+    bom + end + line_break
+}
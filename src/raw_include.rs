@@ -0,0 +1,86 @@
+//! Raw includes for v0.6.0
+//!
+//! `{{include_raw "icons/sprite.svg"}}` puts a file into the output as is:
+//! it is neither rendered nor escaped. Raw includes are resolved once the
+//! whole output has rendered. [`render`](crate::TemplateEngine::render)
+//! reads them into the output like any template source, while
+//! [`render_to_writer`](crate::TemplateEngine::render_to_writer) streams
+//! files at least [`passthrough_threshold`](RawIncludeOptions::passthrough_threshold)
+//! bytes long from disk to the writer, in small chunks, so a multi-megabyte
+//! sprite is never held in memory.
+
+use crate::{TemplateError, TemplateResult};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// How `{{include_raw}}` files are read, see the [module documentation](self)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawIncludeOptions {
+    /// Files at least this many bytes long are streamed by `render_to_writer`
+    /// and count as large for [`cache_large_files`](Self::cache_large_files);
+    /// 256 KiB by default
+    pub passthrough_threshold: u64,
+    /// Larger files fail the render; 64 MiB by default, `None` for no limit
+    pub max_size: Option<u64>,
+    /// Whether large files read by a string render are kept in the template
+    /// cache; `false` keeps giant assets from evicting templates of a
+    /// bounded cache. `true` by default
+    pub cache_large_files: bool,
+}
+
+impl Default for RawIncludeOptions {
+    fn default() -> Self {
+        Self {
+            passthrough_threshold: 256 * 1024,
+            max_size: Some(64 * 1024 * 1024),
+            cache_large_files: true,
+        }
+    }
+}
+
+impl RawIncludeOptions {
+    /// Stream files of at least `bytes` bytes
+    pub fn passthrough_threshold(mut self, bytes: u64) -> Self {
+        self.passthrough_threshold = bytes;
+        self
+    }
+
+    /// Fail renders including files of more than `bytes` bytes; `None` for no limit
+    pub fn max_size(mut self, bytes: Option<u64>) -> Self {
+        self.max_size = bytes;
+        self
+    }
+
+    /// Keep large files read by string renders in the template cache or not
+    pub fn cache_large_files(mut self, cache: bool) -> Self {
+        self.cache_large_files = cache;
+        self
+    }
+
+    /// Whether a file of `size` bytes is streamed by `render_to_writer`
+    pub(crate) fn is_large(&self, size: u64) -> bool {
+        size >= self.passthrough_threshold
+    }
+
+    /// Fail when a file of `size` bytes is over the size limit
+    pub(crate) fn check_size(&self, name: &str, size: u64) -> TemplateResult<()> {
+        match self.max_size {
+            Some(max_size) if size > max_size => Err(TemplateError::Security(format!(
+                "Raw include '{}' is {} bytes, over the limit of {} bytes", name, size, max_size
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Copy the file at `path` to `writer` without reading it into memory
+    pub(crate) fn stream(&self, name: &str, path: &Path, writer: &mut (impl Write + ?Sized)) -> TemplateResult<()> {
+        let file = File::open(path)
+            .map_err(|e| TemplateError::Template(format!("Failed to read raw include '{}': {}", name, e)))?;
+        let size = file.metadata()?.len();
+        self.check_size(name, size)?;
+        // Stop at the size checked, should the file grow while it is copied
+        io::copy(&mut file.take(size), writer)?;
+        Ok(())
+    }
+}
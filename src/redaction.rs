@@ -0,0 +1,115 @@
+//! Context value redaction for v0.6.0
+//!
+//! Decides how context values appear outside the rendered output: debug
+//! traces, IDE hover and completions, render diffs and error messages.
+//! Rendering itself never consults the policy.
+
+use crate::value::TemplateValue;
+use std::sync::Arc;
+
+/// Key words that mark a value as secret for [`Redact::secret_names`]
+const SECRET_WORDS: [&str; 8] = ["password", "passwd", "passphrase", "secret", "token", "key", "apikey", "credentials"];
+
+/// Text surfaced in place of a hidden value
+pub(crate) const HIDDEN_PLACEHOLDER: &str = "(hidden)";
+
+/// What a redaction policy does with a context value, see
+/// [`TemplateEngine::set_redaction`](crate::TemplateEngine::set_redaction)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Redact {
+    /// Surface the value as is (nested values are still checked)
+    Show,
+    /// Surface a placeholder keeping the type and size: `•••• (string, 32 chars)`
+    Mask,
+    /// Surface nothing about the value
+    Hide,
+}
+
+impl Redact {
+    /// The default policy: mask values whose key path has a segment with
+    /// one of the words `password`, `passwd`, `passphrase`, `secret`,
+    /// `token`, `key`, `apikey` or `credentials`
+    ///
+    /// Words are split at `_`, `-` and case changes, so `api_key`,
+    /// `apiKey`, `user.password` and `X-Auth-Token` are masked while
+    /// `monkey` and `keyboard` are not.
+    ///
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{Redact, TemplateValue};
+    ///
+    /// let value = TemplateValue::String("s3cr3t".to_string());
+    /// assert_eq!(Redact::secret_names("user.apiKey", &value), Redact::Mask);
+    /// assert_eq!(Redact::secret_names("user.name", &value), Redact::Show);
+    /// ```
+    pub fn secret_names(path: &str, _value: &TemplateValue) -> Redact {
+        let secret = path.split('.').any(|segment| {
+            key_words(segment).iter().any(|word| SECRET_WORDS.contains(&word.as_str()))
+        });
+        if secret { Redact::Mask } else { Redact::Show }
+    }
+}
+
+/// A redaction policy: key path and value -> how to surface it
+pub type RedactionFunction = Arc<dyn Fn(&str, &TemplateValue) -> Redact + Send + Sync>;
+
+/// `value` as `policy` lets it be surfaced: masked values become their
+/// placeholder, hidden object entries and array items are left out, and
+/// `None` means the value itself is hidden. Array items are checked as
+/// `path.0`, `path.1`, ...
+pub(crate) fn redact_value(policy: &RedactionFunction, path: &str, value: &TemplateValue) -> Option<TemplateValue> {
+    match policy(path, value) {
+        Redact::Hide => None,
+        Redact::Mask => Some(TemplateValue::String(mask_placeholder(value))),
+        Redact::Show => Some(match value {
+            TemplateValue::Object(entries) => TemplateValue::Object(entries.iter()
+                .filter_map(|(key, entry)| {
+                    redact_value(policy, &format!("{}.{}", path, key), entry).map(|entry| (key.clone(), entry))
+                })
+                .collect()),
+            TemplateValue::Array(items) => TemplateValue::Array(items.iter()
+                .enumerate()
+                .filter_map(|(index, item)| redact_value(policy, &format!("{}.{}", path, index), item))
+                .collect()),
+            scalar => scalar.clone(),
+        }),
+    }
+}
+
+/// Placeholder of a masked value, keeping its type and size
+pub(crate) fn mask_placeholder(value: &TemplateValue) -> String {
+    match value {
+        TemplateValue::String(text) => format!("•••• (string, {} chars)", text.chars().count()),
+        TemplateValue::Number(_) => "•••• (number)".to_string(),
+        TemplateValue::Float(_) => "•••• (float)".to_string(),
+        TemplateValue::Bool(_) => "•••• (boolean)".to_string(),
+        TemplateValue::Array(items) => format!("•••• (array, {} items)", items.len()),
+        TemplateValue::Object(entries) => format!("•••• (object, {} keys)", entries.len()),
+        TemplateValue::Null => "•••• (null)".to_string(),
+    }
+}
+
+/// Lowercase words of a key: `X-Auth-Token` -> `x`, `auth`, `token`
+fn key_words(segment: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut previous_lower = false;
+    for c in segment.chars() {
+        if !c.is_alphanumeric() {
+            previous_lower = false;
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        if c.is_uppercase() && previous_lower {
+            words.push(std::mem::take(&mut word));
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        word.extend(c.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
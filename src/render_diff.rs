@@ -0,0 +1,340 @@
+//! Render diffs for v0.6.0
+//!
+//! Compares two renders piece by piece: every part of the output is traced
+//! back to the text or directive that produced it, so a changed output can
+//! be reported with the template lines responsible on both sides.
+
+use std::fmt;
+use std::ops::Range;
+
+/// Above this many segment (or line) pairs, a differing middle part is
+/// reported as a single region instead of being aligned
+const MAX_ALIGNMENT_CELLS: usize = 4_000_000;
+
+/// What produced a [`RenderSegment`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentKind {
+    /// Literal template text
+    Text,
+    /// An output tag: variable, filter chain, helper or macro call, translation, ...
+    Output,
+    /// `{{include "..."}}`
+    Include,
+    /// A whole template using `{{extends}}`, traced as one piece
+    Layout,
+}
+
+/// An enclosing loop iteration or conditional of a segment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentScope {
+    /// `for item in items` or `if condition`
+    pub directive: String,
+    /// Line of the directive
+    pub line: usize,
+    /// Loop iteration (1-based), `None` for conditionals
+    pub iteration: Option<usize>,
+}
+
+impl fmt::Display for SegmentScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.directive)?;
+        if let Some(iteration) = self.iteration {
+            write!(f, " #{}", iteration)?;
+        }
+        write!(f, " (line {})", self.line)
+    }
+}
+
+/// A piece of output and the template location that produced it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderSegment {
+    /// What produced the output
+    pub kind: SegmentKind,
+    /// The directive, braces included, or the literal text
+    pub source: String,
+    /// Line in the template (1-based)
+    pub line: usize,
+    /// Column in the template (1-based)
+    pub column: usize,
+    /// Enclosing loops and conditionals, outermost first
+    pub scope: Vec<SegmentScope>,
+    /// Rendered output
+    pub output: String,
+}
+
+impl RenderSegment {
+    /// Loop iterations of the enclosing loops
+    fn iterations(&self) -> impl Iterator<Item = usize> + '_ {
+        self.scope.iter().filter_map(|scope| scope.iteration)
+    }
+
+    /// Same output at the same loop position
+    fn matches(&self, other: &RenderSegment) -> bool {
+        self.output == other.output && self.iterations().eq(other.iterations())
+    }
+}
+
+impl fmt::Display for RenderSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{} ", self.line, self.column)?;
+        match self.kind {
+            SegmentKind::Text => write!(f, "text {:?}", self.source)?,
+            _ => f.write_str(&self.source)?,
+        }
+        for (index, scope) in self.scope.iter().enumerate() {
+            f.write_str(if index == 0 { " in " } else { " > " })?;
+            write!(f, "{}", scope)?;
+        }
+        Ok(())
+    }
+}
+
+/// One line of a region's text diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffLine {
+    /// Present on both sides
+    Same(String),
+    /// Only in the left render
+    Removed(String),
+    /// Only in the right render
+    Added(String),
+}
+
+/// A part of the output that differs, with the segments that produced it on each side
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffRegion {
+    /// Left segments of the region
+    pub left: Vec<RenderSegment>,
+    /// Right segments of the region
+    pub right: Vec<RenderSegment>,
+    /// Line diff of the region's output
+    pub lines: Vec<DiffLine>,
+}
+
+impl DiffRegion {
+    fn new(left: &[RenderSegment], right: &[RenderSegment]) -> Self {
+        let left_output: String = left.iter().map(|segment| segment.output.as_str()).collect();
+        let right_output: String = right.iter().map(|segment| segment.output.as_str()).collect();
+        Self {
+            left: left.to_vec(),
+            right: right.to_vec(),
+            lines: diff_lines(&left_output, &right_output),
+        }
+    }
+
+    /// Whether the region's output is the same on both sides
+    fn is_unchanged(&self) -> bool {
+        self.lines.iter().all(|line| matches!(line, DiffLine::Same(_)))
+    }
+}
+
+/// A variable that resolved to different values, in render order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariableDiff {
+    /// Variable path, `item.price`
+    pub name: String,
+    /// Values in the left render
+    pub left: Vec<String>,
+    /// Values in the right render
+    pub right: Vec<String>,
+}
+
+/// Differences between two renders, from
+/// [`TemplateEngine::diff_renders`](crate::TemplateEngine::diff_renders)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderDiff {
+    /// Name of the left render
+    pub left_name: String,
+    /// Name of the right render
+    pub right_name: String,
+    /// Complete left output
+    pub left_output: String,
+    /// Complete right output
+    pub right_output: String,
+    /// Differing parts of the output, in order
+    pub regions: Vec<DiffRegion>,
+    /// Variables whose values differ between the renders
+    pub variables: Vec<VariableDiff>,
+}
+
+impl RenderDiff {
+    pub(crate) fn new(left_name: &str, right_name: &str, left: RenderTrace, right: RenderTrace) -> Self {
+        let mut regions: Vec<DiffRegion> = unmatched_ranges(&left.segments, &right.segments, RenderSegment::matches)
+            .into_iter()
+            .map(|(left_range, right_range)| DiffRegion::new(&left.segments[left_range], &right.segments[right_range]))
+            .filter(|region| !region.is_unchanged())
+            .collect();
+        // Output the trace can't attribute (minification, layouts, ...) still shows up
+        if regions.is_empty() && left.output != right.output {
+            regions.push(DiffRegion {
+                left: left.segments.clone(),
+                right: right.segments.clone(),
+                lines: diff_lines(&left.output, &right.output),
+            });
+        }
+
+        let mut variables = Vec::new();
+        for (name, left_values) in &left.variables {
+            let right_values = right.values_of(name);
+            if left_values != right_values {
+                variables.push(VariableDiff { name: name.clone(), left: left_values.clone(), right: right_values.to_vec() });
+            }
+        }
+        for (name, right_values) in &right.variables {
+            if left.values_of(name).is_empty() && !right_values.is_empty() {
+                variables.push(VariableDiff { name: name.clone(), left: Vec::new(), right: right_values.clone() });
+            }
+        }
+
+        Self {
+            left_name: left_name.to_string(),
+            right_name: right_name.to_string(),
+            left_output: left.output,
+            right_output: right.output,
+            regions,
+            variables,
+        }
+    }
+
+    /// Whether both renders produced the same output
+    pub fn is_identical(&self) -> bool {
+        self.left_output == self.right_output
+    }
+}
+
+impl fmt::Display for RenderDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_identical() {
+            return writeln!(f, "No differences between {} and {}", self.left_name, self.right_name);
+        }
+        writeln!(f, "--- {}", self.left_name)?;
+        writeln!(f, "+++ {}", self.right_name)?;
+
+        for region in &self.regions {
+            let first_line = |segments: &[RenderSegment]| segments.first()
+                .map_or_else(|| "-".to_string(), |segment| segment.line.to_string());
+            writeln!(f, "@@ {}:{} | {}:{} @@",
+                self.left_name, first_line(&region.left), self.right_name, first_line(&region.right))?;
+            for segment in region.left.iter().filter(|segment| segment.kind != SegmentKind::Text) {
+                writeln!(f, "  < {}:{}", self.left_name, segment)?;
+            }
+            for segment in region.right.iter().filter(|segment| segment.kind != SegmentKind::Text) {
+                writeln!(f, "  > {}:{}", self.right_name, segment)?;
+            }
+            for line in &region.lines {
+                match line {
+                    DiffLine::Same(text) => writeln!(f, "  {}", text)?,
+                    DiffLine::Removed(text) => writeln!(f, "- {}", text)?,
+                    DiffLine::Added(text) => writeln!(f, "+ {}", text)?,
+                }
+            }
+        }
+
+        if !self.variables.is_empty() {
+            writeln!(f, "Variables:")?;
+            for variable in &self.variables {
+                writeln!(f, "  {}: {:?} | {:?}", variable.name, variable.left, variable.right)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Output of one render with the segments and variable values behind it
+#[derive(Debug, Default)]
+pub(crate) struct RenderTrace {
+    pub output: String,
+    pub segments: Vec<RenderSegment>,
+    /// Variable paths in order of first use, with every value they resolved to
+    pub variables: Vec<(String, Vec<String>)>,
+}
+
+impl RenderTrace {
+    pub(crate) fn new(output: String) -> Self {
+        Self { output, ..Self::default() }
+    }
+
+    pub(crate) fn record_variable(&mut self, name: &str, value: String) {
+        match self.variables.iter_mut().find(|(known, _)| known == name) {
+            Some((_, values)) => values.push(value),
+            None => self.variables.push((name.to_string(), vec![value])),
+        }
+    }
+
+    fn values_of(&self, name: &str) -> &[String] {
+        self.variables.iter()
+            .find(|(known, _)| known == name)
+            .map_or(&[], |(_, values)| values.as_slice())
+    }
+}
+
+/// Ranges of `left` and `right` left over by a longest common subsequence
+/// alignment; each pair is a run of unmatched items between two matches
+fn unmatched_ranges<T>(left: &[T], right: &[T], same: impl Fn(&T, &T) -> bool) -> Vec<(Range<usize>, Range<usize>)> {
+    let prefix = left.iter().zip(right).take_while(|(a, b)| same(a, b)).count();
+    let suffix = left[prefix..].iter().rev()
+        .zip(right[prefix..].iter().rev())
+        .take_while(|(a, b)| same(a, b))
+        .count();
+    let (left_end, right_end) = (left.len() - suffix, right.len() - suffix);
+    let (rows, columns) = (left_end - prefix, right_end - prefix);
+
+    if rows == 0 && columns == 0 {
+        return Vec::new();
+    }
+    if rows == 0 || columns == 0 || rows.saturating_mul(columns) > MAX_ALIGNMENT_CELLS {
+        return vec![(prefix..left_end, prefix..right_end)];
+    }
+
+    // lengths[i][j]: common subsequence length of left[prefix + i..] and right[prefix + j..]
+    let width = columns + 1;
+    let mut lengths = vec![0u32; (rows + 1) * width];
+    for i in (0..rows).rev() {
+        for j in (0..columns).rev() {
+            lengths[i * width + j] = if same(&left[prefix + i], &right[prefix + j]) {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut ranges = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let (mut start_i, mut start_j) = (0, 0);
+    while i < rows && j < columns {
+        if same(&left[prefix + i], &right[prefix + j]) {
+            if (start_i, start_j) != (i, j) {
+                ranges.push((prefix + start_i..prefix + i, prefix + start_j..prefix + j));
+            }
+            i += 1;
+            j += 1;
+            (start_i, start_j) = (i, j);
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    if (start_i, start_j) != (rows, columns) {
+        ranges.push((prefix + start_i..left_end, prefix + start_j..right_end));
+    }
+    ranges
+}
+
+/// Line diff of two texts
+pub(crate) fn diff_lines(left: &str, right: &str) -> Vec<DiffLine> {
+    let left: Vec<&str> = left.lines().collect();
+    let right: Vec<&str> = right.lines().collect();
+    let mut lines = Vec::new();
+    let mut position = (0, 0);
+
+    for (left_range, right_range) in unmatched_ranges(&left, &right, |a, b| a == b) {
+        lines.extend(left[position.0..left_range.start].iter().map(|line| DiffLine::Same(line.to_string())));
+        lines.extend(left[left_range.clone()].iter().map(|line| DiffLine::Removed(line.to_string())));
+        lines.extend(right[right_range.clone()].iter().map(|line| DiffLine::Added(line.to_string())));
+        position = (left_range.end, right_range.end);
+    }
+    lines.extend(left[position.0..].iter().map(|line| DiffLine::Same(line.to_string())));
+    lines
+}
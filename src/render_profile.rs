@@ -0,0 +1,329 @@
+//! Render profiles for v0.6.0
+//!
+//! [`TemplateEngine::profile_render`](crate::TemplateEngine::profile_render)
+//! times every directive of a render as a tree of spans. The tree can be
+//! summarised as the hottest spans or exported to the
+//! [speedscope](https://www.speedscope.app) file format and viewed as a
+//! flame graph.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+/// What a [`ProfileSpan`] measured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpanKind {
+    /// The rendered template
+    Template,
+    /// Loading the `{{extends}}` chain and merging its blocks
+    Layout,
+    /// `{{include "..."}}`
+    Include,
+    /// `{{block name}}`
+    Block,
+    /// `{{for item in items}}`, all iterations
+    Loop,
+    /// `{{if condition}}`, evaluating it and rendering the body; `{{unless}}`
+    /// and `{{ifchanged}}` spans are named after their whole directive
+    Condition,
+    /// `{{try}}` and its rescue body
+    ErrorBoundary,
+    /// An output tag such as `{{user.name}}` or `{{table rows}}`
+    Output,
+    /// One step of a filter chain
+    Filter,
+    /// A registered helper call, `{{price(total)}}`
+    Helper,
+    /// A macro call, `{{button("Save")}}`
+    Macro,
+    /// `{{t "key"}}` or `{{plural count "item" "items"}}`
+    Translation,
+}
+
+impl SpanKind {
+    /// Word put before the span name in frame names
+    pub fn label(self) -> &'static str {
+        match self {
+            SpanKind::Template => "template",
+            SpanKind::Layout => "extends",
+            SpanKind::Include => "include",
+            SpanKind::Block => "block",
+            SpanKind::Loop => "for",
+            SpanKind::Condition => "if",
+            SpanKind::ErrorBoundary => "try",
+            SpanKind::Output => "output",
+            SpanKind::Filter => "filter",
+            SpanKind::Helper => "helper",
+            SpanKind::Macro => "macro",
+            SpanKind::Translation => "translate",
+        }
+    }
+}
+
+impl fmt::Display for SpanKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// One timed piece of a render and the spans inside it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileSpan {
+    pub kind: SpanKind,
+    /// Template name, directive expression, filter, helper or macro name
+    pub name: String,
+    /// Template whose source holds the directive, layouts merged
+    pub template: String,
+    /// 1-based line of the directive in that source
+    pub line: usize,
+    /// Time spent in the span, children included
+    pub total_nanos: u64,
+    /// Time spent in the span outside its children
+    pub self_nanos: u64,
+    /// Times the span ran: iterations for loops, and for spans inside a
+    /// loop the iterations that reached them
+    pub count: usize,
+    pub children: Vec<ProfileSpan>,
+}
+
+impl ProfileSpan {
+    pub(crate) fn new(kind: SpanKind, name: &str, template: &str, line: usize) -> Self {
+        Self {
+            kind,
+            name: name.to_string(),
+            template: template.to_string(),
+            line,
+            total_nanos: 0,
+            self_nanos: 0,
+            count: 1,
+            children: Vec::new(),
+        }
+    }
+
+    /// Close the span after `total_nanos`; self time is what the children
+    /// don't account for
+    pub(crate) fn finish(mut self, total_nanos: u64) -> Self {
+        let children_nanos: u64 = self.children.iter().map(|child| child.total_nanos).sum();
+        self.total_nanos = total_nanos.max(children_nanos);
+        self.self_nanos = self.total_nanos - children_nanos;
+        self
+    }
+
+    /// Name shown in reports and flame graphs, e.g. `for item in items`
+    pub fn frame_name(&self) -> String {
+        frame_name(self.kind, &self.name)
+    }
+
+    /// This span and every span below it, depth first
+    pub fn iter(&self) -> impl Iterator<Item = &ProfileSpan> {
+        let mut pending = vec![self];
+        std::iter::from_fn(move || {
+            let span = pending.pop()?;
+            pending.extend(span.children.iter().rev());
+            Some(span)
+        })
+    }
+
+    fn same_site(&self, other: &ProfileSpan) -> bool {
+        self.kind == other.kind && self.line == other.line && self.name == other.name && self.template == other.template
+    }
+}
+
+/// Add the spans of one loop iteration to those of the previous ones;
+/// spans of the same directive are summed
+pub(crate) fn merge_spans(into: &mut Vec<ProfileSpan>, spans: Vec<ProfileSpan>) {
+    for span in spans {
+        match into.iter_mut().find(|existing| existing.same_site(&span)) {
+            Some(existing) => {
+                existing.total_nanos += span.total_nanos;
+                existing.self_nanos += span.self_nanos;
+                existing.count += span.count;
+                merge_spans(&mut existing.children, span.children);
+            }
+            None => into.push(span),
+        }
+    }
+}
+
+/// A directive's time summed over the whole render
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HotSpot {
+    pub kind: SpanKind,
+    pub name: String,
+    pub template: String,
+    pub line: usize,
+    pub self_nanos: u64,
+    pub total_nanos: u64,
+    pub count: usize,
+}
+
+/// Output and span tree of a profiled render
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileResult {
+    /// What the template rendered to
+    pub output: String,
+    /// Span of the rendered template
+    pub root: ProfileSpan,
+}
+
+impl ProfileResult {
+    /// Time of the whole render
+    pub fn total(&self) -> Duration {
+        Duration::from_nanos(self.root.total_nanos)
+    }
+
+    /// The `limit` directives with the most self time, hottest first. Spans
+    /// of the same directive reached through different paths are summed.
+    pub fn hot_spots(&self, limit: usize) -> Vec<HotSpot> {
+        let mut spots: Vec<HotSpot> = Vec::new();
+        let mut index: HashMap<(SpanKind, &str, &str, usize), usize> = HashMap::new();
+        for span in self.root.iter() {
+            let key = (span.kind, span.name.as_str(), span.template.as_str(), span.line);
+            match index.get(&key) {
+                Some(&position) => {
+                    let spot = &mut spots[position];
+                    spot.self_nanos += span.self_nanos;
+                    spot.total_nanos += span.total_nanos;
+                    spot.count += span.count;
+                }
+                None => {
+                    index.insert(key, spots.len());
+                    spots.push(HotSpot {
+                        kind: span.kind,
+                        name: span.name.clone(),
+                        template: span.template.clone(),
+                        line: span.line,
+                        self_nanos: span.self_nanos,
+                        total_nanos: span.total_nanos,
+                        count: span.count,
+                    });
+                }
+            }
+        }
+        // Stable, so ties keep render order
+        spots.sort_by_key(|spot| std::cmp::Reverse(spot.self_nanos));
+        spots.truncate(limit);
+        spots
+    }
+
+    /// Hot spots report, one directive per line:
+    ///
+    /// ```text
+    /// page.html rendered in 1.2ms, top 2 hot spots by self time:
+    ///    1.  61.0%  732µs self  990µs total  x500  output row.price|currency (page.html:4)
+    ///    2.  20.5%  246µs self  1.1ms total  x1  for row in rows (page.html:3)
+    /// ```
+    pub fn summary(&self, limit: usize) -> String {
+        let spots = self.hot_spots(limit);
+        let mut out = format!(
+            "{} rendered in {:?}, top {} hot spots by self time:\n",
+            self.root.name, self.total(), spots.len()
+        );
+        let total = self.root.total_nanos.max(1) as f64;
+        for (rank, spot) in spots.iter().enumerate() {
+            out.push_str(&format!(
+                "{:>4}. {:>5.1}%  {:?} self  {:?} total  x{}  {} ({}:{})\n",
+                rank + 1,
+                spot.self_nanos as f64 * 100.0 / total,
+                Duration::from_nanos(spot.self_nanos),
+                Duration::from_nanos(spot.total_nanos),
+                spot.count,
+                frame_name(spot.kind, &spot.name),
+                spot.template,
+                spot.line,
+            ));
+        }
+        out
+    }
+
+    /// The profile as a speedscope file (evented profile, nanoseconds).
+    ///
+    /// Children are laid out one after the other from the start of their
+    /// parent, so loop iterations show as a single bar per directive. Open
+    /// the file at <https://www.speedscope.app>.
+    pub fn to_speedscope_json(&self) -> String {
+        let mut frames: Vec<(String, &str, usize)> = Vec::new();
+        let mut frame_index: HashMap<(String, &str, usize), usize> = HashMap::new();
+        let mut events = String::new();
+        let end = write_span_events(&self.root, 0, &mut frames, &mut frame_index, &mut events);
+
+        let mut json = String::from("{\"$schema\":\"https://www.speedscope.app/file-format-schema.json\",");
+        json.push_str("\"exporter\":\"mystical-runic\",\"name\":");
+        push_json_string(&mut json, &self.root.name);
+        json.push_str(",\"activeProfileIndex\":0,\"shared\":{\"frames\":[");
+        for (index, (name, file, line)) in frames.iter().enumerate() {
+            if index > 0 {
+                json.push(',');
+            }
+            json.push_str("{\"name\":");
+            push_json_string(&mut json, name);
+            json.push_str(",\"file\":");
+            push_json_string(&mut json, file);
+            json.push_str(&format!(",\"line\":{}}}", line));
+        }
+        json.push_str("]},\"profiles\":[{\"type\":\"evented\",\"name\":");
+        push_json_string(&mut json, &self.root.name);
+        json.push_str(&format!(
+            ",\"unit\":\"nanoseconds\",\"startValue\":0,\"endValue\":{},\"events\":[{}]}}]}}",
+            end, events
+        ));
+        json
+    }
+}
+
+impl fmt::Display for ProfileResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.summary(10))
+    }
+}
+
+fn frame_name(kind: SpanKind, name: &str) -> String {
+    match kind {
+        SpanKind::Template => name.to_string(),
+        kind => format!("{} {}", kind.label(), name),
+    }
+}
+
+/// Write the open and close events of `span`, opened `at`; returns when it closes
+fn write_span_events<'a>(
+    span: &'a ProfileSpan,
+    at: u64,
+    frames: &mut Vec<(String, &'a str, usize)>,
+    frame_index: &mut HashMap<(String, &'a str, usize), usize>,
+    events: &mut String,
+) -> u64 {
+    let key = (span.frame_name(), span.template.as_str(), span.line);
+    let frame = *frame_index.entry(key.clone()).or_insert_with(|| {
+        frames.push(key);
+        frames.len() - 1
+    });
+
+    if !events.is_empty() {
+        events.push(',');
+    }
+    events.push_str(&format!("{{\"type\":\"O\",\"frame\":{},\"at\":{}}}", frame, at));
+    let mut child_at = at;
+    for child in &span.children {
+        child_at = write_span_events(child, child_at, frames, frame_index, events);
+    }
+    let close = (at + span.total_nanos).max(child_at);
+    events.push_str(&format!(",{{\"type\":\"C\",\"frame\":{},\"at\":{}}}", frame, close));
+    close
+}
+
+/// Append `text` as a quoted JSON string
+fn push_json_string(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
@@ -0,0 +1,272 @@
+//! Render queue for v0.6.0
+//!
+//! Batch generation for static sites: renders are queued with a priority,
+//! then run on a bounded pool of threads sharing one
+//! [`SharedTemplateEngine`]. Higher priorities are dispatched first, in the
+//! order they were pushed within a priority. Past the deadline no new
+//! render starts; the ones left are reported as skipped.
+
+use crate::context::TemplateContext;
+use crate::engine::panic_message;
+use crate::error::TemplateError;
+use crate::shared_engine::SharedTemplateEngine;
+use std::cmp::Reverse;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How early a queued render is dispatched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    /// Rendered last, the first to be skipped at the deadline
+    Low,
+    #[default]
+    Normal,
+    /// Rendered first: homepage, sitemap, ...
+    High,
+}
+
+/// What became of a queued render
+#[derive(Debug)]
+pub enum QueueOutcome {
+    /// The rendered output
+    Rendered(String),
+    /// The render failed
+    Failed(TemplateError),
+    /// The deadline passed before the render was dispatched
+    Skipped,
+}
+
+/// One queued render in a [`QueueReport`]
+#[derive(Debug)]
+pub struct QueueItem {
+    /// Template name
+    pub template: String,
+    pub priority: Priority,
+    pub outcome: QueueOutcome,
+    /// When the render started, from the start of the run; `None` when skipped
+    pub started: Option<Duration>,
+    /// Time spent rendering
+    pub duration: Duration,
+}
+
+impl QueueItem {
+    /// The output, when the render succeeded
+    pub fn output(&self) -> Option<&str> {
+        match &self.outcome {
+            QueueOutcome::Rendered(output) => Some(output),
+            _ => None,
+        }
+    }
+
+    /// The error, when the render failed
+    pub fn error(&self) -> Option<&TemplateError> {
+        match &self.outcome {
+            QueueOutcome::Failed(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Whether the deadline passed before the render was dispatched
+    pub fn is_skipped(&self) -> bool {
+        matches!(self.outcome, QueueOutcome::Skipped)
+    }
+}
+
+/// Result of [`RenderQueue::run`]
+#[derive(Debug)]
+pub struct QueueReport {
+    /// Every queued render, in the order they were pushed
+    pub items: Vec<QueueItem>,
+    /// Renders that succeeded
+    pub completed: usize,
+    /// Renders that failed
+    pub failed: usize,
+    /// Renders skipped at the deadline
+    pub skipped: usize,
+    /// Time the whole run took
+    pub elapsed: Duration,
+}
+
+impl QueueReport {
+    /// Number of queued renders
+    pub fn total(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether every render was dispatched and succeeded
+    pub fn is_success(&self) -> bool {
+        self.failed == 0 && self.skipped == 0
+    }
+
+    /// Get human-readable summary
+    pub fn summary(&self) -> String {
+        format!(
+            "{} rendered, {} failed, {} skipped of {} in {:.2?}",
+            self.completed, self.failed, self.skipped, self.total(), self.elapsed
+        )
+    }
+}
+
+/// Passed to the [`RenderQueue::on_progress`] callback as each render ends
+#[derive(Debug)]
+pub struct QueueProgress<'a> {
+    /// The render that ended
+    pub item: &'a QueueItem,
+    /// Its position in [`QueueReport::items`]
+    pub index: usize,
+    /// Renders ended so far, this one included
+    pub finished: usize,
+    /// Number of queued renders
+    pub total: usize,
+}
+
+type ProgressCallback = Box<dyn Fn(&QueueProgress<'_>) + Send + Sync>;
+
+/// A render waiting in the queue
+struct Pending {
+    template: String,
+    context: TemplateContext,
+    priority: Priority,
+}
+
+/// Renders templates by priority on a bounded worker pool, see the
+/// [module documentation](self)
+///
+/// # Example
+/// ```rust,no_run
+/// use mystical_runic::{Priority, RenderQueue, SharedTemplateEngine, TemplateContext, TemplateEngine};
+/// use std::time::{Duration, Instant};
+///
+/// let mut engine = TemplateEngine::new("templates");
+/// engine.warm_up()?;
+/// let mut queue = RenderQueue::new(SharedTemplateEngine::new(engine));
+/// queue.on_progress(|progress| eprint!("\r{}/{}", progress.finished, progress.total));
+///
+/// queue.push("index.html", TemplateContext::new(), Priority::High);
+/// for page in 0..20_000 {
+///     let mut context = TemplateContext::new();
+///     context.set_number("page", page);
+///     queue.push("page.html", context, Priority::Low);
+/// }
+///
+/// let report = queue.run(8, Some(Instant::now() + Duration::from_secs(60)));
+/// println!("{}", report.summary());
+/// # Ok::<(), mystical_runic::TemplateError>(())
+/// ```
+pub struct RenderQueue {
+    engine: SharedTemplateEngine,
+    pending: Vec<Pending>,
+    progress: Option<ProgressCallback>,
+}
+
+impl RenderQueue {
+    /// An empty queue rendering with `engine`
+    pub fn new(engine: SharedTemplateEngine) -> Self {
+        Self { engine, pending: Vec::new(), progress: None }
+    }
+
+    /// Queue a render; returns its position in [`QueueReport::items`]
+    pub fn push(&mut self, template: &str, context: TemplateContext, priority: Priority) -> usize {
+        self.pending.push(Pending { template: template.to_string(), context, priority });
+        self.pending.len() - 1
+    }
+
+    /// Number of queued renders
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Whether no render is queued
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Call `callback` as each render ends, skipped ones included, from the
+    /// worker that ran it. A panic of the callback is ignored.
+    pub fn on_progress(&mut self, callback: impl Fn(&QueueProgress<'_>) + Send + Sync + 'static) {
+        self.progress = Some(Box::new(callback));
+    }
+
+    /// Render everything queued on at most `parallelism` threads and empty
+    /// the queue.
+    ///
+    /// Renders are dispatched by priority, then in push order. Once
+    /// `deadline` has passed, renders in progress finish but no new one
+    /// starts.
+    pub fn run(&mut self, parallelism: usize, deadline: Option<Instant>) -> QueueReport {
+        let pending = std::mem::take(&mut self.pending);
+        let total = pending.len();
+        let mut order: Vec<usize> = (0..total).collect();
+        order.sort_by_key(|&index| (Reverse(pending[index].priority), index));
+
+        let start = Instant::now();
+        let next = AtomicUsize::new(0);
+        let finished = AtomicUsize::new(0);
+        let (engine, progress) = (&self.engine, self.progress.as_deref());
+        let report_progress = |item: &QueueItem, index: usize| {
+            if let Some(progress) = progress {
+                let finished = finished.fetch_add(1, Ordering::Relaxed) + 1;
+                // A panicking observer must not take the worker down with it
+                let _ = panic::catch_unwind(AssertUnwindSafe(|| progress(&QueueProgress { item, index, finished, total })));
+            }
+        };
+
+        let mut items: Vec<Option<QueueItem>> = (0..total).map(|_| None).collect();
+        let rendered: Vec<(usize, QueueItem)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..parallelism.clamp(1, total.max(1))).map(|_| scope.spawn(|| {
+                let mut rendered = Vec::new();
+                while !deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    let Some(&index) = order.get(next.fetch_add(1, Ordering::Relaxed)) else { break };
+                    let Pending { template, context, priority } = &pending[index];
+                    let started = start.elapsed();
+                    let outcome = match panic::catch_unwind(AssertUnwindSafe(|| engine.render(template, context))) {
+                        Ok(Ok(output)) => QueueOutcome::Rendered(output),
+                        Ok(Err(error)) => QueueOutcome::Failed(error),
+                        Err(payload) => QueueOutcome::Failed(TemplateError::Render(format!("Thread panic: {}", panic_message(payload.as_ref())))),
+                    };
+                    let item = QueueItem {
+                        template: template.clone(),
+                        priority: *priority,
+                        outcome,
+                        started: Some(started),
+                        duration: start.elapsed() - started,
+                    };
+                    report_progress(&item, index);
+                    rendered.push((index, item));
+                }
+                rendered
+            })).collect();
+            workers.into_iter().flat_map(|worker| worker.join().unwrap_or_default()).collect()
+        });
+        for (index, item) in rendered {
+            items[index] = Some(item);
+        }
+
+        // Whatever wasn't dispatched before the deadline
+        for &index in &order {
+            if items[index].is_none() {
+                let item = QueueItem {
+                    template: pending[index].template.clone(),
+                    priority: pending[index].priority,
+                    outcome: QueueOutcome::Skipped,
+                    started: None,
+                    duration: Duration::ZERO,
+                };
+                report_progress(&item, index);
+                items[index] = Some(item);
+            }
+        }
+
+        let items: Vec<QueueItem> = items.into_iter().flatten().collect();
+        let count = |outcome: fn(&QueueItem) -> bool| items.iter().filter(|item| outcome(item)).count();
+        QueueReport {
+            completed: count(|item| item.output().is_some()),
+            failed: count(|item| item.error().is_some()),
+            skipped: count(QueueItem::is_skipped),
+            items,
+            elapsed: start.elapsed(),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Template roots for v0.6.0
+//!
+//! Templates can come from several directories. Same-named templates are
+//! shadowed by the root with the highest priority, so an application can
+//! patch a vendor component by dropping its own copy in its template
+//! directory.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Name of the engine's own template directory in resolution traces and `from` clauses
+pub const APP_ROOT_NAME: &str = "app";
+
+/// An additional directory templates are resolved from
+///
+/// A root with a namespace serves `namespace::name` from `dir/name`; roots
+/// without one serve `name` from `dir/name` and `namespace::name` from
+/// `dir/namespace/name`.
+///
+/// # Example
+/// ```rust
+/// use mystical_runic::{TemplateEngine, TemplateRoot};
+///
+/// let mut engine = TemplateEngine::new("templates");
+/// engine.add_template_root(TemplateRoot::new("vendor", "vendor/design-system").with_namespace("ds"));
+///
+/// // `templates/ds/button.html` shadows `vendor/design-system/button.html`
+/// let trace = engine.explain_resolution("ds::button.html");
+/// assert_eq!(trace.candidates.len(), 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateRoot {
+    name: String,
+    dir: PathBuf,
+    namespace: Option<String>,
+}
+
+impl TemplateRoot {
+    /// Create a root; `name` is used by `{{include "..." from "name"}}` and in traces
+    pub fn new(name: &str, dir: impl AsRef<Path>) -> Self {
+        Self {
+            name: name.to_string(),
+            dir: dir.as_ref().to_path_buf(),
+            namespace: None,
+        }
+    }
+
+    /// Serve only `namespace::name` templates from this root
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Some(namespace.to_string());
+        self
+    }
+
+    /// Root name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Root directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Namespace served by this root, if any
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// Path of a template in this root, if the root serves it
+    pub(crate) fn candidate_path(&self, namespace: Option<&str>, name: &str) -> Option<PathBuf> {
+        match (self.namespace.as_deref(), namespace) {
+            (Some(own), Some(requested)) if own == requested => Some(self.dir.join(name)),
+            (None, Some(requested)) => Some(self.dir.join(requested).join(name)),
+            (None, None) => Some(self.dir.join(name)),
+            _ => None,
+        }
+    }
+}
+
+/// One root considered while resolving a template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolutionCandidate {
+    /// Root name (`"app"` for the engine's template directory)
+    pub root: String,
+    /// Root priority; higher priorities are searched first
+    pub priority: i32,
+    /// File the root would serve
+    pub path: PathBuf,
+    /// Whether the file exists
+    pub exists: bool,
+}
+
+/// How a template name was resolved, from
+/// [`TemplateEngine::explain_resolution`](crate::TemplateEngine::explain_resolution)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolutionTrace {
+    /// Requested template name
+    pub name: String,
+    /// Namespace part of the name (`ds` in `ds::button.html`)
+    pub namespace: Option<String>,
+    /// Root the lookup was restricted to by a `from` clause
+    pub from: Option<String>,
+    /// Roots serving the name, in search order
+    pub candidates: Vec<ResolutionCandidate>,
+}
+
+impl ResolutionTrace {
+    /// The candidate that wins: the first one whose file exists
+    pub fn winner(&self) -> Option<&ResolutionCandidate> {
+        self.candidates.iter().find(|candidate| candidate.exists)
+    }
+
+    /// Candidates with an existing file that the winner hides
+    pub fn shadowed(&self) -> Vec<&ResolutionCandidate> {
+        self.candidates.iter().filter(|candidate| candidate.exists).skip(1).collect()
+    }
+}
+
+impl fmt::Display for ResolutionTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Resolving '{}'", self.name)?;
+        if let Some(root) = &self.from {
+            write!(f, " from '{}'", root)?;
+        }
+        writeln!(f, ":")?;
+        let winner = self.winner().map(|winner| &winner.path);
+        for candidate in &self.candidates {
+            let status = if Some(&candidate.path) == winner {
+                "selected"
+            } else if candidate.exists {
+                "shadowed"
+            } else {
+                "missing"
+            };
+            writeln!(f, "  [{}] {} (priority {}): {}", candidate.root, candidate.path.display(), candidate.priority, status)?;
+        }
+        if winner.is_none() {
+            writeln!(f, "  no root provides this template")?;
+        }
+        Ok(())
+    }
+}
+
+/// Split `namespace::name`
+pub(crate) fn split_namespace(name: &str) -> (Option<&str>, &str) {
+    match name.split_once("::") {
+        Some((namespace, rest)) => (Some(namespace), rest),
+        None => (None, name),
+    }
+}
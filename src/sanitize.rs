@@ -0,0 +1,464 @@
+//! HTML sanitizing for v0.6.0
+//!
+//! A small allow-list sanitizer behind the `sanitize` filter, for rich text
+//! written by end users. The scanner is tolerant: malformed or unclosed
+//! markup is dropped or escaped, never passed through, and the output always
+//! has balanced tags. The same scanner backs `truncate_html` and
+//! [`diff::html_semantic_diff`](crate::diff::html_semantic_diff).
+
+use crate::graphemes::graphemes;
+use crate::utils::html_escape_into;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Elements dropped together with their content
+const DROPPED_WITH_CONTENT: [&str; 15] = [
+    "script", "style", "iframe", "object", "embed", "noscript", "noembed", "noframes",
+    "template", "textarea", "title", "xmp", "plaintext", "svg", "math",
+];
+
+/// Elements that never have content or a closing tag
+const VOID_ELEMENTS: [&str; 6] = ["br", "hr", "img", "wbr", "col", "area"];
+
+/// Elements the `truncate_html` ellipsis is placed after rather than inside
+const INLINE_ELEMENTS: [&str; 20] = [
+    "a", "abbr", "b", "cite", "code", "del", "dfn", "em", "i", "ins",
+    "kbd", "mark", "q", "s", "small", "span", "strong", "sub", "sup", "u",
+];
+
+/// Attributes holding a URL, checked against the allowed schemes
+const URL_ATTRIBUTES: [&str; 7] = ["href", "src", "action", "formaction", "cite", "poster", "xlink:href"];
+
+/// What the `sanitize` filter keeps, see [`TemplateEngine::configure_sanitizer`](crate::TemplateEngine::configure_sanitizer).
+///
+/// Event handler attributes (`on*`) are always removed, even when listed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SanitizerPolicy {
+    /// Tags kept in the output (lowercase); other tags are removed but their text is kept
+    pub allowed_tags: Vec<String>,
+    /// Attributes kept per tag (lowercase)
+    pub allowed_attributes: HashMap<String, Vec<String>>,
+    /// URL schemes accepted in `href`, `src` and other URL attributes;
+    /// relative URLs are accepted unless they are protocol-relative
+    pub allowed_url_schemes: Vec<String>,
+    /// Hosts accepted in protocol-relative URLs (`//host/path`, lowercase);
+    /// others lead off-site under the page's scheme and are removed
+    pub allowed_url_hosts: Vec<String>,
+}
+
+impl Default for SanitizerPolicy {
+    /// `p`, `a`, `strong`, `em`, `ul`, `ol`, `li`, `br`, `code` and `pre`,
+    /// with only `href` on links, limited to `http`, `https` and `mailto`.
+    fn default() -> Self {
+        let allowed_tags = ["p", "a", "strong", "em", "ul", "ol", "li", "br", "code", "pre"];
+        Self {
+            allowed_tags: allowed_tags.iter().map(|tag| tag.to_string()).collect(),
+            allowed_attributes: HashMap::from([("a".to_string(), vec!["href".to_string()])]),
+            allowed_url_schemes: vec!["http".to_string(), "https".to_string(), "mailto".to_string()],
+            allowed_url_hosts: Vec::new(),
+        }
+    }
+}
+
+impl SanitizerPolicy {
+    /// Keep a tag
+    pub fn allow_tag(mut self, tag: &str) -> Self {
+        let tag = tag.to_ascii_lowercase();
+        if !self.allowed_tags.contains(&tag) {
+            self.allowed_tags.push(tag);
+        }
+        self
+    }
+
+    /// Keep an attribute on a tag
+    pub fn allow_attribute(mut self, tag: &str, attribute: &str) -> Self {
+        let attributes = self.allowed_attributes.entry(tag.to_ascii_lowercase()).or_default();
+        let attribute = attribute.to_ascii_lowercase();
+        if !attributes.contains(&attribute) {
+            attributes.push(attribute);
+        }
+        self
+    }
+
+    /// Accept a URL scheme (without the colon)
+    pub fn allow_url_scheme(mut self, scheme: &str) -> Self {
+        let scheme = scheme.to_ascii_lowercase();
+        if !self.allowed_url_schemes.contains(&scheme) {
+            self.allowed_url_schemes.push(scheme);
+        }
+        self
+    }
+
+    /// Accept protocol-relative URLs to a host
+    pub fn allow_url_host(mut self, host: &str) -> Self {
+        let host = host.to_ascii_lowercase();
+        if !self.allowed_url_hosts.contains(&host) {
+            self.allowed_url_hosts.push(host);
+        }
+        self
+    }
+
+    fn allows_tag(&self, tag: &str) -> bool {
+        self.allowed_tags.iter().any(|allowed| allowed == tag)
+    }
+
+    fn allows_attribute(&self, tag: &str, attribute: &str) -> bool {
+        !attribute.starts_with("on")
+            && self.allowed_attributes.get(tag).is_some_and(|allowed| allowed.iter().any(|name| name == attribute))
+    }
+
+    /// Relative URLs pass; absolute ones need an allowed scheme and
+    /// protocol-relative ones an allowed host
+    fn allows_url(&self, url: &str) -> bool {
+        // Browsers ignore whitespace and control characters inside schemes
+        let url: String = url.chars().filter(|c| !c.is_whitespace() && !c.is_control()).collect();
+        // and read backslashes as slashes
+        if let Some(authority) = url.strip_prefix(['/', '\\']).and_then(|rest| rest.strip_prefix(['/', '\\'])) {
+            let authority = &authority[..authority.find(['/', '\\', '?', '#']).unwrap_or(authority.len())];
+            let host = authority.rsplit('@').next().unwrap_or_default();
+            let host = host.split(':').next().unwrap_or_default().to_ascii_lowercase();
+            return self.allowed_url_hosts.contains(&host);
+        }
+        let scheme_end = url.find([':', '/', '?', '#']);
+        match scheme_end {
+            Some(end) if url[end..].starts_with(':') => {
+                let scheme = url[..end].to_ascii_lowercase();
+                self.allowed_url_schemes.contains(&scheme)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// A tag found by the scanner
+pub(crate) struct Tag<'a> {
+    pub name: String,
+    pub closing: bool,
+    /// Whether the tag ends with `>`; browsers drop unterminated tags
+    pub terminated: bool,
+    pub attributes: Vec<(String, Option<&'a str>)>,
+}
+
+/// Sanitize HTML with `policy`
+pub(crate) fn sanitize_html(input: &str, policy: &SanitizerPolicy) -> String {
+    // NUL characters are removed before scanning, so `<scr\0ipt>` is read as the tag it spells
+    let input: Cow<str> = if input.contains('\0') { Cow::Owned(input.replace('\0', "")) } else { Cow::Borrowed(input) };
+    let input = input.as_ref();
+    let mut out = String::with_capacity(input.len());
+    let mut open: Vec<String> = Vec::new();
+    let mut pos = 0;
+
+    while pos < input.len() {
+        let rest = &input[pos..];
+        let Some(lt) = rest.find('<') else {
+            escape_text(rest, &mut out);
+            break;
+        };
+        escape_text(&rest[..lt], &mut out);
+        pos += lt;
+        let rest = &input[pos..];
+
+        // Comments, doctypes and processing instructions are dropped
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            pos = comment.find("-->").map_or(input.len(), |end| pos + 4 + end + 3);
+            continue;
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            pos = rest.find('>').map_or(input.len(), |end| pos + end + 1);
+            continue;
+        }
+
+        let Some((tag, length)) = scan_tag(rest) else {
+            // Not a tag: a literal `<`
+            out.push_str("&lt;");
+            pos += 1;
+            continue;
+        };
+        pos += length;
+
+        if !tag.terminated {
+            break;
+        }
+        if !tag.closing && DROPPED_WITH_CONTENT.contains(&tag.name.as_str()) {
+            pos = find_closing_tag(input, pos, &tag.name);
+            continue;
+        }
+        if !policy.allows_tag(&tag.name) {
+            continue;
+        }
+
+        if tag.closing {
+            if let Some(index) = open.iter().rposition(|name| *name == tag.name) {
+                for name in open.drain(index..).rev() {
+                    push_closing_tag(&name, &mut out);
+                }
+            }
+            continue;
+        }
+
+        out.push('<');
+        out.push_str(&tag.name);
+        for (attribute, value) in &tag.attributes {
+            if !policy.allows_attribute(&tag.name, attribute) {
+                continue;
+            }
+            let value = value.map(decode_entities).unwrap_or_default();
+            if URL_ATTRIBUTES.contains(&attribute.as_str()) && !policy.allows_url(&value) {
+                continue;
+            }
+            out.push(' ');
+            out.push_str(attribute);
+            out.push_str("=\"");
+            html_escape_into(&value, &mut out);
+            out.push('"');
+        }
+        out.push('>');
+        if !VOID_ELEMENTS.contains(&tag.name.as_str()) {
+            open.push(tag.name);
+        }
+    }
+
+    for name in open.into_iter().rev() {
+        push_closing_tag(&name, &mut out);
+    }
+    out
+}
+
+/// Shorten HTML to `limit` visible characters, for the `truncate_html` filter.
+///
+/// Only text counts, a character reference being one character; tags are
+/// kept whole and never counted. When text is cut, the tags open at the cut
+/// are closed in order, with `...` after the innermost inline ones. Tags are
+/// copied, not sanitized: untrusted markup goes through `sanitize` first.
+pub(crate) fn truncate_html(input: &str, limit: usize) -> String {
+    let mut out = String::with_capacity(input.len().min(limit.saturating_mul(2)));
+    let mut open: Vec<String> = Vec::new();
+    let mut visible = 0;
+    // Output and open tags when the limit was reached; anything after is
+    // discarded if more text follows
+    let mut limit_reached: Option<(usize, Vec<String>)> = (limit == 0).then(|| (0, Vec::new()));
+    let mut pos = 0;
+
+    while pos < input.len() {
+        let rest = &input[pos..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment.find("-->").map_or(input.len(), |end| pos + 4 + end + 3);
+            out.push_str(&input[pos..end]);
+            pos = end;
+            continue;
+        }
+        if rest.starts_with('<') {
+            if let Some((tag, length)) = scan_tag(rest) {
+                pos += length;
+                if !tag.terminated {
+                    break;
+                }
+                if tag.closing {
+                    if let Some(index) = open.iter().rposition(|name| *name == tag.name) {
+                        for name in open.drain(index..).rev() {
+                            push_closing_tag(&name, &mut out);
+                        }
+                    }
+                } else {
+                    out.push_str(&rest[..length]);
+                    if !VOID_ELEMENTS.contains(&tag.name.as_str()) && !rest[..length].ends_with("/>") {
+                        open.push(tag.name);
+                    }
+                }
+                continue;
+            }
+        }
+
+        // One visible character: a reference, or a character escaped as text
+        let length = match rest.as_bytes()[0] {
+            b'&' => entity_length(rest).unwrap_or(1),
+            _ => graphemes(rest).next().map_or(1, str::len),
+        };
+        if let Some((cut, open_at_cut)) = limit_reached.take() {
+            out.truncate(cut);
+            open = open_at_cut;
+            let inline = open.iter().rev().take_while(|name| INLINE_ELEMENTS.contains(&name.as_str())).count();
+            for name in open.drain(open.len() - inline..).rev() {
+                push_closing_tag(&name, &mut out);
+            }
+            out.push_str("...");
+            break;
+        }
+        escape_text(&rest[..length], &mut out);
+        pos += length;
+        visible += 1;
+        if visible == limit {
+            limit_reached = Some((out.len(), open.clone()));
+        }
+    }
+
+    for name in open.into_iter().rev() {
+        push_closing_tag(&name, &mut out);
+    }
+    out
+}
+
+fn push_closing_tag(name: &str, out: &mut String) {
+    out.push_str("</");
+    out.push_str(name);
+    out.push('>');
+}
+
+/// Scan a tag at the start of `input`, returning it and its length.
+/// `None` when `<` does not start a tag; an unterminated tag spans the rest of the input.
+pub(crate) fn scan_tag(input: &str) -> Option<(Tag<'_>, usize)> {
+    let bytes = input.as_bytes();
+    let closing = bytes.get(1) == Some(&b'/');
+    let name_start = if closing { 2 } else { 1 };
+    if !bytes.get(name_start).is_some_and(u8::is_ascii_alphabetic) {
+        return None;
+    }
+    let name_end = input[name_start..]
+        .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+        .map_or(input.len(), |end| name_start + end);
+    let name = input[name_start..name_end].to_ascii_lowercase();
+
+    let mut attributes = Vec::new();
+    let mut terminated = false;
+    let mut pos = name_end;
+    loop {
+        pos += input[pos..].find(|c: char| !c.is_ascii_whitespace() && c != '/').unwrap_or(input.len() - pos);
+        if pos >= input.len() {
+            break;
+        }
+        if bytes[pos] == b'>' {
+            pos += 1;
+            terminated = true;
+            break;
+        }
+
+        let attribute_end = input[pos..]
+            .find(|c: char| c.is_ascii_whitespace() || matches!(c, '=' | '>' | '/'))
+            .map_or(input.len(), |end| pos + end);
+        // A lone quote or `=` is skipped rather than looping forever
+        let attribute = input[pos..attribute_end.max(pos + 1)].to_ascii_lowercase();
+        pos = attribute_end.max(pos + 1);
+
+        let after_name = pos + input[pos..].find(|c: char| !c.is_ascii_whitespace()).unwrap_or(input.len() - pos);
+        let mut value = None;
+        if bytes.get(after_name) == Some(&b'=') {
+            let value_start = after_name + 1 + input[after_name + 1..]
+                .find(|c: char| !c.is_ascii_whitespace())
+                .unwrap_or(input.len() - after_name - 1);
+            match bytes.get(value_start) {
+                Some(&quote @ (b'"' | b'\'')) => {
+                    let close = input[value_start + 1..].find(quote as char).map(|end| value_start + 1 + end);
+                    value = Some(&input[value_start + 1..close.unwrap_or(input.len())]);
+                    pos = close.map_or(input.len(), |close| close + 1);
+                }
+                _ => {
+                    let end = input[value_start..]
+                        .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                        .map_or(input.len(), |end| value_start + end);
+                    value = Some(&input[value_start..end]);
+                    pos = end;
+                }
+            }
+        }
+        attributes.push((attribute, value));
+    }
+
+    Some((Tag { name, closing, terminated, attributes }, pos))
+}
+
+/// Offset just past `</name ...>` at or after `from`, or the end of the input
+pub(crate) fn find_closing_tag(input: &str, from: usize, name: &str) -> usize {
+    let lowercase = input.to_ascii_lowercase();
+    let closing = format!("</{}", name);
+    let mut search = from;
+    while let Some(start) = lowercase[search..].find(&closing).map(|start| search + start) {
+        let after = start + closing.len();
+        if lowercase[after..].starts_with(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/') {
+            return lowercase[after..].find('>').map_or(input.len(), |end| after + end + 1);
+        }
+        search = after;
+    }
+    input.len()
+}
+
+/// Escape text content, keeping well-formed character references
+fn escape_text(text: &str, out: &mut String) {
+    let mut rest = text;
+    while let Some(index) = rest.find(['&', '<', '>', '"']) {
+        out.push_str(&rest[..index]);
+        let tail = &rest[index..];
+        let (replacement, length) = match tail.as_bytes()[0] {
+            b'&' => match entity_length(tail) {
+                Some(length) => (&tail[..length], length),
+                None => ("&amp;", 1),
+            },
+            b'<' => ("&lt;", 1),
+            b'>' => ("&gt;", 1),
+            _ => ("&quot;", 1),
+        };
+        out.push_str(replacement);
+        rest = &tail[length..];
+    }
+    out.push_str(rest);
+}
+
+/// Length of a well-formed `&name;`, `&#123;` or `&#x7B;` reference at the start of `text`
+fn entity_length(text: &str) -> Option<usize> {
+    let body = text.strip_prefix('&')?;
+    let (digits, offset, valid): (&str, usize, fn(char) -> bool) = if let Some(hex) = body.strip_prefix("#x").or_else(|| body.strip_prefix("#X")) {
+        (hex, 3, |c| c.is_ascii_hexdigit())
+    } else if let Some(decimal) = body.strip_prefix('#') {
+        (decimal, 2, |c| c.is_ascii_digit())
+    } else {
+        (body, 1, |c| c.is_ascii_alphanumeric())
+    };
+    let length = digits.find(|c: char| !valid(c)).unwrap_or(digits.len());
+    (length > 0 && digits[length..].starts_with(';')).then_some(offset + length + 1)
+}
+
+/// Decode character references in an attribute value, as a browser would
+/// before interpreting a URL (`&#106;avascript:` is `javascript:`)
+pub(crate) fn decode_entities(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(index) = rest.find('&') {
+        decoded.push_str(&rest[..index]);
+        let tail = &rest[index + 1..];
+        let (character, consumed) = decode_reference(tail).unwrap_or(('&', 0));
+        decoded.push(character);
+        rest = &tail[consumed..];
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Decode the reference after `&`; the semicolon is optional, as browsers accept that too
+fn decode_reference(text: &str) -> Option<(char, usize)> {
+    if let Some(number) = text.strip_prefix('#') {
+        let (digits, radix, offset) = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => (hex, 16, 2),
+            None => (number, 10, 1),
+        };
+        let length = digits.find(|c: char| !c.is_digit(radix)).unwrap_or(digits.len());
+        if length == 0 {
+            return None;
+        }
+        // Out of range and null references decode to U+FFFD
+        let character = u32::from_str_radix(&digits[..length], radix).ok()
+            .and_then(char::from_u32)
+            .filter(|&c| c != '\0')
+            .unwrap_or('\u{FFFD}');
+        let semicolon = usize::from(digits[length..].starts_with(';'));
+        return Some((character, offset + length + semicolon));
+    }
+
+    const NAMED: [(&str, char); 9] = [
+        ("amp", '&'), ("lt", '<'), ("gt", '>'), ("quot", '"'), ("apos", '\''),
+        ("colon", ':'), ("tab", '\t'), ("newline", '\n'), ("nbsp", '\u{A0}'),
+    ];
+    let length = text.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(text.len());
+    let name = &text[..length];
+    let (_, character) = NAMED.iter().find(|(entity, _)| entity.eq_ignore_ascii_case(name))?;
+    let semicolon = usize::from(text[length..].starts_with(';'));
+    Some((*character, length + semicolon))
+}
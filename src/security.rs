@@ -0,0 +1,3 @@
+//! Security checks for v0.6.0
+
+pub mod path;
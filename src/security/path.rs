@@ -0,0 +1,195 @@
+//! Template name checks for v0.6.0
+//!
+//! Every template name the engine reads from disk or registers goes through
+//! [`check_template_name`] first: files, includes, `{{extends}}`, dynamic
+//! includes, manifest entries and imported packs. The check is lexical and
+//! the same on every platform, so templates written on Windows are held to
+//! Windows rules when served from Linux. Backslashes count as separators.
+//!
+//! | Rule | Rejects |
+//! |------|---------|
+//! | `empty-name` | an empty name |
+//! | `nul-byte` | a NUL byte anywhere |
+//! | `control-character` | any other control character (`\n`, `\t`, `\x7f`, ...) |
+//! | `unc-path` | `\\server\share`, `//server/share`, `\\?\C:\...` |
+//! | `absolute-path` | a name starting with `/` or `\` |
+//! | `drive-letter` | `C:\...`, `c:file` |
+//! | `alternate-data-stream` | a `:` in the path, as in `file.html::$DATA` |
+//! | `parent-directory` | a `..` segment (or any segment of dots only, `...`) |
+//! | `device-name` | `CON`, `PRN`, `AUX`, `NUL`, `COM0`-`COM9`, `LPT0`-`LPT9`, `CONIN$`, `CONOUT$`, with or without an extension |
+//! | `trailing-dot-or-space` | a segment ending in `.` or a space, which Windows drops |
+//! | `invalid-namespace` | a `namespace::` prefix that isn't a single plain segment |
+//! | `outside-root` | a name resolving out of its template root (checked by the engine, through symlinks) |
+//!
+//! A `namespace::name` prefix is split off before the path is checked.
+
+use crate::TemplateError;
+use std::fmt::{self, Write};
+
+/// Windows device names, reserved in every directory
+const DEVICE_NAMES: [&str; 26] = [
+    "CON", "PRN", "AUX", "NUL", "CONIN$", "CONOUT$",
+    "COM0", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT0", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// The rule a rejected template name broke
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathRule {
+    EmptyName,
+    NulByte,
+    ControlCharacter,
+    UncPath,
+    AbsolutePath,
+    DriveLetter,
+    AlternateDataStream,
+    ParentDirectory,
+    DeviceName,
+    TrailingDotOrSpace,
+    InvalidNamespace,
+    OutsideRoot,
+}
+
+impl PathRule {
+    /// Stable identifier of the rule, as shown in error messages
+    pub fn code(self) -> &'static str {
+        match self {
+            PathRule::EmptyName => "empty-name",
+            PathRule::NulByte => "nul-byte",
+            PathRule::ControlCharacter => "control-character",
+            PathRule::UncPath => "unc-path",
+            PathRule::AbsolutePath => "absolute-path",
+            PathRule::DriveLetter => "drive-letter",
+            PathRule::AlternateDataStream => "alternate-data-stream",
+            PathRule::ParentDirectory => "parent-directory",
+            PathRule::DeviceName => "device-name",
+            PathRule::TrailingDotOrSpace => "trailing-dot-or-space",
+            PathRule::InvalidNamespace => "invalid-namespace",
+            PathRule::OutsideRoot => "outside-root",
+        }
+    }
+
+    /// What the rule protects against
+    pub fn description(self) -> &'static str {
+        match self {
+            PathRule::EmptyName => "template names can't be empty",
+            PathRule::NulByte => "NUL bytes end the name early in system calls",
+            PathRule::ControlCharacter => "control characters are not allowed in template names",
+            PathRule::UncPath => "network and device paths are not allowed",
+            PathRule::AbsolutePath => "absolute paths are not allowed",
+            PathRule::DriveLetter => "drive letter paths are not allowed",
+            PathRule::AlternateDataStream => "':' opens an alternate data stream on Windows",
+            PathRule::ParentDirectory => "path traversal attempt detected",
+            PathRule::DeviceName => "Windows device names are not files",
+            PathRule::TrailingDotOrSpace => "Windows drops a trailing '.' or space, so the name would alias another file",
+            PathRule::InvalidNamespace => "a template namespace is a single plain name",
+            PathRule::OutsideRoot => "path traversal attempt detected: the template resolves outside its root",
+        }
+    }
+}
+
+impl fmt::Display for PathRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// A template name rejected by a [`PathRule`]. Converts into
+/// [`TemplateError::Security`], whose message names the rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathViolation {
+    pub rule: PathRule,
+    /// The name as given
+    pub name: String,
+}
+
+impl PathViolation {
+    pub fn new(rule: PathRule, name: &str) -> Self {
+        Self { rule, name: name.to_string() }
+    }
+}
+
+impl fmt::Display for PathViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Template name '")?;
+        for c in self.name.chars() {
+            if c.is_control() {
+                write!(f, "{}", c.escape_default())?;
+            } else {
+                f.write_char(c)?;
+            }
+        }
+        write!(f, "' rejected by rule '{}': {}", self.rule, self.rule.description())
+    }
+}
+
+impl std::error::Error for PathViolation {}
+
+impl From<PathViolation> for TemplateError {
+    fn from(violation: PathViolation) -> Self {
+        TemplateError::Security(violation.to_string())
+    }
+}
+
+/// Check a template name against the rules of this module
+pub fn check_template_name(name: &str) -> Result<(), PathViolation> {
+    let violation = |rule| Err(PathViolation::new(rule, name));
+    if name.is_empty() {
+        return violation(PathRule::EmptyName);
+    }
+    if name.contains('\0') {
+        return violation(PathRule::NulByte);
+    }
+    if name.chars().any(char::is_control) {
+        return violation(PathRule::ControlCharacter);
+    }
+
+    let normalized = name.replace('\\', "/");
+    let path = match normalized.split_once("::") {
+        // `file.html::$DATA` is a stream, not a namespace
+        Some((_, rest)) if rest.starts_with('$') => return violation(PathRule::AlternateDataStream),
+        Some((namespace, rest)) => {
+            let plain = !namespace.is_empty()
+                && !namespace.contains(['/', ':'])
+                && namespace.chars().any(|c| c != '.');
+            if !plain {
+                return violation(PathRule::InvalidNamespace);
+            }
+            rest
+        }
+        None => normalized.as_str(),
+    };
+
+    if path.starts_with("//") {
+        return violation(PathRule::UncPath);
+    }
+    if path.starts_with('/') {
+        return violation(PathRule::AbsolutePath);
+    }
+    let mut chars = path.chars();
+    if chars.next().is_some_and(|c| c.is_ascii_alphabetic()) && chars.next() == Some(':') {
+        return violation(PathRule::DriveLetter);
+    }
+    if path.contains(':') {
+        return violation(PathRule::AlternateDataStream);
+    }
+
+    for segment in path.split('/') {
+        if segment.len() >= 2 && segment.chars().all(|c| c == '.') {
+            return violation(PathRule::ParentDirectory);
+        }
+        if is_device_name(segment) {
+            return violation(PathRule::DeviceName);
+        }
+        if segment != "." && segment.ends_with(['.', ' ']) {
+            return violation(PathRule::TrailingDotOrSpace);
+        }
+    }
+    Ok(())
+}
+
+/// Whether `segment` names a device, as `nul`, `CON.html` or `com1 .txt` do
+fn is_device_name(segment: &str) -> bool {
+    let stem = segment.split('.').next().unwrap_or_default().trim_end();
+    DEVICE_NAMES.iter().any(|device| device.eq_ignore_ascii_case(stem))
+}
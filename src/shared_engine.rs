@@ -0,0 +1,190 @@
+//! Shared engine for v0.6.0
+//!
+//! One engine rendering on many threads while its templates are hot
+//! reloaded. Each render runs on the snapshot that was current when it
+//! started, and a reload publishes its snapshot in a single swap, so a render
+//! sees all of a reload or none of it.
+
+use crate::context::TemplateContext;
+use crate::engine::TemplateEngine;
+use crate::error::TemplateResult;
+#[cfg(feature = "i18n")]
+use crate::i18n::TranslationVersion;
+#[cfg(feature = "i18n")]
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+/// Engine state a render starts from
+struct Snapshot {
+    generation: u64,
+    engine: TemplateEngine,
+    /// Idle render engines cloned from `engine`; they never outlive their snapshot
+    workers: Mutex<Vec<TemplateEngine>>,
+}
+
+impl Snapshot {
+    fn new(generation: u64, engine: TemplateEngine) -> Arc<Self> {
+        Arc::new(Self { generation, engine, workers: Mutex::new(Vec::new()) })
+    }
+
+    /// Run `render` on an idle worker of this snapshot
+    fn with_worker<T>(&self, render: impl FnOnce(&mut TemplateEngine) -> T) -> T {
+        let idle = self.workers.lock().unwrap_or_else(PoisonError::into_inner).pop();
+        let mut worker = idle.unwrap_or_else(|| self.engine.clone());
+        let result = render(&mut worker);
+        // A worker that read templates from disk would keep serving them after they change
+        if worker.cached_template_count() == self.engine.cached_template_count() {
+            self.workers.lock().unwrap_or_else(PoisonError::into_inner).push(worker);
+        }
+        result
+    }
+}
+
+/// A [`TemplateEngine`] shared by many threads, with atomic hot reload
+///
+/// Renders take `&self` and run on the engine state that was current when
+/// they started; [`reload_changed`](Self::reload_changed) and
+/// [`update`](Self::update) prepare a new state aside and publish it in one
+/// swap, bumping the [`generation`](Self::generation). A render that is
+/// running during a reload finishes on the old state, so its output comes
+/// entirely from the old templates or entirely from the new ones.
+///
+/// Templates missing from the snapshot are read from disk by every render
+/// that needs them; warm the engine up before sharing it so renders don't
+/// touch the disk. Cloning is cheap and every clone is the same engine.
+///
+/// # Example
+/// ```rust,no_run
+/// use mystical_runic::{SharedTemplateEngine, TemplateEngine, TemplateContext};
+/// use std::{thread, time::Duration};
+///
+/// let mut engine = TemplateEngine::new("templates");
+/// engine.warm_up()?;
+/// let shared = SharedTemplateEngine::new(engine);
+///
+/// let watcher = shared.clone();
+/// thread::spawn(move || loop {
+///     watcher.reload_changed();
+///     thread::sleep(Duration::from_millis(500));
+/// });
+///
+/// let html = shared.render("page.html", &TemplateContext::new())?;
+/// # Ok::<(), mystical_runic::TemplateError>(())
+/// ```
+#[derive(Clone)]
+pub struct SharedTemplateEngine {
+    current: Arc<RwLock<Arc<Snapshot>>>,
+    /// Serializes reloads and updates so each starts from the latest snapshot
+    updating: Arc<Mutex<()>>,
+}
+
+impl SharedTemplateEngine {
+    /// Share `engine`.
+    ///
+    /// Its per-render hot reload is turned off: changes are picked up by
+    /// [`reload_changed`](Self::reload_changed), which applies them atomically.
+    pub fn new(mut engine: TemplateEngine) -> Self {
+        engine.disable_hot_reload();
+        Self {
+            current: Arc::new(RwLock::new(Snapshot::new(0, engine))),
+            updating: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Number of states published since the engine was shared
+    pub fn generation(&self) -> u64 {
+        self.snapshot().generation
+    }
+
+    /// Render a template
+    pub fn render(&self, template_name: &str, context: &TemplateContext) -> TemplateResult<String> {
+        self.snapshot().with_worker(|engine| engine.render(template_name, context))
+    }
+
+    /// Render a template string
+    pub fn render_string(&self, template: &str, context: &TemplateContext) -> TemplateResult<String> {
+        self.snapshot().with_worker(|engine| engine.render_string(template, context))
+    }
+
+    /// Replace the translations of `locale`; renders already running keep
+    /// theirs. Translations are versioned apart from the engine, so this
+    /// doesn't bump the [`generation`](Self::generation).
+    ///
+    /// See [`TemplateEngine::swap_translations`].
+    #[cfg(feature = "i18n")]
+    pub fn swap_translations(&self, locale: &str, translations: HashMap<String, String>) -> TranslationVersion {
+        self.snapshot().engine.swap_translations(locale, translations)
+    }
+
+    /// See [`TemplateEngine::rollback_translations`]
+    #[cfg(feature = "i18n")]
+    pub fn rollback_translations(&self, locale: &str, version: TranslationVersion) -> TemplateResult<()> {
+        self.snapshot().engine.rollback_translations(locale, version)
+    }
+
+    /// See [`TemplateEngine::translation_version`]
+    #[cfg(feature = "i18n")]
+    pub fn translation_version(&self, locale: &str) -> Option<TranslationVersion> {
+        self.snapshot().engine.translation_version(locale)
+    }
+
+    /// Reload the templates whose files changed, with their dependents, as
+    /// one new generation. Returns the reloaded templates; nothing is
+    /// published when none changed.
+    ///
+    /// See [`TemplateEngine::reload_changed_templates`].
+    pub fn reload_changed(&self) -> Vec<String> {
+        self.publish_if_reloaded(TemplateEngine::reload_changed_templates)
+    }
+
+    /// Reload templates and their dependents as one new generation
+    ///
+    /// See [`TemplateEngine::reload_templates`].
+    pub fn reload_templates<S: AsRef<str>>(&self, names: &[S]) -> Vec<String> {
+        self.publish_if_reloaded(|engine| engine.reload_templates(names))
+    }
+
+    /// Change the engine (register a filter, switch the locale, ...) and
+    /// publish the result as a new generation
+    ///
+    /// # Example
+    /// ```rust
+    /// use mystical_runic::{SharedTemplateEngine, TemplateEngine, TemplateContext};
+    ///
+    /// let shared = SharedTemplateEngine::new(TemplateEngine::new("templates"));
+    /// shared.update(|engine| engine.register_filter("shout", |value, _| Ok(value.to_uppercase())));
+    ///
+    /// let mut context = TemplateContext::new();
+    /// context.set_string("word", "hi");
+    /// assert_eq!(shared.render_string("{{word|shout}}", &context)?, "HI");
+    /// assert_eq!(shared.generation(), 1);
+    /// # Ok::<(), mystical_runic::TemplateError>(())
+    /// ```
+    pub fn update<T>(&self, change: impl FnOnce(&mut TemplateEngine) -> T) -> T {
+        let _updating = self.updating.lock().unwrap_or_else(PoisonError::into_inner);
+        let current = self.snapshot();
+        let mut engine = current.engine.clone();
+        let result = change(&mut engine);
+        self.publish(current.generation + 1, engine);
+        result
+    }
+
+    fn publish_if_reloaded(&self, reload: impl FnOnce(&mut TemplateEngine) -> Vec<String>) -> Vec<String> {
+        let _updating = self.updating.lock().unwrap_or_else(PoisonError::into_inner);
+        let current = self.snapshot();
+        let mut engine = current.engine.clone();
+        let reloaded = reload(&mut engine);
+        if !reloaded.is_empty() {
+            self.publish(current.generation + 1, engine);
+        }
+        reloaded
+    }
+
+    fn snapshot(&self) -> Arc<Snapshot> {
+        Arc::clone(&self.current.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    fn publish(&self, generation: u64, engine: TemplateEngine) {
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) = Snapshot::new(generation, engine);
+    }
+}
@@ -0,0 +1,77 @@
+//! Flush control for the streaming renderer of v0.6.0
+//!
+//! [`render_to_writer`](crate::TemplateEngine::render_to_writer) writes the
+//! output in pieces and flushes the writer at meaningful boundaries, so a
+//! proxy or browser gets the `<head>` before the rest of the page:
+//!
+//! - `{{flush}}` marks a flush point, such as right after `</head>`;
+//! - [`FlushOn::LoopIteration`] adds one after every loop iteration, for
+//!   the cards of an infinite-scroll list;
+//! - [`set_stream_chunk_size`](crate::TemplateEngine::set_stream_chunk_size)
+//!   flushes every time that many bytes are written without a flush.
+//!
+//! Other renders produce no flush points, `{{flush}}` renders nothing.
+
+use std::io::{self, Write};
+
+/// Where [`render_to_writer`](crate::TemplateEngine::render_to_writer)
+/// flushes the writer besides the chunk size, see the
+/// [module documentation](self)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlushOn {
+    /// At `{{flush}}` and after every loop iteration
+    LoopIteration,
+    /// At `{{flush}}`
+    #[default]
+    Directive,
+    /// Never, `{{flush}}` is ignored: the caller flushes
+    Manual,
+}
+
+/// Writer a streaming render hands the finished parts of its output to
+pub(crate) type StreamSink<'a> = FlushingWriter<'a, dyn Write + 'a>;
+
+/// Writer flushing `inner` every `chunk_size` bytes and at [`flush_point`](Self::flush_point)
+pub(crate) struct FlushingWriter<'a, W: Write + ?Sized> {
+    inner: &'a mut W,
+    chunk_size: Option<usize>,
+    /// Bytes written since the last flush
+    pending: usize,
+}
+
+impl<'a, W: Write + ?Sized> FlushingWriter<'a, W> {
+    pub(crate) fn new(inner: &'a mut W, chunk_size: Option<usize>) -> Self {
+        Self { inner, chunk_size: chunk_size.filter(|size| *size > 0), pending: 0 }
+    }
+
+    /// Flush unless nothing was written since the last flush
+    pub(crate) fn flush_point(&mut self) -> io::Result<()> {
+        if self.pending > 0 {
+            self.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write + ?Sized> Write for FlushingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(chunk_size) = self.chunk_size else {
+            let written = self.inner.write(buf)?;
+            self.pending += written;
+            return Ok(written);
+        };
+        // Never write past the end of the current chunk
+        let room = chunk_size - self.pending;
+        let written = self.inner.write(&buf[..buf.len().min(room)])?;
+        self.pending += written;
+        if self.pending >= chunk_size {
+            self.flush()?;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.pending = 0;
+        self.inner.flush()
+    }
+}
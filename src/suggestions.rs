@@ -1,9 +1,11 @@
 //! Suggestion engine for helpful error messages
 //! 
-//! Provides smart suggestions for common mistakes and typos
+//! Provides smart suggestions for common mistakes and typos (`suggestions`
+//! feature), and the line/column helpers every error report uses
 
 
 /// Calculate Levenshtein distance between two strings
+#[cfg(feature = "suggestions")]
 pub fn levenshtein_distance(a: &str, b: &str) -> usize {
     let a_chars: Vec<char> = a.chars().collect();
     let b_chars: Vec<char> = b.chars().collect();
@@ -16,8 +18,8 @@ pub fn levenshtein_distance(a: &str, b: &str) -> usize {
     for (i, row) in matrix.iter_mut().enumerate().take(a_len + 1) {
         row[0] = i;
     }
-    for j in 0..=b_len {
-        matrix[0][j] = j;
+    for (j, cell) in matrix[0].iter_mut().enumerate() {
+        *cell = j;
     }
     
     // Fill the matrix
@@ -35,6 +37,7 @@ pub fn levenshtein_distance(a: &str, b: &str) -> usize {
 }
 
 /// Generate suggestions for a misspelled template name
+#[cfg(feature = "suggestions")]
 pub fn suggest_templates(target: &str, available: &[String], max_suggestions: usize) -> Vec<String> {
     let mut suggestions_with_distance: Vec<(String, usize)> = available
         .iter()
@@ -61,6 +64,7 @@ pub fn suggest_templates(target: &str, available: &[String], max_suggestions: us
 
 /// Generate suggestions for a misspelled variable name
 #[allow(dead_code)]
+#[cfg(feature = "suggestions")]
 pub fn suggest_variables(target: &str, available: &[String], max_suggestions: usize) -> Vec<String> {
     let mut suggestions_with_score: Vec<(String, f64)> = available
         .iter()
@@ -138,6 +142,7 @@ pub fn find_line_column(content: &str, char_offset: usize) -> (usize, usize) {
 mod tests {
     use super::*;
 
+    #[cfg(feature = "suggestions")]
     #[test]
     fn test_levenshtein_distance() {
         assert_eq!(levenshtein_distance("cat", "bat"), 1);
@@ -146,6 +151,7 @@ mod tests {
         assert_eq!(levenshtein_distance("same", "same"), 0);
     }
 
+    #[cfg(feature = "suggestions")]
     #[test]
     fn test_suggest_templates() {
         let available = vec![
@@ -159,6 +165,7 @@ mod tests {
         assert_eq!(suggestions[0], "user_profile.html");
     }
 
+    #[cfg(feature = "suggestions")]
     #[test]
     fn test_suggest_variables() {
         let available = vec![
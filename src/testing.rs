@@ -0,0 +1,382 @@
+//! Template snapshots for v0.6.0
+//!
+//! Golden testing for rendered output: the first run stores the output in a
+//! `.snap` file next to the tests, later runs compare against it and fail
+//! with a line diff. Output can be normalized first so whitespace, attribute
+//! order or volatile values like timestamps don't make snapshots flaky, or
+//! compared as HTML documents with [`html_semantic_diff`].
+
+use crate::diff::{html_semantic_diff, SemanticDiffOptions};
+use crate::error::TemplateResult;
+use crate::render_diff::{diff_lines, DiffLine};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Environment variable that rewrites mismatching snapshots instead of failing
+const UPDATE_SNAPSHOTS_VAR: &str = "RUNIC_UPDATE_SNAPSHOTS";
+
+/// Unchanged lines shown around each change of a mismatch report
+const CONTEXT_LINES: usize = 3;
+
+type MaskPredicate = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// How output is normalized and where snapshots are stored
+///
+/// # Example
+/// ```rust
+/// use mystical_runic::SnapshotOptions;
+///
+/// let options = SnapshotOptions::new()
+///     .collapse_whitespace(true)
+///     .sort_attributes(true)
+///     .mask("[date]", |word| word.len() == 10 && word.chars().filter(|c| *c == '-').count() == 2);
+///
+/// let output = "<time datetime=\"2024-05-01\"   class=\"x\">\n\n  2024-05-01</time>";
+/// assert_eq!(options.normalize(output), "<time class=\"x\" datetime=\"[date]\">\n[date]</time>");
+/// ```
+#[derive(Clone, Default)]
+pub struct SnapshotOptions {
+    collapse_whitespace: bool,
+    sort_attributes: bool,
+    masks: Vec<(String, MaskPredicate)>,
+    html: Option<SemanticDiffOptions>,
+    directory: Option<PathBuf>,
+    update: Option<bool>,
+}
+
+impl fmt::Debug for SnapshotOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SnapshotOptions")
+            .field("collapse_whitespace", &self.collapse_whitespace)
+            .field("sort_attributes", &self.sort_attributes)
+            .field("masks", &self.masks.iter().map(|(label, _)| label).collect::<Vec<_>>())
+            .field("html", &self.html)
+            .field("directory", &self.directory)
+            .field("update", &self.update)
+            .finish()
+    }
+}
+
+impl SnapshotOptions {
+    /// Compare output exactly, storing snapshots in the default directory
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trim every line, collapse runs of spaces and tabs and drop blank lines
+    pub fn collapse_whitespace(mut self, enabled: bool) -> Self {
+        self.collapse_whitespace = enabled;
+        self
+    }
+
+    /// Sort the attributes of every opening tag by name
+    pub fn sort_attributes(mut self, enabled: bool) -> Self {
+        self.sort_attributes = enabled;
+        self
+    }
+
+    /// Replace every word `matches` accepts with `label`
+    ///
+    /// Words are runs of characters other than whitespace, `<`, `>`, `=`
+    /// and quotes, so `2024-05-01T10:00:00Z` in `<time>2024-05-01T10:00:00Z</time>`
+    /// or `datetime="2024-05-01"` is a single word. Masks apply in the
+    /// order they were added.
+    pub fn mask(mut self, label: &str, matches: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.masks.push((label.to_string(), Arc::new(matches)));
+        self
+    }
+
+    /// Compare output with its snapshot as HTML, ignoring what `options`
+    /// ignores, and report mismatches as element changes instead of lines.
+    ///
+    /// The snapshot is only rewritten, when updating, if the documents
+    /// differ semantically.
+    pub fn compare_html(mut self, options: SemanticDiffOptions) -> Self {
+        self.html = Some(options);
+        self
+    }
+
+    /// Store snapshots in `directory` instead of the default `tests/snapshots`
+    pub fn directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.directory = Some(directory.into());
+        self
+    }
+
+    /// Rewrite mismatching snapshots (`true`) or fail on them (`false`)
+    /// regardless of `RUNIC_UPDATE_SNAPSHOTS`
+    pub fn update(mut self, enabled: bool) -> Self {
+        self.update = Some(enabled);
+        self
+    }
+
+    /// Apply the masks, attribute sorting and whitespace collapsing to `output`
+    pub fn normalize(&self, output: &str) -> String {
+        let mut normalized = if self.masks.is_empty() { output.to_string() } else { self.apply_masks(output) };
+        if self.sort_attributes {
+            normalized = sort_tag_attributes(&normalized);
+        }
+        if self.collapse_whitespace {
+            normalized = collapse_whitespace(&normalized);
+        }
+        normalized
+    }
+
+    /// Snapshot file of `template_name`: `<directory>/<template_name>.snap`,
+    /// with `default_directory` unless [`directory`](Self::directory) was set
+    pub fn snapshot_path(&self, default_directory: impl AsRef<Path>, template_name: &str) -> PathBuf {
+        let directory = self.directory.as_deref().unwrap_or_else(|| default_directory.as_ref());
+        directory.join(format!("{}.snap", template_name))
+    }
+
+    fn should_update(&self) -> bool {
+        self.update.unwrap_or_else(|| {
+            std::env::var(UPDATE_SNAPSHOTS_VAR).is_ok_and(|value| !value.is_empty() && value != "0")
+        })
+    }
+
+    fn apply_masks(&self, output: &str) -> String {
+        let is_word_char = |c: char| !c.is_whitespace() && !matches!(c, '<' | '>' | '=' | '"' | '\'');
+        let mut masked = String::with_capacity(output.len());
+        let mut rest = output;
+
+        while let Some(start) = rest.find(is_word_char) {
+            masked.push_str(&rest[..start]);
+            let word_end = rest[start..].find(|c: char| !is_word_char(c)).map_or(rest.len(), |end| start + end);
+            let word = &rest[start..word_end];
+            match self.masks.iter().find(|(_, matches)| matches(word)) {
+                Some((label, _)) => masked.push_str(label),
+                None => masked.push_str(word),
+            }
+            rest = &rest[word_end..];
+        }
+        masked.push_str(rest);
+        masked
+    }
+}
+
+/// Result of comparing output with its snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SnapshotOutcome {
+    /// No snapshot existed; the output was stored
+    Created,
+    /// The output matches the snapshot
+    Matched,
+    /// The output differed and the snapshot was rewritten
+    Updated,
+    /// The output differs from the snapshot; the line diff report
+    Mismatch(String),
+}
+
+/// Compare `output`, normalized by `options`, with the snapshot at `path`
+///
+/// A missing snapshot is created (with its directories); a differing one is
+/// rewritten when updating is enabled, through
+/// [`SnapshotOptions::update`] or `RUNIC_UPDATE_SNAPSHOTS=1`, and reported
+/// as [`SnapshotOutcome::Mismatch`] otherwise.
+pub fn check_snapshot(path: impl AsRef<Path>, output: &str, options: &SnapshotOptions) -> TemplateResult<SnapshotOutcome> {
+    let path = path.as_ref();
+    let actual = options.normalize(output);
+
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, &actual)?;
+        return Ok(SnapshotOutcome::Created);
+    }
+
+    let expected = fs::read_to_string(path)?;
+    let html_diff = options.html.as_ref()
+        .filter(|_| expected != actual)
+        .map(|html| html_semantic_diff(&expected, &actual, html));
+    if expected == actual || html_diff.as_ref().is_some_and(|diff| diff.is_empty()) {
+        Ok(SnapshotOutcome::Matched)
+    } else if options.should_update() {
+        fs::write(path, &actual)?;
+        Ok(SnapshotOutcome::Updated)
+    } else if let Some(diff) = html_diff {
+        Ok(SnapshotOutcome::Mismatch(format!(
+            "Snapshot {} does not match (set {}=1 to update it)\n{}",
+            path.display(), UPDATE_SNAPSHOTS_VAR, diff,
+        )))
+    } else {
+        Ok(SnapshotOutcome::Mismatch(mismatch_report(path, &expected, &actual)))
+    }
+}
+
+/// Like [`check_snapshot`], panicking with the diff on a mismatch
+pub fn assert_snapshot(path: impl AsRef<Path>, output: &str, options: &SnapshotOptions) {
+    let path = path.as_ref();
+    match check_snapshot(path, output, options) {
+        Ok(SnapshotOutcome::Mismatch(report)) => panic!("{}", report),
+        Ok(_) => {}
+        Err(error) => panic!("Snapshot {} could not be checked: {}", path.display(), error),
+    }
+}
+
+/// Render a template and compare it with `tests/snapshots/<template>.snap`
+///
+/// The first run stores the output; later runs fail with a line diff when it
+/// changes. Run the tests with `RUNIC_UPDATE_SNAPSHOTS=1` to accept the new
+/// output. A fourth argument passes [`SnapshotOptions`].
+///
+/// ```rust,no_run
+/// use mystical_runic::{assert_template_snapshot, SnapshotOptions, TemplateContext, TemplateEngine};
+///
+/// let mut engine = TemplateEngine::new("templates");
+/// let context = TemplateContext::new();
+/// assert_template_snapshot!(engine, "page.html", context);
+///
+/// let options = SnapshotOptions::new().collapse_whitespace(true);
+/// assert_template_snapshot!(engine, "page.html", context, options);
+/// ```
+#[macro_export]
+macro_rules! assert_template_snapshot {
+    ($engine:expr, $template:expr, $context:expr) => {
+        $crate::assert_template_snapshot!($engine, $template, $context, $crate::SnapshotOptions::new())
+    };
+    ($engine:expr, $template:expr, $context:expr, $options:expr) => {{
+        let template: &str = $template;
+        let options: $crate::SnapshotOptions = $options;
+        let output = match $engine.render(template, &$context) {
+            Ok(output) => output,
+            Err(error) => panic!("Template {} failed to render: {}", template, error),
+        };
+        let path = options.snapshot_path(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/snapshots"), template);
+        $crate::assert_snapshot(&path, &output, &options);
+    }};
+}
+
+/// Line diff of a snapshot and the new output, with a few lines of context
+fn mismatch_report(path: &Path, expected: &str, actual: &str) -> String {
+    let lines = diff_lines(expected, actual);
+    let mut report = format!(
+        "Snapshot {} does not match (set {}=1 to update it)\n--- snapshot\n+++ output\n",
+        path.display(), UPDATE_SNAPSHOTS_VAR,
+    );
+
+    let changed: Vec<usize> = lines.iter().enumerate()
+        .filter(|(_, line)| !matches!(line, DiffLine::Same(_)))
+        .map(|(index, _)| index)
+        .collect();
+    if changed.is_empty() {
+        report.push_str("  (only line endings differ)\n");
+        return report;
+    }
+
+    let visible = |index: usize| changed.iter().any(|&change| index + CONTEXT_LINES >= change && index <= change + CONTEXT_LINES);
+    let mut snapshot_line = 1;
+    let mut in_hunk = false;
+    for (index, line) in lines.iter().enumerate() {
+        if visible(index) {
+            if !in_hunk {
+                report.push_str(&format!("@@ line {} @@\n", snapshot_line));
+                in_hunk = true;
+            }
+            match line {
+                DiffLine::Same(text) => report.push_str(&format!("  {}\n", text)),
+                DiffLine::Removed(text) => report.push_str(&format!("- {}\n", text)),
+                DiffLine::Added(text) => report.push_str(&format!("+ {}\n", text)),
+            }
+        } else {
+            in_hunk = false;
+        }
+        if !matches!(line, DiffLine::Added(_)) {
+            snapshot_line += 1;
+        }
+    }
+    report
+}
+
+/// Trim every line, collapse inner whitespace runs and drop blank lines
+fn collapse_whitespace(output: &str) -> String {
+    output.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Rewrite every opening tag with its attributes sorted by name
+fn sort_tag_attributes(output: &str) -> String {
+    let mut sorted = String::with_capacity(output.len());
+    let mut rest = output;
+
+    while let Some(start) = rest.find('<') {
+        sorted.push_str(&rest[..start]);
+        let tag = &rest[start..];
+        if !tag[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            sorted.push('<');
+            rest = &tag[1..];
+            continue;
+        }
+        let Some(end) = tag_end(tag) else {
+            sorted.push_str(tag);
+            return sorted;
+        };
+        sorted.push_str(&sort_attributes_of(&tag[1..end]));
+        rest = &tag[end + 1..];
+    }
+    sorted.push_str(rest);
+    sorted
+}
+
+/// Position of the `>` closing the tag at the start of `tag`, outside quotes
+fn tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in tag.char_indices() {
+        match (quote, c) {
+            (Some(open), _) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `<name b="2" a>` from the inside of a tag, with attributes sorted by name
+fn sort_attributes_of(inner: &str) -> String {
+    let (inner, self_closing) = match inner.trim_end().strip_suffix('/') {
+        Some(inner) => (inner, true),
+        None => (inner, false),
+    };
+    let name_end = inner.find(char::is_whitespace).unwrap_or(inner.len());
+    let (name, mut rest) = inner.split_at(name_end);
+
+    let mut attributes: Vec<(String, String)> = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        let name_end = rest.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(rest.len());
+        let attribute_name = rest[..name_end].to_string();
+        rest = &rest[name_end..];
+
+        let after_name = rest.trim_start();
+        let Some(value_text) = after_name.strip_prefix('=') else {
+            attributes.push((attribute_name.clone(), attribute_name));
+            continue;
+        };
+        let value_text = value_text.trim_start();
+        let value_end = match value_text.chars().next() {
+            Some(quote @ ('"' | '\'')) => value_text[1..].find(quote).map_or(value_text.len(), |close| close + 2),
+            _ => value_text.find(char::is_whitespace).unwrap_or(value_text.len()),
+        };
+        attributes.push((attribute_name.clone(), format!("{}={}", attribute_name, &value_text[..value_end])));
+        rest = &value_text[value_end..];
+    }
+    attributes.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut tag = format!("<{}", name);
+    for (_, attribute) in &attributes {
+        tag.push(' ');
+        tag.push_str(attribute);
+    }
+    tag.push_str(if self_closing { " />" } else { ">" });
+    tag
+}
@@ -0,0 +1,297 @@
+//! Context usage analysis for v0.6.0 fragment caching
+//!
+//! Records which context keys a template reads so a cache key can be built
+//! from just those values.
+
+use crate::arguments::{parse_arguments, ArgumentValue, Separator};
+use crate::context::TemplateContext;
+use crate::engine::{else_condition, split_include_scope, split_range, split_value_test, strip_negations};
+use crate::value::TemplateValue;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashSet};
+use std::hash::{Hash, Hasher};
+
+/// Context keys and dot paths referenced by a template
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContextUsage {
+    /// Top-level context keys (`user` for `{{user.name}}`)
+    pub keys: BTreeSet<String>,
+    /// Full paths as written in the template (`user.name`)
+    pub paths: BTreeSet<String>,
+}
+
+impl ContextUsage {
+    /// Create an empty usage set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a path and its top-level key
+    pub fn add_path(&mut self, path: &str) {
+        self.keys.insert(root_key(path).to_string());
+        self.paths.insert(path.to_string());
+    }
+
+    /// Add every key and path of `other`
+    pub fn merge(&mut self, other: &ContextUsage) {
+        self.keys.extend(other.keys.iter().cloned());
+        self.paths.extend(other.paths.iter().cloned());
+    }
+
+    /// Check if a top-level key is used
+    pub fn uses(&self, key: &str) -> bool {
+        self.keys.contains(key)
+    }
+
+    /// Hash only the values the template uses.
+    ///
+    /// Two contexts produce the same key when every used path resolves to the
+    /// same value, whatever else they contain.
+    pub fn cache_key(&self, context: &TemplateContext) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        // A path is covered by a shorter used path (`items` covers `items.0`)
+        for path in self.paths.iter().filter(|path| !self.is_covered(path)) {
+            path.hash(&mut hasher);
+            match lookup_path(context, path) {
+                Some(value) => {
+                    1u8.hash(&mut hasher);
+                    hash_value(value, &mut hasher);
+                }
+                None => 0u8.hash(&mut hasher),
+            }
+        }
+        hasher.finish()
+    }
+
+    fn is_covered(&self, path: &str) -> bool {
+        self.paths.iter().any(|other| {
+            path.len() > other.len() && path.starts_with(other.as_str()) && path.as_bytes()[other.len()] == b'.'
+        })
+    }
+}
+
+/// Top-level key of a dot path
+fn root_key(path: &str) -> &str {
+    path.split('.').next().unwrap_or(path)
+}
+
+/// Resolve a dot path against the context
+fn lookup_path<'a>(context: &'a TemplateContext, path: &str) -> Option<&'a TemplateValue> {
+    let mut parts = path.split('.');
+    let mut current = context.get(parts.next()?)?;
+    for part in parts {
+        current = match current {
+            TemplateValue::Object(obj) => obj.get(part)?,
+            TemplateValue::Array(arr) => arr.get(part.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+/// Hash a value with object keys in sorted order
+fn hash_value<H: Hasher>(value: &TemplateValue, hasher: &mut H) {
+    match value {
+        TemplateValue::String(s) => {
+            0u8.hash(hasher);
+            s.hash(hasher);
+        }
+        TemplateValue::Bool(b) => {
+            1u8.hash(hasher);
+            b.hash(hasher);
+        }
+        TemplateValue::Number(n) => {
+            2u8.hash(hasher);
+            n.hash(hasher);
+        }
+        TemplateValue::Float(f) => {
+            3u8.hash(hasher);
+            f.to_bits().hash(hasher);
+        }
+        TemplateValue::Array(items) => {
+            4u8.hash(hasher);
+            items.len().hash(hasher);
+            for item in items {
+                hash_value(item, hasher);
+            }
+        }
+        TemplateValue::Object(obj) => {
+            5u8.hash(hasher);
+            obj.len().hash(hasher);
+            let mut keys: Vec<&String> = obj.keys().collect();
+            keys.sort();
+            for key in keys {
+                key.hash(hasher);
+                hash_value(&obj[key], hasher);
+            }
+        }
+        TemplateValue::Null => 6u8.hash(hasher),
+    }
+}
+
+/// Result of statically scanning one template source
+pub(crate) struct TemplateScan {
+    pub usage: ContextUsage,
+    /// Quoted `{{include "..."}}` targets to scan next
+    pub includes: Vec<String>,
+}
+
+/// Statically collect the context paths a template source references.
+///
+/// Loop variables, captured names and macro parameters are local and never
+/// reported. Dynamic include/extends names are reported as the variable they
+/// read.
+pub(crate) fn scan_template(content: &str) -> TemplateScan {
+    let directives = collect_directives(content);
+    let locals = collect_locals(&directives);
+    let mut scan = TemplateScan { usage: ContextUsage::new(), includes: Vec::new() };
+
+    for directive in &directives {
+        let mut add = |expr: &str| add_reference(&mut scan.usage, &locals, expr);
+
+        if directive.starts_with('!') || directive.starts_with("#--") || directive.starts_with('/')
+            || directive == "else" || directive == "empty" || directive.starts_with("macro ") || directive.starts_with("t_scope ")
+            || directive.starts_with("capture ")
+        {
+            continue;
+        } else if let Some(args) = directive.strip_prefix("block ") {
+            // Block parameters read the context where the block is declared
+            for arg in args.split_whitespace().skip(1) {
+                if let Some((_, path)) = arg.split_once('=') {
+                    add(path);
+                }
+            }
+        } else if let Some(condition) = directive.strip_prefix("if ").or_else(|| directive.strip_prefix("unless "))
+            .or_else(|| else_condition(directive).flatten())
+        {
+            let condition = strip_negations(condition).0;
+            let condition = split_value_test(condition).map_or(condition, |(expr, ..)| expr);
+            for side in split_comparison(condition) {
+                add(side);
+            }
+        } else if let Some(expr) = directive.strip_prefix("ifchanged ") {
+            add(expr);
+        } else if let Some(loop_def) = directive.strip_prefix("for ") {
+            if let Some((_, array)) = loop_def.split_once(" in ") {
+                match split_range(array) {
+                    Some((start, end, _)) => {
+                        add(start);
+                        add(end);
+                    }
+                    None => add(array),
+                }
+            }
+        } else if let Some(tag) = directive.strip_prefix("include ").or_else(|| directive.strip_prefix("extends ")) {
+            let tag = tag.trim();
+            let (target, arguments, _) = split_include_scope(tag);
+            let arguments = arguments.and_then(|arguments| parse_arguments(tag, arguments, Separator::Whitespace).ok());
+            for argument in arguments.unwrap_or_default() {
+                if let ArgumentValue::Expression(expr) = argument.value {
+                    add(expr);
+                }
+            }
+            if target.starts_with('"') || target.starts_with('\'') {
+                if directive.starts_with("include ") {
+                    scan.includes.push(target.trim_matches('"').trim_matches('\'').to_string());
+                }
+            } else {
+                add(target);
+            }
+        } else if let Some(args) = directive.strip_prefix("t ") {
+            for arg in args.split_whitespace().skip(1) {
+                if let Some((_, value)) = arg.split_once('=') {
+                    add(value);
+                }
+            }
+        } else if let Some(args) = directive.strip_prefix("table ") {
+            let mut args = args.split_whitespace();
+            if let Some(rows) = args.next() {
+                add(rows);
+            }
+            for arg in args {
+                if let Some(columns) = arg.strip_prefix("columns=").filter(|value| !value.starts_with(['"', '\''])) {
+                    add(columns);
+                }
+            }
+        } else if let Some(args) = directive.strip_prefix("plural ") {
+            if let Some(count) = args.split_whitespace().next() {
+                add(count);
+            }
+        } else if let Some(expr) = directive.strip_prefix('&') {
+            add(expr);
+        } else if let (Some(open), Some(close)) = (directive.find('('), directive.rfind(')')) {
+            // Helper or macro call: only the arguments read the context
+            for arg in directive[open + 1..close.max(open + 1)].split(',') {
+                add(arg.split_once('=').map_or(arg, |(_, value)| value));
+            }
+        } else {
+            add(directive);
+        }
+    }
+
+    scan
+}
+
+/// Trimmed contents of every `{{ ... }}` directive
+fn collect_directives(content: &str) -> Vec<String> {
+    let mut directives = Vec::new();
+    let mut pos = 0;
+    while let Some(start) = content[pos..].find("{{") {
+        let start = pos + start;
+        let Some(end) = content[start..].find("}}") else { break };
+        directives.push(content[start + 2..start + end].trim().to_string());
+        pos = start + end + 2;
+    }
+    directives
+}
+
+/// Names bound by loops, captures and macro parameters
+fn collect_locals(directives: &[String]) -> HashSet<String> {
+    let mut locals = HashSet::new();
+    for directive in directives {
+        if let Some(loop_def) = directive.strip_prefix("for ") {
+            if let Some((item, _)) = loop_def.split_once(" in ") {
+                locals.insert(item.trim().to_string());
+            }
+        } else if let Some(name) = directive.strip_prefix("capture ") {
+            locals.insert(name.trim().to_string());
+        } else if let Some(args) = directive.strip_prefix("block ") {
+            for arg in args.split_whitespace().skip(1) {
+                if let Some((name, _)) = arg.split_once('=') {
+                    locals.insert(name.to_string());
+                }
+            }
+        } else if let Some(signature) = directive.strip_prefix("macro ") {
+            if let (Some(open), Some(close)) = (signature.find('('), signature.rfind(')')) {
+                for param in signature[open + 1..close.max(open + 1)].split(',') {
+                    let name = param.split('=').next().unwrap_or_default().trim();
+                    if !name.is_empty() {
+                        locals.insert(name.to_string());
+                    }
+                }
+            }
+        }
+    }
+    locals
+}
+
+/// Operands of a comparison (or the whole condition)
+fn split_comparison(condition: &str) -> Vec<&str> {
+    for op in ["==", "!=", "<=", ">=", "<", ">"] {
+        if let Some((left, right)) = condition.split_once(op) {
+            return vec![left, right];
+        }
+    }
+    vec![condition]
+}
+
+/// Record `expr` if it is a variable reference (filters stripped, literals ignored)
+fn add_reference(usage: &mut ContextUsage, locals: &HashSet<String>, expr: &str) {
+    let path = expr.split('|').next().unwrap_or_default().trim();
+    let is_identifier = path.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && path.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.');
+    if !is_identifier || path == "true" || path == "false" || path == "null" || locals.contains(root_key(path)) {
+        return;
+    }
+    usage.add_path(path);
+}
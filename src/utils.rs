@@ -2,9 +2,120 @@
 
 /// HTML escape function to prevent XSS attacks
 pub fn html_escape(text: &str) -> String {
-    text.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-        .replace('\'', "&#x27;")
-}
\ No newline at end of file
+    let mut escaped = String::with_capacity(text.len());
+    html_escape_into(text, &mut escaped);
+    escaped
+}
+
+/// HTML escape `text`, appending the result to `out`
+pub fn html_escape_into(text: &str, out: &mut String) {
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#x27;"),
+            _ => out.push(ch),
+        }
+    }
+}
+
+/// Append the decimal digits of `n` to `out` without an intermediate string
+pub(crate) fn write_integer(out: &mut String, n: i64) {
+    // i64::MIN has 19 digits and a sign
+    let mut digits = [0u8; 20];
+    let mut start = digits.len();
+    let mut rest = n.unsigned_abs();
+    loop {
+        start -= 1;
+        digits[start] = b'0' + (rest % 10) as u8;
+        rest /= 10;
+        if rest == 0 {
+            break;
+        }
+    }
+    if n < 0 {
+        start -= 1;
+        digits[start] = b'-';
+    }
+    // Only ASCII digits and '-' were written
+    out.push_str(std::str::from_utf8(&digits[start..]).unwrap_or_default());
+}
+
+/// Elements whose contents are whitespace sensitive and never minified
+const PRESERVED_ELEMENTS: [&str; 4] = ["pre", "textarea", "script", "style"];
+
+/// Minify rendered HTML.
+///
+/// Whitespace runs containing a line break between two tags are removed,
+/// other whitespace runs collapse to a single space and HTML comments are
+/// dropped. The contents of `<pre>`, `<textarea>`, `<script>` and `<style>`
+/// are copied verbatim.
+pub fn minify_html(html: &str) -> String {
+    let lower = html.to_ascii_lowercase();
+    let mut out = String::with_capacity(html.len());
+    let mut pos = 0;
+
+    while pos < html.len() {
+        // Next whitespace sensitive element, if any
+        let preserved = PRESERVED_ELEMENTS.iter()
+            .filter_map(|tag| find_element_start(&lower[pos..], tag).map(|start| (pos + start, *tag)))
+            .min_by_key(|(start, _)| *start);
+
+        let Some((start, tag)) = preserved else {
+            minify_text(&html[pos..], &mut out);
+            break;
+        };
+
+        minify_text(&html[pos..start], &mut out);
+        let close = format!("</{}>", tag);
+        let end = lower[start..].find(&close).map_or(html.len(), |offset| start + offset + close.len());
+        out.push_str(&html[start..end]);
+        pos = end;
+    }
+
+    out.trim().to_string()
+}
+
+/// Start of the first `<tag` (followed by `>` or whitespace) in `lower`
+fn find_element_start(lower: &str, tag: &str) -> Option<usize> {
+    let open = format!("<{}", tag);
+    let mut pos = 0;
+    while let Some(offset) = lower[pos..].find(&open) {
+        let start = pos + offset;
+        match lower[start + open.len()..].chars().next() {
+            Some(c) if c == '>' || c.is_ascii_whitespace() => return Some(start),
+            None => return None,
+            _ => pos = start + open.len(),
+        }
+    }
+    None
+}
+
+/// Collapse whitespace and drop comments in markup outside preserved elements
+fn minify_text(text: &str, out: &mut String) {
+    let mut rest = text;
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+
+        let ch = rest.chars().next().unwrap_or_default();
+        if !ch.is_whitespace() {
+            out.push(ch);
+            rest = &rest[ch.len_utf8()..];
+            continue;
+        }
+
+        let run_end = rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len());
+        let (run, after) = rest.split_at(run_end);
+        // Line breaks between tags are formatting; anything else is one space
+        let formatting = run.contains('\n') && out.ends_with('>') && (after.is_empty() || after.starts_with('<'));
+        if !formatting && !out.ends_with(' ') {
+            out.push(' ');
+        }
+        rest = after;
+    }
+}
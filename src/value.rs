@@ -3,11 +3,143 @@
 use std::collections::HashMap;
 
 /// Template value types that can be used in templates
+///
+/// # Ordering
+/// `Object` keys are stored unordered. Wherever the engine enumerates keys
+/// (object stringification, completion lists, diagnostics), they are sorted
+/// lexicographically by byte value, so equal values always render identically
+/// regardless of insertion order.
+///
+/// # Null
+/// `Null` is a variable that is present without a value, as JSON `null`
+/// is. It renders as an empty string and is falsy, but unlike an empty
+/// string it satisfies `{{if name is null}}`.
 #[derive(Debug, Clone)]
 pub enum TemplateValue {
     String(String),
     Bool(bool),
     Number(i64),
+    Float(f64),
     Array(Vec<TemplateValue>),
     Object(HashMap<String, TemplateValue>),
-}
\ No newline at end of file
+    Null,
+}
+impl From<&str> for TemplateValue {
+    fn from(value: &str) -> Self {
+        TemplateValue::String(value.to_string())
+    }
+}
+
+impl From<String> for TemplateValue {
+    fn from(value: String) -> Self {
+        TemplateValue::String(value)
+    }
+}
+
+impl From<i64> for TemplateValue {
+    fn from(value: i64) -> Self {
+        TemplateValue::Number(value)
+    }
+}
+
+impl From<f64> for TemplateValue {
+    fn from(value: f64) -> Self {
+        TemplateValue::Float(value)
+    }
+}
+
+impl From<bool> for TemplateValue {
+    fn from(value: bool) -> Self {
+        TemplateValue::Bool(value)
+    }
+}
+
+impl From<Vec<TemplateValue>> for TemplateValue {
+    fn from(value: Vec<TemplateValue>) -> Self {
+        TemplateValue::Array(value)
+    }
+}
+
+impl From<HashMap<String, TemplateValue>> for TemplateValue {
+    fn from(value: HashMap<String, TemplateValue>) -> Self {
+        TemplateValue::Object(value)
+    }
+}
+
+/// Build a [`TemplateContext`](crate::TemplateContext) from `"name" => value`
+/// entries
+///
+/// Values are anything that converts into a [`TemplateValue`]; `{ ... }`
+/// builds an object of the same entries and `[ ... ]` an array.
+///
+/// ```rust
+/// use mystical_runic::{runic_context, TemplateEngine};
+///
+/// let context = runic_context! {
+///     "user" => { "name" => "Alex", "age" => 30 },
+///     "tags" => ["a", "b"],
+///     "admin" => false,
+/// };
+/// let mut engine = TemplateEngine::new("templates");
+/// assert_eq!(engine.render_string("{{user.name}} ({{user.age}}): {{tags|join:\", \"}}", &context)?, "Alex (30): a, b");
+/// # Ok::<(), mystical_runic::TemplateError>(())
+/// ```
+#[macro_export]
+macro_rules! runic_context {
+    ($($entries:tt)*) => {{
+        let mut context = $crate::TemplateContext::new();
+        if let $crate::TemplateValue::Object(entries) = $crate::runic_value!({ $($entries)* }) {
+            for (name, value) in entries {
+                context.set(&name, value);
+            }
+        }
+        context
+    }};
+}
+
+/// Build a [`TemplateValue`] the way [`runic_context!`] builds its values
+#[doc(hidden)]
+#[macro_export]
+macro_rules! runic_value {
+    (@object $object:ident ()) => {};
+    (@object $object:ident ($key:expr => { $($value:tt)* } $(, $($rest:tt)*)?)) => {
+        $object.insert(::std::string::String::from($key), $crate::runic_value!({ $($value)* }));
+        $crate::runic_value!(@object $object ($($($rest)*)?));
+    };
+    (@object $object:ident ($key:expr => [ $($value:tt)* ] $(, $($rest:tt)*)?)) => {
+        $object.insert(::std::string::String::from($key), $crate::runic_value!([ $($value)* ]));
+        $crate::runic_value!(@object $object ($($($rest)*)?));
+    };
+    (@object $object:ident ($key:expr => $value:expr $(, $($rest:tt)*)?)) => {
+        $object.insert(::std::string::String::from($key), $crate::runic_value!($value));
+        $crate::runic_value!(@object $object ($($($rest)*)?));
+    };
+    (@array $items:ident ()) => {};
+    (@array $items:ident ({ $($value:tt)* } $(, $($rest:tt)*)?)) => {
+        $items.push($crate::runic_value!({ $($value)* }));
+        $crate::runic_value!(@array $items ($($($rest)*)?));
+    };
+    (@array $items:ident ([ $($value:tt)* ] $(, $($rest:tt)*)?)) => {
+        $items.push($crate::runic_value!([ $($value)* ]));
+        $crate::runic_value!(@array $items ($($($rest)*)?));
+    };
+    (@array $items:ident ($value:expr $(, $($rest:tt)*)?)) => {
+        $items.push($crate::runic_value!($value));
+        $crate::runic_value!(@array $items ($($($rest)*)?));
+    };
+    ({ $($entries:tt)* }) => {{
+        #[allow(unused_mut)]
+        let mut object = ::std::collections::HashMap::new();
+        $crate::runic_value!(@object object ($($entries)*));
+        $crate::TemplateValue::Object(object)
+    }};
+    ([ $($items:tt)* ]) => {{
+        #[allow(unused_mut)]
+        let mut items = ::std::vec::Vec::new();
+        $crate::runic_value!(@array items ($($items)*));
+        $crate::TemplateValue::Array(items)
+    }};
+    ($value:expr) => {
+        $crate::TemplateValue::from($value)
+    };
+}
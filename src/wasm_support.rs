@@ -141,6 +141,13 @@ impl WasmRuneEngine {
     }
 }
 
+#[cfg(feature = "wasm")]
+impl Default for WasmRuneEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(not(feature = "wasm"))]
 /// Placeholder when WASM feature is not enabled
 #[allow(dead_code)]
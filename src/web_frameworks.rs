@@ -5,12 +5,96 @@
 #[cfg(feature = "async")]
 use crate::{TemplateEngine, TemplateContext, async_engine::AsyncTemplateEngine};
 
+#[cfg(feature = "async")]
+use std::sync::Arc;
+
+/// Base context kept in application state and shared by every request.
+///
+/// Cheap to clone (framework state extractors clone it per request); each
+/// request context is a thin layer over the shared base.
+///
+/// ```rust,ignore
+/// let shared = SharedTemplateContext::new(site_context);
+/// let app = Router::new().route("/", get(handler)).with_state(shared);
+///
+/// async fn handler(State(shared): State<SharedTemplateContext>) -> Html<String> {
+///     let mut context = shared.request_context();
+///     context.set_string("title", "Home");
+///     // render with `context`
+/// }
+/// ```
+#[cfg(feature = "async")]
+#[derive(Debug, Clone)]
+pub struct SharedTemplateContext {
+    base: Arc<TemplateContext>,
+}
+
+#[cfg(feature = "async")]
+impl SharedTemplateContext {
+    /// Share `base` across requests
+    pub fn new(base: TemplateContext) -> Self {
+        Self { base: Arc::new(base) }
+    }
+
+    /// A per-request context layered on the shared base
+    pub fn request_context(&self) -> TemplateContext {
+        TemplateContext::layered(Arc::clone(&self.base))
+    }
+
+    /// The shared base context
+    pub fn base(&self) -> &Arc<TemplateContext> {
+        &self.base
+    }
+}
+
+#[cfg(feature = "async")]
+impl From<Arc<TemplateContext>> for SharedTemplateContext {
+    fn from(base: Arc<TemplateContext>) -> Self {
+        Self { base }
+    }
+}
+
+/// Output of `render_to_writer`, one chunk per flush of the writer
+#[cfg(any(feature = "axum-integration", feature = "warp-integration", feature = "actix-integration"))]
+#[derive(Default)]
+struct FlushedChunks {
+    chunks: Vec<Vec<u8>>,
+    current: Vec<u8>,
+}
+
+#[cfg(any(feature = "axum-integration", feature = "warp-integration", feature = "actix-integration"))]
+impl std::io::Write for FlushedChunks {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.current.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if !self.current.is_empty() {
+            self.chunks.push(std::mem::take(&mut self.current));
+        }
+        Ok(())
+    }
+}
+
+/// Render a template file into body chunks that end at its flush points
+/// (see [`TemplateEngine::set_stream_flush_on`]), so proxies forward each
+/// one as soon as it arrives
+#[cfg(any(feature = "axum-integration", feature = "warp-integration", feature = "actix-integration"))]
+fn render_chunks(engine: &mut TemplateEngine, template_name: &str, context: &TemplateContext) -> crate::TemplateResult<Vec<Vec<u8>>> {
+    let mut output = FlushedChunks::default();
+    engine.render_to_writer(template_name, context, &mut output)?;
+    std::io::Write::flush(&mut output)?;
+    Ok(output.chunks)
+}
+
 // Axum integration
 #[cfg(feature = "axum-integration")]
 pub mod axum_integration {
     use super::*;
+    use axum::body::Body;
     use axum::response::{Html, Response, IntoResponse};
-    use axum::http::StatusCode;
+    use axum::http::{header, StatusCode};
     use futures::future::BoxFuture;
 
     /// Axum response extension for TemplateEngine
@@ -20,6 +104,10 @@ pub mod axum_integration {
         
         /// Render template file and return Axum HTML response
         fn render_axum_file<'a>(&'a mut self, template_name: &'a str, context: &'a TemplateContext) -> BoxFuture<'a, Result<Html<String>, TemplateResponseError>>;
+        
+        /// Render template file and return a streamed HTML response, one body
+        /// chunk per flush point
+        fn render_axum_stream<'a>(&'a mut self, template_name: &'a str, context: &'a TemplateContext) -> BoxFuture<'a, Result<Response, TemplateResponseError>>;
     }
 
     #[derive(Debug)]
@@ -61,6 +149,15 @@ pub mod axum_integration {
                 }
             })
         }
+
+        fn render_axum_stream<'a>(&'a mut self, template_name: &'a str, context: &'a TemplateContext) -> BoxFuture<'a, Result<Response, TemplateResponseError>> {
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                let chunks = render_chunks(self, template_name, context)?;
+                let body = Body::from_stream(futures::stream::iter(chunks.into_iter().map(Ok::<_, std::convert::Infallible>)));
+                Ok(([(header::CONTENT_TYPE, "text/html; charset=utf-8")], body).into_response())
+            })
+        }
     }
 }
 
@@ -76,6 +173,10 @@ pub mod warp_integration {
     pub trait WarpTemplateEngine {
         /// Render template and return Warp reply
         fn render_warp<'a>(&'a mut self, template: &'a str, context: &'a TemplateContext) -> BoxFuture<'a, Result<impl Reply, crate::TemplateError>>;
+        
+        /// Render template file and return a streamed Warp reply, one body
+        /// chunk per flush point
+        fn render_warp_stream<'a>(&'a mut self, template_name: &'a str, context: &'a TemplateContext) -> BoxFuture<'a, Result<impl Reply, crate::TemplateError>>;
     }
 
     pub struct TemplateReply {
@@ -97,6 +198,15 @@ pub mod warp_integration {
                 }
             })
         }
+
+        fn render_warp_stream<'a>(&'a mut self, template_name: &'a str, context: &'a TemplateContext) -> BoxFuture<'a, Result<impl Reply, crate::TemplateError>> {
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                let chunks = render_chunks(self, template_name, context)?;
+                let body = warp::hyper::Body::wrap_stream(futures::stream::iter(chunks.into_iter().map(Ok::<_, std::convert::Infallible>)));
+                Ok(warp::reply::with_header(Response::new(body), "content-type", "text/html; charset=utf-8"))
+            })
+        }
     }
 }
 
@@ -105,12 +215,17 @@ pub mod warp_integration {
 pub mod actix_integration {
     use super::*;
     use actix_web::{HttpResponse, Result as ActixResult};
+    use actix_web::web::Bytes;
     use futures::future::BoxFuture;
 
     /// Actix-web response extension for TemplateEngine
     pub trait ActixTemplateEngine {
         /// Render template and return Actix HttpResponse
         fn render_actix<'a>(&'a mut self, template: &'a str, context: &'a TemplateContext) -> BoxFuture<'a, ActixResult<HttpResponse>>;
+        
+        /// Render template file and return a streamed Actix HttpResponse, one
+        /// body chunk per flush point
+        fn render_actix_stream<'a>(&'a mut self, template_name: &'a str, context: &'a TemplateContext) -> BoxFuture<'a, ActixResult<HttpResponse>>;
     }
 
     impl ActixTemplateEngine for TemplateEngine {
@@ -126,6 +241,20 @@ pub mod actix_integration {
                 }
             })
         }
+
+        fn render_actix_stream<'a>(&'a mut self, template_name: &'a str, context: &'a TemplateContext) -> BoxFuture<'a, ActixResult<HttpResponse>> {
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                match render_chunks(self, template_name, context) {
+                    Ok(chunks) => Ok(HttpResponse::Ok()
+                        .content_type("text/html; charset=utf-8")
+                        .streaming(futures::stream::iter(chunks.into_iter().map(|chunk| Ok::<_, std::convert::Infallible>(Bytes::from(chunk)))))),
+                    Err(e) => Ok(HttpResponse::InternalServerError()
+                        .content_type("text/html; charset=utf-8")
+                        .body(format!("Template Error: {}", e))),
+                }
+            })
+        }
     }
 }
 
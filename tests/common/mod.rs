@@ -0,0 +1,47 @@
+//! Setup shared by the integration test files
+//!
+//! Each test file is its own crate and uses only part of this module.
+
+#![allow(dead_code, unused_imports)]
+
+pub use mystical_runic::{TemplateEngine, TemplateContext, TemplateValue};
+pub use std::collections::HashMap;
+pub use std::fs;
+pub use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static TEMP_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+// Utility to create temporary directories for testing; the counter keeps
+// directories apart when tests running in parallel read the same timestamp
+pub fn create_temp_dir() -> PathBuf {
+    let mut temp_path = std::env::temp_dir();
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let count = TEMP_DIRS.fetch_add(1, Ordering::Relaxed);
+    temp_path.push(format!("mystical_runic_test_{}_{}_{}", std::process::id(), timestamp, count));
+    let _ = std::fs::create_dir_all(&temp_path);
+    temp_path
+}
+
+/// A temporary template directory holding `files`, given as
+/// `(relative path, content)` pairs
+pub fn create_template_dir(files: &[(&str, &str)]) -> PathBuf {
+    let templates_path = create_temp_dir();
+    for (name, content) in files {
+        let path = templates_path.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    templates_path
+}
+
+/// An engine over a temporary template directory holding `files`; the
+/// directory is returned so the test can remove it
+pub fn engine_with_templates(files: &[(&str, &str)]) -> (TemplateEngine, PathBuf) {
+    let templates_path = create_template_dir(files);
+    let engine = TemplateEngine::new(templates_path.to_str().unwrap());
+    (engine, templates_path)
+}
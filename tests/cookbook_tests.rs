@@ -0,0 +1,47 @@
+//! Runs every example of examples/cookbook and checks what it prints against
+//! examples/cookbook/expected/<name>.txt
+//!
+//! Each example writes its output through a `run(out)` function that its
+//! `main` calls with stdout, so the examples are included here as modules.
+
+use std::fs;
+use std::io::Write;
+
+type Example = fn(&mut dyn Write) -> Result<(), Box<dyn std::error::Error>>;
+
+/// Run `example` and compare its output with the expected output of `name`
+fn check_example(name: &str, example: Example) {
+    let mut output = Vec::new();
+    example(&mut output).unwrap_or_else(|error| panic!("cookbook example '{}' failed: {}", name, error));
+    let output = String::from_utf8(output).unwrap();
+
+    let expected_path = format!("{}/examples/cookbook/expected/{}.txt", env!("CARGO_MANIFEST_DIR"), name);
+    let expected = fs::read_to_string(&expected_path)
+        .unwrap_or_else(|error| panic!("missing expected output {}: {}", expected_path, error));
+    assert_eq!(output, expected, "output of cookbook example '{}' changed", name);
+}
+
+/// Include a cookbook example as a module and test its output
+macro_rules! cookbook_example {
+    ($(#[$cfg:meta])* $name:ident, $path:literal) => {
+        $(#[$cfg])*
+        #[allow(dead_code)]
+        #[path = $path]
+        mod $name;
+
+        $(#[$cfg])*
+        #[test]
+        fn $name() {
+            check_example(stringify!($name), $name::run);
+        }
+    };
+}
+
+cookbook_example!(custom_filter, "../examples/cookbook/custom_filter.rs");
+cookbook_example!(#[cfg(feature = "template-macros")] helper_named_args, "../examples/cookbook/helper_named_args.rs");
+cookbook_example!(#[cfg(feature = "inheritance")] inheritance_super, "../examples/cookbook/inheritance_super.rs");
+cookbook_example!(#[cfg(feature = "i18n")] i18n_fallback, "../examples/cookbook/i18n_fallback.rs");
+cookbook_example!(streaming_to_file, "../examples/cookbook/streaming_to_file.rs");
+cookbook_example!(#[cfg(feature = "axum-integration")] axum_handler, "../examples/cookbook/axum_handler.rs");
+cookbook_example!(strict_mode_errors, "../examples/cookbook/strict_mode_errors.rs");
+cookbook_example!(sandboxed_user_templates, "../examples/cookbook/sandboxed_user_templates.rs");
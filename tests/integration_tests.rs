@@ -1,18 +1,6 @@
-use mystical_runic::{TemplateEngine, TemplateContext, TemplateValue};
-use std::fs;
-use std::path::PathBuf;
-
-// Utility to create temporary directories for testing
-fn create_temp_dir() -> PathBuf {
-    let mut temp_path = std::env::temp_dir();
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-    temp_path.push(format!("mystical_runic_test_{}_{}", std::process::id(), timestamp));
-    let _ = std::fs::create_dir_all(&temp_path);
-    temp_path
-}
+mod common;
+
+use common::*;
 
 fn cleanup_temp_dir(path: &PathBuf) {
     let _ = std::fs::remove_dir_all(path);
@@ -1242,9 +1230,9 @@ fn test_stress_parsing_edge_cases() {
         let result = engine.render_string(template, &context);
         // These should either work gracefully or return appropriate errors
         // The key is that they shouldn't crash or cause undefined behavior
-        match result {
-            Ok(output) => assert!(output.is_empty(), "Unexpected output for {}: {}", template, output),
-            Err(_) => {} // Errors are acceptable for malformed input
+        // Errors are acceptable for malformed input
+        if let Ok(output) = result {
+            assert!(output.is_empty(), "Unexpected output for {}: {}", template, output);
         }
     }
 }
@@ -1481,9 +1469,9 @@ fn test_for_loop_syntax_validation() {
     for template in invalid_loops {
         let result = engine.render_string(template, &context);
         // The current implementation might handle these gracefully or error
-        match result {
-            Ok(output) => assert_eq!(output, "", "Expected empty output for invalid loop: {}", template),
-            Err(_) => {} // Errors are also acceptable
+        // Errors are also acceptable
+        if let Ok(output) = result {
+            assert_eq!(output, "", "Expected empty output for invalid loop: {}", template);
         }
     }
 }
@@ -1500,7 +1488,7 @@ fn test_file_io_error_scenarios() {
     // Test with permission denied scenario (simulate by using a file as directory)
     let templates_path = create_temp_dir();
     let file_path = &templates_path.join("not_a_directory.txt");
-    fs::write(&file_path, "content").unwrap();
+    fs::write(file_path, "content").unwrap();
     
     let mut engine2 = TemplateEngine::new(file_path.to_str().unwrap());
     let result2 = engine2.render("template.html", &context);
@@ -1600,4 +1588,2169 @@ fn test_complex_nested_scenarios() {
     assert!(result.contains("Item 2"));
     assert!(result.contains("Active"));
     assert!(result.contains("</ul>"));
-}
\ No newline at end of file
+}
+
+/// Dynamic includes and inheritance resolved from context variables
+#[cfg(test)]
+mod dynamic_include_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+
+    fn setup_templates() -> PathBuf {
+        let templates_path = create_temp_dir();
+        fs::create_dir_all(templates_path.join("partials")).unwrap();
+        fs::create_dir_all(templates_path.join("layouts")).unwrap();
+        fs::write(templates_path.join("partials/text.html"), "<p>{{item.body}}</p>").unwrap();
+        fs::write(templates_path.join("partials/image.html"), "<img src=\"{{item.body}}\">").unwrap();
+        fs::write(templates_path.join("partials/header.html"), "<h1>{{title}}</h1>").unwrap();
+        fs::write(templates_path.join("secret.html"), "TOP SECRET").unwrap();
+        fs::write(templates_path.join("layouts/wide.html"), "<wide>{{block content}}{{/block}}</wide>").unwrap();
+        fs::write(templates_path.join("layouts/narrow.html"), "<narrow>{{block content}}{{/block}}</narrow>").unwrap();
+        fs::write(
+            templates_path.join("page.html"),
+            "{{extends page.layout}}{{block content}}{{title}}{{/block}}",
+        ).unwrap();
+        templates_path
+    }
+
+    fn block(kind: &str, body: &str) -> TemplateValue {
+        let mut map = HashMap::new();
+        map.insert("partial".to_string(), TemplateValue::String(format!("partials/{}.html", kind)));
+        map.insert("body".to_string(), TemplateValue::String(body.to_string()));
+        TemplateValue::Object(map)
+    }
+
+    #[test]
+    fn test_dynamic_include_per_loop_item() {
+        let templates_path = setup_templates();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.allow_dynamic_includes(["partials/"]);
+        let mut context = TemplateContext::new();
+        context.set("blocks", TemplateValue::Array(vec![
+            block("text", "Hello"),
+            block("image", "cat.png"),
+            block("text", "Bye"),
+        ]));
+
+        let result = engine.render_string("{{for item in blocks}}{{include item.partial}}{{/for}}", &context).unwrap();
+        assert_eq!(result, "<p>Hello</p><img src=\"cat.png\"><p>Bye</p>");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_dynamic_include_from_dot_path() {
+        let templates_path = setup_templates();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.allow_dynamic_includes(["partials/*.html"]);
+        let mut context = TemplateContext::new();
+        let mut page = HashMap::new();
+        page.insert("header".to_string(), TemplateValue::String("partials/header.html".to_string()));
+        context.set("page", TemplateValue::Object(page));
+        context.set_string("title", "Welcome");
+
+        let result = engine.render_string("{{include page.header}}", &context).unwrap();
+        assert_eq!(result, "<h1>Welcome</h1>");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_dynamic_include_outside_allow_list_is_blocked() {
+        let templates_path = setup_templates();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.allow_dynamic_includes(["partials/"]);
+        let mut context = TemplateContext::new();
+        context.set_string("partial", "secret.html");
+
+        match engine.render_string("{{include partial}}", &context) {
+            Err(TemplateError::Security(message)) => assert!(message.contains("secret.html"), "{}", message),
+            other => panic!("expected security error, got {:?}", other),
+        }
+
+        // Without any allow-list every dynamic include is rejected
+        let mut locked = TemplateEngine::new(templates_path.to_str().unwrap());
+        context.set_string("partial", "partials/header.html");
+        assert!(matches!(
+            locked.render_string("{{include partial}}", &context),
+            Err(TemplateError::Security(_))
+        ));
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_static_and_unresolved_includes() {
+        let templates_path = setup_templates();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        let mut context = TemplateContext::new();
+        context.set_string("title", "Static");
+
+        // Quoted includes need no allow-list
+        let result = engine.render_string("{{include \"partials/header.html\"}}", &context).unwrap();
+        assert_eq!(result, "<h1>Static</h1>");
+
+        let err = engine.render_string("{{include missing.partial}}", &context).unwrap_err();
+        assert!(err.to_string().contains("missing"));
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[cfg(feature = "inheritance")]
+    #[test]
+    fn test_dynamic_extends() {
+        let templates_path = setup_templates();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.allow_dynamic_includes(["layouts/"]);
+
+        for (layout, expected) in [("wide", "<wide>Hi</wide>"), ("narrow", "<narrow>Hi</narrow>")] {
+            let mut context = TemplateContext::new();
+            let mut page = HashMap::new();
+            page.insert("layout".to_string(), TemplateValue::String(format!("layouts/{}.html", layout)));
+            context.set("page", TemplateValue::Object(page));
+            context.set_string("title", "Hi");
+            assert_eq!(engine.render("page.html", &context).unwrap(), expected);
+        }
+
+        let mut context = TemplateContext::new();
+        let mut page = HashMap::new();
+        page.insert("layout".to_string(), TemplateValue::String("secret.html".to_string()));
+        context.set("page", TemplateValue::Object(page));
+        assert!(matches!(engine.render("page.html", &context), Err(TemplateError::Security(_))));
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Asset requirements: {{needs}} gathered into {{required_assets}}
+#[cfg(test)]
+mod asset_requirement_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+
+    fn asset_engine(templates_path: &std::path::Path) -> TemplateEngine {
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.register_asset("carousel", "<link href=\"/carousel.css\">", "<script src=\"/carousel.js\"></script>");
+        engine.register_asset("modal", "<link href=\"/modal.css\">", "<script src=\"/modal.js\"></script>");
+        engine.register_asset("analytics", "", "<script src=\"/analytics.js\"></script>");
+        engine
+    }
+
+    #[cfg(feature = "inheritance")]
+    #[test]
+    fn test_component_in_loop_emits_assets_once_in_first_needed_order() {
+        let templates_path = create_temp_dir();
+        fs::write(
+            templates_path.join("layout.html"),
+            "<head>{{required_assets \"css\"}}</head><body>{{block body}}{{/block}}{{required_assets \"js\"}}</body>",
+        ).unwrap();
+        fs::write(templates_path.join("card.html"), "{{needs \"modal\" \"carousel\"}}<div>{{card}}</div>").unwrap();
+        fs::write(
+            templates_path.join("page.html"),
+            "{{extends \"layout.html\"}}{{block body}}{{for card in cards}}{{include \"card.html\"}}{{/for}}{{needs \"carousel\"}}{{/block}}",
+        ).unwrap();
+        let mut engine = asset_engine(&templates_path);
+
+        let mut context = TemplateContext::new();
+        context.set("cards", TemplateValue::Array(
+            ["a", "b", "c"].iter().map(|card| TemplateValue::String(card.to_string())).collect(),
+        ));
+
+        let result = engine.render("page.html", &context).unwrap();
+        assert_eq!(
+            result,
+            "<head><link href=\"/modal.css\">\n<link href=\"/carousel.css\"></head>\
+             <body><div>a</div><div>b</div><div>c</div>\
+             <script src=\"/modal.js\"></script>\n<script src=\"/carousel.js\"></script></body>"
+        );
+        assert_eq!(result.matches("/carousel.css").count(), 1);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_assets_follow_rendered_branches_only() {
+        let mut engine = asset_engine(std::path::Path::new("."));
+        let mut context = TemplateContext::new();
+        context.set_bool("track", false);
+
+        let template = "{{required_assets \"js\"}}|{{if track}}{{needs \"analytics\"}}{{/if}}{{needs 'modal'}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "<script src=\"/modal.js\"></script>|");
+
+        context.set_bool("track", true);
+        assert_eq!(
+            engine.render_string(template, &context).unwrap(),
+            "<script src=\"/analytics.js\"></script>\n<script src=\"/modal.js\"></script>|"
+        );
+
+        // Assets without tags of the requested kind are skipped
+        assert_eq!(engine.render_string("{{needs \"analytics\"}}[{{required_assets \"css\"}}]", &context).unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_unknown_assets_and_invalid_directives() {
+        let mut engine = asset_engine(std::path::Path::new("."));
+        let context = TemplateContext::new();
+
+        // Unknown assets are ignored unless strict mode is on
+        let template = "{{needs \"chart\"}}{{required_assets \"css\"}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "");
+        engine.enable_strict_mode();
+        let error = engine.render_string(template, &context).unwrap_err();
+        assert!(error.to_string().contains("Asset 'chart' is not registered"), "{}", error);
+
+        assert!(matches!(engine.render_string("{{required_assets \"fonts\"}}", &context), Err(TemplateError::Parse(_))));
+        assert!(matches!(engine.render_string("{{needs carousel}}", &context), Err(TemplateError::Parse(_))));
+    }
+
+    #[test]
+    fn test_values_cannot_forge_asset_markers() {
+        let mut engine = asset_engine(std::path::Path::new("."));
+        let mut context = TemplateContext::new();
+        let forged = "\u{E000}@css\u{E001}|\u{E000}+modal\u{E001}";
+        context.set_string("bio", forged);
+
+        assert_eq!(engine.render_string("{{bio}}{{required_assets \"js\"}}", &context).unwrap(), forged);
+        assert_eq!(engine.render_string("{{& bio}}{{required_assets \"js\"}}", &context).unwrap(), forged);
+
+        // A stray opening character doesn't hide the marker after it
+        context.set_string("bio", "\u{E000}");
+        assert_eq!(
+            engine.render_string("{{& bio}}{{needs \"modal\"}}{{required_assets \"js\"}}", &context).unwrap(),
+            "\u{E000}<script src=\"/modal.js\"></script>"
+        );
+    }
+}
+
+/// Engine profiles: one-call development/production settings
+#[cfg(test)]
+mod profile_tests {
+    use super::*;
+    use mystical_runic::{EngineConfig, Profile};
+
+    #[test]
+    fn test_new_engine_matches_default_config() {
+        let engine = TemplateEngine::new(".");
+        assert_eq!(engine.config(), EngineConfig::default());
+    }
+
+    #[test]
+    fn test_production_profile_settings() {
+        let mut engine = TemplateEngine::new(".");
+        engine.apply_profile(Profile::Production);
+
+        assert!(!engine.is_debug_enabled());
+        assert!(!engine.is_hot_reload_enabled());
+        assert!(engine.is_bytecode_cache_enabled());
+        assert_eq!(engine.get_template_cache_capacity(), None);
+        assert!(engine.is_strict_mode_enabled());
+        assert!(engine.is_minify_output_enabled());
+        assert!(engine.is_strip_visible_comments_enabled());
+        assert!(engine.is_performance_monitoring_enabled());
+        assert_eq!(engine.config(), Profile::Production.config());
+    }
+
+    #[test]
+    fn test_development_after_production_restores_development_values() {
+        let mut engine = TemplateEngine::new(".");
+        engine.apply_profile(Profile::Development);
+        let development = engine.config();
+
+        engine.apply_profile(Profile::Production);
+        assert_ne!(engine.config(), development);
+
+        engine.apply_profile(Profile::Development);
+        assert_eq!(engine.config(), development);
+        assert!(engine.is_debug_enabled());
+        assert!(engine.is_hot_reload_enabled());
+        assert!(!engine.is_bytecode_cache_enabled());
+        assert_eq!(engine.get_template_cache_capacity(), Some(16));
+        assert!(!engine.is_strict_mode_enabled());
+        assert!(!engine.is_minify_output_enabled());
+        assert!(!engine.is_strip_visible_comments_enabled());
+        assert!(!engine.is_performance_monitoring_enabled());
+    }
+
+    #[test]
+    fn test_settings_remain_overridable_after_profile() {
+        let mut engine = TemplateEngine::new(".");
+        engine.apply_profile(Profile::Production);
+        engine.disable_strict_mode();
+        engine.set_minify_output(false);
+
+        let config = engine.config();
+        assert!(!config.strict_mode);
+        assert!(!config.minify_output);
+        assert!(config.bytecode_cache);
+    }
+
+    #[test]
+    fn test_custom_profile_and_builder() {
+        let custom = EngineConfig { strict_mode: true, minify_output: true, ..EngineConfig::default() };
+        let mut engine = TemplateEngine::new(".");
+        engine.apply_profile(Profile::Custom(custom.clone()));
+        assert_eq!(engine.config(), custom);
+
+        // Later builder calls override the profile, earlier ones are replaced by it
+        let engine = TemplateEngine::builder()
+            .strict_mode(false)
+            .profile(Profile::Production)
+            .hot_reload(true)
+            .build();
+        let config = engine.config();
+        assert!(config.strict_mode);
+        assert!(config.hot_reload);
+        assert!(config.minify_output);
+
+        let engine = TemplateEngine::builder().template_dir("tests/templates").build();
+        assert_eq!(engine.get_template_dir(), "tests/templates");
+        assert_eq!(engine.config(), EngineConfig::default());
+    }
+
+    #[test]
+    fn test_production_output_is_minified_without_comments() {
+        let mut engine = TemplateEngine::builder().profile(Profile::Production).build();
+        let mut context = TemplateContext::new();
+        context.set_string("name", "Ada");
+
+        let template = "<ul>\n    <!-- list -->\n    <li>{{#-- item --}}Hello   {{name}}</li>\n</ul>\n<pre>  keep\n  this </pre>";
+        assert_eq!(
+            engine.render_string(template, &context).unwrap(),
+            "<ul><li>Hello Ada</li></ul><pre>  keep\n  this </pre>"
+        );
+
+        engine.apply_profile(Profile::Development);
+        assert_eq!(
+            engine.render_string(template, &context).unwrap(),
+            "<ul>\n    <!-- list -->\n    <li><!-- item -->Hello   Ada</li>\n</ul>\n<pre>  keep\n  this </pre>"
+        );
+    }
+
+    #[test]
+    fn test_template_cache_capacity_bounds_cache() {
+        let templates_path = create_temp_dir();
+        for i in 0..5 {
+            fs::write(templates_path.join(format!("t{}.html", i)), format!("T{}", i)).unwrap();
+        }
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.set_template_cache_capacity(Some(2));
+
+        let context = TemplateContext::new();
+        for i in 0..5 {
+            assert_eq!(engine.render(&format!("t{}.html", i), &context).unwrap(), format!("T{}", i));
+        }
+        let cached = (0..5).filter(|i| engine.is_template_cached(&format!("t{}.html", i))).count();
+        assert_eq!(cached, 2);
+        assert!(engine.is_template_cached("t4.html"));
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Layered contexts: per-request layers over a shared base
+#[cfg(test)]
+mod layered_context_tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn site_context(keys: usize) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("site", "Runic");
+        context.set_string("title", "Default title");
+        context.set_bool("beta", false);
+        let menu = ["Home", "Blog", "About"].iter()
+            .map(|entry| TemplateValue::String(entry.to_string()))
+            .collect();
+        context.set("menu", TemplateValue::Array(menu));
+        for i in 0..keys {
+            context.set_string(&format!("config_{}", i), "x".repeat(64).as_str());
+        }
+        context
+    }
+
+    #[test]
+    fn test_override_precedence_and_base_immutability() {
+        let base = Arc::new(site_context(0));
+        let mut request = TemplateContext::layered(Arc::clone(&base));
+        request.set_string("title", "Profile");
+        request.set_string("user", "ada");
+
+        assert_eq!(request.get_string("title"), Some("Profile".to_string()));
+        assert_eq!(request.get_string("site"), Some("Runic".to_string()));
+        assert_eq!(request.get_string("user"), Some("ada".to_string()));
+        assert!(request.contains("menu"));
+
+        assert_eq!(base.get_string("title"), Some("Default title".to_string()));
+        assert!(base.get("user").is_none());
+
+        // Layers stack: the closest layer wins
+        let mut nested = TemplateContext::layered(Arc::new(request));
+        nested.set_bool("beta", true);
+        assert!(matches!(nested.get("beta"), Some(TemplateValue::Bool(true))));
+        assert_eq!(nested.get_string("title"), Some("Profile".to_string()));
+        assert!(matches!(base.get("beta"), Some(TemplateValue::Bool(false))));
+    }
+
+    #[cfg(feature = "template-macros")]
+    #[test]
+    fn test_layered_context_renders_like_flat_context() {
+        let mut engine = TemplateEngine::new(".");
+        let base = Arc::new(site_context(0));
+        let mut request = TemplateContext::layered(Arc::clone(&base));
+        request.set_string("title", "Profile");
+
+        let mut flat = site_context(0);
+        flat.set_string("title", "Profile");
+
+        let template = "{{macro link(label)}}<a>{{label}}</a>{{/macro}}<h1>{{site}}: {{title|upper}}</h1>\
+            {{if beta}}beta{{/if}}{{for entry in menu}}{{link(entry)}}{{if title == \"Profile\"}}*{{/if}}{{/for}}";
+        let expected = engine.render_string(template, &flat).unwrap();
+        assert_eq!(engine.render_string(template, &request).unwrap(), expected);
+        assert_eq!(expected, "<h1>Runic: PROFILE</h1><a>Home</a>*<a>Blog</a>*<a>About</a>*");
+    }
+
+    #[test]
+    fn test_large_base_is_shared_not_copied() {
+        let base = Arc::new(site_context(10_000));
+        let mut engine = TemplateEngine::new(".");
+
+        let children: Vec<TemplateContext> = (0..1_000).map(|i| {
+            let mut child = TemplateContext::layered(Arc::clone(&base));
+            child.set_number("request_id", i);
+            child
+        }).collect();
+
+        // One shared base, referenced by every child
+        assert_eq!(Arc::strong_count(&base), 1_001);
+        assert!(children.iter().all(|child| Arc::ptr_eq(child.base().unwrap(), &base)));
+
+        for (i, child) in children.iter().enumerate().step_by(250) {
+            let result = engine.render_string("{{site}} #{{request_id}} {{config_9999}}", child).unwrap();
+            assert_eq!(result, format!("Runic #{} {}", i, "x".repeat(64)));
+        }
+
+        drop(children);
+        assert_eq!(Arc::strong_count(&base), 1);
+    }
+
+    #[test]
+    fn test_layered_context_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<TemplateContext>();
+
+        let base = Arc::new(site_context(0));
+        let handles: Vec<_> = (0..4).map(|i| {
+            let base = Arc::clone(&base);
+            std::thread::spawn(move || {
+                let mut context = TemplateContext::layered(base);
+                context.set_number("worker", i);
+                TemplateEngine::new(".").render_string("{{site}}-{{worker}}", &context).unwrap()
+            })
+        }).collect();
+
+        let results: Vec<String> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+        assert_eq!(results, vec!["Runic-0", "Runic-1", "Runic-2", "Runic-3"]);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_shared_template_context_builds_request_layers() {
+        use mystical_runic::SharedTemplateContext;
+
+        let shared = SharedTemplateContext::new(site_context(0));
+        let state = shared.clone();
+        let mut request = state.request_context();
+        request.set_string("title", "Dashboard");
+
+        assert_eq!(request.get_string("site"), Some("Runic".to_string()));
+        assert_eq!(request.get_string("title"), Some("Dashboard".to_string()));
+        assert!(Arc::ptr_eq(request.base().unwrap(), shared.base()));
+    }
+}
+
+#[cfg(test)]
+mod template_root_tests {
+    use super::*;
+    use mystical_runic::TemplateRoot;
+    use std::path::Path;
+
+    /// App templates with a local `ds/button.html` override, and a vendor design system
+    fn create_roots() -> (PathBuf, PathBuf) {
+        let app_dir = create_temp_dir().join("app");
+        let vendor_dir = create_temp_dir().join("vendor");
+        fs::create_dir_all(app_dir.join("ds")).unwrap();
+        fs::create_dir_all(&vendor_dir).unwrap();
+
+        fs::write(app_dir.join("ds/button.html"), "<button class=\"patched\">{{label}}</button>").unwrap();
+        fs::write(vendor_dir.join("button.html"), "<button>{{label}}</button>").unwrap();
+        fs::write(vendor_dir.join("card.html"), "<div class=\"card\">{{include \"ds::button.html\"}}</div>").unwrap();
+        fs::write(app_dir.join("page.html"), "{{include \"ds::button.html\"}}|{{include \"ds::button.html\" from \"vendor\"}}").unwrap();
+        (app_dir, vendor_dir)
+    }
+
+    fn create_engine(app_dir: &Path, vendor_dir: &Path) -> TemplateEngine {
+        let mut engine = TemplateEngine::new(app_dir.to_str().unwrap());
+        engine.add_template_root(TemplateRoot::new("vendor", vendor_dir).with_namespace("ds"));
+        engine
+    }
+
+    fn create_context() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("label", "Save");
+        context
+    }
+
+    #[test]
+    fn test_app_override_shadows_vendor_template() {
+        let (app_dir, vendor_dir) = create_roots();
+        let mut engine = create_engine(&app_dir, &vendor_dir);
+
+        let result = engine.render("ds::card.html", &create_context()).unwrap();
+        assert_eq!(result, "<div class=\"card\"><button class=\"patched\">Save</button></div>");
+    }
+
+    #[test]
+    fn test_from_clause_reaches_shadowed_original() {
+        let (app_dir, vendor_dir) = create_roots();
+        let mut engine = create_engine(&app_dir, &vendor_dir);
+
+        let result = engine.render("page.html", &create_context()).unwrap();
+        assert_eq!(result, "<button class=\"patched\">Save</button>|<button>Save</button>");
+    }
+
+    #[test]
+    fn test_higher_priority_root_wins() {
+        let (app_dir, vendor_dir) = create_roots();
+        let mut engine = TemplateEngine::new(app_dir.to_str().unwrap());
+        engine.add_template_root_with_priority(TemplateRoot::new("vendor", &vendor_dir).with_namespace("ds"), 10);
+
+        let result = engine.render("ds::button.html", &create_context()).unwrap();
+        assert_eq!(result, "<button>Save</button>");
+    }
+
+    #[test]
+    fn test_unknown_from_root_fails() {
+        let (app_dir, vendor_dir) = create_roots();
+        let mut engine = create_engine(&app_dir, &vendor_dir);
+
+        let error = engine.render_string("{{include \"ds::button.html\" from \"nowhere\"}}", &create_context()).unwrap_err();
+        assert!(error.to_string().contains("Unknown template root 'nowhere'"));
+    }
+
+    #[test]
+    fn test_explain_resolution_lists_searched_roots() {
+        let (app_dir, vendor_dir) = create_roots();
+        let engine = create_engine(&app_dir, &vendor_dir);
+
+        let trace = engine.explain_resolution("ds::button.html");
+        assert_eq!(trace.namespace.as_deref(), Some("ds"));
+        let roots: Vec<&str> = trace.candidates.iter().map(|candidate| candidate.root.as_str()).collect();
+        assert_eq!(roots, vec!["app", "vendor"]);
+        assert_eq!(trace.candidates[0].path, app_dir.join("ds").join("button.html"));
+        assert_eq!(trace.candidates[1].path, vendor_dir.join("button.html"));
+        assert_eq!(trace.winner().unwrap().root, "app");
+        assert_eq!(trace.shadowed().len(), 1);
+
+        let report = trace.to_string();
+        assert!(report.contains("[app]") && report.contains("selected"));
+        assert!(report.contains("[vendor]") && report.contains("shadowed"));
+
+        // Namespaced roots don't serve names outside their namespace
+        let trace = engine.explain_resolution("missing.html");
+        assert_eq!(trace.candidates.len(), 1);
+        assert!(trace.winner().is_none());
+        assert!(trace.to_string().contains("no root provides this template"));
+    }
+
+    #[test]
+    fn test_hot_reload_picks_up_new_override() {
+        let (app_dir, vendor_dir) = create_roots();
+        fs::remove_file(app_dir.join("ds/button.html")).unwrap();
+        let mut engine = create_engine(&app_dir, &vendor_dir);
+        engine.enable_hot_reload();
+
+        assert_eq!(engine.render("ds::button.html", &create_context()).unwrap(), "<button>Save</button>");
+
+        fs::write(app_dir.join("ds/button.html"), "<button class=\"local\">{{label}}</button>").unwrap();
+        assert_eq!(engine.render("ds::button.html", &create_context()).unwrap(), "<button class=\"local\">Save</button>");
+
+        fs::remove_file(app_dir.join("ds/button.html")).unwrap();
+        assert_eq!(engine.render("ds::button.html", &create_context()).unwrap(), "<button>Save</button>");
+    }
+}
+
+/// Atomic hot reload of dependency closures and the shared engine
+#[cfg(feature = "inheritance")]
+#[cfg(test)]
+mod atomic_reload_tests {
+    use super::*;
+    use mystical_runic::SharedTemplateEngine;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    /// Replace a file in one step, as editors and deploy tools do
+    fn replace_file(path: &Path, content: &str) {
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, content).unwrap();
+        fs::rename(&temporary, path).unwrap();
+    }
+
+    fn write_site(templates_path: &Path, partial: &str) {
+        fs::write(
+            templates_path.join("layout.html"),
+            "<header>{{include \"partial.html\"}}</header><main>{{block content}}{{/block}}</main>",
+        ).unwrap();
+        fs::write(
+            templates_path.join("page.html"),
+            "{{extends \"layout.html\"}}{{block content}}{{include \"partial.html\"}}{{/block}}",
+        ).unwrap();
+        fs::write(templates_path.join("other.html"), "unrelated").unwrap();
+        fs::write(templates_path.join("partial.html"), partial).unwrap();
+    }
+
+    #[test]
+    fn test_reload_covers_dependency_closure() {
+        let templates_path = create_temp_dir();
+        write_site(&templates_path, "old");
+
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        let context = TemplateContext::new();
+        assert_eq!(engine.render("page.html", &context).unwrap(), "<header>old</header><main>old</main>");
+        engine.render("other.html", &context).unwrap();
+        assert!(engine.reload_changed_templates().is_empty());
+
+        replace_file(&templates_path.join("partial.html"), "brand new");
+        let reloaded = engine.reload_changed_templates();
+        assert_eq!(reloaded, vec!["layout.html", "page.html", "partial.html"]);
+        assert!(engine.is_template_cached("page.html"), "the closure is read again right away");
+        assert_eq!(engine.render("page.html", &context).unwrap(), "<header>brand new</header><main>brand new</main>");
+
+        // The layout's new parent-side markup is picked up through the extends edge
+        fs::write(
+            templates_path.join("layout.html"),
+            "<nav>{{include \"partial.html\"}}</nav><article>{{block content}}{{/block}}</article>",
+        ).unwrap();
+        assert_eq!(engine.reload_templates(&["layout.html"]), vec!["layout.html", "page.html"]);
+        assert_eq!(engine.render("page.html", &context).unwrap(), "<nav>brand new</nav><article>brand new</article>");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_removed_template_reports_error_after_reload() {
+        let templates_path = create_temp_dir();
+        write_site(&templates_path, "old");
+
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.render("page.html", &TemplateContext::new()).unwrap();
+
+        fs::remove_file(templates_path.join("partial.html")).unwrap();
+        assert!(engine.reload_changed_templates().contains(&"partial.html".to_string()));
+        assert!(!engine.is_template_cached("partial.html"));
+        assert!(engine.render("page.html", &TemplateContext::new()).is_err());
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_shared_engine_publishes_generations() {
+        let templates_path = create_temp_dir();
+        write_site(&templates_path, "old");
+
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.warm_up().unwrap();
+        let shared = SharedTemplateEngine::new(engine);
+        assert_eq!(shared.generation(), 0);
+        assert!(shared.reload_changed().is_empty(), "nothing changed");
+        assert_eq!(shared.generation(), 0);
+
+        replace_file(&templates_path.join("partial.html"), "brand new");
+        assert_eq!(shared.reload_changed(), vec!["layout.html", "page.html", "partial.html"]);
+        assert_eq!(shared.generation(), 1);
+        assert_eq!(
+            shared.render("page.html", &TemplateContext::new()).unwrap(),
+            "<header>brand new</header><main>brand new</main>",
+        );
+
+        shared.update(|engine| engine.register_filter("shout", |value, _| Ok(value.to_uppercase())));
+        assert_eq!(shared.generation(), 2);
+        let mut context = TemplateContext::new();
+        context.set_string("word", "hi");
+        assert_eq!(shared.render_string("{{word|shout}}", &context).unwrap(), "HI");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_concurrent_renders_never_mix_reloads() {
+        let templates_path = create_temp_dir();
+        write_site(&templates_path, "old");
+        let expected_old = "<header>old</header><main>old</main>";
+        let expected_new = "<header>brand new</header><main>brand new</main>";
+
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.warm_up().unwrap();
+        let shared = SharedTemplateEngine::new(engine);
+        let done = Arc::new(AtomicBool::new(false));
+
+        let renderers: Vec<_> = (0..4).map(|_| {
+            let shared = shared.clone();
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                let context = TemplateContext::new();
+                let mut renders = 0;
+                while !done.load(Ordering::Relaxed) || renders < 50 {
+                    let output = shared.render("page.html", &context).unwrap();
+                    assert!(output == expected_old || output == expected_new, "mixed render: {}", output);
+                    renders += 1;
+                }
+            })
+        }).collect();
+
+        let partial = templates_path.join("partial.html");
+        for round in 0..100 {
+            replace_file(&partial, if round % 2 == 0 { "brand new" } else { "old" });
+            shared.reload_changed();
+        }
+        done.store(true, Ordering::Relaxed);
+
+        for renderer in renderers {
+            renderer.join().expect("a render saw a mixture of old and new templates");
+        }
+        assert!(shared.generation() > 0);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Whole-directory validation when the engine is created
+#[cfg(test)]
+mod template_validation_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+
+    /// Three valid templates, two broken ones and a broken draft
+    fn template_dir() -> PathBuf {
+        let dir = create_temp_dir();
+        fs::create_dir_all(dir.join("pages")).unwrap();
+        fs::create_dir_all(dir.join("drafts")).unwrap();
+        fs::write(dir.join("layout.html"), "<main>{{block content}}{{/block}}</main>").unwrap();
+        fs::write(dir.join("nav.html"), "<nav>{{site|upper}}</nav>").unwrap();
+        fs::write(dir.join("pages/home.html"), "{{extends \"layout.html\"}}{{block content}}{{include \"nav.html\"}}{{/block}}").unwrap();
+        fs::write(dir.join("pages/broken.html"), "<p>\n{{if user}}Hello{{/for}}</p>").unwrap();
+        fs::write(dir.join("pages/missing.html"), "{{include \"nav.html\"}}\n  {{include \"footer.html\"}}").unwrap();
+        fs::write(dir.join("drafts/wip.html"), "{{for item in}}").unwrap();
+        dir
+    }
+
+    fn failures(result: Result<TemplateEngine, TemplateError>) -> Vec<(String, String)> {
+        match result {
+            Err(TemplateError::InvalidTemplates { failures, .. }) => failures,
+            Err(other) => panic!("expected InvalidTemplates, got {}", other),
+            Ok(_) => panic!("the engine should not be constructed"),
+        }
+    }
+
+    #[test]
+    fn test_every_broken_template_is_reported() {
+        let dir = template_dir();
+        let failures = failures(TemplateEngine::new_validated(dir.to_str().unwrap()));
+        let names: Vec<&str> = failures.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["drafts/wip.html", "pages/broken.html", "pages/missing.html"]);
+
+        assert!(failures[1].1.starts_with("2:"), "{}", failures[1].1);
+        assert_eq!(failures[2].1, "2:3: Referenced template 'footer.html' does not exist");
+    }
+
+    #[test]
+    fn test_builder_validation_respects_ignore_patterns() {
+        let dir = template_dir();
+        let result = TemplateEngine::builder()
+            .template_dir(dir.to_str().unwrap())
+            .validate_on_build(true)
+            .validation_ignore("drafts/")
+            .try_build();
+        match result {
+            Err(error @ TemplateError::InvalidTemplates { .. }) => {
+                let message = error.to_string();
+                assert!(message.starts_with("2 of 5 templates"), "{}", message);
+                assert!(message.contains("pages/broken.html:2:"));
+                assert!(message.contains("pages/missing.html:2:3:"));
+                assert!(!message.contains("drafts/"));
+            }
+            Err(other) => panic!("expected InvalidTemplates, got {}", other),
+            Ok(_) => panic!("the engine should not be constructed"),
+        }
+    }
+
+    #[cfg(feature = "inheritance")]
+    #[test]
+    fn test_valid_directory_builds() {
+        let dir = template_dir();
+        for broken in ["pages/broken.html", "pages/missing.html", "drafts/wip.html"] {
+            fs::remove_file(dir.join(broken)).unwrap();
+        }
+
+        let mut engine = TemplateEngine::new_validated(dir.to_str().unwrap()).unwrap();
+        let mut context = TemplateContext::new();
+        context.set_string("site", "runic");
+        assert!(engine.render("pages/home.html", &context).unwrap().contains("<nav>RUNIC</nav>"));
+
+        let engine = TemplateEngine::builder()
+            .template_dir(dir.to_str().unwrap())
+            .validate_on_build(true)
+            .build();
+        assert_eq!(engine.get_template_dir(), dir.to_str().unwrap());
+    }
+
+    #[test]
+    fn test_registered_filters_count_as_known() {
+        let dir = create_temp_dir();
+        fs::write(dir.join("shout.html"), "{{word|shout}}").unwrap();
+
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        assert!(engine.validate_templates(&[]).is_err());
+        engine.register_filter("shout", |value, _| Ok(value.to_uppercase()));
+        assert!(engine.validate_templates(&[]).is_ok());
+        assert!(engine.validate_templates(&["*.html"]).is_ok());
+    }
+}
+
+/// render_offline: cached templates only, never the filesystem
+#[cfg(all(feature = "i18n", feature = "inheritance"))]
+#[cfg(test)]
+mod offline_render_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+
+    fn site_dir() -> PathBuf {
+        let dir = create_temp_dir();
+        fs::create_dir_all(dir.join("partials")).unwrap();
+        fs::write(dir.join("base.html"), "<main>{{block content}}{{/block}}</main>").unwrap();
+        fs::write(dir.join("page.html"), "{{extends \"base.html\"}}{{block content}}{{include \"partials/nav.html\"}}<h1>{{title}}</h1>{{/block}}").unwrap();
+        fs::write(dir.join("partials/nav.html"), "<nav>{{t \"home\"}}</nav>").unwrap();
+        dir
+    }
+
+    fn context() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("title", "Welcome");
+        context
+    }
+
+    #[test]
+    fn test_warmed_up_page_renders_without_the_directory() {
+        let dir = site_dir();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        engine.set_translations("en", HashMap::from([("home".to_string(), "Home".to_string())]));
+        engine.set_locale("en");
+        engine.warm_up().unwrap();
+
+        // Any read would fail now
+        fs::remove_dir_all(&dir).unwrap();
+        let result = engine.render_offline("page.html", &context()).unwrap();
+        assert_eq!(result, "<main><nav>Home</nav><h1>Welcome</h1></main>");
+    }
+
+    #[test]
+    fn test_uncached_partial_is_an_offline_miss() {
+        let dir = site_dir();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        // The page and its layout are cached, the partial it includes is only on disk
+        engine.warm_up_filtered("page.html").unwrap();
+        match engine.render_offline("page.html", &context()) {
+            Err(TemplateError::OfflineMiss { name }) => assert_eq!(name, "partials/nav.html"),
+            other => panic!("expected an offline miss, got {:?}", other),
+        }
+        assert!(!engine.is_template_cached("partials/nav.html"));
+
+        match engine.render_offline("missing.html", &context()) {
+            Err(TemplateError::OfflineMiss { name }) => assert_eq!(name, "missing.html"),
+            other => panic!("expected an offline miss, got {:?}", other),
+        }
+
+        // Regular renders still load from disk
+        assert_eq!(engine.render("page.html", &context()).unwrap(), "<main><nav>home</nav><h1>Welcome</h1></main>");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_hot_reload_is_skipped_offline() {
+        let dir = site_dir();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        engine.enable_hot_reload();
+        engine.warm_up().unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(engine.render_offline("page.html", &context()).unwrap().contains("<h1>Welcome</h1>"));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_render_offline_async() {
+        use mystical_runic::AsyncTemplateEngine;
+
+        let dir = site_dir();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        engine.warm_up_async().await.unwrap();
+
+        fs::remove_dir_all(&dir).unwrap();
+        let result = engine.render_offline_async("page.html", &context()).await.unwrap();
+        assert!(result.contains("<h1>Welcome</h1>"));
+        assert!(matches!(
+            engine.render_offline_async("other.html", &context()).await,
+            Err(TemplateError::OfflineMiss { .. })
+        ));
+    }
+}
+
+/// Parameters declared on layout blocks, `{{block name param=path}}`
+#[cfg(feature = "inheritance")]
+#[cfg(test)]
+mod block_parameter_tests {
+    use super::*;
+
+    fn layout_dir() -> PathBuf {
+        let dir = create_temp_dir();
+        fs::write(dir.join("base.html"), "<header>{{block page_header title=page.title}}<h1>{{title}}</h1>{{/block}}</header>").unwrap();
+        fs::write(dir.join("child.html"), "{{extends \"base.html\"}}{{block page_header}}<h2>{{title|upper}}</h2>{{super}}{{/block}}").unwrap();
+        fs::write(dir.join("grandchild.html"), "{{extends \"child.html\"}}{{block page_header}}{{if title}}<em>{{title}}</em>{{/if}}|{{super}}{{/block}}").unwrap();
+        dir
+    }
+
+    fn page(title: &str) -> TemplateContext {
+        let mut page = HashMap::new();
+        page.insert("title".to_string(), TemplateValue::String(title.to_string()));
+        let mut context = TemplateContext::new();
+        context.set("page", TemplateValue::Object(page));
+        context
+    }
+
+    #[test]
+    fn test_parent_default_reads_its_parameter() {
+        let dir = layout_dir();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        assert_eq!(engine.render("base.html", &page("Home")).unwrap(), "<header><h1>Home</h1></header>");
+        assert!(engine.check_syntax("{{block page_header title=page.title}}{{title}}{{/block}}").is_ok());
+    }
+
+    #[test]
+    fn test_child_override_reads_parent_parameter() {
+        let dir = layout_dir();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        assert_eq!(engine.render("child.html", &page("News")).unwrap(), "<header><h2>NEWS</h2><h1>News</h1></header>");
+    }
+
+    #[test]
+    fn test_grandchild_re_overrides_the_block() {
+        let dir = layout_dir();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        let result = engine.render("grandchild.html", &page("Blog")).unwrap();
+        assert_eq!(result, "<header><em>Blog</em>|<h2>BLOG</h2><h1>Blog</h1></header>");
+    }
+
+    #[test]
+    fn test_parameters_must_be_variable_paths() {
+        let dir = layout_dir();
+        fs::write(dir.join("bad.html"), "{{block header title=\"Home\"}}{{title}}{{/block}}").unwrap();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        let error = engine.render("bad.html", &page("Home")).unwrap_err();
+        assert!(error.to_string().contains("Invalid parameter 'title=\"Home\"' of block 'header'"), "{}", error);
+        assert!(engine.check_syntax("{{block header title=\"Home\"}}{{/block}}").is_err());
+    }
+}
+
+/// render_component with props and slots
+#[cfg(test)]
+mod component_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+
+    #[cfg(feature = "template-macros")]
+    const BUTTON_BODY: &str = "{{slots icon?}}<button class=\"btn-{{variant}}\">\
+                               {{if has_slot \"icon\"}}{{slot.icon}} {{/if}}{{label}}</button>";
+
+    #[cfg(feature = "template-macros")]
+    fn engine_with_button_template(templates_path: &std::path::Path) -> TemplateEngine {
+        fs::create_dir_all(templates_path.join("components")).unwrap();
+        fs::write(
+            templates_path.join("components/button.html"),
+            format!("{{{{props label variant=\"primary\"}}}}{}", BUTTON_BODY),
+        ).unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.register_component("button", "components/button.html");
+        engine
+    }
+
+    fn props(values: &[(&str, &str)]) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        for (name, value) in values {
+            context.set_string(name, value);
+        }
+        context
+    }
+
+    fn slots(values: &[(&str, &str)]) -> HashMap<String, String> {
+        values.iter().map(|(name, html)| (name.to_string(), html.to_string())).collect()
+    }
+
+    #[test]
+    #[cfg(feature = "template-macros")]
+    fn test_template_and_macro_components_render_the_same() {
+        let templates_path = create_temp_dir();
+        let mut template_engine = engine_with_button_template(&templates_path);
+        let mut macro_engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        macro_engine.render_string(
+            &format!("{{{{macro button(label, variant=\"primary\")}}}}{}{{{{/macro}}}}", BUTTON_BODY),
+            &TemplateContext::new(),
+        ).unwrap();
+
+        let calls = [
+            (props(&[("label", "Save & close")]), slots(&[])),
+            (props(&[("label", "Delete"), ("variant", "danger")]), slots(&[("icon", "<i class=\"trash\"></i>")])),
+        ];
+        for (props, slots) in calls {
+            let from_template = template_engine.render_component("button", props.clone(), slots.clone()).unwrap();
+            let from_macro = macro_engine.render_component("button", props, slots).unwrap();
+            assert_eq!(from_template, from_macro);
+        }
+
+        let result = template_engine.render_component(
+            "button",
+            props(&[("label", "Delete"), ("variant", "danger")]),
+            slots(&[("icon", "<i class=\"trash\"></i>")]),
+        ).unwrap();
+        assert_eq!(result, "<button class=\"btn-danger\"><i class=\"trash\"></i> Delete</button>");
+        let result = template_engine.render_component("button", props(&[("label", "Save & close")]), slots(&[])).unwrap();
+        assert_eq!(result, "<button class=\"btn-primary\">Save &amp; close</button>");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_missing_and_unknown_props_and_slots_name_the_component() {
+        let templates_path = create_temp_dir();
+        fs::write(
+            templates_path.join("card.html"),
+            "{{props title}}{{slots body footer?}}<h2>{{title}}</h2>{{slot.body}}{{slot.footer}}",
+        ).unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.register_component("card", "card.html");
+
+        let error = engine.render_component("card", props(&[("subtitle", "x")]), slots(&[("header", "<b>")])).unwrap_err();
+        match &error {
+            TemplateError::ComponentError { component, missing_props, unknown_props, missing_slots, unknown_slots } => {
+                assert_eq!(component, "card");
+                assert_eq!(missing_props, &["title"]);
+                assert_eq!(unknown_props, &["subtitle"]);
+                assert_eq!(missing_slots, &["body"]);
+                assert_eq!(unknown_slots, &["header"]);
+            }
+            other => panic!("expected a component error, got {:?}", other),
+        }
+        assert_eq!(error.kind(), "component");
+        assert!(error.to_string().contains("Component 'card'"), "{}", error);
+        assert!(error.to_string().contains("missing props 'title'"), "{}", error);
+
+        let result = engine.render_component("card", props(&[("title", "News")]), slots(&[("body", "<p>Hi</p>")])).unwrap();
+        assert_eq!(result, "<h2>News</h2><p>Hi</p>");
+
+        assert!(matches!(
+            engine.render_component("missing", TemplateContext::new(), HashMap::new()),
+            Err(TemplateError::Template(_))
+        ));
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_undeclared_component_accepts_anything() {
+        let templates_path = create_temp_dir();
+        fs::write(
+            templates_path.join("badge.html"),
+            "<span>{{text}}{{has_slot \"extra\"}}{{has_slot \"other\"}}</span>",
+        ).unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.register_component("badge", "badge.html");
+
+        let result = engine.render_component("badge", props(&[("text", "<new>")]), slots(&[("extra", "")])).unwrap();
+        assert_eq!(result, "<span>&lt;new&gt;truefalse</span>");
+
+        // Declarations and has_slot render nothing special outside components
+        let result = engine.render_string("{{props a}}{{slots b}}{{if has_slot \"b\"}}yes{{/if}}", &TemplateContext::new()).unwrap();
+        assert_eq!(result, "");
+        assert!(engine.check_syntax("{{props label variant=\"primary\"}}{{slots body footer?}}{{has_slot \"body\"}}").is_ok());
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// set_deterministic / set_fixed_time reproducible renders
+#[cfg(test)]
+mod deterministic_rendering_tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    const BUILD_TIME: u64 = 1_700_000_000;
+
+    fn site_fixture() -> PathBuf {
+        let templates_path = create_temp_dir();
+        fs::write(
+            templates_path.join("post.html"),
+            "<article data-island=\"{{nonce()}}\"><h1>{{title}}</h1>\
+             <p>{{published_at|timeago}}</p>\
+             {{for tag in tags}}<span>{{tag}}</span>{{/for}}\
+             <pre>{{meta}}</pre>{{include \"footer.html\"}}</article>",
+        ).unwrap();
+        fs::write(
+            templates_path.join("footer.html"),
+            "<footer>built {{now()}}<script nonce=\"{{nonce()}}\"></script></footer>",
+        ).unwrap();
+        templates_path
+    }
+
+    fn post() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("title", "Release notes");
+        context.set("published_at", TemplateValue::Number(BUILD_TIME as i64 - 3 * 3_600));
+        context.set("tags", TemplateValue::Array(vec![
+            TemplateValue::String("rust".to_string()),
+            TemplateValue::String("templates".to_string()),
+        ]));
+        let meta = ["zeta", "alpha", "mu", "beta", "omega"].iter()
+            .map(|key| (key.to_string(), TemplateValue::String(key.to_uppercase())))
+            .collect();
+        context.set("meta", TemplateValue::Object(meta));
+        context
+    }
+
+    fn build(templates_path: &std::path::Path, seed: u64) -> String {
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.set_deterministic(true, seed);
+        engine.set_fixed_time(UNIX_EPOCH + Duration::from_secs(BUILD_TIME));
+        engine.render("post.html", &post()).unwrap()
+    }
+
+    fn nonces(output: &str) -> Vec<&str> {
+        output.match_indices("=\"").map(|(index, _)| &output[index + 2..index + 18]).collect()
+    }
+
+    #[test]
+    fn test_same_seed_renders_identical_bytes() {
+        let templates_path = site_fixture();
+
+        let first = build(&templates_path, 42);
+        assert_eq!(first, build(&templates_path, 42));
+        assert!(first.contains("<p>3 hours ago</p>"), "{}", first);
+        assert!(first.contains(&format!("built {}", BUILD_TIME)), "{}", first);
+
+        // Ids restart with every render of the same engine
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.set_deterministic(true, 42);
+        engine.set_fixed_time(UNIX_EPOCH + Duration::from_secs(BUILD_TIME));
+        assert_eq!(engine.render("post.html", &post()).unwrap(), first);
+        assert_eq!(engine.render("post.html", &post()).unwrap(), first);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_other_seed_only_changes_nonces() {
+        let templates_path = site_fixture();
+
+        let first = build(&templates_path, 42);
+        let other = build(&templates_path, 7);
+        let (first_ids, other_ids) = (nonces(&first), nonces(&other));
+        assert_eq!(first_ids.len(), 2);
+        assert_ne!(first_ids[0], first_ids[1]);
+        assert!(first_ids.iter().chain(&other_ids).all(|id| id.chars().all(|c| c.is_ascii_hexdigit())));
+        assert!(first_ids.iter().all(|id| !other_ids.contains(id)));
+
+        let mut masked = other.clone();
+        for (other_id, first_id) in other_ids.iter().zip(&first_ids) {
+            masked = masked.replace(other_id, first_id);
+        }
+        assert_eq!(masked, first);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_clock_without_deterministic_mode() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set("at", TemplateValue::Number(BUILD_TIME as i64 + 2 * 86_400));
+
+        // Random ids differ between renders, the real clock is past the fixture time
+        let first = engine.render_string("{{nonce()}}", &context).unwrap();
+        assert_eq!(first.len(), 16);
+        assert_ne!(first, engine.render_string("{{nonce()}}", &context).unwrap());
+        let now: u64 = engine.render_string("{{now()}}", &context).unwrap().parse().unwrap();
+        assert!(now > BUILD_TIME);
+
+        engine.set_fixed_time(UNIX_EPOCH + Duration::from_secs(BUILD_TIME));
+        assert_eq!(engine.render_string("{{at|timeago}}", &context).unwrap(), "in 2 days");
+
+        // Deterministic mode without a fixed time uses the Unix epoch
+        let mut engine = TemplateEngine::new(".");
+        engine.set_deterministic(true, 1);
+        assert_eq!(engine.render_string("{{now()}}", &context).unwrap(), "0");
+
+        // Registered helpers win over the built-in ones
+        engine.register_helper("now", |_| Ok(TemplateValue::String("tea time".to_string())));
+        assert_eq!(engine.render_string("{{now()}}", &context).unwrap(), "tea time");
+    }
+}
+
+/// runic.manifest public templates and render_public
+#[cfg(test)]
+mod template_manifest_tests {
+    use super::*;
+    use mystical_runic::{TemplateError, TemplateManifest};
+    use std::time::Duration;
+
+    fn site() -> PathBuf {
+        let templates_path = create_temp_dir();
+        fs::create_dir_all(templates_path.join("pages")).unwrap();
+        fs::create_dir_all(templates_path.join("partials")).unwrap();
+        fs::write(templates_path.join("pages/home.html"), "<main>{{include \"partials/nav.html\"}}{{body}}</main>").unwrap();
+        fs::write(templates_path.join("pages/about.html"), "<p>About {{body}}</p>").unwrap();
+        fs::write(templates_path.join("partials/nav.html"), "<nav></nav>").unwrap();
+        fs::write(
+            templates_path.join("runic.manifest"),
+            "# Pages served by name\nhome -> pages/home.html cache_ttl=300\npages/about.html autoescape=off\n",
+        ).unwrap();
+        templates_path
+    }
+
+    fn body(html: &str) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("body", html);
+        context
+    }
+
+    #[test]
+    fn test_aliases_resolve_through_the_manifest() {
+        let templates_path = site();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        assert_eq!(engine.render_public("home", &body("<b>hi</b>")).unwrap(), "<main><nav></nav>&lt;b&gt;hi&lt;/b&gt;</main>");
+        assert_eq!(engine.render_public("pages/about.html", &body("<b>us</b>")).unwrap(), "<p>About <b>us</b></p>");
+        assert_eq!(engine.list_public_templates().unwrap(), vec!["home", "pages/about.html"]);
+
+        let entry = engine.template_manifest().unwrap().get("home").unwrap().clone();
+        assert_eq!(entry.template, "pages/home.html");
+        assert_eq!(entry.cache_ttl, Some(Duration::from_secs(300)));
+        assert!(entry.autoescape);
+
+        // autoescape=off applies to that render only
+        assert_eq!(engine.render("pages/about.html", &body("<b>us</b>")).unwrap(), "<p>About &lt;b&gt;us&lt;/b&gt;</p>");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_internal_templates_are_refused() {
+        let templates_path = site();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        for name in ["partials/nav.html", "pages/home.html", "../runic.manifest", "missing"] {
+            match engine.render_public(name, &TemplateContext::new()) {
+                Err(TemplateError::Security(message)) => assert!(message.contains(name), "{}", message),
+                other => panic!("expected a security error for {}, got {:?}", name, other),
+            }
+        }
+        // Plain render is unaffected
+        assert_eq!(engine.render("partials/nav.html", &TemplateContext::new()).unwrap(), "<nav></nav>");
+
+        // Without a manifest nothing is public
+        let empty_path = create_temp_dir();
+        fs::write(empty_path.join("page.html"), "page").unwrap();
+        let mut engine = TemplateEngine::new(empty_path.to_str().unwrap());
+        assert!(matches!(engine.render_public("page.html", &TemplateContext::new()), Err(TemplateError::Security(_))));
+        assert!(engine.list_public_templates().unwrap().is_empty());
+
+        let _ = fs::remove_dir_all(&templates_path);
+        let _ = fs::remove_dir_all(&empty_path);
+    }
+
+    #[test]
+    fn test_manifest_reloads_when_it_changes() {
+        let templates_path = site();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.enable_hot_reload();
+        assert!(engine.render_public("about", &body("x")).is_err());
+
+        fs::write(templates_path.join("runic.manifest"), "home -> pages/home.html\nabout -> pages/about.html\n").unwrap();
+        assert_eq!(engine.render_public("about", &body("x")).unwrap(), "<p>About x</p>");
+        assert!(engine.render_public("pages/about.html", &body("x")).is_err());
+
+        // Without hot reload the manifest changes on reload_changed_templates
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        assert!(engine.render_public("home", &body("x")).is_ok());
+        fs::write(templates_path.join("runic.manifest"), "about -> pages/about.html\n").unwrap();
+        assert!(engine.render_public("home", &body("x")).is_ok());
+        engine.reload_changed_templates();
+        assert!(engine.render_public("home", &body("x")).is_err());
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_manifest_parse_errors_name_the_line() {
+        let error = TemplateManifest::parse("home -> pages/home.html\n\nhome -> other.html\n").unwrap_err();
+        assert!(error.to_string().contains("line 3"), "{}", error);
+        let error = TemplateManifest::parse("home -> pages/home.html cache=300").unwrap_err();
+        assert!(error.to_string().contains("unknown option 'cache=300'"), "{}", error);
+        assert!(TemplateManifest::parse("home ->").is_err());
+        assert!(TemplateManifest::parse("home -> pages/home.html cache_ttl=soon").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "i18n")]
+    fn test_locale_option_renders_in_that_locale() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("greeting.html"), "{{t \"hello\"}}").unwrap();
+        fs::write(templates_path.join("runic.manifest"), "accueil -> greeting.html locale=fr\nwelcome -> greeting.html\n").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.set_translations("en", HashMap::from([("hello".to_string(), "Hello".to_string())]));
+        engine.set_translations("fr", HashMap::from([("hello".to_string(), "Bonjour".to_string())]));
+        engine.set_locale("en");
+
+        assert_eq!(engine.render_public("accueil", &TemplateContext::new()).unwrap(), "Bonjour");
+        assert_eq!(engine.render_public("welcome", &TemplateContext::new()).unwrap(), "Hello");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Encoded output from `render_bytes`
+#[cfg(test)]
+mod output_encoding_tests {
+    use super::*;
+    use mystical_runic::{CharEncoder, OutputEncoding, TemplateError};
+
+    /// Seven-bit ASCII, standing in for an application-provided encoding
+    struct Ascii;
+
+    impl CharEncoder for Ascii {
+        fn name(&self) -> &str {
+            "ASCII"
+        }
+
+        fn encode_char(&self, ch: char, out: &mut Vec<u8>) -> bool {
+            ch.is_ascii() && {
+                out.push(ch as u8);
+                true
+            }
+        }
+    }
+
+    fn engine_with_export() -> (TemplateEngine, PathBuf) {
+        engine_with_templates(&[("export.txt", "{{name}};{{city}}\nTotal: {{amount}} €")])
+    }
+
+    fn export_context(name: &str) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("name", name);
+        context.set_string("city", "Besançon");
+        context.set_number("amount", 12);
+        context
+    }
+
+    fn latin1_to_string(bytes: &[u8]) -> String {
+        bytes.iter().map(|&byte| byte as char).collect()
+    }
+
+    #[test]
+    fn test_accented_characters_round_trip_through_latin1() {
+        let (mut engine, templates_path) = engine_with_export();
+        let encoding = OutputEncoding::latin1().with_substitute("EUR");
+
+        let bytes = engine.render_bytes("export.txt", &export_context("Zoë Müller"), encoding).unwrap();
+        assert!(bytes.starts_with(b"Zo\xEB M\xFCller;Besan\xE7on\n"));
+        assert_eq!(latin1_to_string(&bytes), "Zoë Müller;Besançon\nTotal: 12 EUR");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_strict_encoding_reports_the_character_and_its_position() {
+        let (mut engine, templates_path) = engine_with_export();
+
+        let error = engine.render_bytes("export.txt", &export_context("Zoë 🎉"), OutputEncoding::latin1()).unwrap_err();
+        match &error {
+            TemplateError::Encoding { encoding, character, line, column } => {
+                assert_eq!(encoding, "ISO-8859-1");
+                assert_eq!(*character, '🎉');
+                assert_eq!((*line, *column), (1, 5));
+            }
+            other => panic!("expected an encoding error, got {:?}", other),
+        }
+        assert_eq!(error.kind(), "encoding");
+        assert_eq!(
+            error.to_string(),
+            "Character '🎉' (U+1F389) at output line 1, column 5 cannot be encoded as ISO-8859-1"
+        );
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_utf8_is_the_default_and_custom_encoders_plug_in() {
+        let (mut engine, templates_path) = engine_with_export();
+        let context = export_context("Zoë 🎉");
+
+        let bytes = engine.render_bytes("export.txt", &context, OutputEncoding::default()).unwrap();
+        assert_eq!(bytes, engine.render("export.txt", &context).unwrap().into_bytes());
+
+        let ascii = OutputEncoding::custom(Ascii).with_substitute("?");
+        let mut buffer = Vec::new();
+        engine.render_bytes_into("export.txt", &context, &ascii, &mut buffer).unwrap();
+        assert_eq!(buffer, b"Zo? ?;Besan?on\nTotal: 12 ?");
+
+        let error = engine.render_bytes("export.txt", &context, OutputEncoding::custom(Ascii)).unwrap_err();
+        assert!(error.to_string().contains("cannot be encoded as ASCII"));
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Tests for choosing a template's layout at render time
+#[cfg(all(test, feature = "inheritance"))]
+mod layout_selection_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+
+    fn setup() -> (std::path::PathBuf, TemplateEngine) {
+        let (engine, templates_path) = engine_with_templates(&[
+            ("base.html", "<html><nav>menu</nav>{{block content}}empty{{/block}}<footer>{{block footer}}(c){{/block}}</footer></html>"),
+            ("bare.html", "<div class=\"embed\">{{block content}}{{/block}}</div>"),
+            ("article.html", "{{extends \"base.html\"}}{{block content}}<h1>{{title}}</h1>{{/block}}{{block footer}}by {{author}}{{/block}}"),
+            ("chooser.html", "{{extends layout_name}}{{block content}}<h1>{{title}}</h1>{{/block}}"),
+        ]);
+        (templates_path, engine)
+    }
+
+    fn article(layout_name: &str) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("title", "News");
+        context.set_string("author", "Ada");
+        context.set_string("layout_name", layout_name);
+        context
+    }
+
+    #[test]
+    fn test_render_with_layout_replaces_the_declared_parent() {
+        let (templates_path, mut engine) = setup();
+        let context = article("");
+
+        assert_eq!(
+            engine.render("article.html", &context).unwrap(),
+            "<html><nav>menu</nav><h1>News</h1><footer>by Ada</footer></html>"
+        );
+        assert_eq!(
+            engine.render_with_layout("article.html", &context, Some("bare.html")).unwrap(),
+            "<div class=\"embed\"><h1>News</h1></div>"
+        );
+        // The override lasts for one render only
+        assert_eq!(
+            engine.render("article.html", &context).unwrap(),
+            "<html><nav>menu</nav><h1>News</h1><footer>by Ada</footer></html>"
+        );
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_render_without_a_layout_keeps_the_blocks_standalone() {
+        let (templates_path, mut engine) = setup();
+        assert_eq!(
+            engine.render_with_layout("article.html", &article(""), None).unwrap(),
+            "<h1>News</h1>by Ada"
+        );
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_dynamic_extends_picks_a_parent_per_render() {
+        let (templates_path, mut engine) = setup();
+        engine.allow_dynamic_includes(["b*.html"]);
+
+        assert_eq!(
+            engine.render("chooser.html", &article("base.html")).unwrap(),
+            "<html><nav>menu</nav><h1>News</h1><footer>(c)</footer></html>"
+        );
+        assert_eq!(engine.render("chooser.html", &article("bare.html")).unwrap(), "<div class=\"embed\"><h1>News</h1></div>");
+        assert_eq!(
+            engine.render_with_layout("chooser.html", &article("bare.html"), Some("base.html")).unwrap(),
+            "<html><nav>menu</nav><h1>News</h1><footer>(c)</footer></html>"
+        );
+
+        let error = engine.render("chooser.html", &article("../secret.html")).unwrap_err();
+        assert!(matches!(error, TemplateError::Security(_)), "{:?}", error);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_layout_names_are_validated_and_tracked_for_reload() {
+        let (templates_path, mut engine) = setup();
+        let context = article("");
+
+        assert!(engine.render_with_layout("article.html", &context, Some("../etc/passwd")).is_err());
+
+        engine.render_with_layout("article.html", &context, Some("bare.html")).unwrap();
+        fs::write(templates_path.join("bare.html"), "<section>{{block content}}{{/block}}</section>").unwrap();
+        let reloaded = engine.reload_templates(&["bare.html"]);
+        assert!(reloaded.contains(&"article.html".to_string()), "{:?}", reloaded);
+        assert_eq!(
+            engine.render_with_layout("article.html", &context, Some("bare.html")).unwrap(),
+            "<section><h1>News</h1></section>"
+        );
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Provenance marker tests
+#[cfg(test)]
+mod provenance_tests {
+    use super::*;
+    use mystical_runic::{CommentStyle, ProvenanceOptions};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn engine_with(files: &[(&str, &str)]) -> (PathBuf, TemplateEngine) {
+        let (mut engine, temp_dir) = engine_with_templates(files);
+        engine.set_deterministic(true, 7);
+        engine.set_fixed_time(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        engine.set_provenance(ProvenanceOptions::default());
+        (temp_dir, engine)
+    }
+
+    #[test]
+    fn test_provenance_marks_html_and_yaml_in_their_comment_syntax() {
+        let (_dir, mut engine) = engine_with(&[
+            ("pages/home.html", "<!DOCTYPE html>\n<h1>{{title}}</h1>"),
+            ("config/app.yaml", "name: {{title}}\n"),
+            ("bundle.js", "const name = \"{{title}}\";"),
+            ("notes.txt", "{{title}}"),
+        ]);
+        let mut context = TemplateContext::new();
+        context.set_string("title", "Runes");
+        let version = env!("CARGO_PKG_VERSION");
+
+        assert_eq!(
+            engine.render("pages/home.html", &context).unwrap(),
+            format!("<!-- Generated from pages/home.html by mystical-runic {} at 1700000000; do not edit -->\n<!DOCTYPE html>\n<h1>Runes</h1>", version)
+        );
+        assert_eq!(
+            engine.render("config/app.yaml", &context).unwrap(),
+            format!("# Generated from config/app.yaml by mystical-runic {} at 1700000000; do not edit\nname: Runes\n", version)
+        );
+        assert!(engine.render("bundle.js", &context).unwrap().starts_with("// Generated from bundle.js"));
+        // No comment style for .txt, and no template name for render_string
+        assert_eq!(engine.render("notes.txt", &context).unwrap(), "Runes");
+        assert_eq!(engine.render_string("{{title}}", &context).unwrap(), "Runes");
+
+        engine.clear_provenance();
+        assert_eq!(engine.render("config/app.yaml", &context).unwrap(), "name: Runes\n");
+    }
+
+    #[test]
+    fn test_provenance_goes_after_xml_declarations_and_shebangs() {
+        let (_dir, mut engine) = engine_with(&[
+            ("feed.xml", "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<feed>{{title}}</feed>"),
+            ("deploy.sh", "#!/bin/sh\necho {{title}}\n"),
+        ]);
+        engine.set_provenance(ProvenanceOptions::default().marker("From {{template}}"));
+        let mut context = TemplateContext::new();
+        context.set_string("title", "Runes");
+
+        assert_eq!(
+            engine.render("feed.xml", &context).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!-- From feed.xml -->\n<feed>Runes</feed>"
+        );
+        assert_eq!(engine.render("deploy.sh", &context).unwrap(), "#!/bin/sh\n# From deploy.sh\necho Runes\n");
+    }
+
+    #[test]
+    fn test_provenance_marker_and_comment_styles_are_configurable() {
+        let (_dir, mut engine) = engine_with(&[
+            ("site.nginx", "server {}"),
+            ("page.html", "<p></p>"),
+            ("style.css", "p {}"),
+        ]);
+        engine.set_provenance(ProvenanceOptions::default()
+            .marker("Generated by v{{version}}\nEdit templates/{{template}} instead")
+            .comment_style(".nginx", CommentStyle::line("#"))
+            .without_extension("html"));
+
+        assert_eq!(
+            engine.render("site.nginx", &TemplateContext::new()).unwrap(),
+            format!("# Generated by v{}\n# Edit templates/site.nginx instead\nserver {{}}", env!("CARGO_PKG_VERSION"))
+        );
+        assert_eq!(engine.render("page.html", &TemplateContext::new()).unwrap(), "<p></p>");
+        assert!(engine.render("style.css", &TemplateContext::new()).unwrap().starts_with("/* Generated by v"));
+        assert_eq!(engine.provenance().unwrap().style_for("a.CSS"), Some(&CommentStyle::block("/*", "*/")));
+    }
+
+    #[test]
+    fn test_no_provenance_directive_opts_a_template_out() {
+        let (_dir, mut engine) = engine_with(&[
+            ("sitemap.xml", "{{no_provenance}}\n<?xml version=\"1.0\"?>\n<urlset/>"),
+            ("layout.html", "{{no_provenance}}<main>{{block content}}{{/block}}</main>"),
+            ("page.html", "{{extends \"layout.html\"}}{{block content}}Hi{{/block}}"),
+            ("with_partial.html", "<div>{{include \"partial.html\"}}</div>"),
+            ("partial.html", "{{no_provenance}}x"),
+        ]);
+
+        assert_eq!(engine.render("sitemap.xml", &TemplateContext::new()).unwrap(), "<?xml version=\"1.0\"?>\n<urlset/>");
+        if cfg!(feature = "inheritance") {
+            assert_eq!(engine.render("page.html", &TemplateContext::new()).unwrap(), "<main>Hi</main>");
+        }
+        // A partial doesn't decide for the page it is included in
+        let output = engine.render("with_partial.html", &TemplateContext::new()).unwrap();
+        assert!(output.starts_with("<!-- Generated from with_partial.html"), "{}", output);
+        assert!(output.ends_with("<div>x</div>"), "{}", output);
+    }
+
+    #[test]
+    fn test_manifest_provenance_off_opts_a_public_template_out() {
+        let (_dir, mut engine) = engine_with(&[
+            ("runic.manifest", "home -> pages/home.html\nfeed -> feed.xml provenance=off\n"),
+            ("pages/home.html", "<p>home</p>"),
+            ("feed.xml", "<feed/>"),
+        ]);
+
+        assert_eq!(engine.render_public("feed", &TemplateContext::new()).unwrap(), "<feed/>");
+        assert!(engine.render_public("home", &TemplateContext::new()).unwrap().starts_with("<!-- Generated from pages/home.html"));
+        // The option only applies to that render
+        assert!(engine.render("feed.xml", &TemplateContext::new()).unwrap().starts_with("<!-- Generated from feed.xml"));
+        assert!(engine.provenance().is_some());
+
+        let error = mystical_runic::TemplateManifest::parse("feed provenance=maybe").unwrap_err();
+        assert!(error.to_string().contains("provenance=on|off"), "{}", error);
+    }
+}
+
+/// Raw include and streaming render tests
+#[cfg(test)]
+mod raw_include_tests {
+    use super::*;
+    use mystical_runic::{RawIncludeOptions, TemplateError};
+    use std::io::{self, Write};
+
+    /// Records every write, to check how much is written at once
+    #[derive(Default)]
+    struct RecordingWriter {
+        bytes: Vec<u8>,
+        largest_write: usize,
+    }
+
+    impl Write for RecordingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.largest_write = self.largest_write.max(buf.len());
+            self.bytes.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// About 5 MB of SVG symbols
+    fn sprite() -> String {
+        let mut sprite = String::from("<svg xmlns=\"http://www.w3.org/2000/svg\">\n");
+        for index in 0..100_000 {
+            sprite.push_str(&format!("<symbol id=\"icon-{}\"><path d=\"M0 0h{}v24H0z\"/></symbol>\n", index, index % 97));
+        }
+        sprite.push_str("</svg>\n");
+        sprite
+    }
+
+    #[test]
+    fn test_include_raw_outputs_files_unrendered_and_unescaped() {
+        let (mut engine, _dir) = engine_with_templates(&[
+            ("page.html", "<ul>{{for item in items}}<li>{{include_raw \"icons/star.svg\"}}{{item}}</li>{{/for}}</ul>"),
+            ("icons/star.svg", "<svg>{{not a tag}} & co</svg>"),
+            ("bad.html", "{{include_raw \"../secret.txt\"}}"),
+            ("missing.html", "{{include_raw \"nowhere.svg\"}}"),
+            ("unquoted.html", "{{include_raw path}}"),
+        ]);
+        let mut context = TemplateContext::new();
+        context.set("items", TemplateValue::Array(vec![TemplateValue::Number(1), TemplateValue::Number(2)]));
+
+        assert_eq!(
+            engine.render("page.html", &context).unwrap(),
+            "<ul><li><svg>{{not a tag}} & co</svg>1</li><li><svg>{{not a tag}} & co</svg>2</li></ul>"
+        );
+        assert_eq!(
+            engine.render_string("[{{include_raw \"icons/star.svg\"}}]", &context).unwrap(),
+            "[<svg>{{not a tag}} & co</svg>]"
+        );
+        assert!(matches!(engine.render("bad.html", &context), Err(TemplateError::Security(_))));
+        assert!(engine.render("missing.html", &context).unwrap_err().to_string().contains("nowhere.svg"));
+        assert!(engine.render("unquoted.html", &context).is_err());
+        assert!(engine.check_syntax("{{include_raw path}}").is_err());
+    }
+
+    #[test]
+    fn test_values_cannot_forge_raw_includes() {
+        let (mut engine, _dir) = engine_with_templates(&[
+            ("page.html", "<p>{{bio}}</p>{{& bio}}{{include_raw \"logo.svg\"}}"),
+            ("internal.html", "secret"),
+            ("logo.svg", "<svg/>"),
+        ]);
+        let forged = "\u{E000}=internal.html\u{E001}";
+        let mut context = TemplateContext::new();
+        context.set_string("bio", forged);
+
+        let expected = format!("<p>{}</p>{}<svg/>", forged, forged);
+        assert_eq!(engine.render("page.html", &context).unwrap(), expected);
+        let mut writer = RecordingWriter::default();
+        engine.render_to_writer("page.html", &context, &mut writer).unwrap();
+        assert_eq!(writer.bytes, expected.as_bytes());
+    }
+
+    #[test]
+    fn test_render_to_writer_streams_large_raw_includes() {
+        let sprite = sprite();
+        assert!(sprite.len() > 4 * 1024 * 1024);
+        let threshold = 64 * 1024;
+        let (mut engine, _dir) = engine_with_templates(&[
+            ("index.html", "<body>{{include_raw \"sprite.svg\"}}<h1>{{title}}</h1>{{include_raw \"logo.svg\"}}</body>"),
+            ("sprite.svg", &sprite),
+            ("logo.svg", "<svg id=\"logo\"/>"),
+        ]);
+        engine.set_raw_include_options(RawIncludeOptions::default().passthrough_threshold(threshold));
+        let mut context = TemplateContext::new();
+        context.set_string("title", "Icons");
+
+        let mut writer = RecordingWriter::default();
+        engine.render_to_writer("index.html", &context, &mut writer).unwrap();
+        assert!(writer.largest_write <= threshold as usize, "wrote {} bytes at once", writer.largest_write);
+        // The streamed file never entered the cache, the small one did
+        assert!(!engine.is_template_cached("sprite.svg"));
+        assert!(engine.is_template_cached("logo.svg"));
+
+        let buffered = engine.render("index.html", &context).unwrap();
+        assert_eq!(writer.bytes, buffered.as_bytes());
+        assert!(buffered.starts_with("<body><svg xmlns"));
+        assert!(buffered.ends_with("</svg>\n<h1>Icons</h1><svg id=\"logo\"/></body>"));
+
+        // Cached by the string render, still streamed from disk
+        let mut writer = RecordingWriter::default();
+        engine.render_to_writer("index.html", &context, &mut writer).unwrap();
+        assert!(writer.largest_write <= threshold as usize);
+        assert_eq!(writer.bytes, buffered.as_bytes());
+    }
+
+    #[test]
+    fn test_raw_includes_over_the_size_limit_fail() {
+        let (mut engine, _dir) = engine_with_templates(&[
+            ("page.html", "{{include_raw \"data.json\"}}"),
+            ("data.json", &format!("[{}]", "0,".repeat(2000))),
+        ]);
+        engine.set_raw_include_options(RawIncludeOptions::default().passthrough_threshold(100).max_size(Some(1000)));
+
+        let error = engine.render("page.html", &TemplateContext::new()).unwrap_err();
+        assert!(matches!(error, TemplateError::Security(_)), "{}", error);
+        let mut writer = RecordingWriter::default();
+        assert!(engine.render_to_writer("page.html", &TemplateContext::new(), &mut writer).is_err());
+        assert!(writer.bytes.is_empty());
+
+        engine.set_raw_include_options(RawIncludeOptions::default().max_size(None));
+        assert_eq!(engine.render("page.html", &TemplateContext::new()).unwrap().len(), 4002);
+    }
+
+    #[test]
+    fn test_large_raw_includes_can_stay_out_of_the_cache() {
+        let big = "x".repeat(10_000);
+        for cache_large_files in [true, false] {
+            let (mut engine, _dir) = engine_with_templates(&[("page.html", "{{include_raw \"big.txt\"}}"), ("big.txt", &big)]);
+            engine.set_template_cache_capacity(Some(1));
+            engine.set_raw_include_options(RawIncludeOptions::default()
+                .passthrough_threshold(1024)
+                .cache_large_files(cache_large_files));
+
+            // Cached, the asset takes the only cache slot from the page
+            assert_eq!(engine.render("page.html", &TemplateContext::new()).unwrap(), big);
+            assert_eq!(engine.is_template_cached("big.txt"), cache_large_files);
+            assert_eq!(engine.is_template_cached("page.html"), !cache_large_files);
+        }
+    }
+}
+
+/// Test data factory tests
+#[cfg(test)]
+mod value_factory_tests {
+    use super::*;
+    use mystical_runic::{ArrayFactory, ValueFactory};
+
+    /// Structural equality, `TemplateValue` has no `PartialEq`
+    fn same(a: &TemplateValue, b: &TemplateValue) -> bool {
+        match (a, b) {
+            (TemplateValue::String(a), TemplateValue::String(b)) => a == b,
+            (TemplateValue::Number(a), TemplateValue::Number(b)) => a == b,
+            (TemplateValue::Float(a), TemplateValue::Float(b)) => a == b,
+            (TemplateValue::Bool(a), TemplateValue::Bool(b)) => a == b,
+            (TemplateValue::Array(a), TemplateValue::Array(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b)),
+            (TemplateValue::Object(a), TemplateValue::Object(b)) => {
+                a.len() == b.len() && a.iter().all(|(key, a)| b.get(key).is_some_and(|b| same(a, b)))
+            }
+            _ => false,
+        }
+    }
+
+    fn users(seed: u64) -> TemplateValue {
+        ValueFactory::object()
+            .seed(seed)
+            .value("users", ValueFactory::repeat(50, |i, user| user
+                .string("name", format!("User {}", i))
+                .lorem("bio", 12)
+                .int_range("score", 0..100, 7)))
+            .lorem("motto", 5)
+            .build()
+    }
+
+    #[test]
+    fn test_factory_builds_nested_values() {
+        let value = ValueFactory::object()
+            .string("name", "Ada")
+            .number("age", 36)
+            .float("height", 1.7)
+            .bool("admin", true)
+            .array("tags", |tags| tags.push_string("admin").push_number(1).push_object(|tag| tag.string("label", "x")))
+            .nested("address", |address| address.string("city", "London"))
+            .build();
+
+        let mut expected = HashMap::new();
+        expected.insert("name".to_string(), TemplateValue::String("Ada".to_string()));
+        expected.insert("age".to_string(), TemplateValue::Number(36));
+        expected.insert("height".to_string(), TemplateValue::Float(1.7));
+        expected.insert("admin".to_string(), TemplateValue::Bool(true));
+        let mut label = HashMap::new();
+        label.insert("label".to_string(), TemplateValue::String("x".to_string()));
+        expected.insert("tags".to_string(), TemplateValue::Array(vec![
+            TemplateValue::String("admin".to_string()),
+            TemplateValue::Number(1),
+            TemplateValue::Object(label),
+        ]));
+        let mut address = HashMap::new();
+        address.insert("city".to_string(), TemplateValue::String("London".to_string()));
+        expected.insert("address".to_string(), TemplateValue::Object(address));
+        assert!(same(&value, &TemplateValue::Object(expected)));
+
+        assert!(same(
+            &ArrayFactory::new().push_bool(false).push_float(0.5).build(),
+            &TemplateValue::Array(vec![TemplateValue::Bool(false), TemplateValue::Float(0.5)])
+        ));
+    }
+
+    #[test]
+    fn test_factory_fillers_are_deterministic_for_a_seed() {
+        assert!(same(&users(42), &users(42)));
+        assert!(!same(&users(42), &users(43)));
+
+        let TemplateValue::Object(fields) = users(42) else { panic!("not an object") };
+        let TemplateValue::Array(users) = &fields["users"] else { panic!("not an array") };
+        let field = |index: usize, key: &str| match &users[index] {
+            TemplateValue::Object(user) => user[key].clone(),
+            other => panic!("not an object: {:?}", other),
+        };
+        for index in 0..users.len() {
+            let TemplateValue::Number(score) = field(index, "score") else { panic!("not a number") };
+            assert!((0..100).contains(&score));
+            let TemplateValue::String(bio) = field(index, "bio") else { panic!("not a string") };
+            assert_eq!(bio.split(' ').count(), 12);
+            assert!(bio.starts_with(char::is_uppercase) && bio.ends_with('.'), "{}", bio);
+        }
+        // Each repeated object has its own seed
+        assert!(!same(&field(0, "bio"), &field(1, "bio")));
+        let scores: std::collections::HashSet<String> = (0..users.len()).map(|index| format!("{:?}", field(index, "score"))).collect();
+        assert!(scores.len() > 10);
+
+        let pinned = ValueFactory::object().int_range("empty", 5..5, 3).lorem("none", 0).build();
+        let mut expected = HashMap::new();
+        expected.insert("empty".to_string(), TemplateValue::Number(5));
+        expected.insert("none".to_string(), TemplateValue::String(String::new()));
+        assert!(same(&pinned, &TemplateValue::Object(expected)));
+    }
+
+    #[test]
+    fn test_factory_contexts_render() {
+        let context = ValueFactory::object()
+            .string("title", "Team")
+            .value("users", ValueFactory::repeat(3, |i, user| user.string("name", format!("User {}", i))))
+            .build_context();
+        let mut engine = TemplateEngine::new(".");
+        assert_eq!(
+            engine.render_string("{{title}}:{{for user in users}} {{user.name}}{{/for}}", &context).unwrap(),
+            "Team: User 0 User 1 User 2"
+        );
+    }
+}
+
+/// `{{include "partial" with name=value}}` and `only`
+#[cfg(test)]
+mod scoped_include_tests {
+    use super::*;
+    use mystical_runic::ast;
+
+    fn engine_with_partials() -> (TemplateEngine, PathBuf) {
+        engine_with_templates(&[
+            ("card.html", "<h2>{{title}}</h2><p>{{price}}</p><i>{{site}}</i>"),
+            ("badge.html", "[{{label|upper}}]"),
+            ("row.html", "{{include \"badge.html\" with label=name only}}"),
+        ])
+    }
+
+    fn context() -> TemplateContext {
+        let mut product = HashMap::new();
+        product.insert("name".to_string(), TemplateValue::String("Lamp".to_string()));
+        product.insert("price".to_string(), TemplateValue::Number(30));
+        let mut context = TemplateContext::new();
+        context.set("product", TemplateValue::Object(product.clone()));
+        context.set("products", TemplateValue::Array(vec![TemplateValue::Object(product)]));
+        context.set("site", "Shop");
+        context.set("title", "Outer");
+        context
+    }
+
+    #[test]
+    fn test_with_arguments_become_the_partial_context() {
+        let (mut engine, templates_path) = engine_with_partials();
+        let context = context();
+        for (template, expected) in [
+            ("{{include \"card.html\" with title=product.name price=product.price}}", "<h2>Lamp</h2><p>30</p><i>Shop</i>"),
+            ("{{include \"card.html\" with title=\"<Sale>\" price=12.5 only}}", "<h2>&lt;Sale&gt;</h2><p>12.5</p><i></i>"),
+            ("{{include \"card.html\" with title=product.name|upper price=product.missing}}", "<h2>LAMP</h2><p></p><i>Shop</i>"),
+            ("{{include \"card.html\" only}}", "<h2></h2><p></p><i></i>"),
+            ("{{include \"card.html\"}}", "<h2>Outer</h2><p></p><i>Shop</i>"),
+            ("{{for item in products}}{{include \"card.html\" with title=item.name price=item.price}}{{/for}}", "<h2>Lamp</h2><p>30</p><i>Shop</i>"),
+            ("{{include \"row.html\" with name=product.name}}", "[LAMP]"),
+        ] {
+            assert_eq!(engine.render_string(template, &context).unwrap(), expected, "{}", template);
+        }
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_unresolved_or_positional_arguments_fail() {
+        let (mut engine, templates_path) = engine_with_partials();
+        let context = context();
+
+        let error = engine.render_string("{{include \"card.html\" with title=item.name}}", &context).unwrap_err();
+        assert!(error.to_string().contains("variable 'item' of its arguments is not set"), "{}", error);
+        let error = engine.render_string("{{include \"card.html\" with product.name}}", &context).unwrap_err();
+        assert!(error.to_string().contains("include arguments are name=value"), "{}", error);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_scoped_includes_in_usage_and_ast() {
+        let (mut engine, templates_path) = engine_with_partials();
+        fs::write(templates_path.join("page.html"), "{{include \"badge.html\" with label=product.name only}}").unwrap();
+
+        let usage = engine.analyze_context_usage("page.html").unwrap();
+        assert!(usage.paths.contains("product.name"));
+
+        let template = engine.parse("{{include \"card.html\" from \"vendor\" with title=product.name price=3 only}}").unwrap();
+        let ast::Node::Include(include) = &template.nodes[0] else { panic!("expected an include") };
+        assert_eq!(include.target, "card.html");
+        assert_eq!(include.from.as_deref(), Some("vendor"));
+        assert_eq!(include.arguments, vec![("title".to_string(), "product.name".to_string()), ("price".to_string(), "3".to_string())]);
+        assert!(include.only);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_argument_values_are_not_rendered_as_tags() {
+        let (mut engine, templates_path) = engine_with_partials();
+        let mut context = context();
+        context.set("secret", "hunter2");
+        context.set("teaser", "{{secret}}");
+
+        for template in [
+            "{{include \"card.html\" with title=teaser}}",
+            "{{include \"card.html\" with title=teaser only}}",
+            "{{for item in products}}{{include \"card.html\" with title=teaser only}}{{/for}}",
+        ] {
+            let result = engine.render_string(template, &context).unwrap();
+            assert!(result.contains("<h2>{{secret}}</h2>"), "{}: {}", template, result);
+            assert!(!result.contains("hunter2"), "{}: {}", template, result);
+        }
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[cfg(feature = "debug-tools")]
+    #[test]
+    fn test_debug_render_puts_scoped_includes_back() {
+        let (mut engine, templates_path) = engine_with_partials();
+        engine.enable_debug_mode();
+
+        let result = engine.render_string_with_debug("{{include \"card.html\" with title=\"Sale\" only}}", &context()).unwrap();
+        assert!(result.output.contains("<h2>Sale</h2>"), "{:?}", result.output);
+        assert!(!result.output.contains('\u{E000}'), "{:?}", result.output);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Circular includes and the include depth limit
+#[cfg(test)]
+mod include_cycle_tests {
+    use super::*;
+
+    #[test]
+    fn test_circular_includes_name_the_circle() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("a.html"), "A{{include \"b.html\"}}").unwrap();
+        fs::write(templates_path.join("b.html"), "B{{include \"a.html\"}}").unwrap();
+        fs::write(templates_path.join("self.html"), "{{for name in names}}{{include name}}{{/for}}").unwrap();
+        fs::write(templates_path.join("twice.html"), "{{include \"leaf.html\"}}{{include \"leaf.html\"}}").unwrap();
+        fs::write(templates_path.join("leaf.html"), "leaf").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.allow_dynamic_includes(["*.html"]);
+        let mut context = TemplateContext::new();
+        context.set("names", vec![TemplateValue::from("self.html")]);
+
+        let error = engine.render("a.html", &context).unwrap_err();
+        assert!(error.to_string().contains("Circular include detected: a.html -> b.html -> a.html"), "{}", error);
+        let error = engine.render_string("{{include \"b.html\"}}", &context).unwrap_err();
+        assert!(error.to_string().contains("Circular include detected: b.html -> a.html -> b.html"), "{}", error);
+        let error = engine.render("self.html", &context).unwrap_err();
+        assert!(error.to_string().contains("Circular include detected: self.html -> self.html"), "{}", error);
+
+        // The same template may be included more than once, just not within itself
+        assert_eq!(engine.render("twice.html", &context).unwrap(), "leafleaf");
+        assert_eq!(engine.render_string("{{include \"twice.html\"}}", &context).unwrap(), "leafleaf");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_max_include_depth() {
+        let templates_path = create_temp_dir();
+        for level in 1..5 {
+            fs::write(templates_path.join(format!("level{}.html", level)), format!("{}{{{{include \"level{}.html\"}}}}", level, level + 1)).unwrap();
+        }
+        fs::write(templates_path.join("level5.html"), "5").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        let context = TemplateContext::new();
+        assert_eq!(engine.get_max_include_depth(), 64);
+        assert_eq!(engine.render("level1.html", &context).unwrap(), "12345");
+
+        engine.set_max_include_depth(4);
+        assert_eq!(engine.render("level1.html", &context).unwrap(), "12345");
+        engine.set_max_include_depth(3);
+        let error = engine.render("level1.html", &context).unwrap_err();
+        assert!(error.to_string().contains("Maximum include depth of 3 exceeded by 'level5.html'"), "{}", error);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// `{{super}}` chains through layouts and its placement
+#[cfg(feature = "inheritance")]
+#[cfg(test)]
+mod super_block_tests {
+    use super::*;
+
+    #[test]
+    fn test_super_chains_to_the_root_layout() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("base.html"), "[{{block body}}B{{/block}}]").unwrap();
+        fs::write(templates_path.join("child.html"), "{{extends \"base.html\"}}{{block body}}{{super}}C{{/block}}").unwrap();
+        fs::write(templates_path.join("grandchild.html"), "{{extends \"child.html\"}}{{block body}}{{ super }}G{{-super-}}{{/block}}").unwrap();
+        fs::write(templates_path.join("root.html"), "[{{block body}}{{super}}R{{/block}}]").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        let context = TemplateContext::new();
+
+        assert_eq!(engine.render("child.html", &context).unwrap(), "[BC]");
+        assert_eq!(engine.render("grandchild.html", &context).unwrap(), "[BCGBC]");
+        // A root layout block has no parent content
+        assert_eq!(engine.render("root.html", &context).unwrap(), "[R]");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_super_outside_a_block_is_a_parse_error() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("base.html"), "[{{block body}}B{{/block}}]").unwrap();
+        fs::write(templates_path.join("child.html"), "{{extends \"base.html\"}}{{super}}{{block body}}C{{/block}}").unwrap();
+        fs::write(templates_path.join("broken_base.html"), "{{super}}[{{block body}}B{{/block}}]").unwrap();
+        fs::write(templates_path.join("page.html"), "{{extends \"broken_base.html\"}}{{block body}}P{{/block}}").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        let context = TemplateContext::new();
+
+        for result in [
+            engine.render("child.html", &context),
+            engine.render("page.html", &context),
+            engine.render_string("Hello {{super}}", &context),
+        ] {
+            let error = result.unwrap_err();
+            assert!(matches!(error.root_cause(), mystical_runic::TemplateError::Parse(_)), "{:?}", error);
+            assert!(error.to_string().contains("Unexpected {{super}} outside a {{block}}"), "{}", error);
+        }
+        assert!(engine.check_syntax("{{super}}").is_err());
+        assert!(engine.check_syntax("{{block body}}{{if a}}{{super}}{{/if}}{{/block}}").is_ok());
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Blocks nested in other blocks across an extends chain
+#[cfg(feature = "inheritance")]
+#[cfg(test)]
+mod nested_block_tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_blocks_are_overridable_at_any_depth() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("base.html"), "<head>{{block head}}<title>{{block title}}Site{{/block}}</title>{{/block head}}</head>{{block body}}{{/block}}").unwrap();
+        fs::write(templates_path.join("section.html"), "{{extends \"base.html\"}}{{block body}}<main>{{block content}}{{block lead}}Lead{{/block}}Section{{/block content}}</main>{{/block}}").unwrap();
+        fs::write(templates_path.join("page.html"), "{{extends \"section.html\"}}{{block title}}Page - {{super}}{{/block}}{{block lead}}Intro{{/block}}").unwrap();
+        // A block nested in a child's override is an override too
+        fs::write(templates_path.join("wrapped.html"), "{{extends \"page.html\"}}{{block head}}<meta>{{block title}}Wrapped{{/block}}{{/block}}").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        let context = TemplateContext::new();
+
+        assert_eq!(engine.render("page.html", &context).unwrap(), "<head><title>Page - Site</title></head><main>IntroSection</main>");
+        assert_eq!(engine.render("wrapped.html", &context).unwrap(), "<head><meta>Wrapped</head><main>IntroSection</main>");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_mismatched_block_tags_are_parse_errors() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("mismatch.html"), "{{block outer}}{{block inner}}x{{/block outer}}{{/block}}").unwrap();
+        fs::write(templates_path.join("stray.html"), "{{block outer}}x{{/block}}{{/block}}").unwrap();
+        fs::write(templates_path.join("unclosed.html"), "{{block outer}}{{block inner}}x{{/block}}").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        let context = TemplateContext::new();
+
+        for (template, message) in [
+            ("mismatch.html", "'{{/block outer}}' does not match '{{block inner}}'"),
+            ("stray.html", "Unexpected '{{/block}}' without an open {{block}}"),
+            ("unclosed.html", "Missing {{/block}} for block 'outer'"),
+        ] {
+            let error = engine.render(template, &context).unwrap_err();
+            assert!(error.to_string().contains(message), "{}: {}", template, error);
+        }
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
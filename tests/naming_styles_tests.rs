@@ -140,4 +140,127 @@ mod interoperability_tests {
         assert_eq!(std::mem::size_of_val(&conventional_context), std::mem::size_of_val(&mystical_scroll));
         assert_eq!(std::mem::size_of_val(&conventional_value), std::mem::size_of_val(&mystical_symbol));
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod mystical_module_tests {
+    // The mystical module must keep up with the conventional API: these tests
+    // walk the sources for public items and fail when one has no mystical name
+    use mystical_runic::mystical::*;
+    use mystical_runic::{TemplateEngine, TemplateContext};
+    use std::collections::HashMap;
+
+    /// Names of the `pub fn`s declared in `impl <type_name> {` blocks
+    fn public_methods(type_name: &str, sources: &[&str]) -> Vec<String> {
+        let header = format!("impl {} {{", type_name);
+        let mut methods = Vec::new();
+        for source in sources {
+            let mut in_impl = false;
+            for line in source.lines() {
+                if line.starts_with(&header) {
+                    in_impl = true;
+                } else if in_impl && line.starts_with('}') {
+                    in_impl = false;
+                } else if let Some(rest) = line.strip_prefix("    pub fn ").filter(|_| in_impl) {
+                    let name = rest.split(['(', '<']).next().unwrap();
+                    methods.push(name.to_string());
+                }
+            }
+        }
+        methods
+    }
+
+    /// Every `pub use` item of lib.rs: conventional name → mystical alias
+    fn exported_items() -> HashMap<String, Option<String>> {
+        let mut items: HashMap<String, Option<String>> = HashMap::new();
+        for line in include_str!("../src/lib.rs").lines() {
+            let Some(path) = line.strip_prefix("pub use ") else { continue };
+            let path = path.trim_end_matches(';');
+            let list = match path.find('{') {
+                Some(open) => &path[open + 1..path.len() - 1],
+                None => path.rsplit("::").next().unwrap(),
+            };
+            for item in list.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+                match item.split_once(" as ") {
+                    Some((conventional, alias)) => {
+                        items.insert(conventional.trim().to_string(), Some(alias.trim().to_string()));
+                    }
+                    None => {
+                        items.entry(item.to_string()).or_insert(None);
+                    }
+                }
+            }
+        }
+        items
+    }
+
+    #[test]
+    fn test_every_engine_method_has_a_verb() {
+        let sources = [
+            include_str!("../src/engine.rs"),
+            include_str!("../src/profile.rs"),
+            include_str!("../src/wasm_support.rs"),
+        ];
+        let methods = public_methods("TemplateEngine", &sources);
+        assert!(methods.len() > 50, "the source walk found too few methods");
+
+        let missing: Vec<&String> = methods.iter()
+            .filter(|method| !ENGINE_VERBS.iter().any(|(conventional, _)| conventional == method))
+            .collect();
+        assert!(missing.is_empty(), "TemplateEngine methods without a RuneEngineExt verb: {:?}", missing);
+    }
+
+    #[test]
+    fn test_every_context_method_has_a_verb() {
+        let methods = public_methods("TemplateContext", &[include_str!("../src/context.rs")]);
+        assert!(!methods.is_empty());
+
+        let missing: Vec<&String> = methods.iter()
+            .filter(|method| !SCROLL_VERBS.iter().any(|(conventional, _)| conventional == method))
+            .collect();
+        assert!(missing.is_empty(), "TemplateContext methods without a RuneScrollExt verb: {:?}", missing);
+    }
+
+    #[test]
+    fn test_every_exported_item_has_a_mystical_alias() {
+        let missing: Vec<String> = exported_items().into_iter()
+            .filter(|(_, alias)| alias.is_none())
+            .map(|(item, _)| item)
+            .collect();
+        assert!(missing.is_empty(), "exports without a mystical alias: {:?}", missing);
+    }
+
+    #[test]
+    fn test_verbs_are_unique() {
+        for verbs in [ENGINE_VERBS, SCROLL_VERBS] {
+            let mut names: Vec<&str> = verbs.iter().map(|(_, verb)| *verb).collect();
+            names.sort_unstable();
+            names.dedup();
+            assert_eq!(names.len(), verbs.len());
+        }
+    }
+
+    #[test]
+    fn test_verbs_behave_like_conventional_methods() {
+        let mut engine = RuneEngine::awaken("./sacred_scrolls");
+        engine.enchant_filter("enchant", |input, _args| Ok(format!("✨{}✨", input)));
+        engine.swear_strict_oath();
+        assert!(engine.is_strict_mode_enabled());
+
+        let mut scroll = RuneScroll::blank_scroll();
+        scroll.inscribe_string("hero", "ferris");
+        scroll.inscribe_number("level", 7);
+        assert!(scroll.bears("hero"));
+        assert_eq!(scroll.divine_string("level"), Some("7".to_string()));
+
+        let template = "{{hero|enchant}} reached level {{level}}";
+        let mystical = engine.carve_runes(template, &scroll).unwrap();
+        let conventional = TemplateEngine::render_string(&mut engine, template, &scroll).unwrap();
+        assert_eq!(mystical, conventional);
+        assert_eq!(mystical, "✨ferris✨ reached level 7");
+
+        let mut context = TemplateContext::new();
+        context.set_string("hero", "ferris");
+        context.set_number("level", 7);
+        assert_eq!(engine.carve_runes(template, &context).unwrap(), mystical);
+    }
+}
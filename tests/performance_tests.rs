@@ -1,23 +1,10 @@
 // Performance tests following TDD methodology
 // 🔴 RED Phase: Write failing tests first
 
-use mystical_runic::{TemplateEngine, TemplateContext, TemplateValue};
-use std::collections::HashMap;
-use std::fs;
-use std::path::PathBuf;
-use std::time::{Duration, Instant};
+mod common;
 
-// Utility to create temporary directories for testing
-fn create_temp_dir() -> PathBuf {
-    let mut temp_path = std::env::temp_dir();
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-    temp_path.push(format!("mystical_runic_perf_test_{}_{}", std::process::id(), timestamp));
-    let _ = std::fs::create_dir_all(&temp_path);
-    temp_path
-}
+use common::*;
+use std::time::{Duration, Instant};
 
 // 🔴 RED: Test for parallel template processing
 #[test]
@@ -246,4 +233,962 @@ fn test_parallel_bytecode_rendering() {
     // Should complete in reasonable time (less than 100ms for this test)
     assert!(parallel_duration < Duration::from_millis(100),
            "Parallel bytecode rendering should be fast: {:?}", parallel_duration);
-}
\ No newline at end of file
+}
+
+/// Cache warmup and precompile-all API
+#[cfg(test)]
+mod warmup_tests {
+    use super::*;
+
+    fn setup_templates() -> PathBuf {
+        let templates_path = create_temp_dir();
+        for i in 0..9 {
+            fs::write(templates_path.join(format!("page_{}.html", i)), format!("Page {}: {{{{name}}}}", i)).unwrap();
+        }
+        fs::write(templates_path.join("base.html"), "<main>{{block content}}Default{{/block}}</main>").unwrap();
+        fs::write(
+            templates_path.join("child.html"),
+            "{{extends \"base.html\"}}{{block content}}Child {{name}}{{/block}}",
+        ).unwrap();
+        fs::create_dir_all(templates_path.join("emails")).unwrap();
+        fs::write(templates_path.join("emails/welcome.html"), "Welcome {{name}}").unwrap();
+        fs::write(templates_path.join("broken.html"), "Hello {{name").unwrap();
+        templates_path
+    }
+
+    #[cfg(feature = "inheritance")]
+    #[test]
+    fn test_warm_up_reports_broken_templates_and_caches_the_rest() {
+        let templates_path = setup_templates();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.enable_bytecode_cache(true);
+
+        let report = engine.warm_up().unwrap();
+
+        assert!(!report.is_success());
+        assert_eq!(report.failed_templates.len(), 1);
+        assert_eq!(report.failed_templates[0].0, "broken.html");
+        assert_eq!(report.templates_processed.len(), 12);
+        assert!(report.templates_processed.contains(&"emails/welcome.html".to_string()));
+        for template in &report.templates_processed {
+            assert!(engine.is_template_cached(template), "{} should be cached", template);
+            assert!(engine.is_bytecode_cached(template), "{} should be compiled", template);
+        }
+        assert!(report.summary().contains("broken.html"));
+
+        // Subsequent renders are served from the cache, even with the files gone
+        fs::remove_dir_all(&templates_path).unwrap();
+        let mut context = TemplateContext::new();
+        context.set_string("name", "Cached");
+        assert_eq!(engine.render("page_3.html", &context).unwrap(), "Page 3: Cached");
+        assert_eq!(engine.render("emails/welcome.html", &context).unwrap(), "Welcome Cached");
+        assert_eq!(engine.render("child.html", &context).unwrap(), "<main>Child Cached</main>");
+    }
+
+    #[cfg(feature = "inheritance")]
+    #[test]
+    fn test_warm_up_without_bytecode_cache_only_loads_sources() {
+        let templates_path = setup_templates();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let report = engine.warm_up().unwrap();
+        assert_eq!(report.templates_processed.len(), 12);
+        assert!(engine.is_template_cached("page_0.html"));
+        assert!(!engine.is_bytecode_cached("page_0.html"));
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_warm_up_filtered_by_prefix_and_glob() {
+        let templates_path = setup_templates();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let report = engine.warm_up_filtered("emails/").unwrap();
+        assert_eq!(report.templates_processed, vec!["emails/welcome.html".to_string()]);
+        assert!(!engine.is_template_cached("page_0.html"));
+
+        let report = engine.warm_up_filtered("page_*.html").unwrap();
+        assert_eq!(report.templates_processed.len(), 9);
+        assert!(report.is_success());
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_warm_up_missing_directory_is_empty() {
+        let mut engine = TemplateEngine::new("/definitely/does/not/exist/nowhere");
+        let report = engine.warm_up().unwrap();
+        assert!(report.templates_processed.is_empty());
+        assert!(report.is_success());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_warm_up_async() {
+        use mystical_runic::AsyncTemplateEngine;
+
+        let templates_path = setup_templates();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let report = engine.warm_up_async().await.unwrap();
+        assert_eq!(report.templates_processed.len(), 12);
+        assert_eq!(report.failed_templates.len(), 1);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Context usage analysis for fragment cache keys
+#[cfg(test)]
+mod context_usage_tests {
+    use super::*;
+
+    fn setup_templates() -> PathBuf {
+        let templates_path = create_temp_dir();
+        fs::create_dir_all(templates_path.join("partials")).unwrap();
+        fs::write(templates_path.join("layout.html"), "<title>{{site_name}}</title>{{block body}}{{/block}}").unwrap();
+        fs::write(
+            templates_path.join("page.html"),
+            "{{extends \"layout.html\"}}{{block body}}Hi {{user.name}}! {{include \"partials/list.html\"}}{{/block}}",
+        ).unwrap();
+        fs::write(
+            templates_path.join("partials/list.html"),
+            "{{for item in items}}{{item.title|upper}}{{/for}}{{if count > 0}}{{plural count \"x\" \"xs\"}}{{/if}}",
+        ).unwrap();
+        fs::write(templates_path.join("partials/dynamic.html"), "{{footer}}").unwrap();
+        fs::write(templates_path.join("dynamic.html"), "{{include page.partial}}").unwrap();
+        templates_path
+    }
+
+    fn sample_context() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        let mut user = HashMap::new();
+        user.insert("name".to_string(), TemplateValue::String("Ada".to_string()));
+        user.insert("email".to_string(), TemplateValue::String("ada@example.com".to_string()));
+        context.set("user", TemplateValue::Object(user));
+        context.set("items", TemplateValue::Array(vec![TemplateValue::String("a".to_string())]));
+        context.set_string("settings", "dark");
+        context.set_number("count", 1);
+        context.set_string("site_name", "Runic");
+        context
+    }
+
+    #[cfg(feature = "inheritance")]
+    #[test]
+    fn test_static_analysis_follows_includes_and_layouts() {
+        let templates_path = setup_templates();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let usage = engine.analyze_context_usage("page.html").unwrap();
+        let keys: Vec<&str> = usage.keys.iter().map(|k| k.as_str()).collect();
+        assert_eq!(keys, vec!["count", "items", "site_name", "user"]);
+        assert!(usage.paths.contains("user.name"));
+        assert!(!usage.uses("item"), "loop variables are local");
+        assert!(!usage.uses("settings"));
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_cache_key_only_depends_on_used_values() {
+        let templates_path = setup_templates();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        let usage = engine.analyze_context_usage("page.html").unwrap();
+
+        let context = sample_context();
+        let base_key = usage.cache_key(&context);
+        assert_eq!(base_key, usage.cache_key(&sample_context()));
+
+        let mut unrelated = sample_context();
+        unrelated.set_string("settings", "light");
+        assert_eq!(usage.cache_key(&unrelated), base_key);
+
+        // Only user.name is read, so other user fields don't matter either
+        let mut other_email = sample_context();
+        let mut user = HashMap::new();
+        user.insert("name".to_string(), TemplateValue::String("Ada".to_string()));
+        user.insert("email".to_string(), TemplateValue::String("other@example.com".to_string()));
+        other_email.set("user", TemplateValue::Object(user));
+        assert_eq!(usage.cache_key(&other_email), base_key);
+
+        let mut changed_items = sample_context();
+        changed_items.set("items", TemplateValue::Array(vec![TemplateValue::String("b".to_string())]));
+        assert_ne!(usage.cache_key(&changed_items), base_key);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_render_with_usage_records_dynamic_reads() {
+        let templates_path = setup_templates();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.allow_dynamic_includes(["partials/"]);
+        let mut context = sample_context();
+        let mut page = HashMap::new();
+        page.insert("partial".to_string(), TemplateValue::String("partials/dynamic.html".to_string()));
+        context.set("page", TemplateValue::Object(page));
+        context.set_string("footer", "Bye");
+
+        // Static analysis can only see the variable naming the partial
+        let static_usage = engine.analyze_context_usage("dynamic.html").unwrap();
+        assert!(static_usage.paths.contains("page.partial"));
+        assert!(!static_usage.uses("footer"));
+
+        let (output, usage) = engine.render_with_usage("dynamic.html", &context).unwrap();
+        assert_eq!(output, "Bye");
+        assert!(usage.paths.contains("page.partial"));
+        assert!(usage.uses("footer"));
+        assert!(!usage.uses("settings"));
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[cfg(feature = "inheritance")]
+    #[test]
+    fn test_render_with_usage_matches_rendered_page() {
+        let templates_path = setup_templates();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        let context = sample_context();
+
+        let (output, usage) = engine.render_with_usage("page.html", &context).unwrap();
+        assert_eq!(output, engine.render("page.html", &context).unwrap());
+        assert!(usage.paths.contains("user.name"));
+        assert!(usage.uses("items"));
+        assert!(!usage.uses("item"));
+        assert!(!usage.uses("settings"));
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// render_many: one template against many contexts
+#[cfg(test)]
+mod render_many_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+
+    #[cfg(feature = "inheritance")]
+    fn recipient(i: i64) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("name", &format!("Reader <{}>", i));
+        context.set_number("id", i);
+        context.set_bool("vip", i % 3 == 0);
+        let items = (0..i % 4).map(|n| TemplateValue::Number(n * 10)).collect();
+        context.set("items", TemplateValue::Array(items));
+        context
+    }
+
+    #[cfg(feature = "inheritance")]
+    fn letter_engine() -> (TemplateEngine, PathBuf) {
+        engine_with_templates(&[
+            ("base.html", "<html>{{block body}}{{/block}}</html>"),
+            (
+                "letter.html",
+                "{{extends \"base.html\"}}{{block body}}Dear {{name|upper}} (#{{id}})\
+                 {{if vip}} VIP{{/if}}:{{for item in items}} {{item}}{{/for}}{{/block}}",
+            ),
+        ])
+    }
+
+    #[cfg(feature = "inheritance")]
+    #[test]
+    fn test_render_many_matches_individual_renders() {
+        let (mut engine, templates_path) = letter_engine();
+        let contexts: Vec<TemplateContext> = (0..25).map(recipient).collect();
+        let expected: Vec<String> = contexts.iter()
+            .map(|context| engine.render("letter.html", context).unwrap())
+            .collect();
+
+        for workers in [1, 2, 4, 32] {
+            engine.set_render_workers(workers);
+            let results = engine.render_many("letter.html", &contexts).unwrap();
+            let outputs: Vec<String> = results.into_iter().map(Result::unwrap).collect();
+            assert_eq!(outputs, expected, "workers = {}", workers);
+        }
+
+        let outputs: Vec<String> = engine.render_many_iter("letter.html", (0..25).map(recipient))
+            .unwrap().into_iter().map(Result::unwrap).collect();
+        assert_eq!(outputs, expected);
+        assert!(expected[3].contains("Dear READER &lt;3&gt; (#3) VIP: 0 10 20"));
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[cfg(feature = "inheritance")]
+    #[test]
+    fn test_render_many_finishes_outputs_and_errors_like_render() {
+        let (mut engine, templates_path) = letter_engine();
+        fs::write(templates_path.join("row.html"), "[{{item|int}}]").unwrap();
+        fs::write(
+            templates_path.join("table.html"),
+            "{{extends \"base.html\"}}{{block body}}{{for item in items}}{{include \"row.html\"}}{{/for}}{{/block}}",
+        ).unwrap();
+        engine.enable_strict_mode();
+        engine.set_fixed_time(std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000));
+        engine.set_provenance(mystical_runic::ProvenanceOptions::default());
+
+        let contexts: Vec<TemplateContext> = (0..4).map(recipient).collect();
+        let expected: Vec<String> = contexts.iter()
+            .map(|context| engine.render("letter.html", context).unwrap())
+            .collect();
+        assert!(expected[0].starts_with("<!-- Generated from letter.html"), "{}", expected[0]);
+        for workers in [1, 2] {
+            engine.set_render_workers(workers);
+            let outputs: Vec<String> = engine.render_many("letter.html", &contexts).unwrap()
+                .into_iter().map(Result::unwrap).collect();
+            assert_eq!(outputs, expected, "workers = {}", workers);
+        }
+
+        let mut bad = TemplateContext::new();
+        bad.set("items", TemplateValue::Array(vec![TemplateValue::Number(1), TemplateValue::String("x".to_string())]));
+        let error = engine.render("table.html", &bad).unwrap_err();
+        assert!(!error.template_backtrace().is_empty(), "{:?}", error);
+        let batch_error = engine.render_many("table.html", &[bad]).unwrap().remove(0).unwrap_err();
+        assert_eq!(batch_error.template_backtrace(), error.template_backtrace());
+        assert_eq!(batch_error.to_string(), error.to_string());
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_render_many_reports_failures_per_item() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("qty.html"), "Qty: {{qty|int}}").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.enable_strict_mode();
+        engine.set_render_workers(2);
+
+        let contexts: Vec<TemplateContext> = ["3", "three", "7"].iter().map(|qty| {
+            let mut context = TemplateContext::new();
+            context.set_string("qty", qty);
+            context
+        }).collect();
+
+        let results = engine.render_many("qty.html", &contexts).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap(), "Qty: 3");
+        assert!(matches!(results[1], Err(TemplateError::FilterError { .. })));
+        assert_eq!(results[2].as_ref().unwrap(), "Qty: 7");
+
+        // Loading failures abort the whole batch
+        assert!(engine.render_many("missing.html", &contexts).is_err());
+        assert!(engine.render_many("qty.html", &[]).unwrap().is_empty());
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[cfg(feature = "inheritance")]
+    #[test]
+    fn test_render_many_with_dynamic_layout() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("light.html"), "light[{{block body}}{{/block}}]").unwrap();
+        fs::write(templates_path.join("dark.html"), "dark[{{block body}}{{/block}}]").unwrap();
+        fs::write(templates_path.join("page.html"), "{{extends theme}}{{block body}}{{title}}{{/block}}").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.allow_dynamic_includes(["light.html", "dark.html"]);
+
+        let contexts: Vec<TemplateContext> = [("light.html", "a"), ("dark.html", "b")].iter().map(|(theme, title)| {
+            let mut context = TemplateContext::new();
+            context.set_string("theme", theme);
+            context.set_string("title", title);
+            context
+        }).collect();
+
+        let outputs: Vec<String> = engine.render_many("page.html", &contexts).unwrap()
+            .into_iter().map(Result::unwrap).collect();
+        assert_eq!(outputs, vec!["light[a]", "dark[b]"]);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_render_workers_setting() {
+        let mut engine = TemplateEngine::new(".");
+        assert!(engine.get_render_workers() >= 1);
+        engine.set_render_workers(0);
+        assert_eq!(engine.get_render_workers(), 1);
+        engine.set_render_workers(8);
+        assert_eq!(engine.get_render_workers(), 8);
+    }
+}
+
+/// Cooperative cancellation of renders
+#[cfg(test)]
+mod cancellation_tests {
+    use super::*;
+    use mystical_runic::{CancellationToken, TemplateError};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn huge_loop_dir() -> PathBuf {
+        let dir = create_temp_dir();
+        fs::write(dir.join("huge.html"), "{{for row in rows}}<tr><td>{{row}}</td><td>{{title|upper}}</td></tr>{{/for}}").unwrap();
+        fs::write(dir.join("small.html"), "<h1>{{title}}</h1>{{include \"huge.html\"}}").unwrap();
+        dir
+    }
+
+    fn context(rows: i64) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("title", "report");
+        context.set("rows", TemplateValue::Array((0..rows).map(TemplateValue::Number).collect()));
+        context
+    }
+
+    #[test]
+    fn test_cancel_stops_a_huge_loop_promptly() {
+        let dir = huge_loop_dir();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        let big = context(2_000_000);
+        let token = CancellationToken::new();
+
+        let cancelled_at = Arc::new(Mutex::new(None));
+        let canceller = {
+            let (token, cancelled_at) = (token.clone(), Arc::clone(&cancelled_at));
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                *cancelled_at.lock().unwrap() = Some(Instant::now());
+                token.cancel();
+            })
+        };
+
+        let result = engine.render_with_cancel("huge.html", &big, &token);
+        let returned_at = Instant::now();
+        canceller.join().unwrap();
+        assert!(matches!(result, Err(TemplateError::Cancelled)), "{:?}", result.map(|output| output.len()));
+        let cancelled_at = cancelled_at.lock().unwrap().expect("cancel happened before the render returned");
+        // The whole loop runs for many seconds; a second leaves room for busy machines
+        let latency = returned_at.duration_since(cancelled_at);
+        assert!(latency < Duration::from_secs(1), "{:?}", latency);
+
+        // The engine is still usable, cancelled token or not
+        let small = context(3);
+        let expected = "<tr><td>0</td><td>REPORT</td></tr><tr><td>1</td><td>REPORT</td></tr><tr><td>2</td><td>REPORT</td></tr>";
+        assert_eq!(engine.render("huge.html", &small).unwrap(), expected);
+        assert_eq!(engine.render_with_cancel("small.html", &small, &CancellationToken::new()).unwrap(), format!("<h1>report</h1>{}", expected));
+    }
+
+    #[test]
+    fn test_cancelled_token_fails_before_rendering() {
+        let dir = huge_loop_dir();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        let token = CancellationToken::new();
+        token.cancel();
+        assert!(token.clone().is_cancelled());
+        assert!(matches!(engine.render_with_cancel("small.html", &context(3), &token), Err(TemplateError::Cancelled)));
+        assert!(!engine.is_template_cached("small.html"));
+        assert!(engine.render("small.html", &context(1)).is_ok());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_render_with_cancel_async() {
+        use mystical_runic::AsyncTemplateEngine;
+
+        let dir = huge_loop_dir();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        let token = CancellationToken::new();
+        let result = engine.render_with_cancel_async("small.html", &context(1), &token).await.unwrap();
+        assert_eq!(result, "<h1>report</h1><tr><td>0</td><td>REPORT</td></tr>");
+
+        token.cancel();
+        let result = engine.render_with_cancel_async("small.html", &context(1), &token).await;
+        assert!(matches!(result, Err(TemplateError::Cancelled)));
+    }
+}
+
+/// Render profiles: span trees, hot spots and speedscope export
+#[cfg(test)]
+mod render_profiling_tests {
+    use super::*;
+    use mystical_runic::{ProfileSpan, SpanKind};
+
+    fn find<'a>(span: &'a ProfileSpan, kind: SpanKind, name: &str) -> &'a ProfileSpan {
+        span.iter()
+            .find(|span| span.kind == kind && span.name == name)
+            .unwrap_or_else(|| panic!("no {} span named '{}' in {:#?}", kind, name, span))
+    }
+
+    fn profiled_engine() -> (TemplateEngine, TemplateContext, PathBuf) {
+        let templates_path = create_temp_dir();
+        fs::write(
+            templates_path.join("page.html"),
+            "<h1>{{title|upper}}</h1>\n{{include \"row.html\"}}\n{{for item in items}}{{if item.shown}}<li>{{item.name|lower|truncate:3}}</li>{{/if}}{{/for}}\n",
+        ).unwrap();
+        fs::write(templates_path.join("row.html"), "<p>{{shout(title)}}</p>").unwrap();
+
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.register_helper("shout", |args| {
+            match args.first() {
+                Some(TemplateValue::String(text)) => Ok(TemplateValue::String(format!("{}!", text))),
+                _ => Ok(TemplateValue::String("!".to_string())),
+            }
+        });
+        let mut context = TemplateContext::new();
+        context.set_string("title", "Runes");
+        let items = ["Alpha", "Beta", "Gamma"].iter().enumerate().map(|(index, name)| {
+            let mut item = HashMap::new();
+            item.insert("name".to_string(), TemplateValue::String(name.to_string()));
+            item.insert("shown".to_string(), TemplateValue::Bool(index != 1));
+            TemplateValue::Object(item)
+        }).collect();
+        context.set("items", TemplateValue::Array(items));
+        (engine, context, templates_path)
+    }
+
+    #[test]
+    fn test_profile_keeps_the_output_of_a_plain_render() {
+        let (mut engine, context, templates_path) = profiled_engine();
+        let expected = engine.render("page.html", &context).unwrap();
+
+        let profile = engine.profile_render("page.html", &context).unwrap();
+        assert_eq!(profile.output, expected);
+        assert_eq!(profile.root.kind, SpanKind::Template);
+        assert_eq!(profile.root.name, "page.html");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_spans_nest_like_the_template() {
+        let (mut engine, context, templates_path) = profiled_engine();
+        let profile = engine.profile_render("page.html", &context).unwrap();
+        let root = &profile.root;
+
+        let title = find(root, SpanKind::Output, "title|upper");
+        assert_eq!((title.line, title.template.as_str()), (1, "page.html"));
+        assert_eq!(title.children.iter().map(|span| span.name.as_str()).collect::<Vec<_>>(), vec!["upper"]);
+
+        let include = find(root, SpanKind::Include, "row.html");
+        assert_eq!(include.line, 2);
+        let helper = find(include, SpanKind::Helper, "shout");
+        assert_eq!(helper.template, "row.html");
+
+        // Iterations are merged; the hidden item never reaches the output tag
+        let for_loop = find(root, SpanKind::Loop, "item in items");
+        assert_eq!(for_loop.count, 3);
+        let condition = find(for_loop, SpanKind::Condition, "item.shown");
+        assert_eq!(condition.count, 3);
+        let name = find(condition, SpanKind::Output, "item.name|lower|truncate:3");
+        assert_eq!(name.count, 2);
+        let filters: Vec<(&str, usize)> = name.children.iter().map(|span| (span.name.as_str(), span.count)).collect();
+        assert_eq!(filters, vec![("lower", 2), ("truncate", 2)]);
+
+        for span in root.iter() {
+            let children: u64 = span.children.iter().map(|child| child.total_nanos).sum();
+            assert_eq!(span.self_nanos + children, span.total_nanos, "times of {}", span.frame_name());
+        }
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[cfg(feature = "inheritance")]
+    #[test]
+    fn test_layout_merge_is_a_span_of_the_page() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("base.html"), "<main>{{block content}}{{/block}}</main>").unwrap();
+        fs::write(templates_path.join("child.html"), "{{extends \"base.html\"}}{{block content}}{{name}}{{/block}}").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        let mut context = TemplateContext::new();
+        context.set_string("name", "Ada");
+
+        let profile = engine.profile_render("child.html", &context).unwrap();
+        assert_eq!(profile.output, "<main>Ada</main>");
+        assert_eq!(profile.root.children[0].kind, SpanKind::Layout);
+        assert_eq!(profile.root.children[0].name, "base.html");
+        find(&profile.root, SpanKind::Output, "name");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_hot_spots_are_sorted_by_self_time() {
+        let (mut engine, context, templates_path) = profiled_engine();
+        let profile = engine.profile_render("page.html", &context).unwrap();
+
+        let spots = profile.hot_spots(3);
+        assert_eq!(spots.len(), 3);
+        assert!(spots.windows(2).all(|pair| pair[0].self_nanos >= pair[1].self_nanos));
+        let summary = profile.summary(3);
+        assert!(summary.starts_with("page.html rendered in "), "{}", summary);
+        assert_eq!(summary.lines().count(), 4);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_speedscope_json_parses_with_the_frame_names() {
+        let (mut engine, context, templates_path) = profiled_engine();
+        let profile = engine.profile_render("page.html", &context).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&profile.to_speedscope_json()).unwrap();
+        assert_eq!(json["$schema"], "https://www.speedscope.app/file-format-schema.json");
+        let frames: Vec<&str> = json["shared"]["frames"].as_array().unwrap().iter()
+            .map(|frame| frame["name"].as_str().unwrap())
+            .collect();
+        for expected in ["page.html", "output title|upper", "filter upper", "include row.html", "helper shout", "for item in items", "if item.shown", "filter truncate"] {
+            assert!(frames.contains(&expected), "{} missing from {:?}", expected, frames);
+        }
+
+        // Every frame opens and closes in order, within the profile
+        let profile_json = &json["profiles"][0];
+        assert_eq!(profile_json["type"], "evented");
+        assert_eq!(profile_json["unit"], "nanoseconds");
+        let end = profile_json["endValue"].as_u64().unwrap();
+        let mut open = Vec::new();
+        let mut last_at = 0;
+        for event in profile_json["events"].as_array().unwrap() {
+            let at = event["at"].as_u64().unwrap();
+            assert!(at >= last_at && at <= end);
+            last_at = at;
+            match event["type"].as_str().unwrap() {
+                "O" => open.push(event["frame"].as_u64().unwrap()),
+                _ => assert_eq!(open.pop(), event["frame"].as_u64()),
+            }
+        }
+        assert!(open.is_empty());
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn test_cli_profile_writes_speedscope_json() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("card.html"), "{{if shown}}<b>{{name|upper}}</b>{{/if}}").unwrap();
+        let template = templates_path.join("card.html");
+        let json_file = templates_path.join("card.speedscope.json");
+
+        let summary = mystical_runic::profile_template(
+            template.to_str().unwrap(),
+            r#"{"shown": true, "name": "ada"}"#,
+            json_file.to_str().unwrap(),
+        ).unwrap();
+        assert!(summary.starts_with("card.html rendered in "), "{}", summary);
+        assert!(summary.contains("x1  filter upper (card.html:1)"), "{}", summary);
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&json_file).unwrap()).unwrap();
+        assert_eq!(json["name"], "card.html");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Render queue tests
+#[cfg(test)]
+mod render_queue_tests {
+    use super::*;
+    use mystical_runic::{Priority, QueueOutcome, RenderQueue, SharedTemplateEngine};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    /// Engine whose `slow()` helper takes `millis` per render
+    fn create_queue(millis: u64) -> RenderQueue {
+        let temp_dir = create_temp_dir();
+        fs::write(temp_dir.join("page.html"), "{{slow()}}{{name}}").unwrap();
+        fs::write(temp_dir.join("broken.html"), "{{if name}}").unwrap();
+        let mut engine = TemplateEngine::new(temp_dir.to_str().unwrap());
+        engine.register_helper("slow", move |_args| {
+            std::thread::sleep(Duration::from_millis(millis));
+            Ok(TemplateValue::String(String::new()))
+        });
+        RenderQueue::new(SharedTemplateEngine::new(engine))
+    }
+
+    fn page(name: &str) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("name", name);
+        context
+    }
+
+    #[test]
+    fn test_renders_by_priority_then_push_order() {
+        let mut queue = create_queue(1);
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&order);
+        queue.on_progress(move |progress| {
+            seen.lock().unwrap().push(progress.item.output().unwrap().to_string());
+        });
+
+        queue.push("page.html", page("low"), Priority::Low);
+        queue.push("page.html", page("normal-1"), Priority::Normal);
+        let home = queue.push("page.html", page("home"), Priority::High);
+        queue.push("page.html", page("normal-2"), Priority::Normal);
+        queue.push("page.html", page("sitemap"), Priority::High);
+
+        let report = queue.run(1, None);
+        assert_eq!(*order.lock().unwrap(), ["home", "sitemap", "normal-1", "normal-2", "low"]);
+        assert_eq!(report.items[home].output(), Some("home"));
+        assert_eq!(report.items[home].priority, Priority::High);
+        assert!(report.is_success());
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_deadline_skips_remaining_low_priority_renders() {
+        let mut queue = create_queue(40);
+        for index in 0..10 {
+            queue.push("page.html", page(&format!("page-{}", index)), Priority::Low);
+        }
+        let home = queue.push("page.html", page("home"), Priority::High);
+
+        let report = queue.run(1, Some(Instant::now() + Duration::from_millis(100)));
+        assert_eq!(report.items[home].output(), Some("home"));
+        assert!(report.skipped > 0, "{}", report.summary());
+        assert!(report.completed >= 2, "{}", report.summary());
+        assert!(report.items.iter().filter(|item| item.is_skipped()).all(|item| item.started.is_none()));
+        // Push order: skipped renders are the last low priority ones
+        let first_skipped = report.items.iter().position(|item| item.is_skipped()).unwrap();
+        assert!(report.items[first_skipped..home].iter().all(|item| item.is_skipped()));
+    }
+
+    #[test]
+    fn test_report_totals_add_up() {
+        let mut queue = create_queue(5);
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&progress);
+        queue.on_progress(move |progress| seen.lock().unwrap().push((progress.finished, progress.total)));
+        for index in 0..12 {
+            queue.push("page.html", page(&index.to_string()), Priority::Normal);
+        }
+        queue.push("broken.html", page("broken"), Priority::High);
+        queue.push("missing.html", page("missing"), Priority::Low);
+
+        let report = queue.run(4, None);
+        assert_eq!(report.total(), 14);
+        assert_eq!(report.completed + report.failed + report.skipped, report.total());
+        assert_eq!((report.completed, report.failed, report.skipped), (12, 2, 0));
+        assert!(matches!(report.items[12].outcome, QueueOutcome::Failed(_)));
+        assert!(report.items[13].error().is_some());
+        assert_eq!(report.items[3].output(), Some("3"));
+        assert!(report.items.iter().all(|item| item.duration <= report.elapsed));
+        assert_eq!(report.summary().split(" in ").next(), Some("12 rendered, 2 failed, 0 skipped of 14"));
+
+        let mut progress = progress.lock().unwrap().clone();
+        progress.sort();
+        assert_eq!(progress, (1..=14).map(|finished| (finished, 14)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_expired_deadline_skips_everything() {
+        let mut queue = create_queue(0);
+        queue.push("page.html", page("home"), Priority::High);
+        queue.push("page.html", page("about"), Priority::Normal);
+
+        let report = queue.run(2, Some(Instant::now()));
+        assert_eq!((report.completed, report.failed, report.skipped), (0, 0, 2));
+        assert!(!report.is_success());
+        assert!(queue.run(2, None).items.is_empty());
+    }
+}
+
+/// Streaming flush control tests
+#[cfg(test)]
+mod stream_flush_tests {
+    use super::*;
+    use mystical_runic::FlushOn;
+    use std::io::{self, Write};
+
+    /// Records the bytes written between two flushes
+    #[derive(Default)]
+    struct FlushRecorder {
+        bytes: Vec<u8>,
+        flushed: Vec<String>,
+        pending: Vec<u8>,
+    }
+
+    impl Write for FlushRecorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.bytes.extend_from_slice(buf);
+            self.pending.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushed.push(String::from_utf8(std::mem::take(&mut self.pending)).unwrap());
+            Ok(())
+        }
+    }
+
+    fn cards() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set("cards", TemplateValue::Array(
+            ["a", "b", "c"].iter().map(|card| TemplateValue::String(card.to_string())).collect()
+        ));
+        context
+    }
+
+    const FEED: &str = "<head></head>{{flush}}<ul>{{for card in cards}}<li>{{card}}</li>{{/for}}</ul>";
+
+    #[test]
+    fn test_flush_directive_flushes_the_writer() {
+        let (mut engine, _dir) = engine_with_templates(&[("feed.html", FEED)]);
+        assert_eq!(engine.stream_flush_on(), FlushOn::Directive);
+
+        let mut writer = FlushRecorder::default();
+        engine.render_to_writer("feed.html", &cards(), &mut writer).unwrap();
+        assert_eq!(writer.flushed, vec!["<head></head>"]);
+        assert_eq!(String::from_utf8(writer.bytes).unwrap(), "<head></head><ul><li>a</li><li>b</li><li>c</li></ul>");
+
+        // Other renders ignore the directive
+        assert_eq!(engine.render("feed.html", &cards()).unwrap(), "<head></head><ul><li>a</li><li>b</li><li>c</li></ul>");
+    }
+
+    #[test]
+    fn test_flush_on_loop_iteration_and_manual() {
+        let (mut engine, _dir) = engine_with_templates(&[("feed.html", FEED)]);
+        engine.set_stream_flush_on(FlushOn::LoopIteration);
+        let mut writer = FlushRecorder::default();
+        engine.render_to_writer("feed.html", &cards(), &mut writer).unwrap();
+        assert_eq!(writer.flushed, vec!["<head></head>", "<ul><li>a</li>", "<li>b</li>", "<li>c</li>"]);
+        assert_eq!(writer.pending, b"</ul>");
+
+        engine.set_stream_flush_on(FlushOn::Manual);
+        let mut writer = FlushRecorder::default();
+        engine.render_to_writer("feed.html", &cards(), &mut writer).unwrap();
+        assert!(writer.flushed.is_empty());
+        assert_eq!(writer.bytes.len(), 52);
+    }
+
+    #[test]
+    fn test_chunk_size_auto_flushes() {
+        let (mut engine, _dir) = engine_with_templates(&[("feed.html", FEED)]);
+        engine.set_stream_chunk_size(Some(10));
+        assert_eq!(engine.stream_chunk_size(), Some(10));
+        let mut writer = FlushRecorder::default();
+        engine.render_to_writer("feed.html", &cards(), &mut writer).unwrap();
+
+        // Chunks of 10 bytes; the directive ends one early, after `</head>`
+        let sizes: Vec<usize> = writer.flushed.iter().map(String::len).collect();
+        assert_eq!(sizes, vec![10, 3, 10, 10, 10]);
+        assert_eq!(writer.flushed[1], "ad>");
+        assert_eq!(writer.pending, b"/li></ul>");
+        assert_eq!(String::from_utf8(writer.bytes).unwrap(), "<head></head><ul><li>a</li><li>b</li><li>c</li></ul>");
+    }
+
+    #[test]
+    fn test_chunk_size_splits_streamed_raw_includes() {
+        let (mut engine, _dir) = engine_with_templates(&[("page.html", "<body>{{include_raw \"big.txt\"}}</body>"), ("big.txt", &"x".repeat(1000))]);
+        engine.set_raw_include_options(mystical_runic::RawIncludeOptions::default().passthrough_threshold(100));
+        engine.set_stream_chunk_size(Some(256));
+        let mut writer = FlushRecorder::default();
+        engine.render_to_writer("page.html", &TemplateContext::new(), &mut writer).unwrap();
+        let sizes: Vec<usize> = writer.flushed.iter().map(String::len).collect();
+        assert_eq!(sizes, vec![256, 256, 256]);
+        assert_eq!(writer.pending.len(), 6 + 1000 + 7 - 3 * 256);
+    }
+}
+
+/// Loop iterations written to the writer as they are rendered
+#[cfg(test)]
+mod stream_loop_tests {
+    use super::*;
+    use std::io::{self, Write};
+    use std::sync::{Arc, Mutex};
+
+    /// Writer whose bytes a filter can look at during the render
+    #[derive(Clone, Default)]
+    struct SharedWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn rows(count: i64) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set("rows", TemplateValue::Array((1..=count).map(TemplateValue::Number).collect()));
+        context
+    }
+
+    fn fixture_context() -> TemplateContext {
+        let mut context = rows(3);
+        context.set("title", TemplateValue::String("Fish & <Chips>".to_string()));
+        context.set("html", TemplateValue::String("<em>raw</em>".to_string()));
+        context.set("none", TemplateValue::Array(Vec::new()));
+        let groups = [("a", "x"), ("a", "y"), ("b", "z")].iter().map(|(group, name)| {
+            let mut item = HashMap::new();
+            item.insert("group".to_string(), TemplateValue::String(group.to_string()));
+            item.insert("name".to_string(), TemplateValue::String(format!("<{}>", name)));
+            TemplateValue::Object(item)
+        }).collect();
+        context.set("items", TemplateValue::Array(groups));
+        context
+    }
+
+    #[test]
+    fn test_iterations_are_written_before_the_loop_ends() {
+        let written = SharedWriter::default();
+        let mut engine = TemplateEngine::new(".");
+        let probe = written.clone();
+        engine.register_filter("written", move |value, _| Ok(format!("{}@{}", value, probe.0.lock().unwrap().len())));
+        let template = "<ul>{{for row in rows}}<li>{{row|written}}</li>{{/for}}</ul>";
+
+        let mut writer = written.clone();
+        engine.render_string_to_writer(template, &rows(3), &mut writer).unwrap();
+        let output = String::from_utf8(written.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(output, "<ul><li>1@0</li><li>2@16</li><li>3@29</li></ul>");
+
+        // A buffered render writes nothing until the end
+        written.0.lock().unwrap().clear();
+        assert_eq!(engine.render_string(template, &rows(3)).unwrap(), "<ul><li>1@0</li><li>2@0</li><li>3@0</li></ul>");
+    }
+
+    #[test]
+    fn test_streamed_output_matches_render() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("row.html"), "<td>{{row|multiply:2}}</td>").unwrap();
+        let templates = [
+            ("escaping.html", "<h1>{{title}}</h1>{{for row in rows}}<p>{{title|upper}} {{& html}} {{html|truncate:6}}</p>{{/for}}"),
+            ("nested.html", "{{for row in rows}}<tr>{{for cell in rows}}<td>{{cell}}</td>{{/for}}{{include \"row.html\"}}</tr>{{/for}}"),
+            ("groups.html", "{{for item in items}}{{ifchanged item.group}}<h2>{{item.group}}</h2>{{/ifchanged}}{{item.name}}{{/for}}"),
+            ("scoped.html", "{{set label = title|lower}}{{capture cells}}{{for row in rows}}[{{row}}]{{/for}}{{/capture}}\
+                {{for row in rows}}{{set doubled = row|multiply:2}}{{label}}:{{doubled}}{{& cells}}{{/for}}{{doubled}}"),
+            ("empty.html", "{{for row in none}}{{row}}{{empty}}<p>Nothing for {{title}}</p>{{/for}}{{for row in rows}}{{if row > 1}}{{row}}{{/if}}{{/for}}"),
+        ];
+        for (name, content) in templates {
+            fs::write(templates_path.join(name), content).unwrap();
+        }
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        let context = fixture_context();
+
+        for (name, content) in templates {
+            let expected = engine.render(name, &context).unwrap();
+            let mut streamed = Vec::new();
+            engine.render_to_writer(name, &context, &mut streamed).unwrap();
+            assert_eq!(String::from_utf8(streamed).unwrap(), expected, "render_to_writer differs for {}", name);
+
+            let mut streamed = Vec::new();
+            engine.render_string_to_writer(content, &context, &mut streamed).unwrap();
+            assert_eq!(String::from_utf8(streamed).unwrap(), engine.render_string(content, &context).unwrap(), "{}", name);
+        }
+
+        // Minified output is only complete at the end, and still the same
+        engine.set_minify_output(true);
+        let mut streamed = Vec::new();
+        engine.render_to_writer("nested.html", &context, &mut streamed).unwrap();
+        assert_eq!(String::from_utf8(streamed).unwrap(), engine.render("nested.html", &context).unwrap());
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_loop_values_are_not_rendered_as_template_source() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set("secret", TemplateValue::String("hunter2".to_string()));
+        context.set("comments", TemplateValue::Array(vec![TemplateValue::String("{{secret}}".to_string())]));
+        let template = "{{for comment in comments}}<p>{{comment}}</p>{{/for}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "<p>{{secret}}</p>");
+
+        let mut streamed = Vec::new();
+        engine.render_string_to_writer(template, &context, &mut streamed).unwrap();
+        assert_eq!(streamed, b"<p>{{secret}}</p>");
+    }
+}
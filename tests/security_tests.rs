@@ -1,22 +1,10 @@
 // Advanced Security Tests for mystical-runic
 // These tests ensure the template engine is secure against various attack vectors
 
+mod common;
+
+use common::*;
 use mystical_runic::*;
-use std::collections::HashMap;
-use std::fs;
-use std::path::PathBuf;
-
-// Utility to create temporary directories for testing
-fn create_temp_dir() -> PathBuf {
-    let mut temp_path = std::env::temp_dir();
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_nanos();
-    temp_path.push(format!("mystical_runic_test_{}_{}", std::process::id(), timestamp));
-    let _ = std::fs::create_dir_all(&temp_path);
-    temp_path
-}
 
 #[test]
 fn test_xss_prevention_comprehensive() {
@@ -92,7 +80,7 @@ fn test_path_traversal_prevention() {
     
     // Create a file outside the templates directory
     let outside_file = &templates_path.parent().unwrap().join("secret.txt");
-    fs::write(&outside_file, "Secret content").unwrap();
+    fs::write(outside_file, "Secret content").unwrap();
     
     let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
     let context = TemplateContext::new();
@@ -112,7 +100,7 @@ fn test_path_traversal_prevention() {
     ];
     
     for attempt in traversal_attempts {
-        let result = engine.render(&attempt, &context);
+        let result = engine.render(attempt, &context);
         // Should fail to load files outside the templates directory
         assert!(result.is_err(), "Path traversal should fail: {}", attempt);
     }
@@ -122,7 +110,7 @@ fn test_path_traversal_prevention() {
     assert_eq!(result, "Safe content");
     
     // Clean up
-    fs::remove_file(&outside_file).unwrap_or(());
+    fs::remove_file(outside_file).unwrap_or(());
 }
 
 #[test]
@@ -301,4 +289,660 @@ fn test_context_pollution_prevention() {
     
     // Original context should not have the secret
     assert_eq!(context.get_string("secret"), None);
-}
\ No newline at end of file
+}
+#[test]
+fn test_template_name_rules() {
+    let accepted = [
+        "page.html",
+        "partials/header.html",
+        "partials\\header.html",
+        "./page.html",
+        "a..b.html",
+        ".hidden.html",
+        "console.html",
+        "nullable/com10.html",
+        "admin::dashboard.html",
+        "admin::partials\\nav.html",
+        "pages/über.html",
+    ];
+    for name in accepted {
+        assert_eq!(check_template_name(name), Ok(()), "{:?} should be accepted", name);
+    }
+
+    let rejected = [
+        ("", PathRule::EmptyName),
+        ("page\0.html", PathRule::NulByte),
+        ("page.html\0../secret", PathRule::NulByte),
+        ("page\n.html", PathRule::ControlCharacter),
+        ("pa\tge.html", PathRule::ControlCharacter),
+        ("page\u{7f}.html", PathRule::ControlCharacter),
+        ("\\\\server\\share\\page.html", PathRule::UncPath),
+        ("//server/share/page.html", PathRule::UncPath),
+        ("\\\\?\\C:\\page.html", PathRule::UncPath),
+        ("/etc/passwd", PathRule::AbsolutePath),
+        ("\\Windows\\win.ini", PathRule::AbsolutePath),
+        ("C:\\Windows\\System32\\config\\sam", PathRule::DriveLetter),
+        ("c:page.html", PathRule::DriveLetter),
+        ("page.html::$DATA", PathRule::AlternateDataStream),
+        ("page.html:secret", PathRule::AlternateDataStream),
+        ("admin::page.html:hidden:$DATA", PathRule::AlternateDataStream),
+        ("../secret.txt", PathRule::ParentDirectory),
+        ("..\\..\\secret.txt", PathRule::ParentDirectory),
+        ("safe/../../secret.txt", PathRule::ParentDirectory),
+        ("safe\\..\\secret.txt", PathRule::ParentDirectory),
+        (".../secret.txt", PathRule::ParentDirectory),
+        ("admin::../secret.txt", PathRule::ParentDirectory),
+        ("CON", PathRule::DeviceName),
+        ("nul.html", PathRule::DeviceName),
+        ("partials/Com1.txt", PathRule::DeviceName),
+        ("lpt9", PathRule::DeviceName),
+        ("aux .html", PathRule::DeviceName),
+        ("CONOUT$", PathRule::DeviceName),
+        ("page.html.", PathRule::TrailingDotOrSpace),
+        ("page.html ", PathRule::TrailingDotOrSpace),
+        ("partials. /page.html", PathRule::TrailingDotOrSpace),
+        ("::page.html", PathRule::InvalidNamespace),
+        ("../admin::page.html", PathRule::InvalidNamespace),
+        ("..::page.html", PathRule::InvalidNamespace),
+    ];
+    for (name, rule) in rejected {
+        let violation = check_template_name(name).expect_err(name);
+        assert_eq!(violation.rule, rule, "{:?}", name);
+        assert_eq!(violation.name, name);
+        let error = TemplateError::from(violation);
+        assert!(
+            matches!(&error, TemplateError::Security(message) if message.contains(rule.code())),
+            "{:?}: {:?}", name, error
+        );
+    }
+}
+
+#[test]
+fn test_template_name_rules_apply_to_every_loader() {
+    let templates_path = create_temp_dir();
+    fs::write(templates_path.join("page.html"), "ok").unwrap();
+    let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+    engine.allow_dynamic_includes(["*"]);
+    let mut context = TemplateContext::new();
+    context.set_string("partial", "CON.html");
+
+    let security_error = |result: TemplateResult<String>| match result {
+        Err(TemplateError::Security(message)) => message,
+        other => panic!("expected a security error, got {:?}", other),
+    };
+
+    // Files, includes, extends and dynamic includes
+    assert!(security_error(engine.render("\\\\server\\share\\page.html", &context)).contains("unc-path"));
+    assert!(security_error(engine.render_string("{{include \"page.html::$DATA\"}}", &context)).contains("alternate-data-stream"));
+    #[cfg(feature = "inheritance")]
+    {
+        fs::write(templates_path.join("child.html"), "{{extends \"..\\base.html\"}}").unwrap();
+        assert!(security_error(engine.render("child.html", &context)).contains("parent-directory"));
+    }
+    assert!(security_error(engine.render_string("{{include partial}}", &context)).contains("device-name"));
+    assert_eq!(engine.render("page.html", &context).unwrap(), "ok");
+
+    // Manifest entries
+    fs::write(templates_path.join("runic.manifest"), "home -> C:\\pages\\home.html\n").unwrap();
+    let message = security_error(engine.render_public("home", &context));
+    assert!(message.contains("runic.manifest line 1") && message.contains("drive-letter"), "{}", message);
+
+    let _ = fs::remove_dir_all(&templates_path);
+}
+
+/// Expression-only rendering for untrusted snippets
+#[cfg(test)]
+mod expression_only_tests {
+    use super::*;
+    use mystical_runic::{ExpressionOptions, TemplateError};
+
+    fn order_context() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        let mut order = HashMap::new();
+        order.insert("id".to_string(), TemplateValue::Number(1042));
+        order.insert("customer".to_string(), TemplateValue::String("ada <admin>".to_string()));
+        context.set("order", TemplateValue::Object(order));
+        context.set("items", TemplateValue::Array(vec![TemplateValue::String("x".to_string())]));
+        context
+    }
+
+    fn security_message(result: Result<String, TemplateError>) -> String {
+        match result {
+            Err(TemplateError::Security(message)) => message,
+            other => panic!("expected security error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_benign_interpolation_renders() {
+        // A template directory that doesn't exist proves no file access is needed
+        let engine = TemplateEngine::new("/definitely/does/not/exist");
+        let context = order_context();
+
+        let result = engine.render_expression_only(
+            "Order {{order.id}} for {{order.customer|upper}} {{! note }}shipped",
+            &context,
+        ).unwrap();
+        assert_eq!(result, "Order 1042 for ADA &lt;ADMIN&gt; shipped");
+    }
+
+    #[cfg(feature = "i18n")]
+    #[test]
+    fn test_translations_are_allowed() {
+        let mut engine = TemplateEngine::new(".");
+        let mut fr = HashMap::new();
+        fr.insert("shipped".to_string(), "Commande {{order.id}} expédiée".to_string());
+        engine.set_translations("fr", fr);
+        engine.set_locale("fr");
+
+        let result = engine.render_expression_only("{{t \"shipped\"}}", &order_context()).unwrap();
+        assert_eq!(result, "Commande 1042 expédiée");
+    }
+
+    #[test]
+    fn test_hostile_snippets_are_rejected_with_position() {
+        let engine = TemplateEngine::new("templates");
+        let context = order_context();
+
+        let message = security_message(engine.render_expression_only("Hi\n  {{include \"secret.html\"}}", &context));
+        assert!(message.contains("include"), "{}", message);
+        assert!(message.contains("line 2, column 3"), "{}", message);
+
+        let hostile = [
+            "{{for i in items}}{{i}}{{/for}}",
+            "{{if order.id}}x{{/if}}",
+            "{{macro m()}}x{{/macro}}",
+            "{{extends \"base.html\"}}",
+            "{{& order.customer}}",
+            "{{now()}}",
+            "{{set x = 1}}",
+            "{{order.customer|markdown}}",
+        ];
+        for snippet in hostile {
+            let message = security_message(engine.render_expression_only(snippet, &context));
+            assert!(message.contains("line 1, column 1"), "{}: {}", snippet, message);
+        }
+    }
+
+    #[test]
+    fn test_filter_allow_list_and_output_limit() {
+        let engine = TemplateEngine::new(".");
+        let context = order_context();
+        let options = ExpressionOptions {
+            allowed_filters: vec!["lower".to_string()],
+            max_output_length: 12,
+        };
+
+        assert_eq!(engine.render_expression_only_with("{{order.id}}", &context, &options).unwrap(), "1042");
+        let message = security_message(engine.render_expression_only_with("{{order.id|upper}}", &context, &options));
+        assert!(message.contains("Filter 'upper'"), "{}", message);
+
+        let result = engine.render_expression_only_with("Order {{order.customer}}", &context, &options);
+        assert!(matches!(result, Err(TemplateError::ResourceExhaustion { .. })));
+    }
+
+    #[test]
+    fn test_many_renders_are_cheap() {
+        let engine = TemplateEngine::new(".");
+        let context = order_context();
+        for _ in 0..5_000 {
+            assert_eq!(engine.render_expression_only("#{{order.id}}", &context).unwrap(), "#1042");
+        }
+    }
+}
+
+/// Nesting limits: adversarial templates fail fast with clean errors
+#[cfg(test)]
+mod nesting_limit_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+    use std::time::{Duration, Instant};
+
+    /// Small deterministic generator so failures are reproducible
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+    }
+
+    fn nested_context() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_bool("flag", true);
+        context.set("items", TemplateValue::Array(vec![TemplateValue::Number(1)]));
+        context
+    }
+
+    fn assert_fast<F: FnOnce()>(limit: Duration, f: F) {
+        let start = Instant::now();
+        f();
+        assert!(start.elapsed() < limit, "took {:?}", start.elapsed());
+    }
+
+    #[test]
+    fn test_default_and_configurable_depth() {
+        let mut engine = TemplateEngine::new(".");
+        assert_eq!(engine.get_max_nesting_depth(), 128);
+
+        let context = nested_context();
+        let template = format!("{}x{}", "{{if flag}}".repeat(5), "{{/if}}".repeat(5));
+        assert_eq!(engine.render_string(&template, &context).unwrap(), "x");
+
+        engine.set_max_nesting_depth(4);
+        assert_eq!(engine.get_max_nesting_depth(), 4);
+        assert!(engine.render_string(&template, &context).is_err());
+
+        // Sequential blocks do not add up
+        let sequential = "{{if flag}}a{{/if}}".repeat(50);
+        assert_eq!(engine.render_string(&sequential, &context).unwrap(), "a".repeat(50));
+    }
+
+    #[test]
+    fn test_depth_error_is_located() {
+        let mut engine = TemplateEngine::new(".");
+        engine.set_max_nesting_depth(2);
+        let template = "{{for a in items}}\n  {{for b in items}}\n    {{for c in items}}{{c}}{{/for}}\n  {{/for}}\n{{/for}}";
+
+        match engine.render_string(template, &nested_context()) {
+            Err(TemplateError::ParseWithLocation { message, line, column, .. }) => {
+                assert!(message.contains("Maximum nesting depth of 2"), "{}", message);
+                assert!(message.contains("{{for c in items}}"), "{}", message);
+                assert_eq!((line, column), (3, 5));
+            }
+            other => panic!("expected located parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_thousands_of_unclosed_openings_fail_fast() {
+        let mut engine = TemplateEngine::new(".");
+        let context = nested_context();
+
+        for opening in ["{{for i in items}}", "{{if flag}}", "{{macro m()}}"] {
+            let template = opening.repeat(20_000);
+            assert_fast(Duration::from_secs(2), || {
+                assert!(engine.render_string(&template, &context).is_err(), "{}", opening);
+            });
+        }
+    }
+
+    #[test]
+    fn test_deep_balanced_nesting_is_rejected_not_overflowed() {
+        let mut engine = TemplateEngine::new(".");
+        let context = nested_context();
+        let template = format!("{}{{{{i}}}}{}", "{{for i in items}}".repeat(5_000), "{{/for}}".repeat(5_000));
+
+        assert_fast(Duration::from_secs(2), || {
+            assert!(engine.render_string(&template, &context).is_err());
+        });
+
+        // Nesting right at the limit still renders
+        let template = format!("{}{{{{i}}}}{}", "{{for i in items}}".repeat(128), "{{/for}}".repeat(128));
+        assert_eq!(engine.render_string(&template, &context).unwrap(), "1");
+    }
+
+    #[test]
+    fn test_generated_unbalanced_templates_terminate_cleanly() {
+        let mut engine = TemplateEngine::new(".");
+        let context = nested_context();
+        let tokens = [
+            "{{for i in items}}", "{{/for}}", "{{if flag}}", "{{/if}}", "{{else}}",
+            "{{i}}", "{{", "}}", "text", "{{/block}}", "{{macro m()}}", "{{/macro}}",
+        ];
+        let mut rng = XorShift(0x9e37_79b9_7f4a_7c15);
+
+        for _ in 0..200 {
+            let length = 1 + (rng.next() % 2_000) as usize;
+            let template: String = (0..length)
+                .map(|_| tokens[(rng.next() % tokens.len() as u64) as usize])
+                .collect();
+            // Only termination and the absence of panics matter here
+            assert_fast(Duration::from_secs(5), || {
+                let _ = engine.render_string(&template, &context);
+            });
+        }
+    }
+
+    #[test]
+    fn test_deep_data_paths_resolve_iteratively() {
+        let mut engine = TemplateEngine::new(".");
+        let mut value = TemplateValue::String("deep".to_string());
+        for _ in 0..2_000 {
+            let mut object = HashMap::new();
+            object.insert("a".to_string(), value);
+            value = TemplateValue::Object(object);
+        }
+        let mut context = TemplateContext::new();
+        context.set("root", value);
+
+        let path = format!("root{}", ".a".repeat(2_000));
+        let result = engine.render_string(&format!("{{{{{}}}}}{{{{if {}}}}}!{{{{/if}}}}", path, path), &context).unwrap();
+        assert_eq!(result, "deep!");
+    }
+
+    #[cfg(feature = "inheritance")]
+    #[test]
+    fn test_circular_inheritance_is_reported() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("a.html"), "{{extends \"b.html\"}}{{block body}}a{{/block}}").unwrap();
+        fs::write(templates_path.join("b.html"), "{{extends \"a.html\"}}{{block body}}b{{/block}}").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let error = engine.render("a.html", &TemplateContext::new()).unwrap_err();
+        assert!(error.to_string().contains("Circular template inheritance"), "{}", error);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Raw output audit: every unescaped output site with its location
+#[cfg(test)]
+mod raw_output_audit_tests {
+    use super::*;
+    use mystical_runic::RawOutputReason;
+
+    fn audit_fixtures() -> PathBuf {
+        let templates_path = create_temp_dir();
+        fs::create_dir_all(templates_path.join("partials")).unwrap();
+        fs::write(
+            templates_path.join("profile.html"),
+            "<h1>{{user.name}}</h1>\n<div>{{& user.bio}}</div>\n{{! {{& ignored}} }}<p>{{site.footer|safe}}</p>",
+        ).unwrap();
+        fs::write(
+            templates_path.join("partials/post.html"),
+            "{{for post in posts}}\n  {{post.body|markdown}}\n  {{params.snippet|upper|highlight:rust}}\n{{/for}}",
+        ).unwrap();
+        fs::write(templates_path.join("clean.html"), "<p>{{title|upper}}</p>").unwrap();
+        templates_path
+    }
+
+    #[test]
+    fn test_audit_finds_each_raw_construct() {
+        let templates_path = audit_fixtures();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let sites = engine.audit_raw_output().unwrap();
+        let summary: Vec<(&str, usize, usize, &str, RawOutputReason, bool)> = sites.iter()
+            .map(|site| (
+                site.template_name.as_str(), site.line, site.column, site.expression.as_str(),
+                site.reason.clone(), site.likely_user_controlled,
+            ))
+            .collect();
+
+        assert_eq!(summary, vec![
+            ("partials/post.html", 2, 3, "post.body|markdown", RawOutputReason::HtmlFilter("markdown".to_string()), false),
+            ("partials/post.html", 3, 3, "params.snippet|upper|highlight:rust", RawOutputReason::HtmlFilter("highlight".to_string()), true),
+            ("profile.html", 2, 6, "user.bio", RawOutputReason::RawTag, true),
+            ("profile.html", 3, 24, "site.footer|safe", RawOutputReason::SafeFilter, false),
+        ]);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_sanitized_output_is_not_reported() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("page.html"), "{{input.comment|markdown|sanitize}}{{input.bio|sanitize|upper}}").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let sites = engine.audit_raw_output().unwrap();
+        assert_eq!(sites.len(), 1);
+        assert_eq!(sites[0].expression, "input.bio|sanitize|upper");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_raw_tag_with_html_filter_reports_both_reasons() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("page.html"), "{{& input.comment|markdown}}").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let reasons: Vec<String> = engine.audit_raw_output().unwrap().iter()
+            .map(|site| site.reason.to_string())
+            .collect();
+        assert_eq!(reasons, vec!["raw tag", "html-producing filter 'markdown'"]);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[cfg(feature = "lsp")]
+    #[test]
+    fn test_lint_all_reports_raw_output_as_info() {
+        let templates_path = audit_fixtures();
+        fs::write(templates_path.join("broken.html"), "{{if ready}}<p>{{name|nosuchfilter}}</p>").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let results = engine.lint_all().unwrap();
+        assert_eq!(
+            results.keys().map(String::as_str).collect::<Vec<_>>(),
+            vec!["broken.html", "clean.html", "partials/post.html", "profile.html"]
+        );
+        assert!(results["clean.html"].is_empty());
+
+        // `safe` is a built-in filter: its output is reported as raw, not as unknown
+        let profile: Vec<_> = results["profile.html"].iter()
+            .filter(|d| d.code.as_deref() == Some("raw-output"))
+            .collect();
+        assert_eq!(profile.len(), results["profile.html"].len(), "{:?}", results["profile.html"]);
+        assert_eq!(profile.len(), 2, "{:?}", profile);
+        assert_eq!(profile[1].message, "Unescaped output of 'site.footer|safe' (safe filter)");
+        assert!(profile.iter().all(|d| d.severity == "info"));
+        assert!(profile[0].message.contains("'user.bio' (raw tag); the expression looks user-controlled"), "{}", profile[0].message);
+        assert_eq!((profile[0].line, profile[0].column), (2, 6));
+
+        // Structural problems are still errors; unknown variables are not reported without a context
+        let broken: Vec<&str> = results["broken.html"].iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(broken, vec!["Unknown filter: nosuchfilter", "Unclosed if directive"]);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn test_cli_audit_report() {
+        let templates_path = audit_fixtures();
+
+        let report = mystical_runic::audit_templates(templates_path.to_str().unwrap()).unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[2], "profile.html:2:6: user.bio (raw tag) [user-controlled]");
+        assert_eq!(lines[3], "profile.html:3:24: site.footer|safe (safe filter)");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Redaction of context values outside the rendered output
+#[cfg(test)]
+mod redaction_tests {
+    use super::*;
+    use mystical_runic::{Redact, TemplateError};
+
+    const TOKEN: &str = "sk-live-4f9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c";
+
+    fn secret_context() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("api_token", TOKEN);
+        context.set_string("name", "Ada");
+        let mut user = HashMap::new();
+        user.insert("email".to_string(), TemplateValue::String("ada@example.com".to_string()));
+        user.insert("password".to_string(), TemplateValue::String("hunter2-hunter2".to_string()));
+        context.set("user", TemplateValue::Object(user));
+        context
+    }
+
+    #[cfg(all(feature = "debug-tools", feature = "lsp"))]
+    #[test]
+    fn test_secrets_are_masked_in_debug_artifacts_but_rendered() {
+        let mut engine = TemplateEngine::new(".");
+        let context = secret_context();
+        let template = "{{name}} {{api_token}} {{user.password}}";
+
+        let debug = engine.render_string_with_debug(template, &context).unwrap();
+        assert_eq!(debug.output, format!("Ada {} hunter2-hunter2", TOKEN));
+        let debug_text = format!("{:?}", debug.debug_info);
+        assert!(!debug_text.contains(TOKEN) && !debug_text.contains("hunter2"), "{}", debug_text);
+        assert!(debug_text.contains("•••• (string, 40 chars)"), "{}", debug_text);
+        assert!(debug_text.contains("Some(\"Ada\")"), "{}", debug_text);
+
+        let hover = engine.get_hover_info_at_position(template, 15, &context).unwrap();
+        assert_eq!(hover.variable_name, "api_token");
+        assert_eq!(hover.variable_type, "String");
+        assert_eq!(hover.current_value, "•••• (string, 40 chars)");
+
+        let completions = engine.get_completions_at_position("{{api", 5, &context).unwrap();
+        assert!(completions.iter().all(|item| !format!("{:?}", item).contains(TOKEN)), "{:?}", completions);
+    }
+
+    #[test]
+    fn test_render_diff_masks_nested_secrets() {
+        let dir = create_temp_dir();
+        fs::write(dir.join("a.html"), "<p>{{api_token}}</p>").unwrap();
+        fs::write(dir.join("b.html"), "<p>{{user.password}}</p>").unwrap();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        let diff = engine.diff_renders("a.html", "b.html", &secret_context()).unwrap();
+        assert_eq!(diff.left_output, format!("<p>{}</p>", TOKEN));
+        assert_eq!(diff.variables.len(), 2);
+        assert_eq!(diff.variables[0].left, vec!["•••• (string, 40 chars)".to_string()]);
+        assert_eq!(diff.variables[1].right, vec!["•••• (string, 15 chars)".to_string()]);
+
+        engine.set_redaction(|_, _| Redact::Show);
+        let unredacted = engine.diff_renders("a.html", "b.html", &secret_context()).unwrap();
+        assert_eq!(unredacted.variables[0].left, vec![TOKEN.to_string()]);
+    }
+
+    #[test]
+    fn test_filter_errors_do_not_leak_secrets() {
+        let mut engine = TemplateEngine::new(".");
+        engine.enable_strict_mode();
+        let error = engine.render_string("{{api_token|int}}", &secret_context()).unwrap_err();
+        assert!(matches!(error, TemplateError::FilterError { .. }));
+        assert!(!error.to_string().contains(TOKEN), "{}", error);
+        assert!(error.to_string().contains("••••"), "{}", error);
+
+        let error = engine.render_string("{{name|int}}", &secret_context()).unwrap_err();
+        assert!(error.to_string().contains("Ada"), "{}", error);
+    }
+
+    #[cfg(all(feature = "debug-tools", feature = "lsp"))]
+    #[test]
+    fn test_custom_policy_hides_values() {
+        let mut engine = TemplateEngine::new(".");
+        engine.set_redaction(|path, value| match path {
+            "email" | "user.email" => Redact::Hide,
+            _ => Redact::secret_names(path, value),
+        });
+        let mut context = secret_context();
+        context.set_string("email", "ada@example.com");
+        let template = "{{email}} {{user.email}}";
+
+        assert_eq!(engine.get_hover_info_at_position(template, 4, &context).unwrap().current_value, "(hidden)");
+        let debug = engine.render_string_with_debug(template, &context).unwrap();
+        assert!(debug.output.starts_with("ada@example.com"));
+        let debug_text = format!("{:?}", debug.debug_info);
+        assert!(!debug_text.contains("ada@example.com") && !debug_text.contains("hunter2"), "{}", debug_text);
+
+        assert_eq!(Redact::secret_names("headers.X-Auth-Token", &TemplateValue::Bool(true)), Redact::Mask);
+        assert_eq!(Redact::secret_names("monkey.keyboard", &TemplateValue::Bool(true)), Redact::Show);
+    }
+}
+
+/// Panicking filter and helper tests
+#[cfg(test)]
+mod plugin_panic_tests {
+    use super::*;
+    use mystical_runic::{Priority, QueueOutcome, RenderQueue, SharedTemplateEngine, TemplateError};
+
+    fn engine() -> TemplateEngine {
+        let temp_dir = create_temp_dir();
+        fs::write(temp_dir.join("page.html"), "<h1>{{name|third_letter}}</h1>").unwrap();
+        fs::write(temp_dir.join("ok.html"), "<p>{{name|shout}}</p>").unwrap();
+        let mut engine = TemplateEngine::new(temp_dir.to_str().unwrap());
+        engine.register_filter("third_letter", |value: &str, _args: &[&str]| {
+            Ok(value.chars().collect::<Vec<_>>()[2].to_string())
+        });
+        engine.register_filter("shout", |value: &str, _args: &[&str]| Ok(value.to_uppercase()));
+        engine.register_helper("boom", |_args| panic!("helper exploded"));
+        engine
+    }
+
+    fn named(name: &str) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("name", name);
+        context
+    }
+
+    #[test]
+    fn test_panicking_filter_fails_strict_renders_with_a_filter_error() {
+        let mut engine = engine();
+        engine.enable_strict_mode();
+        let error = engine.render("page.html", &named("ab")).unwrap_err();
+        match error.root_cause() {
+            TemplateError::FilterError { filter_name, value, message } => {
+                assert_eq!(filter_name, "third_letter");
+                assert_eq!(value, "ab");
+                assert!(message.contains("filter panicked") && message.contains("out of bounds"), "{}", message);
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert_eq!(error.kind(), "filter");
+
+        // The engine is unharmed
+        assert_eq!(engine.render("page.html", &named("abc")).unwrap(), "<h1>c</h1>");
+        assert_eq!(engine.render("ok.html", &named("abc")).unwrap(), "<p>ABC</p>");
+    }
+
+    #[test]
+    fn test_panicking_filter_falls_back_in_lenient_mode() {
+        let mut engine = engine();
+        assert_eq!(engine.render("page.html", &named("ab")).unwrap(), "<h1>ab</h1>");
+        assert_eq!(engine.render("ok.html", &named("ab")).unwrap(), "<p>AB</p>");
+    }
+
+    #[test]
+    fn test_panicking_helper_raises_a_helper_error() {
+        let mut engine = engine();
+        let error = engine.render_string("{{boom()}}", &TemplateContext::new()).unwrap_err();
+        match &error {
+            TemplateError::HelperError { helper_name, message } => {
+                assert_eq!(helper_name, "boom");
+                assert_eq!(message, "helper panicked: helper exploded");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        assert_eq!(error.kind(), "helper");
+        assert_eq!(error.to_string(), "Helper error in 'boom': helper panicked: helper exploded");
+        assert!(engine.render_string("{{set x = boom()}}{{x}}", &TemplateContext::new()).is_err());
+
+        // Error boundaries rescue it like any other error
+        assert_eq!(
+            engine.render_string("{{try}}{{boom()}}{{rescue}}[{{error.kind}}]{{/try}} ok", &TemplateContext::new()).unwrap(),
+            "[helper] ok"
+        );
+        assert_eq!(engine.render("ok.html", &named("x")).unwrap(), "<p>X</p>");
+    }
+
+    #[test]
+    fn test_panics_dont_take_down_parallel_renders() {
+        let mut engine = engine();
+        engine.enable_strict_mode();
+        let mut queue = RenderQueue::new(SharedTemplateEngine::new(engine));
+        queue.on_progress(|progress| {
+            if progress.index == 0 {
+                panic!("observer exploded");
+            }
+        });
+        let broken = queue.push("page.html", named("ab"), Priority::Normal);
+        let fine = queue.push("page.html", named("abc"), Priority::Normal);
+        let report = queue.run(2, None);
+        let QueueOutcome::Failed(error) = &report.items[broken].outcome else { panic!("the panicking filter didn't fail") };
+        assert!(matches!(error.root_cause(), TemplateError::FilterError { .. }), "{:?}", error);
+        assert_eq!(report.items[fine].output(), Some("<h1>c</h1>"));
+        assert_eq!((report.completed, report.failed), (1, 1));
+    }
+}
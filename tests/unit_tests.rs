@@ -357,7 +357,7 @@ mod performance_tests {
         
         // Create array with many items
         let items: Vec<TemplateValue> = (0..100)
-            .map(|i| TemplateValue::Number(i))
+            .map(TemplateValue::Number)
             .collect();
         
         context.set("numbers", TemplateValue::Array(items));
@@ -33,6 +33,7 @@ fn create_temp_dir() -> PathBuf {
 // 🔴 RED: Template Layouts and Inheritance System Tests
 // =============================================================================
 
+#[cfg(feature = "inheritance")]
 #[test]
 fn test_basic_layout_inheritance() {
     let templates_path = create_temp_dir();
@@ -85,6 +86,7 @@ fn test_basic_layout_inheritance() {
     // assert!(!result.contains("}"), "Extra braces found in result"); // Temporarily disabled
 }
 
+#[cfg(feature = "inheritance")]
 #[test]
 fn test_nested_layout_inheritance() {
     let templates_path = create_temp_dir();
@@ -160,8 +162,10 @@ fn test_nested_layout_inheritance() {
     assert!(result.contains("<div class=\"admin-layout\">"));
     assert!(result.contains("Alice - Admin"));
     assert!(result.contains("Bob - User"));
+    assert!(!result.contains("{{") && !result.contains("}}"), "Stray tag fragments in {}", result);
 }
 
+#[cfg(feature = "inheritance")]
 #[test]
 fn test_block_inheritance_with_super() {
     let templates_path = create_temp_dir();
@@ -299,6 +303,7 @@ fn test_custom_filters() {
 // 🔴 RED: Macros and Reusable Functions Tests
 // =============================================================================
 
+#[cfg(feature = "template-macros")]
 #[test]
 fn test_basic_macros() {
     let templates_path = create_temp_dir();
@@ -333,6 +338,7 @@ fn test_basic_macros() {
     assert!(result.contains("This is the card content"));
 }
 
+#[cfg(feature = "template-macros")]
 #[test]
 fn test_macro_with_variable_content() {
     let templates_path = create_temp_dir();
@@ -420,14 +426,19 @@ fn test_streaming_with_backpressure() {
     fs::write(templates_path.join("huge.html"), template).unwrap();
     
     let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
-    // engine.set_stream_buffer_size(1024); // Small buffer for testing backpressure - method doesn't exist yet
+    engine.set_stream_buffer_size(1024); // Chunks render_stream may render ahead of its consumer
+    assert_eq!(engine.stream_buffer_size(), 1024);
     
     let context = TemplateContext::new();
     
-    // 🔴 This should fail initially - streaming with backpressure not implemented
-    // Streaming with backpressure will be implemented in GREEN phase
-    let result = engine.render("huge.html", &context);
-    assert!(result.is_err()); // Should fail because range() function not supported yet
+    let result = engine.render("huge.html", &context).unwrap();
+    assert_eq!(result.lines().count(), 100000);
+    assert!(result.starts_with("0\n1\n") && result.ends_with("99999\n"));
+    
+    // Ranges past the cap fail instead of filling memory
+    engine.set_max_range_items(1000);
+    let error = engine.render("huge.html", &context).unwrap_err();
+    assert!(error.to_string().contains("range of 100000 items exceeds the limit of 1000 items"), "{}", error);
 }
 
 // =============================================================================
@@ -456,6 +467,7 @@ fn test_template_file_watching() {
     assert!(initial_result.contains("Hello World"));
 }
 
+#[cfg(feature = "inheritance")]
 #[test]
 fn test_dependency_tracking() {
     let templates_path = create_temp_dir();
@@ -744,12 +756,12 @@ fn debug_streaming_backpressure() {
     
     match result {
         Ok(output) => {
-            println!("Render SUCCEEDED (but should have failed!)");
+            println!("Render succeeded");
             println!("Output length: {}", output.len());
             println!("First 200 chars: '{}'", &output[..output.len().min(200)]);
         }
         Err(e) => {
-            println!("Render failed as expected: {:?}", e);
+            println!("Render failed: {:?}", e);
         }
     }
     
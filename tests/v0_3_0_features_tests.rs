@@ -1,8 +1,13 @@
+mod common;
+
+use common::*;
 use mystical_runic::{RuneEngine, RuneScroll, RuneSymbol};
+#[cfg(feature = "i18n")]
 use std::collections::HashMap;
 
 // 🔴 RED PHASE - Writing failing tests first for v0.3.0 features
 
+#[cfg(feature = "i18n")]
 #[cfg(test)]
 mod i18n_tests {
     use super::*;
@@ -170,7 +175,7 @@ mod custom_filter_tests {
         
         // Register a custom repeat filter
         engine.register_filter("repeat", |input: &str, args: &[&str]| {
-            let times = args.get(0).map_or("1", |v| v).parse::<usize>().unwrap_or(1);
+            let times = args.first().map_or("1", |v| v).parse::<usize>().unwrap_or(1);
             Ok(input.repeat(times))
         });
         
@@ -272,4 +277,488 @@ mod math_filter_tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), "35%");
     }
-}
\ No newline at end of file
+}
+
+/// Translation key extraction and the missing/unused report
+#[cfg(feature = "i18n")]
+#[cfg(test)]
+mod translation_key_tests {
+    use super::*;
+    use mystical_runic::TranslationKind;
+
+    fn i18n_fixtures() -> PathBuf {
+        let templates_path = create_temp_dir();
+        fs::create_dir_all(templates_path.join("emails")).unwrap();
+        fs::write(
+            templates_path.join("cart.html"),
+            "<h1>{{t \"cart.title\"}}</h1>\n<p>{{t \"cart.greeting\" name=user.name count=items|length}}</p>\n{{plural count \"item\" \"items\"}}",
+        ).unwrap();
+        fs::write(templates_path.join("emails/receipt.html"), "{{t 'cart.title'}} - {{total}}").unwrap();
+        templates_path
+    }
+
+    fn table(entries: &[(&str, &str)]) -> HashMap<String, String> {
+        entries.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    fn i18n_engine(templates_path: &std::path::Path) -> TemplateEngine {
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.set_translations("en", table(&[
+            ("cart.title", "Cart"), ("cart.greeting", "Hello {{name}}"),
+            ("item", "item"), ("items", "items"), ("cart.legacy_banner", "Old banner"),
+        ]));
+        engine.set_translations("fr", table(&[
+            ("cart.title", "Panier"), ("item", "article"), ("items", "articles"),
+        ]));
+        engine
+    }
+
+    #[test]
+    fn test_named_arguments_are_bound_for_the_translation() {
+        let templates_path = i18n_fixtures();
+        let mut engine = i18n_engine(&templates_path);
+        engine.set_locale("en");
+        let mut context = TemplateContext::new();
+        let mut user = HashMap::new();
+        user.insert("name".to_string(), TemplateValue::String("Ada".to_string()));
+        context.set("user", TemplateValue::Object(user));
+        context.set_string("name", "outer");
+
+        let render = |engine: &mut TemplateEngine, template: &str| engine.render_string(template, &context).unwrap();
+        assert_eq!(render(&mut engine, "{{t \"cart.greeting\" name=user.name}}"), "Hello Ada");
+        assert_eq!(render(&mut engine, "{{t \"cart.greeting\" name=\"Bob\"}}"), "Hello Bob");
+        assert_eq!(render(&mut engine, "{{t \"cart.greeting\" name=user.name|upper}}"), "Hello ADA");
+        // Without arguments the outer context is used; a missing argument renders empty
+        assert_eq!(render(&mut engine, "{{t \"cart.greeting\"}}"), "Hello outer");
+        assert_eq!(render(&mut engine, "{{t \"cart.greeting\" name=user.nickname}}"), "Hello ");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_argument_values_are_not_rendered_as_tags() {
+        let templates_path = i18n_fixtures();
+        let mut engine = i18n_engine(&templates_path);
+        engine.set_locale("en");
+        let mut context = TemplateContext::new();
+        context.set_string("secret", "S3CRET");
+        context.set_string("tag", "{{secret}}");
+        context.set_string("lp", "{{for i in range(0,3)}}x{{/for}}");
+        context.set_string("inc", "{{include \"cart.html\"}}");
+
+        for (argument, expected) in [
+            ("tag", "Hello {{secret}}"),
+            ("lp", "Hello {{for i in range(0,3)}}x{{/for}}"),
+            ("inc", "Hello {{include &quot;cart.html&quot;}}"),
+        ] {
+            let template = format!("{{{{t \"cart.greeting\" name={}}}}} {{{{t \"cart.title\"}}}}", argument);
+            assert_eq!(engine.render_string(&template, &context).unwrap(), format!("{} Cart", expected));
+        }
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_extract_keys_of_one_template() {
+        let templates_path = i18n_fixtures();
+        let mut engine = i18n_engine(&templates_path);
+
+        let usages = engine.extract_translation_keys("cart.html").unwrap();
+        let summary: Vec<(&str, TranslationKind, usize, usize)> = usages.iter()
+            .map(|usage| (usage.key.as_str(), usage.kind, usage.line, usage.column))
+            .collect();
+        assert_eq!(summary, vec![
+            ("cart.title", TranslationKind::Translate, 1, 5),
+            ("cart.greeting", TranslationKind::Translate, 2, 4),
+            ("item", TranslationKind::Plural, 3, 1),
+            ("items", TranslationKind::Plural, 3, 1),
+        ]);
+        assert_eq!(usages[1].arguments, vec!["name", "count"]);
+        assert_eq!(usages[2].arguments, vec!["count"]);
+        assert_eq!(usages[1].missing_locales(), vec!["fr"]);
+        assert!(usages[0].missing_locales().is_empty());
+        assert_eq!(usages[0].locales.get("en"), Some(&true));
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_report_lists_missing_and_unused_keys() {
+        let templates_path = i18n_fixtures();
+        let mut engine = i18n_engine(&templates_path);
+
+        let all = engine.extract_all_translation_keys().unwrap();
+        assert_eq!(all.len(), 5);
+        assert_eq!(all[4].template_name, "emails/receipt.html");
+
+        let report = engine.translation_report().unwrap();
+        assert!(!report.is_complete());
+        assert_eq!(report.missing.len(), 1);
+        assert_eq!(report.missing["fr"], vec!["cart.greeting"]);
+        assert_eq!(report.unused.len(), 1);
+        assert_eq!(report.unused["en"], vec!["cart.legacy_banner"]);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_complete_translations() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("page.html"), "{{t \"hello\"}}").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.set_translations("fr", table(&[("hello", "Bonjour")]));
+
+        let report = engine.translation_report().unwrap();
+        assert!(report.is_complete());
+        assert_eq!(report.to_string(), "Missing translations: none\nUnused translations: none\n");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn test_cli_i18n_report() {
+        let templates_path = i18n_fixtures();
+        let translations = r#"{
+            "en": {"cart.title": "Cart", "cart.greeting": "Hello", "item": "item", "items": "items", "cart.legacy_banner": "Old"},
+            "fr": {"cart.title": "Panier", "item": "article", "items": "articles"}
+        }"#;
+
+        let report = mystical_runic::i18n_report(templates_path.to_str().unwrap(), translations).unwrap();
+        assert_eq!(report, "Missing translations:\n  fr: cart.greeting (cart.html:2:4)\nUnused translations:\n  en: cart.legacy_banner\n");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Nested translation catalogs and `{{t_scope}}`
+#[cfg(feature = "i18n")]
+#[cfg(test)]
+mod nested_translation_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+
+    fn object(entries: Vec<(&str, TemplateValue)>) -> TemplateValue {
+        TemplateValue::Object(entries.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+    }
+
+    fn text(value: &str) -> TemplateValue {
+        TemplateValue::String(value.to_string())
+    }
+
+    fn french_engine() -> TemplateEngine {
+        let mut engine = TemplateEngine::new("templates");
+        let catalog = object(vec![
+            ("title", text("Accueil")),
+            ("nav", object(vec![
+                ("products", text("Produits")),
+                ("item", text("Voir {{name}}")),
+                ("footer", object(vec![("legal", text("Mentions légales"))])),
+            ])),
+            ("limit", TemplateValue::Number(3)),
+        ]);
+        engine.set_translations_nested("fr", catalog).unwrap();
+        engine.set_locale("fr");
+        engine
+    }
+
+    #[test]
+    fn test_nested_catalog_is_flattened_to_dotted_keys() {
+        let mut engine = french_engine();
+        let result = engine.render_string("{{t \"title\"}}|{{t \"nav.products\"}}|{{t \"nav.footer.legal\"}}", &TemplateContext::new()).unwrap();
+        assert_eq!(result, "Accueil|Produits|Mentions légales");
+        assert_eq!(engine.get_translation("limit"), "3");
+    }
+
+    #[test]
+    fn test_scoped_lookup_inside_loops() {
+        let mut engine = french_engine();
+        let mut context = TemplateContext::new();
+        context.set("items", TemplateValue::Array(vec![text("A"), text("B")]));
+        let template = "{{t_scope \"nav\"}}{{t \"products\"}}:{{for item in items}}[{{t \"item\" name=item}}]{{/for}}\
+            {{t_scope \"footer\"}} {{t \"legal\"}}{{/t_scope}}{{/t_scope}} {{t \"title\"}}";
+        let result = engine.render_string(template, &context).unwrap();
+        assert_eq!(result, "Produits:[Voir A][Voir B] Mentions légales Accueil");
+        assert!(engine.check_syntax(template).is_ok());
+    }
+
+    #[test]
+    fn test_flat_key_and_nested_path_conflict() {
+        let mut engine = french_engine();
+        let catalog = object(vec![
+            ("nav.products", text("Produits")),
+            ("nav", object(vec![("products", text("Catalogue"))])),
+        ]);
+        let error = engine.set_translations_nested("fr", catalog).unwrap_err();
+        assert!(matches!(error, TemplateError::Template(_)));
+        assert!(error.to_string().contains("Translation key 'nav.products' of locale 'fr' is defined twice"), "{}", error);
+        assert_eq!(engine.get_translation("nav.products"), "Produits");
+
+        let error = engine.set_translations_nested("de", object(vec![("menu", TemplateValue::Array(vec![]))])).unwrap_err();
+        assert!(error.to_string().contains("Translation 'menu' of locale 'de' is an array"), "{}", error);
+    }
+
+    #[test]
+    fn test_unbalanced_scopes_are_reported() {
+        let mut engine = french_engine();
+        let error = engine.render_string("{{t_scope \"nav\"}}{{t \"products\"}}", &TemplateContext::new()).unwrap_err();
+        assert!(error.to_string().contains("Unclosed translation scope 'nav'"), "{}", error);
+        assert!(engine.check_syntax("{{t_scope nav}}{{/t_scope}}").is_err());
+        assert!(engine.check_syntax("{{t_scope \"nav\"}}").is_err());
+    }
+}
+
+/// Translation modes: missing-key markers, pseudo-localization and the
+/// missing-key report
+#[cfg(test)]
+#[cfg(feature = "i18n")]
+mod translation_mode_tests {
+    use super::*;
+    use mystical_runic::{MissingTranslation, TranslationMode};
+
+    fn english_engine(template_dir: &str) -> TemplateEngine {
+        let mut engine = TemplateEngine::new(template_dir);
+        engine.set_translations("en", HashMap::from([
+            ("save".to_string(), "Save".to_string()),
+            ("greeting".to_string(), "Hello {{name}}, you have {count} items".to_string()),
+            ("status.active".to_string(), "Active".to_string()),
+        ]));
+        engine.set_locale("en");
+        engine
+    }
+
+    #[test]
+    fn test_normal_mode_keeps_current_fallbacks() {
+        let mut engine = english_engine(".");
+        assert_eq!(engine.get_translation_mode(), TranslationMode::Normal);
+        let mut context = TemplateContext::new();
+        context.set_string("status", "pending");
+        context.set_bool("ok", true);
+
+        let output = engine.render_string(r#"{{t "save"}} {{t "cancel"}} {{status|translate:"status."}} {{ok|yesno}}"#, &context).unwrap();
+        assert_eq!(output, "Save cancel pending Yes");
+    }
+
+    #[test]
+    fn test_mark_missing_marks_every_kind_of_lookup() {
+        let mut engine = english_engine(".");
+        engine.set_translation_mode(TranslationMode::MarkMissing);
+        let mut context = TemplateContext::new();
+        context.set_string("status", "pending");
+        context.set_string("active", "active");
+        context.set_bool("ok", false);
+
+        let output = engine.render_string(
+            r#"{{t "save"}} {{t "cancel"}} {{status|translate:"status."}} {{active|translate:"status."}} {{ok|yesno}}"#,
+            &context,
+        ).unwrap();
+        assert_eq!(output, "Save ⟦cancel⟧ ⟦status.pending⟧ Active ⟦no⟧");
+    }
+
+    #[test]
+    fn test_pseudo_localization_keeps_placeholders() {
+        let mut engine = english_engine(".");
+        engine.set_translation_mode(TranslationMode::PseudoLocalize);
+        let mut context = TemplateContext::new();
+        context.set_string("name", "Ada");
+
+        let output = engine.render_string(r#"{{t "greeting"}}"#, &context).unwrap();
+        assert_eq!(output, "⟪Ĥélló Ada, ýóú ĥávé {count} ítémš~~~~~~⟫");
+
+        assert_eq!(engine.render_string(r#"{{t "save"}}"#, &context).unwrap(), "⟪Šávé~~⟫");
+    }
+
+    #[test]
+    fn test_pseudo_localization_applies_to_plural_forms_and_filters() {
+        let mut engine = english_engine(".");
+        engine.set_translation_mode(TranslationMode::PseudoLocalize);
+        let mut context = TemplateContext::new();
+        context.set_number("count", 2);
+        context.set_string("status", "active");
+
+        assert_eq!(engine.render_string(r#"{{plural count "file" "files"}}"#, &context).unwrap(), "⟪fíléš~~⟫");
+        assert_eq!(engine.render_string(r#"{{status|translate:"status."}}"#, &context).unwrap(), "⟪Åçtívé~~⟫");
+    }
+
+    #[test]
+    fn test_missing_key_report_lists_keys_of_the_last_render() {
+        let mut engine = english_engine(".");
+        let mut context = TemplateContext::new();
+        context.set_bool("ok", true);
+
+        engine.render_string(r#"{{t "cancel"}} {{t "save"}} {{t "cancel"}} {{ok|yesno}}"#, &context).unwrap();
+        assert_eq!(engine.missing_translations(), vec![
+            MissingTranslation { key: "cancel".to_string(), locale: Some("en".to_string()), count: 2 },
+            MissingTranslation { key: "yes".to_string(), locale: Some("en".to_string()), count: 1 },
+            MissingTranslation { key: "no".to_string(), locale: Some("en".to_string()), count: 1 },
+        ]);
+
+        engine.render_string(r#"{{t "save"}}"#, &context).unwrap();
+        assert!(engine.missing_translations().is_empty());
+    }
+
+    #[test]
+    fn test_mode_can_be_chosen_per_render() {
+        let dir = create_temp_dir();
+        fs::write(dir.join("page.html"), r#"{{t "save"}} {{t "cancel"}}"#).unwrap();
+        let mut engine = english_engine(dir.to_str().unwrap());
+        let context = TemplateContext::new();
+
+        let output = engine.render_with_translation_mode("page.html", &context, TranslationMode::PseudoLocalize).unwrap();
+        assert_eq!(output, "⟪Šávé~~⟫ ⟦cancel⟧");
+        assert_eq!(engine.missing_translations().len(), 1);
+        assert_eq!(engine.get_translation_mode(), TranslationMode::Normal);
+
+        let output = engine.render_string_with_translation_mode(r#"{{t "cancel"}}"#, &context, TranslationMode::MarkMissing).unwrap();
+        assert_eq!(output, "⟦cancel⟧");
+        assert_eq!(engine.render_string(r#"{{t "cancel"}}"#, &context).unwrap(), "cancel");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+/// Translation hot swap tests
+#[cfg(test)]
+#[cfg(feature = "i18n")]
+mod translation_swap_tests {
+    use super::*;
+    use mystical_runic::{SharedTemplateEngine, TemplateError};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    fn catalog(text: &str) -> HashMap<String, String> {
+        ["title", "item", "footer"].iter()
+            .map(|key| (key.to_string(), text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_swap_versions_count_up_per_locale() {
+        let mut engine = TemplateEngine::new(".");
+        assert_eq!(engine.translation_version("fr"), None);
+
+        let first = engine.swap_translations("fr", catalog("Bonjour"));
+        let second = engine.swap_translations("fr", catalog("Salut"));
+        let english = engine.swap_translations("en", catalog("Hello"));
+        assert_eq!((first.number(), second.number(), english.number()), (1, 2, 1));
+        assert!(second > first);
+        assert_eq!(second.to_string(), "v2");
+        assert_eq!(engine.translation_version("fr"), Some(second));
+
+        engine.set_locale("fr");
+        assert_eq!(engine.render_string("{{t \"title\"}}", &TemplateContext::new()).unwrap(), "Salut");
+        engine.set_locale("en");
+        assert_eq!(engine.render_string("{{t \"title\"}}", &TemplateContext::new()).unwrap(), "Hello");
+    }
+
+    #[test]
+    fn test_rollback_restores_an_earlier_version() {
+        let mut engine = TemplateEngine::new(".");
+        engine.set_locale("fr");
+        let good = engine.swap_translations("fr", catalog("Bonjour"));
+        let bad = engine.swap_translations("fr", catalog("Bonjuor"));
+
+        engine.rollback_translations("fr", good).unwrap();
+        assert_eq!(engine.translation_version("fr"), Some(good));
+        assert_eq!(engine.render_string("{{t \"title\"}}", &TemplateContext::new()).unwrap(), "Bonjour");
+
+        // Versions keep counting after a rollback, and rolling forward works too
+        let next = engine.swap_translations("fr", catalog("Coucou"));
+        assert_eq!(next.number(), 3);
+        engine.rollback_translations("fr", bad).unwrap();
+        assert_eq!(engine.render_string("{{t \"title\"}}", &TemplateContext::new()).unwrap(), "Bonjuor");
+    }
+
+    #[test]
+    fn test_rollback_to_unknown_version_fails() {
+        let engine = TemplateEngine::new(".");
+        let first = engine.swap_translations("fr", catalog("v1"));
+        for index in 2..=20 {
+            engine.swap_translations("fr", catalog(&format!("v{}", index)));
+        }
+        let current = engine.translation_version("fr");
+
+        let error = engine.rollback_translations("fr", first).unwrap_err();
+        assert!(matches!(error, TemplateError::Template(_)), "{:?}", error);
+        assert!(engine.rollback_translations("de", first).is_err());
+        assert_eq!(engine.translation_version("fr"), current);
+    }
+
+    #[test]
+    fn test_clones_share_swaps() {
+        let mut engine = TemplateEngine::new(".");
+        engine.set_locale("fr");
+        let mut clone = Clone::clone(&engine);
+        engine.swap_translations("fr", catalog("Bonjour"));
+        assert_eq!(clone.render_string("{{t \"title\"}}", &TemplateContext::new()).unwrap(), "Bonjour");
+        clone.set_translations("fr", catalog("Salut"));
+        assert_eq!(engine.translation_version("fr").unwrap().number(), 2);
+    }
+
+    #[test]
+    fn test_render_in_progress_keeps_its_translations() {
+        let mut engine = TemplateEngine::new(".");
+        engine.set_locale("fr");
+        engine.swap_translations("fr", catalog("old"));
+        let swapper: Arc<Mutex<Option<TemplateEngine>>> = Arc::new(Mutex::new(None));
+        let hook = Arc::clone(&swapper);
+        engine.register_helper("swap", move |_args| {
+            if let Some(engine) = hook.lock().unwrap().as_ref() {
+                engine.swap_translations("fr", catalog("new"));
+            }
+            Ok(TemplateValue::String(String::new()))
+        });
+        *swapper.lock().unwrap() = Some(Clone::clone(&engine));
+
+        let template = "{{t \"title\"}}{{swap()}}{{t \"footer\"}}";
+        assert_eq!(engine.render_string(template, &TemplateContext::new()).unwrap(), "oldold");
+        assert_eq!(engine.render_string("{{t \"title\"}}", &TemplateContext::new()).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_concurrent_renders_never_mix_versions() {
+        let temp_dir = create_temp_dir();
+        fs::write(
+            temp_dir.join("page.html"),
+            "{{t \"title\"}}|{{for item in items}}{{t \"item\"}}|{{/for}}{{t \"footer\"}}|",
+        ).unwrap();
+        let mut engine = TemplateEngine::new(temp_dir.to_str().unwrap());
+        engine.set_locale("fr");
+        engine.swap_translations("fr", catalog("v0"));
+        engine.warm_up().unwrap();
+        let shared = SharedTemplateEngine::new(engine);
+
+        let mut context = TemplateContext::new();
+        context.set("items", TemplateValue::Array((0..50).map(TemplateValue::Number).collect()));
+        let context = Arc::new(context);
+        let done = Arc::new(AtomicBool::new(false));
+
+        let renderers: Vec<_> = (0..4).map(|_| {
+            let (shared, context, done) = (shared.clone(), Arc::clone(&context), Arc::clone(&done));
+            thread::spawn(move || {
+                let mut renders = 0;
+                while !done.load(Ordering::Relaxed) || renders == 0 {
+                    let output = shared.render("page.html", &context).unwrap();
+                    let words: Vec<&str> = output.split('|').filter(|word| !word.is_empty()).collect();
+                    assert_eq!(words.len(), 52, "{}", output);
+                    assert!(words.iter().all(|word| *word == words[0]), "mixed versions: {}", output);
+                    renders += 1;
+                }
+                renders
+            })
+        }).collect();
+
+        for version in 1..=1000 {
+            let swapped = shared.swap_translations("fr", catalog(&format!("v{}", version)));
+            assert_eq!(swapped.number(), version + 1);
+        }
+        done.store(true, Ordering::Relaxed);
+        for renderer in renderers {
+            assert!(renderer.join().unwrap() > 0);
+        }
+
+        assert_eq!(shared.render("page.html", &context).unwrap().split('|').next(), Some("v1000"));
+        assert_eq!(shared.generation(), 0);
+    }
+}
@@ -35,6 +35,7 @@ mod basic_v040_tests {
         assert!(!engine.is_hot_reload_enabled());
     }
 
+    #[cfg(feature = "debug-tools")]
     #[test]
     fn test_render_string_with_debug_basic() {
         // 🟢 GREEN: Test debug rendering with simple template
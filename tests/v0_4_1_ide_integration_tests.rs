@@ -1,6 +1,11 @@
 //! Tests for v0.4.1 IDE Integration features
 //! Following strict TDD methodology: RED → GREEN → REFACTOR
 
+#![cfg(feature = "lsp")]
+
+mod common;
+
+use common::*;
 use mystical_runic::{RuneEngine, RuneScroll};
 
 /// Test suite for Language Server Protocol features
@@ -270,4 +275,94 @@ mod error_squiggles_tests {
         assert_eq!(definition.line, 2);
         assert_eq!(definition.column, 9);
     }
-}
\ No newline at end of file
+}
+
+/// Tests for the tag balance check of rendered output
+#[cfg(all(test, feature = "lsp", debug_assertions))]
+mod html_validation_tests {
+    use super::*;
+
+    fn featured(flag: bool) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_bool("featured", flag);
+        context.set_string("title", "Launch");
+        context
+    }
+
+    const FEATURED_TEMPLATE: &str = "<main>\n{{if featured}}\n<div class=\"featured\"><h2>{{title}}</h2>\n{{/if}}\n<p>Body</p>\n</main>";
+
+    #[test]
+    fn test_unclosed_tag_in_a_conditional_points_at_the_conditional() {
+        let mut engine = TemplateEngine::new("templates");
+        engine.set_validate_html(true);
+
+        let output = engine.render_string(FEATURED_TEMPLATE, &featured(true)).unwrap();
+        assert!(output.contains("<div class=\"featured\">"));
+
+        let diagnostics = engine.html_diagnostics();
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].line, 2);
+        assert_eq!(diagnostics[0].severity, "warning");
+        assert_eq!(diagnostics[0].code.as_deref(), Some("html-balance"));
+        assert_eq!(
+            diagnostics[0].message,
+            "<div> is not closed before </main> (line 3, column 1, inside {{if featured}})"
+        );
+
+        // The branch that isn't taken leaves balanced markup
+        engine.render_string(FEATURED_TEMPLATE, &featured(false)).unwrap();
+        assert!(engine.html_diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_named_templates_are_checked_and_located() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("card.html"), "<section>\n  <span>{{title}}\n</section>\n</div>").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.set_validate_html(true);
+
+        engine.render("card.html", &featured(true)).unwrap();
+        let found: Vec<(usize, usize, &str)> = engine.html_diagnostics().iter()
+            .map(|diagnostic| (diagnostic.line, diagnostic.column, diagnostic.message.as_str()))
+            .collect();
+        assert_eq!(found, vec![
+            (2, 3, "<span> is not closed before </section>"),
+            (4, 1, "</div> has no matching opening tag"),
+        ]);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_void_elements_comments_and_raw_content_are_not_reported() {
+        let mut engine = TemplateEngine::new("templates");
+        engine.set_validate_html(true);
+        let template = concat!(
+            "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><link rel=\"stylesheet\" href=\"a.css\">",
+            "<style>div > p { color: red }</style>",
+            "<script>if (a < b && c > d) { document.write(\"<div>\"); }</script></head>",
+            "<body><!-- <div> left open on purpose --><br><img src=\"x.png\" alt=\"a > b\"><input type=\"text\"/>",
+            "<pre><code>&lt;div&gt; <b>bold</b> if a < b</code></pre>",
+            "<svg><![CDATA[ <g> ]]><path d=\"M0 0\"/></svg>",
+            "<ul><li>One<li>Two</ul><p>Open paragraph",
+            "<textarea><div></textarea></body></html>",
+        );
+
+        engine.render_string(template, &TemplateContext::new()).unwrap();
+        assert!(engine.html_diagnostics().is_empty(), "{:?}", engine.html_diagnostics());
+    }
+
+    #[test]
+    fn test_validation_is_off_by_default() {
+        let mut engine = TemplateEngine::new("templates");
+        assert!(!engine.is_validate_html_enabled());
+        engine.render_string(FEATURED_TEMPLATE, &featured(true)).unwrap();
+        assert!(engine.html_diagnostics().is_empty());
+
+        engine.set_validate_html(true);
+        engine.render_string(FEATURED_TEMPLATE, &featured(true)).unwrap();
+        assert_eq!(engine.html_diagnostics().len(), 1);
+        engine.set_validate_html(false);
+        assert!(engine.html_diagnostics().is_empty());
+    }
+}
@@ -1,6 +1,9 @@
 //! Tests for v0.5.0 Ecosystem Integration features
 //! Following strict TDD methodology: RED → GREEN → REFACTOR
 
+mod common;
+
+use common::*;
 use mystical_runic::{RuneEngine, RuneScroll, EcosystemTemplateEngine};
 #[cfg(feature = "wasm")]
 use mystical_runic::RuneSymbol;
@@ -24,7 +27,6 @@ use mystical_runic::{process_template, process_files, batch_process, load_config
 #[cfg(feature = "async")]
 mod async_support_tests {
     use super::*;
-    use tokio;
 
     #[tokio::test]
     async fn test_async_render_string() {
@@ -253,6 +255,15 @@ mod cli_tools_tests {
         assert_eq!(result, "Hello CLI User! You have 5 messages.");
     }
 
+    #[test]
+    fn test_cli_json_null_is_null() {
+        let template_content = "{{if avatar is null}}none{{/if}}|{{avatar}}|{{if avatar is defined}}set{{/if}}|{{if photo is defined}}photo{{/if}}";
+        let data = r#"{"avatar": null}"#;
+        
+        let result = process_template(template_content, data).unwrap();
+        assert_eq!(result, "none||set|");
+    }
+
     #[test]
     fn test_cli_file_processing() {
         // 🔴 RED: This test should fail initially
@@ -330,12 +341,12 @@ mod ecosystem_error_handling_tests {
     #[test]
     fn test_feature_not_enabled_errors() {
         // 🔴 RED: This test should fail initially
-        let engine = RuneEngine::new(".");
         let _context = RuneScroll::new();
         
         // Should provide helpful errors when features are not enabled
         #[cfg(not(feature = "async"))]
         {
+            let engine = RuneEngine::new(".");
             let result = engine.try_async_operation();
             assert!(result.is_err());
             assert!(result.unwrap_err().to_string().contains("async feature not enabled"));
@@ -365,4 +376,1092 @@ mod ecosystem_error_handling_tests {
         // Deprecated features list can be empty (no problem)
         assert!(compatibility.deprecated_features.is_empty() || !compatibility.deprecated_features.is_empty());
     }
-}
\ No newline at end of file
+}
+
+/// Render diffs: differing output traced back to directives and lines
+#[cfg(test)]
+mod render_diff_tests {
+    use super::*;
+    use mystical_runic::{DiffLine, SegmentKind};
+
+    const CART_V1: &str = "<h1>{{title}}</h1>\n<ul>\n{{for item in items}}\n  <li>{{item.name}}: {{item.price|currency}}</li>\n{{/for}}\n</ul>\n";
+    const CART_V2: &str = "<h1>{{title}}</h1>\n<ul>\n{{for item in items}}\n  <li>{{item.name}}: {{item.price|round}}</li>\n{{/for}}\n</ul>\n";
+
+    fn item(name: &str, price: f64) -> TemplateValue {
+        TemplateValue::Object(HashMap::from([
+            ("name".to_string(), TemplateValue::String(name.to_string())),
+            ("price".to_string(), TemplateValue::Float(price)),
+        ]))
+    }
+
+    fn cart_context(prices: &[f64]) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("title", "Cart");
+        let items = prices.iter().enumerate().map(|(index, price)| item(&format!("Item {}", index + 1), *price)).collect();
+        context.set("items", TemplateValue::Array(items));
+        context
+    }
+
+    fn cart_templates() -> PathBuf {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("cart_v1.html"), CART_V1).unwrap();
+        fs::write(templates_path.join("cart_v2.html"), CART_V2).unwrap();
+        templates_path
+    }
+
+    #[test]
+    fn test_change_inside_loop_points_at_directive_and_line() {
+        let templates_path = cart_templates();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let diff = engine.diff_renders("cart_v1.html", "cart_v2.html", &cart_context(&[4.5, 3.0])).unwrap();
+        assert!(!diff.is_identical());
+        assert_eq!(diff.regions.len(), 2);
+
+        for (iteration, region) in diff.regions.iter().enumerate() {
+            assert_eq!(region.left.len(), 1);
+            assert_eq!(region.right.len(), 1);
+            let (left, right) = (&region.left[0], &region.right[0]);
+            assert_eq!(left.kind, SegmentKind::Output);
+            assert_eq!(left.source, "{{item.price|currency}}");
+            assert_eq!(right.source, "{{item.price|round}}");
+            assert_eq!((left.line, left.column), (4, 22));
+            assert_eq!((right.line, right.column), (4, 22));
+            assert_eq!(left.scope.len(), 1);
+            assert_eq!(left.scope[0].directive, "for item in items");
+            assert_eq!(left.scope[0].line, 3);
+            assert_eq!(left.scope[0].iteration, Some(iteration + 1));
+        }
+        assert_eq!(diff.regions[0].lines, vec![
+            DiffLine::Removed("$4.50".to_string()),
+            DiffLine::Added("4.50".to_string()),
+        ]);
+
+        // Same context: no variable resolved differently
+        assert!(diff.variables.is_empty());
+
+        let report = diff.to_string();
+        assert!(report.starts_with("--- cart_v1.html\n+++ cart_v2.html\n"));
+        assert!(report.contains("@@ cart_v1.html:4 | cart_v2.html:4 @@"));
+        assert!(report.contains("< cart_v1.html:4:22 {{item.price|currency}} in for item in items #2 (line 3)"));
+        assert!(report.contains("- $3.00\n+ 3.00"));
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_identical_renders() {
+        let templates_path = cart_templates();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let diff = engine.diff_renders("cart_v1.html", "cart_v1.html", &cart_context(&[1.0])).unwrap();
+        assert!(diff.is_identical());
+        assert!(diff.regions.is_empty());
+        assert_eq!(diff.to_string(), "No differences between cart_v1.html and cart_v1.html\n");
+
+        // A refactor that keeps the output is not a difference
+        let diff = engine.diff_render_strings("{{if title}}<b>{{title}}</b>{{/if}}", "<b>{{title}}</b>", &cart_context(&[])).unwrap();
+        assert!(diff.is_identical());
+        assert!(diff.regions.is_empty());
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_context_diff_reports_changed_variables() {
+        let templates_path = cart_templates();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let diff = engine.diff_contexts("cart_v1.html", &cart_context(&[4.5, 3.0]), &cart_context(&[4.5, 3.25, 1.0])).unwrap();
+        assert_eq!(diff.left_name, "cart_v1.html (a)");
+
+        let price = diff.variables.iter().find(|variable| variable.name == "item.price").unwrap();
+        assert_eq!(price.left, vec!["4.5", "3"]);
+        assert_eq!(price.right, vec!["4.5", "3.25", "1"]);
+        assert!(diff.variables.iter().any(|variable| variable.name == "items"));
+        assert!(!diff.variables.iter().any(|variable| variable.name == "title"));
+
+        // Second item changed, third item only on the right
+        let changed = &diff.regions[0];
+        assert_eq!(changed.left[0].source, "{{item.price|currency}}");
+        assert_eq!(changed.left[0].scope[0].iteration, Some(2));
+        let added = diff.regions.last().unwrap();
+        assert!(added.left.is_empty());
+        assert!(added.right.iter().all(|segment| segment.scope[0].iteration == Some(3)));
+        assert!(added.lines.contains(&DiffLine::Added("  <li>Item 3: $1.00</li>".to_string())));
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[cfg(feature = "template-macros")]
+    #[test]
+    fn test_conditionals_and_macros_are_traced() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_bool("vip", true);
+        context.set_string("name", "Ada");
+
+        let left = "{{macro badge(label)}}<i>{{label}}</i>{{/macro}}Hi {{name}}\n{{if vip}}{{badge(\"gold\")}}{{/if}}";
+        let right = "{{macro badge(label)}}<i>{{label}}</i>{{/macro}}Hi {{name}}\n{{if vip}}{{badge(\"silver\")}}{{/if}}";
+        let diff = engine.diff_render_strings(left, right, &context).unwrap();
+
+        assert_eq!(diff.left_output, "Hi Ada\n<i>gold</i>");
+        assert_eq!(diff.regions.len(), 1);
+        let segment = &diff.regions[0].left[0];
+        assert_eq!(segment.source, "{{badge(\"gold\")}}");
+        assert_eq!((segment.line, segment.scope[0].directive.as_str()), (2, "if vip"));
+        assert_eq!(diff.regions[0].right[0].output, "<i>silver</i>");
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn test_cli_diff_report() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("before.html"), "<h1>{{title}}</h1>\n<p>{{total|currency}}</p>\n").unwrap();
+        fs::write(templates_path.join("after.html"), "<h1>{{title}}</h1>\n<p>{{total}}</p>\n").unwrap();
+        let before = templates_path.join("before.html");
+        let after = templates_path.join("after.html");
+
+        let report = mystical_runic::diff_templates(
+            before.to_str().unwrap(),
+            after.to_str().unwrap(),
+            r#"{"title": "Cart", "total": 12}"#,
+        ).unwrap();
+        assert!(report.contains(&format!("< {}:2:4 {{{{total|currency}}}}", before.display())));
+        assert!(report.contains(&format!("> {}:2:4 {{{{total}}}}", after.display())));
+        assert!(report.contains("- $12.00\n+ 12"));
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Browser previews written under a per-run temp directory
+#[cfg(test)]
+#[cfg(feature = "cli")]
+mod browser_preview_tests {
+    use super::*;
+    use mystical_runic::{preview_in_browser, BrowserLauncher, PreviewOptions, PreviewReload, TemplateResult};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// Records the opened URLs instead of launching a browser
+    #[derive(Clone, Default)]
+    struct CapturingBrowser {
+        urls: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl BrowserLauncher for CapturingBrowser {
+        fn open(&self, url: &str) -> TemplateResult<()> {
+            self.urls.lock().unwrap().push(url.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_preview_opens_percent_encoded_file_url() {
+        let base = create_temp_dir().join("my previews");
+        fs::create_dir_all(&base).unwrap();
+        let browser = CapturingBrowser::default();
+
+        let preview = preview_in_browser(
+            "<h1>Hello</h1>",
+            PreviewOptions::new().in_dir(&base).with_launcher(browser.clone()),
+        ).unwrap();
+
+        let urls = browser.urls.lock().unwrap().clone();
+        assert_eq!(urls, vec![preview.url().to_string()]);
+        assert!(preview.url().starts_with("file:///"));
+        assert!(preview.url().contains("/my%20previews/mystical-runic-preview-"));
+        assert!(preview.url().ends_with("/index.html"));
+        assert_eq!(fs::read_to_string(preview.path()).unwrap(), "<h1>Hello</h1>");
+
+        let _ = fs::remove_dir_all(base.parent().unwrap());
+    }
+
+    #[test]
+    fn test_preview_is_removed_on_drop() {
+        let base = create_temp_dir();
+        let preview = preview_in_browser(
+            "<p>bye</p>",
+            PreviewOptions::new().in_dir(&base).with_launcher(CapturingBrowser::default()),
+        ).unwrap();
+        let dir = preview.path().parent().unwrap().to_path_buf();
+        assert!(dir.exists());
+
+        drop(preview);
+        assert!(!dir.exists());
+
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_reload_modes_and_updates() {
+        let base = create_temp_dir();
+        let meta = preview_in_browser(
+            "<html><head><title>t</title></head></html>",
+            PreviewOptions::new().in_dir(&base).with_launcher(CapturingBrowser::default())
+                .with_reload(PreviewReload::MetaRefresh(Duration::from_secs(2))),
+        ).unwrap();
+        assert_eq!(
+            fs::read_to_string(meta.path()).unwrap(),
+            "<html><head><meta http-equiv=\"refresh\" content=\"2\"><title>t</title></head></html>",
+        );
+
+        let script = preview_in_browser(
+            "<p>v1</p>",
+            PreviewOptions::new().in_dir(&base).with_launcher(CapturingBrowser::default())
+                .with_reload(PreviewReload::Script(Duration::from_millis(500))),
+        ).unwrap();
+        script.update("<p>v2</p>").unwrap();
+        let page = fs::read_to_string(script.path()).unwrap();
+        assert!(page.starts_with("<script>"));
+        assert!(page.contains("location.reload();},500);"));
+        assert!(page.ends_with("</script><p>v2</p>"));
+
+        drop((meta, script));
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_ttl_keeps_files_and_sweeps_expired_runs() {
+        let base = create_temp_dir();
+        let stale = base.join("mystical-runic-preview-1-1");
+        fs::create_dir_all(&stale).unwrap();
+        let unrelated = base.join("keep-me");
+        fs::create_dir_all(&unrelated).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+
+        let preview = preview_in_browser(
+            "<p>kept</p>",
+            PreviewOptions::new().in_dir(&base).with_launcher(CapturingBrowser::default())
+                .with_ttl(Duration::from_millis(20)),
+        ).unwrap();
+        assert!(!stale.exists(), "expired preview directories are swept");
+        assert!(unrelated.exists());
+
+        let file = preview.path().to_path_buf();
+        drop(preview);
+        assert!(file.exists(), "files outlive the handle until the TTL elapses");
+
+        let _ = fs::remove_dir_all(&base);
+    }
+}
+
+/// Snapshot testing of rendered templates
+#[cfg(test)]
+mod snapshot_tests {
+    use super::*;
+    use mystical_runic::{assert_template_snapshot, check_snapshot, SnapshotOptions, SnapshotOutcome};
+
+    fn page_context(stamp: &str) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("title", "Runes");
+        context.set_string("stamp", stamp);
+        context
+    }
+
+    fn snapshot_dir() -> (PathBuf, PathBuf) {
+        let dir = create_temp_dir();
+        fs::write(dir.join("page.html"), "<h1 id=\"top\" class=\"title\">{{title}}</h1>\n<p>One</p>\n<p>Two</p>\n").unwrap();
+        let snapshots = dir.join("snapshots");
+        (dir, snapshots)
+    }
+
+    #[test]
+    fn test_first_run_writes_the_snapshot() {
+        let (dir, snapshots) = snapshot_dir();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        let context = page_context("");
+
+        assert_template_snapshot!(engine, "page.html", context, SnapshotOptions::new().directory(&snapshots));
+        let stored = fs::read_to_string(snapshots.join("page.html.snap")).unwrap();
+        assert_eq!(stored, "<h1 id=\"top\" class=\"title\">Runes</h1>\n<p>One</p>\n<p>Two</p>\n");
+        assert_template_snapshot!(engine, "page.html", context, SnapshotOptions::new().directory(&snapshots));
+    }
+
+    #[test]
+    fn test_changed_template_fails_with_line_diff() {
+        let (dir, snapshots) = snapshot_dir();
+        let options = SnapshotOptions::new().directory(&snapshots).update(false);
+        let path = options.snapshot_path("unused", "page.html");
+        let context = page_context("");
+
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        let before = engine.render("page.html", &context).unwrap();
+        assert_eq!(check_snapshot(&path, &before, &options).unwrap(), SnapshotOutcome::Created);
+
+        fs::write(dir.join("page.html"), "<h1 id=\"top\" class=\"title\">{{title}}</h1>\n<p>One</p>\n<p>2</p>\n").unwrap();
+        let after = TemplateEngine::new(dir.to_str().unwrap()).render("page.html", &context).unwrap();
+        let SnapshotOutcome::Mismatch(report) = check_snapshot(&path, &after, &options).unwrap() else {
+            panic!("changed output should not match");
+        };
+        assert!(report.contains("RUNIC_UPDATE_SNAPSHOTS=1"), "{}", report);
+        assert!(report.contains("@@ line 1 @@\n  <h1 id=\"top\" class=\"title\">Runes</h1>\n  <p>One</p>\n- <p>Two</p>\n+ <p>2</p>\n"), "{}", report);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not match")]
+    fn test_assertion_panics_on_mismatch() {
+        let (dir, snapshots) = snapshot_dir();
+        fs::create_dir_all(&snapshots).unwrap();
+        fs::write(snapshots.join("page.html.snap"), "<p>stale</p>\n").unwrap();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        assert_template_snapshot!(engine, "page.html", page_context(""), SnapshotOptions::new().directory(&snapshots).update(false));
+    }
+
+    #[test]
+    fn test_update_rewrites_the_snapshot() {
+        let (_dir, snapshots) = snapshot_dir();
+        let path = snapshots.join("page.html.snap");
+        let options = SnapshotOptions::new().update(true);
+
+        assert_eq!(check_snapshot(&path, "<p>old</p>", &options).unwrap(), SnapshotOutcome::Created);
+        assert_eq!(check_snapshot(&path, "<p>new</p>", &options).unwrap(), SnapshotOutcome::Updated);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "<p>new</p>");
+        assert_eq!(check_snapshot(&path, "<p>new</p>", &options.update(false)).unwrap(), SnapshotOutcome::Matched);
+    }
+
+    #[test]
+    fn test_masking_hides_timestamps() {
+        let dir = create_temp_dir();
+        fs::write(dir.join("stamp.html"), "<footer data-at=\"{{stamp}}\">Built {{stamp}}</footer>").unwrap();
+        let options = SnapshotOptions::new()
+            .directory(dir.join("snapshots"))
+            .update(false)
+            .mask("[timestamp]", |word| word.len() == 20 && word.ends_with('Z') && word.contains('T'));
+
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        assert_template_snapshot!(engine, "stamp.html", page_context("2024-05-01T10:00:00Z"), options.clone());
+        assert_template_snapshot!(engine, "stamp.html", page_context("2031-12-24T23:59:59Z"), options.clone());
+
+        let stored = fs::read_to_string(dir.join("snapshots").join("stamp.html.snap")).unwrap();
+        assert_eq!(stored, "<footer data-at=\"[timestamp]\">Built [timestamp]</footer>");
+    }
+
+    #[test]
+    fn test_whitespace_and_attribute_normalization() {
+        let options = SnapshotOptions::new().collapse_whitespace(true).sort_attributes(true);
+        let output = "<div  class=\"a b\" id='x' hidden>\n\n   <img src=\"a.png\" alt=\"A\"/>  text\t here\n</div>";
+        assert_eq!(options.normalize(output), "<div class=\"a b\" hidden id='x'>\n<img alt=\"A\" src=\"a.png\" /> text here\n</div>");
+    }
+}
+
+/// Include and layout resolution trees from `render_with_trace`
+#[cfg(test)]
+mod include_trace_tests {
+    use super::*;
+    use mystical_runic::LoadSource;
+    #[cfg(feature = "inheritance")]
+    use mystical_runic::IncludeRelation;
+
+    fn engine_with_include_chain() -> (TemplateEngine, PathBuf) {
+        engine_with_templates(&[
+            ("base.html", "<main>{{block content}}{{/block}}</main>"),
+            ("page.html", "{{extends \"base.html\"}}{{block content}}{{include \"a.html\"}}{{/block}}"),
+            ("a.html", "a{{include \"b.html\"}}"),
+            ("b.html", "b{{include \"c.html\"}}"),
+            ("c.html", "c"),
+        ])
+    }
+
+    #[cfg(feature = "inheritance")]
+    fn shape(node: &mystical_runic::IncludeNode) -> String {
+        let children: Vec<String> = node.children.iter().map(shape).collect();
+        if children.is_empty() {
+            node.name.clone()
+        } else {
+            format!("{}({})", node.name, children.join(" "))
+        }
+    }
+
+    #[cfg(feature = "inheritance")]
+    #[test]
+    fn test_trace_nests_layouts_and_includes() {
+        let (mut engine, templates_path) = engine_with_include_chain();
+
+        let (output, tree) = engine.render_with_trace("page.html", &TemplateContext::new()).unwrap();
+        assert_eq!(output, "<main>abc</main>");
+        assert_eq!(shape(&tree.root), "page.html(base.html a.html(b.html(c.html)))");
+        assert_eq!(tree.root.relation, IncludeRelation::Root);
+        assert_eq!(tree.root.children[0].relation, IncludeRelation::Extends);
+        assert_eq!(tree.root.children[1].relation, IncludeRelation::Include);
+        assert_eq!(tree.depth(), 4);
+        assert_eq!(tree.unique_templates(), vec!["a.html", "b.html", "base.html", "c.html", "page.html"]);
+        assert!(tree.root.iter().all(|node| node.source == Some(LoadSource::Disk)));
+
+        let (_, tree) = engine.render_with_trace("page.html", &TemplateContext::new()).unwrap();
+        assert_eq!(shape(&tree.root), "page.html(base.html a.html(b.html(c.html)))");
+        assert!(tree.root.iter().all(|node| node.source == Some(LoadSource::Cache)));
+
+        let text = tree.to_text();
+        let lines: Vec<&str> = text.lines()
+            .map(|line| line.split(" load ").next().unwrap())
+            .collect();
+        assert_eq!(lines, vec![
+            "page.html [cache]",
+            "  extends base.html [cache]",
+            "  include a.html [cache]",
+            "    include b.html [cache]",
+            "      include c.html [cache]",
+        ]);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_trace_of_a_template_without_includes_is_a_single_node() {
+        let (mut engine, templates_path) = engine_with_include_chain();
+
+        let (output, tree) = engine.render_with_trace("c.html", &TemplateContext::new()).unwrap();
+        assert_eq!(output, "c");
+        assert_eq!(tree.depth(), 1);
+        assert_eq!(tree.root.source, Some(LoadSource::Disk));
+        assert_eq!(tree.unique_templates(), vec!["c.html"]);
+
+        // Plain renders do not record anything
+        assert_eq!(engine.render("a.html", &TemplateContext::new()).unwrap(), "abc");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[cfg(feature = "debug-tools")]
+    #[test]
+    fn test_debug_metrics_count_templates_touched() {
+        let (mut engine, templates_path) = engine_with_include_chain();
+        engine.enable_debug_mode();
+
+        let result = engine.render_string_with_debug("{{include \"a.html\"}}{{include \"c.html\"}}", &TemplateContext::new()).unwrap();
+        assert_eq!(result.output, "abcc");
+        assert_eq!(result.debug_info.performance_metrics.templates_touched, 3);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Template packs from `export_pack` and `import_pack`
+#[cfg(test)]
+mod template_pack_tests {
+    use super::*;
+    use mystical_runic::PACK_FORMAT_VERSION;
+
+    fn small_site() -> (TemplateEngine, PathBuf) {
+        engine_with_templates(&[
+            ("base.html", "<body>{{include \"partials/nav.html\"}}{{block content}}{{/block}}</body>"),
+            ("partials/nav.html", "<nav>{{t \"nav.home\"}}</nav>"),
+            ("partials/card.html", "<p>{{name}}</p>"),
+            ("page.html", "{{extends \"base.html\"}}{{block content}}{{include \"partials/card.html\"}}{{/block}}"),
+            ("unused.html", "{{t \"unused.key\"}}"),
+        ])
+    }
+
+    fn named(name: &str) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("name", name);
+        context
+    }
+
+    #[cfg(all(feature = "inheritance", feature = "i18n"))]
+    #[test]
+    fn test_exported_site_renders_from_an_engine_without_templates() {
+        let (mut engine, templates_path) = small_site();
+        let translations = |home: &str| HashMap::from([
+            ("nav.home".to_string(), home.to_string()),
+            ("unused.key".to_string(), "unused".to_string()),
+        ]);
+        engine.set_translations("en", translations("Home"));
+        engine.set_translations("fr", translations("Accueil"));
+        let pack_path = templates_path.join("site.runepack");
+        engine.export_pack(&["page.html"], &pack_path).unwrap();
+
+        let mut imported = TemplateEngine::new(templates_path.join("missing").to_str().unwrap());
+        let names = imported.import_pack(&pack_path, Some("site")).unwrap();
+        assert_eq!(names, vec![
+            "site::base.html", "site::page.html", "site::partials/card.html", "site::partials/nav.html",
+        ]);
+        assert!(imported.render("site::unused.html", &TemplateContext::new()).is_err());
+
+        imported.set_locale("fr");
+        assert_eq!(
+            imported.render("site::page.html", &named("Zoë")).unwrap(),
+            "<body><nav>Accueil</nav><p>Zoë</p></body>"
+        );
+        imported.set_locale("en");
+        assert_eq!(imported.get_translation("nav.home"), "Home");
+        assert_eq!(imported.get_translation("unused.key"), "unused.key");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_import_without_namespace_keeps_names_and_traces_registered_sources() {
+        let (mut engine, templates_path) = small_site();
+        let pack_path = templates_path.join("cards.runepack");
+        fs::write(templates_path.join("list.html"), "{{include \"partials/card.html\"}}!").unwrap();
+        engine.export_pack(&["list.html"], &pack_path).unwrap();
+
+        let mut imported = TemplateEngine::new(templates_path.join("missing").to_str().unwrap());
+        assert_eq!(imported.import_pack(&pack_path, None).unwrap(), vec!["list.html", "partials/card.html"]);
+        let (output, tree) = imported.render_with_trace("list.html", &named("Ada")).unwrap();
+        assert_eq!(output, "<p>Ada</p>!");
+        assert!(tree.root.iter().all(|node| node.source == Some(mystical_runic::LoadSource::Registered)));
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_collisions_with_existing_templates_register_nothing() {
+        let (mut engine, templates_path) = small_site();
+        let pack_path = templates_path.join("cards.runepack");
+        fs::write(templates_path.join("list.html"), "{{include \"partials/card.html\"}}").unwrap();
+        engine.export_pack(&["list.html"], &pack_path).unwrap();
+
+        // The template directory already has these files
+        let error = engine.import_pack(&pack_path, None).unwrap_err();
+        assert_eq!(error.to_string(), "Template error: Template pack would replace the existing template 'list.html'");
+
+        let mut imported = TemplateEngine::new(templates_path.join("missing").to_str().unwrap());
+        imported.import_pack(&pack_path, Some("ui")).unwrap();
+        assert!(imported.import_pack(&pack_path, Some("ui")).is_err());
+        assert!(imported.import_pack(&pack_path, Some("other")).is_ok());
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_other_format_versions_and_corrupt_files_are_rejected() {
+        let (mut engine, templates_path) = small_site();
+        let pack_path = templates_path.join("cards.runepack");
+        engine.export_pack(&["partials/card.html"], &pack_path).unwrap();
+        let bytes = fs::read(&pack_path).unwrap();
+
+        let mut newer = bytes.clone();
+        newer[8..12].copy_from_slice(&(PACK_FORMAT_VERSION + 1).to_le_bytes());
+        fs::write(&pack_path, &newer).unwrap();
+        let mut imported = TemplateEngine::new(templates_path.join("missing").to_str().unwrap());
+        let error = imported.import_pack(&pack_path, Some("ui")).unwrap_err();
+        assert!(error.to_string().contains("format version 2 is not supported; this engine reads version 1"), "{}", error);
+
+        fs::write(&pack_path, &bytes[..bytes.len() - 3]).unwrap();
+        let error = imported.import_pack(&pack_path, Some("ui")).unwrap_err();
+        assert!(error.to_string().contains("Corrupt template pack"), "{}", error);
+
+        fs::write(&pack_path, "<p>not a pack</p>").unwrap();
+        assert!(imported.import_pack(&pack_path, Some("ui")).unwrap_err().to_string().contains("Not a template pack"));
+        assert!(imported.import_pack(templates_path.join("nope.runepack"), None).is_err());
+        assert!(engine.export_pack(&["missing.html"], &pack_path).is_err());
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Semantic HTML diffs and semantic snapshot comparison
+#[cfg(test)]
+mod html_semantic_diff_tests {
+    use super::*;
+    use mystical_runic::diff::{html_semantic_diff, SemanticChange, SemanticDiffOptions};
+    use mystical_runic::{check_snapshot, SnapshotOptions, SnapshotOutcome};
+
+    const CARDS: &str = "<html><body>\n  <div class=\"card\"><h3>First</h3></div>\n  <div class=\"card\"><h3>Second</h3></div>\n</body></html>";
+
+    #[test]
+    fn test_attribute_order_and_whitespace_are_not_changes() {
+        let old = "<ul class=\"menu\" id=\"nav\">\n    <li><a href=\"/\" title=\"Home\">Home</a></li>\n</ul>";
+        let new = "<ul id=\"nav\" class=\"menu\"><li><a title=\"Home\" href=\"/\">Home</a></li></ul>";
+        let diff = html_semantic_diff(old, new, &SemanticDiffOptions::default());
+        assert!(diff.is_empty(), "{}", diff);
+
+        let diff = html_semantic_diff(old, new, &SemanticDiffOptions::new().ignore_attribute_order(false));
+        assert_eq!(diff.changes, vec![
+            SemanticChange::AttributesReordered { path: "ul.menu".to_string() },
+            SemanticChange::AttributesReordered { path: "ul.menu > li > a".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn test_text_change_is_located_by_path() {
+        let changed = CARDS.replace("Second", "Deuxième");
+        let diff = html_semantic_diff(CARDS, &changed, &SemanticDiffOptions::default());
+        assert_eq!(diff.changes, vec![SemanticChange::TextChanged {
+            path: "body > div.card[2] > h3".to_string(),
+            old: "Second".to_string(),
+            new: "Deuxième".to_string(),
+        }]);
+        assert_eq!(diff.to_string(), "~ body > div.card[2] > h3: text \"Second\" -> \"Deuxième\"\n");
+    }
+
+    #[test]
+    fn test_element_inserted_inside_a_list() {
+        let old = "<ul><li>Alpha</li><li>Gamma</li></ul>";
+        let new = "<ul>\n<li>Alpha</li>\n<li class=\"new\">Beta</li>\n<li>Gamma</li>\n</ul>";
+        let diff = html_semantic_diff(old, new, &SemanticDiffOptions::default());
+        assert_eq!(diff.changes, vec![SemanticChange::Added {
+            path: "ul > li.new[2]".to_string(),
+            node: "<li class=\"new\">Beta</li>".to_string(),
+        }]);
+        assert_eq!(diff.len(), 1);
+
+        let diff = html_semantic_diff(new, old, &SemanticDiffOptions::default());
+        assert_eq!(diff.report(), "- ul > li.new[2]: <li class=\"new\">Beta</li>\n");
+    }
+
+    #[test]
+    fn test_attribute_and_comment_changes() {
+        let old = "<!-- build 1 --><a href=\"/old\" class=\"x\">Link</a>";
+        let new = "<!-- build 2 --><a href=\"/new\" target=\"_blank\">Link</a>";
+        let diff = html_semantic_diff(old, new, &SemanticDiffOptions::default());
+        assert_eq!(diff.report(), concat!(
+            "~ a @class: \"x\" -> (none)\n",
+            "~ a @href: \"/old\" -> \"/new\"\n",
+            "~ a @target: (none) -> \"_blank\"\n",
+        ));
+
+        let with_comments = SemanticDiffOptions::new().ignore_comments(false);
+        let diff = html_semantic_diff(old, new, &with_comments);
+        assert_eq!(diff.changes[0], SemanticChange::TextChanged {
+            path: String::new(),
+            old: "<!-- build 1 -->".to_string(),
+            new: "<!-- build 2 -->".to_string(),
+        });
+    }
+
+    #[test]
+    fn test_entities_and_whitespace_runs_compare_by_meaning() {
+        let options = SemanticDiffOptions::default();
+        assert!(html_semantic_diff("<p>Fish &amp; chips</p>", "<p>Fish &#38;   chips</p>", &options).is_empty());
+        assert!(!html_semantic_diff("<pre>a  b</pre>", "<pre>a b</pre>", &options).is_empty());
+        assert!(!html_semantic_diff("<p>a b</p>", "<p>a  b</p>", &options.ignore_whitespace(false)).is_empty());
+    }
+
+    #[test]
+    fn test_snapshots_can_compare_semantically() {
+        let dir = create_temp_dir();
+        let path = dir.join("cards.html.snap");
+        let options = SnapshotOptions::new().update(false).compare_html(SemanticDiffOptions::default());
+        assert_eq!(check_snapshot(&path, CARDS, &options).unwrap(), SnapshotOutcome::Created);
+
+        let reformatted = CARDS.replace("\n  ", "").replace("<div class=\"card\">", "<div  class='card'>");
+        assert_eq!(check_snapshot(&path, &reformatted, &options).unwrap(), SnapshotOutcome::Matched);
+        assert_eq!(fs::read_to_string(&path).unwrap(), CARDS);
+
+        let SnapshotOutcome::Mismatch(report) = check_snapshot(&path, &CARDS.replace("First", "1st"), &options).unwrap() else {
+            panic!("changed text should not match");
+        };
+        assert!(report.contains("~ body > div.card[1] > h3: text \"First\" -> \"1st\""), "{}", report);
+
+        let byte_wise = SnapshotOptions::new().update(false);
+        assert!(matches!(check_snapshot(&path, &reformatted, &byte_wise).unwrap(), SnapshotOutcome::Mismatch(_)));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+/// Errors raised inside includes, layouts, loops and macro calls carry the
+/// template call stack they were raised in
+#[cfg(test)]
+mod template_backtrace_tests {
+    use super::*;
+    use mystical_runic::{FrameKind, TemplateError, TemplateFrame};
+
+    fn cart_item(name: &str, price: &str) -> TemplateValue {
+        TemplateValue::Object(HashMap::from([
+            ("name".to_string(), TemplateValue::String(name.to_string())),
+            ("price".to_string(), TemplateValue::String(price.to_string())),
+        ]))
+    }
+
+    fn cart_context(prices: &[&str]) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        let items = prices.iter().enumerate().map(|(i, price)| cart_item(&format!("Item {}", i), price)).collect();
+        context.set("items", TemplateValue::Array(items));
+        context
+    }
+
+    fn frame(kind: FrameKind, template: &str, directive: &str, line: usize) -> TemplateFrame {
+        TemplateFrame { kind, template: template.to_string(), directive: directive.to_string(), line }
+    }
+
+    #[cfg(all(feature = "inheritance", feature = "template-macros"))]
+    #[test]
+    fn test_filter_error_in_macro_of_included_partial_of_inherited_page() {
+        let dir = create_temp_dir();
+        fs::create_dir_all(dir.join("partials")).unwrap();
+        fs::write(dir.join("base.html"), "<html>\n<body>\n{{block content}}{{/block}}\n</body>\n</html>").unwrap();
+        fs::write(
+            dir.join("page.html"),
+            "{{extends \"base.html\"}}\n{{block content}}\n{{macro price_tag(amount)}}<b>{{amount|int}}</b>{{/macro}}\n<h1>Cart</h1>\n{{include \"partials/cart.html\"}}\n{{/block}}",
+        ).unwrap();
+        fs::write(
+            dir.join("partials/cart.html"),
+            "<ul>\n{{for item in items}}\n<li>{{item.name}} {{price_tag(item.price)}}</li>\n{{/for}}\n</ul>",
+        ).unwrap();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        engine.enable_strict_mode();
+
+        let output = engine.render("page.html", &cart_context(&["3", "4"])).unwrap();
+        assert!(output.contains("<b>4</b>"), "{}", output);
+
+        let error = engine.render("page.html", &cart_context(&["3", "n/a", "5"])).unwrap_err();
+        assert!(matches!(error.root_cause(), TemplateError::FilterError { filter_name, .. } if filter_name == "int"), "{:?}", error);
+        assert_eq!(error.kind(), "filter");
+        assert_eq!(error.template_backtrace(), [
+            frame(FrameKind::Template, "page.html", "", 1),
+            frame(FrameKind::Layout, "page.html", "extends \"base.html\"", 1),
+            frame(FrameKind::Include, "page.html", "include \"partials/cart.html\"", 5),
+            frame(FrameKind::Loop { iteration: 2 }, "partials/cart.html", "for item in items", 2),
+            frame(FrameKind::Macro, "partials/cart.html", "price_tag(item.price)", 3),
+        ]);
+
+        let message = error.to_string();
+        assert!(message.starts_with("Filter error in 'int'"), "{}", message);
+        assert!(message.contains(
+            "Template backtrace (outermost first):\n  rendering page.html\n  page.html:1: {{extends \"base.html\"}}\n  page.html:5: {{include \"partials/cart.html\"}}\n  partials/cart.html:2: {{for item in items}}, iteration 2\n  partials/cart.html:3: {{price_tag(item.price)}}"
+        ), "{}", message);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_errors_outside_nested_directives_are_unchanged() {
+        let dir = create_temp_dir();
+        fs::write(dir.join("page.html"), "<p>{{price|int}}</p>").unwrap();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        engine.enable_strict_mode();
+        let mut context = TemplateContext::new();
+        context.set_string("price", "n/a");
+
+        let error = engine.render("page.html", &context).unwrap_err();
+        assert!(matches!(error, TemplateError::FilterError { .. }), "{:?}", error);
+        assert!(error.template_backtrace().is_empty());
+        assert!(matches!(engine.render("missing.html", &context), Err(TemplateError::Template(_))));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_missing_include_inside_loop_names_the_iteration() {
+        let dir = create_temp_dir();
+        fs::write(dir.join("list.html"), "<ul>\n{{for item in items}}{{include item.name}}{{/for}}\n</ul>").unwrap();
+        fs::write(dir.join("Item 0"), "zero").unwrap();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        engine.allow_dynamic_includes(["Item"]);
+
+        let error = engine.render("list.html", &cart_context(&["1", "2"])).unwrap_err();
+        let backtrace = error.template_backtrace();
+        assert_eq!(backtrace.len(), 3, "{}", error);
+        assert_eq!(backtrace[1], frame(FrameKind::Loop { iteration: 2 }, "list.html", "for item in items", 2));
+        assert_eq!(backtrace[2], frame(FrameKind::Include, "list.html", "include item.name", 2));
+        assert!(matches!(error.root_cause(), TemplateError::Template(_)));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+/// Project configuration (`runic.toml`) tests
+#[cfg(test)]
+mod project_config_tests {
+    use super::*;
+    use mystical_runic::{ProjectConfig, ProjectSymbol, PROJECT_CONFIG_FILE_NAME};
+
+    const CONFIG: &str = r#"
+# runic.toml
+template_dir = "templates"
+delimiters = ["{{", "}}"]
+strict = true
+features = ["i18n", "lsp"]
+filters = ["money(currency)", "initials"]
+helpers = ["asset_url(path)"]
+
+[roots]
+vendor = "vendor/design-system"  # shared components
+"#;
+
+    #[test]
+    fn test_project_config_parses_and_round_trips() {
+        let config = ProjectConfig::parse(CONFIG).unwrap();
+        assert_eq!(config.template_dir, Some(PathBuf::from("templates")));
+        assert_eq!(config.roots.get("vendor"), Some(&PathBuf::from("vendor/design-system")));
+        assert_eq!(config.strict, Some(true));
+        assert_eq!(config.features, vec!["i18n", "lsp"]);
+        assert_eq!(config.filter("money").unwrap().signature(), "money(currency)");
+        assert_eq!(config.filter("initials"), Some(&ProjectSymbol::new("initials")));
+        assert_eq!(config.helper("asset_url").unwrap().parameters, Some(vec!["path".to_string()]));
+
+        assert_eq!(ProjectConfig::parse(&config.to_string()).unwrap(), config);
+    }
+
+    #[test]
+    fn test_project_config_errors_name_the_line() {
+        let error = ProjectConfig::parse("strict = true\nstrict = maybe").unwrap_err();
+        assert!(error.to_string().contains("runic.toml line 2"), "{}", error);
+
+        assert!(ProjectConfig::parse("colour = \"blue\"").is_err());
+        assert!(ProjectConfig::parse("[plugins]").is_err());
+        assert!(ProjectConfig::parse("filters = [money]").is_err());
+        assert!(ProjectConfig::parse("filters = [\"not a name\"]").is_err());
+        assert!(ProjectConfig::parse("delimiters = [\"<%\", \"%>\"]").is_err());
+    }
+
+    #[test]
+    fn test_load_project_config_applies_roots_and_strict_mode() {
+        let temp_dir = create_temp_dir();
+        fs::create_dir_all(temp_dir.join("templates")).unwrap();
+        fs::create_dir_all(temp_dir.join("vendor/design-system")).unwrap();
+        fs::write(temp_dir.join("templates/page.html"), "Page {{name}}").unwrap();
+        fs::write(temp_dir.join("vendor/design-system/card.html"), "Card").unwrap();
+        let path = temp_dir.join(PROJECT_CONFIG_FILE_NAME);
+        fs::write(&path, CONFIG).unwrap();
+
+        let mut engine = TemplateEngine::new("elsewhere");
+        engine.load_project_config(&path).unwrap();
+        assert!(engine.project_config().is_some());
+
+        let mut context = TemplateContext::new();
+        context.set_string("name", "home");
+        assert_eq!(engine.render("page.html", &context).unwrap(), "Page home");
+        assert_eq!(engine.render("card.html", &context).unwrap(), "Card");
+        assert!(engine.is_strict_mode_enabled());
+    }
+
+    #[test]
+    fn test_project_filters_pass_syntax_checks() {
+        let temp_dir = create_temp_dir();
+        let path = temp_dir.join(PROJECT_CONFIG_FILE_NAME);
+        fs::write(&path, "filters = [\"money(currency)\"]").unwrap();
+
+        let mut engine = TemplateEngine::new(temp_dir.to_str().unwrap());
+        assert!(engine.check_syntax("{{price|money}}").is_err());
+        engine.load_project_config(&path).unwrap();
+        assert!(engine.check_syntax("{{price|money}}").is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "lsp")]
+    fn test_project_filters_and_helpers_are_known_to_the_editor() {
+        let temp_dir = create_temp_dir();
+        let path = temp_dir.join(PROJECT_CONFIG_FILE_NAME);
+        fs::write(&path, CONFIG).unwrap();
+
+        let mut engine = TemplateEngine::new(temp_dir.to_str().unwrap());
+        let template = "{{price|money}} {{asset_url(\"logo.png\")}}";
+        let mut context = TemplateContext::new();
+        context.set_number("price", 5);
+
+        let before = engine.get_diagnostics_for_editor(template, &context).unwrap();
+        assert!(before.iter().any(|d| d.message.contains("Unknown filter")), "{:?}", before);
+
+        engine.load_project_config(&path).unwrap();
+        let after = engine.get_diagnostics_for_editor(template, &context).unwrap();
+        assert!(after.is_empty(), "{:?}", after);
+
+        let parsed = engine.parse_for_lsp(template, "page.html").unwrap();
+        assert!(!parsed.variables.iter().any(|v| v.contains("asset_url")), "{:?}", parsed.variables);
+
+        let completions = engine.get_completions_at_position("{{price|mo}}", 10, &context).unwrap();
+        let money = completions.iter().find(|c| c.label == "money").unwrap();
+        assert_eq!(money.detail, "Project filter: money(currency)");
+        let completions = engine.get_completions_at_position("{{asset}}", 7, &context).unwrap();
+        assert!(completions.iter().any(|c| c.label == "asset_url" && c.completion_type == "helper"));
+    }
+
+    #[test]
+    fn test_generate_project_config_round_trips() {
+        let temp_dir = create_temp_dir();
+        fs::create_dir_all(temp_dir.join("templates")).unwrap();
+        let mut engine = TemplateEngine::new(temp_dir.join("templates").to_str().unwrap());
+        engine.register_filter("shout", |value: &str, _args: &[&str]| Ok(value.to_uppercase()));
+        engine.enable_strict_mode();
+
+        let path = temp_dir.join(PROJECT_CONFIG_FILE_NAME);
+        let config = engine.generate_project_config(&path).unwrap();
+        assert_eq!(config.template_dir, Some(PathBuf::from("templates")));
+        assert_eq!(config.filters, vec![ProjectSymbol::new("shout")]);
+        assert_eq!(config.strict, Some(true));
+        assert_eq!(config.features.contains(&"i18n".to_string()), cfg!(feature = "i18n"));
+
+        let written = ProjectConfig::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written, config);
+
+        let mut reloaded = TemplateEngine::new("elsewhere");
+        reloaded.load_project_config(&path).unwrap();
+        assert_eq!(reloaded.project_config(), Some(&config));
+        assert_eq!(reloaded.generate_project_config(&path).unwrap(), config);
+    }
+}
+
+/// Template coverage tests
+#[cfg(test)]
+mod coverage_tests {
+    use super::*;
+    use mystical_runic::CoverageKind;
+
+    const PAGE: &str = "<h1>{{title}}</h1>\n{{if admin}}<b>Admin</b>{{/if}}\n{{if guest}}<i>{{guest.name}}</i>{{/if}}\n";
+
+    fn admin_context() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("title", "Home");
+        context.set_bool("admin", true);
+        context
+    }
+
+    #[test]
+    fn test_untaken_branch_is_the_only_uncovered_span() {
+        let temp_dir = create_temp_dir();
+        fs::write(temp_dir.join("page.html"), PAGE).unwrap();
+        let mut engine = TemplateEngine::new(temp_dir.to_str().unwrap());
+        engine.render("page.html", &admin_context()).unwrap();
+        assert!(engine.coverage_report().templates.is_empty());
+
+        engine.enable_coverage(true);
+        engine.render("page.html", &admin_context()).unwrap();
+        engine.render("page.html", &admin_context()).unwrap();
+        let report = engine.coverage_report();
+        let page = report.template("page.html").unwrap();
+        let uncovered = page.uncovered();
+        assert_eq!(uncovered.len(), 1, "{}", report);
+        assert_eq!(uncovered[0].kind, CoverageKind::Branch);
+        assert_eq!(uncovered[0].span.slice(PAGE), "<i>{{guest.name}}</i>");
+        assert_eq!((uncovered[0].span.line, uncovered[0].directive.as_str()), (3, "if guest"));
+        assert_eq!((page.covered(), page.sites.len()), (4, 6));
+        assert!(page.sites.iter().filter(|site| site.hits > 0).all(|site| site.hits == 2));
+
+        // The other branch completes the coverage
+        let mut guest = TemplateContext::new();
+        let mut profile = HashMap::new();
+        profile.insert("name".to_string(), TemplateValue::String("Ada".to_string()));
+        guest.set("guest", TemplateValue::Object(profile));
+        engine.render("page.html", &guest).unwrap();
+        let report = engine.coverage_report();
+        assert!(report.template("page.html").unwrap().uncovered().is_empty(), "{}", report);
+        assert_eq!(report.percent(), 100.0);
+    }
+
+    #[test]
+    fn test_loops_includes_and_bytecode_renders() {
+        let temp_dir = create_temp_dir();
+        fs::write(temp_dir.join("list.html"), "{{for item in items}}{{include \"row.html\"}}{{/for}}{{for tag in tags}}{{tag}}{{/for}}").unwrap();
+        fs::write(temp_dir.join("row.html"), "{{if item.done}}done{{/if}}{{unless item.done}}todo{{/unless}}").unwrap();
+        let mut engine = TemplateEngine::new(temp_dir.to_str().unwrap());
+        engine.enable_coverage(true);
+        let mut context = TemplateContext::new();
+        let mut task = HashMap::new();
+        task.insert("done".to_string(), TemplateValue::Bool(true));
+        context.set("items", TemplateValue::Array(vec![TemplateValue::Object(task)]));
+        engine.render("list.html", &context).unwrap();
+
+        let report = engine.coverage_report();
+        let list = report.template("list.html").unwrap();
+        let uncovered: Vec<_> = list.uncovered().iter().map(|site| (site.kind, site.directive.as_str())).collect();
+        assert_eq!(uncovered, [(CoverageKind::LoopBody, "for tag in tags")]);
+        let row = report.template("row.html").unwrap();
+        let uncovered: Vec<_> = row.uncovered().iter().map(|site| site.span.slice(&row.source)).collect();
+        assert_eq!(uncovered, ["todo"]);
+
+        // Bytecode renders record the same sites
+        fs::write(temp_dir.join("page.html"), PAGE).unwrap();
+        let compiled = engine.compile_to_bytecode("page.html").unwrap();
+        engine.render_compiled(&compiled, &admin_context()).unwrap();
+        let compiled_report = engine.coverage_report();
+        let mut rendered = TemplateEngine::new(temp_dir.to_str().unwrap());
+        rendered.enable_coverage(true);
+        rendered.render("page.html", &admin_context()).unwrap();
+        assert_eq!(compiled_report.template("page.html"), rendered.coverage_report().template("page.html"));
+
+        // Enabling again starts over
+        engine.enable_coverage(false);
+        engine.render("list.html", &context).unwrap();
+        assert_eq!(engine.coverage_report(), compiled_report);
+        engine.enable_coverage(true);
+        assert!(engine.coverage_report().templates.is_empty());
+    }
+
+    #[test]
+    fn test_lcov_and_html_exports() {
+        let temp_dir = create_temp_dir();
+        fs::write(temp_dir.join("page.html"), PAGE).unwrap();
+        let mut engine = TemplateEngine::new(temp_dir.to_str().unwrap());
+        engine.enable_coverage(true);
+        engine.render("page.html", &admin_context()).unwrap();
+        let report = engine.coverage_report();
+
+        assert_eq!(
+            report.to_lcov(),
+            "TN:\nSF:page.html\nBRDA:2,0,0,1\nBRDA:3,1,0,0\nDA:1,1\nDA:2,1\nDA:3,0\nBRF:2\nBRH:1\nLF:3\nLH:2\nend_of_record\n"
+        );
+        let html = report.to_html();
+        assert!(html.contains("<h2>page.html &mdash; 66.7% (4/6)</h2>"), "{}", html);
+        assert!(html.contains("<mark title=\"if guest\">&lt;i&gt;{{guest.name}}&lt;/i&gt;</mark>"), "{}", html);
+    }
+}
+
+/// `render_stream`, chunks yielded as the render goes
+#[cfg(feature = "async")]
+#[cfg(test)]
+mod render_stream_tests {
+    use super::*;
+    use futures::StreamExt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn table_engine(row: &str) -> (TemplateEngine, PathBuf) {
+        engine_with_templates(&[("table.html", &format!("<ul>{{{{for row in rows}}}}{}{{{{/for}}}}</ul>", row))])
+    }
+
+    fn rows(count: i64) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set("rows", TemplateValue::Array((1..=count).map(TemplateValue::Number).collect()));
+        context
+    }
+
+    #[tokio::test]
+    async fn test_chunks_follow_loop_iterations() {
+        let (mut engine, dir) = table_engine("<li>{{row}}</li>");
+        let chunks: Vec<_> = engine.render_stream("table.html", &rows(3)).await
+            .map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+            .collect()
+            .await;
+        assert_eq!(chunks, ["<ul><li>1</li>", "<li>2</li>", "<li>3</li>", "</ul>"]);
+        assert_eq!(chunks.concat(), engine.render("table.html", &rows(3)).unwrap());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_render_waits_for_the_consumer() {
+        let (mut engine, dir) = table_engine("<li>{{row|counted}}</li>");
+        let rendered = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&rendered);
+        engine.register_filter("counted", move |value, _| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(value.to_string())
+        });
+        engine.set_stream_buffer_size(2);
+        assert_eq!(engine.stream_buffer_size(), 2);
+
+        let mut stream = engine.render_stream("table.html", &rows(100)).await;
+        assert_eq!(stream.next().await.unwrap().unwrap(), "<ul><li>1</li>");
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        // Two chunks wait in the buffer, a third is rendered and waits for room
+        assert!(rendered.load(Ordering::SeqCst) <= 4, "rendered {} rows ahead", rendered.load(Ordering::SeqCst));
+
+        // Dropping the stream stops the render
+        drop(stream);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(rendered.load(Ordering::SeqCst) <= 5, "rendered {} rows", rendered.load(Ordering::SeqCst));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_failing_render_ends_the_stream_with_its_error() {
+        let (mut engine, dir) = table_engine("{{row}}{{for i in range(row)}}{{/for}}");
+        engine.set_max_range_items(2);
+        let chunks: Vec<_> = engine.render_stream("table.html", &rows(5)).await.collect().await;
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].as_ref().unwrap(), "<ul>1");
+        assert_eq!(chunks[1].as_ref().unwrap(), "2");
+        let error = chunks[2].as_ref().unwrap_err().to_string();
+        assert!(error.contains("range of 3 items exceeds the limit of 2 items"), "{}", error);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
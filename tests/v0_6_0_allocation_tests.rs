@@ -0,0 +1,374 @@
+//! Allocation tests for v0.6.0 buffer reuse (render_into)
+//! Uses a counting global allocator, so it lives in its own test binary
+
+use mystical_runic::{TemplateEngine, TemplateContext, TemplateValue};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::collections::HashMap;
+#[cfg(all(feature = "inheritance", feature = "template-macros"))]
+use std::fs;
+#[cfg(all(feature = "inheritance", feature = "template-macros"))]
+use std::path::PathBuf;
+
+/// Counts allocations and live bytes for the current thread
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+    static PEAK_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+
+/// Adjusts the live byte count, keeping track of its high-water mark
+fn track_bytes(delta: isize) {
+    let _ = LIVE_BYTES.try_with(|live| {
+        live.set(live.get() + delta);
+        let _ = PEAK_BYTES.try_with(|peak| peak.set(peak.get().max(live.get())));
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        track_bytes(layout.size() as isize);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        track_bytes(-(layout.size() as isize));
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        track_bytes(new_size as isize - layout.size() as isize);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Allocations performed by `f` on this thread
+fn count_allocations<F: FnMut()>(mut f: F) -> usize {
+    let before = ALLOCATIONS.with(|count| count.get());
+    f();
+    ALLOCATIONS.with(|count| count.get()) - before
+}
+
+/// Highest number of bytes `f` held live at once on this thread
+fn peak_bytes<F: FnMut()>(mut f: F) -> usize {
+    let before = LIVE_BYTES.with(|live| live.get());
+    PEAK_BYTES.with(|peak| peak.set(before));
+    f();
+    (PEAK_BYTES.with(|peak| peak.get()) - before).max(0) as usize
+}
+
+// Utility to create temporary directories for testing
+#[cfg(all(feature = "inheritance", feature = "template-macros"))]
+fn create_temp_dir() -> PathBuf {
+    let mut temp_path = std::env::temp_dir();
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    temp_path.push(format!("mystical_runic_v6_alloc_test_{}_{}", std::process::id(), timestamp));
+    let _ = std::fs::create_dir_all(&temp_path);
+    temp_path
+}
+
+fn fixture_templates() -> Vec<(&'static str, &'static str)> {
+    vec![
+        ("plain.html", "<p>Static text only</p>"),
+        ("variables.html", "<h1>{{title}}</h1><p>{{user.name|upper}} &amp; {{& raw_html}}</p>"),
+        ("conditionals.html", "{{if show}}<b>{{title}}</b>{{/if}}{{if hidden}}secret{{/if}}{{! gone }}"),
+        ("loops.html", "<ul>{{for item in items}}<li>{{item.name}}: {{item.price|currency}}</li>{{/for}}</ul>"),
+        ("comments.html", "{{#-- section --}}{{title}}{{! hidden }}"),
+        ("plural.html", "{{count}} {{plural count \"item\" \"items\"}}"),
+        ("macros.html", "{{macro badge(text)}}<span>{{text}}</span>{{/macro}}{{badge(\"new\")}}{{badge(title)}}"),
+        ("base.html", "<html>{{block body}}Default{{/block}}</html>"),
+        ("child.html", "{{extends \"base.html\"}}{{block body}}Child {{title}}{{/block}}"),
+        ("includes.html", "{{include \"variables.html\"}}|{{include \"loops.html\"}}"),
+    ]
+}
+
+fn fixture_context() -> TemplateContext {
+    let mut context = TemplateContext::new();
+    context.set_string("title", "Fixture <Suite>");
+    context.set_string("raw_html", "<em>raw</em>");
+    context.set_bool("show", true);
+    context.set_bool("hidden", false);
+    context.set_number("count", 3);
+    let mut user = HashMap::new();
+    user.insert("name".to_string(), TemplateValue::String("ada".to_string()));
+    context.set("user", TemplateValue::Object(user));
+    let items = (0..20).map(|i| {
+        let mut item = HashMap::new();
+        item.insert("name".to_string(), TemplateValue::String(format!("Item {}", i)));
+        item.insert("price".to_string(), TemplateValue::Number(i * 150));
+        TemplateValue::Object(item)
+    }).collect();
+    context.set("items", TemplateValue::Array(items));
+    context
+}
+
+/// render_into must produce exactly what render produces
+#[cfg(test)]
+mod render_into_tests {
+    use super::*;
+
+    #[cfg(all(feature = "inheritance", feature = "template-macros"))]
+    #[test]
+    fn test_render_into_matches_render_for_fixture_suite() {
+        let templates_path = create_temp_dir();
+        for (name, content) in fixture_templates() {
+            fs::write(templates_path.join(name), content).unwrap();
+        }
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        let context = fixture_context();
+
+        let mut buffer = String::from("stale content that must be cleared");
+        for (name, _) in fixture_templates() {
+            let expected = engine.render(name, &context).unwrap();
+            engine.render_into(name, &context, &mut buffer).unwrap();
+            assert_eq!(buffer, expected, "render_into differs for {}", name);
+        }
+
+        let mut repo_engine = TemplateEngine::new("tests/templates");
+        for name in ["simple.html", "cli_test.html"] {
+            let expected = repo_engine.render(name, &context).unwrap();
+            repo_engine.render_into(name, &context, &mut buffer).unwrap();
+            assert_eq!(buffer, expected, "render_into differs for {}", name);
+        }
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_render_into_keeps_buffer_capacity() {
+        let mut engine = TemplateEngine::new(".");
+        let context = fixture_context();
+        let template = fixture_templates()[3].1;
+
+        let mut buffer = String::with_capacity(64 * 1024);
+        let capacity = buffer.capacity();
+        for _ in 0..10 {
+            engine.render_string_into(template, &context, &mut buffer).unwrap();
+            assert_eq!(buffer.capacity(), capacity);
+        }
+        assert!(buffer.starts_with("<ul><li>Item 0: $0.00</li>"));
+    }
+
+    #[test]
+    fn test_render_into_reduces_allocations() {
+        let mut engine = TemplateEngine::new(".");
+        let context = fixture_context();
+
+        // Static text renders without allocating once the buffer is warm
+        let mut buffer = String::with_capacity(1024);
+        let allocations = count_allocations(|| {
+            engine.render_string_into("<p>Static text only</p>", &context, &mut buffer).unwrap();
+        });
+        assert_eq!(allocations, 0);
+
+        // A medium template: reusing the buffer beats allocating a fresh result
+        let template = "<h1>{{title}}</h1>{{if show}}<p>{{user.name|upper}}</p>{{/if}}\
+            <ul>{{for item in items}}<li>{{item.name}}</li>{{/for}}</ul>";
+        let mut buffer = String::new();
+        engine.render_string_into(template, &context, &mut buffer).unwrap();
+        let fresh = count_allocations(|| {
+            let _ = engine.render_string(template, &context).unwrap();
+        });
+        let reused = count_allocations(|| {
+            engine.render_string_into(template, &context, &mut buffer).unwrap();
+        });
+        assert!(reused < fresh, "reused buffer: {} allocations, fresh result: {}", reused, fresh);
+    }
+}
+
+/// Numbers are written into the output buffer digit by digit
+#[cfg(test)]
+mod number_output_tests {
+    use super::*;
+
+    fn numeric_context() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_number("zero", 0);
+        context.set_number("negative", -9_876_543);
+        context.set_number("min", i64::MIN);
+        context.set_number("max", i64::MAX);
+        context.set("rows", TemplateValue::Array((0..100).map(|n| TemplateValue::Number(n * 1_001)).collect()));
+        context
+    }
+
+    #[test]
+    fn test_number_variables_render_without_allocating() {
+        let mut engine = TemplateEngine::new(".");
+        let context = numeric_context();
+        let template = "<td>{{zero}}</td><td>{{negative}}</td><td>{{min}}</td><td>{{& max}}</td>";
+
+        let mut buffer = String::with_capacity(1024);
+        engine.render_string_into(template, &context, &mut buffer).unwrap();
+        let allocations = count_allocations(|| {
+            engine.render_string_into(template, &context, &mut buffer).unwrap();
+        });
+        assert_eq!(allocations, 0);
+        assert_eq!(buffer, format!("<td>0</td><td>-9876543</td><td>{}</td><td>{}</td>", i64::MIN, i64::MAX));
+    }
+
+    #[test]
+    fn test_loop_number_cells_do_not_allocate() {
+        let mut engine = TemplateEngine::new(".");
+        let context = numeric_context();
+        let one_cell = "{{for n in rows}}<td>{{n}}</td>{{/for}}";
+        let three_cells = "{{for n in rows}}<td>{{n}}</td><td>{{n}}</td><td>{{n}}</td>{{/for}}";
+
+        let mut buffer = String::with_capacity(64 * 1024);
+        engine.render_string_into(three_cells, &context, &mut buffer).unwrap();
+        let one = count_allocations(|| {
+            engine.render_string_into(one_cell, &context, &mut buffer).unwrap();
+        });
+        let three = count_allocations(|| {
+            engine.render_string_into(three_cells, &context, &mut buffer).unwrap();
+        });
+        // 200 extra cells, only the growth of the loop result allocates
+        assert!(three - one < 20, "one cell per row: {} allocations, three cells: {}", one, three);
+    }
+}
+
+/// Cached template sources are shared, not copied, by loads and renders
+#[cfg(test)]
+#[cfg(all(feature = "inheritance", feature = "template-macros"))]
+mod shared_source_tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    /// About 100KB of templates: a layout, a page extending it and two partials
+    fn write_template_set(dir: &std::path::Path) -> usize {
+        let filler = |label: &str, kb: usize| {
+            (0..kb * 28).map(|i| format!("<p class=\"{}\">line {:>5} of {}</p>\n", label, i, label)).collect::<String>()
+        };
+        let templates = [
+            ("base.html", format!("<html>{}{{{{block body}}}}Default{{{{/block}}}}</html>", filler("base", 30))),
+            ("page.html", format!("{{{{extends \"base.html\"}}}}{{{{block body}}}}{}{{{{/block}}}}", filler("page", 40))),
+            ("header.html", filler("header", 15)),
+            ("footer.html", filler("footer", 15)),
+        ];
+        let mut bytes = 0;
+        for (name, source) in &templates {
+            fs::write(dir.join(name), source).unwrap();
+            bytes += source.len();
+        }
+        bytes
+    }
+
+    /// Bytes held by the distinct sources among `sources`
+    fn resident_bytes<'a>(sources: impl IntoIterator<Item = &'a Arc<str>>) -> usize {
+        let mut seen = HashSet::new();
+        sources.into_iter()
+            .filter(|source| seen.insert(Arc::as_ptr(source) as *const u8))
+            .map(|source| source.len())
+            .sum()
+    }
+
+    #[test]
+    fn test_two_hundred_loads_share_one_copy_of_the_set() {
+        let dir = create_temp_dir();
+        let set_bytes = write_template_set(&dir);
+        assert!(set_bytes > 100 * 1024);
+        let names = ["base.html", "page.html", "header.html", "footer.html"];
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        for name in names {
+            engine.load_template_arc(name).unwrap();
+        }
+
+        let mut shared = Vec::with_capacity(200 * names.len());
+        let allocations = count_allocations(|| {
+            for _ in 0..200 {
+                for name in names {
+                    shared.push(engine.load_template_arc(name).unwrap());
+                }
+            }
+        });
+        assert_eq!(allocations, 0);
+        assert_eq!(resident_bytes(&shared), set_bytes);
+        assert_eq!(engine.get_performance_statistics().cache_bytes, set_bytes);
+
+        // The String API still hands out copies: 200 of them per template
+        let mut copies = Vec::with_capacity(200 * names.len());
+        let allocations = count_allocations(|| {
+            for _ in 0..200 {
+                for name in names {
+                    copies.push(engine.load_template(name).unwrap());
+                }
+            }
+        });
+        assert_eq!(allocations, 200 * names.len());
+        assert_eq!(copies.iter().map(String::len).sum::<usize>(), 200 * set_bytes);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rendering_a_cached_template_does_not_copy_its_source() {
+        let dir = create_temp_dir();
+        write_template_set(&dir);
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        let context = TemplateContext::new();
+
+        let mut buffer = String::new();
+        engine.render_into("header.html", &context, &mut buffer).unwrap();
+        let allocations = count_allocations(|| {
+            for _ in 0..200 {
+                engine.render_into("header.html", &context, &mut buffer).unwrap();
+            }
+        });
+        assert_eq!(allocations, 0);
+
+        // The layout keeps sharing the cached source of a template without `{{extends}}`
+        engine.render_into("page.html", &context, &mut buffer).unwrap();
+        let base = engine.load_template_arc("base.html").unwrap();
+        assert_eq!(engine.get_performance_statistics().cache_bytes, resident_bytes([
+            &base,
+            &engine.load_template_arc("page.html").unwrap(),
+            &engine.load_template_arc("header.html").unwrap(),
+        ]));
+        assert!(Arc::strong_count(&base) > 2, "layout processor should share base.html");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+#[cfg(test)]
+mod streaming_peak_tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// A 100k-row table, kept in a shared base layer so loop scopes don't copy it
+    fn table_context() -> TemplateContext {
+        let mut rows = TemplateContext::new();
+        rows.set("rows", TemplateValue::Array((0..100_000).map(TemplateValue::Number).collect()));
+        TemplateContext::layered(Arc::new(rows))
+    }
+
+    #[test]
+    fn test_streaming_a_large_loop_keeps_peak_memory_low() {
+        let template = "<table>{{for row in rows}}<tr><td>{{row}}</td><td>{{row|multiply:2}}</td><td>Row {{row}} of the table</td></tr>{{/for}}</table>";
+        let mut engine = TemplateEngine::new(".");
+        let context = table_context();
+
+        let mut output = String::new();
+        let buffered = peak_bytes(|| output = engine.render_string(template, &context).unwrap());
+        let mut streamed_output = Vec::new();
+        engine.render_string_to_writer(template, &context, &mut streamed_output).unwrap();
+        assert_eq!(streamed_output, output.as_bytes());
+        drop(output);
+        drop(streamed_output);
+
+        let streamed = peak_bytes(|| engine.render_string_to_writer(template, &context, &mut std::io::sink()).unwrap());
+        println!("peak bytes for 100k rows: buffered {}, streamed {}", buffered, streamed);
+        assert!(buffered > 6_000_000, "buffered render holds the whole table ({} bytes)", buffered);
+        assert!(streamed * 20 < buffered, "streaming peak {} should be far below buffered {}", streamed, buffered);
+    }
+}
@@ -0,0 +1,4045 @@
+//! Tests for v0.6.0 template syntax additions
+//! Following strict TDD methodology: RED → GREEN → REFACTOR
+
+mod common;
+
+use common::*;
+
+/// Visible comments: {{#-- ... --}} rendered as HTML comments
+#[cfg(test)]
+mod visible_comment_tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_comment_renders_as_html_comment() {
+        let mut engine = TemplateEngine::new(".");
+        let context = TemplateContext::new();
+
+        let result = engine.render_string("{{#-- section: hero --}}<h1>Hi</h1>", &context).unwrap();
+        assert_eq!(result, "<!-- section: hero --><h1>Hi</h1>");
+    }
+
+    #[test]
+    fn test_regular_comment_is_still_stripped() {
+        let mut engine = TemplateEngine::new(".");
+        let context = TemplateContext::new();
+
+        let result = engine.render_string("{{! hidden }}{{#-- shown --}}", &context).unwrap();
+        assert_eq!(result, "<!-- shown -->");
+    }
+
+    #[test]
+    fn test_visible_comment_cannot_break_out() {
+        let mut engine = TemplateEngine::new(".");
+        let context = TemplateContext::new();
+
+        let result = engine.render_string("{{#-- a --> <script>x</script> --}}", &context).unwrap();
+        assert!(result.starts_with("<!--"));
+        assert!(result.ends_with("-->"));
+        let body = &result[4..result.len() - 3];
+        assert!(!body.contains("--"), "comment body must not contain '--': {}", body);
+        assert!(!body.contains('<') && !body.contains('>'));
+    }
+
+    #[test]
+    fn test_visible_comment_with_dash_runs() {
+        let mut engine = TemplateEngine::new(".");
+        let context = TemplateContext::new();
+
+        let result = engine.render_string("{{#-- ----- --}}", &context).unwrap();
+        let body = &result[4..result.len() - 3];
+        assert!(!body.contains("--"));
+    }
+
+    #[test]
+    fn test_visible_comment_stripped_for_production() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("name", "World");
+        engine.set_strip_visible_comments(true);
+        assert!(engine.is_strip_visible_comments_enabled());
+
+        let result = engine.render_string("{{#-- greeting --}}Hello {{name}}", &context).unwrap();
+        assert_eq!(result, "Hello World");
+    }
+
+    #[test]
+    fn test_visible_comment_inside_loop_and_conditional() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_bool("show", true);
+        context.set("items", TemplateValue::Array(vec![
+            TemplateValue::String("a".to_string()),
+        ]));
+
+        let result = engine.render_string(
+            "{{if show}}{{#-- if --}}{{/if}}{{for item in items}}{{#-- item --}}{{item}}{{/for}}",
+            &context,
+        ).unwrap();
+        assert_eq!(result, "<!-- if --><!-- item -->a");
+    }
+
+    #[cfg(feature = "lsp")]
+    #[test]
+    fn test_visible_comment_is_a_comment_token() {
+        let mut engine = TemplateEngine::new(".");
+
+        let tokens = engine.tokenize_for_syntax_highlighting("{{#-- note --}}{{name}}").unwrap();
+        assert_eq!(tokens[0].token_type, "comment");
+        assert_eq!(tokens[0].content, "{{#-- note --}}");
+        assert_eq!(tokens[1].token_type, "template_variable");
+    }
+}
+
+/// Value coercion filters: int, float, bool, string
+#[cfg(test)]
+mod value_coercion_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+
+    #[test]
+    fn test_int_filter_compares_numerically() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("qty", "10");
+
+        // Numeric strings compare as numbers, coerced or not
+        let result = engine.render_string("{{if qty > 3}}big{{/if}}", &context).unwrap();
+        assert_eq!(result, "big");
+        let result = engine.render_string("{{if qty|int > 3}}big{{/if}}", &context).unwrap();
+        assert_eq!(result, "big");
+    }
+
+    #[test]
+    fn test_int_filter_truncates_and_does_arithmetic() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("qty", "3.9");
+        context.set_bool("flag", true);
+
+        let result = engine.render_string("{{qty|int}} {{qty|int|add:2}} {{flag|int}}", &context).unwrap();
+        assert_eq!(result, "3 5 1");
+    }
+
+    #[test]
+    fn test_float_filter_in_condition() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("ratio", "0.75");
+
+        let result = engine.render_string("{{if ratio|float >= 0.5}}high{{/if}}", &context).unwrap();
+        assert_eq!(result, "high");
+        let result = engine.render_string("{{if ratio|float < 0.5}}low{{/if}}", &context).unwrap();
+        assert_eq!(result, "");
+    }
+
+    #[test]
+    fn test_bool_filter_rules() {
+        let mut engine = TemplateEngine::new(".");
+        let template = "{{if flag|bool}}on{{/if}}";
+
+        for (input, expected) in [
+            ("1", "on"), ("true", "on"), ("yes", "on"), ("anything", "on"),
+            ("0", ""), ("false", ""), ("FALSE", ""), ("no", ""), ("off", ""), ("", ""),
+        ] {
+            let mut context = TemplateContext::new();
+            context.set_string("flag", input);
+            assert_eq!(engine.render_string(template, &context).unwrap(), expected, "input {:?}", input);
+        }
+
+        let mut context = TemplateContext::new();
+        context.set_number("flag", 0);
+        assert_eq!(engine.render_string("{{if flag|bool == false}}off{{/if}}", &context).unwrap(), "off");
+    }
+
+    #[test]
+    fn test_string_filter_in_condition() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_number("code", 42);
+
+        let result = engine.render_string("{{if code|string == \"42\"}}match{{/if}}", &context).unwrap();
+        assert_eq!(result, "match");
+    }
+
+    #[test]
+    fn test_coercion_in_plural_count() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("count", "1");
+        context.set_string("weight", "1.0");
+
+        let result = engine.render_string("{{plural count \"item\" \"items\"}}", &context).unwrap();
+        assert_eq!(result, "items");
+        let result = engine.render_string("{{plural count|int \"item\" \"items\"}}", &context).unwrap();
+        assert_eq!(result, "item");
+        let result = engine.render_string("{{plural weight|float \"kilo\" \"kilos\"}}", &context).unwrap();
+        assert_eq!(result, "kilo");
+    }
+
+    #[test]
+    fn test_chained_coercion_with_arithmetic_and_currency() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("price", "10");
+
+        let result = engine.render_string("{{price|float|multiply:1.2|currency}}", &context).unwrap();
+        assert_eq!(result, "$12.00");
+    }
+
+    #[test]
+    fn test_invalid_coercion_renders_empty_in_lenient_mode() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("bad", "abc");
+
+        assert!(!engine.is_strict_mode_enabled());
+        let result = engine.render_string("[{{bad|int}}][{{bad|float}}]", &context).unwrap();
+        assert_eq!(result, "[][]");
+    }
+
+    #[test]
+    fn test_invalid_coercion_errors_in_strict_mode() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("bad", "abc");
+        engine.enable_strict_mode();
+
+        match engine.render_string("{{bad|int}}", &context) {
+            Err(TemplateError::FilterError { filter_name, value, .. }) => {
+                assert_eq!(filter_name, "int");
+                assert_eq!(value, "abc");
+            }
+            other => panic!("expected FilterError, got {:?}", other),
+        }
+
+        let err = engine.render_string("{{if bad|float > 1}}x{{/if}}", &context).unwrap_err();
+        assert!(err.to_string().contains("abc"));
+
+        engine.disable_strict_mode();
+        assert_eq!(engine.render_string("{{bad|int}}", &context).unwrap(), "");
+    }
+}
+
+/// yesno filter and configurable boolean display
+#[cfg(test)]
+mod bool_display_tests {
+    use super::*;
+    use mystical_runic::BoolFormat;
+    use std::collections::HashMap;
+
+    fn flags_context() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_bool("on", true);
+        context.set_bool("off", false);
+        context
+    }
+
+    #[test]
+    fn test_yesno_filter_with_arguments() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = flags_context();
+        context.set_number("count", 0);
+
+        let result = engine.render_string(
+            "{{on|yesno:\"Yes\":\"No\"}} {{off|yesno:\"Yes\":\"No\"}} {{count|yesno:\"Y\":\"N\"}}",
+            &context,
+        ).unwrap();
+        assert_eq!(result, "Yes No N");
+    }
+
+    #[test]
+    fn test_yesno_third_argument_for_missing_values() {
+        let mut engine = TemplateEngine::new(".");
+        let context = flags_context();
+
+        let result = engine.render_string("{{missing|yesno:\"Yes\":\"No\":\"Unknown\"}}", &context).unwrap();
+        assert_eq!(result, "Unknown");
+        let result = engine.render_string("{{missing|yesno:\"Yes\":\"No\"}}", &context).unwrap();
+        assert_eq!(result, "No");
+    }
+
+    #[cfg(feature = "i18n")]
+    #[test]
+    fn test_yesno_defaults_follow_locale() {
+        let mut engine = TemplateEngine::new(".");
+        let context = flags_context();
+        let template = "{{on|yesno}}/{{off|yesno}}/{{missing|yesno}}";
+
+        assert_eq!(engine.render_string(template, &context).unwrap(), "Yes/No/No");
+
+        let mut fr = HashMap::new();
+        fr.insert("yes".to_string(), "oui".to_string());
+        fr.insert("no".to_string(), "non".to_string());
+        fr.insert("none".to_string(), "inconnu".to_string());
+        engine.set_translations("fr", fr);
+        engine.set_locale("fr");
+        assert_eq!(engine.render_string(template, &context).unwrap(), "oui/non/inconnu");
+
+        // Locales without the keys keep the English defaults
+        engine.set_locale("de");
+        assert_eq!(engine.render_string(template, &context).unwrap(), "Yes/No/No");
+    }
+
+    #[test]
+    fn test_bool_formats() {
+        let mut engine = TemplateEngine::new(".");
+        let context = flags_context();
+        let template = "[{{on}}|{{off}}]";
+
+        assert_eq!(engine.get_bool_format(), &BoolFormat::TrueFalse);
+        assert_eq!(engine.render_string(template, &context).unwrap(), "[true|false]");
+
+        engine.set_bool_format(BoolFormat::YesNo);
+        assert_eq!(engine.render_string(template, &context).unwrap(), "[Yes|No]");
+
+        engine.set_bool_format(BoolFormat::Empty);
+        assert_eq!(engine.render_string(template, &context).unwrap(), "[|]");
+
+        engine.set_bool_format(BoolFormat::Custom("Activé".to_string(), "Désactivé".to_string()));
+        assert_eq!(engine.render_string(template, &context).unwrap(), "[Activé|Désactivé]");
+    }
+
+    #[test]
+    fn test_bool_format_applies_to_nested_loop_and_filtered_values() {
+        let mut engine = TemplateEngine::new(".");
+        engine.set_bool_format(BoolFormat::YesNo);
+        let mut context = TemplateContext::new();
+        let mut user = HashMap::new();
+        user.insert("admin".to_string(), TemplateValue::Bool(true));
+        context.set("user", TemplateValue::Object(user));
+        context.set("flags", TemplateValue::Array(vec![TemplateValue::Bool(false), TemplateValue::Bool(true)]));
+        context.set_string("raw", "0");
+
+        let result = engine.render_string(
+            "{{user.admin}} {{for f in flags}}{{f}},{{/for}} {{raw|bool}}",
+            &context,
+        ).unwrap();
+        assert_eq!(result, "Yes No,Yes, No");
+    }
+
+    #[test]
+    fn test_bool_format_does_not_affect_conditions() {
+        let mut engine = TemplateEngine::new(".");
+        engine.set_bool_format(BoolFormat::Custom("oui".to_string(), "non".to_string()));
+        let context = flags_context();
+
+        let result = engine.render_string(
+            "{{if on}}A{{/if}}{{if off}}B{{/if}}{{if on == true}}C{{/if}}{{if off == \"false\"}}D{{/if}}",
+            &context,
+        ).unwrap();
+        assert_eq!(result, "ACD");
+    }
+}
+
+/// Value formatters for bare arrays, objects, booleans and missing values
+#[cfg(test)]
+mod value_formatter_tests {
+    use super::*;
+    use mystical_runic::{TemplateError, ValueFormatter, ValueKind};
+    use std::collections::HashMap;
+
+    fn structured_context() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set("tags", TemplateValue::Array(vec![
+            TemplateValue::String("rust".to_string()),
+            TemplateValue::Number(2),
+            TemplateValue::Bool(true),
+        ]));
+        let mut user = HashMap::new();
+        user.insert("name".to_string(), TemplateValue::String("Ada \"L\"".to_string()));
+        user.insert("age".to_string(), TemplateValue::Number(36));
+        context.set("user", TemplateValue::Object(user.clone()));
+        context.set("page", TemplateValue::Object(HashMap::from([
+            ("author".to_string(), TemplateValue::Object(user)),
+        ])));
+        context.set_bool("active", true);
+        context
+    }
+
+    #[test]
+    fn test_empty_preset_keeps_default_rendering() {
+        let mut engine = TemplateEngine::new(".");
+        let context = structured_context();
+        assert_eq!(engine.render_string("[{{tags}}][{{user}}][{{missing}}]", &context).unwrap(), "[][][]");
+
+        engine.set_value_formatter(ValueKind::Array, ValueFormatter::Empty);
+        engine.set_value_formatter(ValueKind::Null, ValueFormatter::Empty);
+        assert_eq!(engine.render_string("[{{tags}}][{{missing}}]", &context).unwrap(), "[][]");
+    }
+
+    #[test]
+    fn test_debug_preset_dumps_values() {
+        let mut engine = TemplateEngine::new(".");
+        for kind in [ValueKind::Array, ValueKind::Object, ValueKind::Null] {
+            engine.set_value_formatter(kind, ValueFormatter::Debug);
+        }
+        let context = structured_context();
+
+        let result = engine.render_string("{{& tags}} {{& page.author}} {{& missing}} {{& user.email}}", &context).unwrap();
+        assert_eq!(result, r#"["rust",2,true] {"age":36,"name":"Ada \"L\""} null null"#);
+
+        // Escaped like any other output
+        assert_eq!(engine.render_string("{{tags}}", &context).unwrap(), "[&quot;rust&quot;,2,true]");
+    }
+
+    #[test]
+    fn test_join_preset() {
+        let mut engine = TemplateEngine::new(".");
+        engine.set_value_formatter(ValueKind::Array, ValueFormatter::Join(", ".to_string()));
+        engine.set_value_formatter(ValueKind::Object, ValueFormatter::Join("; ".to_string()));
+        let context = structured_context();
+
+        let result = engine.render_string("{{tags}} | {{user}}", &context).unwrap();
+        assert_eq!(result, "rust, 2, true | age: 36; name: Ada &quot;L&quot;");
+    }
+
+    #[test]
+    fn test_custom_formatters() {
+        let mut engine = TemplateEngine::new(".");
+        engine.set_value_formatter(ValueKind::Array, ValueFormatter::Custom(|value| match value {
+            TemplateValue::Array(items) => format!("{} items", items.len()),
+            _ => String::new(),
+        }));
+        let checkmark: fn(&TemplateValue) -> String = |value| {
+            if matches!(value, TemplateValue::Bool(true)) { "✓".to_string() } else { "✗".to_string() }
+        };
+        engine.set_value_formatter(ValueKind::Bool, checkmark);
+        engine.set_value_formatter(ValueKind::Null, ValueFormatter::Custom(|_| "n/a".to_string()));
+        let context = structured_context();
+
+        let result = engine.render_string("{{tags}} {{active}} {{missing}} {{if active}}on{{/if}}", &context).unwrap();
+        assert_eq!(result, "3 items ✓ n/a on");
+        assert!(matches!(engine.get_value_formatter(ValueKind::Bool), Some(ValueFormatter::Custom(_))));
+
+        engine.clear_value_formatter(ValueKind::Bool);
+        assert!(engine.get_value_formatter(ValueKind::Bool).is_none());
+        assert_eq!(engine.render_string("{{active}}", &context).unwrap(), "true");
+    }
+
+    #[test]
+    fn test_formatter_applies_to_filter_inputs_and_helpers() {
+        let mut engine = TemplateEngine::new(".");
+        engine.register_helper("pair", |_args| Ok(TemplateValue::Array(vec![
+            TemplateValue::String("a".to_string()),
+            TemplateValue::String("b".to_string()),
+        ])));
+        let context = structured_context();
+
+        // Helper results keep their previous rendering until a formatter is set
+        assert_eq!(engine.render_string("{{tags|upper}}|{{pair()}}", &context).unwrap(), "|[a, b]");
+
+        engine.set_value_formatter(ValueKind::Array, ValueFormatter::Join(" / ".to_string()));
+        let result = engine.render_string("{{tags|upper}}|{{pair()}}|{{tags|truncate:6}}", &context).unwrap();
+        assert_eq!(result, "RUST / 2 / TRUE|a / b|rust /...");
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_bare_structured_values() {
+        let mut engine = TemplateEngine::new(".");
+        engine.enable_strict_mode();
+        let context = structured_context();
+
+        for template in ["{{tags}}", "{{& page.author}}", "{{user|upper}}"] {
+            match engine.render_string(template, &context) {
+                Err(TemplateError::Render(message)) => assert!(message.contains("strict mode"), "{}", message),
+                other => panic!("expected a strict mode error for {}, got {:?}", template, other),
+            }
+        }
+
+        // Missing values, booleans and values behind an explicit formatter still render
+        engine.set_value_formatter(ValueKind::Array, ValueFormatter::Join(",".to_string()));
+        let result = engine.render_string("{{tags}}{{missing}}{{active}}", &context).unwrap();
+        assert_eq!(result, "rust,2,truetrue");
+    }
+}
+
+/// translate / t filters for data-driven translation keys
+#[cfg(feature = "i18n")]
+#[cfg(test)]
+mod translate_filter_tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn engine_with_statuses() -> TemplateEngine {
+        let mut engine = TemplateEngine::new(".");
+        let mut en = HashMap::new();
+        en.insert("pending".to_string(), "Pending".to_string());
+        en.insert("statuses.pending".to_string(), "Awaiting payment".to_string());
+        en.insert("statuses.shipped".to_string(), "On its way".to_string());
+        let mut fr = HashMap::new();
+        fr.insert("pending".to_string(), "En attente".to_string());
+        fr.insert("statuses.pending".to_string(), "Paiement en attente".to_string());
+        fr.insert("statuses.shipped".to_string(), "Expédiée".to_string());
+        engine.set_translations("en", en);
+        engine.set_translations("fr", fr);
+        engine
+    }
+
+    #[test]
+    fn test_translate_present_key() {
+        let mut engine = engine_with_statuses();
+        engine.set_locale("fr");
+        let mut context = TemplateContext::new();
+        context.set_string("status", "pending");
+
+        assert_eq!(engine.render_string("{{status|translate}}", &context).unwrap(), "En attente");
+        assert_eq!(engine.render_string("{{status|t|upper}}", &context).unwrap(), "EN ATTENTE");
+    }
+
+    #[test]
+    fn test_translate_missing_key_falls_back_to_value() {
+        let mut engine = engine_with_statuses();
+        engine.set_locale("fr");
+        let mut context = TemplateContext::new();
+        context.set_string("status", "refunded");
+
+        assert_eq!(engine.render_string("{{status|translate}}", &context).unwrap(), "refunded");
+        assert_eq!(engine.render_string("{{status|t:\"statuses.\"}}", &context).unwrap(), "refunded");
+
+        // No locale selected at all
+        let mut plain = engine_with_statuses();
+        context.set_string("status", "pending");
+        assert_eq!(plain.render_string("{{status|translate}}", &context).unwrap(), "pending");
+    }
+
+    #[test]
+    fn test_prefixed_lookup_in_two_locales() {
+        let mut engine = engine_with_statuses();
+        let mut context = TemplateContext::new();
+        context.set_string("status", "shipped");
+        let template = "{{status|translate:\"statuses.\"}}";
+
+        engine.set_locale("en");
+        assert_eq!(engine.render_string(template, &context).unwrap(), "On its way");
+        engine.set_locale("fr");
+        assert_eq!(engine.render_string(template, &context).unwrap(), "Expédiée");
+    }
+
+    #[test]
+    fn test_translate_in_loops_and_conditions() {
+        let mut engine = engine_with_statuses();
+        engine.set_locale("en");
+        let mut context = TemplateContext::new();
+        let orders = ["pending", "shipped"].iter().map(|status| {
+            let mut order = HashMap::new();
+            order.insert("status".to_string(), TemplateValue::String(status.to_string()));
+            TemplateValue::Object(order)
+        }).collect();
+        context.set("orders", TemplateValue::Array(orders));
+        context.set_string("status", "pending");
+
+        let result = engine.render_string(
+            "{{for order in orders}}[{{order.status|t:\"statuses.\"}}]{{/for}}",
+            &context,
+        ).unwrap();
+        assert_eq!(result, "[Awaiting payment][On its way]");
+
+        let result = engine.render_string("{{if status|t == \"Pending\"}}yes{{/if}}", &context).unwrap();
+        assert_eq!(result, "yes");
+    }
+}
+
+/// sanitize filter: allow-listed HTML from user-written rich text
+#[cfg(test)]
+mod sanitize_filter_tests {
+    use super::*;
+    use mystical_runic::SanitizerPolicy;
+
+    fn sanitize(engine: &mut TemplateEngine, html: &str) -> String {
+        let mut context = TemplateContext::new();
+        context.set_string("body", html);
+        engine.render_string("{{body|sanitize}}", &context).unwrap()
+    }
+
+    #[test]
+    fn test_allowed_markup_is_kept_unescaped() {
+        let mut engine = TemplateEngine::new(".");
+        let html = "<p>Hello <strong>bold</strong> &amp; <em>soft</em></p><ul><li>one<br></li></ul><pre><code>a &lt; b</code></pre>";
+        assert_eq!(sanitize(&mut engine, html), html);
+
+        let result = sanitize(&mut engine, "<a href=\"https://example.com/?a=1&amp;b=2\" title=\"x\">link</a>");
+        assert_eq!(result, "<a href=\"https://example.com/?a=1&amp;b=2\">link</a>");
+    }
+
+    #[test]
+    fn test_disallowed_markup_is_removed() {
+        let mut engine = TemplateEngine::new(".");
+        let cases = [
+            ("<div class=\"x\"><b>Hi</b> <i>there</i></div>", "Hi there"),
+            ("<p style=\"color:red\" onclick=\"alert(1)\">Text</p>", "<p>Text</p>"),
+            ("Before<script>alert(1)</script>After", "BeforeAfter"),
+            ("<style>p { color: red }</style><p>Styled</p>", "<p>Styled</p>"),
+            ("<!-- note --><p>Visible</p><!DOCTYPE html>", "<p>Visible</p>"),
+            ("1 < 2 > 0 \"quoted\"", "1 &lt; 2 &gt; 0 &quot;quoted&quot;"),
+            ("Tom & Jerry &copy; &#169; &#xA9;", "Tom &amp; Jerry &copy; &#169; &#xA9;"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(sanitize(&mut engine, input), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_malformed_input_is_balanced_without_panicking() {
+        let mut engine = TemplateEngine::new(".");
+        let cases = [
+            ("<p>unclosed <strong>bold", "<p>unclosed <strong>bold</strong></p>"),
+            ("<p><em>crossed</p></em>", "<p><em>crossed</em></p>"),
+            ("</li>stray close</p>", "stray close"),
+            ("<a href=\"https://example.com", ""),
+            ("<p", ""),
+            ("<", "&lt;"),
+            ("<<p>>", "&lt;<p>&gt;</p>"),
+            ("<p =x \"'>odd</p>", "<p>odd</p>"),
+            ("<script>never closed", ""),
+            ("<!-- never closed", ""),
+            ("<p>é<strong>ü</strong>", "<p>é<strong>ü</strong></p>"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(sanitize(&mut engine, input), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_xss_corpus_is_neutralized() {
+        let mut engine = TemplateEngine::new(".");
+        let corpus = [
+            "<script>alert(1)</script>",
+            "<SCRIPT SRC=//evil.example/x.js></SCRIPT>",
+            "<ScRiPt>alert(1)</sCrIpT>",
+            "<scr<script>ipt>alert(1)</script>",
+            "<<script>script>alert(1)<</script>/script>",
+            "<script >alert(1)</script >",
+            "<img src=x onerror=alert(1)>",
+            "<IMG SRC=\"javascript:alert(1)\">",
+            "<svg onload=alert(1)>",
+            "<svg><script>alert(1)</script></svg>",
+            "<math><mtext><script>alert(1)</script></mtext></math>",
+            "<iframe src=\"javascript:alert(1)\"></iframe>",
+            "<body onload=alert(1)>",
+            "<p onmouseover=\"alert(1)\">hover</p>",
+            "<P ONCLICK=alert(1)>hover</P>",
+            "<a href=\"javascript:alert(1)\">x</a>",
+            "<A HREF=\"JAVASCRIPT:alert(1)\">x</A>",
+            "<a href=\" javascript:alert(1)\">x</a>",
+            "<a href=\"java\tscript:alert(1)\">x</a>",
+            "<a href=\"java&#x09;script:alert(1)\">x</a>",
+            "<a href=\"&#106;&#97;&#118;&#97;&#115;&#99;&#114;&#105;&#112;&#116;&#58;alert(1)\">x</a>",
+            "<a href=\"&#x6A;&#x61;&#x76;&#x61;&#x73;&#x63;&#x72;&#x69;&#x70;&#x74;&#x3A;alert(1)\">x</a>",
+            "<a href=\"&#106avascript:alert(1)\">x</a>",
+            "<a href=\"&#0000106;avascript:alert(1)\">x</a>",
+            "<a href=\"javascript&colon;alert(1)\">x</a>",
+            "<a href=\"vbscript:msgbox(1)\">x</a>",
+            "<a href=\"data:text/html;base64,PHNjcmlwdD5hbGVydCgxKTwvc2NyaXB0Pg==\">x</a>",
+            "<a href='javascript:alert(1)'>x</a>",
+            "<a href=javascript:alert(1)>x</a>",
+            "<a/href=\"javascript:alert(1)\">x</a>",
+            "<a href=\"https://ok.example\" onclick=\"alert(1)\">x</a>",
+            "<a href=\"https://ok.example\"\"onclick=alert(1)>x</a>",
+            "<p title=\"</p><script>alert(1)</script>\">x</p>",
+            "<style>@import 'javascript:alert(1)';</style>",
+            "<object data=\"javascript:alert(1)\"></object>",
+            "<embed src=\"javascript:alert(1)\">",
+            "<form action=\"javascript:alert(1)\"><button>x</button></form>",
+            "<input onfocus=alert(1) autofocus>",
+            "<details open ontoggle=alert(1)>",
+            "<textarea><script>alert(1)</script></textarea>",
+            "<noscript><p title=\"</noscript><img src=x onerror=alert(1)>\"></noscript>",
+            "<!--<script>alert(1)</script>-->",
+            "<!--[if IE]><script>alert(1)</script><![endif]-->",
+            "<p><a href=\"https://ok.example\"><strong onclick=alert(1)>x",
+        ];
+
+        for payload in corpus {
+            let result = sanitize(&mut engine, payload);
+            let lowercase = result.to_lowercase();
+            assert!(!lowercase.contains("<script"), "{} -> {}", payload, result);
+            assert!(!lowercase.contains("javascript:") && !lowercase.contains("vbscript:") && !lowercase.contains("data:"), "{} -> {}", payload, result);
+            assert!(!lowercase.contains(" on"), "{} -> {}", payload, result);
+            for tag in ["<img", "<svg", "<iframe", "<object", "<embed", "<form", "<input", "<body", "<details", "<style", "<textarea", "<math"] {
+                assert!(!lowercase.contains(tag), "{} -> {}", payload, result);
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_policy() {
+        let mut engine = TemplateEngine::new(".");
+        engine.configure_sanitizer(
+            SanitizerPolicy::default()
+                .allow_tag("IMG")
+                .allow_attribute("img", "src")
+                .allow_attribute("img", "onerror")
+                .allow_url_scheme("ftp"),
+        );
+        assert!(engine.get_sanitizer_policy().allowed_tags.contains(&"img".to_string()));
+
+        let result = sanitize(&mut engine, "<img src=\"/cat.png\" onerror=\"alert(1)\" alt=\"cat\"><a href=\"ftp://files.example\">f</a>");
+        assert_eq!(result, "<img src=\"/cat.png\"><a href=\"ftp://files.example\">f</a>");
+
+        engine.configure_sanitizer(SanitizerPolicy {
+            allowed_tags: vec!["em".to_string()],
+            ..SanitizerPolicy::default()
+        });
+        assert_eq!(sanitize(&mut engine, "<p><em>only</em> <a href=\"https://x\">emphasis</a></p>"), "<em>only</em> emphasis");
+    }
+
+    #[test]
+    fn test_protocol_relative_urls_need_an_allowed_host() {
+        let mut engine = TemplateEngine::new(".");
+        for (input, expected) in [
+            ("<a href=\"//evil.com\">x</a>", "<a>x</a>"),
+            ("<a href=\"//evil.com/path?q#f\">x</a>", "<a>x</a>"),
+            ("<a href=\"\\\\evil.com\">x</a>", "<a>x</a>"),
+            ("<a href=\"/\\evil.com\">x</a>", "<a>x</a>"),
+            ("<a href=\" //evil.com\">x</a>", "<a>x</a>"),
+            ("<a href=\"/docs/page\">x</a>", "<a href=\"/docs/page\">x</a>"),
+            ("<a href=\"docs//page\">x</a>", "<a href=\"docs//page\">x</a>"),
+        ] {
+            assert_eq!(sanitize(&mut engine, input), expected, "input: {}", input);
+        }
+
+        engine.configure_sanitizer(SanitizerPolicy::default().allow_url_host("CDN.example.com"));
+        for (input, expected) in [
+            ("<a href=\"//cdn.example.com/a.png\">x</a>", "<a href=\"//cdn.example.com/a.png\">x</a>"),
+            ("<a href=\"//CDN.example.com:443\">x</a>", "<a href=\"//CDN.example.com:443\">x</a>"),
+            ("<a href=\"//cdn.example.com@evil.com/\">x</a>", "<a>x</a>"),
+            ("<a href=\"//cdn.example.com.evil.com/\">x</a>", "<a>x</a>"),
+        ] {
+            assert_eq!(sanitize(&mut engine, input), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_nul_characters_are_removed() {
+        let mut engine = TemplateEngine::new(".");
+        for (input, expected) in [
+            ("a\0b<p>c\0</p>", "ab<p>c</p>"),
+            ("<scr\0ipt>alert(1)</script>after", "after"),
+            ("<a href=\"java\0script:alert(1)\">x</a>", "<a>x</a>"),
+            ("<p title=\"\0\">x</p>", "<p>x</p>"),
+        ] {
+            let result = sanitize(&mut engine, input);
+            assert_eq!(result, expected, "input: {:?}", input);
+            assert!(!result.contains('\0'));
+        }
+    }
+}
+
+/// Translation values: interpolation, filters, comments and {{t}} references only
+#[cfg(feature = "i18n")]
+#[cfg(test)]
+mod translation_rendering_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+    use std::collections::HashMap;
+
+    fn engine_with(entries: &[(&str, &str)]) -> TemplateEngine {
+        let mut engine = TemplateEngine::new(".");
+        let translations = entries.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect::<HashMap<_, _>>();
+        engine.set_translations("en", translations);
+        engine.set_locale("en");
+        engine
+    }
+
+    #[test]
+    fn test_interpolation_and_filters_still_render() {
+        let mut engine = engine_with(&[
+            ("hello_user", "Hello {{name|upper}}!{{! shown to everyone }}"),
+            ("greeting", "{{t \"hello_user\"}} You have {{count}} items"),
+        ]);
+        let mut context = TemplateContext::new();
+        context.set_string("name", "<ada>");
+        context.set_number("count", 3);
+        assert_eq!(engine.render_string("{{t \"hello_user\"}}", &context).unwrap(), "Hello &lt;ADA&gt;!");
+        assert_eq!(engine.render_string("{{t \"greeting\"}}", &context).unwrap(), "Hello &lt;ADA&gt;! You have 3 items");
+    }
+
+    #[test]
+    fn test_named_arguments_are_variables_of_the_translation() {
+        let mut engine = engine_with(&[("items_count", "{{who}} has {{count}} items")]);
+        let mut context = TemplateContext::new();
+        context.set_string("total", "2.0");
+        let result = engine.render_string("{{t \"items_count\" who=\"Bob\" count=total|int}}", &context).unwrap();
+        assert_eq!(result, "Bob has 2 items");
+    }
+
+    #[test]
+    fn test_self_referencing_key_errors_with_chain() {
+        let mut engine = engine_with(&[("loop", "again {{t \"loop\"}}")]);
+        match engine.render_string("{{t \"loop\"}}", &TemplateContext::new()) {
+            Err(TemplateError::Render(message)) => assert!(message.contains("loop -> loop"), "{}", message),
+            other => panic!("expected a recursion error, got {:?}", other),
+        }
+
+        let mut engine = engine_with(&[("a", "{{t \"b\"}}"), ("b", "{{t 'a'}}")]);
+        match engine.render_string("{{t \"a\"}}", &TemplateContext::new()) {
+            Err(TemplateError::Render(message)) => assert!(message.contains("a -> b -> a"), "{}", message),
+            other => panic!("expected a recursion error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_templates_constructs_are_rejected() {
+        let forbidden = [
+            "{{include \"secret.html\"}}",
+            "{{extends \"layout.html\"}}",
+            "{{if admin}}yes{{/if}}",
+            "{{for item in items}}{{item}}{{/for}}",
+            "{{macro m()}}x{{/macro}}",
+            "{{& body}}",
+            "{{plural count \"item\" \"items\"}}",
+        ];
+        for value in forbidden {
+            let mut engine = engine_with(&[("evil", value), ("wrapper", "ok {{t \"evil\"}}")]);
+            for template in ["{{t \"evil\"}}", "{{t \"wrapper\"}}"] {
+                match engine.render_string(template, &TemplateContext::new()) {
+                    Err(TemplateError::Security(message)) => assert!(message.contains("translation 'evil'"), "{}", message),
+                    other => panic!("expected {} to be rejected, got {:?}", value, other),
+                }
+            }
+        }
+    }
+}
+
+/// Data tables: {{table rows columns="key:Label|filter,..."}}
+#[cfg(test)]
+mod table_directive_tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn row(fields: &[(&str, TemplateValue)]) -> TemplateValue {
+        TemplateValue::Object(fields.iter().map(|(key, value)| (key.to_string(), value.clone())).collect::<HashMap<_, _>>())
+    }
+
+    fn users() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set("users", TemplateValue::Array(vec![
+            row(&[("name", TemplateValue::String("Ada".to_string())), ("email", TemplateValue::String("ada@example.com".to_string()))]),
+            row(&[("name", TemplateValue::String("Bob".to_string())), ("role", TemplateValue::String("admin".to_string()))]),
+        ]));
+        context
+    }
+
+    #[test]
+    fn test_table_renders_headers_and_rows() {
+        let mut engine = TemplateEngine::new(".");
+        let result = engine.render_string(r#"{{table users columns="name,email:E-mail,user_role" class="users"}}"#, &users()).unwrap();
+        assert_eq!(result, concat!(
+            "<table class=\"users\"><thead><tr><th>Name</th><th>E-mail</th><th>User role</th></tr></thead><tbody>",
+            "<tr><td>Ada</td><td>ada@example.com</td><td></td></tr>",
+            "<tr><td>Bob</td><td></td><td></td></tr>",
+            "</tbody></table>",
+        ));
+    }
+
+    #[test]
+    fn test_missing_keys_and_rows_render_empty() {
+        let mut engine = TemplateEngine::new(".");
+        let result = engine.render_string(r#"{{table users columns="role:Role"}}"#, &users()).unwrap();
+        assert_eq!(result, "<table><thead><tr><th>Role</th></tr></thead><tbody><tr><td></td></tr><tr><td>admin</td></tr></tbody></table>");
+
+        let result = engine.render_string(r#"{{table nobody columns="name"}}"#, &TemplateContext::new()).unwrap();
+        assert_eq!(result, "<table><thead><tr><th>Name</th></tr></thead><tbody></tbody></table>");
+    }
+
+    #[test]
+    fn test_column_filters_apply_to_cells() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set("items", TemplateValue::Array(vec![
+            row(&[("title", TemplateValue::String("lamp".to_string())), ("price", TemplateValue::Float(12.5))]),
+        ]));
+        let result = engine.render_string(r#"{{table items columns="title|upper,price:Price|currency"}}"#, &context).unwrap();
+        assert_eq!(result, "<table><thead><tr><th>Title</th><th>Price</th></tr></thead><tbody><tr><td>LAMP</td><td>$12.50</td></tr></tbody></table>");
+    }
+
+    #[test]
+    fn test_hostile_cells_and_class_are_escaped() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set("rows", TemplateValue::Array(vec![
+            row(&[("name", TemplateValue::String("<script>alert(\"x\")</script>".to_string()))]),
+        ]));
+        let result = engine.render_string(r#"{{table rows columns="name:<b>Name</b>" class='a" onclick="x'}}"#, &context).unwrap();
+        assert!(!result.contains("<script>"));
+        assert!(!result.contains("<b>"));
+        assert!(result.contains("<td>&lt;script&gt;alert(&quot;x&quot;)&lt;/script&gt;</td>"));
+        assert!(result.starts_with("<table class=\"a&quot; onclick=&quot;x\">"));
+    }
+
+    #[test]
+    fn test_columns_from_context_array() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = users();
+        context.set("report_columns", TemplateValue::Array(vec![
+            TemplateValue::String("name:Who".to_string()),
+            TemplateValue::String("name|upper".to_string()),
+        ]));
+        let result = engine.render_string("{{table users columns=report_columns}}", &context).unwrap();
+        assert!(result.contains("<th>Who</th><th>Name</th>"));
+        assert!(result.contains("<tr><td>Ada</td><td>ADA</td></tr>"));
+    }
+
+    #[cfg(feature = "template-macros")]
+    #[test]
+    fn test_row_macro_renders_cells() {
+        let mut engine = TemplateEngine::new(".");
+        let template = r#"{{macro user_row(user, index)}}<td>{{index}}</td><td>{{user.name}}</td>{{/macro}}{{table users columns="n:#,name" row="user_row"}}"#;
+        let result = engine.render_string(template, &users()).unwrap();
+        assert!(result.contains("<tr><td>1</td><td>Ada</td></tr><tr><td>2</td><td>Bob</td></tr>"));
+    }
+
+    #[test]
+    fn test_malformed_table_is_rejected() {
+        let mut engine = TemplateEngine::new(".");
+        assert!(engine.render_string("{{table users}}", &users()).is_err());
+        assert!(engine.render_string(r#"{{table users columns="name" border="1"}}"#, &users()).is_err());
+        assert!(engine.render_string(r#"{{table users columns="name" row="missing"}}"#, &users()).is_err());
+        assert!(engine.check_syntax(r#"{{table users class="x"}}"#).is_err());
+
+        let mut context = TemplateContext::new();
+        context.set_string("table", "plain variable");
+        assert_eq!(engine.render_string("{{table}}", &context).unwrap(), "plain variable");
+    }
+}
+
+/// Filter pipelines: named filter chains with positional arguments
+#[cfg(test)]
+mod filter_pipeline_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+
+    #[test]
+    fn test_pipeline_expands_to_its_chain() {
+        let mut engine = TemplateEngine::new(".");
+        engine.register_pipeline("shout", "strip|upper").unwrap();
+        let mut context = TemplateContext::new();
+        context.set_string("name", "  ada <3 ");
+        assert_eq!(engine.render_string("{{name|shout}}!", &context).unwrap(), "ADA &lt;3!");
+        assert_eq!(engine.render_string("{{if name|shout == \"ADA <3\"}}same{{/if}}", &context).unwrap(), "same");
+    }
+
+    #[test]
+    fn test_positional_arguments_are_substituted() {
+        let mut engine = TemplateEngine::new(".");
+        engine.register_pipeline("gross", "multiply:$1|round:$2").unwrap();
+        let mut context = TemplateContext::new();
+        context.set_float("net", 10.0);
+        assert_eq!(engine.render_string("{{net|gross:1.25:1}}", &context).unwrap(), "12.5");
+        assert_eq!(
+            engine.render_string("{{net|gross:1.25:1}}", &context).unwrap(),
+            engine.render_string("{{net|multiply:1.25|round:1}}", &context).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_nested_pipelines() {
+        let mut engine = TemplateEngine::new(".");
+        // Pipelines can reference pipelines registered later
+        engine.register_pipeline("headline", "excerpt:$1|upper").unwrap();
+        engine.register_pipeline("excerpt", "strip|truncate:$1").unwrap();
+        let mut context = TemplateContext::new();
+        context.set_string("body", "  a long story about foxes  ");
+        assert_eq!(
+            engine.render_string("{{body|headline:6}}", &context).unwrap(),
+            engine.render_string("{{body|strip|truncate:6|upper}}", &context).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_cycles_are_rejected() {
+        let mut engine = TemplateEngine::new(".");
+        engine.register_pipeline("a", "b|upper").unwrap();
+        match engine.register_pipeline("b", "a") {
+            Err(TemplateError::Template(message)) => assert!(message.contains("a -> b -> a") || message.contains("b -> a -> b"), "{}", message),
+            other => panic!("expected a cycle error, got {:?}", other),
+        }
+        assert!(engine.register_pipeline("self", "lower|self").is_err());
+        assert!(engine.register_pipeline("upper", "lower").is_err());
+
+        // The rejected pipelines were not kept
+        let mut context = TemplateContext::new();
+        context.set_string("word", "Hi");
+        assert_eq!(engine.render_string("{{word|a}}", &context).unwrap(), "HI");
+    }
+
+    #[cfg(feature = "lsp")]
+    #[test]
+    fn test_pipelines_are_known_filters() {
+        let mut engine = TemplateEngine::new(".");
+        assert!(engine.check_syntax("{{body|excerpt}}").is_err());
+        engine.register_pipeline("excerpt", "strip|truncate:140|capitalize").unwrap();
+        assert!(engine.check_syntax("{{body|excerpt}}").is_ok());
+
+        let diagnostics = engine.get_diagnostics_for_editor("{{body|excerpt}}", &TemplateContext::new()).unwrap();
+        assert!(diagnostics.iter().all(|diagnostic| !diagnostic.message.contains("Unknown filter")));
+
+        let completions = engine.get_completions_at_position("{{body|exc}}", 10, &TemplateContext::new()).unwrap();
+        assert!(completions.iter().any(|item| item.label == "excerpt" && item.detail.contains("strip|truncate:140|capitalize")));
+    }
+}
+
+/// HTML truncation: {{body|truncate_html:n}}
+#[cfg(test)]
+mod truncate_html_filter_tests {
+    use super::*;
+
+    fn truncate(html: &str, limit: usize) -> String {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("body", html);
+        let result = engine.render_string(&format!("{{{{body|truncate_html:{}}}}}", limit), &context).unwrap();
+        assert_balanced(&result);
+        result
+    }
+
+    /// Every opened non-void tag is closed, in order, and no tag is cut
+    fn assert_balanced(html: &str) {
+        let mut stack: Vec<String> = Vec::new();
+        let mut rest = html;
+        while let Some(start) = rest.find('<') {
+            let end = rest[start..].find('>').unwrap_or_else(|| panic!("cut tag in {}", html));
+            let tag = &rest[start + 1..start + end];
+            assert!(!tag.contains('<'), "cut tag in {}", html);
+            if let Some(name) = tag.strip_prefix('/') {
+                assert_eq!(stack.pop().as_deref(), Some(name), "unbalanced {}", html);
+            } else {
+                let name = tag.split_whitespace().next().unwrap().trim_end_matches('/');
+                if !["br", "img", "hr"].contains(&name) {
+                    stack.push(name.to_string());
+                }
+            }
+            rest = &rest[start + end + 1..];
+        }
+        assert!(stack.is_empty(), "unclosed {:?} in {}", stack, html);
+        for entity_start in html.match_indices('&').map(|(index, _)| index) {
+            assert!(html[entity_start..].find(';').is_some_and(|end| end < 10), "cut entity in {}", html);
+        }
+    }
+
+    #[test]
+    fn test_short_html_is_unchanged() {
+        assert_eq!(truncate("<p>Hi <em>there</em></p>", 20), "<p>Hi <em>there</em></p>");
+        assert_eq!(truncate("<p>Hi <em>there</em></p>", 8), "<p>Hi <em>there</em></p>");
+    }
+
+    #[test]
+    fn test_cut_inside_tag_name_keeps_whole_tags() {
+        // Characters 6 to 14 of the source are the `<strong>` tag
+        assert_eq!(truncate("Hello <strong>world</strong>", 6), "Hello ...");
+        assert_eq!(truncate("Hello <strong>world</strong>", 8), "Hello <strong>wo</strong>...");
+        assert_eq!(truncate("<p>Hello</p><p>world</p>", 5), "<p>Hello...</p>");
+    }
+
+    #[test]
+    fn test_entities_count_as_one_character() {
+        assert_eq!(truncate("Tom &amp; Jerry &copy; 2024", 5), "Tom &amp;...");
+        assert_eq!(truncate("A&#x2014;B&#169;C", 4), "A&#x2014;B&#169;...");
+        assert_eq!(truncate("1 < 2 & 3", 3), "1 &lt;...");
+    }
+
+    #[test]
+    fn test_nested_inline_markup_closes_before_ellipsis() {
+        let html = "<div><p>Some <em>very <strong>deeply nested</strong> words</em> here</p></div>";
+        assert_eq!(truncate(html, 14), "<div><p>Some <em>very <strong>deep</strong></em>...</p></div>");
+        assert_eq!(truncate(html, 23), "<div><p>Some <em>very <strong>deeply nested</strong></em>...</p></div>");
+    }
+
+    #[test]
+    fn test_void_elements_and_unclosed_input() {
+        assert_eq!(truncate("one<br>two<img src=\"x.png\">three", 7), "one<br>two<img src=\"x.png\">t...");
+        assert_eq!(truncate("<ul><li>first<li>second", 8), "<ul><li>first<li>sec...</li></li></ul>");
+        assert_eq!(truncate("<em>unterminated <strong", 50), "<em>unterminated </em>");
+    }
+
+    #[test]
+    fn test_output_is_not_escaped_again() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("body", "<b>bold</b> text");
+        assert_eq!(engine.render_string("{{body|truncate_html:4}}", &context).unwrap(), "<b>bold</b>...");
+        assert!(engine.check_syntax("{{body|truncate_html:4}}").is_ok());
+    }
+}
+
+/// Filter arguments checked against the built-in filter signatures
+#[cfg(test)]
+mod filter_argument_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+
+    fn strict_error(template: &str, context: &TemplateContext) -> TemplateError {
+        let mut engine = TemplateEngine::new(".");
+        engine.enable_strict_mode();
+        engine.render_string(template, context).unwrap_err()
+    }
+
+    fn title() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("title", "Hello world");
+        context.set_number("price", 3);
+        context
+    }
+
+    #[test]
+    fn test_missing_required_argument() {
+        match strict_error("<h1>\n  {{title|truncate}}</h1>", &title()) {
+            TemplateError::ParseWithLocation { message, line, column, .. } => {
+                assert_eq!(message, "Filter 'truncate' takes 1 argument, got 0; usage: truncate:length");
+                assert_eq!((line, column), (2, 3));
+            }
+            other => panic!("expected a located error, got {:?}", other),
+        }
+
+        // Lenient mode renders as before
+        let mut engine = TemplateEngine::new(".");
+        assert_eq!(engine.render_string("{{title|truncate}}", &title()).unwrap(), "Hello world");
+    }
+
+    #[test]
+    fn test_too_many_and_empty_arguments() {
+        let error = strict_error("{{title|upper:2}}", &title());
+        assert!(error.to_string().contains("Filter 'upper' takes 0 arguments, got 1; usage: upper"), "{}", error);
+
+        let error = strict_error("{{price|round:2:3}}", &title());
+        assert!(error.to_string().contains("Filter 'round' takes 0 to 1 arguments, got 2; usage: round[:decimals]"), "{}", error);
+
+        let error = strict_error("{{title|truncate::10}}", &title());
+        assert!(error.to_string().contains("Empty argument 1 in 'truncate::10'"), "{}", error);
+    }
+
+    #[test]
+    fn test_non_numeric_literal_to_round() {
+        let error = strict_error("{{price|round:\"abc\"}}", &title());
+        assert!(error.to_string().contains("Filter 'round' expects a whole number, got \"abc\"; usage: round[:decimals]"), "{}", error);
+
+        // Quoted colons don't split arguments
+        let mut engine = TemplateEngine::new(".");
+        engine.enable_strict_mode();
+        assert!(engine.render_string("{{title|date:\"Y-m-d H:i\"}}", &title()).is_ok());
+        assert!(engine.render_string("{{if price|round:\"x\" > 2}}big{{/if}}", &title()).is_err());
+    }
+
+    #[cfg(feature = "lsp")]
+    #[test]
+    fn test_lint_and_editor_diagnostics_show_the_signature() {
+        let mut engine = TemplateEngine::new(".");
+        let diagnostics = engine.get_diagnostics_for_editor("{{price|multiply:\"lots\"}}", &title()).unwrap();
+        let diagnostic = diagnostics.iter()
+            .find(|diagnostic| diagnostic.code.as_deref() == Some("filter-arguments"))
+            .expect("filter-arguments diagnostic");
+        assert_eq!(diagnostic.severity, "warning");
+        assert_eq!(diagnostic.message, "Filter 'multiply' expects a number, got \"lots\"; usage: multiply:number");
+
+        engine.enable_strict_mode();
+        let diagnostics = engine.get_diagnostics_for_editor("{{price|multiply:\"lots\"}}", &title()).unwrap();
+        assert!(diagnostics.iter().any(|diagnostic| diagnostic.code.as_deref() == Some("filter-arguments") && diagnostic.severity == "error"));
+    }
+
+    #[cfg(feature = "lsp")]
+    #[test]
+    fn test_variable_argument_is_checked_when_rendering() {
+        let mut context = title();
+        context.set_number("limit", 5);
+        context.set_number("tax_rate", 2);
+        let mut engine = TemplateEngine::new(".");
+        engine.enable_strict_mode();
+        assert_eq!(engine.render_string("{{title|truncate:limit}}", &context).unwrap(), "Hello...");
+        assert_eq!(engine.render_string("{{price|multiply:tax_rate}}", &context).unwrap(), "6");
+
+        // Nothing to report before rendering
+        let diagnostics = engine.get_diagnostics_for_editor("{{title|truncate:limit}}", &context).unwrap();
+        assert!(diagnostics.iter().all(|diagnostic| diagnostic.code.as_deref() != Some("filter-arguments")));
+
+        context.set_string("limit", "abc");
+        match engine.render_string("{{title|truncate:limit}}", &context) {
+            Err(TemplateError::FilterError { filter_name, value, .. }) => {
+                assert_eq!(filter_name, "truncate");
+                assert_eq!(value, "abc");
+            }
+            other => panic!("expected a filter error, got {:?}", other),
+        }
+        assert!(matches!(
+            engine.render_string("{{title|truncate:missing}}", &context),
+            Err(TemplateError::FilterError { .. })
+        ));
+
+        engine.disable_strict_mode();
+        assert_eq!(engine.render_string("{{title|truncate:limit}}", &context).unwrap(), "Hello world");
+    }
+
+    #[test]
+    fn test_context_arguments_reach_builtin_and_custom_filters() {
+        let mut tax = std::collections::HashMap::new();
+        tax.insert("rate".to_string(), TemplateValue::Float(1.5));
+        let mut context = title();
+        context.set("tax", TemplateValue::Object(tax));
+        context.set_number("count", 4);
+        context.set_string("separator", " | ");
+
+        let mut engine = TemplateEngine::new(".");
+        assert_eq!(engine.render_string("{{price|multiply:tax.rate}}", &context).unwrap(), "4.5");
+        assert_eq!(engine.render_string("{{price|add:count}} {{title|truncate:count}} {{tax.rate|round:count}}", &context).unwrap(), "7 Hell... 1.5000");
+
+        // Custom filters get the resolved text; quoted arguments stay literal
+        engine.register_filter("surround", |input, args| Ok(format!("{}{}{}", args[0], input, args[0])));
+        assert_eq!(engine.render_string("{{title|surround:separator}}", &context).unwrap(), " | Hello world | ");
+        assert_eq!(engine.render_string("{{title|surround:\"separator\"}}", &context).unwrap(), "separatorHello worldseparator");
+        assert_eq!(engine.render_string("{{price|add:\"count\"}}", &context).unwrap(), "3");
+    }
+}
+
+/// Grapheme-aware `length` and `truncate`, `count_chars` and `count_bytes`
+#[cfg(test)]
+mod character_count_tests {
+    use super::*;
+
+    const FAMILY: &str = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+    const FLAG: &str = "\u{1F1EB}\u{1F1F7}";
+    const DECOMPOSED: &str = "he\u{301}llo";
+
+    fn render(template: &str, text: &str) -> String {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("text", text);
+        engine.render_string(template, &context).unwrap()
+    }
+
+    fn counts(text: &str) -> String {
+        render("{{text|length}} {{text|count_chars}} {{text|count_bytes}}", text)
+    }
+
+    #[test]
+    fn test_length_counts_grapheme_clusters() {
+        assert_eq!(counts("héllo"), "5 5 6");
+        assert_eq!(counts(DECOMPOSED), "5 6 7");
+        assert_eq!(counts(FAMILY), "1 5 18");
+        assert_eq!(counts(FLAG), "1 2 8");
+        assert_eq!(counts(&format!("{}{}{}", FLAG, FLAG, "\u{1F1EB}")), "3 5 20");
+        assert_eq!(counts("\u{1F44D}\u{1F3FD}"), "1 2 8");
+        assert_eq!(counts("a\r\nb"), "3 4 4");
+        assert_eq!(counts(""), "0 0 0");
+    }
+
+    #[test]
+    fn test_truncate_agrees_with_length() {
+        assert_eq!(render("{{text|truncate:2}}", "héllo"), "hé...");
+        assert_eq!(render("{{text|truncate:2}}", DECOMPOSED), "he\u{301}...");
+        assert_eq!(render("{{text|truncate:5}}", DECOMPOSED), DECOMPOSED);
+        let family = format!("{}{}{}", FAMILY, FAMILY, FLAG);
+        assert_eq!(render("{{text|truncate:1}}", &family), format!("{}...", FAMILY));
+        assert_eq!(render("{{text|truncate:2}}", &format!("{}{}x", FLAG, FLAG)), format!("{}{}...", FLAG, FLAG));
+        assert_eq!(render("{{text|truncate:3}}", &family), family);
+    }
+
+    #[test]
+    fn test_length_in_conditions() {
+        let template = "{{if text|length <= 3}}fits{{/if}}";
+        assert_eq!(render(template, &format!("{}{}{}", FAMILY, FLAG, "é")), "fits");
+        assert_eq!(render(template, "héllo"), "");
+    }
+
+    #[test]
+    fn test_truncate_html_counts_grapheme_clusters() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("html", &format!("<p>{}{} done</p>", FAMILY, FLAG));
+        let result = engine.render_string("{{html|truncate_html:2}}", &context).unwrap();
+        assert_eq!(result, format!("<p>{}{}...</p>", FAMILY, FLAG));
+    }
+}
+
+/// Deterministic output for object rendering and diagnostics
+#[cfg(test)]
+mod deterministic_output_tests {
+    use super::*;
+
+    fn object_from(pairs: &[(&str, i64)]) -> TemplateValue {
+        let mut map = HashMap::new();
+        for (key, value) in pairs {
+            map.insert(key.to_string(), TemplateValue::Number(*value));
+        }
+        TemplateValue::Object(map)
+    }
+
+    #[test]
+    fn test_object_stringification_is_sorted() {
+        let mut engine = TemplateEngine::new(".");
+        engine.register_helper("stats", |_args| {
+            Ok(object_from(&[("zeta", 3), ("alpha", 1), ("mid", 2)]))
+        });
+        let context = TemplateContext::new();
+
+        let result = engine.render_string("{{stats()}}", &context).unwrap();
+        assert_eq!(result, "{alpha: 1, mid: 2, zeta: 3}");
+    }
+
+    #[test]
+    fn test_repeated_renders_are_byte_identical() {
+        let mut engine = TemplateEngine::new(".");
+        engine.register_helper("echo", |args| Ok(args[0].clone()));
+        let mut context = TemplateContext::new();
+        let keys: Vec<(String, i64)> = (0..50).map(|i| (format!("key{}", i), i)).collect();
+        let pairs: Vec<(&str, i64)> = keys.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        context.set("data", object_from(&pairs));
+
+        let first = engine.render_string("{{echo(data)}}", &context).unwrap();
+        for _ in 0..10 {
+            let mut fresh_engine = TemplateEngine::new(".");
+            fresh_engine.register_helper("echo", |args| Ok(args[0].clone()));
+            assert_eq!(fresh_engine.render_string("{{echo(data)}}", &context).unwrap(), first);
+        }
+    }
+
+    #[test]
+    fn test_equal_contexts_built_in_different_order_render_identically() {
+        let mut engine = TemplateEngine::new(".");
+        engine.register_helper("echo", |args| Ok(args[0].clone()));
+
+        let mut forward = TemplateContext::new();
+        forward.set("data", object_from(&[("a", 1), ("b", 2), ("c", 3), ("d", 4)]));
+        let mut backward = TemplateContext::new();
+        backward.set("data", object_from(&[("d", 4), ("c", 3), ("b", 2), ("a", 1)]));
+
+        let template = "{{echo(data)}}";
+        assert_eq!(
+            engine.render_string(template, &forward).unwrap(),
+            engine.render_string(template, &backward).unwrap()
+        );
+    }
+
+    #[cfg(feature = "lsp")]
+    #[test]
+    fn test_completion_list_is_sorted() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("user_zip", "1");
+        context.set_string("user_age", "2");
+        context.set_string("user_name", "3");
+
+        let completions = engine.get_completions_at_position("{{user_}}", 7, &context).unwrap();
+        let labels: Vec<&str> = completions.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels, vec!["user_age", "user_name", "user_zip"]);
+    }
+
+    #[cfg(feature = "template-macros")]
+    #[test]
+    fn test_overlapping_macro_names_expand_deterministically() {
+        let mut engine = TemplateEngine::new(".");
+        let context = TemplateContext::new();
+
+        let template = r#"{{macro card(t)}}C{{t}}{{/macro}}{{macro info_card(t)}}I{{t}}{{/macro}}{{info_card("x")}}{{card("y")}}"#;
+        for _ in 0..10 {
+            let result = engine.render_string(template, &context).unwrap();
+            assert_eq!(result, "IxCy");
+        }
+    }
+
+    #[cfg(feature = "template-macros")]
+    #[test]
+    fn test_macro_name_inside_helper_name_is_not_a_call() {
+        let mut engine = TemplateEngine::new(".");
+        engine.register_helper("format_price", |args| Ok(args[0].clone()));
+        let context = TemplateContext::new();
+
+        let template = r#"{{macro price(n)}}[{{format_price(n)}}]{{/macro}}price(s): {{price(3)}}"#;
+        assert_eq!(engine.render_string(template, &context).unwrap(), "price(s): [3]");
+    }
+}
+
+#[cfg(test)]
+mod check_syntax_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+
+    #[test]
+    fn test_valid_templates_pass() {
+        let engine = TemplateEngine::new(".");
+
+        assert!(engine.check_syntax("Hello {{name|upper|truncate:10}}").is_ok());
+        assert!(engine.check_syntax("{{for item in items}}{{if item.on}}{{item.name}}{{/if}}{{/for}}").is_ok());
+        assert!(engine.check_syntax("{{! comment }}{{#-- note --}}{{t \"greeting\"}}{{plural n \"a\" \"b\"}}").is_ok());
+    }
+
+    #[test]
+    fn test_errors_report_line_and_column() {
+        let engine = TemplateEngine::new(".");
+
+        match engine.check_syntax("<ul>\n  {{for item in items}}\n  <li>{{item}}</li>\n</ul>") {
+            Err(TemplateError::ParseWithLocation { message, line, column, .. }) => {
+                assert_eq!(message, "Unclosed '{{for}}': missing '{{/for}}'");
+                assert_eq!((line, column), (2, 3));
+            }
+            other => panic!("expected a located parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_malformed_directives_and_unknown_filters_fail() {
+        let engine = TemplateEngine::new(".");
+
+        for broken in [
+            "{{name",
+            "{{for item items}}{{/for}}",
+            "{{if ready}}{{/for}}",
+            "{{/if}}",
+            "{{macro card}}{{/macro}}",
+            "{{name|uppercase}}",
+            "{{name|}}",
+        ] {
+            assert!(engine.check_syntax(broken).is_err(), "{} should not pass", broken);
+        }
+    }
+
+    #[test]
+    fn test_registered_filters_are_known() {
+        let mut engine = TemplateEngine::new(".");
+        assert!(engine.check_syntax("{{price|money}}").is_err());
+
+        engine.register_filter("money", |value, _args| Ok(format!("{} EUR", value)));
+        assert!(engine.check_syntax("{{price|money}}").is_ok());
+    }
+}
+
+/// trim_blocks / lstrip_blocks and `{{- tag -}}` whitespace control
+#[cfg(test)]
+mod block_whitespace_tests {
+    use super::*;
+
+    /// The nested layout fixture of the v0.2.0 inheritance tests
+    #[cfg(feature = "inheritance")]
+    fn layout_dir() -> PathBuf {
+        let dir = create_temp_dir();
+        fs::write(dir.join("base.html"), "<html>\n<head>{{block head}}{{/block}}</head>\n<body>\n    {{block body}}{{/block}}\n</body>\n</html>\n").unwrap();
+        fs::write(dir.join("admin.html"), concat!(
+            "{{extends \"base.html\"}}\n",
+            "{{block head}}\n<title>Admin - {{block title}}{{/block}}</title>\n{{/block}}\n",
+            "{{block body}}\n<div class=\"admin-layout\">\n    <main>{{block admin_content}}{{/block}}</main>\n</div>\n{{/block}}\n",
+        )).unwrap();
+        fs::write(dir.join("admin_users.html"), concat!(
+            "{{extends \"admin.html\"}}\n",
+            "{{block title}}Users{{/block}}\n",
+            "{{block admin_content}}\n<ul>\n    {{for user in users}}\n    <li>{{user}}</li>\n    {{/for}}\n</ul>\n{{/block}}\n",
+        )).unwrap();
+        dir
+    }
+
+    fn users() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set("users", TemplateValue::Array(vec![
+            TemplateValue::String("Alice".to_string()),
+            TemplateValue::String("Bob".to_string()),
+        ]));
+        context
+    }
+
+    #[test]
+    fn test_defaults_keep_whitespace() {
+        let engine = TemplateEngine::new(".");
+        assert!(!engine.is_trim_blocks_enabled());
+        assert!(!engine.is_lstrip_blocks_enabled());
+
+        let mut engine = TemplateEngine::new(".");
+        let result = engine.render_string("<ul>\n  {{for user in users}}\n  <li>{{user}}</li>\n  {{/for}}\n</ul>", &users()).unwrap();
+        assert_eq!(result, "<ul>\n  \n  <li>Alice</li>\n  \n  <li>Bob</li>\n  \n</ul>");
+    }
+
+    #[test]
+    fn test_trim_and_lstrip_blocks_in_loops_and_conditionals() {
+        let mut engine = TemplateEngine::new(".");
+        engine.set_trim_blocks(true);
+        let template = "<ul>\n  {{for user in users}}\n  <li>{{user}}</li>\n  {{/for}}\n</ul>";
+        assert_eq!(engine.render_string(template, &users()).unwrap(), "<ul>\n    <li>Alice</li>\n    <li>Bob</li>\n  </ul>");
+
+        engine.set_lstrip_blocks(true);
+        assert_eq!(engine.render_string(template, &users()).unwrap(), "<ul>\n  <li>Alice</li>\n  <li>Bob</li>\n</ul>");
+
+        let mut context = users();
+        context.set_bool("admin", false);
+        let template = "a\n  {{! hidden }}\n  {{if admin}}\n  secret\n  {{/if}}\nb {{if admin}}x{{/if}}\nc";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "a\nb c");
+    }
+
+    #[test]
+    fn test_variable_tags_are_left_alone() {
+        let mut engine = TemplateEngine::new(".");
+        engine.set_trim_blocks(true);
+        engine.set_lstrip_blocks(true);
+        let mut context = TemplateContext::new();
+        context.set_string("name", "Ada");
+        assert_eq!(engine.render_string("  {{name}}\nnext", &context).unwrap(), "  Ada\nnext");
+        assert_eq!(engine.render_string("text {{if name}}\nyes{{/if}}", &context).unwrap(), "text yes");
+    }
+
+    #[test]
+    fn test_markers_trim_around_any_tag() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = users();
+        context.set_string("name", "Ada");
+        context.set_bool("admin", false);
+        let render = |engine: &mut TemplateEngine, template: &str| engine.render_string(template, &context).unwrap();
+
+        assert_eq!(render(&mut engine, "a \n {{- name -}} \n  b"), "aAda  b");
+        // `-}}` stops after one newline, `{{-` takes every whitespace
+        assert_eq!(render(&mut engine, "{{name -}}\n\nx\n \n{{- name}}"), "Ada\nxAda");
+        assert_eq!(render(&mut engine, "a\n  {{- ! note -}}\nb"), "ab");
+        assert_eq!(render(&mut engine, "a\n{{-#-- note ---}}\nb"), "a<!-- note -->b");
+        assert_eq!(render(&mut engine, "{{if admin -}}\nyes\n{{- elseif name -}}\n{{name}}\n{{- else -}}\nno\n{{- /if}}!"), "Ada!");
+        // A dash elsewhere in the tag is not a marker
+        assert_eq!(render(&mut engine, "{{name|truncate:2}} - {{ name }} -"), "Ad... - Ada -");
+    }
+
+    #[test]
+    fn test_markers_in_nested_loops() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        let row = |cells: &[&str]| TemplateValue::Array(cells.iter().map(|cell| TemplateValue::String(cell.to_string())).collect());
+        context.set("rows", TemplateValue::Array(vec![row(&["a", "b"]), row(&["c"])]));
+        let template = "<table>\n  {{- for row in rows}}\n  <tr>\n    {{- for cell in row}}\n    <td>{{cell}}</td>\n    {{- /for}}\n  </tr>\n  {{- /for}}\n</table>";
+        assert_eq!(
+            engine.render_string(template, &context).unwrap(),
+            "<table>\n  <tr>\n    <td>a</td>\n    <td>b</td>\n  </tr>\n  <tr>\n    <td>c</td>\n  </tr>\n</table>"
+        );
+        assert!(engine.check_syntax(template).is_ok());
+        let parsed = mystical_runic::ast::parse(template).unwrap();
+        assert!(matches!(parsed.nodes[1], mystical_runic::ast::Node::For(_)), "{:?}", parsed.nodes);
+
+        // Markers win over trim_blocks, which keeps applying to other block tags
+        engine.set_trim_blocks(true);
+        engine.set_lstrip_blocks(true);
+        let template = "<ul>\n  {{for row in rows -}}\n  {{- for cell in row}}\n  <li>{{cell}}</li>\n  {{/for}}\n  {{/for}}\n</ul>";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "<ul>\n  <li>a</li>\n  <li>b</li>\n  <li>c</li>\n</ul>");
+    }
+
+    #[test]
+    fn test_markers_in_loaded_and_compiled_templates() {
+        let dir = create_temp_dir();
+        fs::write(dir.join("list.html"), "<ul>\n  {{- for user in users}}\n  <li>{{user}}</li>\n  {{- /for}}\n</ul>\n{{- if users -}}\n!\n{{- /if}}").unwrap();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        let expected = "<ul>\n  <li>Alice</li>\n  <li>Bob</li>\n</ul>!";
+        assert_eq!(engine.render("list.html", &users()).unwrap(), expected);
+
+        fs::write(dir.join("flat.html"), "{{- if admin -}}\n  Admin\n{{- else -}}\n  Guest\n{{- /if -}}\n!").unwrap();
+        let compiled = engine.compile_to_bytecode("flat.html").unwrap();
+        assert_eq!(engine.render_compiled(&compiled, &users()).unwrap(), "  Guest!");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "template-macros")]
+    #[test]
+    fn test_macros_lose_their_formatting_newlines() {
+        let mut engine = TemplateEngine::new(".");
+        engine.set_trim_blocks(true);
+        engine.set_lstrip_blocks(true);
+        let template = "{{macro badge(label)}}\n<span>{{label}}</span>\n{{/macro}}\n<p>{{badge(\"new\")}}</p>";
+        assert_eq!(engine.render_string(template, &TemplateContext::new()).unwrap(), "<p><span>new</span></p>");
+    }
+
+    #[cfg(feature = "inheritance")]
+    #[test]
+    fn test_nested_layouts_render_cleanly() {
+        let dir = layout_dir();
+        let mut engine = TemplateEngine::builder()
+            .template_dir(dir.to_str().unwrap())
+            .trim_blocks(true)
+            .lstrip_blocks(true)
+            .build();
+        let result = engine.render("admin_users.html", &users()).unwrap();
+        assert_eq!(result, concat!(
+            "<html>\n<head><title>Admin - Users</title></head>\n<body>\n",
+            "<div class=\"admin-layout\">\n    <main><ul>\n    <li>Alice</li>\n    <li>Bob</li>\n</ul></main>\n</div></body>\n</html>\n",
+        ));
+    }
+
+    #[cfg(feature = "inheritance")]
+    #[test]
+    fn test_toggling_reloads_cached_sources() {
+        let dir = layout_dir();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        let untrimmed = engine.render("admin_users.html", &users()).unwrap();
+
+        engine.set_trim_blocks(true);
+        engine.set_lstrip_blocks(true);
+        let trimmed = engine.render("admin_users.html", &users()).unwrap();
+        assert!(trimmed.len() < untrimmed.len());
+        assert!(trimmed.contains("<ul>\n    <li>Alice</li>"));
+
+        let compiled = engine.compile_to_bytecode("admin_users.html").unwrap();
+        let bytecode = engine.render_compiled(&compiled, &users()).unwrap();
+        assert!(!bytecode.starts_with('\n'), "{:?}", bytecode);
+
+        engine.set_trim_blocks(false);
+        engine.set_lstrip_blocks(false);
+        assert_eq!(engine.render("admin_users.html", &users()).unwrap(), untrimmed);
+    }
+}
+
+/// Macro calls checked against their definition, header defaults
+#[cfg(feature = "template-macros")]
+#[cfg(test)]
+mod macro_signature_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+
+    const BUTTON: &str = "{{macro button(text, color=\"blue\")}}<button class=\"{{color}}\">{{text}}</button>{{/macro}}";
+
+    #[test]
+    fn test_header_defaults_are_applied() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("theme", "green");
+        let template = format!("{}{{{{macro badge(label, size=2, tone=theme)}}}}{{{{label}}}}-{{{{size}}}}-{{{{tone}}}}{{{{/macro}}}}", BUTTON);
+
+        let result = engine.render_string(&format!("{}{{{{button(\"Save\")}}}}", template), &context).unwrap();
+        assert_eq!(result, "<button class=\"blue\">Save</button>");
+
+        let result = engine.render_string(&format!("{}{{{{button(\"Save\", color=\"red\")}}}}", template), &context).unwrap();
+        assert_eq!(result, "<button class=\"red\">Save</button>");
+
+        let result = engine.render_string(&format!("{}{{{{badge(\"new\")}}}}", template), &context).unwrap();
+        assert_eq!(result, "new-2-green");
+    }
+
+    #[test]
+    fn test_unknown_named_parameter_is_rejected_in_strict_mode() {
+        let mut engine = TemplateEngine::new(".");
+        engine.enable_strict_mode();
+        let template = format!("{}\n<p>{{{{button(\"Save\", colour=\"red\")}}}}</p>", BUTTON);
+        match engine.render_string(&template, &TemplateContext::new()) {
+            Err(TemplateError::ParseWithLocation { message, line, column, .. }) => {
+                assert_eq!(message, "Macro 'button' has no parameter 'colour'; expected button(text, color=\"blue\")");
+                assert_eq!((line, column), (2, 4));
+            }
+            other => panic!("expected a located error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_arity_errors_in_strict_mode() {
+        let mut engine = TemplateEngine::new(".");
+        engine.enable_strict_mode();
+
+        let error = engine.render_string(&format!("{}{{{{button(\"Save\", \"red\", \"big\")}}}}", BUTTON), &TemplateContext::new()).unwrap_err();
+        assert!(error.to_string().contains("Macro 'button' takes 2 arguments but got extra argument \"big\""), "{}", error);
+
+        let error = engine.render_string(&format!("{}{{{{button(color=\"red\")}}}}", BUTTON), &TemplateContext::new()).unwrap_err();
+        assert!(error.to_string().contains("Macro 'button' is missing required argument 'text'"), "{}", error);
+
+        // Quoted text containing '=' is a positional argument
+        let result = engine.render_string(&format!("{}{{{{button(\"a=b\")}}}}", BUTTON), &TemplateContext::new()).unwrap();
+        assert_eq!(result, "<button class=\"blue\">a=b</button>");
+    }
+
+    #[cfg(feature = "lsp")]
+    #[test]
+    fn test_lenient_mode_warns_and_renders() {
+        let mut engine = TemplateEngine::new(".");
+        let template = format!("{}{{{{button(\"Save\", colour=\"red\")}}}}", BUTTON);
+        let result = engine.render_string(&template, &TemplateContext::new()).unwrap();
+        assert_eq!(result, "<button class=\"blue\">Save</button>");
+
+        let diagnostics = engine.get_diagnostics_for_editor(&template, &TemplateContext::new()).unwrap();
+        let warning = diagnostics.iter().find(|d| d.code.as_deref() == Some("macro-call")).expect("macro-call diagnostic");
+        assert_eq!(warning.severity, "warning");
+        assert!(warning.message.contains("colour"));
+
+        engine.enable_strict_mode();
+        let diagnostics = engine.get_diagnostics_for_editor(&template, &TemplateContext::new()).unwrap();
+        assert!(diagnostics.iter().any(|d| d.code.as_deref() == Some("macro-call") && d.severity == "error"));
+    }
+}
+
+#[cfg(test)]
+mod ast_tests {
+    use super::*;
+    use mystical_runic::ast::{self, Node, Variable, Visitor};
+    use mystical_runic::TemplateError;
+
+    #[derive(Default)]
+    struct VariableCounter(HashMap<String, usize>);
+
+    impl Visitor for VariableCounter {
+        fn visit_variable(&mut self, variable: &Variable) {
+            *self.0.entry(variable.path.clone()).or_default() += 1;
+        }
+    }
+
+    #[test]
+    fn test_visitor_counts_variables_per_template() {
+        let temp_dir = create_temp_dir();
+        fs::write(temp_dir.join("page.html"), "<h1>{{title|upper}}</h1>\n{{if user}}Hi {{user.name}}{{/if}}\n{{for item in items}}{{item}} of {{title}}{{/for}}").unwrap();
+        fs::write(temp_dir.join("nav.html"), "{{macro link(url, label=\"Home\")}}<a href=\"{{url}}\">{{label}}</a>{{/macro}}{{! no {{vars}} here }}").unwrap();
+
+        let mut engine = TemplateEngine::new(temp_dir.to_str().unwrap());
+        let mut counts = HashMap::new();
+        for name in ["page.html", "nav.html"] {
+            let template = engine.parse(name).unwrap();
+            assert_eq!(template.name.as_deref(), Some(name));
+            let mut counter = VariableCounter::default();
+            ast::walk(&template, &mut counter);
+            counts.insert(name, counter.0);
+        }
+
+        assert_eq!(counts["page.html"].get("title"), Some(&2));
+        assert_eq!(counts["page.html"].get("user.name"), Some(&1));
+        assert_eq!(counts["page.html"].get("item"), Some(&1));
+        assert_eq!(counts["page.html"].len(), 3);
+        assert_eq!(counts["nav.html"].get("url"), Some(&1));
+        assert_eq!(counts["nav.html"].get("label"), Some(&1));
+        assert_eq!(counts["nav.html"].len(), 2);
+    }
+
+    #[test]
+    fn test_rewrite_raw_output_to_safe_filter_by_splicing() {
+        let mut engine = TemplateEngine::new(".");
+        let source = "<div>{{& body}}</div>\n{{for post in posts}}{{&post.html}}{{/for}}";
+        let template = engine.parse(source).unwrap();
+
+        #[derive(Default)]
+        struct RawOutputs(Vec<(ast::Span, String)>);
+        impl Visitor for RawOutputs {
+            fn visit_variable(&mut self, variable: &Variable) {
+                if variable.raw {
+                    self.0.push((variable.span, format!("{{{{{}|safe}}}}", variable.path)));
+                }
+            }
+        }
+
+        let mut raw = RawOutputs::default();
+        ast::walk(&template, &mut raw);
+        assert_eq!(raw.0[1].0.line, 2);
+        assert_eq!(raw.0[1].0.slice(source), "{{&post.html}}");
+
+        let rewritten = template.splice(raw.0).unwrap();
+        assert_eq!(rewritten, "<div>{{body|safe}}</div>\n{{for post in posts}}{{post.html|safe}}{{/for}}");
+
+        let reparsed = engine.parse(&rewritten).unwrap();
+        assert!(matches!(&reparsed.nodes[1], Node::Variable(variable)
+            if !variable.raw && variable.path == "body" && variable.filters[0].name == "safe"));
+    }
+
+    #[test]
+    fn test_parse_nodes_and_spans() {
+        let source = "{{t_scope \"nav\"}}{{t \"home\" name=user.name}}{{/t_scope}}{{include \"footer.html\" from \"shared\"}}{{link(\"/\", label=\"Home\")}}{{plural n \"item\" \"items\"}}";
+        let template = ast::parse(source).unwrap();
+        assert_eq!(template.nodes.len(), 4);
+        match &template.nodes[0] {
+            Node::TranslationScope(scope) => {
+                assert_eq!(scope.prefix, "nav");
+                assert!(matches!(&scope.body[0], Node::Translation(t) if t.key == "home" && t.arguments == [("name".to_string(), "user.name".to_string())]));
+                assert_eq!(scope.span.slice(source), "{{t_scope \"nav\"}}{{t \"home\" name=user.name}}{{/t_scope}}");
+            }
+            other => panic!("unexpected node {:?}", other),
+        }
+        assert!(matches!(&template.nodes[1], Node::Include(include) if include.target == "footer.html" && include.from.as_deref() == Some("shared") && !include.dynamic));
+        assert!(matches!(&template.nodes[2], Node::MacroCall(call) if call.name == "link" && call.arguments.len() == 2 && call.arguments[1].name.as_deref() == Some("label")));
+        assert!(matches!(&template.nodes[3], Node::Plural(plural) if plural.count == "n" && plural.plural == "items"));
+    }
+
+    #[test]
+    fn test_parse_reports_unbalanced_blocks_with_location() {
+        let error = ast::parse("line one\n{{if a}}{{for x in xs}}{{/if}}").unwrap_err();
+        match error {
+            TemplateError::ParseWithLocation { message, line, column, .. } => {
+                assert!(message.contains("{{/if}}"), "{}", message);
+                assert_eq!((line, column), (2, 24));
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+        assert!(matches!(ast::parse("{{if a}}open"), Err(TemplateError::ParseWithLocation { line: 1, column: 1, .. })));
+        assert!(matches!(ast::parse("text {{/for}}"), Err(TemplateError::ParseWithLocation { .. })));
+    }
+}
+
+/// Template directives whose cargo feature is disabled
+#[cfg(test)]
+mod feature_flag_tests {
+    use super::*;
+
+    #[cfg(not(feature = "inheritance"))]
+    #[test]
+    fn test_layouts_need_the_inheritance_feature() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("base.html"), "<main>{{block body}}{{/block}}</main>").unwrap();
+        fs::write(templates_path.join("page.html"), "{{extends \"base.html\"}}{{block body}}Hi{{/block}}").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let error = engine.render("page.html", &TemplateContext::new()).unwrap_err();
+        assert!(error.to_string().contains("`inheritance` feature"), "{}", error);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[cfg(not(feature = "template-macros"))]
+    #[test]
+    fn test_macros_need_the_template_macros_feature() {
+        let mut engine = TemplateEngine::new(".");
+        let template = "{{macro badge(text)}}<b>{{text}}</b>{{/macro}}{{badge(\"new\")}}";
+        let error = engine.render_string(template, &TemplateContext::new()).unwrap_err();
+        assert!(error.to_string().contains("`template-macros` feature"), "{}", error);
+    }
+
+    #[cfg(not(feature = "i18n"))]
+    #[test]
+    fn test_translations_need_the_i18n_feature() {
+        let mut engine = TemplateEngine::new(".");
+        let error = engine.render_string("{{t \"home\"}}", &TemplateContext::new()).unwrap_err();
+        assert!(error.to_string().contains("`i18n` feature"), "{}", error);
+
+        let error = engine.render_string("{{t_scope \"nav\"}}x{{/t_scope}}", &TemplateContext::new()).unwrap_err();
+        assert!(error.to_string().contains("`i18n` feature"), "{}", error);
+    }
+
+    #[test]
+    fn test_core_directives_work_in_every_feature_set() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("name", "ada");
+        context.set("items", TemplateValue::Array(vec![TemplateValue::Number(1), TemplateValue::Number(2)]));
+        let result = engine.render_string(
+            "{{name|upper}}{{if name}}!{{/if}}{{for item in items}} {{item}}{{/for}}",
+            &context,
+        ).unwrap();
+        assert_eq!(result, "ADA! 1 2");
+    }
+}
+
+/// {{try}}...{{rescue}}...{{/try}} error boundaries
+#[cfg(test)]
+mod error_boundary_tests {
+    use super::*;
+    use mystical_runic::{ast, TemplateError};
+
+    fn engine_with_failing_helper(templates_path: &std::path::Path) -> TemplateEngine {
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.register_helper("recommendations", |args| match args.first() {
+            Some(TemplateValue::Number(7)) => Err(TemplateError::Runtime("recommendation service timed out".to_string())),
+            _ => Ok(TemplateValue::String("Books".to_string())),
+        });
+        engine
+    }
+
+    fn user(id: i64) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set("user", TemplateValue::Object(HashMap::from([("id".to_string(), TemplateValue::Number(id))])));
+        context
+    }
+
+    #[test]
+    fn test_failing_helper_renders_rescue_body() {
+        let templates_path = create_temp_dir();
+        fs::write(
+            templates_path.join("page.html"),
+            "<h1>Home</h1>{{try}}<p>{{recommendations(user.id)}}</p>\
+             {{rescue}}<p>Recommendations unavailable ({{error.kind}}: {{error.message}})</p>{{/try}}<footer/>",
+        ).unwrap();
+        let mut engine = engine_with_failing_helper(&templates_path);
+
+        assert_eq!(engine.render("page.html", &user(1)).unwrap(), "<h1>Home</h1><p>Books</p><footer/>");
+
+        let (output, rescued) = engine.render_with_rescued_errors("page.html", &user(7)).unwrap();
+        assert_eq!(
+            output,
+            "<h1>Home</h1><p>Recommendations unavailable (runtime: Runtime error: recommendation service timed out)</p><footer/>"
+        );
+        assert_eq!(rescued.len(), 1);
+        assert!(matches!(&rescued[0], TemplateError::Runtime(message) if message == "recommendation service timed out"));
+
+        // Without a rescue body the failing block renders nothing
+        let result = engine.render_string("a{{try}}{{recommendations(user.id)}}{{/try}}b", &user(7)).unwrap();
+        assert_eq!(result, "ab");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_missing_include_is_rescued() {
+        let templates_path = create_temp_dir();
+        fs::write(
+            templates_path.join("page.html"),
+            "{{try}}{{include \"widgets/weather.html\"}}{{rescue}}[{{error.kind}}]{{/try}} ok",
+        ).unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let (output, rescued) = engine.render_with_rescued_errors("page.html", &TemplateContext::new()).unwrap();
+        assert_eq!(output, "[template] ok");
+        assert!(rescued[0].to_string().contains("widgets/weather.html"), "{}", rescued[0]);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_security_errors_are_not_rescued() {
+        let templates_path = create_temp_dir();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let template = "{{try}}{{include \"../../etc/passwd\"}}{{rescue}}fallback{{/try}}";
+        let error = engine.render_string(template, &TemplateContext::new()).unwrap_err();
+        assert!(matches!(error, mystical_runic::TemplateError::Security(_)), "{:?}", error);
+
+        // Dynamic includes outside the allow-list can't be probed either
+        let mut context = TemplateContext::new();
+        context.set_string("widget", "admin/secrets.html");
+        let template = "{{try}}{{try}}{{include widget}}{{rescue}}inner{{/try}}{{rescue}}outer{{/try}}";
+        let error = engine.render_string(template, &context).unwrap_err();
+        assert!(matches!(error, mystical_runic::TemplateError::Security(_)), "{:?}", error);
+
+        fs::write(templates_path.join("page.html"), "{{try}}{{include \"../../etc/passwd\"}}{{/try}}").unwrap();
+        let result = engine.render_with_rescued_errors("page.html", &TemplateContext::new());
+        assert!(matches!(result, Err(TemplateError::Security(_))), "{:?}", result);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_boundaries_nest() {
+        let templates_path = create_temp_dir();
+        let mut engine = engine_with_failing_helper(&templates_path);
+
+        // The inner boundary rescues its own error
+        let template = "{{try}}<{{try}}{{recommendations(user.id)}}{{rescue}}inner{{/try}}>{{rescue}}outer{{/try}}";
+        assert_eq!(engine.render_string(template, &user(7)).unwrap(), "<inner>");
+
+        // An error in a rescue body goes to the enclosing boundary
+        let template = "{{try}}<{{try}}{{recommendations(user.id)}}{{rescue}}{{include \"gone.html\"}}{{/try}}>\
+                        {{rescue}}outer: {{error.kind}}{{/try}}";
+        let result = engine.render_string(template, &user(7)).unwrap();
+        assert!(result.starts_with("outer: "), "{}", result);
+        assert!(!result.contains('<'), "{}", result);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_strict_mode_errors_and_loop_variables() {
+        let mut engine = TemplateEngine::new(".");
+        engine.enable_strict_mode();
+        let mut context = TemplateContext::new();
+        context.set("quantities", TemplateValue::Array(vec![
+            TemplateValue::String("3".to_string()),
+            TemplateValue::String("three".to_string()),
+            TemplateValue::String("7".to_string()),
+        ]));
+
+        let template = "{{for qty in quantities}}{{try}}[{{qty|int}}]{{rescue}}[{{error.kind}}]{{/try}}{{/for}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "[3][filter][7]");
+
+        // Strict mode keeps failing outside a boundary
+        assert!(matches!(
+            engine.render_string("{{for qty in quantities}}{{qty|int}}{{/for}}", &context),
+            Err(TemplateError::FilterError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_block_tags_and_parsing() {
+        let mut engine = TemplateEngine::new(".");
+        engine.set_trim_blocks(true);
+        let template = "{{try}}\nok\n{{rescue}}\nfailed\n{{/try}}\n";
+        assert_eq!(engine.render_string(template, &TemplateContext::new()).unwrap(), "ok\n");
+
+        let tree = ast::parse("{{try}}{{name}}{{rescue}}{{error.message}}{{/try}}").unwrap();
+        match &tree.nodes[0] {
+            ast::Node::Try(node) => {
+                assert!(matches!(&node.body[0], ast::Node::Variable(variable) if variable.path == "name"));
+                let rescue = node.rescue.as_ref().expect("rescue body");
+                assert!(matches!(&rescue[0], ast::Node::Variable(variable) if variable.path == "error.message"));
+            }
+            other => panic!("expected a try node, got {:?}", other),
+        }
+
+        assert!(engine.check_syntax("{{try}}{{name}}{{rescue}}x{{/try}}").is_ok());
+        assert!(engine.check_syntax("{{rescue}}").is_err());
+        assert!(engine.check_syntax("{{try}}{{name}}").is_err());
+        assert!(ast::parse("{{try}}a{{rescue}}b{{rescue}}c{{/try}}").is_err());
+        assert!(engine.render_string("{{try}}unclosed", &TemplateContext::new()).is_err());
+    }
+
+    #[test]
+    fn test_output_is_not_rendered_as_tags() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("secret.html"), "hunter2").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        let mut context = TemplateContext::new();
+        context.set_string("secret", "hunter2");
+        context.set("items", TemplateValue::Array(vec![TemplateValue::Number(1)]));
+        context.set_string("tag", "{{secret}}");
+        context.set_string("inc", "{{include \"secret.html\"}}");
+        context.set_string("lp", "{{for i in items}}{{i}}{{/for}}");
+
+        for (template, expected) in [
+            ("{{try}}{{& tag}}{{rescue}}E{{/try}}", "{{secret}}"),
+            ("{{try}}{{& inc}}{{/try}}", "{{include \"secret.html\"}}"),
+            ("{{try}}{{& lp}}{{/try}}", "{{for i in items}}{{i}}{{/for}}"),
+            ("{{try}}{{inc}}{{/try}}", "{{include &quot;secret.html&quot;}}"),
+            ("{{for i in items}}{{try}}{{& tag}}{{/try}}{{/for}}", "{{secret}}"),
+            ("{{try}}{{try}}{{& tag}}{{/try}}{{/try}}", "{{secret}}"),
+        ] {
+            assert_eq!(engine.render_string(template, &context).unwrap(), expected, "{}", template);
+        }
+
+        // Values can't copy the output of another block either
+        context.set_string("part", "\u{E000}~0\u{E001}");
+        assert_eq!(
+            engine.render_string("{{try}}hidden{{/try}}{{& part}}|{{try}}{{& part}}{{/try}}", &context).unwrap(),
+            "hidden\u{E000}~0\u{E001}|\u{E000}~0\u{E001}"
+        );
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// {{input}}, {{select}}, {{checkbox}} and {{field_errors}} form helpers
+#[cfg(test)]
+mod form_helper_tests {
+    use super::*;
+
+    fn object(fields: &[(&str, TemplateValue)]) -> TemplateValue {
+        TemplateValue::Object(fields.iter().map(|(key, value)| (key.to_string(), value.clone())).collect())
+    }
+
+    fn string(text: &str) -> TemplateValue {
+        TemplateValue::String(text.to_string())
+    }
+
+    fn form_context() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set("form", object(&[
+            ("email", string("\"o'neil\"@example.com")),
+            ("country", TemplateValue::Number(2)),
+            ("subscribe", TemplateValue::Bool(true)),
+            ("errors", object(&[
+                ("email", TemplateValue::Array(vec![string("Invalid <email>"), string("Already taken")])),
+                ("name", TemplateValue::Array(vec![])),
+            ])),
+        ]));
+        context.set("countries", TemplateValue::Array(vec![
+            object(&[("value", string("1")), ("label", string("France"))]),
+            object(&[("value", string("2")), ("label", string("Côte d'Ivoire"))]),
+        ]));
+        context.set("sizes", TemplateValue::Array(vec![string("S"), string("M")]));
+        context
+    }
+
+    #[test]
+    fn test_prefilled_values_are_escaped() {
+        let mut engine = TemplateEngine::new(".");
+        let result = engine.render_string(
+            "{{input \"email\" value=form.email type=\"email\" class=\"field\" required=true disabled=false}}",
+            &form_context(),
+        ).unwrap();
+        assert_eq!(
+            result,
+            "<input type=\"email\" name=\"email\" value=\"&quot;o&#x27;neil&quot;@example.com\" class=\"field\" required>"
+        );
+
+        assert_eq!(
+            engine.render_string("{{input \"nickname\" value=form.nickname}}", &form_context()).unwrap(),
+            "<input type=\"text\" name=\"nickname\" value=\"\">"
+        );
+        assert_eq!(
+            engine.render_string("{{checkbox \"subscribe\" checked=form.subscribe}}{{checkbox \"terms\" checked=form.terms}}", &form_context()).unwrap(),
+            "<input type=\"checkbox\" name=\"subscribe\" checked><input type=\"checkbox\" name=\"terms\">"
+        );
+    }
+
+    #[test]
+    fn test_selected_option_matches_by_text() {
+        let mut engine = TemplateEngine::new(".");
+        // A number selects the string value "2"
+        let result = engine.render_string("{{select \"country\" options=countries selected=form.country}}", &form_context()).unwrap();
+        assert_eq!(
+            result,
+            "<select name=\"country\"><option value=\"1\">France</option>\
+             <option value=\"2\" selected>Côte d&#x27;Ivoire</option></select>"
+        );
+
+        // Scalar options are their own labels
+        let result = engine.render_string("{{select \"size\" options=sizes selected=\"M\" class=\"small\"}}", &form_context()).unwrap();
+        assert_eq!(
+            result,
+            "<select name=\"size\" class=\"small\"><option value=\"S\">S</option><option value=\"M\" selected>M</option></select>"
+        );
+    }
+
+    #[test]
+    fn test_field_errors_render_only_when_present() {
+        let mut engine = TemplateEngine::new(".");
+        assert_eq!(
+            engine.render_string("{{field_errors \"email\" errors=form.errors}}", &form_context()).unwrap(),
+            "<ul class=\"field-errors\"><li>Invalid &lt;email&gt;</li><li>Already taken</li></ul>"
+        );
+        assert_eq!(engine.render_string("{{field_errors \"name\" errors=form.errors}}", &form_context()).unwrap(), "");
+        assert_eq!(engine.render_string("{{field_errors \"phone\" errors=form.errors}}", &form_context()).unwrap(), "");
+        assert_eq!(engine.render_string("{{field_errors \"email\" errors=missing}}", &form_context()).unwrap(), "");
+
+        assert!(engine.render_string("{{input email}}", &form_context()).is_err());
+        assert!(engine.render_string("{{input \"email\" value}}", &form_context()).is_err());
+        assert!(engine.check_syntax("{{input \"email\" value=form.email}}{{field_errors \"email\" errors=form.errors}}").is_ok());
+        assert!(engine.check_syntax("{{select country}}").is_err());
+    }
+
+    #[test]
+    fn test_registered_components_override_the_markup() {
+        let templates_path = create_temp_dir();
+        fs::write(
+            templates_path.join("input.html"),
+            "<label>{{name}} <input name=\"{{name}}\" value=\"{{value}}\"></label>",
+        ).unwrap();
+        fs::write(
+            templates_path.join("select.html"),
+            "<div class=\"select\">{{for option in options}}<a data-selected=\"{{option.selected}}\">{{option.label}}</a>{{/for}}</div>",
+        ).unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.register_component("input", "input.html");
+        engine.register_component("select", "select.html");
+
+        let template = "<form>{{input \"email\" value=form.email}}{{select \"country\" options=countries selected=form.country}}</form>";
+        assert_eq!(
+            engine.render_string(template, &form_context()).unwrap(),
+            "<form><label>email <input name=\"email\" value=\"&quot;o&#x27;neil&quot;@example.com\"></label>\
+             <div class=\"select\"><a data-selected=\"false\">France</a><a data-selected=\"true\">Côte d&#x27;Ivoire</a></div></form>"
+        );
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// `duration`, `filesize` and `percent` dashboard filters
+#[cfg(test)]
+mod dashboard_filter_tests {
+    use super::*;
+
+    fn render_filter(engine: &mut TemplateEngine, filter: &str, value: TemplateValue) -> String {
+        let mut context = TemplateContext::new();
+        context.set("value", value);
+        engine.render_string(&format!("{{{{value|{}}}}}", filter), &context).unwrap()
+    }
+
+    #[test]
+    fn test_boundary_values() {
+        let mut engine = TemplateEngine::new("templates");
+        let cases: [(&str, TemplateValue, &str); 26] = [
+            ("duration", TemplateValue::Number(0), "0s"),
+            ("duration", TemplateValue::Number(59), "59s"),
+            ("duration", TemplateValue::Number(60), "1m"),
+            ("duration", TemplateValue::Number(3_599), "59m 59s"),
+            ("duration", TemplateValue::Number(3_600), "1h"),
+            ("duration", TemplateValue::Number(86_399), "23h 59m 59s"),
+            ("duration", TemplateValue::Number(274_320), "3d 4h 12m"),
+            ("duration", TemplateValue::Number(86_405), "1d"),
+            ("duration:2", TemplateValue::Number(274_325), "3d 4h"),
+            ("duration:4", TemplateValue::Number(274_325), "3d 4h 12m 5s"),
+            ("duration", TemplateValue::Number(-90), "-1m 30s"),
+            ("duration", TemplateValue::Float(59.9), "59s"),
+            ("duration", TemplateValue::Number(i64::MIN), "-106751991167300d 15h 30m"),
+            ("duration", TemplateValue::Float(1e300), "3938453320844195178974243141571391d 8h 4m"),
+            ("filesize", TemplateValue::Number(0), "0 B"),
+            ("filesize", TemplateValue::Number(1_023), "1023 B"),
+            ("filesize", TemplateValue::Number(1_024), "1.0 KiB"),
+            ("filesize", TemplateValue::Number(1_048_575), "1.0 MiB"),
+            ("filesize", TemplateValue::Number(1_503_238_553), "1.4 GiB"),
+            ("filesize:2", TemplateValue::Number(1_536), "1.50 KiB"),
+            ("filesize", TemplateValue::Number(-2_048), "-2.0 KiB"),
+            ("filesize", TemplateValue::Number(i64::MAX), "8.0 EiB"),
+            ("percent:1", TemplateValue::Float(0.875), "87.5%"),
+            ("percent", TemplateValue::Float(0.875), "88%"),
+            ("percent", TemplateValue::Number(0), "0%"),
+            ("percent:2", TemplateValue::Float(-0.0125), "-1.25%"),
+        ];
+        for (filter, value, expected) in cases {
+            assert_eq!(render_filter(&mut engine, filter, value.clone()), expected, "{:?}|{}", value, filter);
+        }
+    }
+
+    #[test]
+    fn test_precision_from_a_variable_and_numeric_strings() {
+        let mut engine = TemplateEngine::new("templates");
+        let mut context = TemplateContext::new();
+        context.set_string("bytes", "1536");
+        context.set_number("decimals", 3);
+        assert_eq!(engine.render_string("{{bytes|filesize:decimals}}", &context).unwrap(), "1.500 KiB");
+    }
+
+    #[test]
+    fn test_non_numbers_are_rejected_in_strict_mode() {
+        let mut engine = TemplateEngine::new("templates");
+        assert_eq!(render_filter(&mut engine, "duration", TemplateValue::String("soon".to_string())), "");
+
+        engine.enable_strict_mode();
+        let mut context = TemplateContext::new();
+        context.set_string("value", "soon");
+        let error = engine.render_string("{{value|filesize}}", &context).unwrap_err();
+        assert!(error.to_string().contains("expected a number"), "{}", error);
+        let error = engine.render_string("{{value|percent:1:2}}", &context).unwrap_err();
+        assert!(error.to_string().contains("usage: percent[:decimals]"), "{}", error);
+    }
+
+    #[cfg(feature = "i18n")]
+    #[test]
+    fn test_unit_labels_come_from_the_translation_table() {
+        let mut engine = TemplateEngine::new("templates");
+        let labels = [
+            ("units.days_short", " j"), ("units.hours_short", " h"), ("units.minutes_short", " min"),
+            ("units.kib_short", "Kio"), ("units.percent_short", " %"),
+        ];
+        engine.set_translations("fr", labels.iter().map(|(key, label)| (key.to_string(), label.to_string())).collect());
+        engine.set_locale("fr");
+
+        assert_eq!(render_filter(&mut engine, "duration", TemplateValue::Number(274_320)), "3 j 4 h 12 min");
+        assert_eq!(render_filter(&mut engine, "filesize", TemplateValue::Number(1_024)), "1.0 Kio");
+        assert_eq!(render_filter(&mut engine, "percent:1", TemplateValue::Float(0.875)), "87.5 %");
+        assert_eq!(render_filter(&mut engine, "duration", TemplateValue::Number(5)), "5s");
+    }
+}
+
+/// `{{assert condition "message"}}` render-time assertions
+#[cfg(test)]
+mod template_assertion_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+
+    fn cart(prices: &[i64]) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        let items = prices.iter().enumerate()
+            .map(|(index, price)| {
+                let mut item = HashMap::new();
+                item.insert("name".to_string(), TemplateValue::String(format!("item{}", index + 1)));
+                item.insert("price".to_string(), TemplateValue::Number(*price));
+                TemplateValue::Object(item)
+            })
+            .collect();
+        context.set("items", TemplateValue::Array(items));
+        context.set_number("user_id", 7);
+        context
+    }
+
+    #[test]
+    fn test_passing_assertions_render_nothing() {
+        let mut engine = TemplateEngine::new("templates");
+        let template = "{{assert user_id \"user must be present\"}}<ul>{{assert items|length <= 100 \"too many items for this layout\"}}{{for item in items}}<li>{{item.name}}</li>{{/for}}</ul>";
+        assert_eq!(engine.render_string(template, &cart(&[3, 4])).unwrap(), "<ul><li>item1</li><li>item2</li></ul>");
+        assert!(engine.check_syntax(template).is_ok());
+        assert!(engine.check_syntax("{{assert \"no condition\"}}").is_err());
+        assert!(engine.check_syntax("{{assert user_id}}").is_err());
+    }
+
+    #[test]
+    fn test_failing_assertions_fail_the_render_with_their_location() {
+        let mut engine = TemplateEngine::new("templates");
+        let template = "<ul>\n  {{assert items|length <= 1 \"too many items for this layout\"}}\n</ul>";
+
+        match engine.render_string(template, &cart(&[3, 4])).unwrap_err() {
+            TemplateError::AssertionFailed { message, condition, values, template_name, line, column } => {
+                assert_eq!(message, "too many items for this layout");
+                assert_eq!(condition, "items|length <= 1");
+                assert_eq!(values, vec![("items|length".to_string(), "2".to_string())]);
+                assert_eq!(template_name, None);
+                assert_eq!((line, column), (2, 3));
+            }
+            other => panic!("expected a failed assertion, got {:?}", other),
+        }
+
+        let error = engine.render_string("{{assert account.id \"user must be present\"}}", &cart(&[])).unwrap_err();
+        assert_eq!(error.kind(), "assertion");
+        assert_eq!(
+            error.to_string(),
+            "Assertion failed at line 1, column 1: user must be present (account.id, where account.id = missing)"
+        );
+    }
+
+    #[test]
+    fn test_soft_assertions_are_recorded_and_the_render_continues() {
+        let mut engine = TemplateEngine::new("templates");
+        engine.set_soft_assertions(true);
+        let template = "{{assert items|length <= 1 \"too many items\"}}{{items|length}} items";
+
+        let (output, failed) = engine.render_string_with_failed_assertions(template, &cart(&[3, 4])).unwrap();
+        assert_eq!(output, "2 items");
+        assert_eq!(failed.len(), 1);
+        assert!(matches!(&failed[0], TemplateError::AssertionFailed { message, .. } if message == "too many items"));
+
+        // Outside the recording call the failure is dropped
+        assert_eq!(engine.render_string(template, &cart(&[3, 4])).unwrap(), "2 items");
+    }
+
+    #[test]
+    fn test_assertion_in_a_loop_reports_the_failing_iteration() {
+        let mut engine = TemplateEngine::new("templates");
+        let template = "{{for item in items}}{{assert item.price > 0 \"prices must be positive\"}}{{item.name}} {{/for}}";
+
+        let error = engine.render_string(template, &cart(&[3, -2, 5])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Assertion failed at line 1, column 22: prices must be positive (item.price > 0, where item.price = -2)"
+        );
+
+        engine.set_soft_assertions(true);
+        let (output, failed) = engine.render_string_with_failed_assertions(template, &cart(&[-1, 3, -2])).unwrap();
+        assert_eq!(output, "item1 item2 item3 ");
+        let values: Vec<String> = failed.iter().map(|error| match error {
+            TemplateError::AssertionFailed { values, .. } => values[0].1.clone(),
+            other => panic!("expected a failed assertion, got {:?}", other),
+        }).collect();
+        assert_eq!(values, vec!["-1", "-2"]);
+    }
+
+    #[test]
+    fn test_assertions_in_included_templates_name_the_template() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("badge.html"), "<b>\n{{assert user_id > 10 \"admins only\"}}</b>").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let error = engine.render_string("<p>{{include \"badge.html\"}}</p>", &cart(&[])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Assertion failed in 'badge.html' at line 2, column 1: admins only (user_id > 10, where user_id = 7)"
+        );
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// Tests for the argument parser shared by helpers, macros, {{t}} and {{plural}}
+#[cfg(test)]
+mod argument_list_tests {
+    use super::*;
+
+    /// Template passing one argument to a call site, which renders it
+    type CallSite = (&'static str, fn(&str) -> String);
+
+    fn call_sites() -> Vec<CallSite> {
+        #[allow(unused_mut)]
+        let mut sites: Vec<CallSite> = vec![
+            ("helper", |arg| format!("{{{{echo({})}}}}", arg)),
+        ];
+        #[cfg(feature = "template-macros")]
+        sites.push(("macro", |arg| format!("{{{{macro echo(value)}}}}{{{{value}}}}{{{{/macro}}}}{{{{echo(value={})}}}}", arg)));
+        #[cfg(feature = "i18n")]
+        sites.push(("t", |arg| format!("{{{{t \"echo\" value={}}}}}", arg)));
+        sites
+    }
+
+    fn engine() -> TemplateEngine {
+        let mut engine = TemplateEngine::new("templates");
+        engine.register_helper("echo", |args| {
+            let shown: Vec<String> = args.iter().map(|arg| match arg {
+                TemplateValue::String(text) => text.clone(),
+                TemplateValue::Number(number) => number.to_string(),
+                TemplateValue::Float(number) => number.to_string(),
+                TemplateValue::Bool(flag) => flag.to_string(),
+                other => format!("{:?}", other),
+            }).collect();
+            Ok(TemplateValue::String(shown.join(";")))
+        });
+        #[cfg(feature = "i18n")]
+        {
+            engine.set_translations("en", HashMap::from([("echo".to_string(), "{{value}}".to_string())]));
+            engine.set_locale("en");
+        }
+        engine
+    }
+
+    fn context() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        let mut user = HashMap::new();
+        user.insert("name".to_string(), TemplateValue::String("Ada".to_string()));
+        context.set("user", TemplateValue::Object(user));
+        context.set("one", TemplateValue::Number(1));
+        context
+    }
+
+    /// Tricky strings, with the text every call site should produce
+    const STRINGS: &[(&str, &str)] = &[
+        (r#""a, b""#, "a, b"),
+        (r#"'single'"#, "single"),
+        (r#""a b  c""#, "a b  c"),
+        (r#""a=b""#, "a=b"),
+        (r#""x|upper""#, "x|upper"),
+        (r#""back\\slash""#, "back\\slash"),
+        (r#""C:\path""#, "C:\\path"),
+        (r#""line\tbreak""#, "line\tbreak"),
+        (r#""""#, ""),
+    ];
+
+    #[test]
+    fn test_string_literals_read_the_same_at_every_call_site() {
+        let mut engine = engine();
+        let context = context();
+        for (site, template) in call_sites() {
+            for (arg, expected) in STRINGS {
+                let output = engine.render_string(&template(arg), &context)
+                    .unwrap_or_else(|error| panic!("{} with {}: {}", site, arg, error));
+                assert_eq!(output, *expected, "{} with {}", site, arg);
+            }
+        }
+        for (arg, expected) in STRINGS {
+            let output = engine.render_string(&format!("{{{{plural one {} \"other\"}}}}", arg), &context).unwrap();
+            assert_eq!(output, *expected, "plural with {}", arg);
+        }
+    }
+
+    #[test]
+    fn test_literals_paths_and_filters_resolve_the_same_at_every_call_site() {
+        let mut engine = engine();
+        let context = context();
+        let values = [
+            ("42", "42"),
+            ("-1.5", "-1.5"),
+            ("true", "true"),
+            ("user.name", "Ada"),
+            ("user.name|upper", "ADA"),
+        ];
+        for (site, template) in call_sites() {
+            for (arg, expected) in values {
+                let output = engine.render_string(&template(arg), &context)
+                    .unwrap_or_else(|error| panic!("{} with {}: {}", site, arg, error));
+                assert_eq!(output, expected, "{} with {}", site, arg);
+            }
+        }
+        assert_eq!(engine.render_string("{{plural one|int item items}}", &context).unwrap(), "item");
+    }
+
+    #[test]
+    fn test_length_counts_reach_every_call_site() {
+        let mut engine = engine();
+        let mut context = context();
+        context.set("items", TemplateValue::Array(vec![TemplateValue::Number(1), TemplateValue::Number(2)]));
+        context.set("single", TemplateValue::Array(vec![TemplateValue::Number(1)]));
+        context.set_string("letter", "é");
+        for (site, template) in call_sites() {
+            let output = engine.render_string(&template("items|length"), &context)
+                .unwrap_or_else(|error| panic!("{} with items|length: {}", site, error));
+            assert_eq!(output, "2", "{} with items|length", site);
+        }
+        for (count, expected) in [("items", "items"), ("single", "item"), ("user", "item"), ("letter", "item"), ("missing", "items")] {
+            let template = format!("{{{{plural {}|length \"item\" \"items\"}}}}", count);
+            assert_eq!(engine.render_string(&template, &context).unwrap(), expected, "{}", template);
+        }
+    }
+
+    #[test]
+    fn test_unterminated_strings_fail_with_the_position_at_every_call_site() {
+        let mut engine = engine();
+        for (site, template) in call_sites() {
+            let error = engine.render_string(&template(r#""abc"#), &context()).unwrap_err();
+            assert!(error.to_string().contains("Unterminated string at position"), "{}: {}", site, error);
+        }
+        let error = engine.render_string(r#"{{plural one "item items}}"#, &context()).unwrap_err();
+        assert_eq!(error.to_string(), r#"Parse error: Unterminated string at position 12 in '{{plural one "item items}}'"#);
+
+        let error = engine.render_string(r#"{{echo("ok", "abc)}}"#, &context()).unwrap_err();
+        assert_eq!(error.to_string(), r#"Parse error: Unterminated string at position 12 in '{{echo("ok", "abc)}}'"#);
+    }
+
+    #[test]
+    fn test_comma_lists_take_trailing_commas_and_reject_empty_arguments() {
+        let mut engine = engine();
+        assert_eq!(engine.render_string(r#"{{echo("a", 2,)}}"#, &context()).unwrap(), "a;2");
+
+        let error = engine.render_string(r#"{{echo("a",, 2)}}"#, &context()).unwrap_err();
+        assert_eq!(error.to_string(), r#"Parse error: Empty argument at position 10 in '{{echo("a",, 2)}}'"#);
+    }
+
+    #[test]
+    #[cfg(feature = "template-macros")]
+    fn test_macro_arguments_mix_positional_and_named_values() {
+        let mut engine = engine();
+        let template = r#"{{macro badge(label, count, tone="plain")}}{{label}}:{{count}}:{{tone}}{{/macro}}{{badge(user.name|upper, 3, tone = "loud",)}}"#;
+        assert_eq!(engine.render_string(template, &context()).unwrap(), "ADA:3:loud");
+
+        let error = engine.render_string(r#"{{macro m(a)}}{{a}}{{/macro}}{{m(a="x", a="y")}}"#, &context()).unwrap_err();
+        assert!(error.to_string().contains("Duplicate argument 'a' at position"), "{}", error);
+    }
+
+    #[test]
+    #[cfg(feature = "i18n")]
+    fn test_translation_arguments_must_be_named() {
+        let mut engine = engine();
+        let error = engine.render_string(r#"{{t "echo" user.name}}"#, &context()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            r#"Parse error: Unexpected argument 'user.name' in '{{t "echo" user.name}}'; translation arguments are name=value"#
+        );
+
+        let error = engine.render_string(r#"{{t "echo" value=}}"#, &context()).unwrap_err();
+        assert_eq!(error.to_string(), r#"Parse error: Missing value for argument 'value' at position 10 in '{{t "echo" value=}}'"#);
+    }
+}
+
+/// Tests for `{{set}}` and the `paginate` helper
+#[cfg(test)]
+mod pagination_tests {
+    use super::*;
+
+    fn articles(count: i64, page: TemplateValue) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set("items", TemplateValue::Array((1..=count).map(TemplateValue::Number).collect()));
+        context.set("page", page);
+        context
+    }
+
+    const LIST: &str = "{{set pg = paginate(items, page, 20)}}{{for item in pg.items}}{{item}},{{/for}} {{pg.page}}/{{pg.total_pages}} of {{pg.total_items}}{{if pg.has_prev}} prev={{pg.prev_page}}{{/if}}{{if pg.has_next}} next={{pg.next_page}}{{/if}}";
+
+    #[test]
+    fn test_paginate_slices_the_page_and_links_its_neighbours() {
+        let mut engine = TemplateEngine::new("templates");
+        let output = engine.render_string(LIST, &articles(45, TemplateValue::Number(2))).unwrap();
+        let expected_items: String = (21..=40).map(|n| format!("{},", n)).collect();
+        assert_eq!(output, format!("{} 2/3 of 45 prev=1 next=3", expected_items));
+    }
+
+    #[test]
+    fn test_last_partial_page_and_string_page_numbers() {
+        let mut engine = TemplateEngine::new("templates");
+        let output = engine.render_string(LIST, &articles(45, TemplateValue::String("3".to_string()))).unwrap();
+        assert_eq!(output, "41,42,43,44,45, 3/3 of 45 prev=2");
+    }
+
+    #[test]
+    fn test_empty_list_has_one_empty_page() {
+        let mut engine = TemplateEngine::new("templates");
+        let output = engine.render_string(LIST, &articles(0, TemplateValue::Number(1))).unwrap();
+        assert_eq!(output, " 1/1 of 0");
+    }
+
+    #[test]
+    fn test_out_of_range_pages_are_clamped_unless_strict() {
+        let mut engine = TemplateEngine::new("templates");
+        let output = engine.render_string(LIST, &articles(45, TemplateValue::Number(9))).unwrap();
+        assert_eq!(output, "41,42,43,44,45, 3/3 of 45 prev=2");
+        let output = engine.render_string(LIST, &articles(45, TemplateValue::String("abc".to_string()))).unwrap();
+        assert!(output.ends_with(" 1/3 of 45 next=2"), "{}", output);
+        let output = engine.render_string(LIST, &articles(45, TemplateValue::Number(-4))).unwrap();
+        assert!(output.starts_with("1,2,3,"), "{}", output);
+
+        engine.enable_strict_mode();
+        let error = engine.render_string(LIST, &articles(45, TemplateValue::Number(9))).unwrap_err();
+        assert_eq!(error.to_string(), "Render error: paginate: page 9 is out of range 1..=3");
+    }
+
+    #[test]
+    fn test_windowed_page_numbers() {
+        let mut engine = TemplateEngine::new("templates");
+        let pages = |engine: &mut TemplateEngine, page: i64| {
+            engine.render_string(
+                "{{set pg = paginate(items, page, 10, 5)}}{{for number in pg.pages}}{{number}} {{/for}}",
+                &articles(120, TemplateValue::Number(page)),
+            ).unwrap()
+        };
+        assert_eq!(pages(&mut engine, 1), "1 2 3 4 5 ");
+        assert_eq!(pages(&mut engine, 7), "5 6 7 8 9 ");
+        assert_eq!(pages(&mut engine, 12), "8 9 10 11 12 ");
+
+        let all = engine.render_string(
+            "{{set pg = paginate(items, 1, 50)}}{{for number in pg.pages}}{{number}} {{/for}}",
+            &articles(120, TemplateValue::Number(1)),
+        ).unwrap();
+        assert_eq!(all, "1 2 3 ");
+    }
+
+    #[test]
+    fn test_set_assigns_literals_paths_and_filter_chains() {
+        let mut engine = TemplateEngine::new("templates");
+        let mut context = TemplateContext::new();
+        context.set_string("name", "ada");
+        let template = "{{set shout = name|upper}}{{set limit = 3}}{{set label = \"Top, \\\"best\\\"\"}}{{if limit > 2}}{{shout}} {{& label}}{{/if}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "ADA Top, \"best\"");
+
+        let error = engine.render_string("{{set = 3}}", &context).unwrap_err();
+        assert_eq!(error.to_string(), "Parse error: Malformed '{{set = 3}}': expected '{{set name = value}}'");
+        assert!(engine.check_syntax("{{set pg = paginate(items, 1, 20)}}{{pg.page}}").is_ok());
+    }
+}
+
+/// Integers are written digit by digit and must read as `i64::to_string` does
+#[cfg(test)]
+mod number_output_tests {
+    use super::*;
+
+    const SAMPLES: [i64; 12] = [
+        0, 1, -1, 9, 10, -10, 99_999, -123_456_789, 1_000_000_000_000, i64::MAX, i64::MIN, i64::MIN + 1,
+    ];
+
+    #[test]
+    fn test_number_variables_match_to_string() {
+        let mut engine = TemplateEngine::new(".");
+        for n in SAMPLES {
+            let mut context = TemplateContext::new();
+            context.set_number("n", n);
+            let result = engine.render_string("{{n}}|{{& n}}|{{n|add:0}}", &context).unwrap();
+            assert_eq!(result, format!("{0}|{0}|{0}", n));
+        }
+    }
+
+    #[test]
+    fn test_number_loop_and_table_cells_match_to_string() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set("values", TemplateValue::Array(SAMPLES.iter().map(|n| TemplateValue::Number(*n)).collect()));
+        let rows = SAMPLES.iter().map(|n| {
+            TemplateValue::Object(HashMap::from([("value".to_string(), TemplateValue::Number(*n))]))
+        }).collect();
+        context.set("rows", TemplateValue::Array(rows));
+
+        let expected: String = SAMPLES.iter().map(|n| format!("{},", n)).collect();
+        assert_eq!(engine.render_string("{{for n in values}}{{n}},{{/for}}", &context).unwrap(), expected);
+
+        let table = engine.render_string(r#"{{table rows columns="value:Value"}}"#, &context).unwrap();
+        for n in SAMPLES {
+            assert!(table.contains(&format!("<td>{}</td>", n)), "{} missing from {}", n, table);
+        }
+    }
+}
+
+/// `{{unless}}` and `{{ifchanged}}` directives
+#[cfg(test)]
+mod unless_ifchanged_tests {
+    use super::*;
+
+    fn txn(date: &str, label: &str) -> TemplateValue {
+        let mut fields = HashMap::new();
+        fields.insert("date".to_string(), TemplateValue::String(date.to_string()));
+        fields.insert("label".to_string(), TemplateValue::String(label.to_string()));
+        TemplateValue::Object(fields)
+    }
+
+    fn ledger(entries: &[(&str, &str)]) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set("txns", TemplateValue::Array(entries.iter().map(|(date, label)| txn(date, label)).collect()));
+        context
+    }
+
+    const LEDGER: &str = "{{for txn in txns}}{{ifchanged txn.date}}<h3>{{txn.date}}</h3>{{/ifchanged}}<p>{{txn.label}}</p>{{/for}}";
+
+    #[test]
+    fn test_unless_renders_when_condition_is_false() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_bool("paid", false);
+        context.set_number("count", 2);
+
+        let template = "{{unless paid}}Due{{/unless}}|{{unless count > 3}}few{{/unless}}|{{unless count == 2}}other{{/unless}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "Due|few|");
+
+        context.set_bool("paid", true);
+        assert_eq!(engine.render_string("{{unless paid}}Due{{/unless}}{{unless missing}}!{{/unless}}", &context).unwrap(), "!");
+    }
+
+    #[test]
+    fn test_unless_nests_with_if_and_for() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = ledger(&[("2024-03-01", "Rent"), ("2024-03-02", "Coffee")]);
+        context.set_bool("archived", false);
+        context.set_bool("locked", true);
+
+        let template = "{{unless archived}}A{{unless locked}}B{{/unless}}{{if locked}}L{{/if}}{{/unless}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "AL");
+
+        // Loop variables are only known inside the loop
+        let template = "{{unless archived}}{{for txn in txns}}{{unless txn.label == \"Rent\"}}{{txn.label}}{{/unless}}{{/for}}{{/unless}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "Coffee");
+    }
+
+    #[test]
+    fn test_ifchanged_renders_each_group_header_once() {
+        let mut engine = TemplateEngine::new(".");
+        let context = ledger(&[
+            ("2024-03-01", "Rent"),
+            ("2024-03-01", "Coffee"),
+            ("2024-03-02", "Books"),
+            ("2024-03-03", "Train"),
+            ("2024-03-03", "Lunch"),
+            ("2024-03-03", "Cinema"),
+        ]);
+
+        let output = engine.render_string(LEDGER, &context).unwrap();
+        for date in ["2024-03-01", "2024-03-02", "2024-03-03"] {
+            assert_eq!(output.matches(&format!("<h3>{}</h3>", date)).count(), 1, "{}", output);
+        }
+        assert_eq!(output, "<h3>2024-03-01</h3><p>Rent</p><p>Coffee</p><h3>2024-03-02</h3><p>Books</p>\
+            <h3>2024-03-03</h3><p>Train</p><p>Lunch</p><p>Cinema</p>");
+
+        // A value that comes back after a change starts a new group
+        let output = engine.render_string(LEDGER, &ledger(&[("a", "1"), ("b", "2"), ("a", "3")])).unwrap();
+        assert_eq!(output, "<h3>a</h3><p>1</p><h3>b</h3><p>2</p><h3>a</h3><p>3</p>");
+    }
+
+    #[test]
+    fn test_ifchanged_resets_when_the_outer_loop_advances() {
+        let mut engine = TemplateEngine::new(".");
+        let account = |name: &str, entries: &[(&str, &str)]| {
+            let mut fields = HashMap::new();
+            fields.insert("name".to_string(), TemplateValue::String(name.to_string()));
+            fields.insert("txns".to_string(), TemplateValue::Array(entries.iter().map(|(date, label)| txn(date, label)).collect()));
+            TemplateValue::Object(fields)
+        };
+        let mut context = TemplateContext::new();
+        context.set("accounts", TemplateValue::Array(vec![
+            account("Checking", &[("03-01", "Rent"), ("03-02", "Coffee")]),
+            account("Savings", &[("03-02", "Interest"), ("03-02", "Transfer")]),
+        ]));
+        context.set_bool("show", true);
+
+        let template = "{{if show}}{{for account in accounts}}[{{account.name}}]\
+            {{for txn in account.txns}}{{ifchanged txn.date}}<h3>{{txn.date}}</h3>{{/ifchanged}}{{txn.label}};{{/for}}{{/for}}{{/if}}";
+        let output = engine.render_string(template, &context).unwrap();
+        assert_eq!(output, "[Checking]<h3>03-01</h3>Rent;<h3>03-02</h3>Coffee;[Savings]<h3>03-02</h3>Interest;Transfer;");
+
+        // Nested change checks compare with the previous item of their own loop
+        let template = "{{for account in accounts}}{{ifchanged account.name}}{{account.name}}:{{/ifchanged}}\
+            {{for txn in account.txns}}{{ifchanged txn.date}}{{unless txn.label == \"Rent\"}}*{{/unless}}{{/ifchanged}}{{/for}};{{/for}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "Checking:*;Savings:*;");
+    }
+
+    #[test]
+    fn test_ifchanged_outside_loops_always_renders() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("day", "Monday");
+        assert_eq!(engine.render_string("{{ifchanged day}}{{day}}{{/ifchanged}}", &context).unwrap(), "Monday");
+    }
+
+    #[test]
+    fn test_unclosed_blocks_are_reported() {
+        let mut engine = TemplateEngine::new(".");
+        let context = ledger(&[("a", "1")]);
+        let error = engine.render_string("{{unless paid}}Due", &context).unwrap_err();
+        assert!(error.to_string().contains("Missing {{/unless}} directive"), "{}", error);
+        let error = engine.render_string("{{for txn in txns}}{{ifchanged txn.date}}x{{/for}}", &context).unwrap_err();
+        assert!(error.to_string().contains("Missing {{/ifchanged}} directive"), "{}", error);
+        assert!(engine.check_syntax("{{unless}}x{{/unless}}").is_err());
+        assert!(engine.check_syntax(LEDGER).is_ok());
+    }
+
+    #[test]
+    fn test_parse_unless_and_ifchanged_nodes() {
+        use mystical_runic::ast::{self, Node};
+
+        let template = ast::parse("{{unless paid}}Due{{/unless}}{{for txn in txns}}{{ifchanged txn.date}}{{txn.date}}{{/ifchanged}}{{/for}}").unwrap();
+        assert!(matches!(&template.nodes[0], Node::Unless(node) if node.condition == "paid" && node.body.len() == 1));
+        match &template.nodes[1] {
+            Node::For(node) => assert!(matches!(&node.body[0], Node::IfChanged(changed) if changed.expr == "txn.date")),
+            other => panic!("expected a loop, got {:?}", other),
+        }
+        assert!(ast::parse("{{ifchanged day}}open{{/if}}").is_err());
+    }
+
+    #[test]
+    fn test_bytecode_compiles_unless_and_ifchanged() {
+        let dir = create_temp_dir();
+        fs::write(dir.join("status.html"), "{{unless paid}}Due{{/unless}}{{ifchanged day}}[{{day}}]{{/ifchanged}}").unwrap();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        let mut context = TemplateContext::new();
+        context.set_bool("paid", false);
+        context.set_string("day", "Monday");
+
+        let compiled = engine.compile_to_bytecode("status.html").unwrap();
+        assert_eq!(engine.render_compiled(&compiled, &context).unwrap(), "Due[Monday]");
+        context.set_bool("paid", true);
+        assert_eq!(engine.render_compiled(&compiled, &context).unwrap(), "[Monday]");
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_trace_and_profile_follow_change_checks() {
+        let mut engine = TemplateEngine::new(".");
+        let same_day = ledger(&[("03-01", "Rent"), ("03-01", "Coffee")]);
+        let next_day = ledger(&[("03-01", "Rent"), ("03-02", "Coffee")]);
+
+        let diff = engine.diff_render_strings(LEDGER, LEDGER, &same_day).unwrap();
+        assert!(diff.is_identical());
+        assert_eq!(diff.left_output, engine.render_string(LEDGER, &same_day).unwrap());
+
+        let dir = create_temp_dir();
+        fs::write(dir.join("ledger.html"), LEDGER).unwrap();
+        let mut engine = TemplateEngine::new(dir.to_str().unwrap());
+        let diff = engine.diff_contexts("ledger.html", &same_day, &next_day).unwrap();
+        assert!(diff.regions.iter().any(|region| region.right.iter()
+            .any(|segment| segment.output == "03-02" && segment.scope.iter().any(|scope| scope.directive == "ifchanged txn.date"))));
+
+        let profile = engine.profile_render("ledger.html", &next_day).unwrap();
+        assert_eq!(profile.output, engine.render("ledger.html", &next_day).unwrap());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(feature = "lsp")]
+    #[test]
+    fn test_diagnostics_report_unclosed_unless_and_ifchanged() {
+        let mut engine = TemplateEngine::new(".");
+        let context = ledger(&[]);
+
+        let diagnostics = engine.get_diagnostics_for_editor("{{unless txns}}\n{{for txn in txns}}{{ifchanged txn.date}}{{/for}}", &context).unwrap();
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert!(messages.contains(&"Mismatched closing directive"), "{:?}", messages);
+        assert!(messages.contains(&"Unclosed unless directive"), "{:?}", messages);
+
+        let diagnostics = engine.get_diagnostics_for_editor(LEDGER, &context).unwrap();
+        assert!(diagnostics.iter().all(|d| d.severity != "error"), "{:?}", diagnostics);
+        let diagnostics = engine.get_diagnostics_for_editor("{{ifchanged day}}x{{/unless}}", &context).unwrap();
+        assert!(diagnostics.iter().any(|d| d.message == "Mismatched closing directive"));
+    }
+}
+
+/// Comparison semantics tests: `==`, `!=` and `<` for every pair of value kinds
+#[cfg(test)]
+mod comparison_tests {
+    use super::*;
+
+    /// One value of each kind; `None` is a missing variable
+    fn values() -> Vec<(&'static str, Option<TemplateValue>)> {
+        vec![
+            ("string", Some(TemplateValue::String("pear".to_string()))),
+            ("numeric string", Some(TemplateValue::String("10".to_string()))),
+            ("number", Some(TemplateValue::Number(5))),
+            ("float", Some(TemplateValue::Float(2.5))),
+            ("bool", Some(TemplateValue::Bool(true))),
+            ("array", Some(TemplateValue::Array(vec![TemplateValue::Number(1), TemplateValue::Number(2)]))),
+            ("object", Some(TemplateValue::Object(HashMap::from([("x".to_string(), TemplateValue::Number(1))])))),
+            ("missing", None),
+        ]
+    }
+
+    /// `left == right` for each row, columns in the order of `values()`
+    const EQUAL: [&str; 8] = [
+        "TFFFFFFF",
+        "FTFFFFFF",
+        "FFTFFFFF",
+        "FFFTFFFF",
+        "FFFFTFFF",
+        "FFFFFTFF",
+        "FFFFFFTF",
+        "FFFFFFFT",
+    ];
+
+    /// `left < right`; `E` can't be ordered: false, or an error in strict mode
+    const LESS: [&str; 8] = [
+        "FFEEEEEF",
+        "TFFFEEEF",
+        "ETFFEEEE",
+        "ETTFEEEE",
+        "EEEEEEEE",
+        "EEEEEEEE",
+        "EEEEEEEE",
+        "TTEEEEEF",
+    ];
+
+    fn context(left: &Option<TemplateValue>, right: &Option<TemplateValue>) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        if let Some(left) = left {
+            context.set("a", left.clone());
+        }
+        if let Some(right) = right {
+            context.set("b", right.clone());
+        }
+        context
+    }
+
+    #[test]
+    fn test_comparison_matrix() {
+        let mut engine = TemplateEngine::new(".");
+        let mut strict = TemplateEngine::new(".");
+        strict.enable_strict_mode();
+        let values = values();
+
+        for (row, (left_kind, left)) in values.iter().enumerate() {
+            for (column, (right_kind, right)) in values.iter().enumerate() {
+                let context = context(left, right);
+                let pair = format!("{} vs {}", left_kind, right_kind);
+                let equal = EQUAL[row].as_bytes()[column] == b'T';
+                let mut check = |template: &str| engine.render_string(template, &context).unwrap() == "T";
+
+                assert_eq!(check("{{if a == b}}T{{/if}}"), equal, "{} ==", pair);
+                assert_eq!(check("{{if a != b}}T{{/if}}"), !equal, "{} !=", pair);
+                assert!(strict.render_string("{{if a == b}}T{{/if}}", &context).is_ok(), "{} == strict", pair);
+
+                match LESS[row].as_bytes()[column] {
+                    b'E' => {
+                        assert!(!check("{{if a < b}}T{{/if}}"), "{} <", pair);
+                        assert!(!check("{{if a >= b}}T{{/if}}"), "{} >=", pair);
+                        let error = strict.render_string("{{if a < b}}T{{/if}}", &context).unwrap_err();
+                        assert!(error.to_string().contains("Cannot compare"), "{} < strict: {}", pair, error);
+                    }
+                    less => {
+                        assert_eq!(check("{{if a < b}}T{{/if}}"), less == b'T', "{} <", pair);
+                        assert_eq!(check("{{if a >= b}}T{{/if}}"), less != b'T', "{} >=", pair);
+                        let strict_less = strict.render_string("{{if a < b}}T{{/if}}", &context).unwrap() == "T";
+                        assert_eq!(strict_less, less == b'T', "{} < strict", pair);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_numeric_strings_compare_as_numbers() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_number("count", 10);
+        context.set_string("limit", "5");
+        context.set_string("price", "10.50");
+
+        let result = engine.render_string(
+            "{{if count >= \"5\"}}A{{/if}}{{if count == \"10\"}}B{{/if}}{{if limit < count}}C{{/if}}{{if price > 10}}D{{/if}}{{if \"10\" > \"9\"}}E{{/if}}{{if count == 10.0}}F{{/if}}",
+            &context,
+        ).unwrap();
+        assert_eq!(result, "ABCDEF");
+    }
+
+    #[test]
+    fn test_arrays_and_objects_compare_deeply() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        let numbers = TemplateValue::Array(vec![TemplateValue::Number(1), TemplateValue::Number(2)]);
+        let letters = TemplateValue::Array(vec![TemplateValue::String("a".to_string()), TemplateValue::String("b".to_string())]);
+        let user = |name: &str| TemplateValue::Object(HashMap::from([("name".to_string(), TemplateValue::String(name.to_string()))]));
+        context.set("numbers", numbers.clone());
+        context.set("same_numbers", numbers);
+        context.set("letters", letters);
+        context.set("alice", user("alice"));
+        context.set("also_alice", user("alice"));
+        context.set("bob", user("bob"));
+
+        let result = engine.render_string(
+            "{{if numbers == same_numbers}}A{{/if}}{{if numbers == letters}}B{{/if}}{{if alice == also_alice}}C{{/if}}{{if alice == bob}}D{{/if}}",
+            &context,
+        ).unwrap();
+        assert_eq!(result, "AC");
+    }
+
+    #[test]
+    fn test_words_are_not_numbers() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("word", "NaN");
+        context.set_string("code", "007");
+
+        let result = engine.render_string(
+            "{{if word == \"NaN\"}}A{{/if}}{{if word < 1}}B{{/if}}{{if code == 7}}C{{/if}}{{if code == \"7\"}}D{{/if}}",
+            &context,
+        ).unwrap();
+        assert_eq!(result, "ACD");
+    }
+}
+
+/// uid() helper tests
+#[cfg(test)]
+mod uid_tests {
+    use super::*;
+
+    #[cfg(feature = "template-macros")]
+    const FIELD: &str = "{{macro field(label)}}{{set id = uid(\"email-field\")}}\
+        <label for=\"{{id}}\">{{label}}</label><input id=\"{{id}}\">{{/macro}}";
+
+    #[test]
+    #[cfg(feature = "template-macros")]
+    fn test_uid_pairs_ids_within_macro_calls() {
+        let mut engine = TemplateEngine::new(".");
+        let template = format!("{}{{{{field(\"Work\")}}}}{{{{field(\"Home\")}}}}{{{{field(\"Other\")}}}}", FIELD);
+        let output = engine.render_string(&template, &TemplateContext::new()).unwrap();
+        assert_eq!(
+            output,
+            "<label for=\"email-field-1\">Work</label><input id=\"email-field-1\">\
+             <label for=\"email-field-2\">Home</label><input id=\"email-field-2\">\
+             <label for=\"email-field-3\">Other</label><input id=\"email-field-3\">"
+        );
+
+        // Every render starts over
+        assert_eq!(engine.render_string(&template, &TemplateContext::new()).unwrap(), output);
+    }
+
+    #[test]
+    fn test_uid_counts_per_prefix_and_per_render() {
+        let temp_dir = create_temp_dir();
+        fs::write(temp_dir.join("form.html"), "{{uid \"name\"}} {{uid(\"name\")}} {{uid \"email\"}} {{uid}} {{uid()}} {{include \"extra.html\"}}").unwrap();
+        fs::write(temp_dir.join("extra.html"), "{{uid \"name\"}}").unwrap();
+        let mut engine = TemplateEngine::new(temp_dir.to_str().unwrap());
+        let mut clone = Clone::clone(&engine);
+        let expected = "name-1 name-2 email-1 uid-1 uid-2 name-3";
+        assert_eq!(engine.render("form.html", &TemplateContext::new()).unwrap(), expected);
+        assert_eq!(engine.render("form.html", &TemplateContext::new()).unwrap(), expected);
+        assert_eq!(clone.render("form.html", &TemplateContext::new()).unwrap(), expected);
+
+        // Deterministic renders are reproducible
+        engine.set_deterministic(true, 7);
+        assert_eq!(engine.render("form.html", &TemplateContext::new()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_uid_variable_and_helper_take_precedence() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set_string("uid", "user-42");
+        assert_eq!(engine.render_string("{{uid}} {{uid \"a\"}}", &context).unwrap(), "user-42 a-1");
+        engine.register_helper("uid", |_args| Ok(TemplateValue::String("custom".to_string())));
+        assert_eq!(engine.render_string("{{uid(\"a\")}}", &context).unwrap(), "custom");
+        assert!(engine.render_string("{{uid \"a\" \"b\"}}", &context).is_err());
+    }
+}
+
+/// math.*, str.* and list.* expression function tests
+#[cfg(test)]
+mod expression_function_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+
+    fn strings(items: &[&str]) -> TemplateValue {
+        TemplateValue::Array(items.iter().map(|item| TemplateValue::String(item.to_string())).collect())
+    }
+
+    fn context() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set("prices", TemplateValue::Array(vec![TemplateValue::Number(3), TemplateValue::Number(-7), TemplateValue::Number(12)]));
+        context.set("ratio", TemplateValue::Float(-2.5));
+        context.set("limit", TemplateValue::Number(40));
+        context.set("tags", strings(&["rust", "web", "rust", "cli"]));
+        context.set("names", strings(&["Ada", "Linus"]));
+        context.set("roles", strings(&["admin", "dev", "ops"]));
+        context.set_string("title", "Draft: release notes");
+        context.set_string("csv", "a,b,c");
+        context
+    }
+
+    #[test]
+    fn test_each_function() {
+        let mut engine = TemplateEngine::new(".");
+        let context = context();
+        let cases = [
+            ("{{math.min(limit, 10)}}", "10"),
+            ("{{math.max(3, limit, 7)}}", "40"),
+            ("{{math.min(prices)}} {{math.max(prices)}}", "-7 12"),
+            ("{{math.max(1, 2.5)}}", "2.5"),
+            ("{{math.abs(ratio)}} {{math.abs(-4)}}", "2.5 4"),
+            ("{{math.ceil(ratio)}} {{math.floor(ratio)}} {{math.floor(\"7.9\")}}", "-2 -3 7"),
+            ("{{str.contains(title, \"Draft\")}} {{str.contains(title, \"Final\")}}", "true false"),
+            ("{{str.split(csv, \",\")|length}} {{str.split(\"ab\", \"\")|length}}", "3 2"),
+            ("{{list.sum(prices)}}", "8"),
+            ("{{list.unique(tags)|length}}", "3"),
+            ("{{list.zip(names, roles)|length}} {{list.zip(roles, tags)|length}}", "2 3"),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(engine.render_string(template, &context).unwrap(), expected, "{}", template);
+        }
+
+        for template in ["{{math.abs(1, 2)}}", "{{list.sum(title)}}", "{{math.max(title, 1)}}"] {
+            let error = engine.render_string(template, &context).unwrap_err();
+            assert!(matches!(error.root_cause(), TemplateError::Render(_)), "{}: {:?}", template, error);
+        }
+    }
+
+    #[test]
+    fn test_functions_in_conditions_loops_set_and_filter_arguments() {
+        let mut engine = TemplateEngine::new(".");
+        let context = context();
+        let template = "{{if str.contains(title, \"Draft\")}}draft{{/if}} \
+            {{if math.max(prices) > 10}}big{{/if}} \
+            {{if list.sum(prices) == 8}}eight{{/if}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "draft big eight");
+
+        let template = "{{for pair in list.zip(names, roles)}}{{for side in pair}}{{side}}={{/for}};{{/for}}\
+            {{for tag in list.unique(tags)}}[{{tag}}]{{/for}}\
+            {{for part in str.split(csv, \",\")}}{{part|upper}}{{/for}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "Ada=admin=;Linus=dev=;[rust][web][cli]ABC");
+
+        let template = "{{set total = list.sum(prices)}}{{set cap = math.min(total, 5)}}{{total}}/{{cap}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "8/5");
+
+        engine.enable_strict_mode();
+        let template = "{{title|truncate:math.min(limit, 5)}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "Draft...");
+    }
+
+    #[test]
+    fn test_registered_helpers_extend_and_shadow_namespaces() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = context();
+        engine.register_helper("str.titlecase", |args| {
+            let text = match args {
+                [TemplateValue::String(text)] => text,
+                _ => return Ok(TemplateValue::String(String::new())),
+            };
+            let words: Vec<String> = text.split(' ').map(|word| {
+                let mut chars = word.chars();
+                chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+            }).collect();
+            Ok(TemplateValue::String(words.join(" ")))
+        });
+        assert_eq!(engine.render_string("{{str.titlecase(\"hello dark world\")}}", &context).unwrap(), "Hello Dark World");
+
+        // A dotted call is a function even next to a variable named like its namespace
+        let mut str_object = HashMap::new();
+        str_object.insert("contains".to_string(), TemplateValue::String("field".to_string()));
+        context.set("str", TemplateValue::Object(str_object));
+        assert_eq!(engine.render_string("{{str.contains}} {{str.contains(csv, \"b\")}}", &context).unwrap(), "field true");
+
+        engine.register_helper("math.min", |_args| Ok(TemplateValue::String("custom".to_string())));
+        assert_eq!(engine.render_string("{{math.min(1, 2)}}", &context).unwrap(), "custom");
+    }
+}
+
+/// `{{else}}` branches of `{{if}}` blocks
+#[cfg(test)]
+mod else_tests {
+    use super::*;
+    use mystical_runic::ast::{self, Node};
+    use mystical_runic::TemplateError;
+
+    const PAGE: &str = "{{if user}}<p>{{user}}</p>{{else}}<a>Sign in</a>{{/if}}";
+
+    #[test]
+    fn test_else_renders_when_condition_is_false() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        assert_eq!(engine.render_string(PAGE, &context).unwrap(), "<a>Sign in</a>");
+        context.set_string("user", "Ada");
+        assert_eq!(engine.render_string(PAGE, &context).unwrap(), "<p>Ada</p>");
+        context.set("count", TemplateValue::Number(3));
+        let compared = "{{if count > 5}}many{{else}}few{{/if}}";
+        assert_eq!(engine.render_string(compared, &context).unwrap(), "few");
+    }
+
+    #[test]
+    fn test_nested_blocks_in_both_branches() {
+        let template = "{{if show}}{{for item in items}}{{if item.done}}[x]{{else}}[ ]{{/if}}{{item.name}} {{/for}}\
+            {{else}}{{if items}}hidden{{else}}empty{{/if}}{{/if}}";
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        let mut done = HashMap::new();
+        done.insert("name".to_string(), TemplateValue::String("tea".to_string()));
+        done.insert("done".to_string(), TemplateValue::Bool(true));
+        let mut todo = HashMap::new();
+        todo.insert("name".to_string(), TemplateValue::String("cake".to_string()));
+        todo.insert("done".to_string(), TemplateValue::Bool(false));
+        context.set("items", TemplateValue::Array(vec![TemplateValue::Object(done), TemplateValue::Object(todo)]));
+
+        context.set("show", TemplateValue::Bool(true));
+        assert_eq!(engine.render_string(template, &context).unwrap(), "[x]tea [ ]cake ");
+        context.set("show", TemplateValue::Bool(false));
+        assert_eq!(engine.render_string(template, &context).unwrap(), "hidden");
+        context.set("items", TemplateValue::Array(vec![]));
+        assert_eq!(engine.render_string(template, &context).unwrap(), "empty");
+
+        let parsed = ast::parse(template).unwrap();
+        let Node::If(conditional) = &parsed.nodes[0] else { panic!("expected an if block") };
+        assert_eq!(conditional.branches.len(), 2);
+        assert!(conditional.branches[1].condition.is_none());
+    }
+
+    #[test]
+    fn test_else_in_compiled_templates() {
+        let temp_dir = create_temp_dir();
+        fs::write(temp_dir.join("page.html"), "{{if user}}Hi {{user}}{{else}}Guest{{/if}}!{{unless user}} Sign in{{/unless}}").unwrap();
+        let mut engine = TemplateEngine::new(temp_dir.to_str().unwrap());
+        let compiled = engine.compile_to_bytecode("page.html").unwrap();
+
+        let mut context = TemplateContext::new();
+        assert_eq!(engine.render_compiled(&compiled, &context).unwrap(), "Guest! Sign in");
+        context.set_string("user", "Ada");
+        assert_eq!(engine.render_compiled(&compiled, &context).unwrap(), "Hi Ada!");
+    }
+
+    #[test]
+    fn test_stray_else_is_a_parse_error() {
+        let temp_dir = create_temp_dir();
+        fs::write(temp_dir.join("stray.html"), "before{{else}}after").unwrap();
+        fs::write(temp_dir.join("unless.html"), "{{unless user}}a{{else}}b{{/unless}}").unwrap();
+        let mut engine = TemplateEngine::new(temp_dir.to_str().unwrap());
+        let context = TemplateContext::new();
+
+        let error = engine.render_string("before{{else}}after", &context).unwrap_err();
+        assert!(matches!(&error, TemplateError::Parse(message) if message.contains("{{else}}")), "{:?}", error);
+        let error = engine.render("unless.html", &context).unwrap_err();
+        assert!(matches!(error.root_cause(), TemplateError::Parse(_)), "{:?}", error);
+        assert!(matches!(engine.compile_to_bytecode("stray.html"), Err(TemplateError::Parse(_))));
+        assert!(matches!(engine.compile_to_bytecode("unless.html"), Err(TemplateError::Parse(_))));
+        assert!(ast::parse("{{if a}}x{{else}}y{{else}}z{{/if}}").is_err());
+    }
+}
+
+/// `{{elseif}}` and `{{else if}}` branches of `{{if}}` blocks
+#[cfg(test)]
+mod elseif_tests {
+    use super::*;
+    use mystical_runic::ast::{self, Node};
+    use mystical_runic::TemplateError;
+
+    const STATUS: &str = "{{if status == \"pending\"}}Pending{{elseif status == \"shipped\"}}Shipped\
+        {{else if status == \"delivered\"}}Delivered{{else}}Unknown{{/if}}";
+
+    fn status(status: &str) -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("status", status);
+        context
+    }
+
+    #[test]
+    fn test_first_matching_branch_renders() {
+        let mut engine = TemplateEngine::new(".");
+        for (value, expected) in [("pending", "Pending"), ("shipped", "Shipped"), ("delivered", "Delivered"), ("lost", "Unknown")] {
+            assert_eq!(engine.render_string(STATUS, &status(value)).unwrap(), expected);
+        }
+
+        // Later branches are skipped once one renders, and no branch may render at all
+        let mut context = TemplateContext::new();
+        context.set("count", TemplateValue::Number(12));
+        let template = "{{if count > 10}}many{{elseif count > 5}}some{{/if}}|{{if count > 20}}huge{{elseif count > 15}}big{{/if}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "many|");
+    }
+
+    #[test]
+    fn test_nested_conditionals_keep_their_branches() {
+        let template = "{{if a}}{{if b}}ab{{elseif c}}ac{{else}}a{{/if}}{{elseif c}}{{for x in xs}}{{if x}}{{x}}{{else}}-{{/if}}{{/for}}{{else}}none{{/if}}";
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set("xs", TemplateValue::Array(vec![TemplateValue::String("x".to_string()), TemplateValue::String(String::new())]));
+        context.set("c", TemplateValue::Bool(true));
+        assert_eq!(engine.render_string(template, &context).unwrap(), "x-");
+        context.set("a", TemplateValue::Bool(true));
+        assert_eq!(engine.render_string(template, &context).unwrap(), "ac");
+        context.set("c", TemplateValue::Bool(false));
+        assert_eq!(engine.render_string(template, &context).unwrap(), "a");
+
+        let parsed = ast::parse(template).unwrap();
+        let Node::If(conditional) = &parsed.nodes[0] else { panic!("expected an if block") };
+        let conditions: Vec<_> = conditional.branches.iter().map(|branch| branch.condition.as_deref()).collect();
+        assert_eq!(conditions, [Some("a"), Some("c"), None]);
+    }
+
+    #[test]
+    fn test_elseif_in_compiled_templates() {
+        let temp_dir = create_temp_dir();
+        fs::write(temp_dir.join("badge.html"), "{{if admin}}Admin{{elseif member}}Member{{else if guest}}Guest{{else}}Nobody{{/if}}!").unwrap();
+        let mut engine = TemplateEngine::new(temp_dir.to_str().unwrap());
+        let compiled = engine.compile_to_bytecode("badge.html").unwrap();
+
+        let mut context = TemplateContext::new();
+        assert_eq!(engine.render_compiled(&compiled, &context).unwrap(), "Nobody!");
+        context.set("guest", TemplateValue::Bool(true));
+        assert_eq!(engine.render_compiled(&compiled, &context).unwrap(), "Guest!");
+        context.set("member", TemplateValue::Bool(true));
+        assert_eq!(engine.render_compiled(&compiled, &context).unwrap(), "Member!");
+        context.set("admin", TemplateValue::Bool(true));
+        assert_eq!(engine.render_compiled(&compiled, &context).unwrap(), "Admin!");
+    }
+
+    #[test]
+    fn test_misplaced_elseif_is_a_parse_error() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = status("lost");
+        let error = engine.render_string("a{{elseif status}}b", &context).unwrap_err();
+        assert!(matches!(error.root_cause(), TemplateError::Parse(message) if message.contains("{{elseif status}}")), "{:?}", error);
+        let error = engine.render_string("{{if a}}a{{else}}b{{elseif status}}c{{/if}}", &context).unwrap_err();
+        assert!(matches!(error.root_cause(), TemplateError::Parse(_)), "{:?}", error);
+        assert!(ast::parse("{{if a}}a{{else}}b{{else if c}}c{{/if}}").is_err());
+
+        // Whichever branch renders, the block is checked as a whole
+        context.set("n", TemplateValue::Bool(true));
+        context.set("items", TemplateValue::Array(vec![TemplateValue::Number(1), TemplateValue::Number(2)]));
+        for template in [
+            "{{if n}}a{{else}}b{{else}}c{{/if}}",
+            "{{if n}}a{{else}}b{{elseif status}}c{{/if}}",
+            "{{for i in items}}{{if n}}a{{else}}b{{else}}c{{/if}}{{/for}}",
+        ] {
+            let error = engine.render_string(template, &context).unwrap_err();
+            assert!(matches!(error.root_cause(), TemplateError::Parse(_)), "{}: {:?}", template, error);
+        }
+    }
+
+    #[cfg(feature = "lsp")]
+    #[test]
+    fn test_diagnostics_accept_elseif_inside_if_only() {
+        let mut engine = TemplateEngine::new(".");
+        let diagnostics = engine.get_diagnostics_for_editor(STATUS, &status("lost")).unwrap();
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        let diagnostics = engine.get_diagnostics_for_editor("{{unless a}}{{elseif status}}{{/unless}}", &status("lost")).unwrap();
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, ["Unexpected branch outside an {{if}} block"]);
+    }
+}
+
+/// `!` and `not` negation in conditions
+#[cfg(test)]
+mod negation_tests {
+    use super::*;
+
+    fn user(verified: bool, premium: bool) -> TemplateContext {
+        let mut profile = HashMap::new();
+        profile.insert("verified".to_string(), TemplateValue::Bool(verified));
+        profile.insert("premium".to_string(), TemplateValue::Bool(premium));
+        let mut context = TemplateContext::new();
+        context.set("user", TemplateValue::Object(profile));
+        context.set("count", TemplateValue::Number(3));
+        context
+    }
+
+    #[test]
+    fn test_negation_inverts_truthiness() {
+        let mut engine = TemplateEngine::new(".");
+        let template = "{{if !user.verified}}verify{{/if}}{{if not user.premium}}upgrade{{/if}}{{if !missing}}!missing{{/if}}";
+        assert_eq!(engine.render_string(template, &user(false, false)).unwrap(), "verifyupgrade!missing");
+        assert_eq!(engine.render_string(template, &user(true, true)).unwrap(), "!missing");
+
+        let mut context = TemplateContext::new();
+        for (value, rendered) in [
+            (TemplateValue::String(String::new()), true),
+            (TemplateValue::Number(0), true),
+            (TemplateValue::Array(vec![]), true),
+            (TemplateValue::String("x".to_string()), false),
+            (TemplateValue::Number(-1), false),
+        ] {
+            context.set("value", value.clone());
+            let plain = engine.render_string("{{if value}}y{{/if}}", &context).unwrap();
+            let negated = engine.render_string("{{if !value}}y{{/if}}", &context).unwrap();
+            assert_eq!(negated == "y", rendered, "{:?}", value);
+            assert_ne!(plain, negated, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn test_negated_comparisons_and_parentheses() {
+        let mut engine = TemplateEngine::new(".");
+        let context = user(true, false);
+        let template = "{{if !(count > 5)}}few{{/if}}{{if not (count == 3)}}other{{/if}}{{if !!user.verified}}ok{{/if}}\
+            {{if (count != 4)}}ne{{/if}}{{unless !user.premium}}premium{{/unless}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "fewokne");
+        // `!=` is still a comparison and a variable may start with `not`
+        let mut context = TemplateContext::new();
+        context.set("nothing", TemplateValue::Bool(true));
+        assert_eq!(engine.render_string("{{if nothing}}a{{/if}}{{if nothing != false}}b{{/if}}", &context).unwrap(), "ab");
+    }
+
+    #[test]
+    fn test_negation_in_compiled_templates_and_usage() {
+        let temp_dir = create_temp_dir();
+        fs::write(temp_dir.join("banner.html"), "{{if !user.verified}}Verify{{elseif not user.premium}}Upgrade{{else}}Thanks{{/if}}").unwrap();
+        let mut engine = TemplateEngine::new(temp_dir.to_str().unwrap());
+        let compiled = engine.compile_to_bytecode("banner.html").unwrap();
+        assert_eq!(engine.render_compiled(&compiled, &user(false, false)).unwrap(), "Verify");
+        assert_eq!(engine.render_compiled(&compiled, &user(true, false)).unwrap(), "Upgrade");
+        assert_eq!(engine.render_compiled(&compiled, &user(true, true)).unwrap(), "Thanks");
+
+        let usage = engine.analyze_context_usage("banner.html").unwrap();
+        assert!(usage.paths.contains("user.verified") && usage.paths.contains("user.premium"), "{:?}", usage.paths);
+    }
+}
+
+/// `default` filter and strict variables
+#[cfg(test)]
+mod default_filter_tests {
+    use super::*;
+    use mystical_runic::TemplateError;
+
+    fn profile(nickname: &str) -> TemplateContext {
+        let mut user = HashMap::new();
+        user.insert("name".to_string(), TemplateValue::String("Ada".to_string()));
+        user.insert("nickname".to_string(), TemplateValue::String(nickname.to_string()));
+        let mut context = TemplateContext::new();
+        context.set("user", TemplateValue::Object(user));
+        context
+    }
+
+    #[test]
+    fn test_default_replaces_empty_and_missing_values() {
+        let mut engine = TemplateEngine::new(".");
+        let template = "{{user.nickname|default:\"Anonymous\"}} {{user.title|default:'Dr: none'}} {{stock|default:0}} {{ratio|default:1.5}}";
+        assert_eq!(engine.render_string(template, &profile("")).unwrap(), "Anonymous Dr: none 0 1.5");
+        assert_eq!(engine.render_string(template, &profile("ada")).unwrap(), "ada Dr: none 0 1.5");
+
+        // The default is typed and keeps going through the chain
+        let mut context = profile("");
+        context.set("fallback", TemplateValue::String("Guest".to_string()));
+        let template = "{{stock|default:2|add:3}} {{user.nickname|default:fallback|upper}} {{user.name|default:\"x\"}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "5 GUEST Ada");
+        assert!(engine.render_string("{{if stock|default:2 > 1}}many{{/if}}", &context).unwrap() == "many");
+    }
+
+    #[test]
+    fn test_strict_variables_locate_missing_variables() {
+        let mut engine = TemplateEngine::new(".");
+        let template = "<p>\n  {{user.name}} {{user.nikname}}\n</p>";
+        assert_eq!(engine.render_string(template, &profile("ada")).unwrap(), "<p>\n  Ada \n</p>");
+
+        engine.set_strict_variables(true);
+        assert!(engine.is_strict_variables_enabled());
+        let error = engine.render_string(template, &profile("ada")).unwrap_err();
+        match error.root_cause() {
+            TemplateError::Render(message) => assert_eq!(message, "Undefined variable 'user.nikname' at line 2, column 17"),
+            other => panic!("expected a render error, got {:?}", other),
+        }
+        for template in ["{{& missing}}", "{{missing|upper}}", "{{for item in items}}{{item.nope}}{{/for}}"] {
+            let mut context = profile("");
+            context.set("items", TemplateValue::Array(vec![TemplateValue::Object(HashMap::new())]));
+            assert!(engine.render_string(template, &context).is_err(), "{}", template);
+        }
+
+        // Defaults, conditions, loop variables and empty values are fine
+        let template = "{{missing|default:\"-\"}}{{if missing}}x{{/if}}{{for item in items}}{{item}}{{/for}}[{{user.nickname}}]";
+        let mut context = profile("");
+        context.set("items", TemplateValue::Array(vec![TemplateValue::Number(1), TemplateValue::Number(2)]));
+        assert_eq!(engine.render_string(template, &context).unwrap(), "-12[]");
+    }
+
+    #[test]
+    fn test_strict_variables_name_the_included_template() {
+        let dir = create_temp_dir();
+        fs::write(dir.join("page.html"), "<h1>{{title}}</h1>\n{{include \"card.html\"}}").unwrap();
+        fs::write(dir.join("card.html"), "<div>\n{{bio}}</div>").unwrap();
+        let mut engine = TemplateEngine::builder().template_dir(dir.to_str().unwrap()).strict_variables(true).build();
+        let mut context = TemplateContext::new();
+        context.set_string("title", "Runes");
+        let error = engine.render("page.html", &context).unwrap_err();
+        assert!(error.to_string().contains("Undefined variable 'bio' in template 'card.html' at line 2, column 1"), "{}", error);
+
+        context.set_string("bio", "");
+        assert_eq!(engine.render("page.html", &context).unwrap(), "<h1>Runes</h1>\n<div>\n</div>");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
+
+/// `join`, `first`, `last` and `slice` filters on arrays
+#[cfg(test)]
+mod array_filter_tests {
+    use super::*;
+
+    fn tags() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set("tags", TemplateValue::Array(["rust", "templates", "runes", "macros"].iter()
+            .map(|tag| TemplateValue::String(tag.to_string()))
+            .collect()));
+        context.set("scores", TemplateValue::Array(vec![TemplateValue::Number(3), TemplateValue::Number(9)]));
+        context.set("empty", TemplateValue::Array(vec![]));
+        context
+    }
+
+    #[test]
+    fn test_array_filters_print_lists_without_loops() {
+        let mut engine = TemplateEngine::new(".");
+        let context = tags();
+        for (template, expected) in [
+            ("{{tags|join:\", \"}}", "rust, templates, runes, macros"),
+            ("{{tags|join}}", "rust, templates, runes, macros"),
+            ("{{scores|join:\"-\"}}", "3-9"),
+            ("{{tags|first}} {{tags|last|upper}}", "rust MACROS"),
+            ("{{tags|slice:0:3|join:\" · \"}}", "rust · templates · runes"),
+            ("{{tags|slice:2|join:'/'}}", "runes/macros"),
+            ("{{tags|slice:3:10|first}}{{tags|slice:9|join}}", "macros"),
+            ("[{{empty|first}}{{empty|join}}]", "[]"),
+            ("{{tags|length}} {{tags|slice:1:3|length}}", "4 2"),
+        ] {
+            assert_eq!(engine.render_string(template, &context).unwrap(), expected, "{}", template);
+        }
+    }
+
+    #[test]
+    fn test_array_filters_keep_values_typed() {
+        let mut engine = TemplateEngine::new(".");
+        let context = tags();
+        assert_eq!(engine.render_string("{{scores|last|add:1}}", &context).unwrap(), "10");
+        assert_eq!(engine.render_string("{{if scores|first < 5}}low{{/if}}{{if tags|first == \"rust\"}}!{{/if}}", &context).unwrap(), "low!");
+        assert_eq!(engine.render_string("{{tags|join:\"<br>\"}}", &context).unwrap(), "rust&lt;br&gt;templates&lt;br&gt;runes&lt;br&gt;macros");
+
+        // Text works by character, other filters are unchanged
+        let mut context = TemplateContext::new();
+        context.set_string("flag", "🇫🇷é!");
+        assert_eq!(engine.render_string("{{flag|first}}|{{flag|last}}|{{flag|slice:1:2}}|{{flag|join}}", &context).unwrap(), "🇫🇷|!|é|🇫🇷é!");
+    }
+}
+
+/// `sort`, `reverse` and `unique` filters in `{{for}}` sources
+#[cfg(test)]
+mod sorted_loop_tests {
+    use super::*;
+
+    fn product(name: &str, price: TemplateValue, brand: &str) -> TemplateValue {
+        let mut maker = HashMap::new();
+        maker.insert("name".to_string(), TemplateValue::String(brand.to_string()));
+        let mut fields = HashMap::new();
+        fields.insert("name".to_string(), TemplateValue::String(name.to_string()));
+        fields.insert("price".to_string(), price);
+        fields.insert("brand".to_string(), TemplateValue::Object(maker));
+        TemplateValue::Object(fields)
+    }
+
+    fn catalog() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set("products", TemplateValue::Array(vec![
+            product("lamp", TemplateValue::Number(30), "Lux"),
+            product("desk", TemplateValue::String("120".to_string()), "Oak"),
+            product("pen", TemplateValue::Float(2.5), "Ink"),
+            product("chair", TemplateValue::Number(30), "Oak"),
+            product("mug", TemplateValue::Number(8), "Lux"),
+        ]));
+        context.set("tags", TemplateValue::Array(["b", "a", "c", "a", "b"].iter()
+            .map(|tag| TemplateValue::String(tag.to_string()))
+            .collect()));
+        context
+    }
+
+    #[test]
+    fn test_loops_iterate_sorted_items() {
+        let mut engine = TemplateEngine::new(".");
+        let context = catalog();
+        for (source, expected) in [
+            ("products|sort:\"price\"", "pen mug lamp chair desk "),
+            ("products|sort:\"price\":desc", "desk lamp chair mug pen "),
+            ("products|sort:\"brand.name\"", "pen lamp mug desk chair "),
+            ("products|sort:'name'|reverse", "pen mug lamp desk chair "),
+            ("products|unique:\"brand.name\"", "lamp desk pen "),
+            ("products|reverse|slice:0:2", "mug chair "),
+        ] {
+            let template = format!("{{{{for p in {}}}}}{{{{p.name}}}} {{{{/for}}}}", source);
+            assert_eq!(engine.render_string(&template, &context).unwrap(), expected, "{}", source);
+        }
+        let template = "{{for tag in tags|unique|sort}}{{tag}}{{/for}} {{for tag in tags|sort:desc}}{{tag}}{{/for}}";
+        assert_eq!(engine.render_string(template, &context).unwrap(), "abc cbbaa");
+        assert!(engine.check_syntax("{{for p in products|sort:\"price\":desc}}{{p.name}}{{/for}}").is_ok());
+    }
+
+    #[test]
+    fn test_sort_reverse_and_unique_in_output_tags() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = catalog();
+        context.set("mixed", TemplateValue::Array(vec![
+            TemplateValue::String("b".to_string()),
+            TemplateValue::Number(10),
+            TemplateValue::Bool(true),
+            TemplateValue::String("9".to_string()),
+            TemplateValue::String("a".to_string()),
+            TemplateValue::Float(f64::NAN),
+            TemplateValue::Number(9),
+        ]));
+        context.set_string("word", "añb🇫🇷");
+        assert_eq!(engine.render_string("{{tags|reverse|join:\"\"}} {{tags|unique|join}}", &context).unwrap(), "bacab b, a, c");
+        assert_eq!(engine.render_string("{{mixed|sort|join:\" \"}}", &context).unwrap(), "9 9 10 NaN a b true");
+        assert_eq!(engine.render_string("{{mixed|unique|length}} {{word|reverse}}", &context).unwrap(), "6 🇫🇷bña");
+
+        // A registered filter of the same name wins
+        engine.register_filter("reverse", |input, _| Ok(format!("<{}>", input)));
+        assert_eq!(engine.render_string("{{word|reverse}}", &context).unwrap(), "&lt;añb🇫🇷&gt;");
+    }
+}
+
+/// `safe` and `escape` filters and custom filters producing HTML
+#[cfg(test)]
+mod escaping_filter_tests {
+    use super::*;
+
+    fn engine() -> TemplateEngine {
+        let mut engine = TemplateEngine::new(".");
+        engine.register_html_filter("linkify", |input, _| Ok(format!("<a href=\"/{}\">{}</a>", input, input)));
+        engine.register_filter("shout", |input, _| Ok(format!("<{}>", input.to_uppercase())));
+        engine
+    }
+
+    fn context() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set_string("name", "<b>ada</b>");
+        context.set_string("page", "docs");
+        context
+    }
+
+    #[test]
+    fn test_last_escaping_filter_wins() {
+        let mut engine = engine();
+        let context = context();
+        for (template, expected) in [
+            ("{{name}}", "&lt;b&gt;ada&lt;/b&gt;"),
+            ("{{name|safe}}", "<b>ada</b>"),
+            ("{{name|safe|upper}}", "<B>ADA</B>"),
+            ("{{name|safe|escape}}", "&lt;b&gt;ada&lt;/b&gt;"),
+            ("{{name|e|safe}}", "<b>ada</b>"),
+            ("{{& name|e}}", "&lt;b&gt;ada&lt;/b&gt;"),
+            ("{{page|linkify}}", "<a href=\"/docs\">docs</a>"),
+            ("{{page|linkify|e}}", "&lt;a href=&quot;/docs&quot;&gt;docs&lt;/a&gt;"),
+            ("{{page|shout}}", "&lt;DOCS&gt;"),
+            ("{{page|shout|safe}}", "<DOCS>"),
+        ] {
+            assert_eq!(engine.render_string(template, &context).unwrap(), expected, "{}", template);
+        }
+        assert!(engine.check_syntax("{{name|safe}}{{name|escape}}{{name|e}}").is_ok());
+
+        // Pipelines decide as their last deciding step does
+        engine.register_pipeline("link_text", "linkify|escape").unwrap();
+        engine.register_pipeline("link", "upper|linkify").unwrap();
+        assert_eq!(engine.render_string("{{page|link_text}}|{{page|link}}", &context).unwrap(), "&lt;a href=&quot;/docs&quot;&gt;docs&lt;/a&gt;|<a href=\"/DOCS\">DOCS</a>");
+
+        // Registering the name as a plain filter makes its output escaped again
+        engine.register_filter("linkify", |input, _| Ok(format!("<i>{}</i>", input)));
+        assert_eq!(engine.render_string("{{page|linkify}}", &context).unwrap(), "&lt;i&gt;docs&lt;/i&gt;");
+    }
+
+    #[test]
+    fn test_audit_follows_escaping_filters() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("page.html"), "{{page|linkify}}{{page|linkify|e}}{{& name|escape}}{{name|escape|safe}}{{name|e}}").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        engine.register_html_filter("linkify", |input, _| Ok(input.to_string()));
+
+        let reasons: Vec<(String, String)> = engine.audit_raw_output().unwrap().iter()
+            .map(|site| (site.expression.clone(), site.reason.to_string()))
+            .collect();
+        assert_eq!(reasons, vec![
+            ("page|linkify".to_string(), "html-producing filter 'linkify'".to_string()),
+            ("name|escape|safe".to_string(), "safe filter".to_string()),
+        ]);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// `TemplateValue::Null` and the `is defined` / `is null` condition tests
+#[cfg(test)]
+mod null_value_tests {
+    use super::*;
+
+    fn context() -> TemplateContext {
+        let mut user = HashMap::new();
+        user.insert("name".to_string(), TemplateValue::String("Ada".to_string()));
+        user.insert("middle_name".to_string(), TemplateValue::Null);
+        user.insert("nickname".to_string(), TemplateValue::String(String::new()));
+        let mut context = TemplateContext::new();
+        context.set("user", TemplateValue::Object(user));
+        context.set("avatar", TemplateValue::Null);
+        context.set_string("title", "");
+        context
+    }
+
+    #[test]
+    fn test_null_renders_empty_and_is_falsy() {
+        let mut engine = TemplateEngine::new(".");
+        let context = context();
+        for (template, expected) in [
+            ("[{{avatar}}][{{user.middle_name}}]", "[][]"),
+            ("{{if avatar}}yes{{else}}no{{/if}}", "no"),
+            ("{{avatar|default:\"none\"}}", "none"),
+            ("{{avatar|upper}}", ""),
+        ] {
+            assert_eq!(engine.render_string(template, &context).unwrap(), expected, "{}", template);
+        }
+        engine.set_strict_variables(true);
+        assert_eq!(engine.render_string("[{{avatar}}]", &context).unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_is_defined_and_is_null_tell_missing_null_and_empty_apart() {
+        let mut engine = TemplateEngine::new(".");
+        let context = context();
+        for (condition, expected) in [
+            ("avatar is defined", true),
+            ("avatar is null", true),
+            ("title is defined", true),
+            ("title is null", false),
+            ("missing is defined", false),
+            ("missing is null", false),
+            ("user.middle_name is defined", true),
+            ("user.middle_name is null", true),
+            ("user.nickname is null", false),
+            ("user.nickname is defined", true),
+            ("user.suffix is defined", false),
+            ("avatar is not null", false),
+            ("missing is not defined", true),
+            ("!(user.name is null)", true),
+            ("not title is defined", false),
+        ] {
+            let template = format!("{{{{if {}}}}}yes{{{{else}}}}no{{{{/if}}}}", condition);
+            let expected = if expected { "yes" } else { "no" };
+            assert_eq!(engine.render_string(&template, &context).unwrap(), expected, "{}", condition);
+        }
+    }
+
+    #[test]
+    fn test_null_equals_only_null() {
+        let mut engine = TemplateEngine::new(".");
+        let context = context();
+        for (condition, expected) in [
+            ("avatar == null", true),
+            ("avatar == user.middle_name", true),
+            ("avatar == title", false),
+            ("missing == null", false),
+            ("avatar == 0", false),
+            ("avatar == false", false),
+            ("avatar != \"\"", true),
+        ] {
+            let template = format!("{{{{if {}}}}}yes{{{{else}}}}no{{{{/if}}}}", condition);
+            let expected = if expected { "yes" } else { "no" };
+            assert_eq!(engine.render_string(&template, &context).unwrap(), expected, "{}", condition);
+        }
+    }
+
+    #[test]
+    fn test_value_tests_report_their_variables() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("page.html"), "{{if user.middle_name is defined}}{{/if}}{{if !avatar is not null}}{{/if}}{{if photo == null}}{{/if}}").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+
+        let usage = engine.analyze_context_usage("page.html").unwrap();
+        assert_eq!(usage.paths.iter().collect::<Vec<_>>(), vec!["avatar", "photo", "user.middle_name"]);
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+}
+
+/// `From` conversions into `TemplateValue` and the `runic_context!` macro
+#[cfg(test)]
+mod value_conversion_tests {
+    use super::*;
+    use mystical_runic::runic_context;
+
+    #[test]
+    fn test_set_takes_anything_that_converts() {
+        let mut engine = TemplateEngine::new(".");
+        let mut context = TemplateContext::new();
+        context.set("name", "Ada");
+        context.set("title", String::from("Countess"));
+        context.set("age", 36);
+        context.set("ratio", 0.5);
+        context.set("admin", true);
+        context.set("tags", vec![TemplateValue::from("math"), TemplateValue::from("poetry")]);
+        context.set("links", HashMap::from([("home".to_string(), TemplateValue::from("/"))]));
+        context.set("avatar", TemplateValue::Null);
+
+        assert_eq!(
+            engine.render_string("{{name}} {{title}} {{age}} {{ratio}} {{admin}} {{tags|join:\"+\"}} {{links.home}}{{if avatar is null}}!{{/if}}", &context).unwrap(),
+            "Ada Countess 36 0.5 true math+poetry /!"
+        );
+    }
+
+    #[test]
+    fn test_runic_context_builds_nested_values() {
+        let mut engine = TemplateEngine::new(".");
+        let city = String::from("Paris");
+        let context = runic_context! {
+            "user" => { "name" => "Alex", "age" => 30, "address" => { "city" => city.clone() } },
+            "tags" => ["a", "b"],
+            "orders" => [{ "id" => -4 }, { "id" => 2 + 3 }],
+            "matrix" => [[1, 2], []],
+            "empty" => {},
+            "price" => 9.5,
+        };
+
+        assert_eq!(
+            engine.render_string("{{user.name}} {{user.age}} {{user.address.city}} {{tags|join}}{{for order in orders}} {{order.id}}{{/for}} {{matrix.0|join:\"-\"}} {{price}}", &context).unwrap(),
+            "Alex 30 Paris a, b -4 5 1-2 9.5"
+        );
+        assert!(matches!(context.get("empty"), Some(TemplateValue::Object(entries)) if entries.is_empty()));
+        assert!(matches!(context.get("matrix"), Some(TemplateValue::Array(rows)) if matches!(&rows[1], TemplateValue::Array(row) if row.is_empty())));
+        assert!(runic_context! {}.get("user").is_none());
+    }
+}
+
+/// `{{empty}}` sections of loops with nothing to iterate
+#[cfg(test)]
+mod loop_empty_tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_section_renders_without_items() {
+        let mut engine = TemplateEngine::new("templates");
+        let template = "<ul>{{for item in items}}<li>{{item}}</li>{{empty}}<li>No results for {{query}}</li>{{/for}}</ul>";
+        let mut context = TemplateContext::new();
+        context.set("query", "runes");
+
+        // Missing, not an array, and no elements
+        assert_eq!(engine.render_string(template, &context).unwrap(), "<ul><li>No results for runes</li></ul>");
+        context.set("items", "not a list");
+        assert_eq!(engine.render_string(template, &context).unwrap(), "<ul><li>No results for runes</li></ul>");
+        context.set("items", Vec::<TemplateValue>::new());
+        assert_eq!(engine.render_string(template, &context).unwrap(), "<ul><li>No results for runes</li></ul>");
+
+        context.set("items", vec![TemplateValue::from("a"), TemplateValue::from("b")]);
+        assert_eq!(engine.render_string(template, &context).unwrap(), "<ul><li>a</li><li>b</li></ul>");
+    }
+
+    #[test]
+    fn test_empty_section_belongs_to_its_own_loop() {
+        let mut engine = TemplateEngine::new("templates");
+        let template = "{{for group in groups}}[{{group.name}}:{{for tag in group.tags}}{{tag}}{{empty}}none{{/for}}]{{empty}}no groups{{/for}}";
+        let mut context = TemplateContext::new();
+        assert_eq!(engine.render_string(template, &context).unwrap(), "no groups");
+
+        context.set("groups", vec![
+            TemplateValue::from(HashMap::from([
+                ("name".to_string(), TemplateValue::from("a")),
+                ("tags".to_string(), TemplateValue::from(vec![TemplateValue::from("x"), TemplateValue::from("y")])),
+            ])),
+            TemplateValue::from(HashMap::from([
+                ("name".to_string(), TemplateValue::from("b")),
+                ("tags".to_string(), TemplateValue::from(Vec::<TemplateValue>::new())),
+            ])),
+        ]);
+        assert_eq!(engine.render_string(template, &context).unwrap(), "[a:xy][b:none]");
+
+        // An {{empty}} inside a conditional of the body is not the loop's
+        context.set("flag", true);
+        assert_eq!(engine.render_string("{{for g in groups}}{{if flag}}{{g.name}}{{/if}}{{empty}}-{{/for}}", &context).unwrap(), "ab");
+    }
+
+    #[test]
+    fn test_empty_section_is_parsed() {
+        let engine = TemplateEngine::new("templates");
+        assert!(engine.check_syntax("{{for item in items}}{{item}}{{empty}}none{{/for}}").is_ok());
+        // Outside a loop it is a plain variable
+        assert!(engine.check_syntax("{{empty}}").is_ok());
+
+        let template = mystical_runic::ast::parse("{{for item in items}}{{item}}{{empty}}none{{/for}}").unwrap();
+        let mystical_runic::ast::Node::For(node) = &template.nodes[0] else { panic!("expected a loop") };
+        assert_eq!(node.body.len(), 1);
+        assert_eq!(node.empty.as_ref().map(Vec::len), Some(1));
+        let error = mystical_runic::ast::parse("{{for item in items}}{{empty}}a{{empty}}b{{/for}}").unwrap_err();
+        assert!(error.to_string().contains("Duplicate '{{empty}}' in '{{for}}'"), "{}", error);
+    }
+}
+
+/// Integer range loops and the `range()` function
+#[cfg(test)]
+mod range_loop_tests {
+    use super::*;
+
+    #[test]
+    fn test_range_loops_with_literal_and_variable_bounds() {
+        let mut engine = TemplateEngine::new("templates");
+        let mut context = TemplateContext::new();
+        context.set("pages", 3);
+        context.set("rating", "4");
+
+        assert_eq!(engine.render_string("{{for i in 1..5}}{{i}}{{/for}}", &context).unwrap(), "1234");
+        assert_eq!(engine.render_string("{{for i in 1..=5}}{{i}}{{/for}}", &context).unwrap(), "12345");
+        assert_eq!(engine.render_string("{{for page in 0..pages}}[{{page}}]{{/for}}", &context).unwrap(), "[0][1][2]");
+        assert_eq!(engine.render_string("{{for star in 1..=rating}}*{{/for}}", &context).unwrap(), "****");
+        assert_eq!(engine.render_string("{{for i in -1..=1}}{{i}} {{/for}}", &context).unwrap(), "-1 0 1 ");
+        // Backwards and unset bounds leave nothing to iterate
+        assert_eq!(engine.render_string("{{for i in 5..1}}{{i}}{{empty}}none{{/for}}", &context).unwrap(), "none");
+        assert_eq!(engine.render_string("{{for i in 0..missing}}{{i}}{{empty}}none{{/for}}", &context).unwrap(), "none");
+
+        context.set("pages", "many");
+        let error = engine.render_string("{{for page in 0..pages}}{{page}}{{/for}}", &context).unwrap_err();
+        assert!(error.to_string().contains("Range bound 'pages' must be an integer, got 'many'"), "{}", error);
+    }
+
+    #[test]
+    fn test_range_function() {
+        let mut engine = TemplateEngine::new("templates");
+        let mut context = TemplateContext::new();
+        context.set("count", 3);
+
+        assert_eq!(engine.render_string("{{for i in range(count)}}{{i}}{{/for}}", &context).unwrap(), "012");
+        assert_eq!(engine.render_string("{{for i in range(2, 5)}}{{i}}{{/for}}", &context).unwrap(), "234");
+        assert_eq!(engine.render_string("{{for i in range(0, 10, 3)}}{{i}},{{/for}}", &context).unwrap(), "0,3,6,9,");
+        assert_eq!(engine.render_string("{{for i in range(5, 0, -2)}}{{i}},{{/for}}", &context).unwrap(), "5,3,1,");
+        assert_eq!(engine.render_string("{{range(1, 4)|join:\"-\"}}", &context).unwrap(), "1-2-3");
+
+        let error = engine.render_string("{{for i in range(0, 5, 0)}}{{i}}{{/for}}", &context).unwrap_err();
+        assert!(error.to_string().contains("range step cannot be 0"), "{}", error);
+        let error = engine.render_string("{{for i in range(\"a\")}}{{i}}{{/for}}", &context).unwrap_err();
+        assert!(error.to_string().contains("range expects integers, got \"a\""), "{}", error);
+    }
+
+    #[test]
+    fn test_max_range_items() {
+        let mut engine = TemplateEngine::new("templates");
+        let context = TemplateContext::new();
+        assert_eq!(engine.get_max_range_items(), 1_000_000);
+
+        let error = engine.render_string("{{for i in 0..=9223372036854775807}}{{i}}{{/for}}", &context).unwrap_err();
+        assert!(matches!(error.root_cause(), mystical_runic::TemplateError::Render(_)), "{:?}", error);
+
+        engine.set_max_range_items(3);
+        assert_eq!(engine.render_string("{{for i in 1..=3}}{{i}}{{/for}}", &context).unwrap(), "123");
+        for template in ["{{for i in 1..=4}}{{i}}{{/for}}", "{{for i in range(4)}}{{i}}{{/for}}"] {
+            let error = engine.render_string(template, &context).unwrap_err();
+            assert!(error.to_string().contains("range of 4 items exceeds the limit of 3 items"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_ranges_at_the_integer_bounds() {
+        let mut engine = TemplateEngine::new("templates");
+        let context = TemplateContext::new();
+
+        let template = "{{range(-9223372036854775808, 9223372036854775807, 9223372036854775807)|join:\",\"}}";
+        assert_eq!(
+            engine.render_string(template, &context).unwrap(),
+            "-9223372036854775808,-1,9223372036854775806"
+        );
+        let template = "{{range(9223372036854775807, -9223372036854775808, -9223372036854775807)|join:\",\"}}";
+        assert_eq!(
+            engine.render_string(template, &context).unwrap(),
+            "9223372036854775807,0,-9223372036854775807"
+        );
+        let template = "{{for i in 9223372036854775805..=9223372036854775807}}{{i}} {{/for}}";
+        assert_eq!(
+            engine.render_string(template, &context).unwrap(),
+            "9223372036854775805 9223372036854775806 9223372036854775807 "
+        );
+    }
+}
+
+/// `{{set}}` visible for the rest of its scope
+#[cfg(test)]
+mod assignment_scope_tests {
+    use super::*;
+
+    fn order() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set("price", TemplateValue::Number(4));
+        context.set("quantity", TemplateValue::Number(3));
+        context.set("show", TemplateValue::Bool(true));
+        context.set("items", TemplateValue::Array(vec![
+            TemplateValue::String("a".to_string()),
+            TemplateValue::String("b".to_string()),
+        ]));
+        context
+    }
+
+    #[test]
+    fn test_reassignment_overwrites_from_that_point_on() {
+        let mut engine = TemplateEngine::new(".");
+        let result = engine.render_string("{{set x = 1}}{{x}}{{set x = 2}}{{x}}", &order()).unwrap();
+        assert_eq!(result, "12");
+    }
+
+    #[test]
+    fn test_assignment_takes_a_filter_chain() {
+        let mut engine = TemplateEngine::new(".");
+        let result = engine.render_string("{{set total = price|multiply:quantity}}Total: {{total}}", &order()).unwrap();
+        assert_eq!(result, "Total: 12");
+    }
+
+    #[test]
+    fn test_assignment_in_a_loop_stays_in_the_iteration() {
+        let mut engine = TemplateEngine::new(".");
+        let template = "{{for i in items}}{{set d = i|upper}}[{{d}}]{{/for}}({{d}})";
+        assert_eq!(engine.render_string(template, &order()).unwrap(), "[A][B]()");
+    }
+
+    #[test]
+    fn test_assignment_in_a_chosen_branch_applies_after_it() {
+        let mut engine = TemplateEngine::new(".");
+        let template = "{{set label = \"none\"}}{{if show}}{{set label = \"shown\"}}{{/if}}{{label}}";
+        assert_eq!(engine.render_string(template, &order()).unwrap(), "shown");
+    }
+
+    #[cfg(feature = "lsp")]
+    #[test]
+    fn test_lsp_recognizes_set_as_a_directive() {
+        let mut engine = TemplateEngine::new(".");
+        let template = "{{set total = price|multiply:quantity}}{{total}}";
+        let tokens = engine.tokenize_for_syntax_highlighting(template).unwrap();
+        assert!(tokens.iter().any(|t| t.content == "set" && t.token_type == "template_directive"), "{:?}", tokens);
+
+        let diagnostics = engine.get_diagnostics_for_editor(template, &order()).unwrap();
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let diagnostics = engine.get_diagnostics_for_editor("{{set total = missing}}", &order()).unwrap();
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert!(diagnostics[0].message.contains("missing"), "{:?}", diagnostics);
+    }
+}
+
+/// `{{capture name}}...{{/capture}}` sections
+#[cfg(test)]
+mod capture_tests {
+    use super::*;
+    use mystical_runic::ast;
+
+    fn links() -> TemplateContext {
+        let mut context = TemplateContext::new();
+        context.set("links", TemplateValue::Array(vec![
+            TemplateValue::String("Home".to_string()),
+            TemplateValue::String("<About>".to_string()),
+        ]));
+        context
+    }
+
+    #[test]
+    fn test_capture_renders_once_and_outputs_raw() {
+        let mut engine = TemplateEngine::new(".");
+        let template = "{{capture sidebar}}<ul>{{for link in links}}<li>{{link}}</li>{{/for}}</ul>{{/capture}}\
+            <aside>{{& sidebar}}</aside><footer>{{& sidebar}}</footer>";
+        let expected = "<ul><li>Home</li><li>&lt;About&gt;</li></ul>";
+        assert_eq!(
+            engine.render_string(template, &links()).unwrap(),
+            format!("<aside>{}</aside><footer>{}</footer>", expected, expected)
+        );
+    }
+
+    #[test]
+    fn test_captured_markup_is_not_escaped_again() {
+        let mut engine = TemplateEngine::new(".");
+        let template = "{{capture title}}<b>{{links.1}}</b>{{/capture}}{{title}}|{{title|escape}}|{{title|upper}}";
+        assert_eq!(
+            engine.render_string(template, &links()).unwrap(),
+            "<b>&lt;About&gt;</b>|&lt;b&gt;&amp;lt;About&amp;gt;&lt;/b&gt;|<B>&LT;ABOUT&GT;</B>"
+        );
+
+        // Reassigning the name makes it a plain value again, escaped as usual
+        let template = "{{capture title}}<b>x</b>{{/capture}}{{set title = links.1}}{{title}}";
+        assert_eq!(engine.render_string(template, &links()).unwrap(), "&lt;About&gt;");
+    }
+
+    #[test]
+    fn test_capture_includes_a_template() {
+        let templates_path = create_temp_dir();
+        fs::write(templates_path.join("nav.html"), "{{for link in links}}[{{link}}]{{/for}}").unwrap();
+        let mut engine = TemplateEngine::new(templates_path.to_str().unwrap());
+        let template = "{{capture nav}}{{include \"nav.html\"}}{{/capture}}{{& nav}}|{{& nav}}";
+        assert_eq!(engine.render_string(template, &links()).unwrap(), "[Home][&lt;About&gt;]|[Home][&lt;About&gt;]");
+
+        // A scoped include is put back before the capture is stored
+        let template = "{{capture nav}}{{include \"nav.html\" with links=links only}}{{/capture}}{{nav|length}}:{{& nav}}";
+        assert_eq!(engine.render_string(template, &links()).unwrap(), "21:[Home][&lt;About&gt;]");
+
+        let _ = fs::remove_dir_all(&templates_path);
+    }
+
+    #[test]
+    fn test_nested_captures() {
+        let mut engine = TemplateEngine::new(".");
+        let template = "{{capture outer}}<{{capture inner}}{{links.0}}{{/capture}}{{& inner}}{{& inner}}>{{/capture}}{{& outer}}{{inner}}";
+        assert_eq!(engine.render_string(template, &links()).unwrap(), "<HomeHome>");
+    }
+
+    #[test]
+    fn test_capture_in_a_loop_is_per_iteration() {
+        let mut engine = TemplateEngine::new(".");
+        let template = "{{for link in links}}{{capture label}}{{link|upper}}{{/capture}}({{& label}}){{/for}}[{{label}}]";
+        assert_eq!(engine.render_string(template, &links()).unwrap(), "(HOME)(&lt;ABOUT&gt;)[]");
+    }
+
+    #[test]
+    fn test_malformed_or_unclosed_capture_is_rejected() {
+        let mut engine = TemplateEngine::new(".");
+        let error = engine.render_string("{{capture side bar}}x{{/capture}}", &links()).unwrap_err();
+        assert!(error.to_string().contains("{{capture name}}"), "{}", error);
+        let error = engine.render_string("{{capture sidebar}}x", &links()).unwrap_err();
+        assert!(error.to_string().contains("{{/capture}}"), "{}", error);
+        assert!(engine.check_syntax("{{capture sidebar}}x").is_err());
+        assert!(engine.check_syntax("{{capture sidebar}}x{{/capture}}{{& sidebar}}").is_ok());
+    }
+
+    #[test]
+    fn test_capture_parses_to_a_node() {
+        let tree = ast::parse("{{capture sidebar}}{{for link in links}}{{link}}{{/for}}{{/capture}}{{& sidebar}}").unwrap();
+        match &tree.nodes[0] {
+            ast::Node::Capture(node) => {
+                assert_eq!(node.name, "sidebar");
+                assert!(matches!(&node.body[0], ast::Node::For(_)));
+            }
+            other => panic!("expected a capture node, got {:?}", other),
+        }
+        assert!(matches!(&tree.nodes[1], ast::Node::Variable(variable) if variable.raw));
+        assert!(ast::parse("{{capture sidebar}}x").is_err());
+    }
+
+    #[cfg(feature = "lsp")]
+    #[test]
+    fn test_diagnostics_know_captured_names() {
+        let mut engine = TemplateEngine::new(".");
+        let template = "{{capture sidebar}}{{for link in links}}{{link}}{{/for}}{{/capture}}{{sidebar|upper}}";
+        let diagnostics = engine.get_diagnostics_for_editor(template, &links()).unwrap();
+        assert!(diagnostics.iter().all(|d| !d.message.contains("sidebar") && !d.message.contains("directive")), "{:?}", diagnostics);
+    }
+}
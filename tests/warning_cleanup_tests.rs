@@ -1,5 +1,6 @@
 // Tests to ensure warning cleanup doesn't break functionality
 use mystical_runic::{TemplateEngine, TemplateContext, TemplateValue};
+#[cfg(feature = "i18n")]
 use std::collections::HashMap;
 
 #[cfg(test)]
@@ -65,6 +66,7 @@ mod warning_cleanup_tests {
         assert_eq!(result, "olleh");
     }
 
+    #[cfg(feature = "i18n")]
     #[test]
     fn test_i18n_functionality_preserved() {
         // Test that i18n features still work